@@ -0,0 +1,221 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fil_actors_runtime::actor_dispatch;
+use fil_actors_runtime::actor_error;
+use fil_actors_runtime::builtin::singletons::{BURNT_FUNDS_ACTOR_ADDR, SYSTEM_ACTOR_ADDR};
+use fil_actors_runtime::runtime::{ActorCode, Runtime};
+use fil_actors_runtime::ActorDowncast;
+use fil_actors_runtime::ActorError;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::METHOD_SEND;
+use num_traits::Zero;
+
+use crate::{
+    CancelParams, Method, ScheduleParams, ScheduledMessage, State, MAX_GAS_LIMIT, MAX_HORIZON,
+    MIN_INTERVAL, SCHEDULER_ACTOR_NAME,
+};
+
+fil_actors_runtime::wasm_trampoline!(Actor);
+
+pub struct Actor;
+
+impl Actor {
+    fn constructor(rt: &impl Runtime) -> Result<(), ActorError> {
+        rt.validate_immediate_caller_is(std::iter::once(&SYSTEM_ACTOR_ADDR))?;
+
+        let state = State::new(rt.store()).map_err(|e| {
+            e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to create empty AMT")
+        })?;
+
+        rt.create(&state)?;
+
+        Ok(())
+    }
+
+    /// Schedule a message sent by the caller, escrowing the value received to pay for its
+    /// executions; returns the ID it can be cancelled with.
+    fn schedule(rt: &impl Runtime, params: ScheduleParams) -> Result<u64, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+
+        let curr_epoch = rt.curr_epoch();
+        if params.height <= curr_epoch || params.height > curr_epoch + MAX_HORIZON {
+            return Err(actor_error!(
+                illegal_argument,
+                "height {} is not within the next {} blocks",
+                params.height,
+                MAX_HORIZON
+            ));
+        }
+        if params.interval != 0 && params.interval < MIN_INTERVAL {
+            return Err(actor_error!(
+                illegal_argument,
+                "interval has to be zero or at least {}",
+                MIN_INTERVAL
+            ));
+        }
+        if params.gas_limit == 0 || params.gas_limit > MAX_GAS_LIMIT {
+            return Err(actor_error!(
+                illegal_argument,
+                "gas limit has to be between 1 and {}",
+                MAX_GAS_LIMIT
+            ));
+        }
+        if params.gas_price.is_zero() || params.gas_price < rt.base_fee() {
+            return Err(actor_error!(
+                illegal_argument,
+                "gas price has to be positive and at least the base fee {}",
+                rt.base_fee()
+            ));
+        }
+
+        // The caller is guaranteed to be an ID address.
+        let from = Address::new_id(rt.message().caller().id().unwrap());
+
+        let msg = ScheduledMessage {
+            id: 0,
+            from,
+            to: params.to,
+            method: params.method,
+            params: params.params,
+            value: params.value,
+            gas_limit: params.gas_limit,
+            gas_price: params.gas_price,
+            interval: params.interval,
+            deposit: rt.message().value_received(),
+        };
+
+        if msg.deposit < msg.run_cost() {
+            return Err(actor_error!(
+                insufficient_funds,
+                "deposit has to cover the gas fee of at least one execution: {}",
+                msg.run_cost()
+            ));
+        }
+
+        rt.transaction(|st: &mut State, rt| {
+            st.schedule(rt.store(), params.height, msg).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to schedule message")
+            })
+        })
+    }
+
+    /// Cancel a message scheduled by the caller, refunding what is left of its deposit.
+    fn cancel(rt: &impl Runtime, params: CancelParams) -> Result<(), ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+
+        let caller = Address::new_id(rt.message().caller().id().unwrap());
+
+        let deposit = rt.transaction(|st: &mut State, rt| {
+            let scheduled = st.get_scheduled(rt.store(), params.height).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to load messages")
+            })?;
+
+            match scheduled.iter().find(|m| m.id == params.id) {
+                None => Err(actor_error!(not_found, "no such scheduled message")),
+                Some(msg) if msg.from != caller => Err(actor_error!(
+                    forbidden,
+                    "only the sender can cancel a scheduled message"
+                )),
+                Some(msg) => {
+                    let deposit = msg.deposit.clone();
+                    st.cancel(rt.store(), params.height, params.id)
+                        .map_err(|e| {
+                            e.downcast_default(
+                                ExitCode::USR_ILLEGAL_STATE,
+                                "failed to cancel message",
+                            )
+                        })?;
+                    Ok(deposit)
+                }
+            }
+        })?;
+
+        refund(rt, &caller, deposit);
+
+        Ok(())
+    }
+
+    /// Called by the interpreter to stop repeating a message which failed, refunding what is
+    /// left of its deposit; the height is the one it was scheduled again at.
+    fn cancel_failed(rt: &impl Runtime, params: CancelParams) -> Result<(), ActorError> {
+        rt.validate_immediate_caller_is(std::iter::once(&SYSTEM_ACTOR_ADDR))?;
+
+        let cancelled = rt.transaction(|st: &mut State, rt| {
+            st.cancel(rt.store(), params.height, params.id)
+                .map_err(|e| {
+                    e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to cancel message")
+                })
+        })?;
+
+        if let Some(msg) = cancelled {
+            refund(rt, &msg.from, msg.deposit);
+        }
+
+        Ok(())
+    }
+
+    fn get_scheduled(
+        rt: &impl Runtime,
+        height: ChainEpoch,
+    ) -> Result<Vec<ScheduledMessage>, ActorError> {
+        let st: State = rt.state()?;
+
+        st.get_scheduled(rt.store(), height)
+            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to get messages"))
+    }
+
+    /// Called by the interpreter at the start of every block to get the messages to execute.
+    ///
+    /// Their gas fees are burnt, and the deposits of the messages which are done are refunded.
+    fn take_due(
+        rt: &impl Runtime,
+        height: ChainEpoch,
+    ) -> Result<Vec<ScheduledMessage>, ActorError> {
+        rt.validate_immediate_caller_is(std::iter::once(&SYSTEM_ACTOR_ADDR))?;
+
+        let due = rt.transaction(|st: &mut State, rt| {
+            st.take_due(rt.store(), height).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to take due messages")
+            })
+        })?;
+
+        if !due.fees.is_zero() {
+            rt.send_simple(&BURNT_FUNDS_ACTOR_ADDR, METHOD_SEND, None, due.fees)
+                .map_err(|e| actor_error!(illegal_state, "failed to burn gas fees: {:?}", e))?;
+        }
+        for (to, amount) in due.refunds {
+            refund(rt, &to, amount);
+        }
+
+        Ok(due.msgs)
+    }
+}
+
+/// Return funds to the sender of a scheduled message. A sender refusing them shouldn't
+/// fail the block, so the funds stay with the actor in that case.
+fn refund(rt: &impl Runtime, to: &Address, amount: TokenAmount) {
+    if !amount.is_zero() {
+        let _ = rt.send_simple(to, METHOD_SEND, None, amount);
+    }
+}
+
+impl ActorCode for Actor {
+    type Methods = Method;
+
+    fn name() -> &'static str {
+        SCHEDULER_ACTOR_NAME
+    }
+
+    actor_dispatch! {
+        Constructor => constructor,
+        Schedule => schedule,
+        Cancel => cancel,
+        GetScheduled => get_scheduled,
+        TakeDue => take_due,
+        CancelFailed => cancel_failed,
+    }
+}
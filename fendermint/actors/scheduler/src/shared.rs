@@ -0,0 +1,343 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use cid::Cid;
+use fvm_ipld_amt::Amt;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::tuple::{Deserialize_tuple, Serialize_tuple};
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::{address::Address, clock::ChainEpoch, econ::TokenAmount, MethodNum};
+use fvm_shared::{BLOCK_GAS_LIMIT, METHOD_CONSTRUCTOR};
+use num_derive::FromPrimitive;
+use num_traits::Zero;
+
+pub const SCHEDULER_ACTOR_NAME: &str = "scheduler";
+
+// the bitwidth of the AMT of scheduled messages, indexed by height
+pub const SCHEDULED_AMT_BITWIDTH: u32 = 5;
+
+// the maximum number of messages which can be scheduled at the same height
+pub const MAX_MESSAGES_PER_HEIGHT: usize = 100;
+
+// the maximum gas limit of a scheduled message
+pub const MAX_GAS_LIMIT: u64 = BLOCK_GAS_LIMIT;
+
+// the maximum total gas limit of the messages scheduled at the same height
+pub const MAX_GAS_PER_HEIGHT: u64 = BLOCK_GAS_LIMIT;
+
+// how far ahead of the current height a message can be scheduled
+pub const MAX_HORIZON: ChainEpoch = 1_000_000;
+
+// the minimum number of blocks between the executions of a periodic message
+pub const MIN_INTERVAL: ChainEpoch = 10;
+
+/// A message to be executed by the interpreter at the start of a block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct ScheduledMessage {
+    /// Unique ID to cancel the message with.
+    pub id: u64,
+    /// The actor which scheduled the message and is sending it.
+    pub from: Address,
+    pub to: Address,
+    pub method: MethodNum,
+    pub params: RawBytes,
+    /// Transferred from the balance of the sender when the message is executed.
+    pub value: TokenAmount,
+    pub gas_limit: u64,
+    /// Price paid for each unit of the gas limit, every time the message is executed.
+    pub gas_price: TokenAmount,
+    /// Execute the message again every so many blocks; only once if zero.
+    pub interval: ChainEpoch,
+    /// What is left of the funds escrowed when the message was scheduled, to pay for
+    /// the executions to come.
+    pub deposit: TokenAmount,
+}
+
+impl ScheduledMessage {
+    /// The gas fee paid for each execution, regardless of the gas actually used.
+    pub fn run_cost(&self) -> TokenAmount {
+        &self.gas_price * self.gas_limit
+    }
+}
+
+/// The messages due at a height, with what their escrowed funds have to pay out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Due {
+    /// The messages to execute, in the order they were scheduled.
+    pub msgs: Vec<ScheduledMessage>,
+    /// The gas fees of the messages, to be burnt.
+    pub fees: TokenAmount,
+    /// Deposits of the messages which won't be executed anymore, to be returned to the senders.
+    pub refunds: Vec<(Address, TokenAmount)>,
+}
+
+// The state stores the scheduled messages by the height they execute at.
+#[derive(Serialize_tuple, Deserialize_tuple)]
+pub struct State {
+    // the AMT root cid of the messages scheduled at each height
+    pub scheduled: Cid,
+
+    // the ID of the next scheduled message
+    pub next_id: u64,
+}
+
+impl State {
+    pub fn new<BS: Blockstore>(store: &BS) -> anyhow::Result<Self> {
+        let scheduled = Amt::<(), _>::new_with_bit_width(store, SCHEDULED_AMT_BITWIDTH)
+            .flush()
+            .map_err(|e| anyhow::anyhow!("scheduler actor failed to create empty Amt: {}", e))?;
+
+        Ok(Self {
+            scheduled,
+            next_id: 0,
+        })
+    }
+
+    /// Add a message at the given height, assigning it the next ID.
+    pub fn schedule<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        height: ChainEpoch,
+        mut msg: ScheduledMessage,
+    ) -> anyhow::Result<u64> {
+        let mut scheduled = self.load(store)?;
+        let mut msgs = scheduled.get(height as u64)?.cloned().unwrap_or_default();
+        if !has_room(&msgs, &msg) {
+            anyhow::bail!("too many messages scheduled at height {height}");
+        }
+        msg.id = self.next_id;
+        self.next_id += 1;
+        msgs.push(msg);
+        scheduled.set(height as u64, msgs)?;
+        self.scheduled = scheduled.flush()?;
+        Ok(self.next_id - 1)
+    }
+
+    /// Remove a message scheduled at the given height, returning it if it existed.
+    pub fn cancel<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        height: ChainEpoch,
+        id: u64,
+    ) -> anyhow::Result<Option<ScheduledMessage>> {
+        let mut scheduled = self.load(store)?;
+        let Some(mut msgs) = scheduled.get(height as u64)?.cloned() else {
+            return Ok(None);
+        };
+        let Some(idx) = msgs.iter().position(|m| m.id == id) else {
+            return Ok(None);
+        };
+        let msg = msgs.remove(idx);
+        if msgs.is_empty() {
+            scheduled.delete(height as u64)?;
+        } else {
+            scheduled.set(height as u64, msgs)?;
+        }
+        self.scheduled = scheduled.flush()?;
+        Ok(Some(msg))
+    }
+
+    /// The messages scheduled at the given height.
+    pub fn get_scheduled<BS: Blockstore>(
+        &self,
+        store: &BS,
+        height: ChainEpoch,
+    ) -> anyhow::Result<Vec<ScheduledMessage>> {
+        Ok(self
+            .load(store)?
+            .get(height as u64)?
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Remove the messages due at the given height and return them, paying for their execution
+    /// from their deposits, and scheduling the periodic ones again at their next height as long
+    /// as their deposit covers another execution.
+    pub fn take_due<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        height: ChainEpoch,
+    ) -> anyhow::Result<Due> {
+        let mut scheduled = self.load(store)?;
+        let Some(msgs) = scheduled.delete(height as u64)? else {
+            return Ok(Due::default());
+        };
+        let mut due = Due::default();
+        for mut msg in msgs {
+            let cost = msg.run_cost();
+            if msg.deposit < cost {
+                due.refunds.push((msg.from, msg.deposit));
+                continue;
+            }
+            msg.deposit -= cost.clone();
+            due.fees += cost;
+
+            // Keep the ID, so the message can be cancelled for good. A full height
+            // ends the repetition rather than failing the block.
+            let mut repeats = false;
+            if msg.interval > 0 && msg.deposit >= msg.run_cost() {
+                let next = (height + msg.interval) as u64;
+                let mut next_msgs = scheduled.get(next)?.cloned().unwrap_or_default();
+                if has_room(&next_msgs, &msg) {
+                    next_msgs.push(msg.clone());
+                    scheduled.set(next, next_msgs)?;
+                    repeats = true;
+                }
+            }
+            if !repeats && !msg.deposit.is_zero() {
+                due.refunds.push((msg.from, msg.deposit.clone()));
+            }
+            due.msgs.push(msg);
+        }
+        self.scheduled = scheduled.flush()?;
+        Ok(due)
+    }
+
+    fn load<'a, BS: Blockstore>(
+        &self,
+        store: &'a BS,
+    ) -> anyhow::Result<Amt<Vec<ScheduledMessage>, &'a BS>> {
+        Amt::load(&self.scheduled, store).map_err(|e| {
+            anyhow::anyhow!(
+                "failed to load scheduled messages from AMT cid {}, error: {}",
+                self.scheduled,
+                e
+            )
+        })
+    }
+}
+
+/// Whether a message fits in the limits of a height with the other messages scheduled there.
+fn has_room(msgs: &[ScheduledMessage], msg: &ScheduledMessage) -> bool {
+    let gas: u64 = msgs.iter().map(|m| m.gas_limit).sum();
+    msgs.len() < MAX_MESSAGES_PER_HEIGHT && gas + msg.gas_limit <= MAX_GAS_PER_HEIGHT
+}
+
+/// The parameters of a message to schedule. The value sent along with them is escrowed to pay
+/// the gas fee of the executions, and has to cover at least one; the rest is refunded when the
+/// message is done or cancelled.
+#[derive(Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct ScheduleParams {
+    /// The height to execute the message at, which has to be in the future.
+    pub height: ChainEpoch,
+    pub to: Address,
+    pub method: MethodNum,
+    pub params: RawBytes,
+    pub value: TokenAmount,
+    pub gas_limit: u64,
+    /// At least the base fee at the time of scheduling, and more than zero.
+    pub gas_price: TokenAmount,
+    /// Zero to execute the message once, otherwise at least `MIN_INTERVAL`.
+    pub interval: ChainEpoch,
+}
+
+#[derive(Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct CancelParams {
+    pub height: ChainEpoch,
+    pub id: u64,
+}
+
+#[derive(FromPrimitive)]
+#[repr(u64)]
+pub enum Method {
+    Constructor = METHOD_CONSTRUCTOR,
+    Schedule = frc42_dispatch::method_hash!("Schedule"),
+    Cancel = frc42_dispatch::method_hash!("Cancel"),
+    GetScheduled = frc42_dispatch::method_hash!("GetScheduled"),
+    TakeDue = frc42_dispatch::method_hash!("TakeDue"),
+    CancelFailed = frc42_dispatch::method_hash!("CancelFailed"),
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::{address::Address, econ::TokenAmount};
+
+    use super::{ScheduledMessage, State, MAX_GAS_PER_HEIGHT};
+
+    fn message(interval: i64, runs: u64) -> ScheduledMessage {
+        ScheduledMessage {
+            id: 0,
+            from: Address::new_id(100),
+            to: Address::new_id(200),
+            method: 2,
+            params: RawBytes::default(),
+            value: TokenAmount::from_atto(0),
+            gas_limit: 1000,
+            gas_price: TokenAmount::from_atto(2),
+            interval,
+            deposit: TokenAmount::from_atto(2000 * runs),
+        }
+    }
+
+    #[test]
+    fn take_due() {
+        let store = MemoryBlockstore::new();
+        let mut state = State::new(&store).unwrap();
+
+        let once = state.schedule(&store, 10, message(0, 1)).unwrap();
+        let periodic = state.schedule(&store, 10, message(5, 3)).unwrap();
+        let cancelled = state.schedule(&store, 10, message(0, 1)).unwrap();
+        assert!(state.cancel(&store, 10, cancelled).unwrap().is_some());
+        assert!(state.cancel(&store, 10, cancelled).unwrap().is_none());
+
+        assert!(state.take_due(&store, 9).unwrap().msgs.is_empty());
+
+        let due = state.take_due(&store, 10).unwrap();
+        assert_eq!(
+            due.msgs.iter().map(|m| m.id).collect::<Vec<_>>(),
+            [once, periodic]
+        );
+        assert_eq!(due.fees, TokenAmount::from_atto(4000));
+        assert!(due.refunds.is_empty());
+        assert!(state.get_scheduled(&store, 10).unwrap().is_empty());
+
+        let due = state.take_due(&store, 15).unwrap();
+        assert_eq!(
+            due.msgs.iter().map(|m| m.id).collect::<Vec<_>>(),
+            [periodic]
+        );
+
+        let cancelled = state.cancel(&store, 20, periodic).unwrap().unwrap();
+        assert_eq!(cancelled.deposit, TokenAmount::from_atto(2000));
+        assert!(state.take_due(&store, 20).unwrap().msgs.is_empty());
+    }
+
+    #[test]
+    fn deposit_runs_out() {
+        let store = MemoryBlockstore::new();
+        let mut state = State::new(&store).unwrap();
+
+        let mut msg = message(10, 2);
+        msg.deposit += TokenAmount::from_atto(500);
+        let id = state.schedule(&store, 10, msg).unwrap();
+
+        let due = state.take_due(&store, 10).unwrap();
+        assert_eq!(due.msgs.len(), 1);
+        assert!(due.refunds.is_empty());
+
+        // the last run it can pay for returns the rest
+        let due = state.take_due(&store, 20).unwrap();
+        assert_eq!(due.msgs.iter().map(|m| m.id).collect::<Vec<_>>(), [id]);
+        assert_eq!(due.fees, TokenAmount::from_atto(2000));
+        assert_eq!(
+            due.refunds,
+            vec![(Address::new_id(100), TokenAmount::from_atto(500))]
+        );
+        assert!(state.get_scheduled(&store, 30).unwrap().is_empty());
+    }
+
+    #[test]
+    fn gas_per_height() {
+        let store = MemoryBlockstore::new();
+        let mut state = State::new(&store).unwrap();
+
+        let mut msg = message(0, 1);
+        msg.gas_limit = MAX_GAS_PER_HEIGHT / 2;
+        state.schedule(&store, 10, msg.clone()).unwrap();
+        state.schedule(&store, 10, msg.clone()).unwrap();
+        assert!(state.schedule(&store, 10, msg.clone()).is_err());
+        state.schedule(&store, 11, msg).unwrap();
+    }
+}
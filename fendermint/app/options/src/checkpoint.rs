@@ -0,0 +1,29 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use clap::{Args, Subcommand};
+
+#[derive(Subcommand, Debug)]
+pub enum CheckpointCommands {
+    /// Re-derive the bottom-up checkpoint created at a height from the local state, and compare
+    /// it with the one the node stored and signed; print the comparison as JSON.
+    /// The node must not be running.
+    Simulate(CheckpointSimulateArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CheckpointArgs {
+    #[command(subcommand)]
+    pub command: CheckpointCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct CheckpointSimulateArgs {
+    /// Block height the checkpoint was created at.
+    #[arg(long)]
+    pub epoch: u64,
+    /// Hex encoded hash of the CometBFT block at that height, to check the one in the checkpoint;
+    /// it isn't part of the local state, so it's not checked otherwise.
+    #[arg(long)]
+    pub block_hash: Option<String>,
+}
@@ -31,6 +31,10 @@ pub enum GenesisCommands {
     AddValidator(GenesisAddValidatorArgs),
     /// Set the EAM actor permission mode.
     SetEamPermissions(GenesisSetEAMPermissionsArgs),
+    /// Set the resource limits applied during execution; unset limits use the FVM defaults.
+    SetExecLimits(GenesisSetExecLimitsArgs),
+    /// Add the actors, contracts and seed calls of a JSON manifest to the ones deployed at genesis.
+    AddDeployments(GenesisAddDeploymentsArgs),
     /// IPC commands.
     Ipc {
         #[command(subcommand)]
@@ -40,6 +44,44 @@ pub enum GenesisCommands {
     IntoTendermint(GenesisIntoTendermintArgs),
 }
 
+#[derive(Args, Debug)]
+pub struct GenesisSetExecLimitsArgs {
+    /// Maximum memory used during the entire (recursive) execution of a message, in bytes.
+    #[arg(long)]
+    pub max_memory_bytes: Option<u64>,
+    /// Maximum memory of any single Wasm instance, in bytes.
+    #[arg(long)]
+    pub max_inst_memory_bytes: Option<u64>,
+    /// Maximum depth of actor calls.
+    #[arg(long)]
+    pub max_call_depth: Option<u32>,
+    /// Maximum number of elements on the Wasm stack.
+    #[arg(long)]
+    pub max_wasm_stack: Option<u32>,
+    /// Gas the top-down messages can use in a block; the rest are executed in the following blocks.
+    #[arg(long)]
+    pub topdown_gas_budget: Option<u64>,
+    /// Maximum number of bottom-up messages in a checkpoint; the rest go into the following ones.
+    #[arg(long)]
+    pub bottomup_max_msgs: Option<u32>,
+    /// Maximum ABI encoded size of the bottom-up messages in a checkpoint, in bytes.
+    #[arg(long)]
+    pub bottomup_max_bytes: Option<u64>,
+    /// Number of blocks of bottom-up checkpoint history to keep; older checkpoints are pruned.
+    #[arg(long)]
+    pub checkpoint_history: Option<u64>,
+    /// Ethereum address of the contract setting the block gas limit and the base fee parameters.
+    #[arg(long, value_parser = parse_eth_address)]
+    pub gas_governance: Option<Address>,
+}
+
+#[derive(Args, Debug)]
+pub struct GenesisAddDeploymentsArgs {
+    /// Path to the JSON manifest with the `actors`, `contracts` and `calls` to add.
+    #[arg(long, short)]
+    pub manifest: PathBuf,
+}
+
 #[derive(Args, Debug)]
 pub struct GenesisSetEAMPermissionsArgs {
     #[arg(
@@ -10,10 +10,12 @@ use lazy_static::lazy_static;
 use tracing_subscriber::EnvFilter;
 
 use self::{
-    eth::EthArgs, genesis::GenesisArgs, key::KeyArgs, materializer::MaterializerArgs, rpc::RpcArgs,
-    run::RunArgs,
+    checkpoint::CheckpointArgs, eth::EthArgs, genesis::GenesisArgs, key::KeyArgs,
+    materializer::MaterializerArgs, rpc::RpcArgs, run::RunArgs, snapshot::SnapshotArgs,
+    topdown::TopDownArgs, upgrade::UpgradeArgs,
 };
 
+pub mod checkpoint;
 pub mod config;
 pub mod eth;
 pub mod genesis;
@@ -21,6 +23,9 @@ pub mod key;
 pub mod materializer;
 pub mod rpc;
 pub mod run;
+pub mod snapshot;
+pub mod topdown;
+pub mod upgrade;
 
 mod log;
 mod parse;
@@ -194,6 +199,15 @@ pub enum Commands {
     /// Subcommands related to the Testnet Materializer.
     #[clap(aliases  = &["mat", "matr", "mate"])]
     Materializer(MaterializerArgs),
+    /// Subcommands related to the scheduled upgrades of the chain.
+    Upgrade(UpgradeArgs),
+    /// Subcommands related to the top-down finality with the parent.
+    #[clap(name = "topdown")]
+    TopDown(TopDownArgs),
+    /// Subcommands related to the bottom-up checkpoints of the subnet.
+    Checkpoint(CheckpointArgs),
+    /// Subcommands related to exporting and importing snapshots of the application state.
+    Snapshot(SnapshotArgs),
 }
 
 #[cfg(test)]
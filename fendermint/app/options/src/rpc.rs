@@ -92,6 +92,102 @@ pub enum RpcQueryCommands {
     },
     /// Get the slowly changing state parameters.
     StateParams,
+    /// Get the parent finality votes collected by the node at each height above the last
+    /// finalized one, with the validators who voted and their power; print it as JSON.
+    ///
+    /// These are not part of the ledger, so the height is ignored.
+    VoteTally,
+    /// Administer the top-down finality of the node; print its state as JSON.
+    ///
+    /// Needs the admin token configured on the node.
+    TopDown {
+        /// The admin token configured as `ipc.topdown.admin_token` on the node.
+        #[arg(long, env = "FM_IPC__TOPDOWN__ADMIN_TOKEN", hide_env_values = true)]
+        token: String,
+        #[command(subcommand)]
+        command: RpcTopDownCommands,
+    },
+    /// Get the top-down finality settings the node is running with, e.g. the chain head delay
+    /// and the polling and vote intervals; print them as JSON.
+    ///
+    /// These are not part of the ledger, so the height is ignored.
+    TopDownParams,
+    /// Get the parent finality committed for a parent height, with the validator changes and
+    /// top-down messages executed with it, from the log of the node; print it as JSON.
+    ///
+    /// The log is kept by the node, not the ledger, so the height is ignored.
+    FinalityLog {
+        /// The parent height to look up.
+        #[arg(long)]
+        parent_height: u64,
+    },
+    /// Look up executed transactions in the transaction index of the node, either by hash,
+    /// or by sender, recipient or method, newest first; print them as JSON.
+    ///
+    /// The index is kept by the node if enabled with `db.tx_index`, so the height is ignored.
+    Txs {
+        /// The CometBFT or the Ethereum hash of the transaction, in hexadecimal format.
+        #[arg(long, value_parser = parse_bytes, conflicts_with_all = ["sender", "recipient", "method"])]
+        hash: Option<Bytes>,
+        /// Address of the sender of the transactions.
+        #[arg(long, value_parser = parse_address, conflicts_with_all = ["recipient", "method"])]
+        sender: Option<Address>,
+        /// Address of the recipient of the transactions.
+        #[arg(long, value_parser = parse_address, conflicts_with = "method")]
+        recipient: Option<Address>,
+        /// Method number invoked by the transactions.
+        #[arg(long)]
+        method: Option<MethodNum>,
+        /// Number of the newest matching transactions to skip.
+        #[arg(long, default_value = "0")]
+        skip: u64,
+        /// Maximum number of transactions to return.
+        #[arg(long, default_value = "20")]
+        limit: u64,
+    },
+    /// Get the tree of calls a transaction made when it was executed, with their parameters,
+    /// gas and exit codes; print it as JSON.
+    ///
+    /// The traces are recorded by the node if enabled with `db.exec_traces`, so the height is ignored.
+    Trace {
+        /// The CometBFT hash of the transaction, or the Ethereum one if the node also keeps
+        /// a transaction index, in hexadecimal format.
+        #[arg(long, value_parser = parse_bytes)]
+        hash: Bytes,
+    },
+    /// Get the expected outcome of a transaction waiting in the mempool; print it as JSON.
+    ///
+    /// The results are kept by the node if enabled with `fvm.pending_receipts`, so the height is ignored.
+    PendingReceipt {
+        /// The CometBFT or the Ethereum hash of the transaction, in hexadecimal format.
+        #[arg(long, value_parser = parse_bytes)]
+        hash: Bytes,
+    },
+    /// Add up the gas, calls and state bytes used by each actor in the recent blocks,
+    /// the actors using the most gas first; print them as JSON.
+    ///
+    /// The statistics are kept by the node if enabled with `db.actor_stats`, so the height is ignored.
+    ActorStats {
+        /// Number of the most recent blocks to add up.
+        #[arg(long, default_value = "1000")]
+        blocks: u64,
+        /// Maximum number of actors to return; all of them if zero.
+        #[arg(long, default_value = "20")]
+        limit: u64,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RpcTopDownCommands {
+    /// Show whether the node is proposing parent finality, and how far it has synced.
+    Status,
+    /// Stop proposing new parent finality, e.g. during an incident on the parent chain.
+    ///
+    /// The node keeps syncing with the parent and voting on the proposals of others.
+    /// This is not persisted, a restarted node proposes again.
+    Pause,
+    /// Resume proposing new parent finality.
+    Resume,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -0,0 +1,42 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommands {
+    /// Export the application state after a block into a snapshot directory with a manifest
+    /// of checksums; print the manifest as JSON. The node must not be running.
+    Export(SnapshotExportArgs),
+    /// Verify a snapshot directory against its manifest and import it as the committed state.
+    /// The node must not be running.
+    Import(SnapshotImportArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotArgs {
+    #[command(subcommand)]
+    pub command: SnapshotCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotExportArgs {
+    /// Block height to export the state after; the last committed one by default.
+    #[arg(long)]
+    pub height: Option<u64>,
+    /// Directory to write the snapshot into; it must not exist yet.
+    #[arg(long, short)]
+    pub output: PathBuf,
+    /// Maximum size of the chunks the snapshot is split into; `snapshots.chunk_size_bytes` by default.
+    #[arg(long)]
+    pub chunk_size: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotImportArgs {
+    /// Directory of the snapshot, with the `manifest.json` file in it.
+    #[arg(long, short)]
+    pub input: PathBuf,
+}
@@ -0,0 +1,57 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use tendermint_rpc::Url;
+
+#[derive(Subcommand, Debug)]
+pub enum TopDownCommands {
+    /// Collect the validator changes the subnet missed from its parent, and write them to a file
+    /// with the upgrade to store them in the gateway; print the upgrade definition as JSON.
+    BackfillChanges(TopDownBackfillChangesArgs),
+    /// Print the recommended top-down settings for subnets of a parent chain,
+    /// as the environment variables to set on the node.
+    RecommendedSettings(TopDownRecommendedSettingsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct TopDownArgs {
+    #[command(subcommand)]
+    pub command: TopDownCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct TopDownBackfillChangesArgs {
+    /// First parent block height to scan for `NewStakingChangeRequest` events.
+    #[arg(long)]
+    pub from_height: u64,
+    /// Last parent block height to scan, inclusive.
+    #[arg(long)]
+    pub to_height: u64,
+    /// The Ethereum API of the subnet, to find out which configuration number it has applied.
+    #[arg(long, default_value = "http://127.0.0.1:8545")]
+    pub child_url: Url,
+    /// Configuration number of the first missing change; the one after the configuration
+    /// number applied by the subnet if not set. Changes which have been stored by the subnet
+    /// but not applied yet by a checkpoint have to be skipped this way.
+    #[arg(long)]
+    pub start_configuration_number: Option<u64>,
+    /// Path to write the missing changes to (JSON format).
+    #[arg(long, short)]
+    pub output: PathBuf,
+    /// The name of the chain to run the upgrade on, as in the genesis.
+    #[arg(long)]
+    pub chain_name: String,
+    /// Block height to run the upgrade at.
+    #[arg(long)]
+    pub block_height: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct TopDownRecommendedSettingsArgs {
+    /// The parent chain: filecoin, calibration, ethereum, sepolia or local.
+    #[arg(long)]
+    pub parent_chain: String,
+}
@@ -0,0 +1,75 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use tendermint_rpc::Url;
+
+#[derive(Subcommand, Debug)]
+pub enum UpgradeCommands {
+    /// Sign the upgrades in an upgrade manifest and add the signatures to their approvals.
+    Approve(UpgradeApproveArgs),
+    /// Run the migrations in an upgrade manifest against a snapshot of the state and show
+    /// what they would change, without touching the state of the node.
+    DryRun(UpgradeDryRunArgs),
+    /// Roll the application state back to the checkpoint taken before the last upgrade.
+    /// The node has to be stopped first.
+    Rollback(UpgradeRollbackArgs),
+    /// List the upgrades the node schedules for its chain, to check it runs the right
+    /// binary and configuration before a coordinated upgrade.
+    List(UpgradeListArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct UpgradeArgs {
+    #[command(subcommand)]
+    pub command: UpgradeCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct UpgradeApproveArgs {
+    /// Path to the upgrade manifest (JSON format); the approvals are written back to it.
+    #[arg(long, short)]
+    pub manifest: PathBuf,
+    /// Path to the secret key of the approver (base64 format).
+    #[arg(long, short)]
+    pub secret_key: PathBuf,
+    /// Only approve the upgrades at this block height; all of them if not set.
+    #[arg(long)]
+    pub block_height: Option<u64>,
+    /// Only approve the upgrades on the chain with this name; all of them if not set.
+    #[arg(long)]
+    pub chain_name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct UpgradeDryRunArgs {
+    /// Path to the upgrade manifest (JSON format).
+    #[arg(long, short)]
+    pub manifest: PathBuf,
+    /// Path to a state snapshot: either a CAR file, or a snapshot directory with
+    /// a `manifest.json` and the `parts` of the CAR file.
+    #[arg(long, short)]
+    pub snapshot: PathBuf,
+    /// Only run the upgrade at this block height; all upgrades of the chain in the
+    /// snapshot are run in order of height if not set.
+    #[arg(long)]
+    pub block_height: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct UpgradeRollbackArgs {}
+
+#[derive(Args, Debug)]
+pub struct UpgradeListArgs {
+    /// The URL of the Tendermint node's RPC endpoint, to find out the chain ID and
+    /// the current height.
+    #[arg(
+        long,
+        short,
+        default_value = "http://127.0.0.1:26657",
+        env = "TENDERMINT_RPC_URL"
+    )]
+    pub url: Url,
+}
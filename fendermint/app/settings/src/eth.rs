@@ -4,6 +4,7 @@
 use fvm_shared::econ::TokenAmount;
 use serde::Deserialize;
 use serde_with::{serde_as, DurationSeconds};
+use std::path::PathBuf;
 use std::time::Duration;
 
 use crate::{IsHumanReadable, SocketAddress};
@@ -16,7 +17,14 @@ pub struct EthSettings {
     #[serde_as(as = "DurationSeconds<u64>")]
     pub filter_timeout: Duration,
     pub cache_capacity: usize,
+    /// Maximum number of blocks a single `eth_getLogs` query can span; unlimited if zero.
+    pub max_log_range: u64,
     pub gas: GasOpt,
+    pub http: HttpOpt,
+    pub methods: MethodOpt,
+    pub cors: CorsOpt,
+    /// Optionally also serve the API on a Unix domain socket.
+    pub unix_socket: Option<UnixSocketOpt>,
 }
 
 #[serde_as]
@@ -28,3 +36,49 @@ pub struct GasOpt {
     pub num_blocks_max_prio_fee: u64,
     pub max_fee_hist_size: u64,
 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpOpt {
+    /// Maximum number of requests in a JSON-RPC batch; unlimited if zero.
+    pub max_batch_size: usize,
+    /// Maximum size of a request body in bytes.
+    pub max_body_size: usize,
+    /// Whether to compress responses with gzip or deflate when the client accepts it.
+    pub compression: bool,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct MethodOpt {
+    /// Methods to serve, e.g. `eth_call`, or `debug_*` for a whole namespace; all of them if empty.
+    pub allowed: Vec<String>,
+    /// Methods not to serve, even if they are allowed.
+    pub disabled: Vec<String>,
+    /// Maximum gas of the messages executed by `eth_call`, `eth_estimateGas`, `debug_traceCall`
+    /// and `ipc_callAtParentFinality`; the block gas limit if zero.
+    pub max_call_gas: u64,
+    /// Maximum time to wait for the execution of those messages, and for `eth_getProof`,
+    /// in seconds; unlimited if zero.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub call_timeout: Duration,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CorsOpt {
+    /// Origins allowed to make cross-domain requests, e.g. `https://example.com`, or `*` for any.
+    pub allowed_origins: Vec<String>,
+    /// HTTP methods allowed in cross-domain requests.
+    pub allowed_methods: Vec<String>,
+    /// HTTP headers allowed in cross-domain requests.
+    pub allowed_headers: Vec<String>,
+    /// Whether to expose the response to the frontend when the request includes credentials.
+    pub allow_credentials: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UnixSocketOpt {
+    /// Path of the socket file; any stale socket found there is replaced.
+    pub path: PathBuf,
+    /// Permissions of the socket file, e.g. `0o600` to only allow the owner.
+    pub mode: u32,
+}
@@ -22,6 +22,11 @@ pub struct FvmSettings {
     /// Enabling this option is required to fully support "pending" queries in the Ethereum API,
     /// otherwise only the nonces and balances are projected into a partial state.
     pub exec_in_check: bool,
+    /// Keep the results of executing the transactions in the mempool during their checks,
+    /// so clients can see their expected outcome before they are included in a block.
+    ///
+    /// Requires `exec_in_check`.
+    pub pending_receipts: bool,
 
     /// Gas fee used when broadcasting transactions.
     #[serde_as(as = "IsHumanReadable")]
@@ -1,7 +1,7 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use config::{Config, ConfigError, Environment, File};
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
@@ -15,7 +15,7 @@ use tendermint_rpc::Url;
 use utils::EnvInterpol;
 
 use fendermint_vm_encoding::{human_readable_delegate, human_readable_str};
-use fendermint_vm_topdown::BlockHeight;
+use fendermint_vm_topdown::{BlockHeight, ParentFinalityRule};
 
 use self::eth::EthSettings;
 use self::fvm::FvmSettings;
@@ -25,6 +25,7 @@ use ipc_provider::config::deserialize::deserialize_eth_address_from_str;
 pub mod eth;
 pub mod fvm;
 pub mod resolver;
+pub mod topdown;
 pub mod utils;
 
 /// Marker to be used with the `#[serde_as(as = "IsHumanReadable")]` annotations.
@@ -99,6 +100,18 @@ pub struct DbSettings {
     ///
     /// This affects how long we can go back in state queries.
     pub state_hist_size: u64,
+    /// Whether to maintain an index of the executed transactions.
+    pub tx_index: bool,
+    /// Whether to index the Ethereum logs emitted in each block, to serve `eth_getLogs` faster.
+    pub log_index: bool,
+    /// Whether to record the call tree of the executed transactions, for debugging.
+    pub exec_traces: bool,
+    /// Number of blocks to keep the execution traces for; 0 means unlimited.
+    pub exec_trace_history: u64,
+    /// Whether to record the gas, calls and state bytes used by each actor, for analytics.
+    pub actor_stats: bool,
+    /// Number of blocks to keep the actor statistics for; 0 means unlimited.
+    pub actor_stats_history: u64,
 }
 
 /// Settings affecting how we deal with failures in trying to send transactions to the local CometBFT node.
@@ -130,6 +143,18 @@ pub struct TopDownSettings {
     pub proposal_delay: BlockHeight,
     /// The max number of blocks one should make the topdown proposal
     pub max_proposal_range: BlockHeight,
+    /// The max number of parent heights to fetch concurrently while catching up with the parent
+    pub parallel_fetches: Option<usize>,
+    /// The max number of blocks to walk back looking for where we diverged from the parent after a reorg
+    pub max_reorg_lookback: Option<BlockHeight>,
+    /// How to decide which parent blocks are final: `confirmations` waits for `chain_head_delay`
+    /// blocks on top of the chain head, `finalized_tag` uses the block the parent reports as
+    /// finalized, e.g. on Ethereum.
+    pub parent_finality_rule: Option<ParentFinalityRule>,
+    /// Token authorizing the admin queries which pause and resume proposing parent finality
+    /// on this node, e.g. during an incident on the parent chain. They are disabled without one.
+    /// Best set as `FM_IPC__TOPDOWN__ADMIN_TOKEN` rather than in a config file.
+    pub admin_token: Option<String>,
     /// Parent syncing cron period, in seconds
     #[serde_as(as = "DurationSeconds<u64>")]
     pub polling_interval: Duration,
@@ -145,6 +170,14 @@ pub struct TopDownSettings {
     pub parent_http_timeout: Option<Duration>,
     /// Bearer token for any Authorization header.
     pub parent_http_auth_token: Option<String>,
+    /// Independent parent rpc http endpoints, ideally operated by different parties, to verify
+    /// the blocks fetched from `parent_http_endpoint` against before proposing them.
+    pub parent_witness_endpoints: Option<Vec<Url>>,
+    /// Bearer tokens for the Authorization header of each witness, in the same order as
+    /// `parent_witness_endpoints`; an empty one sends none. No witness gets a token by default.
+    pub parent_witness_auth_tokens: Option<Vec<String>>,
+    /// The number of witnesses which have to confirm a parent block; all of them by default.
+    pub parent_witness_threshold: Option<usize>,
     /// The parent registry address
     #[serde(deserialize_with = "deserialize_eth_address_from_str")]
     pub parent_registry: Address,
@@ -153,6 +186,22 @@ pub struct TopDownSettings {
     pub parent_gateway: Address,
 }
 
+/// A service holding the validator key, which signs the bottom-up checkpoints for the node.
+#[serde_as]
+#[derive(Debug, Deserialize, Clone)]
+pub struct CheckpointSignerSettings {
+    /// The HTTP endpoint the checkpoint hashes are posted to for signing.
+    pub url: Url,
+    /// Base64 encoded public key of the validator the service signs for.
+    pub public_key: String,
+    /// Bearer token for the Authorization header.
+    /// Best set as `FM_IPC__CHECKPOINT_SIGNER__AUTH_TOKEN` rather than in a config file.
+    pub auth_token: Option<String>,
+    /// Timeout for the signing requests.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub timeout: Duration,
+}
+
 #[serde_as]
 #[derive(Debug, Deserialize, Clone)]
 pub struct IpcSettings {
@@ -167,6 +216,11 @@ pub struct IpcSettings {
     /// The config for top down checkpoint. It's None if subnet id is root or not activating
     /// any top down checkpoint related operations
     pub topdown: Option<TopDownSettings>,
+    /// Directory where the bottom-up checkpoints are exported to as JSON before they are
+    /// pruned from the ledger, if pruning is enabled. Pruned checkpoints are not kept if not set.
+    pub checkpoint_archive_dir: Option<PathBuf>,
+    /// Sign the bottom-up checkpoints with a remote service instead of the validator key.
+    pub checkpoint_signer: Option<CheckpointSignerSettings>,
 }
 
 impl IpcSettings {
@@ -175,6 +229,46 @@ impl IpcSettings {
             .as_ref()
             .ok_or_else(|| anyhow!("top down config missing"))
     }
+
+    /// The directory to export pruned checkpoints to, if one is configured.
+    pub fn checkpoint_archive_dir(&self, home_dir: &Path) -> Option<PathBuf> {
+        self.checkpoint_archive_dir
+            .as_ref()
+            .map(|path| utils::expand_path(home_dir, path))
+    }
+
+    /// Check the settings which can be parsed but would make the node misbehave.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.vote_interval.is_zero() {
+            bail!("ipc.vote_interval has to be at least 1 second");
+        }
+        if self.vote_timeout < self.vote_interval {
+            bail!("ipc.vote_timeout can't be shorter than ipc.vote_interval");
+        }
+        if let Some(ref topdown) = self.topdown {
+            topdown.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl TopDownSettings {
+    /// Check the settings which can be parsed but would make the node misbehave.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.polling_interval.is_zero() {
+            bail!("ipc.topdown.polling_interval has to be at least 1 second");
+        }
+        if self.max_proposal_range == 0 {
+            bail!("ipc.topdown.max_proposal_range has to be positive");
+        }
+        if self.parallel_fetches == Some(0) {
+            bail!("ipc.topdown.parallel_fetches has to be positive");
+        }
+        if self.exponential_back_off.is_zero() {
+            bail!("ipc.topdown.exponential_back_off has to be at least 1 second");
+        }
+        Ok(())
+    }
 }
 
 #[serde_as]
@@ -212,6 +306,88 @@ pub struct MetricsSettings {
     pub listen: SocketAddress,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventSettings {
+    /// URLs to post the events to as JSON.
+    pub webhooks: Vec<Url>,
+    /// Base URL of a Kafka REST proxy to produce the events through.
+    pub kafka_rest_url: Option<Url>,
+    /// Kafka topic to produce the events to.
+    pub kafka_topic: String,
+    /// Address of a NATS server to publish the events to, e.g. `127.0.0.1:4222`.
+    pub nats_address: Option<String>,
+    /// NATS subject to publish the events on.
+    pub nats_subject: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct UpgradeSettings {
+    /// Base64 encoded public keys of those who can approve upgrades.
+    pub approvers: Vec<String>,
+    /// Number of distinct approvers who have to sign an upgrade before it is scheduled.
+    /// Zero means upgrades don't need approval.
+    pub threshold: usize,
+    /// JSON file declaring the upgrades to schedule, see `UpgradeManifest`.
+    pub manifest: Option<PathBuf>,
+    /// Address of the contract deciding when the upgrades marked as governed in the
+    /// manifest activate, see `UpgradeGovernance`.
+    pub governance: Option<String>,
+    /// Run upgrades again even if the state records that they already succeeded.
+    /// Only meant for development networks.
+    pub allow_rerun: bool,
+    /// Block height of an upcoming upgrade where the node stops unless its binary has an
+    /// upgrade scheduled at that height, so nodes which missed the release halt rather
+    /// than fork the chain. Zero means never halt.
+    pub halt_height: BlockHeight,
+}
+
+/// Rules for admitting transactions to the mempool of the node.
+#[serde_as]
+#[derive(Debug, Deserialize, Clone)]
+pub struct MempoolSettings {
+    /// Minimum gas premium of the transactions admitted.
+    #[serde_as(as = "IsHumanReadable")]
+    pub min_gas_premium: TokenAmount,
+    /// Maximum size of the message parameters in bytes; 0 means unlimited.
+    pub max_params_size: usize,
+    /// Addresses, in `f` or `0x` format, whose transactions are rejected.
+    pub banned_senders: Vec<String>,
+    /// Addresses, in `f` or `0x` format, allowed to deploy contracts; anyone can if empty.
+    pub contract_creators: Vec<String>,
+}
+
+/// How the node fills the blocks it proposes, depending on the pending load.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProposalSettings {
+    /// Fraction of the block space filled with mempool transactions under normal load.
+    pub target_fullness: f64,
+    /// Number of transactions in the mempool from which blocks are filled up; 0 means never.
+    pub burst_mempool_txs: usize,
+    /// Number of top-down messages waiting for execution from which blocks are filled up; 0 means never.
+    pub burst_topdown_msgs: usize,
+    /// Bytes of block space kept free for the parent finality and checkpoints the proposer adds.
+    pub ipc_reserve_bytes: usize,
+}
+
+impl ProposalSettings {
+    /// Check the settings which can be parsed but would make the node misbehave.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(self.target_fullness > 0.0 && self.target_fullness <= 1.0) {
+            bail!("proposal.target_fullness has to be more than 0 and at most 1");
+        }
+        Ok(())
+    }
+}
+
+impl UpgradeSettings {
+    /// The upgrade manifest, if one is configured.
+    pub fn manifest(&self, home_dir: &Path) -> Option<PathBuf> {
+        self.manifest
+            .as_ref()
+            .map(|path| utils::expand_path(home_dir, path))
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     /// Home directory configured on the CLI, to which all paths in settings can be set relative.
@@ -245,6 +421,10 @@ pub struct Settings {
     pub resolver: ResolverSettings,
     pub broadcast: BroadcastSettings,
     pub ipc: IpcSettings,
+    pub upgrades: UpgradeSettings,
+    pub mempool: MempoolSettings,
+    pub proposal: ProposalSettings,
+    pub events: EventSettings,
 }
 
 impl Settings {
@@ -287,7 +467,16 @@ impl Settings {
                     .list_separator(",") // need to list keys explicitly below otherwise it can't pase simple `String` type
                     .with_list_parse_key("resolver.connection.external_addresses")
                     .with_list_parse_key("resolver.discovery.static_addresses")
-                    .with_list_parse_key("resolver.membership.static_subnets"),
+                    .with_list_parse_key("resolver.membership.static_subnets")
+                    .with_list_parse_key("eth.cors.allowed_origins")
+                    .with_list_parse_key("eth.cors.allowed_methods")
+                    .with_list_parse_key("eth.cors.allowed_headers")
+                    .with_list_parse_key("upgrades.approvers")
+                    .with_list_parse_key("mempool.banned_senders")
+                    .with_list_parse_key("mempool.contract_creators")
+                    .with_list_parse_key("events.webhooks")
+                    .with_list_parse_key("ipc.topdown.parent_witness_endpoints")
+                    .with_list_parse_key("ipc.topdown.parent_witness_auth_tokens"),
             ))
             // Set the home directory based on what was passed to the CLI,
             // so everything in the config can be relative to it.
@@ -364,6 +553,15 @@ mod tests {
         assert!(settings.resolver_enabled());
     }
 
+    #[test]
+    fn validate_test_config() {
+        let mut settings = parse_config("test");
+        settings.ipc.validate().expect("the test config is valid");
+
+        settings.ipc.vote_timeout = settings.ipc.vote_interval / 2;
+        assert!(settings.ipc.validate().is_err());
+    }
+
     // Run these tests serially because they modify the environment.
     #[serial]
     mod env {
@@ -75,6 +75,27 @@ pub struct MembershipSettings {
     /// Maximum age of provider records before the peer is removed without an update.
     #[serde_as(as = "DurationSeconds<u64>")]
     pub max_provider_age: Duration,
+
+    /// Maximum age of votes gossiped by validators before they are dropped; 0 means no limit.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub max_vote_age: Duration,
+
+    /// Overrides for the peer scoring of the gossip.
+    #[serde(default)]
+    pub peer_score: PeerScoreSettings,
+}
+
+/// Overrides for the GossipSub peer scoring; anything not set uses the libp2p defaults.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PeerScoreSettings {
+    /// Score below which gossip is not propagated to or accepted from a peer.
+    pub gossip_threshold: Option<f64>,
+    /// Score below which our own messages are not published to a peer.
+    pub publish_threshold: Option<f64>,
+    /// Score below which all messages from a peer are ignored.
+    pub graylist_threshold: Option<f64>,
+    /// Weight of the voting topics in the peer score; votes don't count if not set.
+    pub vote_topic_weight: Option<f64>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
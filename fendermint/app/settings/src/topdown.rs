@@ -0,0 +1,123 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Recommended top-down settings for the parent chains subnets are commonly deployed on.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::bail;
+use fendermint_vm_topdown::{BlockHeight, ParentFinalityRule};
+
+/// Parent chains with known good top-down settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParentChain {
+    /// Filecoin mainnet, with 30 second epochs.
+    Filecoin,
+    /// The Filecoin Calibration testnet.
+    Calibration,
+    /// Ethereum mainnet, with 12 second slots and finality from the beacon chain.
+    Ethereum,
+    /// The Ethereum Sepolia testnet.
+    Sepolia,
+    /// A local development chain, e.g. Anvil or a local Lotus devnet.
+    Local,
+}
+
+impl FromStr for ParentChain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "filecoin" => Ok(Self::Filecoin),
+            "calibration" => Ok(Self::Calibration),
+            "ethereum" => Ok(Self::Ethereum),
+            "sepolia" => Ok(Self::Sepolia),
+            "local" => Ok(Self::Local),
+            other => bail!(
+                "unknown parent chain: {other}; expected one of filecoin, calibration, ethereum, sepolia or local"
+            ),
+        }
+    }
+}
+
+/// The settings of a node which depend on how fast and how final the parent chain is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopDownTiming {
+    /// See `ipc.topdown.chain_head_delay`.
+    pub chain_head_delay: BlockHeight,
+    /// See `ipc.topdown.proposal_delay`.
+    pub proposal_delay: BlockHeight,
+    /// See `ipc.topdown.polling_interval`.
+    pub polling_interval: Duration,
+    /// See `ipc.topdown.parent_finality_rule`.
+    pub parent_finality_rule: ParentFinalityRule,
+    /// See `ipc.vote_interval`.
+    pub vote_interval: Duration,
+}
+
+impl ParentChain {
+    /// The recommended settings for subnets of this chain.
+    ///
+    /// Filecoin blocks are final after a handful of epochs in practice, while on Ethereum
+    /// the finalized block reported by the node is followed, which is about two epochs behind.
+    pub fn recommended_timing(&self) -> TopDownTiming {
+        match self {
+            Self::Filecoin => TopDownTiming {
+                chain_head_delay: 20,
+                proposal_delay: 2,
+                polling_interval: Duration::from_secs(10),
+                parent_finality_rule: ParentFinalityRule::Confirmations,
+                vote_interval: Duration::from_secs(1),
+            },
+            Self::Calibration => TopDownTiming {
+                chain_head_delay: 10,
+                proposal_delay: 2,
+                polling_interval: Duration::from_secs(10),
+                parent_finality_rule: ParentFinalityRule::Confirmations,
+                vote_interval: Duration::from_secs(1),
+            },
+            Self::Ethereum | Self::Sepolia => TopDownTiming {
+                chain_head_delay: 0,
+                proposal_delay: 2,
+                polling_interval: Duration::from_secs(12),
+                parent_finality_rule: ParentFinalityRule::FinalizedTag,
+                vote_interval: Duration::from_secs(1),
+            },
+            Self::Local => TopDownTiming {
+                chain_head_delay: 0,
+                proposal_delay: 0,
+                polling_interval: Duration::from_secs(1),
+                parent_finality_rule: ParentFinalityRule::Confirmations,
+                vote_interval: Duration::from_secs(1),
+            },
+        }
+    }
+}
+
+impl TopDownTiming {
+    /// The settings as environment variables the node reads its configuration from.
+    pub fn to_env_vars(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "FM_IPC__TOPDOWN__CHAIN_HEAD_DELAY",
+                self.chain_head_delay.to_string(),
+            ),
+            (
+                "FM_IPC__TOPDOWN__PROPOSAL_DELAY",
+                self.proposal_delay.to_string(),
+            ),
+            (
+                "FM_IPC__TOPDOWN__POLLING_INTERVAL",
+                self.polling_interval.as_secs().to_string(),
+            ),
+            (
+                "FM_IPC__TOPDOWN__PARENT_FINALITY_RULE",
+                self.parent_finality_rule.to_string(),
+            ),
+            (
+                "FM_IPC__VOTE_INTERVAL",
+                self.vote_interval.as_secs().to_string(),
+            ),
+        ]
+    }
+}
@@ -1,5 +1,6 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::BTreeMap;
 use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -8,6 +9,7 @@ use anyhow::{anyhow, Context, Result};
 use async_stm::{atomically, atomically_or_err};
 use async_trait::async_trait;
 use cid::Cid;
+use ethers_core::types::Bloom;
 use fendermint_abci::util::take_until_max_size;
 use fendermint_abci::{AbciResult, Application};
 use fendermint_storage::{
@@ -24,26 +26,43 @@ use fendermint_vm_interpreter::fvm::state::{
     FvmUpdatableParams,
 };
 use fendermint_vm_interpreter::fvm::store::ReadOnlyBlockstore;
-use fendermint_vm_interpreter::fvm::{FvmApplyRet, FvmGenesisOutput, PowerUpdates};
+use fendermint_vm_interpreter::fvm::{call_trace, FvmApplyRet, FvmGenesisOutput, PowerUpdates};
 use fendermint_vm_interpreter::signed::InvalidSignature;
 use fendermint_vm_interpreter::{
     CheckInterpreter, ExecInterpreter, GenesisInterpreter, ProposalInterpreter, QueryInterpreter,
 };
-use fendermint_vm_message::query::FvmQueryHeight;
+use fendermint_vm_message::bloom::{accrue_log, log_address, log_topics};
+use fendermint_vm_message::query::{
+    ActorStats, ActorStatsQuery, BlockVotes, CallTrace, FvmQueryHeight, HeightVotes, IndexedTx,
+    LogIndexQuery, LoggedValidatorChange, ParentFinalityLogEntry, TopDownAdminCommand,
+    TopDownAdminRequest, TopDownParams, TopDownStatus, TxIndexQuery, VoteTally,
+    ACTOR_STATS_QUERY_PATH, EXEC_TRACE_QUERY_PATH, LOG_INDEX_QUERY_PATH,
+    PARENT_FINALITY_LOG_QUERY_PATH, PENDING_RECEIPT_QUERY_PATH, TOPDOWN_ADMIN_QUERY_PATH,
+    TOPDOWN_PARAMS_QUERY_PATH, TX_INDEX_QUERY_PATH, VOTE_TALLY_QUERY_PATH,
+};
+use fendermint_vm_message::signed::DomainHash;
 use fendermint_vm_snapshot::{SnapshotClient, SnapshotError};
 use fvm::engine::MultiEngine;
 use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::address::Address;
 use fvm_shared::chainid::ChainID;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::version::NetworkVersion;
 use num_traits::Zero;
 use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 use tendermint::abci::request::CheckTxKind;
 use tendermint::abci::{request, response};
+use tendermint::crypto::sha256::Sha256;
 use tracing::instrument;
 
 use crate::events::{NewBlock, ProposalProcessed};
+use crate::pacing::ProposalPacing;
+use crate::receipts::PendingReceipts;
+use crate::store::{
+    ActorStatsDbStore, ExecTraceDbStore, LogIndexDbStore, TxIndexDbStore, TxIndexEntry,
+};
 use crate::AppExitCode;
 use crate::BlockHeight;
 use crate::{tmconv::*, VERSION};
@@ -52,8 +71,15 @@ use crate::{tmconv::*, VERSION};
 #[repr(u8)]
 pub enum AppStoreKey {
     State,
+    UpgradeCheckpoint,
 }
 
+/// The maximum number of transactions returned by a single transaction index query.
+const MAX_TX_INDEX_QUERY_LIMIT: u64 = 1000;
+
+/// The maximum number of blocks a single log index query can cover.
+const MAX_LOG_INDEX_QUERY_RANGE: u64 = 10_000;
+
 // TODO: What range should we use for our own error codes? Should we shift FVM errors?
 #[derive(Debug)]
 #[repr(u32)]
@@ -66,8 +92,48 @@ pub enum AppError {
     IllegalMessage = 53,
     /// The genesis block hasn't been initialized yet.
     NotInitialized = 54,
+    /// The admin query doesn't carry the admin token of the node.
+    Unauthorized = 55,
+    /// The state at the queried height has been pruned or doesn't exist yet.
+    StateNotAvailable = 56,
+    /// The query asks for more than the node is willing to serve.
+    QueryTooLarge = 57,
+    /// The query is for a feature which is disabled on this node.
+    Disabled = 58,
+}
+
+/// The reason the state at a queried height can't be served.
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateNotAvailable {
+    /// The height is past the latest state.
+    Future {
+        height: BlockHeight,
+        latest: BlockHeight,
+    },
+    /// The state at the height has been pruned from the history.
+    Pruned {
+        height: BlockHeight,
+        oldest: BlockHeight,
+    },
+}
+
+impl std::fmt::Display for StateNotAvailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Future { height, latest } => write!(
+                f,
+                "height {height} is in the future; the latest state is at height {latest}"
+            ),
+            Self::Pruned { height, oldest } => write!(
+                f,
+                "the state at height {height} has been pruned; the oldest state is at height {oldest}"
+            ),
+        }
+    }
 }
 
+impl std::error::Error for StateNotAvailable {}
+
 /// The application state record we keep a history of in the database.
 #[derive(Serialize, Deserialize)]
 pub struct AppState {
@@ -99,6 +165,19 @@ impl AppState {
     }
 }
 
+/// The committed state before the block which executed the last upgrade, kept so that
+/// the node can be rolled back to it if the migration turns out to be wrong.
+///
+/// Only upgrades scheduled at a fixed height get a checkpoint; the height of governed
+/// upgrades isn't known before their block is executed, so they can't be rolled back.
+#[derive(Serialize, Deserialize)]
+pub struct UpgradeCheckpoint {
+    /// Height of the block which executed the upgrade.
+    pub upgrade_height: BlockHeight,
+    /// The last committed state before that block.
+    pub state: AppState,
+}
+
 pub struct AppConfig<S: KVStore> {
     /// Namespace to store the current app state.
     pub app_namespace: S::Namespace,
@@ -114,6 +193,26 @@ pub struct AppConfig<S: KVStore> {
     pub custom_actors_bundle: PathBuf,
     /// Block height where we should gracefully stop the node
     pub halt_height: i64,
+    /// Block height where we should stop the node unless it has an upgrade scheduled there.
+    pub upgrade_halt_height: BlockHeight,
+    /// Chain ID and block height of the scheduled upgrades, to checkpoint the state before.
+    pub upgrade_heights: Vec<(ChainID, BlockHeight)>,
+    /// Token authorizing the top-down finality admin queries; they are disabled without one.
+    pub topdown_admin_token: Option<String>,
+    /// The top-down finality settings, to report them to operators.
+    pub topdown_params: TopDownParams,
+    /// Index of the executed transactions; disabled if `None`.
+    pub tx_index: Option<Arc<TxIndexDbStore>>,
+    /// Execution traces of the delivered transactions; tracing is disabled if `None`.
+    pub exec_traces: Option<Arc<ExecTraceDbStore>>,
+    /// Resources used by the actors in the delivered transactions; disabled if `None`.
+    pub actor_stats: Option<Arc<ActorStatsDbStore>>,
+    /// Index of the Ethereum logs emitted in each block; disabled if `None`.
+    pub log_index: Option<Arc<LogIndexDbStore>>,
+    /// Whether to keep the results of executing the transactions in the mempool.
+    pub pending_receipts: bool,
+    /// How full to make the proposed blocks depending on the pending load.
+    pub proposal_pacing: ProposalPacing,
 }
 
 /// Handle ABCI requests.
@@ -141,6 +240,36 @@ where
     custom_actors_bundle: PathBuf,
     /// Block height where we should gracefully stop the node
     halt_height: i64,
+    /// Block height where we should stop the node unless it has an upgrade scheduled there.
+    upgrade_halt_height: BlockHeight,
+    /// Chain ID and block height of the scheduled upgrades, to checkpoint the state before.
+    upgrade_heights: Vec<(ChainID, BlockHeight)>,
+    /// Token authorizing the top-down finality admin queries; they are disabled without one.
+    topdown_admin_token: Option<String>,
+    /// The top-down finality settings, to report them to operators.
+    topdown_params: TopDownParams,
+    /// Index of the executed transactions, if enabled.
+    tx_index: Option<Arc<TxIndexDbStore>>,
+    /// The transactions delivered in the current block, to index once it's committed;
+    /// `None` for the ones which could not be executed at all.
+    delivered_txs: Arc<std::sync::Mutex<Vec<Option<TxIndexEntry>>>>,
+    /// Execution traces of the delivered transactions, if enabled.
+    exec_traces: Option<Arc<ExecTraceDbStore>>,
+    /// The traces of the transactions delivered in the current block, to store once it's
+    /// committed, under their CometBFT hashes.
+    delivered_traces: Arc<std::sync::Mutex<Vec<(Vec<u8>, CallTrace)>>>,
+    /// Resources used by the actors in the delivered transactions, if enabled.
+    actor_stats: Option<Arc<ActorStatsDbStore>>,
+    /// The resources used by the actors in the current block, to store once it's committed.
+    delivered_usage: Arc<std::sync::Mutex<BTreeMap<Address, ActorStats>>>,
+    /// Index of the Ethereum logs emitted in each block, if enabled.
+    log_index: Option<Arc<LogIndexDbStore>>,
+    /// The bloom filter of the logs emitted in the current block, to index once it's committed.
+    delivered_bloom: Arc<std::sync::Mutex<Bloom>>,
+    /// The results of executing the transactions checked since the last commit, if enabled.
+    pending_receipts: Option<Arc<PendingReceipts>>,
+    /// How full to make the proposed blocks depending on the pending load.
+    proposal_pacing: ProposalPacing,
     /// Namespace to store app state.
     namespace: S::Namespace,
     /// Collection of past state parameters.
@@ -195,6 +324,20 @@ where
             builtin_actors_bundle: config.builtin_actors_bundle,
             custom_actors_bundle: config.custom_actors_bundle,
             halt_height: config.halt_height,
+            upgrade_halt_height: config.upgrade_halt_height,
+            upgrade_heights: config.upgrade_heights,
+            topdown_admin_token: config.topdown_admin_token,
+            topdown_params: config.topdown_params,
+            tx_index: config.tx_index,
+            delivered_txs: Default::default(),
+            exec_traces: config.exec_traces,
+            delivered_traces: Default::default(),
+            actor_stats: config.actor_stats,
+            delivered_usage: Default::default(),
+            log_index: config.log_index,
+            delivered_bloom: Default::default(),
+            pending_receipts: config.pending_receipts.then(Default::default),
+            proposal_pacing: config.proposal_pacing,
             namespace: config.app_namespace,
             state_hist: KVCollection::new(config.state_hist_namespace),
             state_hist_size: config.state_hist_size,
@@ -243,6 +386,13 @@ where
                     chain_id: 0,
                     power_scale: 0,
                     app_version: 0,
+                    exec_limits: None,
+                    executed_upgrades: Default::default(),
+                    upgrades_in_progress: Default::default(),
+                    pending_topdown_msgs: Default::default(),
+                    pending_bottomup_msgs: Default::default(),
+                    gas_params: None,
+                    parent_checkpoint_height: None,
                 },
             };
             self.set_committed_state(state)?;
@@ -355,29 +505,12 @@ where
 
     /// Look up a past state at a particular height Tendermint Core is looking for.
     ///
-    /// A height of zero means we are looking for the latest state.
-    /// The genesis block state is saved under height 1.
-    /// Under height 0 we saved the empty state, which we must not query,
-    /// because it doesn't contain any initialized state for the actors.
-    ///
-    /// Returns the state params and the height of the block which committed it.
+    /// See [state_params_at_height].
     fn state_params_at_height(
         &self,
         height: FvmQueryHeight,
     ) -> Result<(FvmStateParams, BlockHeight)> {
-        if let FvmQueryHeight::Height(h) = height {
-            let tx = self.db.read();
-            let sh = self
-                .state_hist
-                .get(&tx, &h)
-                .context("error looking up history")?;
-
-            if let Some(p) = sh {
-                return Ok((p, h));
-            }
-        }
-        let state = self.committed_state()?;
-        Ok((state.state_params, state.block_height))
+        state_params_at_height(&self.db, &self.state_hist, self.committed_state()?, height)
     }
 
     /// Check whether the state has been initialized by genesis.
@@ -387,6 +520,654 @@ where
         // It's really the empty state tree that would be the best indicator.
         !(height == 0 && params.timestamp.0 == 0 && params.network_version == NetworkVersion::V0)
     }
+
+    /// Answer a query about the parent finality votes collected by this node.
+    async fn query_vote_tally(&self) -> anyhow::Result<response::Query> {
+        let votes = &self.chain_env.parent_finality_votes;
+
+        let (last_finalized_height, latest_height, total_power, quorum_threshold, heights) =
+            atomically(|| {
+                Ok((
+                    votes.last_finalized_height()?,
+                    votes.latest_height()?,
+                    votes.total_power()?,
+                    votes.quorum_threshold()?,
+                    votes.votes()?,
+                ))
+            })
+            .await;
+
+        let heights = heights
+            .into_iter()
+            .map(|h| HeightVotes {
+                height: h.height,
+                block_hash: h.block_hash.map(hex::encode),
+                votes: h
+                    .votes
+                    .into_iter()
+                    .map(|(block_hash, voters)| BlockVotes {
+                        block_hash: hex::encode(block_hash),
+                        power: voters.iter().map(|(_, w)| w).sum(),
+                        validators: voters
+                            .into_iter()
+                            .map(|(vk, _)| {
+                                let pk = libp2p::identity::PublicKey::from(vk);
+                                match pk.clone().try_into_secp256k1() {
+                                    Ok(pk) => hex::encode(pk.to_bytes_uncompressed()),
+                                    Err(_) => hex::encode(pk.encode_protobuf()),
+                                }
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let tally = VoteTally {
+            last_finalized_height,
+            latest_height,
+            total_power,
+            quorum_threshold,
+            heights,
+        };
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&tally)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Execute a top-down finality admin command, if the request carries the admin token.
+    ///
+    /// Pausing only affects what this node proposes, not the ledger, so it isn't a transaction.
+    async fn query_topdown_admin(&self, data: &[u8]) -> anyhow::Result<response::Query> {
+        let request: TopDownAdminRequest = match fvm_ipld_encoding::from_slice(data) {
+            Ok(request) => request,
+            Err(e) => return Ok(invalid_query(AppError::InvalidEncoding, e.to_string())),
+        };
+
+        match self.topdown_admin_token {
+            Some(ref token) if bool::from(token.as_bytes().ct_eq(request.token.as_bytes())) => {}
+            Some(_) => {
+                return Ok(invalid_query(
+                    AppError::Unauthorized,
+                    "invalid admin token".to_owned(),
+                ))
+            }
+            None => {
+                return Ok(invalid_query(
+                    AppError::Unauthorized,
+                    "top-down admin queries are disabled on this node".to_owned(),
+                ))
+            }
+        }
+
+        let provider = &self.chain_env.parent_finality_provider;
+
+        let (paused, last_committed_height, latest_height) = atomically(|| {
+            match request.command {
+                TopDownAdminCommand::Status => {}
+                TopDownAdminCommand::Pause => provider.pause()?,
+                TopDownAdminCommand::Resume => provider.resume()?,
+            }
+            Ok((
+                provider.is_paused()?,
+                provider.last_committed_finality()?.map(|f| f.height),
+                provider.latest_height()?,
+            ))
+        })
+        .await;
+
+        match request.command {
+            TopDownAdminCommand::Status => {}
+            TopDownAdminCommand::Pause => tracing::warn!("parent finality proposals paused"),
+            TopDownAdminCommand::Resume => tracing::info!("parent finality proposals resumed"),
+        }
+
+        let status = TopDownStatus {
+            enabled: provider.is_enabled(),
+            paused,
+            last_committed_height,
+            latest_height,
+        };
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&status)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Look up the parent finality committed for a parent height in the log of the node.
+    fn query_parent_finality_log(&self, data: &[u8]) -> anyhow::Result<response::Query> {
+        let height: BlockHeight = match fvm_ipld_encoding::from_slice(data) {
+            Ok(height) => height,
+            Err(e) => return Ok(invalid_query(AppError::InvalidEncoding, e.to_string())),
+        };
+
+        let Some(ref log) = self.chain_env.parent_finality_log else {
+            return Ok(invalid_query(
+                AppError::Disabled,
+                "the parent finality log is disabled on this node".to_owned(),
+            ));
+        };
+
+        let entry = match log.get(height)? {
+            None => None,
+            Some(c) => Some(ParentFinalityLogEntry {
+                block_height: c.block_height,
+                parent_height: c.finality.height,
+                parent_block_hash: hex::encode(&c.finality.block_hash),
+                executed_from: c.executed_from,
+                executed_to: c.executed_to,
+                validator_changes: c
+                    .validator_changes
+                    .iter()
+                    .map(|v| LoggedValidatorChange {
+                        configuration_number: v.configuration_number,
+                        op: v.change.op.clone().into(),
+                        payload: hex::encode(&v.change.payload),
+                        validator: v.change.validator.to_string(),
+                    })
+                    .collect(),
+                top_down_msgs: c.top_down_msgs,
+            }),
+        };
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&entry)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Look up transactions in the transaction index of the node.
+    fn query_tx_index(&self, data: &[u8]) -> anyhow::Result<response::Query> {
+        let query: TxIndexQuery = match fvm_ipld_encoding::from_slice(data) {
+            Ok(query) => query,
+            Err(e) => return Ok(invalid_query(AppError::InvalidEncoding, e.to_string())),
+        };
+
+        let Some(ref tx_index) = self.tx_index else {
+            return Ok(invalid_query(
+                AppError::Disabled,
+                "the transaction index is disabled on this node".to_owned(),
+            ));
+        };
+
+        let entries = match query {
+            TxIndexQuery::Hash(hash) => tx_index.get(&hash)?.into_iter().collect(),
+            TxIndexQuery::List {
+                filter,
+                skip,
+                limit,
+            } => tx_index.list(&filter, skip, limit.min(MAX_TX_INDEX_QUERY_LIMIT))?,
+        };
+
+        let txs = entries
+            .into_iter()
+            .map(|e| IndexedTx {
+                hash: hex::encode(&e.hash),
+                domain_hash: e.domain_hash.map(hex::encode),
+                height: e.height,
+                index: e.index,
+                from: e.from,
+                to: e.to,
+                method_num: e.method_num,
+                exit_code: e.exit_code,
+                gas_used: e.gas_used,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&txs)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Look up the execution trace of a transaction recorded by the node.
+    fn query_exec_trace(&self, hash: &[u8]) -> anyhow::Result<response::Query> {
+        let Some(ref exec_traces) = self.exec_traces else {
+            return Ok(invalid_query(
+                AppError::Disabled,
+                "execution tracing is disabled on this node".to_owned(),
+            ));
+        };
+
+        let mut trace = exec_traces.get(hash)?;
+        if trace.is_none() {
+            // The index can tell the CometBFT hash of the transaction from its domain specific one.
+            if let Some(ref tx_index) = self.tx_index {
+                if let Some(entry) = tx_index.get(hash)? {
+                    trace = exec_traces.get(&entry.hash)?;
+                }
+            }
+        }
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&trace)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Add up the resources used by the actors in the recent blocks.
+    fn query_actor_stats(&self, data: &[u8]) -> anyhow::Result<response::Query> {
+        let query: ActorStatsQuery = match fvm_ipld_encoding::from_slice(data) {
+            Ok(query) => query,
+            Err(e) => return Ok(invalid_query(AppError::InvalidEncoding, e.to_string())),
+        };
+
+        let Some(ref actor_stats) = self.actor_stats else {
+            return Ok(invalid_query(
+                AppError::Disabled,
+                "actor statistics are disabled on this node".to_owned(),
+            ));
+        };
+
+        let height = self.committed_state()?.block_height;
+        let report = actor_stats.report(height, query.blocks, query.limit)?;
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&report)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Find the blocks which may contain logs matching a filter.
+    fn query_log_index(&self, data: &[u8]) -> anyhow::Result<response::Query> {
+        let query: LogIndexQuery = match fvm_ipld_encoding::from_slice(data) {
+            Ok(query) => query,
+            Err(e) => return Ok(invalid_query(AppError::InvalidEncoding, e.to_string())),
+        };
+
+        let Some(ref log_index) = self.log_index else {
+            return Ok(invalid_query(
+                AppError::Disabled,
+                "the log index is disabled on this node".to_owned(),
+            ));
+        };
+
+        if query.to_height.saturating_sub(query.from_height) >= MAX_LOG_INDEX_QUERY_RANGE {
+            return Ok(invalid_query(
+                AppError::QueryTooLarge,
+                format!(
+                    "block range {}..={} too large, at most {MAX_LOG_INDEX_QUERY_RANGE} blocks allowed",
+                    query.from_height, query.to_height
+                ),
+            ));
+        }
+
+        let heights = log_index.matching_heights(&query)?;
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&heights)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Look up the result of executing a transaction waiting in the mempool.
+    fn query_pending_receipt(&self, hash: &[u8]) -> anyhow::Result<response::Query> {
+        let Some(ref pending_receipts) = self.pending_receipts else {
+            return Ok(invalid_query(
+                AppError::Disabled,
+                "pending receipts are disabled on this node".to_owned(),
+            ));
+        };
+
+        let receipt = pending_receipts.get(hash);
+
+        Ok(response::Query {
+            value: fvm_ipld_encoding::to_vec(&receipt)?.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Add the resources used by the actors in a delivered transaction to the ones of the block,
+    /// to store once the block is committed.
+    fn record_actor_usage(
+        &self,
+        block_height: BlockHeight,
+        usage: Option<anyhow::Result<BTreeMap<Address, ActorStats>>>,
+    ) {
+        match usage {
+            None => {}
+            Some(Err(e)) => {
+                tracing::error!(
+                    error = e.to_string(),
+                    block_height,
+                    "failed to collect actor statistics"
+                );
+            }
+            Some(Ok(usage)) => {
+                let mut block_usage = self.delivered_usage.lock().unwrap();
+                for (actor, stats) in usage {
+                    match block_usage.get_mut(&actor) {
+                        Some(total) => total.add(&stats),
+                        None => {
+                            block_usage.insert(actor, stats);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add the logs emitted by a delivered transaction to the bloom filter of the block,
+    /// to index once the block is committed.
+    fn record_delivered_logs(&self, ret: &FvmApplyRet) {
+        if self.log_index.is_none() || ret.apply_ret.events.is_empty() {
+            return;
+        }
+        let mut bloom = self.delivered_bloom.lock().unwrap();
+        for event in ret.apply_ret.events.iter() {
+            let address = log_address(event.emitter, ret.emitters.get(&event.emitter));
+            accrue_log(&mut bloom, &address, &log_topics(event));
+        }
+    }
+
+    /// Remember a delivered transaction, to index it and store its execution trace
+    /// once the block is committed.
+    fn record_delivered_tx(
+        &self,
+        tx_hash: Option<[u8; 32]>,
+        block_height: BlockHeight,
+        domain_hash: Option<&DomainHash>,
+        ret: Option<&FvmApplyRet>,
+    ) {
+        let Some(hash) = tx_hash else {
+            return;
+        };
+        if self.exec_traces.is_some() {
+            if let Some(trace) = ret.and_then(|ret| call_trace(&ret.apply_ret.exec_trace)) {
+                self.delivered_traces
+                    .lock()
+                    .unwrap()
+                    .push((hash.to_vec(), trace));
+            }
+        }
+        if self.tx_index.is_none() {
+            return;
+        }
+        let mut txs = self.delivered_txs.lock().unwrap();
+        let entry = ret.map(|ret| TxIndexEntry {
+            hash: hash.to_vec(),
+            domain_hash: domain_hash.map(|h| match h {
+                DomainHash::Eth(h) => h.to_vec(),
+            }),
+            height: block_height,
+            index: txs.len() as u64,
+            from: ret.from,
+            to: ret.to,
+            method_num: ret.method_num,
+            exit_code: ret.apply_ret.msg_receipt.exit_code.value(),
+            gas_used: ret.apply_ret.msg_receipt.gas_used,
+        });
+        txs.push(entry);
+    }
+}
+
+impl<DB, SS, S, I> App<DB, SS, S, I>
+where
+    S: KVStore + Encode<AppStoreKey> + Codec<UpgradeCheckpoint>,
+    DB: KVWritable<S> + KVReadable<S> + 'static + Clone,
+    SS: Blockstore + 'static + Clone,
+{
+    /// Save the committed state before executing a block with an upgrade in it.
+    fn set_upgrade_checkpoint(&self, upgrade_height: BlockHeight, state: AppState) -> Result<()> {
+        let checkpoint = UpgradeCheckpoint {
+            upgrade_height,
+            state,
+        };
+        self.db
+            .with_write(|tx| {
+                tx.put(
+                    &self.namespace,
+                    &AppStoreKey::UpgradeCheckpoint,
+                    &checkpoint,
+                )
+            })
+            .context("failed to save upgrade checkpoint")
+    }
+}
+
+/// What to do at the upgrade halt height.
+#[derive(Debug, PartialEq, Eq)]
+enum UpgradeHalt {
+    /// The node has the upgrade scheduled there, so it can carry on.
+    Continue,
+    /// The node doesn't know about the upgrade; a newer release is needed.
+    Stop,
+}
+
+/// Check whether the block is at the upgrade halt height, and if so, whether the node
+/// has an upgrade scheduled there for the chain; `None` if it isn't the halt height.
+fn upgrade_halt(
+    upgrade_halt_height: BlockHeight,
+    upgrade_heights: &[(ChainID, BlockHeight)],
+    chain_id: ChainID,
+    block_height: BlockHeight,
+) -> Option<UpgradeHalt> {
+    if upgrade_halt_height == 0 || block_height != upgrade_halt_height {
+        return None;
+    }
+    if upgrade_heights.contains(&(chain_id, upgrade_halt_height)) {
+        Some(UpgradeHalt::Continue)
+    } else {
+        Some(UpgradeHalt::Stop)
+    }
+}
+
+/// Roll the committed state back to the checkpoint saved before the last upgrade,
+/// removing the state history written since, so the upgrade block can be executed again.
+///
+/// The state itself is never deleted from the blockstore, so the checkpoint only needs
+/// to remember its parameters.
+pub fn rollback_upgrade<DB, S>(
+    db: &DB,
+    app_namespace: &S::Namespace,
+    state_hist_namespace: S::Namespace,
+) -> Result<UpgradeCheckpoint>
+where
+    S: KVStore
+        + Codec<AppState>
+        + Encode<AppStoreKey>
+        + Encode<BlockHeight>
+        + Codec<FvmStateParams>
+        + Codec<UpgradeCheckpoint>,
+    DB: KVWritable<S> + KVReadable<S>,
+{
+    let (current, checkpoint) = {
+        let tx = db.read();
+        let current: Option<AppState> = tx.get(app_namespace, &AppStoreKey::State)?;
+        let checkpoint: Option<UpgradeCheckpoint> =
+            tx.get(app_namespace, &AppStoreKey::UpgradeCheckpoint)?;
+        (current, checkpoint)
+    };
+    let current = current.ok_or_else(|| anyhow!("app state not found"))?;
+    let mut checkpoint = checkpoint.ok_or_else(|| anyhow!("no upgrade checkpoint found"))?;
+
+    if current.block_height <= checkpoint.state.block_height {
+        return Err(anyhow!(
+            "the state at height {} is not after the checkpoint at height {}",
+            current.block_height,
+            checkpoint.state.block_height
+        ));
+    }
+
+    let state_hist = KVCollection::<S, BlockHeight, FvmStateParams>::new(state_hist_namespace);
+    let state_height = checkpoint.state.state_height();
+
+    // History may have been pruned since the checkpoint was taken.
+    checkpoint.state.oldest_state_height = current.oldest_state_height.min(state_height);
+
+    db.with_write(|tx| {
+        for height in state_height + 1..=current.state_height() {
+            state_hist.delete(tx, &height)?;
+        }
+        state_hist.put(tx, &state_height, &checkpoint.state.state_params)?;
+        tx.put(app_namespace, &AppStoreKey::State, &checkpoint.state)
+    })
+    .context("rollback failed")?;
+
+    Ok(checkpoint)
+}
+
+/// Look up a past state at a particular height Tendermint Core is looking for.
+///
+/// A height of zero means we are looking for the latest state.
+/// The genesis block state is saved under height 1.
+/// Under height 0 we saved the empty state, which we must not query,
+/// because it doesn't contain any initialized state for the actors.
+///
+/// Returns the state params and the height of the block which committed it, or a
+/// [StateNotAvailable] error if the state at a specific height is not retained.
+fn state_params_at_height<DB, S>(
+    db: &DB,
+    state_hist: &KVCollection<S, BlockHeight, FvmStateParams>,
+    state: AppState,
+    height: FvmQueryHeight,
+) -> Result<(FvmStateParams, BlockHeight)>
+where
+    S: KVStore + Encode<BlockHeight> + Codec<FvmStateParams>,
+    DB: KVReadable<S>,
+{
+    let FvmQueryHeight::Height(h) = height else {
+        return Ok((state.state_params, state.block_height));
+    };
+
+    let tx = db.read();
+    if let Some(p) = state_hist
+        .get(&tx, &h)
+        .context("error looking up history")?
+    {
+        // The state is saved under the height following the block which committed it.
+        return Ok((p, h.saturating_sub(1)));
+    }
+
+    // Rather than answering from a different state than what was asked for,
+    // tell the client which heights it can query.
+    let e = if h > state.state_height() {
+        StateNotAvailable::Future {
+            height: h,
+            latest: state.state_height(),
+        }
+    } else {
+        StateNotAvailable::Pruned {
+            height: h,
+            oldest: state.oldest_state_height,
+        }
+    };
+    Err(e.into())
+}
+
+/// The state parameters after the block at the given height was committed, if they are still
+/// in the state history; the node must not be running.
+pub fn historical_state_params<DB, S>(
+    db: &DB,
+    state_hist_namespace: S::Namespace,
+    height: BlockHeight,
+) -> Result<Option<FvmStateParams>>
+where
+    S: KVStore + Encode<BlockHeight> + Codec<FvmStateParams>,
+    DB: KVReadable<S>,
+{
+    let state_hist = KVCollection::<S, BlockHeight, FvmStateParams>::new(state_hist_namespace);
+    let tx = db.read();
+    state_hist
+        .get(&tx, &height)
+        .context("error looking up history")
+}
+
+/// The height of a committed block and the state parameters after it, which are either the last
+/// committed ones or still in the state history; the last committed block if `None`.
+/// The node must not be running.
+pub fn committed_state_params<DB, S>(
+    db: &DB,
+    app_namespace: &S::Namespace,
+    state_hist_namespace: S::Namespace,
+    block_height: Option<BlockHeight>,
+) -> Result<(BlockHeight, FvmStateParams)>
+where
+    S: KVStore
+        + Codec<AppState>
+        + Encode<AppStoreKey>
+        + Encode<BlockHeight>
+        + Codec<FvmStateParams>,
+    DB: KVReadable<S>,
+{
+    let state: Option<AppState> = db.read().get(app_namespace, &AppStoreKey::State)?;
+    let state = state.ok_or_else(|| anyhow!("app state not found"))?;
+
+    let (block_height, state_params) = match block_height {
+        None => (state.block_height, state.state_params),
+        Some(h) if h == state.block_height => (h, state.state_params),
+        Some(h) if h > state.block_height => {
+            return Err(anyhow!(
+                "block {h} hasn't been committed yet; the last one is {}",
+                state.block_height
+            ))
+        }
+        Some(h) => {
+            // The state after a block is stored under the next height.
+            let params = historical_state_params::<DB, S>(db, state_hist_namespace, h + 1)?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "the state after block {h} is not in the history anymore; the oldest is after block {}",
+                        state.oldest_state_height.saturating_sub(1)
+                    )
+                })?;
+            (h, params)
+        }
+    };
+
+    if state_params.timestamp.0 == 0 && state_params.network_version == NetworkVersion::V0 {
+        return Err(anyhow!("the app hasn't been initialized yet"));
+    }
+
+    Ok((block_height, state_params))
+}
+
+/// Make the state of an imported snapshot the committed state, the same way as state sync does,
+/// so the node carries on with the blocks after it. The node must not be running.
+pub fn restore_snapshot_state<DB, S>(
+    db: &DB,
+    app_namespace: &S::Namespace,
+    state_hist_namespace: S::Namespace,
+    block_height: BlockHeight,
+    state_params: FvmStateParams,
+) -> Result<()>
+where
+    S: KVStore
+        + Codec<AppState>
+        + Encode<AppStoreKey>
+        + Encode<BlockHeight>
+        + Codec<FvmStateParams>,
+    DB: KVWritable<S> + KVReadable<S>,
+{
+    let current: Option<AppState> = db.read().get(app_namespace, &AppStoreKey::State)?;
+
+    if let Some(ref current) = current {
+        if current.block_height >= block_height && current.block_height > 0 {
+            return Err(anyhow!(
+                "the node is already at block {}, not before the snapshot at block {block_height}",
+                current.block_height
+            ));
+        }
+    }
+
+    let state = AppState {
+        block_height,
+        // The history before the snapshot is not available, unless the node already had it.
+        oldest_state_height: current.map_or(block_height + 1, |c| c.oldest_state_height),
+        state_params,
+    };
+    let state_height = state.state_height();
+
+    let state_hist = KVCollection::<S, BlockHeight, FvmStateParams>::new(state_hist_namespace);
+
+    db.with_write(|tx| {
+        state_hist.put(tx, &state_height, &state.state_params)?;
+        tx.put(app_namespace, &AppStoreKey::State, &state)
+    })
+    .context("failed to restore the snapshot state")
 }
 
 // NOTE: The `Application` interface doesn't allow failures at the moment. The protobuf
@@ -401,7 +1182,8 @@ where
         + Codec<AppState>
         + Encode<AppStoreKey>
         + Encode<BlockHeight>
-        + Codec<FvmStateParams>,
+        + Codec<FvmStateParams>
+        + Codec<UpgradeCheckpoint>,
     S::Namespace: Sync + Send,
     DB: KVWritable<S> + KVReadable<S> + Clone + Send + Sync + 'static,
     SS: Blockstore + Clone + Send + Sync + 'static,
@@ -511,6 +1293,13 @@ where
                 chain_id: out.chain_id.into(),
                 power_scale: out.power_scale,
                 app_version: 0,
+                exec_limits: out.exec_limits,
+                executed_upgrades: Default::default(),
+                upgrades_in_progress: Default::default(),
+                pending_topdown_msgs: Default::default(),
+                pending_bottomup_msgs: Default::default(),
+                gas_params: None,
+                parent_checkpoint_height: None,
             },
         };
 
@@ -537,9 +1326,48 @@ where
     /// Query the application for data at the current or past height.
     #[instrument(skip(self))]
     async fn query(&self, request: request::Query) -> AbciResult<response::Query> {
+        if request.path == VOTE_TALLY_QUERY_PATH {
+            return Ok(self.query_vote_tally().await?);
+        }
+        if request.path == TOPDOWN_ADMIN_QUERY_PATH {
+            return Ok(self.query_topdown_admin(&request.data).await?);
+        }
+        if request.path == TOPDOWN_PARAMS_QUERY_PATH {
+            return Ok(response::Query {
+                value: fvm_ipld_encoding::to_vec(&self.topdown_params)
+                    .context("failed to encode top-down params")?
+                    .into(),
+                ..Default::default()
+            });
+        }
+        if request.path == PARENT_FINALITY_LOG_QUERY_PATH {
+            return Ok(self.query_parent_finality_log(&request.data)?);
+        }
+        if request.path == TX_INDEX_QUERY_PATH {
+            return Ok(self.query_tx_index(&request.data)?);
+        }
+        if request.path == EXEC_TRACE_QUERY_PATH {
+            return Ok(self.query_exec_trace(&request.data)?);
+        }
+        if request.path == PENDING_RECEIPT_QUERY_PATH {
+            return Ok(self.query_pending_receipt(&request.data)?);
+        }
+        if request.path == ACTOR_STATS_QUERY_PATH {
+            return Ok(self.query_actor_stats(&request.data)?);
+        }
+        if request.path == LOG_INDEX_QUERY_PATH {
+            return Ok(self.query_log_index(&request.data)?);
+        }
+
         let db = self.state_store_clone();
         let height = FvmQueryHeight::from(request.height.value());
-        let (state_params, block_height) = self.state_params_at_height(height)?;
+        let (state_params, block_height) = match self.state_params_at_height(height) {
+            Ok(found) => found,
+            Err(e) => match e.downcast::<StateNotAvailable>() {
+                Ok(e) => return Ok(invalid_query(AppError::StateNotAvailable, e.to_string())),
+                Err(e) => return Err(e.into()),
+            },
+        };
 
         tracing::debug!(
             query_height = request.height.value(),
@@ -606,6 +1434,8 @@ where
             }
         };
 
+        let block_height = state.block_height() as BlockHeight;
+
         let (state, result) = self
             .interpreter
             .check(
@@ -624,7 +1454,12 @@ where
             Ok(result) => match result {
                 Err(IllegalMessage) => invalid_check_tx(AppError::IllegalMessage, "".to_owned()),
                 Ok(Err(InvalidSignature(d))) => invalid_check_tx(AppError::InvalidSignature, d),
-                Ok(Ok(ret)) => to_check_tx(ret),
+                Ok(Ok(ret)) => {
+                    if let Some(ref pending_receipts) = self.pending_receipts {
+                        pending_receipts.record(&request.tx, block_height, &ret);
+                    }
+                    to_check_tx(ret)
+                }
             },
         };
 
@@ -641,7 +1476,28 @@ where
             time = request.time.to_string(),
             "prepare proposal"
         );
+        let max_tx_bytes: usize = request.max_tx_bytes.try_into().unwrap();
+
+        // Leave room in quiet times, fill the block up under load.
+        let mempool_txs = request.txs.len();
+        let topdown_msgs = self
+            .committed_state()?
+            .state_params
+            .pending_topdown_msgs
+            .len();
+        let mempool_tx_bytes =
+            self.proposal_pacing
+                .mempool_tx_bytes(max_tx_bytes, mempool_txs, topdown_msgs);
+
         let txs = request.txs.into_iter().map(|tx| tx.to_vec()).collect();
+        let txs = take_until_max_size(txs, mempool_tx_bytes);
+
+        tracing::debug!(
+            mempool_txs,
+            topdown_msgs,
+            proposed_txs = txs.len(),
+            "paced proposal"
+        );
 
         let txs = self
             .interpreter
@@ -650,7 +1506,7 @@ where
             .context("failed to prepare proposal")?;
 
         let txs = txs.into_iter().map(bytes::Bytes::from).collect();
-        let txs = take_until_max_size(txs, request.max_tx_bytes.try_into().unwrap());
+        let txs = take_until_max_size(txs, max_tx_bytes);
 
         Ok(response::PrepareProposal { txs })
     }
@@ -709,6 +1565,41 @@ where
         let state = self.committed_state()?;
         let mut state_params = state.state_params.clone();
 
+        match upgrade_halt(
+            self.upgrade_halt_height,
+            &self.upgrade_heights,
+            state.chain_id(),
+            block_height as BlockHeight,
+        ) {
+            Some(UpgradeHalt::Continue) => {
+                tracing::info!(
+                    height = block_height,
+                    "Continuing past the upgrade halt height with the scheduled upgrade"
+                );
+            }
+            Some(UpgradeHalt::Stop) => {
+                // Carrying on without the upgrade would fork us off the validators who have it.
+                tracing::error!(
+                    height = block_height,
+                    "Stopping node due to reaching the upgrade halt height without an upgrade scheduled there; install the release containing the upgrade"
+                );
+                std::process::exit(AppExitCode::Halt as i32);
+            }
+            None => {}
+        }
+
+        if self
+            .upgrade_heights
+            .contains(&(state.chain_id(), block_height as BlockHeight))
+        {
+            tracing::info!(
+                height = block_height,
+                state_root = state.state_root().to_string(),
+                "saving checkpoint before upgrade"
+            );
+            self.set_upgrade_checkpoint(block_height as BlockHeight, state)?;
+        }
+
         tracing::debug!(
             height = block_height,
             timestamp = request.header.time.unix_timestamp(),
@@ -719,9 +1610,15 @@ where
 
         state_params.timestamp = to_timestamp(request.header.time);
 
-        let state = FvmExecState::new(db, self.multi_engine.as_ref(), block_height, state_params)
-            .context("error creating new state")?
-            .with_block_hash(block_hash);
+        let state = FvmExecState::new_with_tracing(
+            db,
+            self.multi_engine.as_ref(),
+            block_height,
+            state_params,
+            self.exec_traces.is_some() || self.actor_stats.is_some(),
+        )
+        .context("error creating new state")?
+        .with_block_hash(block_hash);
 
         tracing::debug!("initialized exec state");
 
@@ -737,26 +1634,55 @@ where
 
     /// Apply a transaction to the application's state.
     async fn deliver_tx(&self, request: request::DeliverTx) -> AbciResult<response::DeliverTx> {
+        let tx_hash = (self.tx_index.is_some() || self.exec_traces.is_some())
+            .then(|| tendermint::crypto::default::Sha256::digest(&request.tx));
         let msg = request.tx.to_vec();
-        let (result, block_hash) = self
+        let (result, block_hash, block_height, usage) = self
             .modify_exec_state(|s| async {
                 let ((env, state), res) = self.interpreter.deliver(s, msg).await?;
                 let block_hash = state.block_hash();
-                Ok(((env, state), (res, block_hash)))
+                let block_height = state.block_height() as BlockHeight;
+                let usage = match res {
+                    _ if self.actor_stats.is_none() => None,
+                    Ok(ChainMessageApplyRet::Signed(Ok(ref ret))) => {
+                        Some(state.actor_usage(&ret.fvm.apply_ret))
+                    }
+                    Ok(ChainMessageApplyRet::Ipc(ref ret)) => {
+                        Some(state.actor_usage(&ret.apply_ret))
+                    }
+                    _ => None,
+                };
+                Ok(((env, state), (res, block_hash, block_height, usage)))
             })
             .await
             .context("deliver failed")?;
 
+        self.record_actor_usage(block_height, usage);
+
         let response = match result {
-            Err(e) => invalid_deliver_tx(AppError::InvalidEncoding, e.description),
+            Err(e) => {
+                self.record_delivered_tx(tx_hash, block_height, None, None);
+                invalid_deliver_tx(AppError::InvalidEncoding, e.description)
+            }
             Ok(ret) => match ret {
                 ChainMessageApplyRet::Signed(Err(InvalidSignature(d))) => {
+                    self.record_delivered_tx(tx_hash, block_height, None, None);
                     invalid_deliver_tx(AppError::InvalidSignature, d)
                 }
                 ChainMessageApplyRet::Signed(Ok(ret)) => {
+                    self.record_delivered_logs(&ret.fvm);
+                    self.record_delivered_tx(
+                        tx_hash,
+                        block_height,
+                        ret.domain_hash.as_ref(),
+                        Some(&ret.fvm),
+                    );
                     to_deliver_tx(ret.fvm, ret.domain_hash, block_hash)
                 }
-                ChainMessageApplyRet::Ipc(ret) => to_deliver_tx(ret, None, block_hash),
+                ChainMessageApplyRet::Ipc(ret) => {
+                    self.record_delivered_tx(tx_hash, block_height, None, Some(&ret));
+                    to_deliver_tx(ret, None, block_hash)
+                }
             },
         };
 
@@ -802,6 +1728,12 @@ where
                 base_fee,
                 circ_supply,
                 power_scale,
+                executed_upgrades,
+                upgrades_in_progress,
+                pending_topdown_msgs,
+                pending_bottomup_msgs,
+                gas_params,
+                parent_checkpoint_height,
             },
             _,
         ) = exec_state.commit().context("failed to commit FVM")?;
@@ -811,6 +1743,12 @@ where
         state.state_params.base_fee = base_fee;
         state.state_params.circ_supply = circ_supply;
         state.state_params.power_scale = power_scale;
+        state.state_params.executed_upgrades = executed_upgrades;
+        state.state_params.upgrades_in_progress = upgrades_in_progress;
+        state.state_params.pending_topdown_msgs = pending_topdown_msgs;
+        state.state_params.pending_bottomup_msgs = pending_bottomup_msgs;
+        state.state_params.gas_params = gas_params;
+        state.state_params.parent_checkpoint_height = parent_checkpoint_height;
 
         let app_hash = state.app_hash();
         let block_height = state.block_height;
@@ -866,12 +1804,64 @@ where
         // Commit app state to the datastore.
         self.set_committed_state(state)?;
 
+        // Index the transactions of the block now that it's committed; the index is not
+        // part of the consensus, so failing to update it shouldn't stop the node.
+        if let Some(ref tx_index) = self.tx_index {
+            let txs = std::mem::take(&mut *self.delivered_txs.lock().unwrap());
+            let entries = txs.into_iter().flatten().collect::<Vec<_>>();
+            if let Err(e) = tx_index.index(&entries) {
+                tracing::error!(
+                    error = e.to_string(),
+                    block_height,
+                    "failed to index transactions"
+                );
+            }
+        }
+
+        // Same with the execution traces.
+        if let Some(ref exec_traces) = self.exec_traces {
+            let traces = std::mem::take(&mut *self.delivered_traces.lock().unwrap());
+            if let Err(e) = exec_traces.put_block(block_height, traces) {
+                tracing::error!(
+                    error = e.to_string(),
+                    block_height,
+                    "failed to store execution traces"
+                );
+            }
+        }
+
+        // Same with the log index.
+        if let Some(ref log_index) = self.log_index {
+            let bloom = std::mem::take(&mut *self.delivered_bloom.lock().unwrap());
+            if let Err(e) = log_index.put_block(block_height, &bloom) {
+                tracing::error!(error = e.to_string(), block_height, "failed to index logs");
+            }
+        }
+
+        // Same with the actor statistics.
+        if let Some(ref actor_stats) = self.actor_stats {
+            let usage = std::mem::take(&mut *self.delivered_usage.lock().unwrap());
+            if let Err(e) = actor_stats.put_block(block_height, usage.into_values().collect()) {
+                tracing::error!(
+                    error = e.to_string(),
+                    block_height,
+                    "failed to store actor statistics"
+                );
+            }
+        }
+
         emit!(NewBlock { block_height });
 
         // Reset check state.
         let mut guard = self.check_state.lock().await;
         *guard = None;
 
+        // The pending transactions have to be executed again on the new state,
+        // which CometBFT does by rechecking the ones still in the mempool.
+        if let Some(ref pending_receipts) = self.pending_receipts {
+            pending_receipts.clear();
+        }
+
         Ok(response::Commit {
             data: app_hash.into(),
             retain_height: retain_height.try_into().expect("height is valid"),
@@ -1052,3 +2042,166 @@ where
         Ok(default)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fendermint_rocksdb::{RocksDb, RocksDbConfig};
+    use fendermint_storage::{KVCollection, KVRead, KVReadable, KVWritable, KVWrite};
+    use fendermint_vm_interpreter::fvm::state::FvmStateParams;
+    use fendermint_vm_message::query::FvmQueryHeight;
+    use quickcheck::Arbitrary;
+
+    use fvm_shared::chainid::ChainID;
+
+    use super::{
+        rollback_upgrade, state_params_at_height, upgrade_halt, AppState, AppStoreKey,
+        StateNotAvailable, UpgradeCheckpoint, UpgradeHalt,
+    };
+    use crate::{AppStore, BlockHeight};
+
+    #[test]
+    fn state_not_available() {
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "state_hist";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let state_hist = KVCollection::<AppStore, BlockHeight, FvmStateParams>::new(ns.to_string());
+
+        let mut g = quickcheck::Gen::new(5);
+        let params = FvmStateParams::arbitrary(&mut g);
+
+        // Blocks 9 and 10 are retained, saved under the heights following them.
+        KVWritable::<AppStore>::with_write(&db, |tx| {
+            state_hist.put(tx, &10, &params)?;
+            state_hist.put(tx, &11, &params)?;
+            Ok(())
+        })
+        .unwrap();
+
+        let state = || AppState {
+            block_height: 10,
+            oldest_state_height: 10,
+            state_params: params.clone(),
+        };
+        let lookup =
+            |h| state_params_at_height(&db, &state_hist, state(), FvmQueryHeight::Height(h));
+        let not_available = |h| {
+            lookup(h)
+                .expect_err("state should not be available")
+                .downcast::<StateNotAvailable>()
+                .expect("should be a typed error")
+        };
+
+        assert_eq!(lookup(10).unwrap(), (params.clone(), 9));
+        assert_eq!(
+            state_params_at_height(&db, &state_hist, state(), FvmQueryHeight::Committed).unwrap(),
+            (params.clone(), 10)
+        );
+        assert_eq!(
+            not_available(9),
+            StateNotAvailable::Pruned {
+                height: 9,
+                oldest: 10
+            }
+        );
+        assert_eq!(
+            not_available(12),
+            StateNotAvailable::Future {
+                height: 12,
+                latest: 11
+            }
+        );
+    }
+
+    #[test]
+    fn rollback_to_upgrade_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let (app_ns, hist_ns) = ("app".to_string(), "state_hist".to_string());
+        let db = RocksDb::open_cf(
+            dir.path(),
+            &RocksDbConfig::default(),
+            [app_ns.as_str(), hist_ns.as_str()].iter(),
+        )
+        .unwrap();
+        let state_hist =
+            KVCollection::<AppStore, BlockHeight, FvmStateParams>::new(hist_ns.clone());
+
+        let mut g = quickcheck::Gen::new(5);
+        let params: Vec<FvmStateParams> = (0..=10)
+            .map(|_| FvmStateParams::arbitrary(&mut g))
+            .collect();
+        let state = |block_height: BlockHeight| AppState {
+            block_height,
+            oldest_state_height: 2,
+            state_params: params[block_height as usize].clone(),
+        };
+
+        // Blocks 1 to 10 are committed, with the checkpoint taken before the upgrade at block 7.
+        KVWritable::<AppStore>::with_write(&db, |tx| {
+            for h in 1..=10 {
+                state_hist.put(tx, &state(h).state_height(), &params[h as usize])?;
+            }
+            tx.put(&app_ns, &AppStoreKey::State, &state(10))?;
+            tx.put(
+                &app_ns,
+                &AppStoreKey::UpgradeCheckpoint,
+                &UpgradeCheckpoint {
+                    upgrade_height: 7,
+                    state: state(6),
+                },
+            )
+        })
+        .unwrap();
+
+        let checkpoint = rollback_upgrade::<_, AppStore>(&db, &app_ns, hist_ns.clone()).unwrap();
+        assert_eq!(checkpoint.upgrade_height, 7);
+
+        {
+            let tx = KVReadable::<AppStore>::read(&db);
+            let current: AppState = tx.get(&app_ns, &AppStoreKey::State).unwrap().unwrap();
+            assert_eq!(current.block_height, 6);
+            assert_eq!(current.state_params, params[6]);
+            assert_eq!(current.oldest_state_height, 2);
+
+            // The history ends with the state before the upgrade block, to execute it again.
+            for h in 2..=7 {
+                assert_eq!(
+                    state_hist.get(&tx, &h).unwrap(),
+                    Some(params[h as usize - 1].clone())
+                );
+            }
+            for h in 8..=11 {
+                assert!(state_hist.get(&tx, &h).unwrap().is_none());
+            }
+        }
+
+        // The state is already at the checkpoint.
+        assert!(rollback_upgrade::<_, AppStore>(&db, &app_ns, hist_ns).is_err());
+    }
+
+    #[test]
+    fn halt_at_upgrade_height() {
+        let chain_id = ChainID::from(1);
+        let other_chain_id = ChainID::from(2);
+        let halt = |upgrade_halt_height, upgrade_heights: &[(ChainID, BlockHeight)], height| {
+            upgrade_halt(upgrade_halt_height, upgrade_heights, chain_id, height)
+        };
+
+        // Not configured.
+        assert_eq!(halt(0, &[], 100), None);
+        // Not the halt height.
+        assert_eq!(halt(100, &[], 99), None);
+        assert_eq!(halt(100, &[], 101), None);
+        // No upgrade scheduled at the halt height, or only for another chain.
+        assert_eq!(halt(100, &[], 100), Some(UpgradeHalt::Stop));
+        assert_eq!(halt(100, &[(chain_id, 99)], 100), Some(UpgradeHalt::Stop));
+        assert_eq!(
+            halt(100, &[(other_chain_id, 100)], 100),
+            Some(UpgradeHalt::Stop)
+        );
+        // The upgrade is scheduled there.
+        assert_eq!(
+            halt(100, &[(chain_id, 100)], 100),
+            Some(UpgradeHalt::Continue)
+        );
+    }
+}
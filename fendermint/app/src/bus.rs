@@ -0,0 +1,349 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Publishing the events emitted by the node to external systems, so downstream indexers
+//! can follow the chain without polling the RPC.
+//!
+//! Every event emitted with `emit!`, e.g. [crate::events::NewBlock] or
+//! [crate::events::NewBottomUpCheckpoint], is put on an in-process broadcast bus
+//! as a flat JSON object, from which each configured sink forwards it on its own.
+//! Delivery is at most once: a sink which fails or falls behind misses events.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{filter, layer, registry::LookupSpan, Layer};
+
+/// Number of events buffered for the sinks; a sink further behind than this misses events.
+const BUS_CAPACITY: usize = 10_000;
+/// How long the HTTP sinks wait to connect to their endpoint.
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the HTTP sinks wait for a response, so a hanging endpoint only delays its own sink.
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref BUS: broadcast::Sender<Arc<BusEvent>> = broadcast::channel(BUS_CAPACITY).0;
+}
+
+/// An event as published to the sinks.
+#[derive(Debug, Clone, Serialize)]
+pub struct BusEvent {
+    /// The name of the event type, e.g. `NewBlock`.
+    pub event: String,
+    /// Milliseconds since the Unix epoch when the event was emitted.
+    pub timestamp: u64,
+    /// The fields of the event type.
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+/// Receive the events emitted from now on.
+pub fn subscribe() -> broadcast::Receiver<Arc<BusEvent>> {
+    BUS.subscribe()
+}
+
+/// Create a layer that puts the emitted events on the bus, as long as anyone is subscribed.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber,
+    for<'a> S: LookupSpan<'a>,
+{
+    BusLayer::new().with_filter(filter::filter_fn(|md| md.name().starts_with("event::")))
+}
+
+struct BusLayer<S> {
+    _subscriber: PhantomData<S>,
+}
+
+impl<S> BusLayer<S> {
+    pub fn new() -> Self {
+        Self {
+            _subscriber: PhantomData,
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for BusLayer<S> {
+    fn on_event(&self, event: &Event<'_>, _ctx: layer::Context<'_, S>) {
+        if BUS.receiver_count() == 0 {
+            return;
+        }
+        let name = event.metadata().name();
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+
+        let event = BusEvent {
+            event: name.strip_prefix("event::").unwrap_or(name).to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            fields: visitor.fields,
+        };
+        // Only fails if the last sink has stopped in the meantime.
+        let _ = BUS.send(Arc::new(event));
+    }
+}
+
+/// Collect the fields of an event into a JSON object.
+#[derive(Default)]
+struct JsonVisitor {
+    fields: Map<String, Value>,
+}
+
+impl JsonVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        // The event name is already on the bus event.
+        if field.name() != "event" {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into())
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into())
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into())
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into())
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, format!("{value:?}").into())
+    }
+}
+
+/// An external system the events are forwarded to.
+#[async_trait]
+pub trait EventSink: Send + Sync + 'static {
+    /// Name of the sink in the logs.
+    fn name(&self) -> String;
+
+    async fn publish(&self, event: &BusEvent) -> anyhow::Result<()>;
+}
+
+/// Forward the events on the bus to a sink in the background.
+pub fn spawn_sink(sink: Box<dyn EventSink>) {
+    let mut rx = subscribe();
+    tracing::info!(sink = sink.name(), "publishing events");
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if let Err(e) = sink.publish(&event).await {
+                        tracing::warn!(
+                            sink = sink.name(),
+                            event = event.event,
+                            error = format!("{e:#}"),
+                            "failed to publish event"
+                        );
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!(sink = sink.name(), missed, "event sink fell behind");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// The client of the HTTP sinks, with timeouts.
+fn http_client() -> anyhow::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .build()
+        .context("failed to build event sink HTTP client")
+}
+
+/// Posts every event as JSON to a URL.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: reqwest::Url,
+}
+
+impl WebhookSink {
+    pub fn new(url: reqwest::Url) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            url,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url)
+    }
+
+    async fn publish(&self, event: &BusEvent) -> anyhow::Result<()> {
+        self.client
+            .post(self.url.clone())
+            .json(event)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Produces every event as a JSON record to a Kafka topic through a Kafka REST proxy.
+pub struct KafkaRestSink {
+    client: reqwest::Client,
+    url: reqwest::Url,
+}
+
+impl KafkaRestSink {
+    pub fn new(proxy_url: reqwest::Url, topic: &str) -> anyhow::Result<Self> {
+        let url = proxy_url
+            .join(&format!("topics/{topic}"))
+            .context("invalid Kafka topic URL")?;
+        Ok(Self {
+            client: http_client()?,
+            url,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaRestSink {
+    fn name(&self) -> String {
+        format!("kafka {}", self.url)
+    }
+
+    async fn publish(&self, event: &BusEvent) -> anyhow::Result<()> {
+        let records = serde_json::json!({ "records": [{ "value": event }] });
+        self.client
+            .post(self.url.clone())
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/vnd.kafka.json.v2+json",
+            )
+            .body(serde_json::to_vec(&records)?)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Publishes every event as JSON to a NATS subject, reconnecting when the connection breaks.
+pub struct NatsSink {
+    address: String,
+    subject: String,
+    conn: Mutex<Option<TcpStream>>,
+}
+
+impl NatsSink {
+    pub fn new(address: String, subject: String) -> anyhow::Result<Self> {
+        if subject.is_empty() || subject.contains(char::is_whitespace) {
+            return Err(anyhow!("invalid NATS subject: {subject:?}"));
+        }
+        Ok(Self {
+            address,
+            subject,
+            conn: Mutex::new(None),
+        })
+    }
+
+    async fn connect(&self) -> anyhow::Result<TcpStream> {
+        let mut stream = TcpStream::connect(&self.address)
+            .await
+            .with_context(|| format!("failed to connect to NATS at {}", self.address))?;
+        // The server greets with its `INFO`, which we don't need.
+        stream
+            .write_all(b"CONNECT {\"verbose\":false,\"pedantic\":false}\r\n")
+            .await?;
+        Ok(stream)
+    }
+
+    async fn send(&self, stream: &mut TcpStream, payload: &[u8]) -> anyhow::Result<()> {
+        // Answer the pings of the server, otherwise it drops the connection as stale.
+        let mut buf = [0u8; 4096];
+        loop {
+            match stream.try_read(&mut buf) {
+                Ok(0) => return Err(anyhow!("NATS connection closed")),
+                Ok(n) => {
+                    if buf[..n].windows(4).any(|w| w == b"PING") {
+                        stream.write_all(b"PONG\r\n").await?;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let mut msg = format!("PUB {} {}\r\n", self.subject, payload.len()).into_bytes();
+        msg.extend_from_slice(payload);
+        msg.extend_from_slice(b"\r\n");
+        stream.write_all(&msg).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    fn name(&self) -> String {
+        format!("nats {}/{}", self.address, self.subject)
+    }
+
+    async fn publish(&self, event: &BusEvent) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(event)?;
+        let mut conn = self.conn.lock().await;
+        // Try an existing connection first, and a new one if that's broken.
+        if let Some(stream) = conn.as_mut() {
+            if self.send(stream, &payload).await.is_ok() {
+                return Ok(());
+            }
+        }
+        let mut stream = self.connect().await?;
+        let res = self.send(&mut stream, &payload).await;
+        *conn = res.is_ok().then_some(stream);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_tracing::emit;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::events::NewBlock;
+
+    #[test]
+    fn events_on_bus() {
+        let mut rx = super::subscribe();
+        let subscriber = tracing_subscriber::registry().with(super::layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            emit!(NewBlock { block_height: 10 });
+            tracing::info!("not an event");
+        });
+
+        let event = rx.try_recv().expect("event published");
+        assert_eq!(event.event, "NewBlock");
+        assert_eq!(event.fields.get("block_height"), Some(&10u64.into()));
+        assert!(!event.fields.contains_key("event"));
+        assert!(rx.try_recv().is_err());
+    }
+}
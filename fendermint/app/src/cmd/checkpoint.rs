@@ -0,0 +1,43 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    cmd,
+    cmd::run::simulate_checkpoint,
+    options::checkpoint::{CheckpointArgs, CheckpointCommands, CheckpointSimulateArgs},
+};
+
+cmd! {
+    CheckpointArgs(self, settings) {
+        match &self.command {
+            CheckpointCommands::Simulate(args) => args.exec(settings).await,
+        }
+    }
+}
+
+cmd! {
+    CheckpointSimulateArgs(self, settings) {
+        let block_hash = match self.block_hash {
+            Some(ref hash) => {
+                let bz = hex::decode(hash.trim_start_matches("0x")).context("invalid block hash")?;
+                let hash: [u8; 32] = bz.try_into().map_err(|_| anyhow!("block hash should be 32 bytes"))?;
+                Some(hash)
+            }
+            None => None,
+        };
+
+        let simulation = simulate_checkpoint(&settings, self.epoch, block_hash)?;
+
+        if simulation.differences.is_empty() {
+            eprintln!("the re-derived checkpoint matches the one stored and signed");
+        } else {
+            for d in simulation.differences.iter() {
+                eprintln!("difference: {d}");
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&simulation)?);
+        Ok(())
+    }
+}
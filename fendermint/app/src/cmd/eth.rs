@@ -38,12 +38,41 @@ async fn run(settings: EthSettings, client: HybridClient) -> anyhow::Result<()>
         num_blocks_max_prio_fee: settings.gas.num_blocks_max_prio_fee,
         max_fee_hist_size: settings.gas.max_fee_hist_size,
     };
+    let http = fendermint_eth_api::HttpOpt {
+        max_batch_size: settings.http.max_batch_size,
+        max_body_size: settings.http.max_body_size,
+        compression: settings.http.compression,
+    };
+    let methods = fendermint_eth_api::MethodOpt {
+        allowed: settings.methods.allowed,
+        disabled: settings.methods.disabled,
+        max_call_gas: settings.methods.max_call_gas,
+        call_timeout: settings.methods.call_timeout,
+    };
+    let cors = fendermint_eth_api::CorsOpt {
+        allowed_origins: settings.cors.allowed_origins,
+        allowed_methods: settings.cors.allowed_methods,
+        allowed_headers: settings.cors.allowed_headers,
+        allow_credentials: settings.cors.allow_credentials,
+    };
+    let unix_socket = settings
+        .unix_socket
+        .map(|opt| fendermint_eth_api::UnixSocketOpt {
+            path: opt.path,
+            mode: opt.mode,
+        });
     fendermint_eth_api::listen(
         settings.listen,
         client,
         settings.filter_timeout,
         settings.cache_capacity,
         gas,
+        settings.max_log_range,
+        http,
+        methods,
+        cors,
+        unix_socket,
+        Vec::new(),
     )
     .await
 }
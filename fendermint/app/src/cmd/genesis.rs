@@ -11,8 +11,8 @@ use std::path::PathBuf;
 use fendermint_vm_actor_interface::eam::EthAddress;
 use fendermint_vm_core::{chainid, Timestamp};
 use fendermint_vm_genesis::{
-    ipc, Account, Actor, ActorMeta, Collateral, Genesis, Multisig, PermissionMode, SignerAddr,
-    Validator, ValidatorKey,
+    ipc, Account, Actor, ActorMeta, Collateral, Deployments, ExecLimits, Genesis, Multisig,
+    PermissionMode, SignerAddr, Validator, ValidatorKey,
 };
 
 use crate::cmd;
@@ -30,6 +30,8 @@ cmd! {
         GenesisCommands::AddValidator(args) => args.exec(genesis_file).await,
         GenesisCommands::IntoTendermint(args) => args.exec(genesis_file).await,
         GenesisCommands::SetEamPermissions(args) => args.exec(genesis_file).await,
+        GenesisCommands::SetExecLimits(args) => args.exec(genesis_file).await,
+        GenesisCommands::AddDeployments(args) => args.exec(genesis_file).await,
         GenesisCommands::Ipc { command } => command.exec(genesis_file).await,
     }
   }
@@ -47,6 +49,8 @@ cmd! {
       accounts: Vec::new(),
       eam_permission_mode: PermissionMode::Unrestricted,
       ipc: None,
+      exec_limits: None,
+      deployments: None,
     };
 
     let json = serde_json::to_string_pretty(&genesis)?;
@@ -86,6 +90,18 @@ cmd! {
   }
 }
 
+cmd! {
+  GenesisSetExecLimitsArgs(self, genesis_file: PathBuf) {
+    set_exec_limits(&genesis_file, self)
+  }
+}
+
+cmd! {
+  GenesisAddDeploymentsArgs(self, genesis_file: PathBuf) {
+    add_deployments(&genesis_file, self)
+  }
+}
+
 cmd! {
   GenesisIpcCommands(self, genesis_file: PathBuf) {
     match self {
@@ -210,6 +226,84 @@ fn set_eam_permissions(
     })
 }
 
+fn set_exec_limits(genesis_file: &PathBuf, args: &GenesisSetExecLimitsArgs) -> anyhow::Result<()> {
+    update_genesis(genesis_file, |mut genesis| {
+        let limits = ExecLimits {
+            max_memory_bytes: args.max_memory_bytes,
+            max_inst_memory_bytes: args.max_inst_memory_bytes,
+            max_call_depth: args.max_call_depth,
+            max_wasm_stack: args.max_wasm_stack,
+            topdown_gas_budget: args.topdown_gas_budget,
+            bottomup_max_msgs: args.bottomup_max_msgs,
+            bottomup_max_bytes: args.bottomup_max_bytes,
+            checkpoint_history: args.checkpoint_history,
+            gas_governance: args.gas_governance,
+        };
+        genesis.exec_limits = if limits == ExecLimits::default() {
+            None
+        } else {
+            Some(limits)
+        };
+        Ok(genesis)
+    })
+}
+
+fn add_deployments(genesis_file: &PathBuf, args: &GenesisAddDeploymentsArgs) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(&args.manifest).context("failed to read manifest")?;
+    let manifest: Deployments = serde_json::from_str(&json).context("failed to parse manifest")?;
+    manifest.check_contract_names()?;
+
+    // Catch typos here rather than when the chain fails to start.
+    let check_hex = |what: String, s: &str| {
+        hex::decode(s.trim().trim_start_matches("0x"))
+            .map(|_| ())
+            .with_context(|| format!("{what} is not valid hex"))
+    };
+    for a in manifest.actors.iter() {
+        check_hex(format!("state of actor {}", a.name), &a.state)?;
+    }
+    for c in manifest.contracts.iter() {
+        check_hex(format!("bytecode of contract {}", c.name), &c.bytecode)?;
+        check_hex(
+            format!("constructor args of contract {}", c.name),
+            &c.constructor_args,
+        )?;
+    }
+
+    update_genesis(genesis_file, |mut genesis| {
+        let mut deployments = genesis.deployments.unwrap_or_default();
+        for c in manifest.contracts.iter() {
+            if deployments.contracts.iter().any(|d| d.name == c.name) {
+                return Err(anyhow!(
+                    "contract {} already exists in the genesis file",
+                    c.name
+                ));
+            }
+        }
+        for (i, call) in manifest.calls.iter().enumerate() {
+            check_hex(format!("calldata of call {i}"), &call.calldata)?;
+            let known = deployments
+                .contracts
+                .iter()
+                .chain(manifest.contracts.iter())
+                .any(|c| c.name == call.contract);
+            if !known {
+                return Err(anyhow!("call {i} is to unknown contract {}", call.contract));
+            }
+        }
+        deployments.actors.extend(manifest.actors);
+        deployments.contracts.extend(manifest.contracts);
+        deployments.calls.extend(manifest.calls);
+
+        genesis.deployments = if deployments.is_empty() {
+            None
+        } else {
+            Some(deployments)
+        };
+        Ok(genesis)
+    })
+}
+
 fn into_tendermint(genesis_file: &PathBuf, args: &GenesisIntoTendermintArgs) -> anyhow::Result<()> {
     let genesis = read_genesis(genesis_file)?;
     let genesis_json = serde_json::to_value(&genesis)?;
@@ -293,11 +387,21 @@ async fn new_genesis_from_parent(
                 .ok_or_else(|| anyhow!("subnet is not a child"))?,
             config: SubnetConfig::Fevm(EVMSubnet {
                 provider_http: args.parent_endpoint.clone(),
+                provider_http_fallbacks: Vec::new(),
+                provider_ws: None,
+                provider_archive: None,
                 provider_timeout: None,
+                provider_max_in_flight: None,
+                provider_queue_timeout: None,
+                provider_retry: None,
+                fee_caps: None,
+                resubmit: None,
                 auth_token: None,
                 registry_addr: args.parent_registry,
                 gateway_addr: args.parent_gateway,
             }),
+            manager: None,
+            read_only: false,
         },
     )?;
 
@@ -326,6 +430,8 @@ async fn new_genesis_from_parent(
         accounts: Vec::new(),
         eam_permission_mode: PermissionMode::Unrestricted,
         ipc: Some(ipc_params),
+        exec_limits: None,
+        deployments: None,
     };
 
     for v in genesis_info.validators {
@@ -147,7 +147,7 @@ fn public_to_b64(pk: &PublicKey) -> String {
     to_b64(&pk.serialize_compressed())
 }
 
-fn b64_to_public(b64: &str) -> anyhow::Result<PublicKey> {
+pub fn b64_to_public(b64: &str) -> anyhow::Result<PublicKey> {
     let json = serde_json::json!(b64);
     let pk: PublicKey = serde_json::from_value(json)?;
     Ok(pk)
@@ -10,6 +10,7 @@ use crate::{
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 
+pub mod checkpoint;
 pub mod config;
 pub mod eth;
 pub mod genesis;
@@ -17,6 +18,9 @@ pub mod key;
 pub mod materializer;
 pub mod rpc;
 pub mod run;
+pub mod snapshot;
+pub mod topdown;
+pub mod upgrade;
 
 #[async_trait]
 pub trait Cmd {
@@ -68,6 +72,10 @@ pub async fn exec(opts: &Options) -> anyhow::Result<()> {
         Commands::Rpc(args) => args.exec(()).await,
         Commands::Eth(args) => args.exec(settings(opts)?.eth).await,
         Commands::Materializer(args) => args.exec(()).await,
+        Commands::Upgrade(args) => args.exec(settings(opts)).await,
+        Commands::TopDown(args) => args.exec(settings(opts)?).await,
+        Commands::Checkpoint(args) => args.exec(settings(opts)?).await,
+        Commands::Snapshot(args) => args.exec(settings(opts)?).await,
     }
 }
 
@@ -5,7 +5,7 @@ use std::future::Future;
 use std::path::PathBuf;
 use std::pin::Pin;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use bytes::Bytes;
 use fendermint_app_options::genesis::AccountKind;
@@ -17,7 +17,9 @@ use fendermint_rpc::tx::{
 };
 use fendermint_vm_core::chainid;
 use fendermint_vm_message::chain::ChainMessage;
-use fendermint_vm_message::query::FvmQueryHeight;
+use fendermint_vm_message::query::{
+    ActorStatsQuery, FvmQueryHeight, TopDownAdminCommand, TxIndexFilter, TxIndexQuery,
+};
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
@@ -34,7 +36,7 @@ use fendermint_vm_actor_interface::eam::{self, CreateReturn, EthAddress};
 
 use crate::cmd;
 use crate::options::rpc::{BroadcastMode, FevmArgs, RpcFevmCommands, TransArgs};
-use crate::options::rpc::{RpcArgs, RpcCommands, RpcQueryCommands};
+use crate::options::rpc::{RpcArgs, RpcCommands, RpcQueryCommands, RpcTopDownCommands};
 
 use super::key::read_secret_key;
 
@@ -103,6 +105,89 @@ async fn query(
             let json = json!({ "response": res });
             print_json(&json)?;
         }
+        RpcQueryCommands::VoteTally => {
+            let res = client.vote_tally().await?;
+            let json = json!({ "response": res });
+            print_json(&json)?;
+        }
+        RpcQueryCommands::TopDown { token, command } => {
+            let command = match command {
+                RpcTopDownCommands::Status => TopDownAdminCommand::Status,
+                RpcTopDownCommands::Pause => TopDownAdminCommand::Pause,
+                RpcTopDownCommands::Resume => TopDownAdminCommand::Resume,
+            };
+            let res = client.topdown_admin(token, command).await?;
+            let json = json!({ "response": res });
+            print_json(&json)?;
+        }
+        RpcQueryCommands::TopDownParams => {
+            let res = client.topdown_params().await?;
+            let json = json!({ "response": res });
+            print_json(&json)?;
+        }
+        RpcQueryCommands::FinalityLog { parent_height } => {
+            match client.parent_finality_log(parent_height).await? {
+                Some(res) => {
+                    let json = json!({ "response": res });
+                    print_json(&json)?;
+                }
+                None => {
+                    eprintln!("no finality committed at parent height {parent_height}")
+                }
+            }
+        }
+        RpcQueryCommands::Txs {
+            hash,
+            sender,
+            recipient,
+            method,
+            skip,
+            limit,
+        } => {
+            let filter = match (sender, recipient, method) {
+                (Some(a), _, _) => Some(TxIndexFilter::Sender(a)),
+                (_, Some(a), _) => Some(TxIndexFilter::Recipient(a)),
+                (_, _, Some(m)) => Some(TxIndexFilter::Method(m)),
+                _ => None,
+            };
+            let query = match (hash, filter) {
+                (Some(hash), _) => TxIndexQuery::Hash(hash.to_vec()),
+                (_, Some(filter)) => TxIndexQuery::List {
+                    filter,
+                    skip,
+                    limit,
+                },
+                _ => {
+                    return Err(anyhow!(
+                        "one of --hash, --sender, --recipient or --method is required"
+                    ))
+                }
+            };
+            let res = client.indexed_txs(query).await?;
+            let json = json!({ "response": res });
+            print_json(&json)?;
+        }
+        RpcQueryCommands::Trace { hash } => match client.exec_trace(&hash).await? {
+            Some(res) => {
+                let json = json!({ "response": res });
+                print_json(&json)?;
+            }
+            None => eprintln!("no execution trace of transaction {}", hex::encode(&hash)),
+        },
+        RpcQueryCommands::PendingReceipt { hash } => match client.pending_receipt(&hash).await? {
+            Some(res) => {
+                let json = json!({ "response": res });
+                print_json(&json)?;
+            }
+            None => eprintln!("no pending receipt of transaction {}", hex::encode(&hash)),
+        },
+        RpcQueryCommands::ActorStats { blocks, limit } => {
+            let res = client
+                .actor_stats(ActorStatsQuery { blocks, limit })
+                .await?;
+            let json = json!({ "response": res });
+            print_json(&json)?;
+        }
     };
     Ok(())
 }
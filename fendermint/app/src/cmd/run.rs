@@ -4,37 +4,57 @@
 use anyhow::{anyhow, bail, Context};
 use async_stm::atomically_or_err;
 use fendermint_abci::ApplicationService;
+use fendermint_app::bus;
 use fendermint_app::ipc::{AppParentFinalityQuery, AppVote};
-use fendermint_app::{App, AppConfig, AppStore, BitswapBlockstore};
+use fendermint_app::pacing::ProposalPacing;
+use fendermint_app::{
+    ActorStatsDbStore, App, AppConfig, AppStore, BitswapBlockstore, ExecTraceDbStore,
+    LogIndexDbStore, ParentBlockHashStore, ParentFinalityLogDbStore, ParentViewDbStore,
+    TxIndexDbStore, UpgradeCheckpoint,
+};
 use fendermint_app_settings::AccountKind;
 use fendermint_crypto::SecretKey;
 use fendermint_rocksdb::{blockstore::NamespaceBlockstore, namespaces, RocksDb, RocksDbConfig};
 use fendermint_vm_actor_interface::eam::EthAddress;
 use fendermint_vm_interpreter::chain::ChainEnv;
-use fendermint_vm_interpreter::fvm::upgrades::UpgradeScheduler;
+use fendermint_vm_interpreter::fvm::state::ipc::GatewayCaller;
+use fendermint_vm_interpreter::fvm::state::FvmExecState;
+use fendermint_vm_interpreter::fvm::upgrades::{
+    actors, Migration, MigrationParams, MigrationRegistry, PowerTableSnapshot, UpgradeManifest,
+    UpgradePolicy, UpgradeScheduler, ValidatorChangesBackfill,
+};
 use fendermint_vm_interpreter::{
     bytes::{BytesMessageInterpreter, ProposalPrepareMode},
     chain::{ChainMessageInterpreter, CheckpointPool},
-    fvm::{Broadcaster, FvmMessageInterpreter, ValidatorContext},
+    fvm::{
+        Broadcaster, CheckPolicy, CheckpointSigner, CheckpointSimulation, FvmMessageInterpreter,
+        RemoteSigner, ValidatorContext,
+    },
     signed::SignedMessageInterpreter,
 };
+use fendermint_vm_message::query::{ParentSyncParams, TopDownParams};
 use fendermint_vm_resolver::ipld::IpldResolver;
-use fendermint_vm_snapshot::{SnapshotManager, SnapshotParams};
+use fendermint_vm_snapshot::{SnapshotItem, SnapshotManager, SnapshotParams};
 use fendermint_vm_topdown::proxy::IPCProviderProxy;
-use fendermint_vm_topdown::sync::launch_polling_syncer;
+use fendermint_vm_topdown::sync::{launch_polling_syncer, ParentViewStore};
+use fendermint_vm_topdown::verify::{ParentBlockVerifier, WitnessVerifier};
 use fendermint_vm_topdown::voting::{publish_vote_loop, Error as VoteError, VoteTally};
-use fendermint_vm_topdown::{CachedFinalityProvider, IPCParentFinality, Toggle};
+use fendermint_vm_topdown::{CachedFinalityProvider, IPCParentFinality, ParentFinalityLog, Toggle};
+use fvm::engine::MultiEngine;
 use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
 use ipc_ipld_resolver::{Event as ResolverEvent, VoteRecord};
 use ipc_provider::config::subnet::{EVMSubnet, SubnetConfig};
 use ipc_provider::IpcProvider;
 use libp2p::identity::secp256k1;
 use libp2p::identity::Keypair;
+use std::str::FromStr;
 use std::sync::Arc;
+use tendermint_rpc::Url;
 use tokio::sync::broadcast::error::RecvError;
 use tracing::info;
 
-use crate::cmd::key::read_secret_key;
+use crate::cmd::key::{b64_to_public, read_secret_key};
 use crate::{cmd, options::run::RunArgs, settings::Settings};
 
 cmd! {
@@ -49,7 +69,14 @@ namespaces! {
         app,
         state_hist,
         state_store,
-        bit_store
+        bit_store,
+        parent_block_hash,
+        parent_view,
+        parent_finality_log,
+        tx_index,
+        exec_traces,
+        actor_stats,
+        log_index
     }
 }
 
@@ -57,6 +84,12 @@ namespaces! {
 ///
 /// This method acts as our composition root.
 async fn run(settings: Settings) -> anyhow::Result<()> {
+    settings.ipc.validate().context("invalid IPC settings")?;
+    settings
+        .proposal
+        .validate()
+        .context("invalid proposal settings")?;
+
     let tendermint_rpc_url = settings.tendermint_rpc_url()?;
     tracing::info!("Connecting to Tendermint at {tendermint_rpc_url}");
 
@@ -76,6 +109,9 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
         None
     };
 
+    // Forward the emitted events to the configured sinks.
+    spawn_event_sinks(&settings).context("failed to start event sinks")?;
+
     let validator = match settings.validator_key {
         Some(ref key) => {
             let sk = key.path(settings.home_dir());
@@ -102,6 +138,26 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
         libp2p::identity::Keypair::from(kp)
     });
 
+    let checkpoint_signer = match settings.ipc.checkpoint_signer {
+        Some(ref signer) => {
+            tracing::info!(url = %signer.url, "signing checkpoints with a remote signer");
+            let public_key = b64_to_public(&signer.public_key)
+                .context("failed to parse checkpoint signer public key")?;
+            let signer = RemoteSigner::new(
+                signer.url.to_string().parse()?,
+                signer.auth_token.clone(),
+                public_key,
+                signer.timeout,
+            )?;
+            Some(CheckpointSigner::Remote(signer))
+        }
+        None => None,
+    };
+
+    if checkpoint_signer.is_some() && validator.is_none() {
+        bail!("a checkpoint signer needs a validator key to send the signatures with");
+    }
+
     let validator_ctx = validator.map(|(sk, addr)| {
         // For now we are using the validator key for submitting transactions.
         // This allows us to identify transactions coming from empowered validators, to give priority to protocol related transactions.
@@ -116,9 +172,16 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
         .with_max_retries(settings.broadcast.max_retries)
         .with_retry_delay(settings.broadcast.retry_delay);
 
-        ValidatorContext::new(sk, broadcaster)
+        let ctx = ValidatorContext::new(sk, broadcaster);
+        match checkpoint_signer {
+            Some(signer) => ctx.with_checkpoint_signer(signer),
+            None => ctx,
+        }
     });
 
+    let upgrade_scheduler = make_upgrade_scheduler(&settings)?;
+    let upgrade_heights = upgrade_scheduler.upgrade_heights();
+
     let interpreter = FvmMessageInterpreter::<NamespaceBlockstore, _>::new(
         tendermint_client.clone(),
         validator_ctx,
@@ -126,8 +189,10 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
         settings.fvm.gas_overestimation_rate,
         settings.fvm.gas_search_step,
         settings.fvm.exec_in_check,
-        UpgradeScheduler::new(),
-    );
+        upgrade_scheduler,
+    )
+    .with_checkpoint_archive_dir(settings.ipc.checkpoint_archive_dir(settings.home_dir()))
+    .with_check_policy(to_check_policy(&settings)?);
     let interpreter = SignedMessageInterpreter::new(interpreter);
     let interpreter = ChainMessageInterpreter::<_, NamespaceBlockstore>::new(interpreter);
     let interpreter =
@@ -219,7 +284,7 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
     let (parent_finality_provider, ipc_tuple) = if topdown_enabled {
         info!("topdown finality enabled");
         let topdown_config = settings.ipc.topdown_config()?;
-        let config = fendermint_vm_topdown::Config::new(
+        let mut config = fendermint_vm_topdown::Config::new(
             topdown_config.chain_head_delay,
             topdown_config.polling_interval,
             topdown_config.exponential_back_off,
@@ -227,16 +292,87 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
         )
         .with_proposal_delay(topdown_config.proposal_delay)
         .with_max_proposal_range(topdown_config.max_proposal_range);
-        let ipc_provider = Arc::new(make_ipc_provider_proxy(&settings)?);
+        if let Some(parallel_fetches) = topdown_config.parallel_fetches {
+            config = config.with_parallel_fetches(parallel_fetches);
+        }
+        if let Some(max_reorg_lookback) = topdown_config.max_reorg_lookback {
+            config = config.with_max_reorg_lookback(max_reorg_lookback);
+        }
+        if let Some(finality_rule) = topdown_config.parent_finality_rule {
+            config = config.with_finality_rule(finality_rule);
+        }
+        let block_hash_store = ParentBlockHashStore::new(db.clone(), ns.parent_block_hash);
+        let ipc_provider = make_ipc_provider_proxy(
+            &settings,
+            &topdown_config.parent_http_endpoint,
+            topdown_config.parent_http_auth_token.clone(),
+        )?
+        .with_block_hash_store(Arc::new(block_hash_store), topdown_config.chain_head_delay)
+        .with_finality_rule(config.finality_rule());
+        let ipc_provider = Arc::new(ipc_provider);
         let finality_provider =
             CachedFinalityProvider::uninitialized(config.clone(), ipc_provider.clone()).await?;
         let p = Arc::new(Toggle::enabled(finality_provider));
-        (p, Some((ipc_provider, config)))
+        let verifier = make_parent_verifier(&settings)?;
+        // Only the heights which are not final yet are restored, which fit in the cache.
+        let view_store: Arc<dyn ParentViewStore> = Arc::new(ParentViewDbStore::new(
+            db.clone(),
+            ns.parent_view,
+            config.max_cache_blocks() * 2,
+        ));
+        (p, Some((ipc_provider, config, verifier, view_store)))
     } else {
         info!("topdown finality disabled");
         (Arc::new(Toggle::disabled()), None)
     };
 
+    // Only nodes syncing with the parent can execute the top-down finality to log.
+    let parent_finality_log: Option<Arc<dyn ParentFinalityLog>> = if topdown_enabled {
+        Some(Arc::new(ParentFinalityLogDbStore::new(
+            db.clone(),
+            ns.parent_finality_log,
+        )))
+    } else {
+        None
+    };
+
+    let tx_index = if settings.db.tx_index {
+        Some(Arc::new(TxIndexDbStore::new(db.clone(), ns.tx_index)))
+    } else {
+        info!("transaction index disabled");
+        None
+    };
+
+    let exec_traces = if settings.db.exec_traces {
+        Some(Arc::new(ExecTraceDbStore::new(
+            db.clone(),
+            ns.exec_traces,
+            settings.db.exec_trace_history,
+        )))
+    } else {
+        None
+    };
+
+    let log_index = if settings.db.log_index {
+        Some(Arc::new(LogIndexDbStore::new(db.clone(), ns.log_index)))
+    } else {
+        None
+    };
+
+    let actor_stats = if settings.db.actor_stats {
+        Some(Arc::new(ActorStatsDbStore::new(
+            db.clone(),
+            ns.actor_stats,
+            settings.db.actor_stats_history,
+        )))
+    } else {
+        None
+    };
+
+    if settings.fvm.pending_receipts && !settings.fvm.exec_in_check {
+        tracing::warn!("pending receipts need `fvm.exec_in_check`; there won't be any");
+    }
+
     // Start a snapshot manager in the background.
     let snapshots = if settings.snapshots.enabled {
         let (manager, client) = SnapshotManager::new(
@@ -271,6 +407,20 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
             builtin_actors_bundle: settings.builtin_actors_bundle(),
             custom_actors_bundle: settings.custom_actors_bundle(),
             halt_height: settings.halt_height,
+            upgrade_halt_height: settings.upgrades.halt_height,
+            upgrade_heights,
+            topdown_admin_token: settings
+                .ipc
+                .topdown
+                .as_ref()
+                .and_then(|t| t.admin_token.clone()),
+            topdown_params: to_topdown_params(&settings, topdown_enabled),
+            tx_index,
+            exec_traces,
+            actor_stats,
+            log_index,
+            pending_receipts: settings.fvm.pending_receipts,
+            proposal_pacing: to_proposal_pacing(&settings),
         },
         db,
         state_store,
@@ -279,11 +429,12 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
             checkpoint_pool,
             parent_finality_provider: parent_finality_provider.clone(),
             parent_finality_votes: parent_finality_votes.clone(),
+            parent_finality_log,
         },
         snapshots,
     )?;
 
-    if let Some((agent_proxy, config)) = ipc_tuple {
+    if let Some((agent_proxy, config, verifier, view_store)) = ipc_tuple {
         let app_parent_finality_query = AppParentFinalityQuery::new(app.clone());
         tokio::spawn(async move {
             match launch_polling_syncer(
@@ -292,6 +443,8 @@ async fn run(settings: Settings) -> anyhow::Result<()> {
                 parent_finality_provider,
                 parent_finality_votes,
                 agent_proxy,
+                verifier,
+                Some(view_store),
                 tendermint_client,
             )
             .await
@@ -350,6 +503,251 @@ fn open_db(settings: &Settings, ns: &Namespaces) -> anyhow::Result<RocksDb> {
     Ok(db)
 }
 
+/// Re-derive the bottom-up checkpoint created at a height from the state history and compare it
+/// with the one stored then; the node must not be running.
+pub fn simulate_checkpoint(
+    settings: &Settings,
+    height: u64,
+    block_hash: Option<[u8; 32]>,
+) -> anyhow::Result<CheckpointSimulation> {
+    if height == 0 {
+        bail!("there are no checkpoints at genesis");
+    }
+    let ns = Namespaces::default();
+    let db = open_db(settings, &ns).context("error opening DB")?;
+    let state_store =
+        NamespaceBlockstore::new(db.clone(), ns.state_store).context("error creating state DB")?;
+
+    let state_params = |h| {
+        fendermint_app::historical_state_params::<_, AppStore>(&db, ns.state_hist.clone(), h)?
+            .ok_or_else(|| anyhow!("the state at height {h} is not in the history anymore"))
+    };
+    let prev_params = state_params(height - 1)?;
+    let params = state_params(height)?;
+
+    let multi_engine = MultiEngine::new(1);
+    let mut prev_state = FvmExecState::new(
+        state_store.clone(),
+        &multi_engine,
+        height as ChainEpoch,
+        prev_params,
+    )
+    .context("error creating the state before the block")?;
+    let mut state = FvmExecState::new(state_store, &multi_engine, height as ChainEpoch, params)
+        .context("error creating the state after the block")?;
+
+    fendermint_vm_interpreter::fvm::simulate_checkpoint(
+        &GatewayCaller::default(),
+        &mut prev_state,
+        &mut state,
+        height,
+        block_hash,
+    )
+}
+
+/// Export the state after a block, or the last committed one, into a snapshot directory;
+/// the node must not be running.
+pub async fn export_snapshot(
+    settings: &Settings,
+    block_height: Option<u64>,
+    chunk_size: usize,
+    snapshot_dir: &std::path::Path,
+) -> anyhow::Result<SnapshotItem> {
+    let ns = Namespaces::default();
+    let db = open_db(settings, &ns).context("error opening DB")?;
+    let state_store =
+        NamespaceBlockstore::new(db.clone(), ns.state_store).context("error creating state DB")?;
+
+    let (block_height, state_params) = fendermint_app::committed_state_params::<_, AppStore>(
+        &db,
+        &ns.app,
+        ns.state_hist,
+        block_height,
+    )?;
+
+    fendermint_vm_snapshot::export_snapshot(
+        state_store,
+        block_height,
+        state_params,
+        chunk_size,
+        snapshot_dir,
+    )
+    .await
+}
+
+/// Verify a snapshot and import it into the state store, then make its state the committed one;
+/// the node must not be running.
+pub async fn import_snapshot(
+    settings: &Settings,
+    snapshot_dir: &std::path::Path,
+) -> anyhow::Result<SnapshotItem> {
+    let snapshot = SnapshotItem::load(snapshot_dir.into())?;
+    snapshot
+        .verify()
+        .context("the snapshot failed verification")?;
+
+    let ns = Namespaces::default();
+    let db = open_db(settings, &ns).context("error opening DB")?;
+    let state_store =
+        NamespaceBlockstore::new(db.clone(), ns.state_store).context("error creating state DB")?;
+
+    snapshot.import(state_store, true).await?;
+
+    fendermint_app::restore_snapshot_state::<_, AppStore>(
+        &db,
+        &ns.app,
+        ns.state_hist,
+        snapshot.manifest.block_height,
+        snapshot.manifest.state_params.clone(),
+    )?;
+
+    Ok(snapshot)
+}
+
+/// Roll the application state back to before the last upgrade; the node must not be running.
+pub fn rollback_upgrade(settings: &Settings) -> anyhow::Result<UpgradeCheckpoint> {
+    let ns = Namespaces::default();
+    let db = open_db(settings, &ns).context("error opening DB")?;
+    fendermint_app::rollback_upgrade::<_, AppStore>(&db, &ns.app, ns.state_hist)
+}
+
+/// Create the upgrade scheduler, requiring approvals if the settings have a policy,
+/// with the upgrades declared in the manifest, if there is one.
+pub fn make_upgrade_scheduler<DB>(settings: &Settings) -> anyhow::Result<UpgradeScheduler<DB>>
+where
+    DB: fvm_ipld_blockstore::Blockstore + 'static + Clone,
+{
+    let mut scheduler = if settings.upgrades.threshold == 0 {
+        UpgradeScheduler::new()
+    } else {
+        let approvers = settings
+            .upgrades
+            .approvers
+            .iter()
+            .map(|b64| b64_to_public(b64).context("failed to parse upgrade approver public key"))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let policy = UpgradePolicy::new(approvers, settings.upgrades.threshold)?;
+
+        UpgradeScheduler::new_with_policy(policy)
+    };
+
+    scheduler = scheduler.with_rerun(settings.upgrades.allow_rerun);
+
+    if let Some(ref contract) = settings.upgrades.governance {
+        scheduler = scheduler.with_governance(contract.parse()?);
+    }
+
+    if let Some(path) = settings.upgrades.manifest(settings.home_dir()) {
+        info!(
+            path = path.to_string_lossy().into_owned(),
+            "loading upgrade manifest"
+        );
+        UpgradeManifest::from_file(&path)?.schedule(&migration_registry(), &mut scheduler)?;
+    }
+
+    Ok(scheduler)
+}
+
+/// The migrations the upgrade manifest can schedule.
+pub fn migration_registry<DB>() -> MigrationRegistry<DB>
+where
+    DB: fvm_ipld_blockstore::Blockstore + 'static + Clone,
+{
+    let mut registry = MigrationRegistry::default();
+    registry
+        .register("noop", noop_migration::<DB>)
+        .expect("migration names are unique");
+    registry
+        .register("install_bundle", install_bundle_migration::<DB>)
+        .expect("migration names are unique");
+    registry
+        .register("import_power_table", import_power_table_migration::<DB>)
+        .expect("migration names are unique");
+    registry
+        .register(
+            "store_validator_changes",
+            store_validator_changes_migration::<DB>,
+        )
+        .expect("migration names are unique");
+    registry
+}
+
+/// A migration which doesn't change the state, for upgrades which only change the app version.
+fn noop_migration<DB>(_: &MigrationParams) -> anyhow::Result<Migration<DB>>
+where
+    DB: fvm_ipld_blockstore::Blockstore + 'static + Clone,
+{
+    Ok(Arc::new(|_: &mut FvmExecState<DB>| Ok(())))
+}
+
+/// A migration which installs the actors named in the comma separated `actors` parameter
+/// from the bundle at the `bundle` path, switching the existing actors to the new code
+/// without changing their state.
+fn install_bundle_migration<DB>(params: &MigrationParams) -> anyhow::Result<Migration<DB>>
+where
+    DB: fvm_ipld_blockstore::Blockstore + 'static + Clone,
+{
+    let path = params.get("bundle").context("missing bundle parameter")?;
+    let bundle = std::fs::read(path).with_context(|| format!("failed to read bundle {path}"))?;
+    let names = params
+        .get("actors")
+        .context("missing actors parameter")?
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .collect::<Vec<_>>();
+
+    Ok(Arc::new(move |state: &mut FvmExecState<DB>| {
+        let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        let replaced = actors::install_bundle(state, &bundle, &names)?;
+        for (old_code, new_code) in replaced.into_values() {
+            actors::replace_code(state, old_code, new_code, &mut actors::keep_state::<DB>)?;
+        }
+        Ok(())
+    }))
+}
+
+/// A migration which sets the power of the validators to that in the power table at the
+/// `file` path, which has to be signed by the base64 encoded public key in `signer`.
+fn import_power_table_migration<DB>(params: &MigrationParams) -> anyhow::Result<Migration<DB>>
+where
+    DB: fvm_ipld_blockstore::Blockstore + 'static + Clone,
+{
+    let path = params.get("file").context("missing file parameter")?;
+    let signer = params.get("signer").context("missing signer parameter")?;
+    let signer = b64_to_public(signer).context("invalid signer public key")?;
+
+    let table = PowerTableSnapshot::from_file(path)?;
+    table.verify(&signer)?;
+
+    Ok(Arc::new(move |state: &mut FvmExecState<DB>| {
+        table.apply(state)?;
+        Ok(())
+    }))
+}
+
+/// A migration which stores the validator changes in the `file` path in the gateway, which
+/// have to match the hex encoded `digest`, e.g. as generated by `fendermint topdown backfill-changes`.
+fn store_validator_changes_migration<DB>(params: &MigrationParams) -> anyhow::Result<Migration<DB>>
+where
+    DB: fvm_ipld_blockstore::Blockstore + 'static + Clone,
+{
+    let path = params.get("file").context("missing file parameter")?;
+    let digest = params.get("digest").context("missing digest parameter")?;
+
+    let backfill = ValidatorChangesBackfill::from_file(path)?;
+    if hex::encode(backfill.digest()?) != digest.trim_start_matches("0x").to_lowercase() {
+        bail!("the validator changes in {path} don't match the digest");
+    }
+    // Fail early rather than at the upgrade height.
+    backfill.change_requests()?;
+
+    Ok(Arc::new(move |state: &mut FvmExecState<DB>| {
+        backfill.apply(state)?;
+        Ok(())
+    }))
+}
+
 fn make_resolver_service(
     settings: &Settings,
     db: RocksDb,
@@ -370,7 +768,12 @@ fn make_resolver_service(
     Ok(service)
 }
 
-fn make_ipc_provider_proxy(settings: &Settings) -> anyhow::Result<IPCProviderProxy> {
+/// A proxy to the parent through the given endpoint, authenticating with the given token.
+pub(crate) fn make_ipc_provider_proxy(
+    settings: &Settings,
+    endpoint: &Url,
+    auth_token: Option<String>,
+) -> anyhow::Result<IPCProviderProxy> {
     let topdown_config = settings.ipc.topdown_config()?;
     let subnet = ipc_provider::config::Subnet {
         id: settings
@@ -379,16 +782,22 @@ fn make_ipc_provider_proxy(settings: &Settings) -> anyhow::Result<IPCProviderPro
             .parent()
             .ok_or_else(|| anyhow!("subnet has no parent"))?,
         config: SubnetConfig::Fevm(EVMSubnet {
-            provider_http: topdown_config
-                .parent_http_endpoint
-                .to_string()
-                .parse()
-                .unwrap(),
+            provider_http: endpoint.to_string().parse().unwrap(),
+            provider_http_fallbacks: Vec::new(),
+            provider_ws: None,
+            provider_archive: None,
             provider_timeout: topdown_config.parent_http_timeout,
-            auth_token: topdown_config.parent_http_auth_token.as_ref().cloned(),
+            provider_max_in_flight: None,
+            provider_queue_timeout: None,
+            provider_retry: None,
+            fee_caps: None,
+            resubmit: None,
+            auth_token,
             registry_addr: topdown_config.parent_registry,
             gateway_addr: topdown_config.parent_gateway,
         }),
+        manager: None,
+        read_only: false,
     };
     info!("init ipc provider with subnet: {}", subnet.id);
 
@@ -396,9 +805,79 @@ fn make_ipc_provider_proxy(settings: &Settings) -> anyhow::Result<IPCProviderPro
     IPCProviderProxy::new(ipc_provider, settings.ipc.subnet_id.clone())
 }
 
+/// Verify the parent blocks against the configured witnesses, if any.
+fn make_parent_verifier(
+    settings: &Settings,
+) -> anyhow::Result<Option<Arc<dyn ParentBlockVerifier>>> {
+    let topdown_config = settings.ipc.topdown_config()?;
+    let endpoints = match topdown_config.parent_witness_endpoints {
+        Some(ref endpoints) if !endpoints.is_empty() => endpoints,
+        _ => return Ok(None),
+    };
+
+    // The token of the primary endpoint is not for third parties to see.
+    let auth_tokens = topdown_config
+        .parent_witness_auth_tokens
+        .clone()
+        .unwrap_or_default();
+    if !auth_tokens.is_empty() && auth_tokens.len() != endpoints.len() {
+        bail!(
+            "{} parent witness auth tokens given for {} witnesses",
+            auth_tokens.len(),
+            endpoints.len()
+        );
+    }
+
+    let witnesses = endpoints
+        .iter()
+        .enumerate()
+        .map(|(i, endpoint)| {
+            let auth_token = auth_tokens.get(i).filter(|t| !t.is_empty()).cloned();
+            make_ipc_provider_proxy(settings, endpoint, auth_token).map(Arc::new)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let threshold = topdown_config
+        .parent_witness_threshold
+        .unwrap_or(witnesses.len());
+
+    info!(
+        witnesses = witnesses.len(),
+        threshold, "verifying parent blocks against witnesses"
+    );
+
+    let verifier =
+        WitnessVerifier::new(witnesses, threshold).context("invalid parent witness settings")?;
+
+    Ok(Some(Arc::new(verifier)))
+}
+
+/// The top-down finality settings the node reports to operators.
+fn to_topdown_params(settings: &Settings, topdown_enabled: bool) -> TopDownParams {
+    let parent = settings
+        .ipc
+        .topdown
+        .as_ref()
+        .filter(|_| topdown_enabled)
+        .map(|t| ParentSyncParams {
+            chain_head_delay: t.chain_head_delay,
+            proposal_delay: t.proposal_delay,
+            max_proposal_range: t.max_proposal_range,
+            polling_interval_secs: t.polling_interval.as_secs(),
+            finality_rule: t.parent_finality_rule.unwrap_or_default().to_string(),
+        });
+
+    TopDownParams {
+        vote_interval_secs: settings.ipc.vote_interval.as_secs(),
+        vote_timeout_secs: settings.ipc.vote_timeout.as_secs(),
+        parent,
+    }
+}
+
 fn to_resolver_config(settings: &Settings) -> anyhow::Result<ipc_ipld_resolver::Config> {
     use ipc_ipld_resolver::{
         Config, ConnectionConfig, ContentConfig, DiscoveryConfig, MembershipConfig, NetworkConfig,
+        PeerScoreConfig,
     };
 
     let r = &settings.resolver;
@@ -440,6 +919,13 @@ fn to_resolver_config(settings: &Settings) -> anyhow::Result<ipc_ipld_resolver::
             publish_interval: r.membership.publish_interval,
             min_time_between_publish: r.membership.min_time_between_publish,
             max_provider_age: r.membership.max_provider_age,
+            max_vote_age: r.membership.max_vote_age,
+            peer_score: PeerScoreConfig {
+                gossip_threshold: r.membership.peer_score.gossip_threshold,
+                publish_threshold: r.membership.peer_score.publish_threshold,
+                graylist_threshold: r.membership.peer_score.graylist_threshold,
+                vote_topic_weight: r.membership.peer_score.vote_topic_weight,
+            },
         },
         content: ContentConfig {
             rate_limit_bytes: r.content.rate_limit_bytes,
@@ -450,6 +936,80 @@ fn to_resolver_config(settings: &Settings) -> anyhow::Result<ipc_ipld_resolver::
     Ok(config)
 }
 
+/// Forward the emitted events to the sinks configured in the settings.
+fn spawn_event_sinks(settings: &Settings) -> anyhow::Result<()> {
+    let events = &settings.events;
+    for url in &events.webhooks {
+        let url = url
+            .to_string()
+            .parse()
+            .context("invalid event webhook URL")?;
+        bus::spawn_sink(Box::new(bus::WebhookSink::new(url)?));
+    }
+    if let Some(ref url) = events.kafka_rest_url {
+        let url = url
+            .to_string()
+            .parse()
+            .context("invalid Kafka REST proxy URL")?;
+        bus::spawn_sink(Box::new(bus::KafkaRestSink::new(url, &events.kafka_topic)?));
+    }
+    if let Some(ref address) = events.nats_address {
+        bus::spawn_sink(Box::new(bus::NatsSink::new(
+            address.clone(),
+            events.nats_subject.clone(),
+        )?));
+    }
+    Ok(())
+}
+
+/// How full to make the proposed blocks, from the settings.
+fn to_proposal_pacing(settings: &Settings) -> ProposalPacing {
+    let proposal = &settings.proposal;
+    ProposalPacing {
+        target_fullness: proposal.target_fullness,
+        burst_mempool_txs: proposal.burst_mempool_txs,
+        burst_topdown_msgs: proposal.burst_topdown_msgs,
+        ipc_reserve_bytes: proposal.ipc_reserve_bytes,
+    }
+}
+
+/// The rules for admitting transactions to the mempool, from the settings.
+fn to_check_policy(settings: &Settings) -> anyhow::Result<CheckPolicy> {
+    let parse = |addrs: &[String]| {
+        addrs
+            .iter()
+            .map(|a| parse_policy_address(a))
+            .collect::<anyhow::Result<Vec<_>>>()
+    };
+    let mempool = &settings.mempool;
+    if settings.fvm.gas_premium < mempool.min_gas_premium {
+        tracing::warn!(
+            gas_premium = settings.fvm.gas_premium.atto().to_string(),
+            min_gas_premium = mempool.min_gas_premium.atto().to_string(),
+            "the transactions broadcast by the node pay less than the minimum gas premium of its mempool"
+        );
+    }
+    Ok(CheckPolicy {
+        min_gas_premium: mempool.min_gas_premium.clone(),
+        max_params_size: Some(mempool.max_params_size).filter(|s| *s > 0),
+        banned_senders: parse(&mempool.banned_senders).context("invalid banned sender")?,
+        contract_creators: parse(&mempool.contract_creators).context("invalid contract creator")?,
+    })
+}
+
+/// Parse an address given either in the `f` or the Ethereum `0x` format.
+fn parse_policy_address(s: &str) -> anyhow::Result<Address> {
+    match s.strip_prefix("0x") {
+        Some(hex_addr) => {
+            let bytes: [u8; 20] = hex::decode(hex_addr)?
+                .try_into()
+                .map_err(|_| anyhow!("Ethereum address {s} should be 20 bytes"))?;
+            Ok(Address::from(EthAddress(bytes)))
+        }
+        None => Ok(Address::from_str(s)?),
+    }
+}
+
 fn to_address(sk: &SecretKey, kind: &AccountKind) -> anyhow::Result<Address> {
     let pk = sk.public_key().serialize();
     match kind {
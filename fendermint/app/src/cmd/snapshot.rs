@@ -0,0 +1,51 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use anyhow::bail;
+
+use crate::{
+    cmd,
+    cmd::run::{export_snapshot, import_snapshot},
+    options::snapshot::{SnapshotArgs, SnapshotCommands, SnapshotExportArgs, SnapshotImportArgs},
+};
+
+cmd! {
+    SnapshotArgs(self, settings) {
+        match &self.command {
+            SnapshotCommands::Export(args) => args.exec(settings).await,
+            SnapshotCommands::Import(args) => args.exec(settings).await,
+        }
+    }
+}
+
+cmd! {
+    SnapshotExportArgs(self, settings) {
+        if self.output.exists() {
+            bail!("output directory {} already exists", self.output.to_string_lossy());
+        }
+        let chunk_size = self.chunk_size.unwrap_or(settings.snapshots.chunk_size_bytes);
+
+        let snapshot = export_snapshot(&settings, self.height, chunk_size, &self.output).await?;
+
+        eprintln!(
+            "exported the state at height {} into {}",
+            snapshot.manifest.block_height,
+            self.output.to_string_lossy()
+        );
+        println!("{}", serde_json::to_string_pretty(&snapshot.manifest)?);
+        Ok(())
+    }
+}
+
+cmd! {
+    SnapshotImportArgs(self, settings) {
+        let snapshot = import_snapshot(&settings, &self.input).await?;
+
+        eprintln!(
+            "imported the state at height {}; bootstrap CometBFT to the same height before starting the node",
+            snapshot.manifest.block_height
+        );
+        println!("{}", serde_json::to_string_pretty(&snapshot.manifest)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Context};
+use fendermint_app_settings::topdown::ParentChain;
+use fendermint_vm_actor_interface::eam::{EthAddress, EAM_ACTOR_ID};
+use fendermint_vm_actor_interface::ipc::{GATEWAY_ACTOR_ID, SUBNETREGISTRY_ACTOR_ID};
+use fendermint_vm_interpreter::fvm::upgrades::{UpgradeDefinition, ValidatorChangesBackfill};
+use fendermint_vm_topdown::is_null_round_error;
+use fendermint_vm_topdown::proxy::ParentQueryProxy;
+use fvm_shared::address::Address;
+use ipc_provider::config::subnet::{EVMSubnet, SubnetConfig};
+use ipc_provider::IpcProvider;
+
+use crate::{
+    cmd,
+    cmd::run::make_ipc_provider_proxy,
+    options::topdown::{
+        TopDownArgs, TopDownBackfillChangesArgs, TopDownCommands, TopDownRecommendedSettingsArgs,
+    },
+    settings::Settings,
+};
+
+cmd! {
+    TopDownArgs(self, settings) {
+        match &self.command {
+            TopDownCommands::BackfillChanges(args) => args.exec(settings).await,
+            TopDownCommands::RecommendedSettings(args) => args.exec(()).await,
+        }
+    }
+}
+
+cmd! {
+    TopDownBackfillChangesArgs(self, settings) {
+        if self.from_height > self.to_height {
+            bail!("the scanned range is empty");
+        }
+        let topdown_config = settings.ipc.topdown_config()?;
+        let parent = make_ipc_provider_proxy(
+            &settings,
+            &topdown_config.parent_http_endpoint,
+            topdown_config.parent_http_auth_token.clone(),
+        )?;
+
+        let start = match self.start_configuration_number {
+            Some(n) => n,
+            None => applied_configuration_number(&settings, &self.child_url).await? + 1,
+        };
+
+        let mut changes = BTreeMap::new();
+        for height in self.from_height..=self.to_height {
+            let res = match parent.get_validator_changes(height).await {
+                Ok(res) => res,
+                Err(e) if is_null_round_error(&e) => continue,
+                Err(e) => return Err(e.context(format!("failed to get validator changes at height {height}"))),
+            };
+            for change in res.value {
+                if change.configuration_number >= start {
+                    changes.insert(change.configuration_number, change);
+                }
+            }
+        }
+
+        let changes = changes.into_values().collect::<Vec<_>>();
+        match changes.first() {
+            None => bail!("no validator changes from configuration number {start} in the scanned range"),
+            Some(c) if c.configuration_number != start => bail!(
+                "the first validator change in the scanned range is {} instead of {start}; scan from an earlier height",
+                c.configuration_number
+            ),
+            Some(_) => {}
+        }
+
+        let backfill = ValidatorChangesBackfill::from_change_requests(&changes)?;
+        // Catch gaps now rather than at the upgrade height.
+        backfill.change_requests().context("the scanned range is missing some changes")?;
+
+        let json = serde_json::to_string_pretty(&backfill)?;
+        std::fs::write(&self.output, json).context("failed to write validator changes")?;
+
+        let file = std::fs::canonicalize(&self.output)?;
+        let definition = UpgradeDefinition {
+            chain_name: self.chain_name.clone(),
+            block_height: self.block_height,
+            new_app_version: None,
+            migration: "store_validator_changes".to_string(),
+            params: BTreeMap::from([
+                ("file".to_string(), file.to_string_lossy().to_string()),
+                ("digest".to_string(), hex::encode(backfill.digest()?)),
+            ]),
+            governed: false,
+            description: Some(format!(
+                "Store the validator changes {start} to {} missed from the parent.",
+                start + changes.len() as u64 - 1
+            )),
+            approvals: Vec::new(),
+            networks: Vec::new(),
+        };
+
+        eprintln!(
+            "wrote {} validator change(s) to {}",
+            changes.len(),
+            self.output.to_string_lossy()
+        );
+        println!("{}", serde_json::to_string_pretty(&definition)?);
+        Ok(())
+    }
+}
+
+cmd! {
+    TopDownRecommendedSettingsArgs(self) {
+        let parent_chain: ParentChain = self.parent_chain.parse()?;
+        for (name, value) in parent_chain.recommended_timing().to_env_vars() {
+            println!("{name}={value}");
+        }
+        Ok(())
+    }
+}
+
+/// The configuration number of the membership the subnet applied last, from its gateway.
+async fn applied_configuration_number(
+    settings: &Settings,
+    child_url: &tendermint_rpc::Url,
+) -> anyhow::Result<u64> {
+    // The gateway and the registry are built-in actors of the subnet, reachable
+    // over the Ethereum API at their masked ID addresses.
+    let builtin_address = |id| {
+        Address::new_delegated(EAM_ACTOR_ID, &EthAddress::from_id(id).0)
+            .map_err(|e| anyhow!("invalid address: {e}"))
+    };
+    let subnet = ipc_provider::config::Subnet {
+        id: settings.ipc.subnet_id.clone(),
+        config: SubnetConfig::Fevm(EVMSubnet {
+            provider_http: child_url.to_string().parse()?,
+            provider_http_fallbacks: Vec::new(),
+            provider_ws: None,
+            provider_archive: None,
+            provider_timeout: None,
+            provider_max_in_flight: None,
+            provider_queue_timeout: None,
+            provider_retry: None,
+            fee_caps: None,
+            resubmit: None,
+            auth_token: None,
+            registry_addr: builtin_address(SUBNETREGISTRY_ACTOR_ID)?,
+            gateway_addr: builtin_address(GATEWAY_ACTOR_ID)?,
+        }),
+        manager: None,
+        read_only: true,
+    };
+    let subnet_id = subnet.id.clone();
+    let provider = IpcProvider::new_with_subnet(None, subnet)?;
+    provider
+        .current_configuration_number(&subnet_id)
+        .await
+        .context("failed to get the configuration number applied by the subnet")
+}
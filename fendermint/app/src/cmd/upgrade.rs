@@ -0,0 +1,273 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use fendermint_crypto::to_b64;
+use fendermint_rpc::{client::FendermintClient, query::QueryClient};
+use fendermint_vm_core::chainid;
+use fendermint_vm_interpreter::fvm::state::snapshot::Snapshot;
+use fendermint_vm_interpreter::fvm::store::memory::MemoryBlockstore;
+use fendermint_vm_interpreter::fvm::upgrades::{
+    ActorChange, UpgradeDefinition, UpgradeDryRun, UpgradeManifest,
+};
+use fendermint_vm_message::query::FvmQueryHeight;
+use fendermint_vm_snapshot::{SnapshotItem, SnapshotManifest};
+use fvm::engine::MultiEngine;
+use fvm_shared::chainid::ChainID;
+
+use crate::{
+    cmd,
+    cmd::key::read_secret_key,
+    cmd::run::{make_upgrade_scheduler, migration_registry, rollback_upgrade},
+    options::upgrade::{
+        UpgradeApproveArgs, UpgradeArgs, UpgradeCommands, UpgradeDryRunArgs, UpgradeListArgs,
+        UpgradeRollbackArgs,
+    },
+    settings::Settings,
+};
+
+// Only rolling back needs the settings; the other commands work without a config.
+cmd! {
+    UpgradeArgs(self, settings: anyhow::Result<Settings>) {
+        match &self.command {
+            UpgradeCommands::Approve(args) => args.exec(()).await,
+            UpgradeCommands::DryRun(args) => args.exec(()).await,
+            UpgradeCommands::Rollback(args) => args.exec(settings?).await,
+            UpgradeCommands::List(args) => args.exec(settings?).await,
+        }
+    }
+}
+
+cmd! {
+    UpgradeApproveArgs(self) {
+        let sk = read_secret_key(&self.secret_key)?;
+        let mut manifest = UpgradeManifest::from_file(&self.manifest)?;
+
+        let mut approved = 0;
+        let mut approve = |definition: UpgradeDefinition, approvals: &mut Vec<String>| {
+            if self.block_height.map_or(false, |h| h != definition.block_height)
+                || self.chain_name.as_ref().map_or(false, |c| *c != definition.chain_name)
+            {
+                return Ok(());
+            }
+            let approval = to_b64(&sk.sign(&definition.digest()?).0.serialize());
+            if !approvals.contains(&approval) {
+                approvals.push(approval);
+            }
+            approved += 1;
+            anyhow::Ok(())
+        };
+        for upgrade in manifest.upgrades.iter_mut() {
+            // Each network has its own digest, because its chain and height are part of it.
+            for i in 0..upgrade.networks.len() {
+                let definition = upgrade.on_network(&upgrade.networks[i]);
+                approve(definition, &mut upgrade.networks[i].approvals)?;
+            }
+            approve(upgrade.clone(), &mut upgrade.approvals)?;
+        }
+
+        let json = serde_json::to_string_pretty(&manifest)?;
+        std::fs::write(&self.manifest, json).context("failed to write upgrade manifest")?;
+
+        println!("approved {approved} upgrade(s)");
+        Ok(())
+    }
+}
+
+cmd! {
+    UpgradeDryRunArgs(self) {
+        // The snapshot is imported into memory, so whatever the migrations do stays there.
+        let store = MemoryBlockstore::new();
+        let Snapshot::V1(snapshot) = load_snapshot(&self.snapshot, store.clone()).await?;
+        let snapshot_height = snapshot.block_height();
+        let mut params = snapshot.state_params().clone();
+        let chain_id = ChainID::from(params.chain_id);
+
+        let manifest = UpgradeManifest::from_file(&self.manifest)?;
+        let mut definitions = Vec::new();
+        for definition in manifest.definitions() {
+            if self.block_height.map_or(false, |h| h != definition.block_height) {
+                continue;
+            }
+            if chainid::from_str_hashed(&definition.chain_name)? == chain_id {
+                definitions.push(definition);
+            }
+        }
+        if definitions.is_empty() {
+            bail!("no upgrades for chain ID {} in the manifest", params.chain_id);
+        }
+        definitions.sort_by_key(|d| d.block_height);
+
+        let registry = migration_registry::<MemoryBlockstore>();
+        let multi_engine = MultiEngine::new(1);
+
+        // Each upgrade runs on the state left behind by the previous one, as it would on the chain.
+        for definition in definitions {
+            if definition.block_height <= snapshot_height {
+                eprintln!(
+                    "warning: the snapshot is at height {snapshot_height}, which is not before the upgrade at height {}",
+                    definition.block_height
+                );
+            }
+            let upgrade = definition.to_upgrade(&registry)?;
+            let dry_run = upgrade
+                .dry_run(store.clone(), &multi_engine, params.clone())
+                .with_context(|| {
+                    format!(
+                        "dry run of upgrade {} at height {} failed",
+                        definition.migration, definition.block_height
+                    )
+                })?;
+
+            println!(
+                "upgrade {} at height {}:",
+                definition.migration, definition.block_height
+            );
+            print_dry_run(&dry_run);
+
+            params.state_root = dry_run.state_root_after;
+            if let Some(app_version) = dry_run.new_app_version {
+                params.app_version = app_version;
+            }
+        }
+        Ok(())
+    }
+}
+
+cmd! {
+    UpgradeRollbackArgs(self, settings) {
+        let checkpoint = rollback_upgrade(&settings)?;
+        println!(
+            "rolled back to the state before the upgrade at height {}, with state root {}",
+            checkpoint.upgrade_height,
+            checkpoint.state.state_root()
+        );
+        println!(
+            "roll CometBFT back to height {} as well before restarting the node",
+            checkpoint.upgrade_height - 1
+        );
+        Ok(())
+    }
+}
+
+cmd! {
+    UpgradeListArgs(self, settings) {
+        let scheduler = make_upgrade_scheduler::<MemoryBlockstore>(&settings)?;
+
+        let client = FendermintClient::new_http(self.url.clone(), None)?;
+        let res = client.state_params(FvmQueryHeight::Committed).await?;
+        let chain_id = ChainID::from(res.value.chain_id);
+        let height = res.height.value();
+
+        println!("chain ID {} at height {height}", res.value.chain_id);
+
+        let mut count = 0;
+        for upgrade in scheduler.upgrades(chain_id) {
+            count += 1;
+            let app_version = upgrade
+                .new_app_version()
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            // Chunked upgrades are still running until their last chunk.
+            let status = if upgrade.block_height() + upgrade.chunks() - 1 <= height {
+                "executed"
+            } else if upgrade.block_height() <= height {
+                "running"
+            } else {
+                "pending"
+            };
+            println!(
+                "{:>12}  app version {:>3}  {:<8}  {}",
+                upgrade.block_height(),
+                app_version,
+                status,
+                upgrade.description().unwrap_or_default()
+            );
+        }
+        for upgrade in scheduler.governed_upgrades(chain_id) {
+            count += 1;
+            println!(
+                "{:>12}+ app version {:>3}  governed  {}",
+                upgrade.block_height(),
+                upgrade
+                    .new_app_version()
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                upgrade.description().unwrap_or_default()
+            );
+        }
+        if count == 0 {
+            println!("no upgrades scheduled");
+        }
+        Ok(())
+    }
+}
+
+/// Import a snapshot from a CAR file or from a snapshot directory.
+async fn load_snapshot(
+    path: &Path,
+    store: MemoryBlockstore,
+) -> anyhow::Result<Snapshot<MemoryBlockstore>> {
+    if !path.is_dir() {
+        return Snapshot::read_car(path, store, true)
+            .await
+            .with_context(|| format!("failed to import snapshot {}", path.display()));
+    }
+    let manifest_path = path.join("manifest.json");
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let manifest: SnapshotManifest =
+        serde_json::from_str(&manifest).context("failed to parse snapshot manifest")?;
+
+    SnapshotItem::new(path.to_path_buf(), manifest)
+        .import(store, true)
+        .await
+        .with_context(|| format!("failed to import snapshot {}", path.display()))
+}
+
+fn print_dry_run(dry_run: &UpgradeDryRun) {
+    println!(
+        "  state root: {} -> {}",
+        dry_run.state_root_before, dry_run.state_root_after
+    );
+    if let Some(app_version) = dry_run.new_app_version {
+        println!(
+            "  app version: {} -> {app_version}",
+            dry_run.app_version_before
+        );
+    }
+    if dry_run.changes.is_empty() {
+        println!("  no actors changed");
+    }
+    for (id, change) in dry_run.changes.iter() {
+        match change {
+            ActorChange::Added(state) => {
+                println!("  actor {id} added with code {}", state.code)
+            }
+            ActorChange::Removed(state) => {
+                println!("  actor {id} removed, had code {}", state.code)
+            }
+            ActorChange::Changed { before, after } => {
+                let mut fields = Vec::new();
+                if before.code != after.code {
+                    fields.push(format!("code {} -> {}", before.code, after.code));
+                }
+                if before.state != after.state {
+                    fields.push(format!("state {} -> {}", before.state, after.state));
+                }
+                if before.sequence != after.sequence {
+                    fields.push(format!("nonce {} -> {}", before.sequence, after.sequence));
+                }
+                if before.balance != after.balance {
+                    fields.push(format!("balance {} -> {}", before.balance, after.balance));
+                }
+                if before.delegated_address != after.delegated_address {
+                    fields.push("delegated address".to_string());
+                }
+                println!("  actor {id} changed: {}", fields.join(", "));
+            }
+        }
+    }
+}
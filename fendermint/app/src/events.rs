@@ -4,7 +4,11 @@
 use crate::BlockHeight;
 
 /// Re-export other events, just to provide the visibility of where they are.
-pub use fendermint_vm_event::{NewBottomUpCheckpoint, NewParentView, ParentFinalityCommitted};
+pub use fendermint_vm_event::{
+    MsgApplied, MsgExecLimits, NewBottomUpCheckpoint, NewParentView, ParentFinalityCommitted,
+    ParentFinalityExecuted, ParentFinalityLag, ParentFinalityLatency, ParentFinalityQuorum,
+    UpgradeExecuting, UpgradeFailed, UpgradePending, UpgradeProgress, UpgradeSucceeded,
+};
 
 #[derive(Debug, Default)]
 pub struct ProposalProcessed<'a> {
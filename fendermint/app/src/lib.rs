@@ -1,14 +1,24 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 mod app;
+pub mod bus;
 pub mod events;
 pub mod ipc;
 pub mod metrics;
+pub mod pacing;
+mod receipts;
 mod store;
 mod tmconv;
 
-pub use app::{App, AppConfig};
-pub use store::{AppStore, BitswapBlockstore};
+pub use app::{
+    committed_state_params, historical_state_params, restore_snapshot_state, rollback_upgrade, App,
+    AppConfig, UpgradeCheckpoint,
+};
+pub use store::{
+    ActorStatsDbStore, AppStore, BitswapBlockstore, ExecTraceDbStore, LogIndexDbStore,
+    ParentBlockHashStore, ParentFinalityLogDbStore, ParentViewDbStore, TxIndexDbStore,
+    TxIndexEntry,
+};
 
 // Different type from `ChainEpoch` just because we might use epoch in a more traditional sense for checkpointing.
 pub type BlockHeight = u64;
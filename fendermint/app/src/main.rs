@@ -65,7 +65,8 @@ fn init_tracing(opts: &options::Options) -> Option<WorkerGuard> {
     let registry = tracing_subscriber::registry()
         .with(console_layer)
         .with(file_layer)
-        .with(metrics_layer);
+        .with(metrics_layer)
+        .with(fendermint_app::bus::layer());
 
     tracing::subscriber::set_global_default(registry).expect("Unable to set a global collector");
 
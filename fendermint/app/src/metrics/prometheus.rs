@@ -30,11 +30,30 @@ pub mod app {
         TOPDOWN_VIEW_NUM_MSGS: IntCounter = "Number of top-down messages observed since start";
         TOPDOWN_VIEW_NUM_VAL_CHNGS: IntCounter = "Number of top-down validator changes observed since start";
         TOPDOWN_FINALIZED_BLOCK_HEIGHT: IntGauge = "Highest parent subnet block finalized";
+        TOPDOWN_FINALITY_LAG: IntGauge = "Number of parent subnet blocks the committed finality is behind the parent chain head";
+        TOPDOWN_FINALITY_LATENCY_MS: IntGauge = "Milliseconds from syncing the last finalized parent block to committing it";
+        TOPDOWN_QUORUM_LATENCY_MS: IntGauge = "Milliseconds from the first vote on the last parent block with a quorum to reaching the quorum";
+        TOPDOWN_EXEC_NUM_MSGS: IntGauge = "Number of top-down messages delivered with the last parent finality";
+        TOPDOWN_EXEC_NUM_MSGS_TOTAL: IntCounter = "Number of top-down messages delivered with parent finalities since start";
+        TOPDOWN_EXEC_NUM_VAL_CHNGS_TOTAL: IntCounter = "Number of top-down validator changes delivered with parent finalities since start";
 
         BOTTOMUP_CKPT_BLOCK_HEIGHT: IntGauge = "Highest bottom-up checkpoint created";
         BOTTOMUP_CKPT_CONFIG_NUM: IntGauge = "Highest configuration number checkpointed";
         BOTTOMUP_CKPT_NUM_MSGS: IntCounter = "Number of bottom-up messages observed since start";
 
+        EXEC_MSGS_NEAR_GAS_LIMIT: IntCounter = "Number of messages which used most of their gas limit";
+        EXEC_MSGS_OUT_OF_GAS: IntCounter = "Number of messages which ran out of gas";
+        EXEC_MSGS_TRAPPED: IntCounter = "Number of messages aborted by the sandbox, e.g. for exceeding memory or stack limits";
+
+        UPGRADE_PENDING_HEIGHT: IntGauge = "Height of the next upgrade scheduled for the chain";
+        UPGRADE_PENDING_BLOCKS_REMAINING: IntGauge = "Number of blocks until the next scheduled upgrade";
+        UPGRADE_EXECUTING_HEIGHT: IntGauge = "Height of the last upgrade which started executing";
+        UPGRADE_SUCCEEDED_HEIGHT: IntGauge = "Height of the last upgrade executed successfully";
+        UPGRADE_SUCCEEDED_APP_VERSION: IntGauge = "App version after the last successful upgrade";
+        UPGRADE_FAILED_HEIGHT: IntGauge = "Height of the last upgrade which failed";
+        UPGRADE_CHUNKS_DONE: IntGauge = "Number of blocks of the upgrade in progress which have been executed";
+        UPGRADE_CHUNKS: IntGauge = "Number of blocks the upgrade in progress is split across";
+
         // This metrics is available in CometBFT as well, but it's something that should increase even without subnets,
         // which can be a useful way to check if metrics work at all.
         ABCI_COMMITTED_BLOCK_HEIGHT: IntGauge = "Highest committed block";
@@ -108,11 +108,48 @@ impl<S: Subscriber> Layer<S> for MetricsLayer<S> {
             ParentFinalityCommitted {
                 block_height              => set_gauge   ! &am::TOPDOWN_FINALIZED_BLOCK_HEIGHT,
             },
+            ParentFinalityLag {
+                lag                       => set_gauge   ! &am::TOPDOWN_FINALITY_LAG,
+            },
+            ParentFinalityLatency {
+                latency_ms                => set_gauge   ! &am::TOPDOWN_FINALITY_LATENCY_MS,
+            },
+            ParentFinalityQuorum {
+                latency_ms                => set_gauge   ! &am::TOPDOWN_QUORUM_LATENCY_MS,
+            },
+            ParentFinalityExecuted {
+                num_msgs                  => set_gauge   ! &am::TOPDOWN_EXEC_NUM_MSGS,
+                num_msgs                  => inc_counter ! &am::TOPDOWN_EXEC_NUM_MSGS_TOTAL,
+                num_validator_changes     => inc_counter ! &am::TOPDOWN_EXEC_NUM_VAL_CHNGS_TOTAL,
+            },
             NewBottomUpCheckpoint {
                 block_height              => set_gauge   ! &am::BOTTOMUP_CKPT_BLOCK_HEIGHT,
                 next_configuration_number => set_gauge   ! &am::BOTTOMUP_CKPT_CONFIG_NUM,
                 num_msgs                  => inc_counter ! &am::BOTTOMUP_CKPT_NUM_MSGS,
             },
+            MsgExecLimits {
+                near_gas_limit            => inc_counter ! &am::EXEC_MSGS_NEAR_GAS_LIMIT,
+                out_of_gas                => inc_counter ! &am::EXEC_MSGS_OUT_OF_GAS,
+                trapped                   => inc_counter ! &am::EXEC_MSGS_TRAPPED,
+            },
+            UpgradePending {
+                upgrade_height            => set_gauge   ! &am::UPGRADE_PENDING_HEIGHT,
+                blocks_remaining          => set_gauge   ! &am::UPGRADE_PENDING_BLOCKS_REMAINING,
+            },
+            UpgradeExecuting {
+                upgrade_height            => set_gauge   ! &am::UPGRADE_EXECUTING_HEIGHT,
+            },
+            UpgradeSucceeded {
+                upgrade_height            => set_gauge   ! &am::UPGRADE_SUCCEEDED_HEIGHT,
+                app_version               => set_gauge   ! &am::UPGRADE_SUCCEEDED_APP_VERSION,
+            },
+            UpgradeFailed {
+                upgrade_height            => set_gauge   ! &am::UPGRADE_FAILED_HEIGHT,
+            },
+            UpgradeProgress {
+                chunks_done               => set_gauge   ! &am::UPGRADE_CHUNKS_DONE,
+                chunks                    => set_gauge   ! &am::UPGRADE_CHUNKS,
+            },
             NewBlock {
                 block_height              => set_gauge   ! &am::ABCI_COMMITTED_BLOCK_HEIGHT
             }
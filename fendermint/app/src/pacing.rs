@@ -0,0 +1,87 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Adjusting how full the proposed blocks are to the pending load.
+//!
+//! Under light load smaller blocks propagate and execute faster, which keeps the latency down.
+//! Under bursty load, i.e. a deep mempool, or many top-down messages waiting in the state after
+//! a parent finality which carried more than a block could execute, the proposer fills its blocks
+//! up to drain the backlog sooner.
+//!
+//! The pacing only affects the blocks this node proposes; the other validators accept any block
+//! within the consensus limits.
+
+/// How the proposer fills its blocks with the transactions offered from its mempool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProposalPacing {
+    /// Fraction of the block space filled with mempool transactions under normal load.
+    pub target_fullness: f64,
+    /// Number of transactions offered from the mempool from which the load counts as bursty;
+    /// never if zero.
+    pub burst_mempool_txs: usize,
+    /// Number of top-down messages waiting for execution from which the load counts as bursty;
+    /// never if zero.
+    pub burst_topdown_msgs: usize,
+    /// Bytes of block space kept free of mempool transactions for the parent finality and
+    /// bottom-up checkpoints added by the proposer, which would be left out otherwise.
+    pub ipc_reserve_bytes: usize,
+}
+
+impl Default for ProposalPacing {
+    /// Fill the blocks up regardless of the load.
+    fn default() -> Self {
+        Self {
+            target_fullness: 1.0,
+            burst_mempool_txs: 0,
+            burst_topdown_msgs: 0,
+            ipc_reserve_bytes: 0,
+        }
+    }
+}
+
+impl ProposalPacing {
+    /// Whether the pending load counts as bursty.
+    pub fn is_bursty(&self, mempool_txs: usize, topdown_msgs: usize) -> bool {
+        (self.burst_mempool_txs > 0 && mempool_txs >= self.burst_mempool_txs)
+            || (self.burst_topdown_msgs > 0 && topdown_msgs >= self.burst_topdown_msgs)
+    }
+
+    /// The bytes of mempool transactions to propose out of the maximum bytes of a block.
+    pub fn mempool_tx_bytes(
+        &self,
+        max_tx_bytes: usize,
+        mempool_txs: usize,
+        topdown_msgs: usize,
+    ) -> usize {
+        let max = max_tx_bytes.saturating_sub(self.ipc_reserve_bytes);
+        if self.is_bursty(mempool_txs, topdown_msgs) {
+            max
+        } else {
+            (max as f64 * self.target_fullness.clamp(0.0, 1.0)) as usize
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProposalPacing;
+
+    #[test]
+    fn fuller_blocks_under_load() {
+        let pacing = ProposalPacing {
+            target_fullness: 0.5,
+            burst_mempool_txs: 100,
+            burst_topdown_msgs: 10,
+            ipc_reserve_bytes: 1000,
+        };
+
+        assert_eq!(pacing.mempool_tx_bytes(11000, 0, 0), 5000);
+        assert_eq!(pacing.mempool_tx_bytes(11000, 99, 9), 5000);
+        assert_eq!(pacing.mempool_tx_bytes(11000, 100, 0), 10000);
+        assert_eq!(pacing.mempool_tx_bytes(11000, 0, 10), 10000);
+        assert_eq!(pacing.mempool_tx_bytes(500, 100, 0), 0);
+
+        let default = ProposalPacing::default();
+        assert_eq!(default.mempool_tx_bytes(11000, 0, 0), 11000);
+        assert!(!default.is_bursty(usize::MAX, usize::MAX));
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! The expected outcome of the transactions waiting in the mempool.
+//!
+//! CometBFT checks every transaction it admits to the mempool, and rechecks the ones left after
+//! each block, so executing them during the checks tells what they are likely to do once included.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use fendermint_vm_interpreter::fvm::FvmCheckRet;
+use fendermint_vm_message::query::PendingReceipt;
+use fendermint_vm_message::signed::DomainHash;
+use tendermint::crypto::sha256::Sha256;
+
+use crate::BlockHeight;
+
+/// The results of executing the transactions checked since the last commit,
+/// under their CometBFT and domain specific hashes.
+#[derive(Default)]
+pub struct PendingReceipts {
+    receipts: Mutex<HashMap<Vec<u8>, PendingReceipt>>,
+}
+
+impl PendingReceipts {
+    /// Remember the result of a transaction executed during the check, until the next commit.
+    ///
+    /// Only the transactions which were actually executed have a receipt, not the ones
+    /// rejected before that, e.g. for their nonce.
+    pub fn record(&self, tx: &[u8], block_height: BlockHeight, ret: &FvmCheckRet) {
+        let Some(gas_used) = ret.gas_used else {
+            return;
+        };
+        let hash = tendermint::crypto::default::Sha256::digest(tx).to_vec();
+        let domain_hash = ret.domain_hash.as_ref().map(|h| match h {
+            DomainHash::Eth(h) => h.to_vec(),
+        });
+        let receipt = PendingReceipt {
+            hash: hex::encode(&hash),
+            domain_hash: domain_hash.as_ref().map(hex::encode),
+            height: block_height,
+            from: ret.sender,
+            exit_code: ret.exit_code.value(),
+            gas_used,
+            return_data: hex::encode(ret.return_data.as_deref().unwrap_or_default()),
+            info: ret.info.clone(),
+        };
+        let mut receipts = self.receipts.lock().unwrap();
+        if let Some(domain_hash) = domain_hash {
+            receipts.insert(domain_hash, receipt.clone());
+        }
+        receipts.insert(hash, receipt);
+    }
+
+    /// Look up a receipt by the CometBFT or the domain specific hash of the transaction.
+    pub fn get(&self, hash: &[u8]) -> Option<PendingReceipt> {
+        self.receipts.lock().unwrap().get(hash).cloned()
+    }
+
+    /// Forget the receipts once a block is committed, as the transactions still in the mempool
+    /// have to be executed again on the new state.
+    pub fn clear(&self) {
+        self.receipts.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_vm_interpreter::fvm::FvmCheckRet;
+    use fendermint_vm_message::signed::DomainHash;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::{address::Address, error::ExitCode};
+    use tendermint::crypto::sha256::Sha256;
+
+    use super::PendingReceipts;
+
+    fn check_ret(gas_used: Option<u64>, domain_hash: Option<DomainHash>) -> FvmCheckRet {
+        FvmCheckRet {
+            sender: Address::new_id(100),
+            gas_limit: 1000,
+            exit_code: ExitCode::OK,
+            gas_used,
+            return_data: Some(RawBytes::new(vec![1, 2, 3])),
+            info: None,
+            domain_hash,
+        }
+    }
+
+    #[test]
+    fn receipts_by_both_hashes() {
+        let receipts = PendingReceipts::default();
+        let tx = b"transaction";
+        let hash = tendermint::crypto::default::Sha256::digest(tx).to_vec();
+        let eth_hash = [7u8; 32];
+
+        receipts.record(
+            tx,
+            10,
+            &check_ret(Some(500), Some(DomainHash::Eth(eth_hash))),
+        );
+
+        let receipt = receipts.get(&hash).expect("found by CometBFT hash");
+        assert_eq!(receipts.get(&eth_hash), Some(receipt.clone()));
+        assert_eq!(receipt.hash, hex::encode(&hash));
+        assert_eq!(receipt.domain_hash, Some(hex::encode(eth_hash)));
+        assert_eq!(receipt.height, 10);
+        assert_eq!(receipt.gas_used, 500);
+        assert_eq!(receipt.return_data, "010203");
+
+        receipts.clear();
+        assert_eq!(receipts.get(&hash), None);
+        assert_eq!(receipts.get(&eth_hash), None);
+    }
+
+    #[test]
+    fn no_receipt_if_not_executed() {
+        let receipts = PendingReceipts::default();
+        let tx = b"transaction";
+        let hash = tendermint::crypto::default::Sha256::digest(tx).to_vec();
+
+        receipts.record(tx, 10, &check_ret(None, None));
+
+        assert_eq!(receipts.get(&hash), None);
+    }
+}
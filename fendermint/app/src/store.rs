@@ -4,11 +4,34 @@ use ipc_ipld_resolver::missing_blocks::missing_blocks;
 // SPDX-License-Identifier: Apache-2.0, MIT
 use libp2p_bitswap::BitswapStore;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
+use anyhow::{anyhow, bail, Context};
+use ethers_core::types::Bloom;
 use fendermint_rocksdb::blockstore::NamespaceBlockstore;
-use fendermint_storage::{Codec, Decode, Encode, KVError, KVResult, KVStore};
+use fendermint_rocksdb::RocksDb;
+use fendermint_storage::{
+    Codec, Decode, Encode, KVCollection, KVError, KVRead, KVReadable, KVResult, KVStore, KVWritable,
+};
+use fendermint_vm_message::bloom;
+use fendermint_vm_message::query::{
+    ActorStats, ActorStatsReport, CallTrace, LogIndexQuery, TxIndexFilter,
+};
+use fendermint_vm_topdown::proxy::BlockHashStore;
+use fendermint_vm_topdown::sync::ParentViewStore;
+use fendermint_vm_topdown::{
+    CommittedFinality, IPCParentFinality, ParentFinalityLog, ParentViewPayload,
+};
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::{de::DeserializeOwned, serde::Serialize};
+use fvm_ipld_encoding::{de::DeserializeOwned, serde::Serialize, strict_bytes};
+use fvm_shared::address::Address;
+use fvm_shared::MethodNum;
+use ipc_api::cross::IpcEnvelope;
+use ipc_api::staking::{StakingChange, StakingChangeRequest, StakingOperation};
+use ipc_provider::manager::GetBlockHashResult;
+use serde::Deserialize;
+
+use crate::BlockHeight;
 
 /// [`KVStore`] type we use to store historial data in the database.
 #[derive(Clone)]
@@ -114,3 +137,1000 @@ impl BitswapStore for BitswapBlockstore {
         missing_blocks::<Self, Self::Params>(self, cid)
     }
 }
+
+/// The hashes of a parent block, as stored in the database.
+#[derive(Serialize, Deserialize)]
+struct ParentBlockHash {
+    #[serde(with = "strict_bytes")]
+    parent_block_hash: Vec<u8>,
+    #[serde(with = "strict_bytes")]
+    block_hash: Vec<u8>,
+}
+
+/// A [`BlockHashStore`] persisting the block hashes of finalized parent heights in RocksDB.
+pub struct ParentBlockHashStore {
+    db: RocksDb,
+    hashes: KVCollection<AppStore, BlockHeight, ParentBlockHash>,
+}
+
+impl ParentBlockHashStore {
+    pub fn new(db: RocksDb, ns: String) -> Self {
+        Self {
+            db,
+            hashes: KVCollection::new(ns),
+        }
+    }
+}
+
+impl BlockHashStore for ParentBlockHashStore {
+    fn get(&self, height: BlockHeight) -> anyhow::Result<Option<GetBlockHashResult>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let hash = self
+            .hashes
+            .get(&tx, &height)
+            .context("failed to get parent block hash")?;
+
+        Ok(hash.map(|h| GetBlockHashResult {
+            parent_block_hash: h.parent_block_hash,
+            block_hash: h.block_hash,
+        }))
+    }
+
+    fn put(&self, height: BlockHeight, block_hash: &GetBlockHashResult) -> anyhow::Result<()> {
+        let hash = ParentBlockHash {
+            parent_block_hash: block_hash.parent_block_hash.clone(),
+            block_hash: block_hash.block_hash.clone(),
+        };
+        KVWritable::<AppStore>::with_write(&self.db, |tx| self.hashes.put(tx, &height, &hash))
+            .context("failed to put parent block hash")
+    }
+}
+
+/// The parent view at a height, as stored in the database.
+#[derive(Serialize, Deserialize)]
+struct ParentView {
+    /// The height the view is for, as the slot it's stored in is shared.
+    height: BlockHeight,
+    /// `None` for a null round.
+    payload: Option<ParentViewData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ParentViewData {
+    #[serde(with = "strict_bytes")]
+    block_hash: Vec<u8>,
+    validator_changes: Vec<ValidatorChange>,
+    top_down_msgs: Vec<IpcEnvelope>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ValidatorChange {
+    configuration_number: u64,
+    op: u8,
+    #[serde(with = "strict_bytes")]
+    payload: Vec<u8>,
+    validator: Address,
+}
+
+/// A [`ParentViewStore`] persisting the parent view observed by the syncer in RocksDB.
+///
+/// Only the heights which haven't been finalized yet are needed, so rather than pruning,
+/// heights are stored in a fixed number of slots, overwriting whatever was stored for a
+/// height `capacity` blocks earlier.
+pub struct ParentViewDbStore {
+    db: RocksDb,
+    views: KVCollection<AppStore, u64, ParentView>,
+    capacity: u64,
+}
+
+impl ParentViewDbStore {
+    pub fn new(db: RocksDb, ns: String, capacity: u64) -> Self {
+        Self {
+            db,
+            views: KVCollection::new(ns),
+            capacity: capacity.max(1),
+        }
+    }
+}
+
+impl ParentViewStore for ParentViewDbStore {
+    fn get(&self, height: BlockHeight) -> anyhow::Result<Option<Option<ParentViewPayload>>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let view = self
+            .views
+            .get(&tx, &(height % self.capacity))
+            .context("failed to get parent view")?;
+
+        let Some(view) = view.filter(|v| v.height == height) else {
+            return Ok(None);
+        };
+        let Some(data) = view.payload else {
+            return Ok(Some(None));
+        };
+
+        Ok(Some(Some((
+            data.block_hash,
+            from_validator_changes(data.validator_changes)?,
+            data.top_down_msgs,
+        ))))
+    }
+
+    fn put(&self, height: BlockHeight, view: Option<&ParentViewPayload>) -> anyhow::Result<()> {
+        let payload = match view {
+            None => None,
+            Some((block_hash, changes, msgs)) => Some(ParentViewData {
+                block_hash: block_hash.clone(),
+                validator_changes: to_validator_changes(changes),
+                top_down_msgs: msgs.clone(),
+            }),
+        };
+        let view = ParentView { height, payload };
+
+        KVWritable::<AppStore>::with_write(&self.db, |tx| {
+            self.views.put(tx, &(height % self.capacity), &view)
+        })
+        .context("failed to put parent view")
+    }
+
+    fn remove_above(&self, height: BlockHeight) -> anyhow::Result<()> {
+        KVWritable::<AppStore>::with_write(&self.db, |tx| {
+            for slot in 0..self.capacity {
+                if let Some(view) = self.views.get(tx, &slot)? {
+                    if view.height > height {
+                        self.views.delete(tx, &slot)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+        .context("failed to remove parent views")
+    }
+}
+
+/// A committed parent finality, as stored in the database.
+#[derive(Serialize, Deserialize)]
+struct FinalityLogEntry {
+    block_height: BlockHeight,
+    #[serde(with = "strict_bytes")]
+    parent_block_hash: Vec<u8>,
+    executed_from: BlockHeight,
+    executed_to: BlockHeight,
+    validator_changes: Vec<ValidatorChange>,
+    top_down_msgs: Vec<IpcEnvelope>,
+}
+
+/// A [`ParentFinalityLog`] persisting the committed parent finalities in RocksDB.
+///
+/// Unlike the parent view, nothing is ever pruned from it.
+pub struct ParentFinalityLogDbStore {
+    db: RocksDb,
+    entries: KVCollection<AppStore, BlockHeight, FinalityLogEntry>,
+}
+
+impl ParentFinalityLogDbStore {
+    pub fn new(db: RocksDb, ns: String) -> Self {
+        Self {
+            db,
+            entries: KVCollection::new(ns),
+        }
+    }
+}
+
+impl ParentFinalityLog for ParentFinalityLogDbStore {
+    fn get(&self, height: BlockHeight) -> anyhow::Result<Option<CommittedFinality>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let entry = self
+            .entries
+            .get(&tx, &height)
+            .context("failed to get parent finality log entry")?;
+
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+
+        Ok(Some(CommittedFinality {
+            block_height: entry.block_height,
+            finality: IPCParentFinality {
+                height,
+                block_hash: entry.parent_block_hash,
+            },
+            executed_from: entry.executed_from,
+            executed_to: entry.executed_to,
+            validator_changes: from_validator_changes(entry.validator_changes)?,
+            top_down_msgs: entry.top_down_msgs,
+        }))
+    }
+
+    fn append(&self, entry: &CommittedFinality) -> anyhow::Result<()> {
+        let height = entry.finality.height;
+        let entry = FinalityLogEntry {
+            block_height: entry.block_height,
+            parent_block_hash: entry.finality.block_hash.clone(),
+            executed_from: entry.executed_from,
+            executed_to: entry.executed_to,
+            validator_changes: to_validator_changes(&entry.validator_changes),
+            top_down_msgs: entry.top_down_msgs.clone(),
+        };
+
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let existing = self
+            .entries
+            .get(&tx, &height)
+            .context("failed to get parent finality log entry")?;
+
+        if let Some(existing) = existing {
+            // The same block can be executed again after a crash, but the log is append-only.
+            if AppStore::to_repr(&existing)? != AppStore::to_repr(&entry)? {
+                bail!("a different finality is already logged at parent height {height}");
+            }
+            return Ok(());
+        }
+
+        KVWritable::<AppStore>::with_write(&self.db, |tx| self.entries.put(tx, &height, &entry))
+            .context("failed to append to the parent finality log")
+    }
+}
+
+/// A transaction executed in a block, as recorded in the [`TxIndexDbStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIndexEntry {
+    /// The CometBFT hash of the transaction, i.e. the SHA256 hash of its bytes.
+    pub hash: Vec<u8>,
+    /// The domain specific hash of the transaction, e.g. the Ethereum one.
+    pub domain_hash: Option<Vec<u8>>,
+    pub height: BlockHeight,
+    /// The position of the transaction in the block.
+    pub index: u64,
+    pub from: Address,
+    pub to: Address,
+    pub method_num: MethodNum,
+    pub exit_code: u32,
+    pub gas_used: u64,
+}
+
+/// The keys of the transaction index, which share a namespace.
+#[derive(Serialize, Deserialize)]
+enum TxIndexKey {
+    /// The transaction under its CometBFT hash.
+    Tx(#[serde(with = "strict_bytes")] Vec<u8>),
+    /// The CometBFT hash of a transaction under its domain specific hash.
+    DomainHash(#[serde(with = "strict_bytes")] Vec<u8>),
+    /// The number of transactions matching a filter.
+    Count(TxIndexFilter),
+    /// The CometBFT hash of the transaction matching a filter at a position, oldest first.
+    Nth(TxIndexFilter, u64),
+}
+
+/// A transaction, as stored in the database under its CometBFT hash.
+#[derive(Serialize, Deserialize)]
+struct IndexedTxData {
+    /// Empty if the transaction has no domain specific hash.
+    #[serde(with = "strict_bytes")]
+    domain_hash: Vec<u8>,
+    height: BlockHeight,
+    index: u64,
+    from: Address,
+    to: Address,
+    method_num: MethodNum,
+    exit_code: u32,
+    gas_used: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TxHash(#[serde(with = "strict_bytes")] Vec<u8>);
+
+/// An optional index of the executed transactions in RocksDB, by hash, sender, recipient
+/// and method, so they can be looked up without the CometBFT transaction index.
+///
+/// Like the parent finality log, nothing is ever pruned from it.
+pub struct TxIndexDbStore {
+    db: RocksDb,
+    txs: KVCollection<AppStore, TxIndexKey, IndexedTxData>,
+    hashes: KVCollection<AppStore, TxIndexKey, TxHash>,
+    counts: KVCollection<AppStore, TxIndexKey, u64>,
+}
+
+impl TxIndexDbStore {
+    pub fn new(db: RocksDb, ns: String) -> Self {
+        Self {
+            db,
+            txs: KVCollection::new(ns.clone()),
+            hashes: KVCollection::new(ns.clone()),
+            counts: KVCollection::new(ns),
+        }
+    }
+
+    /// Index the transactions of a committed block, in one go.
+    pub fn index(&self, entries: &[TxIndexEntry]) -> anyhow::Result<()> {
+        KVWritable::<AppStore>::with_write(&self.db, |tx| {
+            for entry in entries {
+                let key = TxIndexKey::Tx(entry.hash.clone());
+                // The same block can be committed again after a crash.
+                if self.txs.get(tx, &key)?.is_some() {
+                    continue;
+                }
+                let data = IndexedTxData {
+                    domain_hash: entry.domain_hash.clone().unwrap_or_default(),
+                    height: entry.height,
+                    index: entry.index,
+                    from: entry.from,
+                    to: entry.to,
+                    method_num: entry.method_num,
+                    exit_code: entry.exit_code,
+                    gas_used: entry.gas_used,
+                };
+                self.txs.put(tx, &key, &data)?;
+
+                let hash = TxHash(entry.hash.clone());
+                if let Some(ref h) = entry.domain_hash {
+                    self.hashes
+                        .put(tx, &TxIndexKey::DomainHash(h.clone()), &hash)?;
+                }
+                for filter in [
+                    TxIndexFilter::Sender(entry.from),
+                    TxIndexFilter::Recipient(entry.to),
+                    TxIndexFilter::Method(entry.method_num),
+                ] {
+                    let count_key = TxIndexKey::Count(filter.clone());
+                    let count = self.counts.get(tx, &count_key)?.unwrap_or_default();
+                    self.hashes
+                        .put(tx, &TxIndexKey::Nth(filter, count), &hash)?;
+                    self.counts.put(tx, &count_key, &(count + 1))?;
+                }
+            }
+            Ok(())
+        })
+        .context("failed to index transactions")
+    }
+
+    /// Look up a transaction by its CometBFT or its domain specific hash.
+    pub fn get(&self, hash: &[u8]) -> anyhow::Result<Option<TxIndexEntry>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        if let Some(entry) = self.get_tx(&tx, hash)? {
+            return Ok(Some(entry));
+        }
+        let Some(TxHash(hash)) = self
+            .hashes
+            .get(&tx, &TxIndexKey::DomainHash(hash.to_vec()))
+            .context("failed to get transaction hash")?
+        else {
+            return Ok(None);
+        };
+        self.get_tx(&tx, &hash)
+    }
+
+    /// List the transactions matching a filter, newest first.
+    pub fn list(
+        &self,
+        filter: &TxIndexFilter,
+        skip: u64,
+        limit: u64,
+    ) -> anyhow::Result<Vec<TxIndexEntry>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let count = self
+            .counts
+            .get(&tx, &TxIndexKey::Count(filter.clone()))
+            .context("failed to get transaction count")?
+            .unwrap_or_default();
+
+        let mut entries = Vec::new();
+        for n in (0..count.saturating_sub(skip)).rev().take(limit as usize) {
+            let hash = self
+                .hashes
+                .get(&tx, &TxIndexKey::Nth(filter.clone(), n))
+                .context("failed to get transaction hash")?
+                .ok_or_else(|| anyhow!("transaction {n} of the index is missing"))?;
+            let entry = self
+                .get_tx(&tx, &hash.0)?
+                .ok_or_else(|| anyhow!("transaction {n} of the index is missing"))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    fn get_tx(
+        &self,
+        tx: &impl KVRead<AppStore>,
+        hash: &[u8],
+    ) -> anyhow::Result<Option<TxIndexEntry>> {
+        let data = self
+            .txs
+            .get(tx, &TxIndexKey::Tx(hash.to_vec()))
+            .context("failed to get indexed transaction")?;
+
+        Ok(data.map(|d| TxIndexEntry {
+            hash: hash.to_vec(),
+            domain_hash: Some(d.domain_hash).filter(|h| !h.is_empty()),
+            height: d.height,
+            index: d.index,
+            from: d.from,
+            to: d.to,
+            method_num: d.method_num,
+            exit_code: d.exit_code,
+            gas_used: d.gas_used,
+        }))
+    }
+}
+
+/// The keys of the execution traces, which share a namespace.
+#[derive(Serialize, Deserialize)]
+enum ExecTraceKey {
+    /// The trace of a transaction under its CometBFT hash.
+    Tx(#[serde(with = "strict_bytes")] Vec<u8>),
+    /// The CometBFT hashes of the transactions traced in a block, to prune them by height.
+    Block(BlockHeight),
+}
+
+/// The execution traces of the transactions delivered by a node running with tracing enabled.
+///
+/// The traces of blocks older than `history` are pruned as new blocks are added, unless it's zero.
+pub struct ExecTraceDbStore {
+    db: RocksDb,
+    traces: KVCollection<AppStore, ExecTraceKey, CallTrace>,
+    blocks: KVCollection<AppStore, ExecTraceKey, Vec<TxHash>>,
+    history: BlockHeight,
+}
+
+impl ExecTraceDbStore {
+    pub fn new(db: RocksDb, ns: String, history: BlockHeight) -> Self {
+        Self {
+            db,
+            traces: KVCollection::new(ns.clone()),
+            blocks: KVCollection::new(ns),
+            history,
+        }
+    }
+
+    /// Store the traces of the transactions of a committed block, under their CometBFT hashes,
+    /// and prune the block falling out of the history.
+    pub fn put_block(
+        &self,
+        height: BlockHeight,
+        traces: Vec<(Vec<u8>, CallTrace)>,
+    ) -> anyhow::Result<()> {
+        KVWritable::<AppStore>::with_write(&self.db, |tx| {
+            let mut hashes = Vec::new();
+            for (hash, trace) in traces {
+                self.traces
+                    .put(tx, &ExecTraceKey::Tx(hash.clone()), &trace)?;
+                hashes.push(TxHash(hash));
+            }
+            if !hashes.is_empty() {
+                self.blocks.put(tx, &ExecTraceKey::Block(height), &hashes)?;
+            }
+
+            if self.history > 0 && height > self.history {
+                let key = ExecTraceKey::Block(height - self.history);
+                if let Some(hashes) = self.blocks.get(tx, &key)? {
+                    for TxHash(hash) in hashes {
+                        self.traces.delete(tx, &ExecTraceKey::Tx(hash))?;
+                    }
+                    self.blocks.delete(tx, &key)?;
+                }
+            }
+            Ok(())
+        })
+        .context("failed to store execution traces")
+    }
+
+    /// Look up the trace of a transaction by its CometBFT hash.
+    pub fn get(&self, hash: &[u8]) -> anyhow::Result<Option<CallTrace>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        self.traces
+            .get(&tx, &ExecTraceKey::Tx(hash.to_vec()))
+            .context("failed to get execution trace")
+    }
+}
+
+/// The keys of the log index, which share a namespace.
+#[derive(Serialize, Deserialize)]
+enum LogIndexKey {
+    /// The ranges of blocks indexed, inclusive; the ones outside them have to be checked for logs.
+    Ranges,
+    /// The bloom filter of the logs of a block, if it had any.
+    Block(BlockHeight),
+}
+
+#[derive(Serialize, Deserialize)]
+struct BloomBytes(#[serde(with = "strict_bytes")] Vec<u8>);
+
+/// An optional index of the Ethereum logs emitted in each block, as bloom filters of their
+/// addresses and topics, to find the blocks matching a log filter without scanning a range.
+pub struct LogIndexDbStore {
+    db: RocksDb,
+    ranges: KVCollection<AppStore, LogIndexKey, Vec<(BlockHeight, BlockHeight)>>,
+    blooms: KVCollection<AppStore, LogIndexKey, BloomBytes>,
+}
+
+impl LogIndexDbStore {
+    pub fn new(db: RocksDb, ns: String) -> Self {
+        Self {
+            db,
+            ranges: KVCollection::new(ns.clone()),
+            blooms: KVCollection::new(ns),
+        }
+    }
+
+    /// Store the bloom filter of the logs of a committed block.
+    ///
+    /// Blocks without logs have no bloom filter, so the block is also added to the indexed
+    /// ranges, which tell them apart from the blocks which were never indexed, e.g. because
+    /// the index was disabled for a while, or the node failed to write it.
+    pub fn put_block(&self, height: BlockHeight, bloom: &Bloom) -> anyhow::Result<()> {
+        KVWritable::<AppStore>::with_write(&self.db, |tx| {
+            let mut ranges = self
+                .ranges
+                .get(tx, &LogIndexKey::Ranges)?
+                .unwrap_or_default();
+            match ranges.last_mut() {
+                Some((_, to)) if *to + 1 == height => *to = height,
+                Some((_, to)) if *to >= height => {}
+                _ => ranges.push((height, height)),
+            }
+            self.ranges.put(tx, &LogIndexKey::Ranges, &ranges)?;
+
+            if !bloom.is_zero() {
+                self.blooms.put(
+                    tx,
+                    &LogIndexKey::Block(height),
+                    &BloomBytes(bloom.as_bytes().to_vec()),
+                )?;
+            }
+            Ok(())
+        })
+        .context("failed to index logs")
+    }
+
+    /// The heights in a range which may have logs matching the filter in the query,
+    /// including the ones which were not indexed.
+    pub fn matching_heights(&self, query: &LogIndexQuery) -> anyhow::Result<Vec<BlockHeight>> {
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let ranges = self
+            .ranges
+            .get(&tx, &LogIndexKey::Ranges)
+            .context("failed to get indexed ranges")?
+            .unwrap_or_default();
+
+        let mut heights = Vec::new();
+        for height in query.from_height..=query.to_height {
+            if !ranges
+                .iter()
+                .any(|(from, to)| *from <= height && height <= *to)
+            {
+                heights.push(height);
+                continue;
+            }
+            let bloom = self
+                .blooms
+                .get(&tx, &LogIndexKey::Block(height))
+                .context("failed to get logs bloom")?;
+            if let Some(BloomBytes(bz)) = bloom {
+                if bz.len() == 256
+                    && bloom::may_match(&Bloom::from_slice(&bz), &query.addresses, &query.topics)
+                {
+                    heights.push(height);
+                }
+            }
+        }
+        Ok(heights)
+    }
+}
+
+/// The resources used by the actors in the transactions of each block, of a node running with
+/// actor statistics enabled, to find out which ones dominate the chain.
+///
+/// The statistics of blocks older than `history` are pruned as new blocks are added, unless it's zero.
+pub struct ActorStatsDbStore {
+    db: RocksDb,
+    blocks: KVCollection<AppStore, BlockHeight, Vec<ActorStats>>,
+    history: BlockHeight,
+}
+
+impl ActorStatsDbStore {
+    pub fn new(db: RocksDb, ns: String, history: BlockHeight) -> Self {
+        Self {
+            db,
+            blocks: KVCollection::new(ns),
+            history,
+        }
+    }
+
+    /// Store the statistics of a committed block and prune the block falling out of the history.
+    pub fn put_block(&self, height: BlockHeight, stats: Vec<ActorStats>) -> anyhow::Result<()> {
+        KVWritable::<AppStore>::with_write(&self.db, |tx| {
+            if !stats.is_empty() {
+                self.blocks.put(tx, &height, &stats)?;
+            }
+            if self.history > 0 && height > self.history {
+                self.blocks.delete(tx, &(height - self.history))?;
+            }
+            Ok(())
+        })
+        .context("failed to store actor statistics")
+    }
+
+    /// Add up the statistics of the last `blocks` blocks up to a height, limited to the history,
+    /// returning the `limit` actors using the most gas, or all of them if it's zero.
+    pub fn report(
+        &self,
+        to_height: BlockHeight,
+        blocks: u64,
+        limit: u64,
+    ) -> anyhow::Result<ActorStatsReport> {
+        let mut blocks = blocks.min(to_height);
+        if self.history > 0 {
+            blocks = blocks.min(self.history);
+        }
+        let from_height = to_height + 1 - blocks.max(1);
+
+        let tx = KVReadable::<AppStore>::read(&self.db);
+        let mut totals = BTreeMap::<Address, ActorStats>::new();
+        for height in from_height..=to_height {
+            let stats = self
+                .blocks
+                .get(&tx, &height)
+                .context("failed to get actor statistics")?;
+            for s in stats.unwrap_or_default() {
+                match totals.get_mut(&s.actor) {
+                    Some(total) => total.add(&s),
+                    None => {
+                        totals.insert(s.actor, s);
+                    }
+                }
+            }
+        }
+
+        let mut actors = totals.into_values().collect::<Vec<_>>();
+        actors.sort_by(|a, b| b.gas_used.cmp(&a.gas_used));
+        if limit > 0 {
+            actors.truncate(limit as usize);
+        }
+
+        Ok(ActorStatsReport {
+            from_height,
+            to_height,
+            actors,
+        })
+    }
+}
+
+fn to_validator_changes(changes: &[StakingChangeRequest]) -> Vec<ValidatorChange> {
+    changes
+        .iter()
+        .map(|c| ValidatorChange {
+            configuration_number: c.configuration_number,
+            op: c.change.op.clone().into(),
+            payload: c.change.payload.clone(),
+            validator: c.change.validator,
+        })
+        .collect()
+}
+
+fn from_validator_changes(
+    changes: Vec<ValidatorChange>,
+) -> anyhow::Result<Vec<StakingChangeRequest>> {
+    changes
+        .into_iter()
+        .map(|c| {
+            let op = StakingOperation::try_from(c.op)
+                .map_err(|e| anyhow!("invalid staking operation: {e}"))?;
+            Ok(StakingChangeRequest {
+                configuration_number: c.configuration_number,
+                change: StakingChange {
+                    op,
+                    payload: c.payload,
+                    validator: c.validator,
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_rocksdb::{RocksDb, RocksDbConfig};
+    use fendermint_vm_topdown::proxy::BlockHashStore;
+    use ipc_provider::manager::GetBlockHashResult;
+
+    use super::{
+        ExecTraceDbStore, ParentBlockHashStore, ParentFinalityLogDbStore, ParentViewDbStore,
+        TxIndexDbStore, TxIndexEntry,
+    };
+
+    #[test]
+    fn parent_block_hash_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "parent_block_hash";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = ParentBlockHashStore::new(db, ns.to_string());
+
+        assert!(store.get(10).unwrap().is_none());
+
+        let hash = GetBlockHashResult {
+            parent_block_hash: vec![1; 32],
+            block_hash: vec![2; 32],
+        };
+        store.put(10, &hash).unwrap();
+
+        let cached = store.get(10).unwrap().expect("hash was cached");
+        assert_eq!(cached.parent_block_hash, hash.parent_block_hash);
+        assert_eq!(cached.block_hash, hash.block_hash);
+        assert!(store.get(11).unwrap().is_none());
+    }
+
+    #[test]
+    fn parent_view_roundtrip() {
+        use fendermint_vm_topdown::sync::ParentViewStore;
+        use fvm_shared::address::Address;
+        use ipc_api::staking::{StakingChange, StakingChangeRequest, StakingOperation};
+
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "parent_view";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = ParentViewDbStore::new(db, ns.to_string(), 10);
+
+        assert!(store.get(10).unwrap().is_none());
+
+        let change = StakingChangeRequest {
+            configuration_number: 5,
+            change: StakingChange {
+                op: StakingOperation::SetFederatedPower,
+                payload: vec![1, 2, 3],
+                validator: Address::new_id(100),
+            },
+        };
+        store
+            .put(10, Some(&(vec![2; 32], vec![change], vec![])))
+            .unwrap();
+        store.put(11, None).unwrap();
+
+        let (block_hash, changes, msgs) = store.get(10).unwrap().unwrap().unwrap();
+        assert_eq!(block_hash, vec![2; 32]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].configuration_number, 5);
+        assert!(matches!(
+            changes[0].change.op,
+            StakingOperation::SetFederatedPower
+        ));
+        assert_eq!(changes[0].change.payload, vec![1, 2, 3]);
+        assert!(msgs.is_empty());
+
+        // null round
+        assert!(store.get(11).unwrap().unwrap().is_none());
+
+        // height 20 takes the slot of height 10
+        store.put(20, None).unwrap();
+        assert!(store.get(10).unwrap().is_none());
+        assert!(store.get(20).unwrap().is_some());
+
+        // a reorg drops everything above the fork point
+        store.remove_above(11).unwrap();
+        assert!(store.get(11).unwrap().is_some());
+        assert!(store.get(20).unwrap().is_none());
+    }
+
+    #[test]
+    fn parent_finality_log_append_only() {
+        use fendermint_vm_topdown::{CommittedFinality, IPCParentFinality, ParentFinalityLog};
+
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "parent_finality_log";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = ParentFinalityLogDbStore::new(db, ns.to_string());
+
+        assert!(store.get(10).unwrap().is_none());
+
+        let entry = CommittedFinality {
+            block_height: 100,
+            finality: IPCParentFinality {
+                height: 10,
+                block_hash: vec![1; 32],
+            },
+            executed_from: 5,
+            executed_to: 9,
+            validator_changes: vec![],
+            top_down_msgs: vec![],
+        };
+        store.append(&entry).unwrap();
+
+        let logged = store.get(10).unwrap().expect("finality was logged");
+        assert_eq!(logged.block_height, 100);
+        assert_eq!(logged.finality, entry.finality);
+        assert_eq!(logged.executed_from, 5);
+        assert_eq!(logged.executed_to, 9);
+
+        // re-executing the block logs the same entry again
+        store.append(&entry).unwrap();
+
+        // but it can't be changed
+        let mut other = entry.clone();
+        other.finality.block_hash = vec![2; 32];
+        assert!(store.append(&other).is_err());
+        assert_eq!(store.get(10).unwrap().unwrap().finality, entry.finality);
+    }
+
+    #[test]
+    fn tx_index_lookup() {
+        use fendermint_vm_message::query::TxIndexFilter;
+        use fvm_shared::address::Address;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "tx_index";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = TxIndexDbStore::new(db, ns.to_string());
+
+        let sender = Address::new_id(100);
+        let entries = (0..3)
+            .map(|i| TxIndexEntry {
+                hash: vec![i; 32],
+                domain_hash: (i == 0).then(|| vec![9; 32]),
+                height: 10,
+                index: i as u64,
+                from: sender,
+                to: Address::new_id(200 + i as u64),
+                method_num: 2,
+                exit_code: 0,
+                gas_used: 1000,
+            })
+            .collect::<Vec<_>>();
+        store.index(&entries).unwrap();
+        // committing the block again doesn't index it twice
+        store.index(&entries).unwrap();
+
+        assert_eq!(store.get(&[0; 32]).unwrap(), Some(entries[0].clone()));
+        assert_eq!(store.get(&[9; 32]).unwrap(), Some(entries[0].clone()));
+        assert!(store.get(&[7; 32]).unwrap().is_none());
+
+        let sent = store.list(&TxIndexFilter::Sender(sender), 0, 10).unwrap();
+        assert_eq!(
+            sent,
+            vec![entries[2].clone(), entries[1].clone(), entries[0].clone()]
+        );
+
+        let sent = store.list(&TxIndexFilter::Sender(sender), 1, 1).unwrap();
+        assert_eq!(sent, vec![entries[1].clone()]);
+
+        let received = store
+            .list(&TxIndexFilter::Recipient(Address::new_id(201)), 0, 10)
+            .unwrap();
+        assert_eq!(received, vec![entries[1].clone()]);
+        assert!(store
+            .list(&TxIndexFilter::Method(3), 0, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn exec_traces_pruned() {
+        use fendermint_vm_message::query::CallTrace;
+        use fvm_shared::{address::Address, econ::TokenAmount};
+
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "exec_traces";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = ExecTraceDbStore::new(db, ns.to_string(), 2);
+
+        let trace = CallTrace {
+            from: Address::new_id(100),
+            to: Address::new_id(200),
+            method_num: 2,
+            params: String::new(),
+            value: TokenAmount::from_atto(0),
+            gas_limit: 1000,
+            gas_used: 100,
+            read_only: false,
+            exit_code: 0,
+            return_data: String::new(),
+            error: None,
+            calls: Vec::new(),
+        };
+
+        store
+            .put_block(10, vec![(vec![1; 32], trace.clone())])
+            .unwrap();
+        store
+            .put_block(11, vec![(vec![2; 32], trace.clone())])
+            .unwrap();
+        assert_eq!(store.get(&[1; 32]).unwrap(), Some(trace.clone()));
+
+        // height 12 prunes height 10
+        store.put_block(12, vec![]).unwrap();
+        assert!(store.get(&[1; 32]).unwrap().is_none());
+        assert_eq!(store.get(&[2; 32]).unwrap(), Some(trace));
+    }
+
+    #[test]
+    fn actor_stats_added_up() {
+        use fendermint_vm_message::query::ActorStats;
+        use fvm_shared::address::Address;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "actor_stats";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = ActorStatsDbStore::new(db, ns.to_string(), 3);
+
+        let stats = |id, gas_used| ActorStats {
+            actor: Address::new_id(id),
+            calls: 1,
+            gas_used,
+            state_bytes: 10,
+        };
+
+        store.put_block(1, vec![stats(100, 1000)]).unwrap();
+        store
+            .put_block(2, vec![stats(100, 10), stats(200, 500)])
+            .unwrap();
+        store.put_block(3, vec![stats(200, 500)]).unwrap();
+
+        let report = store.report(3, 10, 0).unwrap();
+        assert_eq!(report.from_height, 1);
+        assert_eq!(report.to_height, 3);
+        assert_eq!(report.actors.len(), 2);
+        assert_eq!(report.actors[0].actor, Address::new_id(100));
+        assert_eq!(report.actors[0].gas_used, 1010);
+        assert_eq!(report.actors[0].calls, 2);
+        assert_eq!(report.actors[1].gas_used, 1000);
+        assert_eq!(report.actors[1].state_bytes, 20);
+
+        let report = store.report(3, 2, 1).unwrap();
+        assert_eq!(report.from_height, 2);
+        assert_eq!(report.actors.len(), 1);
+        assert_eq!(report.actors[0].actor, Address::new_id(200));
+        assert_eq!(report.actors[0].gas_used, 1000);
+
+        // height 4 prunes height 1
+        store.put_block(4, vec![]).unwrap();
+        let report = store.report(4, 10, 0).unwrap();
+        assert_eq!(report.from_height, 2);
+        assert_eq!(report.actors[0].actor, Address::new_id(200));
+        assert_eq!(report.actors[1].gas_used, 10);
+    }
+
+    #[test]
+    fn log_index_matching_heights() {
+        use ethers_core::types::{self as et, Bloom};
+        use fendermint_vm_message::bloom::accrue_log;
+        use fendermint_vm_message::query::LogIndexQuery;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ns = "log_index";
+        let db = RocksDb::open_cf(dir.path(), &RocksDbConfig::default(), [ns].iter()).unwrap();
+        let store = LogIndexDbStore::new(db, ns.to_string());
+
+        let address = et::H160::repeat_byte(1);
+        let topic = et::H256::repeat_byte(2);
+        let mut bloom = Bloom::zero();
+        accrue_log(&mut bloom, &address, &[topic]);
+
+        store.put_block(3, &Bloom::zero()).unwrap();
+        store.put_block(4, &bloom).unwrap();
+        store.put_block(5, &Bloom::zero()).unwrap();
+        // height 6 was not indexed, e.g. because the node failed to write it
+        store.put_block(7, &Bloom::zero()).unwrap();
+
+        let query = |addresses, topics| LogIndexQuery {
+            from_height: 1,
+            to_height: 8,
+            addresses,
+            topics,
+        };
+
+        // the blocks which were not indexed are always included
+        assert_eq!(
+            store.matching_heights(&query(vec![], vec![])).unwrap(),
+            vec![1, 2, 4, 6, 8]
+        );
+        assert_eq!(
+            store
+                .matching_heights(&query(vec![address], vec![vec![topic]]))
+                .unwrap(),
+            vec![1, 2, 4, 6, 8]
+        );
+        assert_eq!(
+            store
+                .matching_heights(&query(vec![et::H160::repeat_byte(3)], vec![]))
+                .unwrap(),
+            vec![1, 2, 6, 8]
+        );
+    }
+}
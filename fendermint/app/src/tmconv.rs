@@ -262,6 +262,9 @@ pub fn to_query(ret: FvmQueryRet, block_height: BlockHeight) -> anyhow::Result<r
         FvmQueryRet::Call(_) | FvmQueryRet::EstimateGas(_) => ExitCode::OK,
         FvmQueryRet::StateParams(_) => ExitCode::OK,
         FvmQueryRet::BuiltinActors(_) => ExitCode::OK,
+        // Like calls, a failed execution is still a successful trace.
+        FvmQueryRet::TraceCall(_) => ExitCode::OK,
+        FvmQueryRet::ActorProof(_) => ExitCode::OK,
     };
 
     // The return value has a `key` field which is supposed to be set to the data matched.
@@ -302,6 +305,14 @@ pub fn to_query(ret: FvmQueryRet, block_height: BlockHeight) -> anyhow::Result<r
             let v = ipld_encode!(ba);
             (Vec::new(), v)
         }
+        FvmQueryRet::TraceCall(trace) => {
+            let v = ipld_encode!(trace);
+            (Vec::new(), v)
+        }
+        FvmQueryRet::ActorProof(proof) => {
+            let v = ipld_encode!(proof);
+            (Vec::new(), v)
+        }
     };
 
     // The height here is the height of the block that was committed, not in which the app hash appeared.
@@ -433,6 +444,7 @@ pub fn from_snapshot(
         size: metadata.size,
         chunks: offer.snapshot.chunks,
         checksum,
+        chunk_checksums: Vec::new(),
         state_params: metadata.state_params,
         version: offer.snapshot.format,
     };
@@ -7,7 +7,7 @@ use base64::{alphabet, Engine};
 use rand::Rng;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-pub use libsecp256k1::{PublicKey, RecoveryId, Signature};
+pub use libsecp256k1::{recover, verify, Message, PublicKey, RecoveryId, Signature};
 
 /// A [`GeneralPurpose`] engine using the [`alphabet::STANDARD`] base64 alphabet
 /// padding bytes when writing but requireing no padding when reading.
@@ -0,0 +1,292 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Geth style tracing of transactions and calls, based on the calls the FVM records while executing them.
+//!
+//! See <https://geth.ethereum.org/docs/interacting-with-geth/rpc/ns-debug>
+
+use anyhow::Context;
+use ethers_core::types as et;
+use fendermint_rpc::query::QueryClient;
+use fendermint_rpc::response::decode_fevm_return_data;
+use fendermint_vm_actor_interface::eam::EAM_ACTOR_ADDR;
+use fendermint_vm_message::query::CallTrace;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::error::ExitCode;
+use jsonrpc_v2::Params;
+use serde::{Deserialize, Serialize};
+use tendermint_rpc::Client;
+
+use super::eth::TypedTransactionCompat;
+use crate::conv::from_eth::to_fvm_message;
+use crate::conv::from_fvm::{to_eth_address, to_eth_tokens};
+use crate::{error, JsonRpcData, JsonRpcResult};
+
+/// The name of the tracer returning the tree of calls.
+const CALL_TRACER: &str = "callTracer";
+
+/// Options of the tracing methods; the calls are returned by the struct logger unless another tracer is chosen.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    pub tracer: Option<String>,
+    #[serde(default)]
+    pub tracer_config: TracerConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TracerConfig {
+    /// Only return the call made by the message itself, without the calls it made in turn.
+    #[serde(default)]
+    pub only_top_call: bool,
+}
+
+/// A call as returned by the `callTracer`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: et::H160,
+    /// Empty for contract creation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<et::H160>,
+    pub value: et::U256,
+    pub gas: et::U64,
+    pub gas_used: et::U64,
+    pub input: et::Bytes,
+    pub output: et::Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+/// The result of the struct logger.
+///
+/// The FVM doesn't record the EVM opcodes, so `structLogs` is always empty;
+/// use the `callTracer` to see what happened during the execution.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    pub gas: u64,
+    pub failed: bool,
+    /// Hex encoded return value, without a `0x` prefix.
+    pub return_value: String,
+    pub struct_logs: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Trace {
+    Call(CallFrame),
+    StructLog(StructLogTrace),
+}
+
+/// Returns the trace of an executed transaction.
+///
+/// The node only keeps the traces if it runs with `db.exec_traces` enabled, and it keeps them
+/// under the CometBFT hash of the transactions, so the Ethereum hash is first resolved to the
+/// CometBFT transaction it was delivered in. Failing that, the hash is tried as a CometBFT one.
+pub async fn trace_transaction<C>(
+    data: JsonRpcData<C>,
+    Params(params): Params<TraceTransactionParams>,
+) -> JsonRpcResult<Trace>
+where
+    C: Client + Sync + Send,
+{
+    let (tx_hash, opts) = match params {
+        TraceTransactionParams::One((tx_hash,)) => (tx_hash, TraceOptions::default()),
+        TraceTransactionParams::Two((tx_hash, opts)) => (tx_hash, opts),
+    };
+
+    // The traces are stored under the CometBFT hash, not the Ethereum one.
+    let hash = match data.tx_by_hash(tx_hash).await? {
+        Some(res) => res.hash.as_bytes().to_vec(),
+        None => tx_hash.as_bytes().to_vec(),
+    };
+
+    let trace = data
+        .client
+        .exec_trace(&hash)
+        .await
+        .context("failed to query execution trace")?;
+
+    match trace {
+        Some(trace) => to_trace(trace, &opts),
+        None => error(
+            ExitCode::USR_NOT_FOUND,
+            "transaction trace not found; traces are only kept by nodes with `db.exec_traces` enabled, for transactions that can be found by their Ethereum hash",
+        ),
+    }
+}
+
+/// Executes a call on top of the state at a block and returns its trace,
+/// without creating a transaction on the block chain.
+pub async fn trace_call<C>(
+    data: JsonRpcData<C>,
+    Params(params): Params<TraceCallParams>,
+) -> JsonRpcResult<Trace>
+where
+    C: Client + Sync + Send,
+{
+    let (tx, block_id, opts) = match params {
+        TraceCallParams::One((tx,)) => (
+            tx,
+            et::BlockId::Number(et::BlockNumber::Latest),
+            TraceOptions::default(),
+        ),
+        TraceCallParams::Two((tx, block_id)) => (tx, block_id, TraceOptions::default()),
+        TraceCallParams::Three((tx, block_id, opts)) => (tx, block_id, opts),
+    };
+
+    let mut msg = to_fvm_message(tx.into(), true).context("failed to convert to FVM message")?;
+    data.cap_call_gas(&mut msg);
+    let height = data.query_height(block_id).await?;
+    let response = data
+        .with_call_timeout(data.client.trace_call(msg, height))
+        .await?;
+
+    match response.value {
+        Some(trace) => to_trace(trace, &opts),
+        None => error(
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            "the message failed before calling its recipient",
+        ),
+    }
+}
+
+fn to_trace(trace: CallTrace, opts: &TraceOptions) -> JsonRpcResult<Trace> {
+    match opts.tracer.as_deref() {
+        None => {
+            let output = unwrap_bytes(&trace.return_data)?;
+            Ok(Trace::StructLog(StructLogTrace {
+                gas: trace.gas_used,
+                failed: trace.exit_code != 0 || trace.error.is_some(),
+                return_value: hex::encode(output),
+                struct_logs: Vec::new(),
+            }))
+        }
+        Some(CALL_TRACER) => Ok(Trace::Call(to_call_frame(
+            trace,
+            opts.tracer_config.only_top_call,
+        )?)),
+        Some(other) => error(
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            format!("unsupported tracer: {other}"),
+        ),
+    }
+}
+
+fn to_call_frame(trace: CallTrace, only_top_call: bool) -> anyhow::Result<CallFrame> {
+    let call_type = if trace.to == EAM_ACTOR_ADDR {
+        "CREATE"
+    } else if trace.read_only {
+        "STATICCALL"
+    } else {
+        "CALL"
+    };
+
+    let error = trace.error.or_else(|| {
+        (trace.exit_code != 0)
+            .then(|| format!("execution failed with exit code {}", trace.exit_code))
+    });
+
+    let calls = if only_top_call {
+        Vec::new()
+    } else {
+        trace
+            .calls
+            .into_iter()
+            .map(|call| to_call_frame(call, false))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    Ok(CallFrame {
+        call_type: call_type.to_string(),
+        // Addresses which can't be expressed in Ethereum format, e.g. f1 accounts, are left empty.
+        from: to_eth_address(&trace.from)
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+        to: to_eth_address(&trace.to).ok().flatten(),
+        value: to_eth_tokens(&trace.value)?,
+        gas: et::U64::from(trace.gas_limit),
+        gas_used: et::U64::from(trace.gas_used),
+        input: unwrap_bytes(&trace.params)?.into(),
+        output: unwrap_bytes(&trace.return_data)?.into(),
+        error,
+        calls,
+    })
+}
+
+/// Decode the hex encoded parameters or return value of a call.
+///
+/// Contracts take and return IPLD encoded bytes; anything else is returned as it is.
+pub(super) fn unwrap_bytes(data: &str) -> anyhow::Result<Vec<u8>> {
+    let bz = hex::decode(data).context("invalid hex data in trace")?;
+    Ok(decode_fevm_return_data(RawBytes::new(bz.clone())).unwrap_or(bz))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum TraceTransactionParams {
+    One((et::H256,)),
+    Two((et::H256, TraceOptions)),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum TraceCallParams {
+    One((TypedTransactionCompat,)),
+    Two((TypedTransactionCompat, et::BlockId)),
+    Three((TypedTransactionCompat, et::BlockId, TraceOptions)),
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_vm_message::query::CallTrace;
+    use fvm_ipld_encoding::BytesSer;
+    use fvm_shared::{address::Address, econ::TokenAmount};
+
+    use super::{to_call_frame, unwrap_bytes};
+
+    fn call(to: u64, calls: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            from: Address::new_id(100),
+            to: Address::new_id(to),
+            method_num: 3844450837,
+            params: hex::encode(fvm_ipld_encoding::to_vec(&BytesSer(&[1, 2, 3])).unwrap()),
+            value: TokenAmount::from_atto(5),
+            gas_limit: 1000,
+            gas_used: 500,
+            read_only: false,
+            exit_code: 0,
+            return_data: String::new(),
+            error: None,
+            calls,
+        }
+    }
+
+    #[test]
+    fn call_frames() {
+        let trace = call(200, vec![call(300, vec![])]);
+
+        let frame = to_call_frame(trace.clone(), false).unwrap();
+        assert_eq!(frame.call_type, "CALL");
+        assert_eq!(frame.input.to_vec(), vec![1, 2, 3]);
+        assert_eq!(frame.value.as_u64(), 5);
+        assert!(frame.error.is_none());
+        assert_eq!(frame.calls.len(), 1);
+
+        let frame = to_call_frame(trace, true).unwrap();
+        assert!(frame.calls.is_empty());
+    }
+
+    #[test]
+    fn unwrap_raw_bytes() {
+        assert_eq!(unwrap_bytes("").unwrap(), Vec::<u8>::new());
+        assert_eq!(unwrap_bytes("ff").unwrap(), vec![0xff]);
+    }
+}
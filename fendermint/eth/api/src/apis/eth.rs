@@ -6,8 +6,6 @@
 // * https://github.com/filecoin-project/lotus/blob/v1.23.1-rc2/api/api_full.go#L783
 // * https://github.com/filecoin-project/lotus/blob/v1.23.1-rc2/node/impl/full/eth.go
 
-use std::collections::HashSet;
-
 use anyhow::Context;
 use ethers_core::types::transaction::eip2718::TypedTransaction;
 use ethers_core::types::{self as et, BlockNumber};
@@ -15,19 +13,22 @@ use ethers_core::utils::rlp;
 use fendermint_rpc::message::SignedMessageFactory;
 use fendermint_rpc::query::QueryClient;
 use fendermint_rpc::response::{decode_data, decode_fevm_invoke, decode_fevm_return_data};
-use fendermint_vm_actor_interface::eam::{EthAddress, EAM_ACTOR_ADDR};
+use fendermint_vm_actor_interface::eam::EAM_ACTOR_ADDR;
 use fendermint_vm_actor_interface::evm;
 use fendermint_vm_message::chain::ChainMessage;
-use fendermint_vm_message::query::FvmQueryHeight;
+use fendermint_vm_message::query::{
+    FvmQueryHeight, LogIndexQuery, StateParams, MAX_ACTOR_PROOF_KEYS,
+};
 use fendermint_vm_message::signed::SignedMessage;
 use futures::FutureExt;
 use fvm_ipld_encoding::RawBytes;
-use fvm_shared::address::Address;
 use fvm_shared::bigint::BigInt;
 use fvm_shared::crypto::signature::Signature;
-use fvm_shared::{chainid::ChainID, error::ExitCode};
+use fvm_shared::{chainid::ChainID, econ::TokenAmount, error::ExitCode};
 use jsonrpc_v2::Params;
 use rand::Rng;
+use serde::Serialize;
+use tendermint::abci::response::DeliverTx;
 use tendermint::block::Height;
 use tendermint_rpc::endpoint::{self, status};
 use tendermint_rpc::SubscriptionClient;
@@ -41,7 +42,7 @@ use fil_actors_evm_shared::uints;
 use crate::conv::from_eth::to_fvm_message;
 use crate::conv::from_tm::{self, msg_hash, to_chain_message, to_cumulative, to_eth_block_zero};
 use crate::error::error_with_revert;
-use crate::filters::{matches_topics, FilterId, FilterKind, FilterRecords};
+use crate::filters::{matches_address, matches_topics, FilterId, FilterKind, FilterRecords};
 use crate::{
     conv::{
         from_eth::to_fvm_address,
@@ -94,28 +95,15 @@ where
 {
     // get the latest block
     let res: block::Response = data.tm().latest_block().await?;
-    let latest_h = res.block.header.height;
-
-    // get consensus params to fetch block gas limit
-    // (this just needs to be done once as we assume that is constant
-    // for all blocks)
-    let consensus_params: consensus_params::Response = data
-        .tm()
-        .consensus_params(latest_h)
-        .await
-        .context("failed to get consensus params")?;
-    let mut block_gas_limit = consensus_params.consensus_params.block.max_gas;
-    if block_gas_limit <= 0 {
-        block_gas_limit =
-            i64::try_from(fvm_shared::BLOCK_GAS_LIMIT).expect("FVM block gas limit not i64")
-    };
+    let latest_h: u64 = res.block.header.height.into();
 
     let mut premiums = Vec::new();
-    // iterate through the blocks in the range
-    // we may be able to de-duplicate a lot of this code from fee_history
-    let latest_h: u64 = latest_h.into();
+    let mut block_gas_limit = 0;
+    let mut blocks = 0;
+
+    // iterate through the recent blocks, newest first
     let mut blk = latest_h;
-    while blk > latest_h - data.gas_opt.num_blocks_max_prio_fee {
+    while blk > latest_h.saturating_sub(data.gas_opt.num_blocks_max_prio_fee) {
         let block = data
             .block_by_height(blk.into())
             .await
@@ -131,29 +119,30 @@ where
         let state_params = data
             .client
             .state_params(FvmQueryHeight::Height(height.value()))
-            .await?;
-
-        let base_fee = &state_params.value.base_fee;
+            .await?
+            .value;
 
         // The latest block might not have results yet.
         if let Ok(block_results) = data.tm().block_results(height).await {
             let txs_results = block_results.txs_results.unwrap_or_default();
 
-            for (tx, txres) in block.data().iter().zip(txs_results) {
-                let msg = fvm_ipld_encoding::from_slice::<ChainMessage>(tx)
-                    .context("failed to decode tx as ChainMessage")?;
+            premiums.extend(block_premiums(
+                &block,
+                &txs_results,
+                &state_params.base_fee,
+            )?);
 
-                if let ChainMessage::Signed(msg) = msg {
-                    let premium = crate::gas::effective_gas_premium(&msg.message, base_fee);
-                    premiums.push((premium, txres.gas_used));
-                }
+            // The limit of the newest block is the one in effect for the next.
+            if blocks == 0 {
+                block_gas_limit = get_block_gas_limit(&data, height, &state_params).await?;
             }
+            blocks += 1;
         }
         blk -= 1;
     }
 
     // compute median gas price
-    let mut median = crate::gas::median_gas_premium(&mut premiums, block_gas_limit);
+    let mut median = crate::gas::median_gas_premium(&mut premiums, blocks, block_gas_limit / 2);
     let min_premium = data.gas_opt.min_gas_premium.clone();
     if median < min_premium {
         median = min_premium;
@@ -168,27 +157,46 @@ where
     let coeff: u64 = ((noise * (1 << precision) as f64) as u64) + 1;
 
     median *= BigInt::from(coeff);
-    median.div_ceil(BigInt::from(1 << PRECISION));
+    let median = median.div_ceil(BigInt::from(1u64 << PRECISION));
 
     Ok(to_eth_tokens(&median)?)
 }
 
 /// Returns transaction base fee per gas and effective priority fee per gas for the requested/supported block range.
+///
+/// The rewards are the effective premiums paid at the requested percentiles of the gas used by each block.
 pub async fn fee_history<C>(
     data: JsonRpcData<C>,
-    Params((block_count, last_block, reward_percentiles)): Params<(
-        et::U256,
-        et::BlockNumber,
-        Vec<f64>,
-    )>,
+    Params(params): Params<FeeHistoryParams>,
 ) -> JsonRpcResult<et::FeeHistory>
 where
     C: Client + Sync + Send,
 {
+    let (block_count, last_block, reward_percentiles) = match params {
+        FeeHistoryParams::Two((block_count, last_block)) => (block_count, last_block, Vec::new()),
+        FeeHistoryParams::Three((block_count, last_block, reward_percentiles)) => {
+            (block_count, last_block, reward_percentiles)
+        }
+    };
+
     if block_count > et::U256::from(data.gas_opt.max_fee_hist_size) {
         return error(
             ExitCode::USR_ILLEGAL_ARGUMENT,
-            "block_count must be <= 1024",
+            format!(
+                "block count must be at most {}",
+                data.gas_opt.max_fee_hist_size
+            ),
+        );
+    }
+
+    if reward_percentiles
+        .iter()
+        .any(|p| !(0.0..=100.0).contains(p))
+        || reward_percentiles.windows(2).any(|w| w[0] > w[1])
+    {
+        return error(
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            "reward percentiles must be between 0 and 100, in ascending order",
         );
     }
 
@@ -201,10 +209,10 @@ where
     let mut block_number = last_block;
     let mut block_count = block_count.as_usize();
 
-    let get_base_fee = |height: Height| {
+    let get_state_params = |height: Height| {
         data.client
             .state_params(FvmQueryHeight::Height(height.value()))
-            .map(|result| result.map(|state_params| state_params.value.base_fee))
+            .map(|result| result.map(|state_params| state_params.value))
     };
 
     while block_count > 0 {
@@ -218,27 +226,22 @@ where
         // Apparently the base fees have to include the next fee after the newest block.
         // See https://github.com/filecoin-project/lotus/blob/v1.25.2/node/impl/full/eth.go#L721-L725
         if hist.base_fee_per_gas.is_empty() {
-            let base_fee = get_base_fee(height.increment())
+            let next_params = get_state_params(height.increment())
                 .await
                 .context("failed to get next base fee")?;
 
-            hist.base_fee_per_gas.push(to_eth_tokens(&base_fee)?);
+            hist.base_fee_per_gas
+                .push(to_eth_tokens(&next_params.base_fee)?);
         }
 
-        let base_fee = get_base_fee(height)
-            .await
-            .context("failed to get block base fee")?;
-
-        let consensus_params: consensus_params::Response = data
-            .tm()
-            .consensus_params(height)
-            .await
-            .context("failed to get consensus params")?;
-
-        let mut block_gas_limit = consensus_params.consensus_params.block.max_gas;
-        if block_gas_limit <= 0 {
-            block_gas_limit =
-                i64::try_from(fvm_shared::BLOCK_GAS_LIMIT).expect("FVM block gas limit not i64")
+        let state_params = match get_state_params(height).await {
+            Ok(state_params) => state_params,
+            // Return what we have if the older states have been pruned.
+            Err(e) if !hist.gas_used_ratio.is_empty() => {
+                tracing::debug!(height = height.value(), "stopping fee history: {e:#}");
+                break;
+            }
+            Err(e) => return Err(e.context("failed to get block base fee").into()),
         };
 
         // The latest block might not have results yet.
@@ -246,43 +249,21 @@ where
             let txs_results = block_results.txs_results.unwrap_or_default();
             let total_gas_used: i64 = txs_results.iter().map(|r| r.gas_used).sum();
 
-            let mut premiums = Vec::new();
-            for (tx, txres) in block.data().iter().zip(txs_results) {
-                let msg = fvm_ipld_encoding::from_slice::<ChainMessage>(tx)
-                    .context("failed to decode tx as ChainMessage")?;
-
-                if let ChainMessage::Signed(msg) = msg {
-                    let premium = crate::gas::effective_gas_premium(&msg.message, &base_fee);
-                    premiums.push((premium, txres.gas_used));
-                }
-            }
-            premiums.sort();
+            let block_gas_limit = get_block_gas_limit(&data, height, &state_params).await?;
 
-            let premium_gas_used: i64 = premiums.iter().map(|(_, gas)| *gas).sum();
+            let mut premiums = block_premiums(&block, &txs_results, &state_params.base_fee)?;
 
-            let rewards: Result<Vec<et::U256>, _> = reward_percentiles
+            let rewards = crate::gas::reward_percentiles(&mut premiums, &reward_percentiles)
                 .iter()
-                .map(|p| {
-                    if premiums.is_empty() {
-                        Ok(et::U256::zero())
-                    } else {
-                        let threshold_gas_used = (premium_gas_used as f64 * p / 100f64) as i64;
-                        let mut sum_gas_used = 0;
-                        let mut idx = 0;
-                        while sum_gas_used < threshold_gas_used && idx < premiums.len() - 1 {
-                            sum_gas_used += premiums[idx].1;
-                            idx += 1;
-                        }
-                        to_eth_tokens(&premiums[idx].0)
-                    }
-                })
-                .collect();
+                .map(to_eth_tokens)
+                .collect::<Result<Vec<_>, _>>()?;
 
             hist.oldest_block = et::U256::from(height.value());
-            hist.base_fee_per_gas.push(to_eth_tokens(&base_fee)?);
+            hist.base_fee_per_gas
+                .push(to_eth_tokens(&state_params.base_fee)?);
             hist.gas_used_ratio
                 .push(total_gas_used as f64 / block_gas_limit as f64);
-            hist.reward.push(rewards?);
+            hist.reward.push(rewards);
 
             block_count -= 1;
         }
@@ -300,9 +281,61 @@ where
     hist.gas_used_ratio.reverse();
     hist.reward.reverse();
 
+    // Only return rewards if they were asked for.
+    if reward_percentiles.is_empty() {
+        hist.reward.clear();
+    }
+
     Ok(hist)
 }
 
+/// The effective premiums paid by the signed messages of a block, with the gas they used.
+fn block_premiums(
+    block: &tendermint::Block,
+    txs_results: &[DeliverTx],
+    base_fee: &TokenAmount,
+) -> anyhow::Result<Vec<(TokenAmount, i64)>> {
+    let mut premiums = Vec::new();
+    for (tx, txres) in block.data().iter().zip(txs_results) {
+        let msg = fvm_ipld_encoding::from_slice::<ChainMessage>(tx)
+            .context("failed to decode tx as ChainMessage")?;
+
+        if let ChainMessage::Signed(msg) = msg {
+            let premium = crate::gas::effective_gas_premium(&msg.message, base_fee);
+            premiums.push((premium, txres.gas_used));
+        }
+    }
+    Ok(premiums)
+}
+
+/// The gas limit of a block: the one set by governance in the state the block was executed on,
+/// or else the one in the consensus parameters, or else the FVM default.
+async fn get_block_gas_limit<C>(
+    data: &JsonRpcData<C>,
+    height: Height,
+    state_params: &StateParams,
+) -> JsonRpcResult<i64>
+where
+    C: Client + Sync + Send,
+{
+    if state_params.block_gas_limit > 0 {
+        return Ok(i64::try_from(state_params.block_gas_limit).unwrap_or(i64::MAX));
+    }
+
+    let consensus_params: consensus_params::Response = data
+        .tm()
+        .consensus_params(height)
+        .await
+        .context("failed to get consensus params")?;
+
+    let block_gas_limit = consensus_params.consensus_params.block.max_gas;
+    if block_gas_limit > 0 {
+        Ok(block_gas_limit)
+    } else {
+        Ok(i64::try_from(fvm_shared::BLOCK_GAS_LIMIT).expect("FVM block gas limit not i64"))
+    }
+}
+
 /// Returns the current price per gas in wei.
 pub async fn gas_price<C>(data: JsonRpcData<C>) -> JsonRpcResult<et::U256>
 where
@@ -655,10 +688,13 @@ pub async fn call<C>(
 where
     C: Client + Sync + Send,
 {
-    let msg = to_fvm_message(tx.into(), true)?;
+    let mut msg = to_fvm_message(tx.into(), true)?;
+    data.cap_call_gas(&mut msg);
     let is_create = msg.to == EAM_ACTOR_ADDR;
     let height = data.query_height(block_id).await?;
-    let response = data.client.call(msg, height).await?;
+    let response = data
+        .with_call_timeout(data.client.call(msg, height))
+        .await?;
     let deliver_tx = response.value;
 
     // Based on Lotus, we should return the data from the receipt.
@@ -704,7 +740,8 @@ where
         EstimateGasParams::Two((tx, block_id)) => (tx, block_id),
     };
 
-    let msg = to_fvm_message(tx.into(), true).context("failed to convert to FVM message")?;
+    let mut msg = to_fvm_message(tx.into(), true).context("failed to convert to FVM message")?;
+    data.cap_call_gas(&mut msg);
 
     let height = data
         .query_height(block_id)
@@ -712,10 +749,13 @@ where
         .context("failed to get height")?;
 
     let response = data
-        .client
-        .estimate_gas(msg, height)
-        .await
-        .context("failed to call estimate gas query")?;
+        .with_call_timeout(async {
+            data.client
+                .estimate_gas(msg, height)
+                .await
+                .context("failed to call estimate gas query")
+        })
+        .await?;
 
     let estimate = response.value;
 
@@ -727,6 +767,14 @@ where
             Err(e) => (format!("{msg}\n{e:#}"), None),
         };
         error_with_revert(estimate.exit_code, msg, data)
+    } else if data.max_call_gas > 0 && estimate.gas_limit > data.max_call_gas {
+        error(
+            ExitCode::SYS_OUT_OF_GAS,
+            format!(
+                "gas required exceeds the limit of {} allowed by the node",
+                data.max_call_gas
+            ),
+        )
     } else {
         Ok(estimate.gas_limit.into())
     }
@@ -793,7 +841,17 @@ where
     C: Client + Sync + Send,
 {
     let height = data.query_height(block_id).await?;
+    get_code_at(&data, address, height).await
+}
 
+async fn get_code_at<C>(
+    data: &JsonRpcData<C>,
+    address: et::H160,
+    height: FvmQueryHeight,
+) -> JsonRpcResult<et::Bytes>
+where
+    C: Client + Sync + Send,
+{
     // Return empty if not an EVM actor.
     if data.get_actor_type(&address, height).await? != ActorType::EVM {
         return Ok(Default::default());
@@ -820,6 +878,129 @@ where
     }
 }
 
+/// Returns the account and storage values of an address along with the proofs of them.
+///
+/// Instead of Merkle-Patricia trie nodes, the proofs are the IPLD blocks of the FVM state,
+/// starting with the state parameters whose CID is the app hash of the block after the
+/// queried one. Every block is committed to by the CID of its parent, the state root being
+/// in the state parameters, and the actor state being in the state tree.
+pub async fn get_proof<C>(
+    data: JsonRpcData<C>,
+    Params((address, keys, block_id)): Params<(et::H160, Vec<et::U256>, et::BlockId)>,
+) -> JsonRpcResult<AccountProof>
+where
+    C: Client + Sync + Send,
+{
+    if keys.len() > MAX_ACTOR_PROOF_KEYS {
+        return error(
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            format!("at most {MAX_ACTOR_PROOF_KEYS} storage keys can be proven at once"),
+        );
+    }
+
+    let height = data.query_height(block_id).await?;
+
+    let storage_keys = keys
+        .iter()
+        .map(|key| {
+            let mut bz = [0u8; 32];
+            key.to_big_endian(&mut bz);
+            uints::U256::from_big_endian(&bz)
+        })
+        .collect();
+
+    let res = data
+        .with_call_timeout(
+            data.client
+                .actor_proof(&to_fvm_address(address), storage_keys, height),
+        )
+        .await?;
+
+    let proof = res.value;
+    // Read the code at the same height, even if the query was for the latest one.
+    let height = FvmQueryHeight::Height(res.height.value());
+    let code = get_code_at(&data, address, height).await?;
+
+    let mut account_proof = vec![et::Bytes::from(proof.state_params.to_vec())];
+    account_proof.extend(
+        proof
+            .account_proof
+            .into_iter()
+            .map(|b| et::Bytes::from(b.to_vec())),
+    );
+
+    let (balance, nonce, storage_hash) = match proof.actor {
+        Some((_, state)) => {
+            // The actor state is the root of the storage of EVM actors.
+            let digest = state.state.hash().digest();
+            let storage_hash = if digest.len() == 32 {
+                et::H256::from_slice(digest)
+            } else {
+                et::H256::zero()
+            };
+            (
+                to_eth_tokens(&state.balance)?,
+                et::U64::from(state.sequence),
+                storage_hash,
+            )
+        }
+        None => (et::U256::zero(), et::U64::zero(), et::H256::zero()),
+    };
+
+    let storage_proof = proof
+        .storage_proof
+        .into_iter()
+        .map(|p| {
+            let mut key = [0u8; 32];
+            p.key.to_big_endian(&mut key);
+            let mut value = [0u8; 32];
+            p.value.to_big_endian(&mut value);
+            StorageProof {
+                key: et::H256::from(key),
+                value: et::U256::from_big_endian(&value),
+                proof: p
+                    .proof
+                    .into_iter()
+                    .map(|b| et::Bytes::from(b.to_vec()))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(AccountProof {
+        address,
+        account_proof,
+        balance,
+        code_hash: et::H256::from(ethers_core::utils::keccak256(&code)),
+        nonce,
+        storage_hash,
+        storage_proof,
+    })
+}
+
+/// The response of `eth_getProof`, in the shape defined by EIP-1186.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: et::H160,
+    pub account_proof: Vec<et::Bytes>,
+    pub balance: et::U256,
+    /// Keccak hash of the EVM bytecode; the hash of empty code for other actors.
+    pub code_hash: et::H256,
+    pub nonce: et::U64,
+    /// Hash in the CID of the actor state.
+    pub storage_hash: et::H256,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+    pub key: et::H256,
+    pub value: et::U256,
+    pub proof: Vec<et::Bytes>,
+}
+
 /// Returns an object with data about the sync status or false.
 pub async fn syncing<C>(data: JsonRpcData<C>) -> JsonRpcResult<et::SyncingStatus>
 where
@@ -915,20 +1096,59 @@ where
         }
     };
 
-    let addrs = match &filter.address {
+    if from_height > to_height {
+        return Ok(Vec::new());
+    }
+    let range = to_height.value() - from_height.value() + 1;
+    if data.max_log_range > 0 && range > data.max_log_range {
+        return error(
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            format!(
+                "block range too large: {range} blocks, at most {} allowed",
+                data.max_log_range
+            ),
+        );
+    }
+
+    let addresses = match &filter.address {
         Some(et::ValueOrArray::Value(addr)) => vec![*addr],
         Some(et::ValueOrArray::Array(addrs)) => addrs.clone(),
         None => Vec::new(),
     };
-    let addrs = addrs
-        .into_iter()
-        .map(|addr| Address::from(EthAddress(addr.0)))
-        .collect::<HashSet<_>>();
+    let topics = filter
+        .topics
+        .iter()
+        .map(|topics| match topics {
+            Some(et::ValueOrArray::Value(Some(t))) => vec![*t],
+            Some(et::ValueOrArray::Array(ts)) if ts.iter().all(|t| t.is_some()) => {
+                ts.iter().flatten().cloned().collect()
+            }
+            // Any topic at this position, which the index can't narrow down.
+            _ => Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let query = LogIndexQuery {
+        from_height: from_height.value(),
+        to_height: to_height.value(),
+        addresses,
+        topics,
+    };
+
+    // Only visit the blocks which may have matching logs according to the index,
+    // or every block in the range if the node doesn't keep one.
+    let heights = match data.client.log_index(query).await {
+        Ok(heights) => heights,
+        Err(e) => {
+            tracing::debug!(error = ?e, "log index unavailable; scanning all blocks");
+            (from_height.value()..=to_height.value()).collect()
+        }
+    };
 
-    let mut height = from_height;
     let mut logs = Vec::new();
 
-    while height <= to_height {
+    for height in heights {
+        let height = Height::try_from(height).context("invalid height")?;
         if let Ok(block_results) = data.tm().block_results(height).await {
             if let Some(tx_results) = block_results.txs_results {
                 let block_number = et::U64::from(height.value());
@@ -941,19 +1161,7 @@ where
 
                 let mut log_index_start = 0usize;
                 for ((tx_idx, tx_result), tx) in tx_results.iter().enumerate().zip(block.data()) {
-                    let msg = match to_chain_message(tx) {
-                        Ok(ChainMessage::Signed(msg)) => msg,
-                        _ => continue,
-                    };
-
-                    let emitters = from_tm::collect_emitters(&tx_result.events);
-
-                    // Filter by address.
-                    if !addrs.is_empty()
-                        && !addrs.contains(&msg.message().from)
-                        && !addrs.contains(&msg.message().to)
-                        && addrs.intersection(&emitters).next().is_none()
-                    {
+                    if !matches!(to_chain_message(tx), Ok(ChainMessage::Signed(_))) {
                         continue;
                     }
 
@@ -969,8 +1177,10 @@ where
                         log_index_start,
                     )?;
 
-                    // Filter by topic.
-                    tx_logs.retain(|log| matches_topics(&filter, log));
+                    // Filter by the address which emitted the log and the topics.
+                    tx_logs.retain(|log| {
+                        matches_address(&filter, log) && matches_topics(&filter, log)
+                    });
 
                     logs.append(&mut tx_logs);
 
@@ -980,7 +1190,6 @@ where
         } else {
             break;
         }
-        height = height.increment()
     }
 
     Ok(logs)
@@ -1076,59 +1285,59 @@ pub async fn subscribe<C>(
 where
     C: Client + SubscriptionClient + Clone + Sync + Send + 'static,
 {
-    match params {
+    let (kind, web_socket_id) = match params {
         SubscribeParams::One((tag, web_socket_id)) => match tag.as_str() {
-            "newHeads" => {
-                // Subscribe to `Block<TxHash>`
-                let ws_sender = data.get_web_socket(&web_socket_id).await?;
-                let id = data
-                    .new_subscription(FilterKind::NewBlocks, ws_sender)
-                    .await
-                    .context("failed to add block subscription")?;
-                Ok(id)
+            // Subscribe to `Block<TxHash>`
+            "newHeads" => (FilterKind::NewBlocks, web_socket_id),
+            // Subscribe to `TxHash`
+            "newPendingTransactions" | "pendingTransactions" => {
+                (FilterKind::PendingTransactions, web_socket_id)
             }
-            "newPendingTransactions" => {
-                // Subscribe to `TxHash`
-                let ws_sender = data.get_web_socket(&web_socket_id).await?;
-                let id = data
-                    .new_subscription(FilterKind::PendingTransactions, ws_sender)
-                    .await
-                    .context("failed to add transaction subscription")?;
-                Ok(id)
+            // Subscribe to every `Log`
+            "logs" => (FilterKind::Logs(Box::default()), web_socket_id),
+            other => {
+                return error(
+                    ExitCode::USR_ILLEGAL_ARGUMENT,
+                    format!("unknown subscription: {other}"),
+                )
             }
-            other => error(
-                ExitCode::USR_ILLEGAL_ARGUMENT,
-                format!("unknown subscription: {other}"),
-            ),
         },
         SubscribeParams::Two((tag, filter, web_socket_id)) => match tag.as_str() {
-            "logs" => {
-                // Subscribe to `Log`
-                let ws_sender = data.get_web_socket(&web_socket_id).await?;
-                let id = data
-                    .new_subscription(FilterKind::Logs(Box::new(filter)), ws_sender)
-                    .await
-                    .context("failed to add transaction subscription")?;
-                Ok(id)
+            // Subscribe to `Log`
+            "logs" => (FilterKind::Logs(Box::new(filter)), web_socket_id),
+            other => {
+                return error(
+                    ExitCode::USR_ILLEGAL_ARGUMENT,
+                    format!("unknown subscription: {other}"),
+                )
             }
-            other => error(
-                ExitCode::USR_ILLEGAL_ARGUMENT,
-                format!("unknown subscription: {other}"),
-            ),
         },
-    }
+    };
+
+    let id = data
+        .new_subscription(kind, web_socket_id)
+        .await
+        .context("failed to add subscription")?;
+
+    Ok(id)
 }
 
-/// Unsubscribe from the filter registered by this websocket.
+/// Unsubscribe from a subscription made through this websocket.
 pub async fn unsubscribe<C>(
     data: JsonRpcData<C>,
-    Params((filter_id,)): Params<(FilterId,)>,
+    Params((filter_id, web_socket_id)): Params<(FilterId, WebSocketId)>,
 ) -> JsonRpcResult<bool> {
-    uninstall_filter(data, Params((filter_id,))).await
+    let removed = data
+        .remove_subscription(&web_socket_id, filter_id)
+        .await
+        .context("failed to remove subscription")?;
+
+    Ok(removed)
 }
 
-use crate::state::ActorType;
-use params::{EstimateGasParams, SubscribeParams, TypedTransactionCompat};
+use crate::state::{ActorType, WebSocketId};
+pub(super) use params::TypedTransactionCompat;
+use params::{EstimateGasParams, FeeHistoryParams, SubscribeParams};
 
 mod params {
     use ethers_core::types::transaction::eip2718::TypedTransaction;
@@ -1210,6 +1419,14 @@ mod params {
         Two((TypedTransactionCompat, et::BlockId)),
     }
 
+    /// The reward percentiles are optional.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum FeeHistoryParams {
+        Two((et::U256, et::BlockNumber)),
+        Three((et::U256, et::BlockNumber, Vec<f64>)),
+    }
+
     /// The client either sends one or two items in the array, depending on whether it's subscribing to block,
     /// transactions or logs. To that we add the web socket ID.
     #[derive(Deserialize)]
@@ -0,0 +1,107 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! IPC specific extensions to the Ethereum API.
+
+use anyhow::Context;
+use ethers_core::types as et;
+use fendermint_rpc::query::QueryClient;
+use fendermint_rpc::response::decode_fevm_return_data;
+use fvm_ipld_encoding::RawBytes;
+use jsonrpc_v2::Params;
+use serde::Serialize;
+use tendermint_rpc::Client;
+
+use super::eth::{self, TypedTransactionCompat};
+use crate::{JsonRpcData, JsonRpcResult};
+
+/// The result of a call evaluated against the state pinned to a parent finality.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParentFinalityCall {
+    /// The child block in which the parent finality was committed; the call sees its effects.
+    pub block_number: et::U64,
+    /// The parent height of the finality committed in that block.
+    ///
+    /// This can be higher than the requested height if finality skipped over it.
+    pub parent_height: et::U256,
+    /// The hash of the parent block at `parent_height`.
+    pub parent_block_hash: et::H256,
+    /// The return data of the call.
+    pub data: et::Bytes,
+}
+
+/// Executes a new message call immediately without creating a transaction on the block chain,
+/// against the state of the child subnet as of the block in which the parent finality
+/// first reached the given parent height.
+///
+/// This lets applications align reads of the child with observations of the parent.
+/// The message is executed by [eth::call], so it's subject to the same gas cap and timeout.
+pub async fn call_at_parent_finality<C>(
+    data: JsonRpcData<C>,
+    Params((tx, parent_height)): Params<(TypedTransactionCompat, et::U64)>,
+) -> JsonRpcResult<ParentFinalityCall>
+where
+    C: Client + Sync + Send,
+{
+    let (height, finality) = data.parent_finality_block(parent_height.as_u64()).await?;
+    let block_number = et::U64::from(height.value());
+
+    let block_id = et::BlockId::Number(et::BlockNumber::Number(block_number));
+    let return_data = eth::call(data, Params((tx, block_id))).await?;
+
+    Ok(ParentFinalityCall {
+        block_number,
+        parent_height: finality.height,
+        parent_block_hash: et::H256::from(finality.block_hash),
+        data: return_data,
+    })
+}
+
+/// The expected outcome of a transaction waiting in the mempool.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingReceipt {
+    pub transaction_hash: et::H256,
+    /// The latest block the transaction was executed on top of, along with the transactions
+    /// ahead of it in the mempool.
+    pub block_number: et::U64,
+    /// 1 if the transaction is expected to succeed, 0 if it's expected to fail.
+    pub status: et::U64,
+    pub gas_used: et::U256,
+    /// The data returned by the call, or the revert data if it failed.
+    pub return_data: et::Bytes,
+    /// The reason the transaction is expected to fail with, if any.
+    pub error: Option<String>,
+}
+
+/// Returns the expected outcome of a transaction which hasn't been included in a block yet,
+/// or `null` if the node doesn't know about it, e.g. because it's already been included.
+///
+/// The node only keeps these if it runs with `fvm.pending_receipts` enabled. They are
+/// worked out again after every block, and the actual outcome can still be different.
+pub async fn get_pending_receipt<C>(
+    data: JsonRpcData<C>,
+    Params((tx_hash,)): Params<(et::H256,)>,
+) -> JsonRpcResult<Option<PendingReceipt>>
+where
+    C: Client + Sync + Send,
+{
+    let Some(receipt) = data.client.pending_receipt(tx_hash.as_bytes()).await? else {
+        return Ok(None);
+    };
+
+    let return_data = hex::decode(&receipt.return_data).context("invalid return data")?;
+    // Calls to contracts return IPLD encoded bytes; anything else is returned as it is.
+    let return_data =
+        decode_fevm_return_data(RawBytes::new(return_data.clone())).unwrap_or(return_data);
+
+    Ok(Some(PendingReceipt {
+        transaction_hash: tx_hash,
+        block_number: et::U64::from(receipt.height),
+        status: et::U64::from(u64::from(receipt.exit_code == 0)),
+        gas_used: et::U256::from(receipt.gas_used),
+        return_data: return_data.into(),
+        error: (receipt.exit_code != 0).then(|| receipt.info.unwrap_or_default()),
+    }))
+}
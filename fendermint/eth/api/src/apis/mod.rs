@@ -4,31 +4,48 @@
 // See https://ethereum.org/en/developers/docs/apis/json-rpc/#json-rpc-methods
 // and https://ethereum.github.io/execution-apis/api-documentation/
 
-use crate::HybridClient;
+use crate::{HybridClient, MethodOpt};
 use jsonrpc_v2::{MapRouter, ServerBuilder};
 use paste::paste;
 
+mod debug;
 mod eth;
+mod ipc;
 mod net;
+mod trace;
 mod web3;
 
+/// Register the methods of a module which are enabled in the options.
 macro_rules! with_methods {
-    ($server:ident, $module:ident, { $($method:ident),* }) => {
+    ($server:ident, $opt:ident, $module:ident, { $($method:ident),* }) => {
         paste!{
-            $server
-                $(.with_method(
-                    stringify!([< $module _ $method >]),
-                    $module :: [< $method:snake >] ::<HybridClient>
-                ))*
+            {
+                let mut server = $server;
+                $(
+                    let name = stringify!([< $module _ $method >]);
+                    if $opt.is_enabled(name) {
+                        server = server.with_method(
+                            name,
+                            $module :: [< $method:snake >] ::<HybridClient>
+                        );
+                    } else {
+                        tracing::debug!(method = name, "method disabled");
+                    }
+                )*
+                server
+            }
         }
     };
 }
 
-pub fn register_methods(server: ServerBuilder<MapRouter>) -> ServerBuilder<MapRouter> {
+pub fn register_methods(
+    server: ServerBuilder<MapRouter>,
+    opt: &MethodOpt,
+) -> ServerBuilder<MapRouter> {
     // This is the list of eth methods. Apart from these Lotus implements 1 method from web3,
     // while Ethermint does more across web3, debug, miner, net, txpool, and personal.
     // The unimplemented ones are commented out, to make it easier to see where we're at.
-    let server = with_methods!(server, eth, {
+    let server = with_methods!(server, opt, eth, {
         accounts,
         blockNumber,
         call,
@@ -52,6 +69,7 @@ pub fn register_methods(server: ServerBuilder<MapRouter>) -> ServerBuilder<MapRo
         getFilterChanges,
         getFilterLogs,
         getLogs,
+        getProof,
         getStorageAt,
         getTransactionByBlockHashAndIndex,
         getTransactionByBlockNumberAndIndex,
@@ -81,19 +99,34 @@ pub fn register_methods(server: ServerBuilder<MapRouter>) -> ServerBuilder<MapRo
         unsubscribe
     });
 
-    let server = with_methods!(server, web3, {
+    let server = with_methods!(server, opt, debug, {
+        traceCall,
+        traceTransaction
+    });
+
+    let server = with_methods!(server, opt, trace, {
+        block,
+        filter
+    });
+
+    let server = with_methods!(server, opt, web3, {
         clientVersion,
         sha3
     });
 
-    with_methods!(server, net, {
+    let server = with_methods!(server, opt, net, {
         version,
         listening,
         peerCount
+    });
+
+    with_methods!(server, opt, ipc, {
+        callAtParentFinality,
+        getPendingReceipt
     })
 }
 
 /// Indicate whether a method requires a WebSocket connection.
 pub fn is_streaming_method(method: &str) -> bool {
-    method == "eth_subscribe"
+    method == "eth_subscribe" || method == "eth_unsubscribe"
 }
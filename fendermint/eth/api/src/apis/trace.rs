@@ -0,0 +1,418 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! OpenEthereum style traces of the calls made by the transactions of a block,
+//! flattened from the call trees the node records with `db.exec_traces`.
+//!
+//! See <https://openethereum.github.io/JSONRPC-trace-module>
+
+use anyhow::Context;
+use ethers_core::types as et;
+use fendermint_rpc::query::QueryClient;
+use fendermint_vm_actor_interface::eam::{self, EAM_ACTOR_ADDR};
+use fendermint_vm_message::query::CallTrace;
+use fvm_shared::error::ExitCode;
+use jsonrpc_v2::Params;
+use serde::{Deserialize, Serialize};
+use tendermint::block::Height;
+use tendermint_rpc::endpoint::block_results;
+use tendermint_rpc::Client;
+
+use super::debug::unwrap_bytes;
+use crate::conv::from_fvm::{to_eth_address, to_eth_tokens};
+use crate::conv::from_tm::{self, msg_hash};
+use crate::{error, JsonRpcData, JsonRpcResult};
+
+/// A call made by a transaction, with its position in the call tree and the block.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedTrace {
+    pub action: Action,
+    /// Empty if the call failed.
+    pub result: Option<TraceResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Number of calls made by this one.
+    pub subtraces: usize,
+    /// Indexes of the calls leading to this one from the top call, which has none.
+    pub trace_address: Vec<usize>,
+    pub transaction_hash: et::H256,
+    pub transaction_position: usize,
+    pub block_hash: et::H256,
+    pub block_number: u64,
+    #[serde(rename = "type")]
+    pub trace_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Action {
+    Call(CallAction),
+    Create(CreateAction),
+}
+
+impl Action {
+    fn from(&self) -> et::H160 {
+        match self {
+            Action::Call(a) => a.from,
+            Action::Create(a) => a.from,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallAction {
+    pub call_type: String,
+    pub from: et::H160,
+    pub to: et::H160,
+    pub gas: et::U64,
+    pub input: et::Bytes,
+    pub value: et::U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAction {
+    pub from: et::H160,
+    pub gas: et::U64,
+    pub init: et::Bytes,
+    pub value: et::U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum TraceResult {
+    Call(CallResult),
+    Create(CreateResult),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallResult {
+    pub gas_used: et::U64,
+    pub output: et::Bytes,
+}
+
+/// The FVM trace doesn't include the deployed bytecode, so `code` is always empty.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateResult {
+    pub gas_used: et::U64,
+    pub code: et::Bytes,
+    pub address: et::H160,
+}
+
+/// Criteria of `trace_filter`; the blocks default to the latest one, and an empty list of
+/// addresses matches any address.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceFilter {
+    pub from_block: Option<et::BlockNumber>,
+    pub to_block: Option<et::BlockNumber>,
+    #[serde(default)]
+    pub from_address: Vec<et::H160>,
+    /// Matched against the recipient of calls and the address of created contracts.
+    #[serde(default)]
+    pub to_address: Vec<et::H160>,
+    /// Number of matching traces to skip.
+    pub after: Option<usize>,
+    /// Maximum number of traces to return.
+    pub count: Option<usize>,
+}
+
+impl TraceFilter {
+    fn matches(&self, trace: &LocalizedTrace) -> bool {
+        let to = match (&trace.action, &trace.result) {
+            (Action::Call(a), _) => Some(a.to),
+            (Action::Create(_), Some(TraceResult::Create(r))) => Some(r.address),
+            (Action::Create(_), _) => None,
+        };
+        (self.from_address.is_empty() || self.from_address.contains(&trace.action.from()))
+            && (self.to_address.is_empty() || to.is_some_and(|to| self.to_address.contains(&to)))
+    }
+}
+
+/// Returns the traces of the calls made by all transactions in a block.
+///
+/// The node only keeps the traces if it runs with `db.exec_traces` enabled.
+pub async fn block<C>(
+    data: JsonRpcData<C>,
+    Params((block_number,)): Params<(et::BlockNumber,)>,
+) -> JsonRpcResult<Vec<LocalizedTrace>>
+where
+    C: Client + Sync + Send,
+{
+    let block = data.block_by_height(block_number).await?;
+    block_traces(&data, block).await
+}
+
+/// Returns the traces of the calls matching a filter in a range of blocks.
+///
+/// The range can span at most `eth.max_log_range` blocks, like `eth_getLogs`.
+pub async fn filter<C>(
+    data: JsonRpcData<C>,
+    Params((filter,)): Params<(TraceFilter,)>,
+) -> JsonRpcResult<Vec<LocalizedTrace>>
+where
+    C: Client + Sync + Send,
+{
+    let from_height = resolve_height(&data, filter.from_block).await?;
+    let to_height = resolve_height(&data, filter.to_block).await?;
+
+    if from_height > to_height {
+        return Ok(Vec::new());
+    }
+    let range = to_height - from_height + 1;
+    if data.max_log_range > 0 && range > data.max_log_range {
+        return error(
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            format!(
+                "block range too large: {range} blocks, at most {} allowed",
+                data.max_log_range
+            ),
+        );
+    }
+
+    let mut skip = filter.after.unwrap_or_default();
+    let count = filter.count.unwrap_or(usize::MAX);
+    let mut traces = Vec::new();
+
+    if count == 0 {
+        return Ok(traces);
+    }
+
+    for height in from_height..=to_height {
+        let block = data
+            .block_by_height(et::BlockNumber::Number(et::U64::from(height)))
+            .await?;
+
+        for trace in block_traces(&data, block).await? {
+            if !filter.matches(&trace) {
+                continue;
+            }
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            traces.push(trace);
+            if traces.len() == count {
+                return Ok(traces);
+            }
+        }
+    }
+
+    Ok(traces)
+}
+
+/// Turn a block number into a height, restricting it to the blocks available.
+async fn resolve_height<C>(
+    data: &JsonRpcData<C>,
+    block_number: Option<et::BlockNumber>,
+) -> JsonRpcResult<u64>
+where
+    C: Client + Sync + Send,
+{
+    match block_number.unwrap_or_default() {
+        et::BlockNumber::Number(n) => {
+            let latest_height = data.latest_height().await?;
+            Ok(n.as_u64().min(latest_height.value()))
+        }
+        other => {
+            let header = data.header_by_height(other).await?;
+            Ok(header.height.value())
+        }
+    }
+}
+
+/// The position of a transaction on the chain.
+struct TxPosition {
+    block_hash: et::H256,
+    block_number: u64,
+    transaction_hash: et::H256,
+    transaction_position: usize,
+}
+
+async fn block_traces<C>(
+    data: &JsonRpcData<C>,
+    block: tendermint::Block,
+) -> JsonRpcResult<Vec<LocalizedTrace>>
+where
+    C: Client + Sync + Send,
+{
+    if from_tm::is_block_zero(&block) {
+        return Ok(Vec::new());
+    }
+
+    let height: Height = block.header().height;
+    let block_hash = et::H256::from_slice(block.header().hash().as_bytes());
+    let block_results: block_results::Response = data.tm().block_results(height).await?;
+    let txs_results = block_results.txs_results.unwrap_or_default();
+
+    let mut traces = Vec::new();
+
+    for (i, (tx, result)) in block.data().iter().zip(txs_results.iter()).enumerate() {
+        let trace = data
+            .client
+            .exec_trace(from_tm::tx_hash(tx).as_bytes())
+            .await
+            .context("failed to query execution trace")?;
+
+        if let Some(trace) = trace {
+            let position = TxPosition {
+                block_hash,
+                block_number: height.value(),
+                transaction_hash: msg_hash(&result.events, tx),
+                transaction_position: i,
+            };
+            flatten(trace, &position, Vec::new(), &mut traces)?;
+        }
+    }
+
+    Ok(traces)
+}
+
+/// Add a call and the ones it made to the traces, depth first.
+fn flatten(
+    trace: CallTrace,
+    position: &TxPosition,
+    trace_address: Vec<usize>,
+    traces: &mut Vec<LocalizedTrace>,
+) -> anyhow::Result<()> {
+    let from = to_eth_address(&trace.from)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let gas = et::U64::from(trace.gas_limit);
+    let gas_used = et::U64::from(trace.gas_used);
+    let value = to_eth_tokens(&trace.value)?;
+    let failed = trace.exit_code != 0 || trace.error.is_some();
+
+    // Contracts are created by calling the EAM.
+    let (trace_type, action, result) = if trace.to == EAM_ACTOR_ADDR {
+        let address = hex::decode(&trace.return_data)
+            .ok()
+            .and_then(|bz| fvm_ipld_encoding::from_slice::<eam::CreateReturn>(&bz).ok())
+            .and_then(|ret| to_eth_address(&ret.delegated_address()).ok().flatten())
+            .unwrap_or_default();
+        let action = Action::Create(CreateAction {
+            from,
+            gas,
+            init: unwrap_bytes(&trace.params)?.into(),
+            value,
+        });
+        let result = TraceResult::Create(CreateResult {
+            gas_used,
+            code: et::Bytes::default(),
+            address,
+        });
+        ("create", action, result)
+    } else {
+        let action = Action::Call(CallAction {
+            call_type: if trace.read_only {
+                "staticcall"
+            } else {
+                "call"
+            }
+            .to_string(),
+            from,
+            to: to_eth_address(&trace.to).ok().flatten().unwrap_or_default(),
+            gas,
+            input: unwrap_bytes(&trace.params)?.into(),
+            value,
+        });
+        let result = TraceResult::Call(CallResult {
+            gas_used,
+            output: unwrap_bytes(&trace.return_data)?.into(),
+        });
+        ("call", action, result)
+    };
+
+    let error = failed.then(|| {
+        trace
+            .error
+            .clone()
+            .unwrap_or_else(|| format!("execution failed with exit code {}", trace.exit_code))
+    });
+
+    traces.push(LocalizedTrace {
+        action,
+        result: (!failed).then_some(result),
+        error,
+        subtraces: trace.calls.len(),
+        trace_address: trace_address.clone(),
+        transaction_hash: position.transaction_hash,
+        transaction_position: position.transaction_position,
+        block_hash: position.block_hash,
+        block_number: position.block_number,
+        trace_type: trace_type.to_string(),
+    });
+
+    for (i, call) in trace.calls.into_iter().enumerate() {
+        let mut address = trace_address.clone();
+        address.push(i);
+        flatten(call, position, address, traces)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types as et;
+    use fendermint_vm_message::query::CallTrace;
+    use fvm_shared::{address::Address, econ::TokenAmount};
+
+    use super::{flatten, Action, TraceFilter, TxPosition};
+
+    fn call(to: u64, exit_code: u32, calls: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            from: Address::new_id(100),
+            to: Address::new_id(to),
+            method_num: 3844450837,
+            params: String::new(),
+            value: TokenAmount::from_atto(5),
+            gas_limit: 1000,
+            gas_used: 500,
+            read_only: false,
+            exit_code,
+            return_data: String::new(),
+            error: None,
+            calls,
+        }
+    }
+
+    #[test]
+    fn flatten_traces() {
+        let trace = call(200, 0, vec![call(300, 0, vec![call(400, 33, vec![])])]);
+        let position = TxPosition {
+            block_hash: et::H256::zero(),
+            block_number: 10,
+            transaction_hash: et::H256::zero(),
+            transaction_position: 1,
+        };
+
+        let mut traces = Vec::new();
+        flatten(trace, &position, Vec::new(), &mut traces).unwrap();
+
+        assert_eq!(
+            traces
+                .iter()
+                .map(|t| (t.trace_address.clone(), t.subtraces))
+                .collect::<Vec<_>>(),
+            vec![(vec![], 1), (vec![0], 1), (vec![0, 0], 0)]
+        );
+        assert!(traces[0].result.is_some());
+        assert!(traces[2].result.is_none());
+        assert!(traces[2].error.is_some());
+
+        let Action::Call(ref action) = traces[1].action else {
+            panic!("expected a call")
+        };
+        let filter = TraceFilter {
+            to_address: vec![action.to],
+            ..Default::default()
+        };
+        assert_eq!(traces.iter().filter(|t| filter.matches(t)).count(), 1);
+    }
+}
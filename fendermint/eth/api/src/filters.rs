@@ -50,6 +50,8 @@ pub fn matches_topics(filter: &et::Filter, log: &et::Log) -> bool {
             let topic = log.topics.get(i);
             let matches = match topics {
                 et::ValueOrArray::Value(Some(t)) => topic == Some(t),
+                // An array of nulls is the same as a null.
+                et::ValueOrArray::Array(ts) if ts.iter().all(|t| t.is_none()) => true,
                 et::ValueOrArray::Array(ts) => ts.iter().flatten().any(|t| topic == Some(t)),
                 _ => true,
             };
@@ -61,6 +63,16 @@ pub fn matches_topics(filter: &et::Filter, log: &et::Log) -> bool {
     true
 }
 
+/// Check whether to keep a log according to the address filter, which matches any of the
+/// addresses if it's an array, and anything if it's missing or empty.
+pub fn matches_address(filter: &et::Filter, log: &et::Log) -> bool {
+    match &filter.address {
+        None => true,
+        Some(et::ValueOrArray::Value(addr)) => log.address == *addr,
+        Some(et::ValueOrArray::Array(addrs)) => addrs.is_empty() || addrs.contains(&log.address),
+    }
+}
+
 pub type FilterId = et::U256;
 pub type FilterMap = Arc<RwLock<HashMap<FilterId, Sender<FilterCommand>>>>;
 
@@ -331,7 +343,8 @@ where
                 )?;
 
                 if let Some(filter) = filter {
-                    tx_logs.retain(|log| matches_topics(filter, log));
+                    tx_logs
+                        .retain(|log| matches_address(filter, log) && matches_topics(filter, log));
                 }
 
                 logs.extend(tx_logs)
@@ -757,7 +770,7 @@ pub async fn run_subscription(id: FilterId, mut sub: Subscription, tx: Sender<Fi
 mod tests {
     use ethers_core::types as et;
 
-    use super::FilterKind;
+    use super::{matches_address, matches_topics, FilterKind};
 
     #[test]
     fn default_filter_to_query() {
@@ -769,6 +782,36 @@ mod tests {
         assert_eq!(queries[0].to_string(), "tm.event = 'Tx'");
     }
 
+    #[test]
+    fn filter_matches_log() {
+        let (a, b) = (et::H160::repeat_byte(1), et::H160::repeat_byte(2));
+        let (t1, t2, t3) = (
+            et::H256::repeat_byte(1),
+            et::H256::repeat_byte(2),
+            et::H256::repeat_byte(3),
+        );
+        let log = et::Log {
+            address: a,
+            topics: vec![t1, t2],
+            ..Default::default()
+        };
+
+        let filter = et::Filter::new().address(vec![b, a]).topic1(vec![t3, t2]);
+        assert!(matches_address(&filter, &log));
+        assert!(matches_topics(&filter, &log));
+
+        let filter = et::Filter::new().address(b).topic0(t2);
+        assert!(!matches_address(&filter, &log));
+        assert!(!matches_topics(&filter, &log));
+
+        // Empty arrays and arrays of nulls are wildcards.
+        let mut filter = et::Filter::new().address(Vec::<et::H160>::new());
+        filter.topics[0] = Some(et::ValueOrArray::Array(vec![None]));
+        filter.topics[1] = Some(et::ValueOrArray::Array(vec![None, Some(t2)]));
+        assert!(matches_address(&filter, &log));
+        assert!(matches_topics(&filter, &log));
+    }
+
     #[test]
     fn filter_to_query() {
         fn hash(s: &str) -> et::H256 {
@@ -44,10 +44,13 @@ pub fn effective_gas_premium(msg: &Message, base_fee: &TokenAmount) -> TokenAmou
 // finds 55th percntile instead of median to put negative pressure on gas price
 // Rust implementation of:
 // https://github.com/consensus-shipyard/lotus/blob/156f5556b3ecc042764d76308dca357da3adfb4d/node/impl/full/gas.go#L144
-pub fn median_gas_premium(prices: &mut [(TokenAmount, i64)], block_gas_target: i64) -> TokenAmount {
+pub fn median_gas_premium(
+    prices: &mut [(TokenAmount, i64)],
+    blocks: i64,
+    block_gas_target: i64,
+) -> TokenAmount {
     // Sort in descending order based on premium
     prices.sort_by(|a, b| b.0.cmp(&a.0));
-    let blocks = prices.len() as i64;
 
     let mut at = block_gas_target * blocks / 2;
     at += block_gas_target * blocks / (2 * 20);
@@ -68,8 +71,68 @@ pub fn median_gas_premium(prices: &mut [(TokenAmount, i64)], block_gas_target: i
 
     if prev2 != TokenAmount::zero() {
         premium += &prev2;
-        premium.div_ceil(BigInt::from(2));
+        premium = premium.div_ceil(BigInt::from(2));
     }
 
     premium
 }
+
+/// The premiums paid at percentiles of the gas used by a block, for `eth_feeHistory`:
+/// for each percentile, the premium of the first message, in ascending order of premiums,
+/// at which the gas used adds up to that percentage of the total, like Geth does.
+pub fn reward_percentiles(
+    premiums: &mut [(TokenAmount, i64)],
+    percentiles: &[f64],
+) -> Vec<TokenAmount> {
+    if premiums.is_empty() {
+        return vec![TokenAmount::zero(); percentiles.len()];
+    }
+
+    premiums.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let total_gas_used: i64 = premiums.iter().map(|(_, gas)| *gas).sum();
+
+    percentiles
+        .iter()
+        .map(|p| {
+            let threshold = (total_gas_used as f64 * p / 100f64) as i64;
+            let mut idx = 0;
+            let mut sum_gas_used = premiums[0].1;
+            while sum_gas_used < threshold && idx < premiums.len() - 1 {
+                idx += 1;
+                sum_gas_used += premiums[idx].1;
+            }
+            premiums[idx].0.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::econ::TokenAmount;
+
+    use super::{median_gas_premium, reward_percentiles};
+
+    fn atto(n: u64) -> TokenAmount {
+        TokenAmount::from_atto(n)
+    }
+
+    #[test]
+    fn rewards_at_percentiles() {
+        let mut premiums = vec![(atto(30), 10), (atto(10), 50), (atto(20), 40)];
+
+        let rewards = reward_percentiles(&mut premiums, &[0.0, 50.0, 60.0, 100.0]);
+        assert_eq!(rewards, vec![atto(10), atto(10), atto(20), atto(30)]);
+
+        assert_eq!(reward_percentiles(&mut [], &[50.0]), vec![atto(0)]);
+    }
+
+    #[test]
+    fn median_premium_of_blocks() {
+        // Two blocks which are just over half full, with a target of 100 gas each.
+        let mut prices = vec![(atto(10), 60), (atto(20), 60), (atto(30), 60)];
+        let premium = median_gas_premium(&mut prices, 2, 100);
+        // The 105 gas it looks for is reached by the second message, averaged with the one before it.
+        assert_eq!(premium, atto(25));
+    }
+}
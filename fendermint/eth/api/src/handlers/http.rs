@@ -7,7 +7,8 @@
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use jsonrpc_v2::{RequestObject, ResponseObjects};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 use crate::{apis, AppState};
 
@@ -15,15 +16,19 @@ type ResponseHeaders = [(&'static str, &'static str); 1];
 
 const RESPONSE_HEADERS: ResponseHeaders = [("content-type", "application/json-rpc;charset=utf-8")];
 
+/// JSON-RPC error code of requests which aren't valid request objects.
+const INVALID_REQUEST: i64 = -32600;
+
 /// The Ethereum API implementations accept `{}` or `[{}, {}, ...]` as requests,
 /// with the expectation of as many responses.
 ///
 /// `jsonrpc_v2` has a type named `RequestKind` but it's not `Deserialize`.
+/// The requests of a batch are parsed one by one, so that an invalid one only fails itself.
 #[derive(Deserialize)]
 #[serde(untagged)]
 pub enum RequestKind {
     One(RequestObject),
-    Many(Vec<RequestObject>),
+    Many(Vec<serde_json::Value>),
 }
 
 /// Handle JSON-RPC calls.
@@ -40,19 +45,97 @@ pub async fn handle(
             }
             state.rpc_server.handle(request).await
         }
-        RequestKind::Many(requests) => {
-            for request in requests.iter() {
-                if let Err(response) = check_request(request) {
-                    return response;
-                }
-            }
-            state.rpc_server.handle(requests).await
-        }
+        RequestKind::Many(requests) => return handle_batch(&state, requests).await,
     };
     debug_response(&response);
     json_response(&response)
 }
 
+/// Handle a batch of requests, responding to each valid one and with an error to the rest.
+async fn handle_batch(
+    state: &AppState,
+    requests: Vec<serde_json::Value>,
+) -> (StatusCode, ResponseHeaders, std::string::String) {
+    if requests.is_empty() {
+        return json_response(&error_object(
+            serde_json::Value::Null,
+            "empty batch".to_string(),
+        ));
+    }
+    if state.max_batch_size > 0 && requests.len() > state.max_batch_size {
+        return json_response(&error_object(
+            serde_json::Value::Null,
+            format!(
+                "batch of {} requests exceeds the limit of {}",
+                requests.len(),
+                state.max_batch_size
+            ),
+        ));
+    }
+
+    let (requests, mut responses) = split_batch(requests);
+
+    if !requests.is_empty() {
+        let response = state.rpc_server.handle_batch(requests).await;
+        debug_response(&response);
+        let rs = match response {
+            ResponseObjects::Empty => Vec::new(),
+            ResponseObjects::One(r) => vec![serde_json::to_value(r)],
+            ResponseObjects::Many(rs) => rs.iter().map(serde_json::to_value).collect(),
+        };
+        for r in rs {
+            match r {
+                Ok(r) => responses.push(r),
+                Err(e) => tracing::error!(error = e.to_string(), "RPC to JSON failure"),
+            }
+        }
+    }
+
+    // There is nothing to return if all requests were notifications.
+    if responses.is_empty() {
+        json_response(&ResponseObjects::Empty)
+    } else {
+        json_response(&responses)
+    }
+}
+
+/// Parse the requests of a batch, returning the valid ones along with
+/// error responses to the ones which are invalid or can't be served over HTTP.
+fn split_batch(requests: Vec<serde_json::Value>) -> (Vec<RequestObject>, Vec<serde_json::Value>) {
+    let mut valid = Vec::new();
+    let mut errors = Vec::new();
+
+    for request in requests {
+        let id = request.get("id").cloned().unwrap_or_default();
+        // `RequestObject` can only be parsed with `from_str`, not `from_value`.
+        match serde_json::from_str::<RequestObject>(&request.to_string()) {
+            Err(e) => errors.push(error_object(id, format!("invalid request: {e}"))),
+            Ok(request) => {
+                let method = request.method_ref();
+                if apis::is_streaming_method(method) {
+                    errors.push(error_object(
+                        id,
+                        format!("'{method}' is only available through WebSocket"),
+                    ));
+                } else {
+                    tracing::debug!(?request, "RPC request");
+                    valid.push(request);
+                }
+            }
+        }
+    }
+
+    (valid, errors)
+}
+
+fn error_object(id: serde_json::Value, message: String) -> serde_json::Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": INVALID_REQUEST, "message": message },
+        "id": id,
+    })
+}
+
 fn debug_response(response: &ResponseObjects) {
     let debug = |r| {
         tracing::debug!(
@@ -73,7 +156,7 @@ fn debug_response(response: &ResponseObjects) {
     }
 }
 
-fn json_response(response: &ResponseObjects) -> (StatusCode, ResponseHeaders, std::string::String) {
+fn json_response<T: Serialize>(response: &T) -> (StatusCode, ResponseHeaders, std::string::String) {
     match serde_json::to_string(response) {
         Ok(json) => (StatusCode::OK, RESPONSE_HEADERS, json),
         Err(err) => {
@@ -100,3 +183,34 @@ fn check_request(
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::split_batch;
+
+    #[test]
+    fn splits_batch() {
+        let requests: serde_json::Value = serde_json::from_str(
+            r#"[
+                {"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []},
+                {"jsonrpc": "2.0", "id": 2, "method": "eth_subscribe", "params": ["newHeads"]},
+                {"id": 3, "foo": "bar"},
+                {"jsonrpc": "2.0", "id": 4, "method": "eth_chainId"}
+            ]"#,
+        )
+        .unwrap();
+        let requests = requests.as_array().unwrap().clone();
+
+        let (valid, errors) = split_batch(requests);
+
+        assert_eq!(
+            valid.iter().map(|r| r.method_ref()).collect::<Vec<_>>(),
+            vec!["eth_blockNumber", "eth_chainId"]
+        );
+        assert_eq!(
+            errors.iter().map(|e| e["id"].clone()).collect::<Vec<_>>(),
+            vec![serde_json::json!(2), serde_json::json!(3)]
+        );
+        assert!(errors.iter().all(|e| e["error"]["code"] == -32600));
+    }
+}
@@ -234,6 +234,23 @@ fn is_closed_connection(e: axum::Error) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use super::maybe_add_web_socket_id;
+
+    #[test]
+    fn adds_web_socket_id_to_subscriptions() {
+        for (method, params, expected) in [
+            ("eth_subscribe", "[\"newHeads\"]", "[\"newHeads\",7]"),
+            ("eth_unsubscribe", "[\"0x1\"]", "[\"0x1\",7]"),
+            ("eth_blockNumber", "[]", "[]"),
+        ] {
+            let text = format!(
+                "{{\"id\":0,\"jsonrpc\":\"2.0\",\"method\":\"{method}\",\"params\":{params}}}"
+            );
+            let json: serde_json::Value =
+                serde_json::from_str(&maybe_add_web_socket_id(text, 7)).unwrap();
+            assert_eq!(json["params"].to_string(), expected, "{method}");
+        }
+    }
 
     #[test]
     fn can_parse_request() {
@@ -1,11 +1,22 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, Context};
+use axum::extract::DefaultBodyLimit;
+use axum::http::{HeaderName, HeaderValue, Method};
 use axum::routing::{get, post};
 use fvm_shared::econ::TokenAmount;
 use jsonrpc_v2::Data;
-use std::{net::ToSocketAddrs, sync::Arc, time::Duration};
+#[cfg(unix)]
+use std::fs::Permissions;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::PathBuf;
+use std::{net::ToSocketAddrs, str::FromStr, sync::Arc, time::Duration};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
 mod apis;
 mod cache;
@@ -15,16 +26,17 @@ mod error;
 mod filters;
 mod gas;
 mod handlers;
+mod middleware;
 mod state;
 
 pub use client::{HybridClient, HybridClientDriver};
+pub use middleware::{JsonRpcServer, Next, RpcMiddleware};
 
 use error::{error, JsonRpcError};
 use state::JsonRpcState;
 
 /// This is passed to every method handler. It's generic in the client type to facilitate testing with mocks.
 type JsonRpcData<C> = Data<JsonRpcState<C>>;
-type JsonRpcServer = Arc<jsonrpc_v2::Server<jsonrpc_v2::MapRouter>>;
 type JsonRpcResult<T> = Result<T, JsonRpcError>;
 
 /// This is the state we will pass to [axum] so that we can extract it in handlers.
@@ -32,6 +44,8 @@ type JsonRpcResult<T> = Result<T, JsonRpcError>;
 pub struct AppState {
     pub rpc_server: JsonRpcServer,
     pub rpc_state: Arc<JsonRpcState<HybridClient>>,
+    /// Maximum number of requests in an HTTP batch; unlimited if zero.
+    pub max_batch_size: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -41,48 +55,293 @@ pub struct GasOpt {
     pub max_fee_hist_size: u64,
 }
 
+/// Cross-Origin Resource Sharing options, allowing browser based applications
+/// to call the API directly.
+#[derive(Debug, Clone, Default)]
+pub struct CorsOpt {
+    /// Origins allowed to make cross-domain requests; `*` allows any origin.
+    /// CORS is disabled if the list is empty.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+}
+
+/// Options of the JSON-RPC endpoint served over HTTP.
+#[derive(Debug, Clone)]
+pub struct HttpOpt {
+    /// Maximum number of requests in a batch; unlimited if zero.
+    pub max_batch_size: usize,
+    /// Maximum size of a request body in bytes.
+    pub max_body_size: usize,
+    /// Compress the responses with gzip or deflate if the client accepts it.
+    pub compression: bool,
+}
+
+/// Which methods to serve, and limits on the ones executing messages,
+/// so that a public endpoint can't be overwhelmed by expensive requests.
+#[derive(Debug, Clone, Default)]
+pub struct MethodOpt {
+    /// Methods to serve, e.g. `eth_call`, or `debug_*` for a whole namespace; all of them if empty.
+    pub allowed: Vec<String>,
+    /// Methods not to serve, even if they are allowed.
+    pub disabled: Vec<String>,
+    /// Maximum gas of the messages executed by `eth_call`, `eth_estimateGas`, `debug_traceCall`
+    /// and `ipc_callAtParentFinality`; the block gas limit if zero.
+    pub max_call_gas: u64,
+    /// Maximum time to wait for the execution of those messages, and for `eth_getProof`;
+    /// unlimited if zero.
+    pub call_timeout: Duration,
+}
+
+impl MethodOpt {
+    /// Check whether a method is allowed and not disabled.
+    pub fn is_enabled(&self, method: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => method == pattern,
+        };
+        (self.allowed.is_empty() || self.allowed.iter().any(matches))
+            && !self.disabled.iter().any(matches)
+    }
+}
+
+/// Options to serve the API on a Unix domain socket, in addition to TCP,
+/// with access controlled by filesystem permissions.
+#[derive(Debug, Clone)]
+pub struct UnixSocketOpt {
+    pub path: PathBuf,
+    /// Permissions of the socket file, e.g. `0o600` to only allow the owner.
+    pub mode: u32,
+}
+
 /// Start listening to JSON-RPC requests.
+///
+/// Every request is passed through the `middleware`, in order, before reaching the method handler.
+#[allow(clippy::too_many_arguments)]
 pub async fn listen<A: ToSocketAddrs>(
     listen_addr: A,
     client: HybridClient,
     filter_timeout: Duration,
     cache_capacity: usize,
     gas_opt: GasOpt,
+    max_log_range: u64,
+    http_opt: HttpOpt,
+    method_opt: MethodOpt,
+    cors_opt: CorsOpt,
+    unix_socket_opt: Option<UnixSocketOpt>,
+    middleware: Vec<Arc<dyn RpcMiddleware>>,
 ) -> anyhow::Result<()> {
     if let Some(listen_addr) = listen_addr.to_socket_addrs()?.next() {
+        let cors = make_cors(&cors_opt).context("invalid CORS options")?;
         let rpc_state = Arc::new(JsonRpcState::new(
             client,
             filter_timeout,
             cache_capacity,
             gas_opt,
+            max_log_range,
+            method_opt.max_call_gas,
+            method_opt.call_timeout,
         ));
-        let rpc_server = make_server(rpc_state.clone());
+        let rpc_server = make_server(rpc_state.clone(), &method_opt, middleware);
         let app_state = AppState {
             rpc_server,
             rpc_state,
+            max_batch_size: http_opt.max_batch_size,
         };
-        let router = make_router(app_state);
-        let server = axum::Server::try_bind(&listen_addr)?.serve(router.into_make_service());
+        let router = make_router(app_state, &http_opt, cors);
+        let server =
+            axum::Server::try_bind(&listen_addr)?.serve(router.clone().into_make_service());
 
         tracing::info!(?listen_addr, "bound Ethereum API");
-        server.await?;
+
+        match unix_socket_opt {
+            None => server.await?,
+            #[cfg(not(unix))]
+            Some(opt) => bail!(
+                "cannot listen on {:?}: Unix sockets are not supported on this platform",
+                opt.path
+            ),
+            #[cfg(unix)]
+            Some(opt) => {
+                let listener = bind_unix_socket(&opt)?;
+                tracing::info!(path = ?opt.path, "bound Ethereum API to Unix socket");
+                let unix_server = serve_unix_socket(listener, router);
+                tokio::try_join!(async { Ok::<_, anyhow::Error>(server.await?) }, unix_server)?;
+            }
+        }
         Ok(())
     } else {
         Err(anyhow!("failed to convert to any socket address"))
     }
 }
 
+/// Bind a Unix domain socket and restrict access to it with the configured permissions.
+#[cfg(unix)]
+fn bind_unix_socket(opt: &UnixSocketOpt) -> anyhow::Result<UnixListener> {
+    // A socket left behind by a previous run would make binding fail.
+    if let Ok(meta) = std::fs::symlink_metadata(&opt.path) {
+        if !meta.file_type().is_socket() {
+            bail!("{:?} exists and is not a socket", opt.path);
+        }
+        std::fs::remove_file(&opt.path)
+            .with_context(|| format!("failed to remove stale socket {:?}", opt.path))?;
+    }
+
+    let listener = UnixListener::bind(&opt.path)
+        .with_context(|| format!("failed to bind Unix socket {:?}", opt.path))?;
+
+    std::fs::set_permissions(&opt.path, Permissions::from_mode(opt.mode))
+        .with_context(|| format!("failed to set permissions on {:?}", opt.path))?;
+
+    Ok(listener)
+}
+
+/// Serve the same routes as over TCP on a Unix domain socket.
+#[cfg(unix)]
+async fn serve_unix_socket(listener: UnixListener, router: axum::Router) -> anyhow::Result<()> {
+    let incoming = futures::stream::unfold(listener, |listener| async move {
+        let conn = listener.accept().await.map(|(stream, _)| stream);
+        Some((conn, listener))
+    });
+
+    axum::Server::builder(hyper::server::accept::from_stream(incoming))
+        .serve(router.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
 /// Register method handlers with the JSON-RPC server construct.
-fn make_server(state: Arc<JsonRpcState<HybridClient>>) -> JsonRpcServer {
+fn make_server(
+    state: Arc<JsonRpcState<HybridClient>>,
+    method_opt: &MethodOpt,
+    middleware: Vec<Arc<dyn RpcMiddleware>>,
+) -> JsonRpcServer {
     let server = jsonrpc_v2::Server::new().with_data(Data(state));
-    let server = apis::register_methods(server);
-    server.finish()
+    let server = apis::register_methods(server, method_opt);
+    JsonRpcServer::new(server.finish(), middleware)
 }
 
 /// Register routes in the `axum` HTTP router to handle JSON-RPC and WebSocket calls.
-fn make_router(state: AppState) -> axum::Router {
-    axum::Router::new()
-        .route("/", post(handlers::http::handle))
-        .route("/", get(handlers::ws::handle))
-        .with_state(state)
+fn make_router(state: AppState, http_opt: &HttpOpt, cors: Option<CorsLayer>) -> axum::Router {
+    let http = post(handlers::http::handle).layer(DefaultBodyLimit::max(http_opt.max_body_size));
+    // Only compress the JSON-RPC responses, not the WebSocket upgrades.
+    let http = if http_opt.compression {
+        http.layer(CompressionLayer::new())
+    } else {
+        http
+    };
+
+    let router = axum::Router::new()
+        .route("/", http)
+        .route("/", get(handlers::ws::handle));
+
+    let router = match cors {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+
+    router.with_state(state)
+}
+
+/// Build the CORS middleware from the options, or return `None` if CORS is not enabled.
+fn make_cors(opt: &CorsOpt) -> anyhow::Result<Option<CorsLayer>> {
+    if opt.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let any_origin = opt.allowed_origins.iter().any(|o| o == "*");
+
+    if any_origin && opt.allow_credentials {
+        bail!("credentials cannot be allowed together with any origin");
+    }
+
+    let origins = if any_origin {
+        AllowOrigin::any()
+    } else {
+        let origins = opt
+            .allowed_origins
+            .iter()
+            .map(|o| HeaderValue::from_str(o).with_context(|| format!("invalid origin: {o}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let methods = opt
+        .allowed_methods
+        .iter()
+        .map(|m| Method::from_str(m).with_context(|| format!("invalid method: {m}")))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let headers = opt
+        .allowed_headers
+        .iter()
+        .map(|h| HeaderName::from_str(h).with_context(|| format!("invalid header: {h}")))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let cors = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(opt.allow_credentials);
+
+    Ok(Some(cors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{make_cors, CorsOpt, MethodOpt};
+
+    fn cors_opt(origins: &[&str], allow_credentials: bool) -> CorsOpt {
+        CorsOpt {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allowed_methods: vec!["GET".into(), "POST".into()],
+            allowed_headers: vec!["content-type".into()],
+            allow_credentials,
+        }
+    }
+
+    #[test]
+    fn cors_disabled_without_origins() {
+        assert!(make_cors(&cors_opt(&[], false)).unwrap().is_none());
+    }
+
+    #[test]
+    fn cors_enabled_with_origins() {
+        assert!(make_cors(&cors_opt(&["*"], false)).unwrap().is_some());
+        assert!(make_cors(&cors_opt(&["https://example.com"], true))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn cors_rejects_credentials_with_any_origin() {
+        assert!(make_cors(&cors_opt(&["*"], true)).is_err());
+    }
+
+    #[test]
+    fn methods_enabled() {
+        let opt = MethodOpt::default();
+        assert!(opt.is_enabled("debug_traceCall"));
+
+        let opt = MethodOpt {
+            disabled: vec!["debug_*".into(), "eth_getLogs".into()],
+            ..Default::default()
+        };
+        assert!(!opt.is_enabled("debug_traceCall"));
+        assert!(!opt.is_enabled("eth_getLogs"));
+        assert!(opt.is_enabled("eth_getLogsAndMore"));
+        assert!(opt.is_enabled("eth_call"));
+
+        let opt = MethodOpt {
+            allowed: vec!["eth_*".into(), "net_version".into()],
+            disabled: vec!["eth_call".into()],
+            ..Default::default()
+        };
+        assert!(opt.is_enabled("eth_chainId"));
+        assert!(opt.is_enabled("net_version"));
+        assert!(!opt.is_enabled("net_peerCount"));
+        assert!(!opt.is_enabled("eth_call"));
+    }
 }
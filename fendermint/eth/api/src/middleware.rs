@@ -0,0 +1,181 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Interceptors around the dispatch of JSON-RPC requests to the method handlers,
+//! for concerns such as authorization, metrics, caching or validation which apply
+//! to many methods, without having to change each handler.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use jsonrpc_v2::{MapRouter, RequestObject, ResponseObjects, Server};
+
+/// A link in the middleware chain.
+///
+/// Implementations can inspect or modify the request, pass it on to the rest
+/// of the chain with [Next::run], and inspect or modify the response; or they
+/// can answer the request on their own without calling the method handler.
+#[async_trait]
+pub trait RpcMiddleware: Send + Sync {
+    async fn handle(&self, request: RequestObject, next: Next<'_>) -> ResponseObjects;
+}
+
+/// The rest of the middleware chain, ending with the method handler.
+pub struct Next<'a> {
+    server: &'a Server<MapRouter>,
+    chain: &'a [Arc<dyn RpcMiddleware>],
+}
+
+impl<'a> Next<'a> {
+    /// Pass the request to the next middleware, or the method handler if this was the last one.
+    pub async fn run(self, request: RequestObject) -> ResponseObjects {
+        match self.chain.split_first() {
+            Some((middleware, chain)) => {
+                let next = Next {
+                    server: self.server,
+                    chain,
+                };
+                middleware.handle(request, next).await
+            }
+            None => self.server.handle(request).await,
+        }
+    }
+}
+
+/// JSON-RPC server which passes every request through the middleware chain,
+/// in the order of registration, before calling the method handler.
+#[derive(Clone)]
+pub struct JsonRpcServer {
+    server: Arc<Server<MapRouter>>,
+    chain: Arc<Vec<Arc<dyn RpcMiddleware>>>,
+}
+
+impl JsonRpcServer {
+    pub fn new(server: Arc<Server<MapRouter>>, chain: Vec<Arc<dyn RpcMiddleware>>) -> Self {
+        Self {
+            server,
+            chain: Arc::new(chain),
+        }
+    }
+
+    /// Handle a single request.
+    pub async fn handle(&self, request: RequestObject) -> ResponseObjects {
+        let next = Next {
+            server: &self.server,
+            chain: &self.chain,
+        };
+        next.run(request).await
+    }
+
+    /// Handle a batch of requests, passing each of them through the middleware separately.
+    pub async fn handle_batch(&self, requests: Vec<RequestObject>) -> ResponseObjects {
+        let responses = futures::future::join_all(requests.into_iter().map(|r| self.handle(r)))
+            .await
+            .into_iter()
+            .flat_map(|r| match r {
+                ResponseObjects::Empty => Vec::new(),
+                ResponseObjects::One(r) => vec![r],
+                ResponseObjects::Many(rs) => rs,
+            })
+            .collect::<Vec<_>>();
+
+        if responses.is_empty() {
+            ResponseObjects::Empty
+        } else {
+            ResponseObjects::Many(responses)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use jsonrpc_v2::{Params, RequestObject, ResponseObjects, Server};
+
+    use super::{JsonRpcServer, Next, RpcMiddleware};
+
+    /// Records the order in which it was called.
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl RpcMiddleware for Recorder {
+        async fn handle(&self, request: RequestObject, next: Next<'_>) -> ResponseObjects {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{}:before", self.name));
+            let response = next.run(request).await;
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{}:after", self.name));
+            response
+        }
+    }
+
+    /// Answers every request without calling the handler.
+    struct Blocker;
+
+    #[async_trait]
+    impl RpcMiddleware for Blocker {
+        async fn handle(&self, _request: RequestObject, _next: Next<'_>) -> ResponseObjects {
+            ResponseObjects::Empty
+        }
+    }
+
+    async fn echo(
+        Params(params): Params<serde_json::Value>,
+    ) -> Result<serde_json::Value, jsonrpc_v2::Error> {
+        Ok(params)
+    }
+
+    fn server(chain: Vec<Arc<dyn RpcMiddleware>>) -> JsonRpcServer {
+        let server = Server::new().with_method("echo", echo).finish();
+        JsonRpcServer::new(server, chain)
+    }
+
+    fn request(id: u64) -> RequestObject {
+        serde_json::from_str(&format!(
+            r#"{{"jsonrpc":"2.0","method":"echo","params":[{id}],"id":{id}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn middleware_runs_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let recorder = |name| {
+            Arc::new(Recorder {
+                name,
+                log: log.clone(),
+            }) as Arc<dyn RpcMiddleware>
+        };
+        let server = server(vec![recorder("a"), recorder("b")]);
+
+        let response = server.handle(request(1)).await;
+        assert!(matches!(response, ResponseObjects::One(_)));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["a:before", "b:before", "b:after", "a:after"]
+        );
+    }
+
+    #[tokio::test]
+    async fn middleware_can_short_circuit() {
+        let server = server(vec![Arc::new(Blocker)]);
+        let response = server.handle_batch(vec![request(1), request(2)]).await;
+        assert!(matches!(response, ResponseObjects::Empty));
+    }
+
+    #[tokio::test]
+    async fn batch_without_middleware() {
+        let server = server(Vec::new());
+        let response = server.handle_batch(vec![request(1), request(2)]).await;
+        assert!(matches!(response, ResponseObjects::Many(rs) if rs.len() == 2));
+    }
+}
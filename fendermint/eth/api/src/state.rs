@@ -4,21 +4,27 @@
 //! Tendermint RPC helper methods for the implementation of the APIs.
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 use anyhow::{anyhow, Context};
 use cid::Cid;
+use ethers_core::abi::{AbiDecode, AbiEncode};
 use ethers_core::types::{self as et};
 use fendermint_rpc::client::{FendermintClient, TendermintClient};
 use fendermint_rpc::query::QueryClient;
-use fendermint_vm_actor_interface::{evm, system};
-use fendermint_vm_message::query::{ActorState, FvmQueryHeight};
+use fendermint_vm_actor_interface::eam::EthAddress;
+use fendermint_vm_actor_interface::{evm, ipc, system};
+use fendermint_vm_message::query::{ActorState, FvmQueryHeight, TxIndexQuery};
 use fendermint_vm_message::signed::DomainHash;
 use fendermint_vm_message::{chain::ChainMessage, conv::from_eth::to_fvm_address};
-use fvm_ipld_encoding::{de::DeserializeOwned, RawBytes};
+use fvm_ipld_encoding::{de::DeserializeOwned, BytesDe, BytesSer, RawBytes};
 use fvm_shared::{chainid::ChainID, econ::TokenAmount, error::ExitCode, message::Message};
+use ipc_actors_abis::gateway_getter_facet::{
+    GetLatestParentFinalityCall, GetLatestParentFinalityReturn, ParentFinality,
+};
 use rand::Rng;
 use tendermint::block::Height;
 use tendermint_rpc::query::Query;
@@ -46,6 +52,12 @@ use crate::{
 pub type WebSocketId = usize;
 pub type WebSocketSender = UnboundedSender<MethodNotification>;
 
+/// An open web socket, with the subscriptions to uninstall when it's closed.
+struct WebSocketState {
+    sender: WebSocketSender,
+    subscriptions: HashSet<FilterId>,
+}
+
 // Made generic in the client type so we can mock it if we want to test API
 // methods without having to spin up a server. In those tests the methods
 // below would not be used, so those aren't generic; we'd directly invoke
@@ -56,8 +68,14 @@ pub struct JsonRpcState<C> {
     filter_timeout: Duration,
     filters: FilterMap,
     next_web_socket_id: AtomicUsize,
-    web_sockets: RwLock<HashMap<WebSocketId, WebSocketSender>>,
+    web_sockets: RwLock<HashMap<WebSocketId, WebSocketState>>,
     pub gas_opt: GasOpt,
+    /// Maximum number of blocks `eth_getLogs` can look at; unlimited if zero.
+    pub max_log_range: u64,
+    /// Maximum gas of read-only calls; the block gas limit if zero.
+    pub max_call_gas: u64,
+    /// Maximum time to wait for read-only calls; unlimited if zero.
+    pub call_timeout: Duration,
 }
 
 impl<C> JsonRpcState<C>
@@ -69,6 +87,9 @@ where
         filter_timeout: Duration,
         cache_capacity: usize,
         gas_opt: GasOpt,
+        max_log_range: u64,
+        max_call_gas: u64,
+        call_timeout: Duration,
     ) -> Self {
         let client = FendermintClient::new(client);
         let addr_cache = AddressCache::new(client.clone(), cache_capacity);
@@ -80,6 +101,9 @@ where
             next_web_socket_id: Default::default(),
             web_sockets: Default::default(),
             gas_opt,
+            max_log_range,
+            max_call_gas,
+            call_timeout,
         }
     }
 }
@@ -90,18 +114,58 @@ impl<C> JsonRpcState<C> {
         self.client.underlying()
     }
 
+    /// Limit the gas a read-only call can use to `max_call_gas`.
+    pub fn cap_call_gas(&self, msg: &mut Message) {
+        if self.max_call_gas > 0 && (msg.gas_limit == 0 || msg.gas_limit > self.max_call_gas) {
+            msg.gas_limit = self.max_call_gas;
+        }
+    }
+
+    /// Wait for a read-only call at most `call_timeout`.
+    ///
+    /// This only stops waiting: the query can't be cancelled, so the node carries on executing
+    /// the message after a timeout. It's the gas limit set with [Self::cap_call_gas] which bounds
+    /// the work it does, so every message passed here should have been capped first.
+    pub async fn with_call_timeout<T, F>(&self, f: F) -> JsonRpcResult<T>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        if self.call_timeout.is_zero() {
+            return Ok(f.await?);
+        }
+        match tokio::time::timeout(self.call_timeout, f).await {
+            Ok(res) => Ok(res?),
+            Err(_) => error(
+                ExitCode::USR_UNSPECIFIED,
+                format!("call timed out after {:?}", self.call_timeout),
+            ),
+        }
+    }
+
     /// Register the sender of a web socket.
     pub async fn add_web_socket(&self, tx: WebSocketSender) -> WebSocketId {
         let next_id = self.next_web_socket_id.fetch_add(1, Ordering::Relaxed);
         let mut guard = self.web_sockets.write().await;
-        guard.insert(next_id, tx);
+        guard.insert(
+            next_id,
+            WebSocketState {
+                sender: tx,
+                subscriptions: Default::default(),
+            },
+        );
         next_id
     }
 
-    /// Remove the sender of a web socket.
+    /// Remove the sender of a web socket and uninstall its subscriptions.
     pub async fn remove_web_socket(&self, id: &WebSocketId) {
-        let mut guard = self.web_sockets.write().await;
-        guard.remove(id);
+        let removed = self.web_sockets.write().await.remove(id);
+        if let Some(ws) = removed {
+            for filter_id in ws.subscriptions {
+                if let Err(e) = self.uninstall_filter(filter_id).await {
+                    tracing::debug!(?filter_id, "failed to uninstall subscription: {e}");
+                }
+            }
+        }
     }
 
     /// Get the sender of a web socket.
@@ -109,9 +173,30 @@ impl<C> JsonRpcState<C> {
         let guard = self.web_sockets.read().await;
         guard
             .get(id)
-            .cloned()
+            .map(|ws| ws.sender.clone())
             .ok_or_else(|| anyhow!("web socket not found"))
     }
+
+    /// Uninstall a subscription made through a web socket, unless it belongs to another one.
+    pub async fn remove_subscription(
+        &self,
+        id: &WebSocketId,
+        filter_id: FilterId,
+    ) -> anyhow::Result<bool> {
+        let removed = self
+            .web_sockets
+            .write()
+            .await
+            .get_mut(id)
+            .map(|ws| ws.subscriptions.remove(&filter_id))
+            .unwrap_or_default();
+
+        if removed {
+            self.uninstall_filter(filter_id).await
+        } else {
+            Ok(false)
+        }
+    }
 }
 
 /// Represents the actor type of a concrete actor.
@@ -364,16 +449,70 @@ where
         // Doesn't work with `Query::from(EventType::Tx).and_eq()`
         let query = Query::eq("eth.hash", hex::encode(tx_hash.as_bytes()));
 
-        match self
+        let res = self
             .tm()
             .tx_search(query, false, 1, 1, Order::Ascending)
-            .await
-        {
-            Ok(res) => Ok(res.txs.into_iter().next()),
+            .await;
+
+        if let Ok(Some(tx)) = res.as_ref().map(|r| r.txs.first()) {
+            return Ok(Some(tx.clone()));
+        }
+
+        // Fall back to the transaction index of the application, if it's enabled,
+        // which works even if CometBFT doesn't index transactions.
+        match self.indexed_tx_by_hash(tx_hash).await {
+            Ok(Some(tx)) => return Ok(Some(tx)),
+            Ok(None) => {}
+            Err(e) => tracing::debug!(error = e.to_string(), "failed to look up indexed tx"),
+        }
+
+        match res {
+            Ok(_) => Ok(None),
             Err(e) => error(ExitCode::USR_UNSPECIFIED, e),
         }
     }
 
+    /// Get the Tendermint transaction by hash from the transaction index of the application,
+    /// putting it together from the block and the block results it was indexed at.
+    async fn indexed_tx_by_hash(
+        &self,
+        tx_hash: et::TxHash,
+    ) -> JsonRpcResult<Option<tendermint_rpc::endpoint::tx::Response>> {
+        let txs = self
+            .client
+            .indexed_txs(TxIndexQuery::Hash(tx_hash.as_bytes().to_vec()))
+            .await?;
+
+        let Some(indexed) = txs.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let height = Height::try_from(indexed.height).context("invalid indexed height")?;
+        let index = indexed.index as usize;
+        let block: block::Response = self.tm().block(height).await?;
+        let block_results: block_results::Response = self.tm().block_results(height).await?;
+
+        let tx = block
+            .block
+            .data()
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow!("indexed tx missing from block {height}"))?;
+        let tx_result = block_results
+            .txs_results
+            .and_then(|rs| rs.into_iter().nth(index))
+            .ok_or_else(|| anyhow!("indexed tx missing from the results of block {height}"))?;
+
+        Ok(Some(tendermint_rpc::endpoint::tx::Response {
+            hash: from_tm::tx_hash(&tx),
+            height,
+            index: indexed.index as u32,
+            tx_result,
+            tx,
+            proof: None,
+        }))
+    }
+
     /// Send a message by the system actor to an EVM actor for a read-only query.
     ///
     /// If the actor doesn't exist then the FVM will create a placeholder actor,
@@ -436,6 +575,67 @@ where
         }
     }
 
+    /// Query the latest parent finality the gateway has committed to, as of a given height.
+    pub async fn latest_parent_finality(
+        &self,
+        height: FvmQueryHeight,
+    ) -> JsonRpcResult<ParentFinality> {
+        let gateway = et::H160::from(EthAddress::from_id(ipc::GATEWAY_ACTOR_ID).0);
+        let calldata = GetLatestParentFinalityCall.encode();
+        let params =
+            RawBytes::serialize(BytesSer(&calldata)).context("failed to serialize calldata")?;
+
+        let return_data = self
+            .read_evm_actor::<BytesDe>(gateway, evm::Method::InvokeContract, params, height)
+            .await?
+            .ok_or_else(|| anyhow!("the gateway did not return a parent finality"))?;
+
+        let ret = GetLatestParentFinalityReturn::decode(return_data.0)
+            .context("failed to decode parent finality")?;
+
+        Ok(ret.0)
+    }
+
+    /// Find the first block in which the committed parent finality reached at least `parent_height`.
+    ///
+    /// The finality is monotonic in the child height, so we can do a binary search over the blocks.
+    /// This relies on the historical state of the blocks being available, ie. not pruned.
+    pub async fn parent_finality_block(
+        &self,
+        parent_height: u64,
+    ) -> JsonRpcResult<(tendermint::block::Height, ParentFinality)> {
+        let latest = self.latest_height().await?.value();
+        // The effects of a block are visible in the state at the next height.
+        let finality_at = |h: u64| self.latest_parent_finality(FvmQueryHeight::Height(h + 1));
+
+        let mut hi_finality = finality_at(latest).await?;
+        if hi_finality.height < et::U256::from(parent_height) {
+            return error(
+                ExitCode::USR_NOT_FOUND,
+                format!(
+                    "parent height {parent_height} has not been finalized yet; latest finality is {}",
+                    hi_finality.height
+                ),
+            );
+        }
+
+        let (mut lo, mut hi) = (1, latest);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let finality = finality_at(mid).await?;
+            if finality.height >= et::U256::from(parent_height) {
+                hi = mid;
+                hi_finality = finality;
+            } else {
+                lo = mid + 1;
+            }
+        }
+
+        let height = Height::try_from(hi).context("failed to convert to height")?;
+
+        Ok((height, hi_finality))
+    }
+
     pub async fn get_actor_type(
         &self,
         address: &et::H160,
@@ -561,12 +761,30 @@ where
     }
 
     /// Create a new subscription, subscribe with Tendermint and start handlers in the background.
+    ///
+    /// The subscription is uninstalled when the web socket is closed.
     pub async fn new_subscription(
         &self,
         kind: FilterKind,
-        ws_sender: WebSocketSender,
+        web_socket_id: WebSocketId,
     ) -> anyhow::Result<FilterId> {
-        self.new_filter_driver(kind, Some(ws_sender)).await
+        let ws_sender = self.get_web_socket(&web_socket_id).await?;
+        let filter_id = self.new_filter_driver(kind, Some(ws_sender)).await?;
+
+        let added = self
+            .web_sockets
+            .write()
+            .await
+            .get_mut(&web_socket_id)
+            .map(|ws| ws.subscriptions.insert(filter_id))
+            .is_some();
+
+        if !added {
+            // The socket was closed while we were subscribing.
+            self.uninstall_filter(filter_id).await?;
+            return Err(anyhow!("web socket not found"));
+        }
+        Ok(filter_id)
     }
 }
 
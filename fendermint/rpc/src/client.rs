@@ -129,6 +129,10 @@ where
     async fn perform(&self, query: FvmQuery, height: FvmQueryHeight) -> anyhow::Result<AbciQuery> {
         perform_query(&self.inner, query, height).await
     }
+
+    async fn perform_at_path(&self, path: &str, data: Vec<u8>) -> anyhow::Result<AbciQuery> {
+        perform_path_query(&self.inner, path, data).await
+    }
 }
 
 /// Fendermint client capable of signing transactions.
@@ -166,6 +170,10 @@ where
     async fn perform(&self, query: FvmQuery, height: FvmQueryHeight) -> anyhow::Result<AbciQuery> {
         perform_query(&self.inner, query, height).await
     }
+
+    async fn perform_at_path(&self, path: &str, data: Vec<u8>) -> anyhow::Result<AbciQuery> {
+        perform_path_query(&self.inner, path, data).await
+    }
 }
 
 #[async_trait]
@@ -258,3 +266,15 @@ where
 
     Ok(res)
 }
+
+async fn perform_path_query<C>(client: &C, path: &str, data: Vec<u8>) -> anyhow::Result<AbciQuery>
+where
+    C: Client + Sync + Send,
+{
+    tracing::debug!(path, "perform ABCI query");
+    let res = client
+        .abci_query(Some(path.to_owned()), data, None, false)
+        .await?;
+
+    Ok(res)
+}
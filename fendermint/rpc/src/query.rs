@@ -15,8 +15,14 @@ use cid::Cid;
 use fvm_shared::ActorID;
 use fvm_shared::{address::Address, error::ExitCode};
 
+use fendermint_vm_actor_interface::evm;
 use fendermint_vm_message::query::{
-    ActorState, BuiltinActors, FvmQuery, FvmQueryHeight, GasEstimate, StateParams,
+    ActorProof, ActorState, ActorStatsQuery, ActorStatsReport, BuiltinActors, CallTrace, FvmQuery,
+    FvmQueryHeight, GasEstimate, IndexedTx, LogIndexQuery, ParentFinalityLogEntry, PendingReceipt,
+    StateParams, TopDownAdminCommand, TopDownAdminRequest, TopDownParams, TopDownStatus,
+    TxIndexQuery, VoteTally, ACTOR_STATS_QUERY_PATH, EXEC_TRACE_QUERY_PATH, LOG_INDEX_QUERY_PATH,
+    PARENT_FINALITY_LOG_QUERY_PATH, PENDING_RECEIPT_QUERY_PATH, TOPDOWN_ADMIN_QUERY_PATH,
+    TOPDOWN_PARAMS_QUERY_PATH, TX_INDEX_QUERY_PATH, VOTE_TALLY_QUERY_PATH,
 };
 
 use crate::response::encode_data;
@@ -63,6 +69,42 @@ pub trait QueryClient: Sync {
         Ok(QueryResponse { height, value })
     }
 
+    /// Run a message in a read-only fashion, recording the calls it makes.
+    async fn trace_call(
+        &self,
+        message: Message,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<QueryResponse<Option<CallTrace>>> {
+        let res = self
+            .perform(FvmQuery::TraceCall(Box::new(message)), height)
+            .await?;
+        let height = res.height;
+        let value = extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode CallTrace from query")
+        })?;
+        Ok(QueryResponse { height, value })
+    }
+
+    /// Query the state of an actor and the values of EVM storage slots, with the IPLD blocks
+    /// proving them against the app hash of the block after the queried height.
+    async fn actor_proof(
+        &self,
+        address: &Address,
+        keys: Vec<evm::uints::U256>,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<QueryResponse<ActorProof>> {
+        let res = self
+            .perform(FvmQuery::ActorProof(*address, keys), height)
+            .await?;
+        let height = res.height;
+        let value = extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode ActorProof from query")
+        })?;
+        Ok(QueryResponse { height, value })
+    }
+
     /// Estimate the gas limit of a message.
     async fn estimate_gas(
         &self,
@@ -114,8 +156,117 @@ pub trait QueryClient: Sync {
         Ok(QueryResponse { height, value })
     }
 
+    /// The parent finality votes collected by the node.
+    ///
+    /// These aren't part of the ledger, so they can only be queried as they are now.
+    async fn vote_tally(&self) -> anyhow::Result<VoteTally> {
+        let res = self
+            .perform_at_path(VOTE_TALLY_QUERY_PATH, Vec::new())
+            .await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode VoteTally from query")
+        })
+    }
+
+    /// Execute a top-down finality admin command on the node, authorized by its admin token,
+    /// and return the resulting state.
+    async fn topdown_admin(
+        &self,
+        token: String,
+        command: TopDownAdminCommand,
+    ) -> anyhow::Result<TopDownStatus> {
+        let data = fvm_ipld_encoding::to_vec(&TopDownAdminRequest { token, command })?;
+        let res = self.perform_at_path(TOPDOWN_ADMIN_QUERY_PATH, data).await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode TopDownStatus from query")
+        })
+    }
+
+    /// The top-down finality settings the node is running with.
+    async fn topdown_params(&self) -> anyhow::Result<TopDownParams> {
+        let res = self
+            .perform_at_path(TOPDOWN_PARAMS_QUERY_PATH, Vec::new())
+            .await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode TopDownParams from query")
+        })
+    }
+
+    /// The parent finality committed for a parent height, from the log kept by the node.
+    async fn parent_finality_log(
+        &self,
+        parent_height: u64,
+    ) -> anyhow::Result<Option<ParentFinalityLogEntry>> {
+        let data = fvm_ipld_encoding::to_vec(&parent_height)?;
+        let res = self
+            .perform_at_path(PARENT_FINALITY_LOG_QUERY_PATH, data)
+            .await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode ParentFinalityLogEntry from query")
+        })
+    }
+
+    /// The executed transactions matching a query, from the transaction index kept by the node.
+    async fn indexed_txs(&self, query: TxIndexQuery) -> anyhow::Result<Vec<IndexedTx>> {
+        let data = fvm_ipld_encoding::to_vec(&query)?;
+        let res = self.perform_at_path(TX_INDEX_QUERY_PATH, data).await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode IndexedTx list from query")
+        })
+    }
+
+    /// The call tree of a transaction, from the execution traces recorded by the node.
+    async fn exec_trace(&self, hash: &[u8]) -> anyhow::Result<Option<CallTrace>> {
+        let res = self
+            .perform_at_path(EXEC_TRACE_QUERY_PATH, hash.to_vec())
+            .await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode CallTrace from query")
+        })
+    }
+
+    /// The expected outcome of a transaction waiting in the mempool, if the node keeps pending receipts.
+    async fn pending_receipt(&self, hash: &[u8]) -> anyhow::Result<Option<PendingReceipt>> {
+        let res = self
+            .perform_at_path(PENDING_RECEIPT_QUERY_PATH, hash.to_vec())
+            .await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode PendingReceipt from query")
+        })
+    }
+
+    /// The resources used by the actors in the recent blocks, if the node keeps actor statistics.
+    async fn actor_stats(&self, query: ActorStatsQuery) -> anyhow::Result<ActorStatsReport> {
+        let data = fvm_ipld_encoding::to_vec(&query)?;
+        let res = self.perform_at_path(ACTOR_STATS_QUERY_PATH, data).await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value)
+                .context("failed to decode ActorStatsReport from query")
+        })
+    }
+
+    /// The heights in a range which may contain Ethereum logs matching a filter,
+    /// from the log index kept by the node.
+    async fn log_index(&self, query: LogIndexQuery) -> anyhow::Result<Vec<u64>> {
+        let data = fvm_ipld_encoding::to_vec(&query)?;
+        let res = self.perform_at_path(LOG_INDEX_QUERY_PATH, data).await?;
+        extract(res, |res| {
+            fvm_ipld_encoding::from_slice(&res.value).context("failed to decode heights from query")
+        })
+    }
+
     /// Run an ABCI query.
     async fn perform(&self, query: FvmQuery, height: FvmQueryHeight) -> anyhow::Result<AbciQuery>;
+
+    /// Run an ABCI query on a path served by the node outside the ledger.
+    async fn perform_at_path(&self, path: &str, data: Vec<u8>) -> anyhow::Result<AbciQuery>;
 }
 
 /// Extract some value from the query result, unless it's not found or other error.
@@ -137,8 +288,9 @@ where
 {
     if res.code.is_err() {
         Err(anyhow!(
-            "query returned non-zero exit code: {}",
-            res.code.value()
+            "query returned non-zero exit code: {}; {}",
+            res.code.value(),
+            res.info
         ))
     } else {
         f(res)
@@ -0,0 +1,74 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+
+use crate::SledDb;
+
+impl Blockstore for SledDb {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.db.get(k.to_bytes())?.map(|v| v.to_vec()))
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.db.insert(k.to_bytes(), block)?;
+        Ok(())
+    }
+
+    // Called by the BufferedBlockstore during flush.
+    fn put_many_keyed<D, I>(&self, blocks: I) -> anyhow::Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        Ok(self.db.apply_batch(to_batch(blocks))?)
+    }
+}
+
+/// A [`Blockstore`] implementation that writes to a specific namespace, not the default like above.
+#[derive(Clone)]
+pub struct NamespaceBlockstore {
+    tree: sled::Tree,
+}
+
+impl NamespaceBlockstore {
+    /// Unlike column families in RocksDB, trees are created on demand.
+    pub fn new(db: SledDb, ns: String) -> anyhow::Result<Self> {
+        let tree = db.db.open_tree(ns)?;
+        Ok(Self { tree })
+    }
+}
+
+impl Blockstore for NamespaceBlockstore {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.tree.get(k.to_bytes())?.map(|v| v.to_vec()))
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.tree.insert(k.to_bytes(), block)?;
+        Ok(())
+    }
+
+    // Called by the BufferedBlockstore during flush.
+    fn put_many_keyed<D, I>(&self, blocks: I) -> anyhow::Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        Ok(self.tree.apply_batch(to_batch(blocks))?)
+    }
+}
+
+fn to_batch<D, I>(blocks: I) -> sled::Batch
+where
+    D: AsRef<[u8]>,
+    I: IntoIterator<Item = (Cid, D)>,
+{
+    let mut batch = sled::Batch::default();
+    for (cid, v) in blocks.into_iter() {
+        batch.insert(cid.to_bytes(), v.as_ref());
+    }
+    batch
+}
@@ -0,0 +1,303 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak};
+
+use fendermint_storage::{
+    Decode, Encode, KVError, KVRead, KVReadable, KVResult, KVStore, KVTransaction, KVWritable,
+    KVWrite,
+};
+
+use crate::{Preserved, SledDb};
+
+/// Sled has no snapshots, so reads go to the database, except for the keys
+/// which were overwritten since the transaction started: their original
+/// values are preserved by the commits, as long as the transaction is alive.
+pub struct SledDbReadTx<'a> {
+    kv: &'a sled::Tree,
+    preserved: Arc<Preserved>,
+}
+
+/// Writes are buffered in memory and applied as a single atomic batch on commit.
+///
+/// There is no conflict detection: if two transactions write the same key,
+/// the one committed last wins. The application only commits from a single
+/// thread, which is what makes this acceptable.
+pub struct SledDbWriteTx<'a> {
+    db: &'a SledDb,
+    /// Pending changes; `None` marks a deletion.
+    changes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<S> KVReadable<S> for SledDb
+where
+    S: KVStore<Repr = Vec<u8>>,
+    S::Namespace: AsRef<str>,
+{
+    type Tx<'a> = SledDbReadTx<'a>
+    where
+        Self: 'a;
+
+    fn read(&self) -> Self::Tx<'_> {
+        let preserved = Arc::new(Preserved::default());
+        // Don't start in the middle of a commit, or we could see some of its changes but not others.
+        let _guard = self.commit_lock.lock().unwrap();
+        self.readers
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&preserved));
+        SledDbReadTx {
+            kv: &self.kv,
+            preserved,
+        }
+    }
+}
+
+impl<S> KVWritable<S> for SledDb
+where
+    S: KVStore<Repr = Vec<u8>>,
+    S::Namespace: AsRef<str>,
+{
+    type Tx<'a> = SledDbWriteTx<'a>
+    where
+        Self: 'a;
+
+    fn write(&self) -> Self::Tx<'_> {
+        SledDbWriteTx {
+            db: self,
+            changes: Default::default(),
+        }
+    }
+}
+
+impl<'a, S> KVRead<S> for SledDbReadTx<'a>
+where
+    S: KVStore<Repr = Vec<u8>>,
+    S::Namespace: AsRef<str>,
+{
+    fn get<K, V>(&self, ns: &S::Namespace, k: &K) -> KVResult<Option<V>>
+    where
+        S: Encode<K> + Decode<V>,
+    {
+        let key = ns_key(ns.as_ref(), &S::to_repr(k)?);
+
+        // Hold the lock while reading from the tree, so a commit can't change the value
+        // between us checking that it hasn't been preserved and reading it.
+        let preserved = self.preserved.lock().unwrap();
+        let res = match preserved.get(&key) {
+            Some(value) => value.clone(),
+            None => self.kv.get(key).map_err(to_kv_error)?.map(|bz| bz.to_vec()),
+        };
+
+        match res {
+            Some(bz) => Ok(Some(S::from_repr(&bz)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, S> KVRead<S> for SledDbWriteTx<'a>
+where
+    S: KVStore<Repr = Vec<u8>>,
+    S::Namespace: AsRef<str>,
+{
+    fn get<K, V>(&self, ns: &S::Namespace, k: &K) -> KVResult<Option<V>>
+    where
+        S: Encode<K> + Decode<V>,
+    {
+        let key = ns_key(ns.as_ref(), &S::to_repr(k)?);
+
+        // Read our own writes first.
+        let res = match self.changes.get(&key) {
+            Some(change) => change.clone(),
+            None => self
+                .db
+                .kv
+                .get(key)
+                .map_err(to_kv_error)?
+                .map(|bz| bz.to_vec()),
+        };
+
+        match res {
+            Some(bz) => Ok(Some(S::from_repr(&bz)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, S> KVWrite<S> for SledDbWriteTx<'a>
+where
+    S: KVStore<Repr = Vec<u8>>,
+    S::Namespace: AsRef<str>,
+{
+    fn put<K, V>(&mut self, ns: &S::Namespace, k: &K, v: &V) -> KVResult<()>
+    where
+        S: Encode<K> + Encode<V>,
+    {
+        let key = ns_key(ns.as_ref(), &S::to_repr(k)?);
+        let v = S::to_repr(v)?;
+        self.changes.insert(key, Some(v.into_owned()));
+        Ok(())
+    }
+
+    fn delete<K>(&mut self, ns: &S::Namespace, k: &K) -> KVResult<()>
+    where
+        S: Encode<K>,
+    {
+        let key = ns_key(ns.as_ref(), &S::to_repr(k)?);
+        self.changes.insert(key, None);
+        Ok(())
+    }
+}
+
+impl<'a> KVTransaction for SledDbWriteTx<'a> {
+    fn commit(self) -> KVResult<()> {
+        let _guard = self.db.commit_lock.lock().unwrap();
+
+        // Keep the current values for the read transactions which are still open.
+        let readers = {
+            let mut readers = self.db.readers.lock().unwrap();
+            readers.retain(|r| r.strong_count() > 0);
+            readers.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+        };
+        if !readers.is_empty() {
+            for k in self.changes.keys() {
+                let current = self.db.kv.get(k).map_err(to_kv_error)?;
+                for r in readers.iter() {
+                    r.lock()
+                        .unwrap()
+                        .entry(k.clone())
+                        .or_insert_with(|| current.as_ref().map(|bz| bz.to_vec()));
+                }
+            }
+        }
+
+        let mut batch = sled::Batch::default();
+        for (k, v) in self.changes {
+            match v {
+                Some(v) => batch.insert(k, v),
+                None => batch.remove(k),
+            }
+        }
+        self.db.kv.apply_batch(batch).map_err(to_kv_error)
+    }
+
+    fn rollback(self) -> KVResult<()> {
+        Ok(())
+    }
+}
+
+/// Prefix the key with the namespace, so all namespaces can share the same tree.
+///
+/// The length of the namespace goes first, so that one namespace being the prefix
+/// of another cannot make their keys collide.
+fn ns_key(ns: &str, k: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(2 + ns.len() + k.len());
+    key.extend_from_slice(&(ns.len() as u16).to_be_bytes());
+    key.extend_from_slice(ns.as_bytes());
+    key.extend_from_slice(k);
+    key
+}
+
+fn to_kv_error(e: sled::Error) -> KVError {
+    KVError::Unexpected(Box::new(e))
+}
+
+#[cfg(all(feature = "kvstore", test))]
+mod tests {
+    use std::borrow::Cow;
+
+    use quickcheck::{QuickCheck, Testable};
+    use serde::{de::DeserializeOwned, Serialize};
+
+    use fendermint_storage::{testing::*, Codec, Decode, Encode, KVError, KVResult, KVStore};
+
+    use crate::SledDb;
+
+    const TEST_COUNT: u64 = 20;
+
+    #[derive(Clone)]
+    struct TestKVStore;
+
+    impl KVStore for TestKVStore {
+        type Namespace = TestNamespace;
+        type Repr = Vec<u8>;
+    }
+
+    impl<T: Serialize> Encode<T> for TestKVStore {
+        fn to_repr(value: &T) -> KVResult<Cow<Self::Repr>> {
+            fvm_ipld_encoding::to_vec(value)
+                .map_err(|e| KVError::Codec(Box::new(e)))
+                .map(Cow::Owned)
+        }
+    }
+    impl<T: DeserializeOwned> Decode<T> for TestKVStore {
+        fn from_repr(repr: &Self::Repr) -> KVResult<T> {
+            fvm_ipld_encoding::from_slice(repr).map_err(|e| KVError::Codec(Box::new(e)))
+        }
+    }
+
+    impl<T> Codec<T> for TestKVStore where TestKVStore: Encode<T> + Decode<T> {}
+
+    fn new_backend() -> SledDb {
+        let dir = tempfile::Builder::new()
+            .tempdir()
+            .expect("error creating temporary path for db");
+        let path = dir.path().join("sled");
+        SledDb::open(path).expect("error creating sled")
+    }
+
+    fn run_quickcheck<F: Testable>(f: F) {
+        QuickCheck::new().tests(TEST_COUNT).quickcheck(f)
+    }
+
+    #[test]
+    fn writable() {
+        run_quickcheck(
+            (|data| {
+                let backend = new_backend();
+                check_writable::<TestKVStore>(&backend, data)
+            }) as fn(TestData) -> bool,
+        )
+    }
+
+    #[test]
+    fn write_isolation() {
+        run_quickcheck(
+            (|data| {
+                let backend = new_backend();
+                check_write_isolation::<TestKVStore>(&backend, data)
+            }) as fn(TestDataMulti<2>) -> bool,
+        )
+    }
+
+    #[test]
+    fn write_isolation_concurrent() {
+        run_quickcheck(
+            (|data1, data2| {
+                let backend = new_backend();
+                check_write_isolation_concurrent::<TestKVStore, _>(&backend, data1, data2)
+            }) as fn(TestData, TestData) -> bool,
+        )
+    }
+
+    #[test]
+    fn write_serialization_concurrent() {
+        run_quickcheck(
+            (|data1, data2| {
+                let backend = new_backend();
+                check_write_serialization_concurrent::<TestKVStore, _>(&backend, data1, data2)
+            }) as fn(TestData, TestData) -> bool,
+        )
+    }
+
+    #[test]
+    fn read_isolation() {
+        run_quickcheck(
+            (|data| {
+                let backend = new_backend();
+                check_read_isolation::<TestKVStore, _>(&backend, data)
+            }) as fn(TestData) -> bool,
+        )
+    }
+}
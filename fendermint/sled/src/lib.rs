@@ -0,0 +1,67 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! An alternative to `fendermint_rocksdb` using the pure Rust [sled](https://docs.rs/sled) database,
+//! implementing the same storage traits.
+
+#[cfg(feature = "blockstore")]
+pub mod blockstore;
+#[cfg(feature = "kvstore")]
+mod kvstore;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+
+pub use sled::Error as SledDbError;
+
+/// The tree holding the data written through the `KVStore` abstraction.
+///
+/// All namespaces are kept in a single tree, so that a transaction touching
+/// multiple namespaces can be applied as one atomic batch.
+const KV_TREE: &str = "__kvstore";
+
+/// `SledDb` is used as the KV store and the blockstore.
+///
+/// Usage:
+/// ```no_run
+/// use fendermint_sled::SledDb;
+///
+/// let db = SledDb::open("test_db").unwrap();
+/// ```
+#[derive(Clone)]
+#[cfg_attr(not(feature = "kvstore"), allow(dead_code))]
+pub struct SledDb {
+    pub db: sled::Db,
+    kv: sled::Tree,
+    /// Serializes commits and the start of read transactions.
+    commit_lock: Arc<Mutex<()>>,
+    /// The values open read transactions have to keep seeing after a commit overwrote them.
+    readers: Arc<Mutex<Vec<Weak<Preserved>>>>,
+}
+
+/// Values as they were when a read transaction started, for the keys written since;
+/// `None` marks a key that didn't exist.
+type Preserved = Mutex<HashMap<Vec<u8>, Option<Vec<u8>>>>;
+
+impl SledDb {
+    /// Open the database, creating it if it doesn't exist.
+    pub fn open<P>(path: P) -> Result<Self, SledDbError>
+    where
+        P: AsRef<Path>,
+    {
+        let db = sled::open(path)?;
+        let kv = db.open_tree(KV_TREE)?;
+        Ok(Self {
+            db,
+            kv,
+            commit_lock: Default::default(),
+            readers: Default::default(),
+        })
+    }
+
+    /// Flush all pending writes to disk.
+    pub fn flush(&self) -> Result<(), SledDbError> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,25 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+//! Storage abstraction for application state that isn't kept in the IPLD blockstore.
+//!
+//! A storage backend is a crate implementing these traits for its database handle:
+//!
+//! * [KVReadable] and [KVWritable] to start transactions, where the read transactions
+//!   implement [KVRead], and the write transactions [KVRead], [KVWrite] and [KVTransaction];
+//! * `fvm_ipld_blockstore::Blockstore` to hold the IPLD data of the state tree.
+//!
+//! The application only depends on the backend through these traits, with the
+//! serialization format decided by the [KVStore] it is used with, so backends only
+//! ever see byte keys and values, grouped into string namespaces.
+//!
+//! Backends are expected to provide the following guarantees:
+//!
+//! * changes of a write transaction are applied atomically on commit, across all namespaces;
+//! * write transactions see their own changes, but not the uncommitted changes of others;
+//! * read transactions see the data as it was when they started.
+//!
+//! The property tests in the `testing` module check these, and should be run against every backend.
+//! `fendermint_rocksdb` and `fendermint_sled` are the reference implementations.
 use std::borrow::Cow;
 use std::error::Error;
 use std::hash::Hash;
@@ -0,0 +1,224 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Gas benchmarks of canned message workloads, to catch execution regressions before release.
+//!
+//! The messages of a workload are delivered through a [crate::Tester] with tracing enabled,
+//! recording the gas used by each message and by each actor call it made, along with the
+//! wall-clock time of the message. Gas is deterministic, so it's compared against a baseline
+//! checked into the repository; the wall-clock time varies between machines and is only
+//! reported.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use fendermint_vm_interpreter::fvm::{call_trace, FvmApplyRet};
+use fendermint_vm_message::query::CallTrace;
+use serde::{Deserialize, Serialize};
+
+/// Set to update the baseline with the current measurements instead of comparing them.
+pub const UPDATE_BASELINE_ENV: &str = "UPDATE_GAS_BASELINE";
+
+/// The gas used by an actor call made while executing a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallGas {
+    /// Nesting of the call; the message itself is at zero.
+    pub depth: usize,
+    pub to: String,
+    pub method_num: u64,
+    /// Gas used by the call, including the calls it made.
+    pub gas_used: u64,
+}
+
+/// The cost of executing one message of a workload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measurement {
+    /// Unique name of the message within the benchmark, e.g. `simplecoin/send_coin`.
+    pub name: String,
+    pub exit_code: u32,
+    pub gas_used: u64,
+    pub wall_clock: Duration,
+    pub calls: Vec<CallGas>,
+}
+
+impl Measurement {
+    pub fn new(name: &str, ret: &FvmApplyRet, wall_clock: Duration) -> Self {
+        let mut calls = Vec::new();
+        if let Some(trace) = call_trace(&ret.apply_ret.exec_trace) {
+            flatten(&trace, 0, &mut calls);
+        }
+        Self {
+            name: name.to_string(),
+            exit_code: ret.apply_ret.msg_receipt.exit_code.value(),
+            gas_used: ret.apply_ret.msg_receipt.gas_used,
+            wall_clock,
+            calls,
+        }
+    }
+}
+
+fn flatten(trace: &CallTrace, depth: usize, calls: &mut Vec<CallGas>) {
+    calls.push(CallGas {
+        depth,
+        to: trace.to.to_string(),
+        method_num: trace.method_num,
+        gas_used: trace.gas_used,
+    });
+    for call in &trace.calls {
+        flatten(call, depth + 1, calls);
+    }
+}
+
+/// The measurements of all the workloads of a benchmark run.
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    pub measurements: Vec<Measurement>,
+}
+
+impl BenchReport {
+    pub fn add(&mut self, measurement: Measurement) {
+        self.measurements.push(measurement)
+    }
+
+    /// The gas used by each message and each call in it, keyed by a name which stays the same
+    /// as long as the workload does, e.g. `simplecoin/send_coin` and `simplecoin/send_coin/1:f0101:3844450837`.
+    pub fn gas(&self) -> BTreeMap<String, u64> {
+        let mut gas = BTreeMap::new();
+        for m in &self.measurements {
+            gas.insert(m.name.clone(), m.gas_used);
+            // The message itself is the first call; the rest are numbered in the order they were made.
+            for (i, call) in m.calls.iter().enumerate().skip(1) {
+                gas.insert(
+                    format!("{}/{i}:{}:{}", m.name, call.to, call.method_num),
+                    call.gas_used,
+                );
+            }
+        }
+        gas
+    }
+
+    /// A table of the measurements, with the calls indented under their messages.
+    pub fn table(&self) -> String {
+        let mut s = format!(
+            "{:<60} {:>14} {:>12} {:>5}\n",
+            "call", "gas", "wall us", "exit"
+        );
+        for m in &self.measurements {
+            s.push_str(&format!(
+                "{:<60} {:>14} {:>12} {:>5}\n",
+                m.name,
+                m.gas_used,
+                m.wall_clock.as_micros(),
+                m.exit_code
+            ));
+            for call in m.calls.iter().skip(1) {
+                let name = format!("{}{}:{}", "  ".repeat(call.depth), call.to, call.method_num);
+                s.push_str(&format!("{:<60} {:>14}\n", name, call.gas_used));
+            }
+        }
+        s
+    }
+}
+
+/// Compare the gas used against a baseline, returning the differences which are regressions:
+/// gas going up by more than `tolerance_pct` percent, and entries missing from either side,
+/// which mean the workload or the calls it makes changed.
+pub fn compare(
+    baseline: &BTreeMap<String, u64>,
+    current: &BTreeMap<String, u64>,
+    tolerance_pct: u64,
+) -> Vec<String> {
+    let mut regressions = Vec::new();
+    for (name, base) in baseline {
+        match current.get(name) {
+            None => regressions.push(format!("{name}: missing, was {base}")),
+            Some(gas) if gas.saturating_mul(100) > base.saturating_mul(100 + tolerance_pct) => {
+                regressions.push(format!(
+                    "{name}: {gas} gas, up from {base} ({:+.2}%)",
+                    (*gas as f64 - *base as f64) * 100.0 / (*base).max(1) as f64
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+    for name in current.keys().filter(|name| !baseline.contains_key(*name)) {
+        regressions.push(format!("{name}: not in the baseline"));
+    }
+    regressions
+}
+
+/// Check the gas used against the JSON baseline file, failing with the regressions, if any.
+///
+/// The baseline is written instead if [UPDATE_BASELINE_ENV] is set, e.g. after an intentional
+/// change in the cost of execution. A missing baseline is an error otherwise, so that the check
+/// can't pass silently when the file wasn't committed.
+pub fn check_baseline(path: &Path, report: &BenchReport, tolerance_pct: u64) -> anyhow::Result<()> {
+    let current = report.gas();
+
+    if std::env::var_os(UPDATE_BASELINE_ENV).is_some() {
+        let json = serde_json::to_string_pretty(&current)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| format!("failed to write gas baseline {}", path.display()))?;
+        return Ok(());
+    }
+
+    if !path.exists() {
+        bail!(
+            "gas baseline {} is missing; set {UPDATE_BASELINE_ENV}=1 to create it",
+            path.display()
+        );
+    }
+
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read gas baseline {}", path.display()))?;
+    let baseline: BTreeMap<String, u64> =
+        serde_json::from_str(&json).context("failed to parse gas baseline")?;
+
+    let regressions = compare(&baseline, &current, tolerance_pct);
+    if !regressions.is_empty() {
+        bail!(
+            "gas regressions against {}; set {UPDATE_BASELINE_ENV}=1 to accept them:\n{}",
+            path.display(),
+            regressions.join("\n")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use super::{check_baseline, compare, BenchReport, UPDATE_BASELINE_ENV};
+
+    #[test]
+    fn compare_gas() {
+        let baseline = BTreeMap::from([("a".to_string(), 100), ("b".to_string(), 100)]);
+
+        let same = baseline.clone();
+        assert!(compare(&baseline, &same, 0).is_empty());
+
+        let cheaper = BTreeMap::from([("a".to_string(), 90), ("b".to_string(), 100)]);
+        assert!(compare(&baseline, &cheaper, 0).is_empty());
+
+        let dearer = BTreeMap::from([("a".to_string(), 105), ("b".to_string(), 100)]);
+        assert_eq!(compare(&baseline, &dearer, 0).len(), 1);
+        assert!(compare(&baseline, &dearer, 5).is_empty());
+
+        let changed = BTreeMap::from([("a".to_string(), 100), ("c".to_string(), 100)]);
+        assert_eq!(compare(&baseline, &changed, 0).len(), 2);
+    }
+
+    #[test]
+    fn missing_baseline() {
+        if std::env::var_os(UPDATE_BASELINE_ENV).is_some() {
+            return;
+        }
+        let path = Path::new("does/not/exist/gas_baseline.json");
+        let err = check_baseline(path, &BenchReport::default(), 0).unwrap_err();
+        assert!(err.to_string().contains("missing"));
+    }
+}
@@ -22,7 +22,9 @@ use fendermint_vm_interpreter::{
 };
 use fvm::engine::MultiEngine;
 
+pub mod bench;
 pub mod ipc;
+pub mod upgrades;
 
 pub async fn init_exec_state(
     multi_engine: Arc<MultiEngine>,
@@ -77,6 +79,8 @@ pub struct Tester<I> {
     multi_engine: Arc<MultiEngine>,
     exec_state: Arc<tokio::sync::Mutex<Option<FvmExecState<MemoryBlockstore>>>>,
     state_params: FvmStateParams,
+    /// Whether to record the execution traces of the messages.
+    tracing: bool,
 }
 
 impl<I> Tester<I>
@@ -113,10 +117,24 @@ where
                 chain_id: 0,
                 power_scale: 0,
                 app_version: 0,
+                exec_limits: None,
+                executed_upgrades: Default::default(),
+                upgrades_in_progress: Default::default(),
+                pending_topdown_msgs: Default::default(),
+                pending_bottomup_msgs: Default::default(),
+                gas_params: None,
+                parent_checkpoint_height: None,
             },
+            tracing: false,
         }
     }
 
+    /// Record the execution traces of the messages, e.g. to break down the gas they use.
+    pub fn with_tracing(mut self, tracing: bool) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
     pub async fn init(&mut self, genesis: Genesis) -> anyhow::Result<()> {
         let bundle_path = bundle_path();
         let bundle = std::fs::read(&bundle_path)
@@ -157,6 +175,13 @@ where
             chain_id: out.chain_id.into(),
             power_scale: out.power_scale,
             app_version: 0,
+            exec_limits: out.exec_limits,
+            executed_upgrades: Default::default(),
+            upgrades_in_progress: Default::default(),
+            pending_topdown_msgs: Default::default(),
+            pending_bottomup_msgs: Default::default(),
+            gas_params: None,
+            parent_checkpoint_height: None,
         };
 
         Ok(())
@@ -199,20 +224,32 @@ where
         let mut state_params = self.state_params.clone();
         state_params.timestamp = Timestamp(block_height as u64);
 
-        let state = FvmExecState::new(db, self.multi_engine.as_ref(), block_height, state_params)
-            .context("error creating new state")?
-            .with_block_hash(block_hash);
+        let state = FvmExecState::new_with_tracing(
+            db,
+            self.multi_engine.as_ref(),
+            block_height,
+            state_params,
+            self.tracing,
+        )
+        .context("error creating new state")?
+        .with_block_hash(block_hash);
 
         self.put_exec_state(state).await;
 
         let _res = self
             .modify_exec_state(|s| self.interpreter.begin(s))
             .await
-            .unwrap();
+            .context("begin failed")?;
 
         Ok(())
     }
 
+    pub async fn deliver_message(&self, msg: FvmMessage) -> Result<FvmApplyRet> {
+        self.modify_exec_state(|s| self.interpreter.deliver(s, msg))
+            .await
+            .context("deliver failed")
+    }
+
     pub async fn end_block(&self, _block_height: ChainEpoch) -> Result<()> {
         let _ret = self
             .modify_exec_state(|s| self.interpreter.end(s))
@@ -232,6 +269,12 @@ where
                 base_fee,
                 circ_supply,
                 power_scale,
+                executed_upgrades,
+                upgrades_in_progress,
+                pending_topdown_msgs,
+                pending_bottomup_msgs,
+                gas_params,
+                parent_checkpoint_height,
             },
             _,
         ) = exec_state.commit().context("failed to commit FVM")?;
@@ -241,6 +284,12 @@ where
         self.state_params.base_fee = base_fee;
         self.state_params.circ_supply = circ_supply;
         self.state_params.power_scale = power_scale;
+        self.state_params.executed_upgrades = executed_upgrades;
+        self.state_params.upgrades_in_progress = upgrades_in_progress;
+        self.state_params.pending_topdown_msgs = pending_topdown_msgs;
+        self.state_params.pending_bottomup_msgs = pending_bottomup_msgs;
+        self.state_params.gas_params = gas_params;
+        self.state_params.parent_checkpoint_height = parent_checkpoint_height;
 
         eprintln!("self.state_params: {:?}", self.state_params);
 
@@ -250,4 +299,18 @@ where
     pub fn state_params(&self) -> FvmStateParams {
         self.state_params.clone()
     }
+
+    /// A fresh execution state on top of the last committed one, e.g. to inspect it.
+    pub fn committed_state(
+        &self,
+        block_height: ChainEpoch,
+    ) -> Result<FvmExecState<MemoryBlockstore>> {
+        FvmExecState::new(
+            self.state_store_clone(),
+            self.multi_engine.as_ref(),
+            block_height,
+            self.state_params.clone(),
+        )
+        .context("error creating new state")
+    }
 }
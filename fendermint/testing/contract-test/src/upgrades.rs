@@ -0,0 +1,195 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Harness to simulate a chain going through scheduled upgrades, so that upgrade authors
+//! can write regression tests against an in-memory state instead of trying them on a
+//! live network.
+//!
+//! The simulation starts from a genesis, then executes a script of blocks, each with the
+//! messages to deliver in it, running the upgrades of the scheduler at their heights,
+//! and lets the test inspect the committed state in between.
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use fendermint_vm_core::Timestamp;
+use fendermint_vm_genesis::{Genesis, PermissionMode};
+use fvm_shared::address::Address;
+use fvm_shared::bigint::Zero;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::version::NetworkVersion;
+use tendermint_rpc::Client;
+
+use fendermint_vm_interpreter::fvm::{
+    bundle::contracts_path,
+    state::{FvmExecState, FvmStateParams},
+    store::memory::MemoryBlockstore,
+    upgrades::UpgradeScheduler,
+    FvmApplyRet, FvmMessage, FvmMessageInterpreter,
+};
+
+use crate::Tester;
+
+/// The interpreter the simulation runs blocks with.
+pub type SimulationInterpreter = FvmMessageInterpreter<MemoryBlockstore, NeverCallClient>;
+
+/// A chain executing blocks one after the other, with the upgrades of a scheduler.
+pub struct UpgradeSimulation {
+    tester: Tester<SimulationInterpreter>,
+    /// Height of the last committed block; zero is the genesis.
+    height: ChainEpoch,
+}
+
+impl UpgradeSimulation {
+    /// Initialize the chain from the genesis; its chain name is what the upgrades have
+    /// to be scheduled for.
+    pub async fn new(
+        genesis: Genesis,
+        upgrade_scheduler: UpgradeScheduler<MemoryBlockstore>,
+    ) -> anyhow::Result<Self> {
+        let interpreter = FvmMessageInterpreter::new(
+            NeverCallClient,
+            None,
+            contracts_path(),
+            1.05,
+            1.05,
+            false,
+            upgrade_scheduler,
+        );
+
+        let mut tester = Tester::new(interpreter, MemoryBlockstore::new());
+        tester
+            .init(genesis)
+            .await
+            .context("failed to init genesis")?;
+
+        Ok(Self { tester, height: 0 })
+    }
+
+    /// A minimal genesis without validators or IPC, for the simulations which don't need
+    /// anything else.
+    pub fn genesis(chain_name: &str) -> Genesis {
+        Genesis {
+            chain_name: chain_name.to_string(),
+            timestamp: Timestamp(0),
+            network_version: NetworkVersion::V21,
+            base_fee: TokenAmount::zero(),
+            power_scale: 0,
+            validators: Vec::new(),
+            accounts: Vec::new(),
+            eam_permission_mode: PermissionMode::Unrestricted,
+            ipc: None,
+            exec_limits: None,
+            deployments: None,
+        }
+    }
+
+    /// Height of the last committed block.
+    pub fn height(&self) -> ChainEpoch {
+        self.height
+    }
+
+    /// The parameters of the last committed state, e.g. the app version and the upgrades
+    /// which have been executed.
+    pub fn state_params(&self) -> FvmStateParams {
+        self.tester.state_params()
+    }
+
+    /// Execute the next block with the messages in it, running any upgrade scheduled at
+    /// its height, and commit it. Returns the results of the messages.
+    ///
+    /// An upgrade failing makes the block fail, like it would halt the node.
+    pub async fn run_block(
+        &mut self,
+        messages: Vec<FvmMessage>,
+    ) -> anyhow::Result<Vec<FvmApplyRet>> {
+        self.run_block_at(self.height + 1, messages).await
+    }
+
+    /// Execute the last committed block again, on top of the state it produced, like a node
+    /// replaying blocks it already executed, e.g. to check that upgrades don't run twice.
+    pub async fn replay_block(
+        &mut self,
+        messages: Vec<FvmMessage>,
+    ) -> anyhow::Result<Vec<FvmApplyRet>> {
+        self.run_block_at(self.height, messages).await
+    }
+
+    async fn run_block_at(
+        &mut self,
+        height: ChainEpoch,
+        messages: Vec<FvmMessage>,
+    ) -> anyhow::Result<Vec<FvmApplyRet>> {
+        self.tester.begin_block(height).await?;
+
+        let mut rets = Vec::new();
+        for msg in messages {
+            rets.push(self.tester.deliver_message(msg).await?);
+        }
+
+        self.tester.end_block(height).await?;
+        self.tester.commit().await?;
+        self.height = height;
+
+        Ok(rets)
+    }
+
+    /// Execute the blocks of a script, one for each item with the messages to deliver in it.
+    pub async fn run_script(
+        &mut self,
+        blocks: impl IntoIterator<Item = Vec<FvmMessage>>,
+    ) -> anyhow::Result<Vec<Vec<FvmApplyRet>>> {
+        let mut rets = Vec::new();
+        for messages in blocks {
+            rets.push(self.run_block(messages).await?);
+        }
+        Ok(rets)
+    }
+
+    /// Execute empty blocks up to and including the given height.
+    pub async fn run_until(&mut self, height: ChainEpoch) -> anyhow::Result<()> {
+        while self.height < height {
+            self.run_block(Vec::new()).await?;
+        }
+        Ok(())
+    }
+
+    /// Inspect the last committed state, e.g. to check what an upgrade did.
+    ///
+    /// Any changes made to the state are discarded.
+    pub fn with_state<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&mut FvmExecState<MemoryBlockstore>) -> anyhow::Result<T>,
+    {
+        let mut state = self.tester.committed_state(self.height)?;
+        f(&mut state)
+    }
+
+    /// Balance of an actor in the last committed state, if it exists.
+    pub fn balance(&self, addr: &Address) -> anyhow::Result<Option<TokenAmount>> {
+        self.with_state(|state| {
+            let state_tree = state.state_tree();
+            let Some(id) = state_tree.lookup_id(addr)? else {
+                return Ok(None);
+            };
+            let actor = state_tree
+                .get_actor(id)?
+                .ok_or_else(|| anyhow!("actor {id} not found"))?;
+            Ok(Some(actor.balance))
+        })
+    }
+}
+
+/// A CometBFT client for interpreters which aren't expected to talk to CometBFT at all.
+#[derive(Clone)]
+pub struct NeverCallClient;
+
+#[async_trait]
+impl Client for NeverCallClient {
+    async fn perform<R>(&self, _request: R) -> Result<R::Output, tendermint_rpc::Error>
+    where
+        R: tendermint_rpc::SimpleRequest,
+    {
+        unreachable!("the simulated chain has no CometBFT node to call")
+    }
+}
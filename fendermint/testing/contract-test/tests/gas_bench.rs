@@ -0,0 +1,189 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Gas regression suite: executes canned workloads through the interpreter and compares the
+//! gas they use against `gas_baseline.json`.
+//!
+//! The suite is ignored by `cargo test`, because it needs a baseline generated on the same
+//! bundles; run it with `make gas-bench` in `fendermint`, or with
+//! `cargo test --release -p fendermint_contract_test --test gas_bench -- --ignored --nocapture`
+//! to see the breakdown per actor call and the wall-clock times. Set `UPDATE_GAS_BASELINE=1`
+//! to create the baseline or accept intentional changes, and `GAS_BENCH_TOLERANCE_PCT` to allow
+//! some increase.
+
+use std::path::Path;
+use std::time::Instant;
+
+use bytes::Bytes;
+use ethers::contract::abigen;
+use ethers::types::U256;
+use fendermint_contract_test::bench::{check_baseline, BenchReport, Measurement};
+use fendermint_contract_test::upgrades::{NeverCallClient, UpgradeSimulation};
+use fendermint_contract_test::Tester;
+use fendermint_crypto::SecretKey;
+use fendermint_rpc::message::{GasParams, MessageFactory};
+use fendermint_vm_actor_interface::eam::{self, EthAddress};
+use fendermint_vm_genesis::{Account, Actor, ActorMeta, SignerAddr};
+use fendermint_vm_interpreter::fvm::store::memory::MemoryBlockstore;
+use fendermint_vm_interpreter::fvm::upgrades::UpgradeScheduler;
+use fendermint_vm_interpreter::fvm::{bundle::contracts_path, FvmMessage, FvmMessageInterpreter};
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const CONTRACT_HEX: &str = include_str!("../../contracts/SimpleCoin.bin");
+abigen!(SimpleCoin, "../contracts/SimpleCoin.abi");
+
+const BASELINE: &str = "tests/gas_baseline.json";
+
+fn gas_params() -> GasParams {
+    GasParams {
+        gas_limit: 10_000_000_000,
+        gas_fee_cap: TokenAmount::default(),
+        gas_premium: TokenAmount::default(),
+    }
+}
+
+fn secret_key(seed: u64) -> SecretKey {
+    SecretKey::random(&mut StdRng::seed_from_u64(seed))
+}
+
+fn address(sk: &SecretKey) -> Address {
+    Address::new_secp256k1(&sk.public_key().serialize()).unwrap()
+}
+
+/// Deliver a message and record what it cost under the given name.
+async fn measure(
+    tester: &Tester<FvmMessageInterpreter<MemoryBlockstore, NeverCallClient>>,
+    report: &mut BenchReport,
+    name: &str,
+    msg: FvmMessage,
+) -> fendermint_vm_interpreter::fvm::FvmApplyRet {
+    let start = Instant::now();
+    let ret = tester
+        .deliver_message(msg)
+        .await
+        .expect("message delivered");
+    let measurement = Measurement::new(name, &ret, start.elapsed());
+    assert_eq!(
+        measurement.exit_code, 0,
+        "{name} failed: {:?}",
+        ret.apply_ret.failure_info
+    );
+    report.add(measurement);
+    ret
+}
+
+#[tokio::test]
+#[ignore = "compares against a baseline; run with `make gas-bench`"]
+async fn gas_regressions() {
+    let (alice_sk, bob_sk) = (secret_key(1), secret_key(2));
+    let (alice, bob) = (address(&alice_sk), address(&bob_sk));
+
+    let mut genesis = UpgradeSimulation::genesis("gasbench");
+    genesis.accounts = [alice, bob]
+        .into_iter()
+        .map(|addr| Actor {
+            meta: ActorMeta::Account(Account {
+                owner: SignerAddr(addr),
+            }),
+            balance: TokenAmount::from_whole(1000),
+        })
+        .collect();
+
+    let interpreter = FvmMessageInterpreter::new(
+        NeverCallClient,
+        None,
+        contracts_path(),
+        1.05,
+        1.05,
+        false,
+        UpgradeScheduler::new(),
+    );
+    let mut tester = Tester::new(interpreter, MemoryBlockstore::new()).with_tracing(true);
+    tester.init(genesis).await.unwrap();
+
+    let mut report = BenchReport::default();
+    let mut mf = MessageFactory::new(alice, 0);
+
+    // Workload: plain transfers, the first of which creates the recipient.
+    tester.begin_block(1).await.unwrap();
+    let carol = address(&secret_key(3));
+    for (name, to) in [
+        ("transfer/existing", bob),
+        ("transfer/new_account", carol),
+        ("transfer/existing_again", carol),
+    ] {
+        let msg = mf.transaction(
+            to,
+            fvm_shared::METHOD_SEND,
+            Default::default(),
+            TokenAmount::from_whole(1),
+            gas_params(),
+        );
+        measure(&tester, &mut report, name, msg).await;
+    }
+    tester.end_block(1).await.unwrap();
+    tester.commit().await.unwrap();
+
+    // Workload: deploy an ERC20-like contract and use it.
+    tester.begin_block(2).await.unwrap();
+    let msg = mf
+        .fevm_create(
+            Bytes::from(hex::decode(CONTRACT_HEX).unwrap()),
+            Bytes::default(),
+            TokenAmount::default(),
+            gas_params(),
+        )
+        .unwrap();
+    let ret = measure(&tester, &mut report, "simplecoin/deploy", msg).await;
+    let created =
+        fvm_ipld_encoding::from_slice::<eam::CreateReturn>(&ret.apply_ret.msg_receipt.return_data)
+            .unwrap();
+    let contract = created.delegated_address();
+
+    let (client, _mock) = ethers::providers::Provider::mocked();
+    let simple_coin = SimpleCoin::new(EthAddress::from_id(0), client.into());
+    let bob_eth: ethers::types::Address = EthAddress::from(bob_sk.public_key()).into();
+
+    for (i, amount) in [100u64, 200].into_iter().enumerate() {
+        let call = simple_coin.send_coin(bob_eth, U256::from(amount));
+        let msg = mf
+            .fevm_invoke(
+                contract,
+                call.calldata().unwrap().0,
+                TokenAmount::default(),
+                gas_params(),
+            )
+            .unwrap();
+        measure(
+            &tester,
+            &mut report,
+            &format!("simplecoin/send_coin/{i}"),
+            msg,
+        )
+        .await;
+    }
+
+    let call = simple_coin.get_balance(bob_eth);
+    let msg = mf
+        .fevm_invoke(
+            contract,
+            call.calldata().unwrap().0,
+            TokenAmount::default(),
+            gas_params(),
+        )
+        .unwrap();
+    measure(&tester, &mut report, "simplecoin/get_balance", msg).await;
+    tester.end_block(2).await.unwrap();
+    tester.commit().await.unwrap();
+
+    println!("{}", report.table());
+
+    let tolerance_pct = std::env::var("GAS_BENCH_TOLERANCE_PCT")
+        .map(|s| s.parse().expect("tolerance should be a number"))
+        .unwrap_or(0);
+
+    check_baseline(Path::new(BASELINE), &report, tolerance_pct).unwrap();
+}
@@ -0,0 +1,202 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::str::FromStr;
+
+use bytes::Bytes;
+use fendermint_contract_test::upgrades::UpgradeSimulation;
+use fendermint_crypto::SecretKey;
+use fendermint_rpc::message::{GasParams as MsgGasParams, MessageFactory};
+use fendermint_vm_actor_interface::eam;
+use fendermint_vm_genesis::{Account, Actor, ActorMeta, ExecLimits, SignerAddr};
+use fendermint_vm_interpreter::fvm::state::GasParams;
+use fendermint_vm_interpreter::fvm::upgrades::{Upgrade, UpgradeScheduler};
+use fendermint_vm_interpreter::fvm::FvmMessage;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::address::Address;
+use fvm_shared::bigint::Zero;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const CHAIN_NAME: &str = "mychain";
+
+/// Creation bytecode of a contract returning the same values from any call, which `gasParams()`
+/// decodes as an activation height of 3, a block gas limit of 10_000_000, a minimum base fee
+/// of 100 atto and a maximum base fee change of 1/8 between blocks.
+const GOVERNANCE_HEX: &str =
+    "601b600c600039601b6000f36003600052629896806020526064604052600860605260806000f3";
+
+/// Where the governance contract is deployed to by the first message of the deployer.
+const GOVERNANCE_ADDRESS: &str = "f410fnz5jdky3zzcj6pejqkomkggw72pcuvkpihz2rwa";
+
+const BLOCK_GAS_LIMIT: u64 = 10_000_000;
+const MIN_BASE_FEE: u64 = 100;
+
+fn address(seed: u64) -> Address {
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(seed));
+    Address::new_secp256k1(&sk.public_key().serialize()).unwrap()
+}
+
+fn deployer() -> Address {
+    address(123)
+}
+
+fn sender() -> Address {
+    address(456)
+}
+
+fn receiver() -> Address {
+    address(789)
+}
+
+fn transfer(sequence: u64, gas_limit: u64) -> FvmMessage {
+    FvmMessage {
+        version: Default::default(),
+        from: sender(),
+        to: receiver(),
+        sequence,
+        value: TokenAmount::from_atto(1),
+        method_num: 0,
+        params: RawBytes::default(),
+        gas_limit,
+        gas_fee_cap: TokenAmount::from_atto(1000),
+        gas_premium: TokenAmount::zero(),
+    }
+}
+
+// this test deploys a gas governance contract before the parameters it returns activate,
+// then checks that blocks are limited to the gas it allows and the base fee follows them
+#[tokio::test]
+async fn test_gas_governance() {
+    let mut upgrade_scheduler = UpgradeScheduler::new();
+    upgrade_scheduler
+        .add(
+            Upgrade::new(CHAIN_NAME, 1, None, |state| {
+                let mut mf = MessageFactory::new(deployer(), 1);
+                let message = mf.fevm_create(
+                    Bytes::from(hex::decode(GOVERNANCE_HEX)?),
+                    Bytes::default(),
+                    TokenAmount::zero(),
+                    MsgGasParams {
+                        gas_limit: 10_000_000_000,
+                        gas_fee_cap: TokenAmount::zero(),
+                        gas_premium: TokenAmount::zero(),
+                    },
+                )?;
+                let (res, _) = state.execute_implicit(message)?;
+                anyhow::ensure!(
+                    res.msg_receipt.exit_code.is_success(),
+                    "{:?}",
+                    res.failure_info
+                );
+                let res = fvm_ipld_encoding::from_slice::<eam::CreateReturn>(
+                    &res.msg_receipt.return_data,
+                )?;
+                anyhow::ensure!(
+                    res.delegated_address() == Address::from_str(GOVERNANCE_ADDRESS)?,
+                    "unexpected governance address"
+                );
+                Ok(())
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+    let mut genesis = UpgradeSimulation::genesis(CHAIN_NAME);
+    genesis.exec_limits = Some(ExecLimits {
+        gas_governance: Some(Address::from_str(GOVERNANCE_ADDRESS).unwrap()),
+        ..Default::default()
+    });
+    for (owner, balance) in [(deployer(), 0), (sender(), 1_000_000_000_000_000_000u64)] {
+        genesis.accounts.push(Actor {
+            meta: ActorMeta::Account(Account {
+                owner: SignerAddr(owner),
+            }),
+            balance: TokenAmount::from_atto(balance),
+        });
+    }
+
+    let mut sim = UpgradeSimulation::new(genesis, upgrade_scheduler)
+        .await
+        .unwrap();
+
+    // the contract is there, but its parameters are not active yet
+    sim.run_until(2).await.unwrap();
+    assert_eq!(sim.state_params().gas_params, None);
+    assert!(sim.state_params().base_fee.is_zero());
+
+    // once active, the base fee is adjusted, but not below the minimum
+    sim.run_block(Vec::new()).await.unwrap();
+    let params = GasParams {
+        block_gas_limit: BLOCK_GAS_LIMIT,
+        min_base_fee: TokenAmount::from_atto(MIN_BASE_FEE),
+        base_fee_max_change_denominator: 8,
+    };
+    assert_eq!(sim.state_params().gas_params, Some(params));
+    assert_eq!(
+        sim.state_params().base_fee,
+        TokenAmount::from_atto(MIN_BASE_FEE)
+    );
+
+    // the second message doesn't fit in what the first one left of the block,
+    // so it's skipped without using up the nonce, unlike the third one
+    let rets = sim
+        .run_block(vec![
+            transfer(0, 6_000_000),
+            transfer(1, BLOCK_GAS_LIMIT - 1),
+            transfer(1, 3_000_000),
+        ])
+        .await
+        .unwrap();
+
+    assert!(rets[0].apply_ret.msg_receipt.exit_code.is_success());
+    assert_eq!(
+        rets[1].apply_ret.msg_receipt.exit_code,
+        ExitCode::SYS_ASSERTION_FAILED
+    );
+    assert_eq!(rets[1].apply_ret.msg_receipt.gas_used, 0);
+    assert!(rets[2].apply_ret.msg_receipt.exit_code.is_success());
+    assert_eq!(
+        sim.balance(&receiver()).unwrap(),
+        Some(TokenAmount::from_atto(2))
+    );
+
+    // the base fee goes up only if the messages used more than half of the block
+    let gas_used: u64 = rets.iter().map(|r| r.apply_ret.msg_receipt.gas_used).sum();
+    let base_fee = sim.state_params().base_fee;
+    if gas_used > BLOCK_GAS_LIMIT / 2 {
+        assert!(base_fee > TokenAmount::from_atto(MIN_BASE_FEE));
+    } else {
+        assert_eq!(base_fee, TokenAmount::from_atto(MIN_BASE_FEE));
+    }
+}
+
+// this test checks that without a governance contract blocks have no gas limit
+// and the base fee stays what it was at genesis
+#[tokio::test]
+async fn test_no_gas_governance() {
+    let mut genesis = UpgradeSimulation::genesis(CHAIN_NAME);
+    genesis.accounts.push(Actor {
+        meta: ActorMeta::Account(Account {
+            owner: SignerAddr(sender()),
+        }),
+        balance: TokenAmount::from_atto(1_000_000_000_000_000_000u64),
+    });
+
+    let mut sim = UpgradeSimulation::new(genesis, UpgradeScheduler::new())
+        .await
+        .unwrap();
+
+    let rets = sim
+        .run_block(vec![transfer(0, 6_000_000), transfer(1, 6_000_000)])
+        .await
+        .unwrap();
+
+    assert!(rets
+        .iter()
+        .all(|r| r.apply_ret.msg_receipt.exit_code.is_success()));
+    assert_eq!(sim.state_params().gas_params, None);
+    assert!(sim.state_params().base_fee.is_zero());
+}
@@ -4,8 +4,8 @@
 mod staking;
 
 use anyhow::{Context, Ok};
-use async_trait::async_trait;
 use ethers::types::U256;
+use fendermint_contract_test::upgrades::{NeverCallClient, UpgradeSimulation};
 use fendermint_contract_test::Tester;
 use fendermint_rpc::response::decode_fevm_return_data;
 use rand::rngs::StdRng;
@@ -17,7 +17,6 @@ use fvm_shared::address::Address;
 use fvm_shared::bigint::Zero;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::version::NetworkVersion;
-use tendermint_rpc::Client;
 
 use fendermint_crypto::SecretKey;
 use fendermint_vm_actor_interface::eam;
@@ -25,8 +24,8 @@ use fendermint_vm_actor_interface::eam::EthAddress;
 use fendermint_vm_core::Timestamp;
 use fendermint_vm_genesis::{Account, Actor, ActorMeta, Genesis, PermissionMode, SignerAddr};
 use fendermint_vm_interpreter::fvm::store::memory::MemoryBlockstore;
-use fendermint_vm_interpreter::fvm::upgrades::{Upgrade, UpgradeScheduler};
-use fendermint_vm_interpreter::fvm::{bundle::contracts_path, FvmMessageInterpreter};
+use fendermint_vm_interpreter::fvm::upgrades::{MigrationFunc, Upgrade, UpgradeScheduler};
+use fendermint_vm_interpreter::fvm::{bundle::contracts_path, FvmMessage, FvmMessageInterpreter};
 
 // returns a seeded secret key which is guaranteed to be the same every time
 fn my_secret_key() -> SecretKey {
@@ -221,6 +220,8 @@ async fn test_applying_upgrades() {
         }],
         eam_permission_mode: PermissionMode::Unrestricted,
         ipc: None,
+        exec_limits: None,
+        deployments: None,
     };
 
     tester.init(genesis).await.unwrap();
@@ -239,15 +240,224 @@ async fn test_applying_upgrades() {
     }
 }
 
-#[derive(Clone)]
-struct NeverCallClient;
+// this test replays the block of an upgrade on top of a state in which it already ran,
+// which should only run the upgrade again if reruns are explicitly allowed
+#[tokio::test]
+async fn test_upgrades_run_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CHAIN_NAME: &str = "mychain";
+
+    // migrations are plain function pointers, so they count their runs in statics;
+    // each case has its own, as the tests of this file run concurrently in one process
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    static RERUNS: AtomicUsize = AtomicUsize::new(0);
+
+    async fn replay_upgrade(allow_rerun: bool) -> usize {
+        let (runs, migration): (_, MigrationFunc<MemoryBlockstore>) = if allow_rerun {
+            (&RERUNS, |_state| {
+                RERUNS.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        } else {
+            (&RUNS, |_state| {
+                RUNS.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+
+        let mut upgrade_scheduler = UpgradeScheduler::new().with_rerun(allow_rerun);
+        upgrade_scheduler
+            .add(Upgrade::new(CHAIN_NAME, 1, Some(1), migration).unwrap())
+            .unwrap();
+
+        let mut sim =
+            UpgradeSimulation::new(UpgradeSimulation::genesis(CHAIN_NAME), upgrade_scheduler)
+                .await
+                .unwrap();
+
+        // the same height twice, the second time on top of the state after the upgrade
+        sim.run_block(Vec::new()).await.unwrap();
+        sim.replay_block(Vec::new()).await.unwrap();
+
+        assert_eq!(sim.state_params().executed_upgrades.len(), 1);
+
+        runs.load(Ordering::SeqCst)
+    }
+
+    assert_eq!(replay_upgrade(false).await, 1);
+    assert_eq!(replay_upgrade(true).await, 2);
+}
+
+// this test runs an upgrade split across several blocks, one chunk at each block
+#[tokio::test]
+async fn test_chunked_upgrade() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const CHAIN_NAME: &str = "mychain";
+    const CHUNKS: u64 = 3;
+
+    // the index of the next chunk expected to run
+    static NEXT_CHUNK: AtomicU64 = AtomicU64::new(0);
+
+    let mut upgrade_scheduler = UpgradeScheduler::new();
+    upgrade_scheduler
+        .add(
+            Upgrade::new_chunked(CHAIN_NAME, 1, Some(1), CHUNKS, |state, chunk| {
+                assert_eq!(chunk.count, CHUNKS);
+                assert_eq!(chunk.index, NEXT_CHUNK.fetch_add(1, Ordering::SeqCst));
+                assert_eq!(state.block_height() as u64, 1 + chunk.index);
+                Ok(())
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+    let mut sim = UpgradeSimulation::new(UpgradeSimulation::genesis(CHAIN_NAME), upgrade_scheduler)
+        .await
+        .unwrap();
+
+    for block_height in 1..=CHUNKS as i64 + 1 {
+        sim.run_until(block_height).await.unwrap();
+
+        let params = sim.state_params();
+        let chunks_done = (block_height as u64).min(CHUNKS);
+
+        if chunks_done < CHUNKS {
+            // the progress is persisted and the app version only changes after the last chunk
+            assert_eq!(
+                params.upgrades_in_progress.values().collect::<Vec<_>>(),
+                vec![&chunks_done]
+            );
+            assert!(params.executed_upgrades.is_empty());
+            assert_eq!(params.app_version, 0);
+        } else {
+            assert!(params.upgrades_in_progress.is_empty());
+            assert_eq!(params.executed_upgrades.len(), 1);
+            assert_eq!(params.app_version, 1);
+        }
+    }
+
+    assert_eq!(NEXT_CHUNK.load(Ordering::SeqCst), CHUNKS);
+}
+
+// this test checks that an upgrade fails, halting the node, if an invariant doesn't hold after it
+#[tokio::test]
+async fn test_upgrade_invariants() {
+    const CHAIN_NAME: &str = "mychain";
+
+    let mut upgrade_scheduler = UpgradeScheduler::new();
+    upgrade_scheduler
+        .add(
+            Upgrade::new(CHAIN_NAME, 1, Some(1), |_state| Ok(()))
+                .unwrap()
+                .with_check("power scale unchanged", |state| {
+                    anyhow::ensure!(state.power_scale() == 0, "power scale changed");
+                    Ok(())
+                }),
+        )
+        .unwrap();
+    upgrade_scheduler
+        .add(
+            Upgrade::new(CHAIN_NAME, 2, Some(2), |_state| Ok(()))
+                .unwrap()
+                .with_check("supply conserved", |_state| {
+                    anyhow::bail!("supply is not conserved")
+                }),
+        )
+        .unwrap();
+
+    let mut sim = UpgradeSimulation::new(UpgradeSimulation::genesis(CHAIN_NAME), upgrade_scheduler)
+        .await
+        .unwrap();
+
+    sim.run_block(Vec::new()).await.unwrap();
+    assert_eq!(sim.state_params().app_version, 1);
+
+    let err = sim.run_block(Vec::new()).await.unwrap_err();
+    assert!(format!("{err:#}").contains("upgrade invariant violated: supply conserved"));
+}
+
+// this test uses the simulation harness to run a script of blocks around an upgrade,
+// checking the state before and after it
+#[tokio::test]
+async fn test_upgrade_simulation() {
+    use fvm_ipld_encoding::RawBytes;
+
+    const CHAIN_NAME: &str = "mychain";
+
+    fn sender() -> Address {
+        Address::new_secp256k1(&my_secret_key().public_key().serialize()).unwrap()
+    }
+
+    fn receiver() -> Address {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(456));
+        Address::new_secp256k1(&sk.public_key().serialize()).unwrap()
+    }
 
-#[async_trait]
-impl Client for NeverCallClient {
-    async fn perform<R>(&self, _request: R) -> Result<R::Output, tendermint_rpc::Error>
-    where
-        R: tendermint_rpc::SimpleRequest,
-    {
-        todo!()
+    fn transfer(sequence: u64, value: u64) -> FvmMessage {
+        FvmMessage {
+            version: Default::default(),
+            from: sender(),
+            to: receiver(),
+            sequence,
+            value: TokenAmount::from_atto(value),
+            method_num: 0,
+            params: RawBytes::default(),
+            gas_limit: 10_000_000_000,
+            gas_fee_cap: TokenAmount::zero(),
+            gas_premium: TokenAmount::zero(),
+        }
     }
+
+    let mut upgrade_scheduler = UpgradeScheduler::new();
+    upgrade_scheduler
+        .add(
+            Upgrade::new(CHAIN_NAME, 2, Some(1), |state| {
+                let (res, _) = state.execute_implicit(transfer(1, 50))?;
+                anyhow::ensure!(
+                    res.msg_receipt.exit_code.is_success(),
+                    "{:?}",
+                    res.failure_info
+                );
+                Ok(())
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+    let mut genesis = UpgradeSimulation::genesis(CHAIN_NAME);
+    genesis.accounts.push(Actor {
+        meta: ActorMeta::Account(Account {
+            owner: SignerAddr(sender()),
+        }),
+        balance: TokenAmount::from_atto(1000),
+    });
+
+    let mut sim = UpgradeSimulation::new(genesis, upgrade_scheduler)
+        .await
+        .unwrap();
+
+    // a regular transfer before the upgrade
+    let rets = sim.run_block(vec![transfer(0, 100)]).await.unwrap();
+    assert!(rets[0].apply_ret.msg_receipt.exit_code.is_success());
+    assert_eq!(
+        sim.balance(&receiver()).unwrap(),
+        Some(TokenAmount::from_atto(100))
+    );
+    assert_eq!(sim.state_params().app_version, 0);
+
+    // the upgrade moves some more funds, then the chain carries on
+    sim.run_until(3).await.unwrap();
+    assert_eq!(sim.height(), 3);
+    assert_eq!(
+        sim.balance(&receiver()).unwrap(),
+        Some(TokenAmount::from_atto(150))
+    );
+    assert_eq!(
+        sim.balance(&sender()).unwrap(),
+        Some(TokenAmount::from_atto(850))
+    );
+    assert_eq!(sim.state_params().app_version, 1);
+    assert_eq!(sim.state_params().executed_upgrades.len(), 1);
 }
@@ -0,0 +1,146 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fendermint_actor_scheduler::{Method, ScheduleParams};
+use fendermint_contract_test::upgrades::UpgradeSimulation;
+use fendermint_crypto::SecretKey;
+use fendermint_vm_actor_interface::{burntfunds, scheduler};
+use fendermint_vm_genesis::{Account, Actor, ActorMeta, SignerAddr};
+use fendermint_vm_interpreter::fvm::upgrades::UpgradeScheduler;
+use fendermint_vm_interpreter::fvm::FvmMessage;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::address::Address;
+use fvm_shared::bigint::Zero;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const CHAIN_NAME: &str = "mychain";
+const BALANCE: u64 = 1_000_000_000_000_000_000;
+const GAS_LIMIT: u64 = 1_000_000;
+
+fn address(seed: u64) -> Address {
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(seed));
+    Address::new_secp256k1(&sk.public_key().serialize()).unwrap()
+}
+
+fn sender() -> Address {
+    address(123)
+}
+
+fn receiver() -> Address {
+    address(456)
+}
+
+/// A message from the sender scheduling another one, with a deposit for the given
+/// number of executions at a gas price of 1.
+fn schedule(sequence: u64, params: ScheduleParams, runs: u64) -> FvmMessage {
+    FvmMessage {
+        version: Default::default(),
+        from: sender(),
+        to: scheduler::SCHEDULER_ACTOR_ADDR,
+        sequence,
+        value: TokenAmount::from_atto(GAS_LIMIT * runs),
+        method_num: Method::Schedule as u64,
+        params: RawBytes::serialize(params).unwrap(),
+        gas_limit: 10_000_000_000,
+        gas_fee_cap: TokenAmount::zero(),
+        gas_premium: TokenAmount::zero(),
+    }
+}
+
+fn params(method: u64, value: u64, interval: i64) -> ScheduleParams {
+    ScheduleParams {
+        height: 5,
+        to: receiver(),
+        method,
+        params: RawBytes::default(),
+        value: TokenAmount::from_atto(value),
+        gas_limit: GAS_LIMIT,
+        gas_price: TokenAmount::from_atto(1),
+        interval,
+    }
+}
+
+// this test schedules a transfer and a periodic message which fails, then checks that both
+// are executed at their height, paid for from their deposits, and the failing one is cancelled
+#[tokio::test]
+async fn test_execute_scheduled() {
+    let mut genesis = UpgradeSimulation::genesis(CHAIN_NAME);
+    genesis.accounts.push(Actor {
+        meta: ActorMeta::Account(Account {
+            owner: SignerAddr(sender()),
+        }),
+        balance: TokenAmount::from_atto(BALANCE),
+    });
+
+    let mut sim = UpgradeSimulation::new(genesis, UpgradeScheduler::new())
+        .await
+        .unwrap();
+
+    let rets = sim
+        .run_block(vec![
+            schedule(0, params(0, 10, 0), 1),
+            // the account actor doesn't have this method
+            schedule(1, params(1234, 0, 10), 3),
+            // too frequent
+            schedule(2, params(0, 0, 5), 1),
+            // no deposit
+            schedule(3, params(0, 0, 0), 0),
+        ])
+        .await
+        .unwrap();
+
+    let exit_codes = rets
+        .iter()
+        .map(|r| r.apply_ret.msg_receipt.exit_code)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exit_codes,
+        vec![
+            ExitCode::OK,
+            ExitCode::OK,
+            ExitCode::USR_ILLEGAL_ARGUMENT,
+            ExitCode::USR_INSUFFICIENT_FUNDS
+        ]
+    );
+
+    // nothing happens until the height they are scheduled at
+    sim.run_until(4).await.unwrap();
+    assert_eq!(sim.balance(&receiver()).unwrap(), None);
+    assert_eq!(
+        sim.balance(&scheduler::SCHEDULER_ACTOR_ADDR).unwrap(),
+        Some(TokenAmount::from_atto(4 * GAS_LIMIT))
+    );
+    let burnt = sim
+        .balance(&burntfunds::BURNT_FUNDS_ACTOR_ADDR)
+        .unwrap()
+        .unwrap_or_default();
+
+    // the fees of both are burnt, and the failing one gets back the rest of its deposit
+    sim.run_block(Vec::new()).await.unwrap();
+    assert_eq!(
+        sim.balance(&receiver()).unwrap(),
+        Some(TokenAmount::from_atto(10))
+    );
+    assert_eq!(
+        sim.balance(&scheduler::SCHEDULER_ACTOR_ADDR).unwrap(),
+        Some(TokenAmount::zero())
+    );
+    assert_eq!(
+        sim.balance(&burntfunds::BURNT_FUNDS_ACTOR_ADDR).unwrap(),
+        Some(burnt + TokenAmount::from_atto(2 * GAS_LIMIT))
+    );
+    assert_eq!(
+        sim.balance(&sender()).unwrap(),
+        Some(TokenAmount::from_atto(BALANCE - 2 * GAS_LIMIT - 10))
+    );
+
+    // the failing one isn't repeated
+    sim.run_until(15).await.unwrap();
+    assert_eq!(
+        sim.balance(&sender()).unwrap(),
+        Some(TokenAmount::from_atto(BALANCE - 2 * GAS_LIMIT - 10))
+    );
+}
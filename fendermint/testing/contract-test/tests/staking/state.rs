@@ -586,6 +586,8 @@ impl arbitrary::Arbitrary<'_> for StakingState {
             accounts: parent_actors,
             eam_permission_mode: PermissionMode::Unrestricted,
             ipc: Some(parent_ipc),
+            exec_limits: None,
+            deployments: None,
         };
 
         let child_ipc = IpcParams {
@@ -607,6 +609,8 @@ impl arbitrary::Arbitrary<'_> for StakingState {
             accounts: Vec::new(),
             eam_permission_mode: PermissionMode::Unrestricted,
             ipc: Some(child_ipc),
+            exec_limits: None,
+            deployments: None,
         };
 
         Ok(StakingState::new(accounts, parent_genesis, child_genesis))
@@ -514,11 +514,21 @@ impl DockerMaterializer {
                 id: subnet_id,
                 config: IpcCliSubnetConfig::Fevm(EVMSubnet {
                     provider_http: url,
+                    provider_http_fallbacks: Vec::new(),
+                    provider_ws: None,
+                    provider_archive: None,
                     provider_timeout: Some(Duration::from_secs(30)),
+                    provider_max_in_flight: None,
+                    provider_queue_timeout: None,
+                    provider_retry: None,
+                    fee_caps: None,
+                    resubmit: None,
                     auth_token: None,
                     registry_addr: submit_config.deployment.registry.into(),
                     gateway_addr: submit_config.deployment.gateway.into(),
                 }),
+                manager: None,
+                read_only: false,
             })
         })
         .context("failed to update CLI config")?;
@@ -696,6 +706,8 @@ impl Materializer<DockerMaterials> for DockerMaterializer {
                         active_validators_limit: 100,
                     },
                 }),
+                exec_limits: None,
+                deployments: None,
             };
             Ok(genesis)
         })
@@ -1076,11 +1088,21 @@ mod tests {
             id: SubnetID::new_root(12345),
             config: IpcCliSubnetConfig::Fevm(EVMSubnet {
                 provider_http: url::Url::parse("http://example.net").unwrap(),
+                provider_http_fallbacks: Vec::new(),
+                provider_ws: None,
+                provider_archive: None,
                 provider_timeout: Some(Duration::from_secs(30)),
+                provider_max_in_flight: None,
+                provider_queue_timeout: None,
+                provider_retry: None,
+                fee_caps: None,
+                resubmit: None,
                 auth_token: None,
                 registry_addr: ipc::SUBNETREGISTRY_ACTOR_ADDR,
                 gateway_addr: ipc::GATEWAY_ACTOR_ADDR,
             }),
+            manager: None,
+            read_only: false,
         });
 
         let config_toml = toml::to_string_pretty(&config0).expect("failed to serialize");
@@ -60,6 +60,10 @@ impl State {
         eth_builtin_ids: &BTreeSet<ActorID>,
         // Number of dynamically deployed EVM library contracts.
         eth_library_count: u64,
+        // Number of custom actors deployed from the genesis manifest, after the libraries.
+        custom_actor_count: u64,
+        // Number of EVM contracts deployed from the genesis manifest, after the custom actors.
+        eth_contract_count: u64,
     ) -> anyhow::Result<(Self, AddressMap)> {
         // Returning only the addreses that belong to user accounts.
         let mut allocated_ids = AddressMap::new();
@@ -116,6 +120,16 @@ impl State {
             next_id += 1;
         }
 
+        // Custom actors have no delegated address, they only need their IDs reserved.
+        next_id += custom_actor_count;
+
+        // Insert dynamic EVM contracts from the manifest.
+        for _ in 0..eth_contract_count {
+            let addr = Address::from(builtin_actor_eth_addr(next_id));
+            set_address(addr, next_id).context("cannot set ID of eth contract address")?;
+            next_id += 1;
+        }
+
         // Insert the null-Ethereum address to equal the system actor,
         // so the system actor can be identified by 0xff00..00 as well as 0x00..00
         set_address(*system::SYSTEM_ACTOR_ETH_ADDR, system::SYSTEM_ACTOR_ID)
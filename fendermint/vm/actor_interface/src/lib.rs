@@ -55,4 +55,5 @@ pub mod ipc;
 pub mod multisig;
 pub mod placeholder;
 pub mod reward;
+pub mod scheduler;
 pub mod system;
@@ -0,0 +1,4 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+define_id!(SCHEDULER { id: 49 });
@@ -20,6 +20,39 @@ pub struct ParentFinalityCommitted<'a> {
     pub block_hash: BlockHashHex<'a>,
 }
 
+/// How far the committed parent finality is behind the parent chain head this node can see.
+#[derive(Debug, Default)]
+pub struct ParentFinalityLag {
+    /// The parent chain head, after the chain head delay is applied.
+    pub parent_height: BlockHeight,
+    pub finalized_height: BlockHeight,
+    pub lag: u64,
+}
+
+/// Time from a parent block first being synced by this node to it being committed as finality.
+#[derive(Debug, Default)]
+pub struct ParentFinalityLatency {
+    pub block_height: BlockHeight,
+    pub latency_ms: u64,
+}
+
+/// The top-down messages and validator changes delivered with a parent finality.
+///
+/// With a top-down gas budget some of the messages may only be executed in later blocks.
+#[derive(Debug, Default)]
+pub struct ParentFinalityExecuted {
+    pub block_height: BlockHeight,
+    pub num_msgs: usize,
+    pub num_validator_changes: usize,
+}
+
+/// Time from the first vote on a parent block to the votes for it reaching a quorum.
+#[derive(Debug, Default)]
+pub struct ParentFinalityQuorum {
+    pub block_height: BlockHeight,
+    pub latency_ms: u64,
+}
+
 #[derive(Debug, Default)]
 pub struct NewBottomUpCheckpoint<'a> {
     pub block_height: BlockHeight,
@@ -27,3 +60,72 @@ pub struct NewBottomUpCheckpoint<'a> {
     pub num_msgs: usize,
     pub next_configuration_number: u64,
 }
+
+/// A message included in a block was executed.
+#[derive(Debug, Default)]
+pub struct MsgApplied<'a> {
+    pub block_height: BlockHeight,
+    pub from: &'a str,
+    pub to: &'a str,
+    pub method_num: u64,
+    pub exit_code: u32,
+    pub gas_used: u64,
+}
+
+/// A message which used most of, or ran out of, the resources it was allowed.
+#[derive(Debug, Default)]
+pub struct MsgExecLimits<'a> {
+    pub block_height: BlockHeight,
+    pub to: &'a str,
+    pub method_num: u64,
+    pub exit_code: u32,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// 1 if the message completed but used most of its gas limit.
+    pub near_gas_limit: usize,
+    /// 1 if the message ran out of gas.
+    pub out_of_gas: usize,
+    /// 1 if the execution was aborted by the sandbox, e.g. for exceeding the memory or stack limits.
+    pub trapped: usize,
+}
+
+/// A chunk of an upgrade whose migration is split across several blocks was executed.
+#[derive(Debug, Default)]
+pub struct UpgradeProgress {
+    pub block_height: BlockHeight,
+    /// The height at which the upgrade started.
+    pub upgrade_height: BlockHeight,
+    pub chunks_done: u64,
+    pub chunks: u64,
+}
+
+/// The next upgrade scheduled for the chain, emitted at every block until its height.
+#[derive(Debug, Default)]
+pub struct UpgradePending {
+    pub block_height: BlockHeight,
+    pub upgrade_height: BlockHeight,
+    pub blocks_remaining: u64,
+}
+
+/// An upgrade, or a chunk of it, started executing.
+#[derive(Debug, Default)]
+pub struct UpgradeExecuting {
+    pub block_height: BlockHeight,
+    pub upgrade_height: BlockHeight,
+}
+
+/// An upgrade was executed, including all its chunks.
+#[derive(Debug, Default)]
+pub struct UpgradeSucceeded {
+    pub block_height: BlockHeight,
+    pub upgrade_height: BlockHeight,
+    pub app_version: u64,
+}
+
+/// An upgrade failed, which halts the node.
+#[derive(Debug, Default)]
+pub struct UpgradeFailed<'a> {
+    pub block_height: BlockHeight,
+    pub upgrade_height: BlockHeight,
+    pub reason: &'a str,
+}
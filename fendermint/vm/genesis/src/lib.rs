@@ -47,6 +47,137 @@ pub struct Genesis {
     /// IPC related configuration, if enabled.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ipc: Option<ipc::IpcParams>,
+    /// Overrides for the resource limits applied during execution, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_limits: Option<ExecLimits>,
+    /// Additional actors and contracts to deploy after the built-in ones, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployments: Option<Deployments>,
+}
+
+/// Limits on the resources used during execution.
+///
+/// These are part of the consensus rules, because exceeding them fails the message,
+/// or changes what gets executed in a block, so they have to be the same on every
+/// validator. Anything not set uses the FVM defaults.
+#[serde_as]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecLimits {
+    /// Maximum memory used during the entire (recursive) execution of a message, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_bytes: Option<u64>,
+    /// Maximum memory of any single Wasm instance, ie. each level of the recursion, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_inst_memory_bytes: Option<u64>,
+    /// Maximum depth of actor calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_call_depth: Option<u32>,
+    /// Maximum number of elements on the Wasm stack.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wasm_stack: Option<u32>,
+    /// Gas the top-down messages can use in a block, after which the rest of them are
+    /// carried over to the following blocks. Unlimited if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topdown_gas_budget: Option<u64>,
+    /// Maximum number of bottom-up messages in a checkpoint; the rest are carried over
+    /// to the following checkpoints, in order. Unlimited if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bottomup_max_msgs: Option<u32>,
+    /// Maximum ABI encoded size of the bottom-up messages in a checkpoint, in bytes; the rest
+    /// are carried over like above, except that a single message is always let through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bottomup_max_bytes: Option<u64>,
+    /// Number of blocks of bottom-up checkpoint history kept in the gateway; older checkpoints
+    /// which reached a quorum are pruned, a few periods at a time. Kept forever if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_history: Option<u64>,
+    /// Ethereum address of the contract setting the block gas limit and the base fee
+    /// parameters. Blocks have no gas limit and the base fee stays the same if not set.
+    #[serde_as(as = "Option<IsHumanReadable>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_governance: Option<Address>,
+}
+
+/// Actors and contracts an application subnet wants to launch with, deployed in the
+/// order they are listed, after the built-in and IPC actors.
+///
+/// They are given the next free actor IDs, so adding one changes the IDs of those after it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Deployments {
+    /// Actors from the custom actor bundle.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actors: Vec<GenesisActor>,
+    /// EVM contracts, deployed after the actors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contracts: Vec<GenesisContract>,
+    /// Calls to the contracts above, executed after all of them have been deployed,
+    /// to seed their storage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<GenesisCall>,
+}
+
+impl Deployments {
+    pub fn is_empty(&self) -> bool {
+        self.actors.is_empty() && self.contracts.is_empty() && self.calls.is_empty()
+    }
+
+    /// Check that no two contracts have the same name, as the calls refer to them by name.
+    pub fn check_contract_names(&self) -> anyhow::Result<()> {
+        let mut names = std::collections::BTreeSet::new();
+        for c in self.contracts.iter() {
+            if !names.insert(c.name.as_str()) {
+                return Err(anyhow!("duplicate contract name: {}", c.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An actor from the custom actor bundle, created with a given state.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenesisActor {
+    /// The name of the actor code in the custom actor bundle.
+    pub name: String,
+    /// The hex encoded DAG-CBOR state of the actor.
+    pub state: String,
+    #[serde_as(as = "IsHumanReadable")]
+    #[serde(default)]
+    pub balance: TokenAmount,
+}
+
+/// An EVM contract deployed by running its constructor.
+///
+/// The constructor sees the system actor as the `msg.sender`, so the owner of the
+/// contract, if it has one, has to be passed as a constructor argument.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenesisContract {
+    /// The name the calls can refer to the contract by.
+    pub name: String,
+    /// The hex encoded creation bytecode of the contract.
+    pub bytecode: String,
+    /// The hex encoded ABI encoded constructor arguments, appended to the bytecode.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub constructor_args: String,
+    #[serde_as(as = "IsHumanReadable")]
+    #[serde(default)]
+    pub balance: TokenAmount,
+}
+
+/// A call to one of the contracts deployed at genesis.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenesisCall {
+    /// The name of the contract to call.
+    pub contract: String,
+    /// The sender of the call, which has to be one of the genesis accounts;
+    /// the system actor if not set.
+    #[serde_as(as = "Option<IsHumanReadable>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    /// The hex encoded ABI encoded calldata, including the function selector.
+    pub calldata: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -252,7 +383,7 @@ mod tests {
     use num_traits::Num;
     use quickcheck_macros::quickcheck;
 
-    use crate::{Collateral, Genesis};
+    use crate::{Collateral, Deployments, Genesis, GenesisContract};
 
     #[quickcheck]
     fn genesis_json(value0: Genesis) {
@@ -272,6 +403,24 @@ mod tests {
         assert_eq!(value1, value0)
     }
 
+    #[test]
+    fn duplicate_contract_names() {
+        let contract = |name: &str| GenesisContract {
+            name: name.to_string(),
+            bytecode: String::new(),
+            constructor_args: String::new(),
+            balance: TokenAmount::default(),
+        };
+        let mut deployments = Deployments {
+            contracts: vec![contract("a"), contract("b")],
+            ..Default::default()
+        };
+        assert!(deployments.check_contract_names().is_ok());
+
+        deployments.contracts.push(contract("a"));
+        assert!(deployments.check_contract_names().is_err());
+    }
+
     #[test]
     fn tokens_to_power() {
         // Collateral given in atto (18 digits after the decimal)
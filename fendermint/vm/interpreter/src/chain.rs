@@ -1,7 +1,7 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use crate::fvm::state::ipc::GatewayCaller;
-use crate::fvm::{topdown, FvmApplyRet, PowerUpdates};
+use crate::fvm::{checkpoint, topdown, FvmApplyRet, PowerUpdates};
 use crate::{
     fvm::state::FvmExecState,
     fvm::FvmMessage,
@@ -11,7 +11,9 @@ use crate::{
 use anyhow::{bail, Context};
 use async_stm::atomically;
 use async_trait::async_trait;
+use fendermint_tracing::emit;
 use fendermint_vm_actor_interface::ipc;
+use fendermint_vm_event::ParentFinalityExecuted;
 use fendermint_vm_message::ipc::ParentFinality;
 use fendermint_vm_message::{
     chain::ChainMessage,
@@ -21,7 +23,8 @@ use fendermint_vm_resolver::pool::{ResolveKey, ResolvePool};
 use fendermint_vm_topdown::proxy::IPCProviderProxy;
 use fendermint_vm_topdown::voting::{ValidatorKey, VoteTally};
 use fendermint_vm_topdown::{
-    CachedFinalityProvider, IPCParentFinality, ParentFinalityProvider, ParentViewProvider, Toggle,
+    CachedFinalityProvider, CommittedFinality, IPCParentFinality, ParentFinalityLog,
+    ParentFinalityProvider, ParentViewProvider, Toggle,
 };
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::RawBytes;
@@ -43,6 +46,8 @@ pub struct ChainEnv {
     /// The parent finality provider for top down checkpoint
     pub parent_finality_provider: TopDownFinalityProvider,
     pub parent_finality_votes: VoteTally,
+    /// Log of the committed parent finalities, if the node keeps one.
+    pub parent_finality_log: Option<Arc<dyn ParentFinalityLog>>,
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
@@ -323,6 +328,14 @@ where
                         "chain interpreter received total validator changes"
                     );
 
+                    let num_validator_changes = validator_changes.len();
+                    let logged_validator_changes = env
+                        .parent_finality_log
+                        .as_ref()
+                        .map(|_| validator_changes.clone());
+
+                    // The changes are stored before the messages are executed and don't count towards
+                    // the top-down gas budget, so a flood of messages can't hold them back.
                     self.gateway_caller
                         .store_validator_changes(&mut state, validator_changes)
                         .context("failed to store validator changes")?;
@@ -341,10 +354,26 @@ where
                         "chain interpreter received topdown msgs",
                     );
 
+                    let num_msgs = msgs.len();
+                    let logged_msgs = env.parent_finality_log.as_ref().map(|_| msgs.clone());
+
                     let ret = topdown::execute_topdown_msgs(&self.gateway_caller, &mut state, msgs)
                         .await
                         .context("failed to execute top down messages")?;
 
+                    // The checkpoints the parent committed as of the finalized block can be pruned.
+                    checkpoint::maybe_record_parent_checkpoint_height(&mut state, || {
+                        env.parent_finality_provider
+                            .last_bottom_up_checkpoint_height(finality.height)
+                    })
+                    .await?;
+
+                    emit!(ParentFinalityExecuted {
+                        block_height: finality.height,
+                        num_msgs,
+                        num_validator_changes,
+                    });
+
                     tracing::debug!("chain interpreter applied topdown msgs");
 
                     atomically(|| {
@@ -363,6 +392,25 @@ where
                         "chain interpreter has set new"
                     );
 
+                    if let Some(log) = env.parent_finality_log.as_ref() {
+                        let entry = CommittedFinality {
+                            block_height: state.block_height() as u64,
+                            finality: finality.clone(),
+                            executed_from: execution_fr,
+                            executed_to: execution_to,
+                            validator_changes: logged_validator_changes.unwrap_or_default(),
+                            top_down_msgs: logged_msgs.unwrap_or_default(),
+                        };
+                        // The log is node-local, so failing to write it must not fail the block.
+                        if let Err(e) = log.append(&entry) {
+                            tracing::warn!(
+                                error = e.to_string(),
+                                height = entry.block_height,
+                                "failed to append to the parent finality log"
+                            );
+                        }
+                    }
+
                     Ok(((env, state), ChainMessageApplyRet::Ipc(ret)))
                 }
             },
@@ -373,7 +421,21 @@ where
         &self,
         (env, state): Self::State,
     ) -> anyhow::Result<(Self::State, Self::BeginOutput)> {
-        let (state, out) = self.inner.begin(state).await?;
+        let (mut state, out) = self.inner.begin(state).await?;
+
+        // Top-down messages which didn't fit into the gas budget of earlier blocks
+        // go first, before anything else can be executed in this block.
+        if let Some(ret) = topdown::execute_pending_topdown_msgs(&self.gateway_caller, &mut state)
+            .await
+            .context("failed to execute pending top down messages")?
+        {
+            tracing::debug!(
+                gas_used = ret.apply_ret.msg_receipt.gas_used,
+                pending = state.pending_topdown_msgs().len(),
+                "chain interpreter applied pending topdown msgs"
+            );
+        }
+
         Ok(((env, state), out))
     }
 
@@ -3,6 +3,7 @@
 
 use async_trait::async_trait;
 
+use fendermint_vm_message::signed::DomainHash;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared::{address::Address, error::ExitCode};
@@ -17,8 +18,12 @@ pub struct FvmCheckRet {
     pub sender: Address,
     pub gas_limit: u64,
     pub exit_code: ExitCode,
+    /// Gas used if the message was executed, see `exec_in_check`.
+    pub gas_used: Option<u64>,
     pub return_data: Option<RawBytes>,
     pub info: Option<String>,
+    /// Domain specific hash of the message, filled in by the signature checking layer.
+    pub domain_hash: Option<DomainHash>,
 }
 
 #[async_trait]
@@ -34,6 +39,8 @@ where
     type Output = FvmCheckRet;
 
     /// Check that:
+    /// * the message follows the admission policy of the node
+    /// * the message fits in a block under the block gas limit
     /// * sender exists
     /// * sender nonce matches the message sequence
     /// * sender has enough funds to cover the gas cost
@@ -62,8 +69,10 @@ where
                 sender: msg.from,
                 gas_limit: msg.gas_limit,
                 exit_code,
+                gas_used,
                 return_data,
                 info,
+                domain_hash: None,
             };
             Ok((state, ret))
         };
@@ -78,6 +87,31 @@ where
             );
         }
 
+        if let Some((exit_code, reason)) = self.check_policy.check(state.state_tree(), &msg)? {
+            return checked(
+                state,
+                exit_code,
+                None,
+                None,
+                Some(format!("rejected by policy: {reason}")),
+            );
+        }
+
+        if let Some(block_gas_limit) = state.block_gas_available() {
+            if msg.gas_limit > block_gas_limit {
+                return checked(
+                    state,
+                    ExitCode::SYS_ASSERTION_FAILED,
+                    None,
+                    None,
+                    Some(format!(
+                        "gas limit {} over the block gas limit {block_gas_limit}",
+                        msg.gas_limit
+                    )),
+                );
+            }
+        }
+
         // NOTE: This would be a great place for let-else, but clippy runs into a compilation bug.
         let state_tree = state.state_tree_mut();
 
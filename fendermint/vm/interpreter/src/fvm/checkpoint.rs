@@ -2,10 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
 use std::time::Duration;
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use ethers::abi::Tokenizable;
+use serde::Serialize;
 use tendermint::block::Height;
 use tendermint_rpc::endpoint::commit;
 use tendermint_rpc::{endpoint::validators, Client, Paging};
@@ -14,10 +17,9 @@ use fvm_ipld_blockstore::Blockstore;
 use fvm_shared::{address::Address, chainid::ChainID};
 
 use fendermint_crypto::PublicKey;
-use fendermint_crypto::SecretKey;
 use fendermint_tracing::emit;
 use fendermint_vm_actor_interface::eam::EthAddress;
-use fendermint_vm_actor_interface::ipc::BottomUpCheckpoint;
+use fendermint_vm_actor_interface::ipc::{AbiHash, BottomUpCheckpoint};
 use fendermint_vm_event::NewBottomUpCheckpoint;
 use fendermint_vm_genesis::{Power, Validator, ValidatorKey};
 
@@ -28,10 +30,38 @@ use ipc_api::staking::ConfigurationNumber;
 use super::state::ipc::tokens_to_burn;
 use super::{
     broadcast::Broadcaster,
+    signer::CheckpointSigner,
     state::{ipc::GatewayCaller, FvmExecState},
     ValidatorContext,
 };
 
+/// The number of checkpoint periods pruned at most in one go.
+const MAX_PRUNED_PERIODS: u64 = 2;
+
+/// Record the height of the last bottom-up checkpoint committed by the parent, which bounds
+/// what [maybe_prune_checkpoints] can remove, as fetched when a parent finality is executed.
+///
+/// Only chains with a checkpoint history limit prune anything, so on the others nothing is
+/// fetched and the state parameters, hence the app hash, stay the same as before pruning existed.
+pub async fn maybe_record_parent_checkpoint_height<DB, F, Fut>(
+    state: &mut FvmExecState<DB>,
+    fetch_height: F,
+) -> anyhow::Result<()>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<u64>>,
+{
+    if state.checkpoint_history().is_none() {
+        return Ok(());
+    }
+    let height = fetch_height()
+        .await
+        .context("failed to fetch the last committed bottom-up checkpoint height")?;
+    state.set_parent_checkpoint_height(height);
+    Ok(())
+}
+
 /// Validator voting power snapshot.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PowerTable(pub Vec<Validator<Power>>);
@@ -131,6 +161,265 @@ where
     Ok(Some((checkpoint, power_updates)))
 }
 
+/// A pruned checkpoint as exported to the archive, with the signatures it was relayed with.
+#[derive(Debug, Serialize)]
+struct ArchivedCheckpoint {
+    checkpoint: ipc_api::checkpoint::BottomUpCheckpoint,
+    /// Hex encoded hash of the ABI encoded checkpoint, which the validators signed.
+    hash: String,
+    /// Whether the signatures reached the quorum threshold.
+    reached: bool,
+    /// Hex encoded Ethereum addresses of the signatories.
+    signatories: Vec<String>,
+    /// Hex encoded signatures, in the same order as the signatories.
+    signatures: Vec<String>,
+}
+
+/// Prune the checkpoints which fell out of the history kept in the ledger, if it's limited,
+/// exporting them to the archive directory first if there is one.
+///
+/// Only checkpoints below the height of the last one committed by the parent are pruned,
+/// as of the last executed parent finality, so the parent keeps its own copy of them and
+/// the relayers don't need their signatures any more. Pruning happens at the checkpoint
+/// period, removing at most a few periods at a time to bound the gas it uses.
+///
+/// Failing to archive the checkpoints is logged but doesn't stop them from being pruned,
+/// as the ledger can't depend on the local file system of a node.
+///
+/// Returns the range of heights pruned, if any.
+pub fn maybe_prune_checkpoints<DB>(
+    gateway: &GatewayCaller<DB>,
+    state: &mut FvmExecState<DB>,
+    archive_dir: Option<&Path>,
+) -> anyhow::Result<Option<Range<u64>>>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+{
+    let Some(history) = state.checkpoint_history() else {
+        return Ok(None);
+    };
+
+    let Some(parent_checkpoint_height) = state.parent_checkpoint_height() else {
+        return Ok(None);
+    };
+
+    if !gateway.enabled(state)? || gateway.is_root(state)? {
+        return Ok(None);
+    }
+
+    let height = state.block_height() as u64;
+    let period = gateway.bottom_up_check_period(state)?;
+
+    if period == 0 || height % period != 0 {
+        return Ok(None);
+    }
+
+    let retention_height = gateway.checkpoint_retention_height(state)?;
+
+    let mut new_retention_height = height
+        .saturating_sub(history)
+        .min(retention_height + MAX_PRUNED_PERIODS * period)
+        .min(parent_checkpoint_height);
+
+    if let Some(first_incomplete) = gateway
+        .incomplete_checkpoint_heights(state)?
+        .into_iter()
+        .min()
+    {
+        new_retention_height = new_retention_height.min(first_incomplete);
+    }
+
+    if new_retention_height <= retention_height {
+        return Ok(None);
+    }
+
+    if let Some(dir) = archive_dir {
+        if let Err(e) =
+            archive_checkpoints(gateway, state, dir, retention_height..new_retention_height)
+        {
+            tracing::warn!(
+                error = e.to_string(),
+                from = retention_height,
+                to = new_retention_height,
+                "failed to archive bottom-up checkpoints"
+            );
+        }
+    }
+
+    gateway
+        .prune_bottom_up_checkpoints(state, new_retention_height)
+        .context("failed to prune checkpoints")?;
+
+    tracing::info!(
+        from = retention_height,
+        to = new_retention_height,
+        "pruned bottom-up checkpoints"
+    );
+
+    Ok(Some(retention_height..new_retention_height))
+}
+
+/// Export the checkpoints in a range of heights as JSON files named after their height.
+///
+/// The files are simply overwritten if the blocks are executed again, e.g. after a restart.
+fn archive_checkpoints<DB>(
+    gateway: &GatewayCaller<DB>,
+    state: &mut FvmExecState<DB>,
+    dir: &Path,
+    heights: Range<u64>,
+) -> anyhow::Result<()>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+{
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create {}", dir.to_string_lossy()))?;
+
+    for height in heights {
+        let (checkpoint, info, signatories, signatures) =
+            gateway.checkpoint_signature_bundle(state, height)?;
+
+        // Most heights don't have a checkpoint.
+        if checkpoint.block_height.is_zero() {
+            continue;
+        }
+
+        let archived = ArchivedCheckpoint {
+            checkpoint: checkpoint.try_into()?,
+            hash: hex::encode(info.hash),
+            reached: info.reached,
+            signatories: signatories.iter().map(hex::encode).collect(),
+            signatures: signatures.iter().map(hex::encode).collect(),
+        };
+
+        let path = dir.join(format!("{height}.json"));
+        let json = serde_json::to_string_pretty(&archived)?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("failed to write {}", path.to_string_lossy()))?;
+    }
+    Ok(())
+}
+
+/// A bottom-up checkpoint re-derived from the ledger, next to the one the node created.
+#[derive(Debug, Serialize)]
+pub struct CheckpointSimulation {
+    /// The checkpoint stored in the gateway when the block was executed.
+    pub stored: ipc_api::checkpoint::BottomUpCheckpoint,
+    /// Hex encoded hash of the stored checkpoint.
+    pub stored_hash: String,
+    /// Hex encoded hash the gateway collects the signatures for.
+    pub signed_hash: String,
+    /// The checkpoint re-derived from the state before and after the block.
+    pub simulated: ipc_api::checkpoint::BottomUpCheckpoint,
+    /// Hex encoded hash of the re-derived checkpoint.
+    pub simulated_hash: String,
+    /// Whether the block hash was checked against one given from CometBFT.
+    pub block_hash_checked: bool,
+    /// The differences found, empty if the checkpoints and the hashes match.
+    pub differences: Vec<String>,
+}
+
+/// Re-derive the checkpoint created at the end of block `height` and compare it with the one
+/// stored in the gateway then, to debug nodes which produce a different checkpoint hash.
+///
+/// The inputs are taken from `prev_state`, the state before the block, and `state`, the state
+/// after it: the message batch of the height, the messages carried over from earlier checkpoints
+/// and the limits on them, and the configuration number the validator changes took effect with.
+/// The block hash isn't in the ledger, so it's only checked if one is given.
+pub fn simulate_checkpoint<DB>(
+    gateway: &GatewayCaller<DB>,
+    prev_state: &mut FvmExecState<DB>,
+    state: &mut FvmExecState<DB>,
+    height: u64,
+    block_hash: Option<[u8; 32]>,
+) -> anyhow::Result<CheckpointSimulation>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+{
+    let (stored, info, _, _) = gateway.checkpoint_signature_bundle(state, height)?;
+
+    if stored.block_height.is_zero() {
+        bail!("there is no checkpoint at height {height}, or it has been pruned");
+    }
+
+    let subnet_id = gateway.subnet_id(state)?;
+    let batch = gateway.bottom_up_msg_batch(state, height)?;
+    // Uses the pending messages and the limits before the block.
+    let msgs = limit_bottom_up_msgs(prev_state, batch.msgs)?;
+
+    let prev_configuration_number = gateway.current_membership(prev_state)?.configuration_number;
+    let configuration_number = gateway.current_membership(state)?.configuration_number;
+    let next_configuration_number = if configuration_number != prev_configuration_number {
+        configuration_number
+    } else {
+        0
+    };
+
+    let simulated = getter::BottomUpCheckpoint {
+        subnet_id,
+        block_height: height.into(),
+        block_hash: block_hash.unwrap_or(stored.block_hash),
+        next_configuration_number,
+        msgs,
+    };
+
+    let mut differences = Vec::new();
+    if simulated.subnet_id != stored.subnet_id {
+        differences.push(format!(
+            "subnet ID: stored {:?}, re-derived {:?}",
+            stored.subnet_id, simulated.subnet_id
+        ));
+    }
+    if simulated.block_hash != stored.block_hash {
+        differences.push(format!(
+            "block hash: stored {}, re-derived {}",
+            hex::encode(stored.block_hash),
+            hex::encode(simulated.block_hash)
+        ));
+    }
+    if simulated.next_configuration_number != stored.next_configuration_number {
+        differences.push(format!(
+            "next configuration number: stored {}, re-derived {}",
+            stored.next_configuration_number, simulated.next_configuration_number
+        ));
+    }
+    if simulated.msgs != stored.msgs {
+        let first = simulated
+            .msgs
+            .iter()
+            .zip(stored.msgs.iter())
+            .position(|(a, b)| a != b)
+            .unwrap_or(simulated.msgs.len().min(stored.msgs.len()));
+        differences.push(format!(
+            "messages: stored {}, re-derived {}, first differing at index {first}",
+            stored.msgs.len(),
+            simulated.msgs.len()
+        ));
+    }
+
+    let stored_hash = checkpoint_hash(&stored)?;
+    let simulated_hash = checkpoint_hash(&simulated)?;
+
+    if stored_hash != info.hash {
+        differences.push("the stored checkpoint doesn't match the signed hash".to_string());
+    }
+
+    Ok(CheckpointSimulation {
+        stored: stored.try_into()?,
+        stored_hash: hex::encode(stored_hash),
+        signed_hash: hex::encode(info.hash),
+        simulated: simulated.try_into()?,
+        simulated_hash: hex::encode(simulated_hash),
+        block_hash_checked: block_hash.is_some(),
+        differences,
+    })
+}
+
+/// The hash of a checkpoint as the validators sign it.
+fn checkpoint_hash(cp: &getter::BottomUpCheckpoint) -> anyhow::Result<[u8; 32]> {
+    let cp = checkpoint::BottomUpCheckpoint::from_token(cp.clone().into_token())?;
+    Ok(cp.abi_hash())
+}
+
 /// Wait until CometBFT has reached a specific block height.
 ///
 /// This is used so we can wait for the next block where the ledger changes
@@ -249,7 +538,7 @@ where
                 checkpoint,
                 &power_table,
                 &validator,
-                &validator_ctx.secret_key,
+                &validator_ctx.signer,
                 chain_id,
             )
             .await
@@ -268,15 +557,22 @@ pub async fn broadcast_signature<C, DB>(
     checkpoint: checkpoint::BottomUpCheckpoint,
     power_table: &PowerTable,
     validator: &Validator<Power>,
-    secret_key: &SecretKey,
+    signer: &CheckpointSigner,
     chain_id: ChainID,
 ) -> anyhow::Result<()>
 where
     C: Client + Clone + Send + Sync + 'static,
     DB: Blockstore + Send + Sync + Clone + 'static,
 {
+    debug_assert_eq!(validator.public_key.0, signer.public_key());
+
+    let signature = signer
+        .sign(&checkpoint.clone().abi_hash())
+        .await
+        .context("failed to sign checkpoint")?;
+
     let calldata = gateway
-        .add_checkpoint_signature_calldata(checkpoint, &power_table.0, validator, secret_key)
+        .add_checkpoint_signature_calldata(checkpoint, &power_table.0, validator, &signature)
         .context("failed to produce checkpoint signature calldata")?;
 
     let tx_hash = broadcaster
@@ -338,10 +634,88 @@ where
         root: id.root,
         route: id.route,
     };
-    let msgs = convert_tokenizables(batch.msgs)?;
+    let msgs = limit_bottom_up_msgs(state, batch.msgs)?;
+    let msgs = convert_tokenizables(msgs)?;
     Ok(Some((msgs, id)))
 }
 
+/// Put the bottom-up messages carried over from earlier checkpoints in front of the new ones,
+/// and carry over whatever doesn't fit into the limits of this checkpoint to the next one.
+///
+/// The parent executes the messages strictly in the order of their nonces, so the ones
+/// carried over always form a suffix, and the first message is included even if it is
+/// bigger than the limit on its own, so that it can't hold back the rest forever.
+fn limit_bottom_up_msgs<DB>(
+    state: &mut FvmExecState<DB>,
+    msgs: Vec<getter::IpcEnvelope>,
+) -> anyhow::Result<Vec<getter::IpcEnvelope>>
+where
+    DB: Blockstore + Clone,
+{
+    let (max_msgs, max_bytes) = state.bottomup_limits();
+    let num_pending = state.pending_bottomup_msgs().len();
+
+    if num_pending == 0 && max_msgs.is_none() && max_bytes.is_none() {
+        return Ok(msgs);
+    }
+
+    let mut all = state
+        .pending_bottomup_msgs()
+        .iter()
+        .cloned()
+        .map(getter::IpcEnvelope::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .context("failed to convert pending bottom-up messages")?;
+    all.extend(msgs);
+
+    let sizes = all
+        .iter()
+        .map(|msg| ethers::abi::encode(&[msg.clone().into_token()]).len() as u64);
+    let count = msgs_within_limits(sizes, max_msgs, max_bytes);
+
+    let spilled = all
+        .split_off(count)
+        .into_iter()
+        .map(ipc_api::cross::IpcEnvelope::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .context("failed to convert spilled bottom-up messages")?;
+
+    if !spilled.is_empty() {
+        tracing::info!(
+            included = count,
+            carried_over = spilled.len(),
+            "bottom-up messages exceed the checkpoint limits"
+        );
+    }
+
+    if num_pending > 0 || !spilled.is_empty() {
+        state.set_pending_bottomup_msgs(spilled);
+    }
+
+    Ok(all)
+}
+
+/// The number of messages from the front which fit into the limits, given their sizes;
+/// always at least one, unless there are no messages at all.
+fn msgs_within_limits(
+    sizes: impl IntoIterator<Item = u64>,
+    max_msgs: Option<u32>,
+    max_bytes: Option<u64>,
+) -> usize {
+    let mut count = 0;
+    let mut bytes = 0;
+    for size in sizes {
+        let fits = max_msgs.map_or(true, |max| count < max as usize)
+            && max_bytes.map_or(true, |max| bytes + size <= max);
+        if !fits && count > 0 {
+            break;
+        }
+        count += 1;
+        bytes += size;
+    }
+    count
+}
+
 /// Get the power table from CometBFT.
 ///
 /// This is prone to failing, e.g. one theory is that CometBFT is trying to restart
@@ -432,10 +806,21 @@ mod tests {
     use fendermint_vm_genesis::{Power, Validator};
     use quickcheck_macros::quickcheck;
 
-    use crate::fvm::checkpoint::{into_power_map, power_diff};
+    use crate::fvm::checkpoint::{
+        into_power_map, maybe_create_checkpoint, maybe_record_parent_checkpoint_height,
+        msgs_within_limits, power_diff, simulate_checkpoint,
+    };
+    use crate::fvm::state::ipc::GatewayCaller;
+    use crate::fvm::state::{BlockHash, FvmExecState, FvmStateParams};
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use crate::fvm::topdown::tests::{make_genesis, Chain};
+
+    use anyhow::anyhow;
 
     use super::{PowerTable, PowerUpdates};
 
+    const BLOCK_HASH: BlockHash = [1u8; 32];
+
     fn power_update(current: PowerTable, updates: PowerUpdates) -> PowerTable {
         let mut current = into_power_map(current);
 
@@ -499,4 +884,124 @@ mod tests {
         let next = PowerTable(vec![v2, v1]);
         assert!(power_diff(current, next).0.is_empty());
     }
+
+    #[test]
+    fn test_msgs_within_limits() {
+        let sizes = [100, 200, 300, 400];
+
+        assert_eq!(msgs_within_limits(sizes, None, None), 4);
+        assert_eq!(msgs_within_limits(sizes, Some(2), None), 2);
+        assert_eq!(msgs_within_limits(sizes, None, Some(600)), 3);
+        assert_eq!(msgs_within_limits(sizes, Some(2), Some(250)), 1);
+        // An oversized message is let through on its own.
+        assert_eq!(msgs_within_limits([1000, 100], None, Some(500)), 1);
+        assert_eq!(msgs_within_limits([], Some(1), Some(1)), 0);
+    }
+
+    /// Run a subnet up to its first checkpoint, returning it along with the
+    /// state parameters before the block which created the checkpoint.
+    async fn run_to_checkpoint() -> (Chain, FvmStateParams) {
+        let mut chain = Chain::new(make_genesis(None)).await;
+        let gateway = GatewayCaller::default();
+        let mut prev_params = chain.params.clone();
+        // The genesis has a checkpoint period of 10.
+        while chain.height < 10 {
+            prev_params = chain.params.clone();
+            let mut state = chain.begin_block().with_block_hash(BLOCK_HASH);
+            maybe_create_checkpoint(&gateway, &mut state).expect("failed to create checkpoint");
+            chain.commit(state);
+        }
+        (chain, prev_params)
+    }
+
+    fn simulate(
+        chain: &Chain,
+        prev_params: FvmStateParams,
+        block_hash: Option<BlockHash>,
+    ) -> super::CheckpointSimulation {
+        let exec_state = |params| {
+            FvmExecState::<MemoryBlockstore>::new(
+                chain.store.clone(),
+                &chain.multi_engine,
+                chain.height,
+                params,
+            )
+            .expect("failed to create exec state")
+        };
+        let mut prev_state = exec_state(prev_params);
+        let mut state = exec_state(chain.params.clone());
+
+        simulate_checkpoint(
+            &GatewayCaller::default(),
+            &mut prev_state,
+            &mut state,
+            chain.height as u64,
+            block_hash,
+        )
+        .expect("failed to simulate checkpoint")
+    }
+
+    #[tokio::test]
+    async fn test_simulate_checkpoint_match() {
+        let (chain, prev_params) = run_to_checkpoint().await;
+        let sim = simulate(&chain, prev_params, Some(BLOCK_HASH));
+
+        assert!(sim.differences.is_empty(), "{:?}", sim.differences);
+        assert!(sim.block_hash_checked);
+        assert_eq!(sim.stored_hash, sim.simulated_hash);
+        assert_eq!(sim.stored_hash, sim.signed_hash);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_checkpoint_mismatch() {
+        let (chain, prev_params) = run_to_checkpoint().await;
+        let sim = simulate(&chain, prev_params, Some([2u8; 32]));
+
+        assert_eq!(
+            sim.differences,
+            vec![format!(
+                "block hash: stored {}, re-derived {}",
+                hex::encode(BLOCK_HASH),
+                hex::encode([2u8; 32])
+            )]
+        );
+        assert_ne!(sim.stored_hash, sim.simulated_hash);
+        // The stored checkpoint is still the one that was signed.
+        assert_eq!(sim.stored_hash, sim.signed_hash);
+    }
+
+    #[tokio::test]
+    async fn test_parent_checkpoint_height_without_history_limit() {
+        let chain = Chain::new(make_genesis(None)).await;
+        let mut state = chain.begin_block();
+
+        maybe_record_parent_checkpoint_height(&mut state, || async {
+            Err::<u64, _>(anyhow!("the parent is not asked without a history limit"))
+        })
+        .await
+        .unwrap();
+
+        // Nothing changes, so the app hash stays the same as well.
+        let (state_root, params, params_dirty) = state.commit().unwrap();
+        assert!(!params_dirty);
+        assert_eq!(state_root, chain.params.state_root);
+        assert_eq!(params.parent_checkpoint_height, None);
+    }
+
+    #[tokio::test]
+    async fn test_parent_checkpoint_height_with_history_limit() {
+        let mut chain = Chain::new(make_genesis(None)).await;
+        if let Some(limits) = chain.params.exec_limits.as_mut() {
+            limits.checkpoint_history = Some(100);
+        }
+        let mut state = chain.begin_block();
+
+        maybe_record_parent_checkpoint_height(&mut state, || async { Ok(42) })
+            .await
+            .unwrap();
+
+        let (_, params, params_dirty) = state.commit().unwrap();
+        assert!(params_dirty);
+        assert_eq!(params.parent_checkpoint_height, Some(42));
+    }
 }
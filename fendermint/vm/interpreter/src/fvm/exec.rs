@@ -5,20 +5,29 @@ use anyhow::Context;
 use async_trait::async_trait;
 use std::collections::HashMap;
 
-use fendermint_vm_actor_interface::{chainmetadata, cron, system};
+use fendermint_tracing::emit;
+use fendermint_vm_actor_interface::{chainmetadata, cron, scheduler, system};
+use fendermint_vm_event::{
+    MsgApplied, MsgExecLimits, UpgradeExecuting, UpgradeFailed, UpgradePending, UpgradeProgress,
+    UpgradeSucceeded,
+};
 use fvm::executor::ApplyRet;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_shared::{address::Address, ActorID, MethodNum, BLOCK_GAS_LIMIT};
+use fvm_shared::{address::Address, error::ExitCode, ActorID, MethodNum, BLOCK_GAS_LIMIT};
 use tendermint_rpc::Client;
 
 use crate::ExecInterpreter;
 
 use super::{
     checkpoint::{self, PowerUpdates},
-    state::FvmExecState,
+    gas::{next_base_fee, GasGovernance},
+    state::{check_error, FvmExecState},
     FvmMessage, FvmMessageInterpreter,
 };
 
+/// Messages using at least this percentage of their gas limit are reported as approaching it.
+const NEAR_GAS_LIMIT_PERCENT: u64 = 90;
+
 /// The return value extended with some things from the message that
 /// might not be available to the caller, because of the message lookups
 /// and transformations that happen along the way, e.g. where we need
@@ -58,12 +67,83 @@ where
         // check for upgrades in the upgrade_scheduler
         let chain_id = state.chain_id();
         let block_height: u64 = state.block_height().try_into().unwrap();
-        if let Some(upgrade) = self.upgrade_scheduler.get(chain_id, block_height) {
-            // TODO: consider using an explicit tracing enum for upgrades
-            tracing::info!(?chain_id, height = block_height, "Executing an upgrade");
+        let mut upgrade = match self.upgrade_scheduler.get(chain_id, block_height) {
+            Some(upgrade) => Some(upgrade.clone()),
+            None => self
+                .upgrade_scheduler
+                .get_governed(&mut state)
+                .context("failed to check for governed upgrades")?,
+        };
+        if let Some(executed_at) = upgrade
+            .as_ref()
+            .and_then(|upgrade| self.upgrade_scheduler.executed_at(&state, upgrade))
+        {
+            tracing::warn!(
+                ?chain_id,
+                height = block_height,
+                executed_at,
+                "Skipping an upgrade which has already been executed"
+            );
+            upgrade = None;
+        }
+        // an upgrade split across several blocks continues where it left off
+        let in_progress = self
+            .upgrade_scheduler
+            .get_in_progress(&state)
+            .context("failed to continue upgrade")?;
+        let upgrade = match (in_progress, upgrade) {
+            (None, upgrade) => upgrade.map(|upgrade| (upgrade, 0)),
+            (Some(in_progress), None) => Some(in_progress),
+            (Some((current, _)), Some(_)) => anyhow::bail!(
+                "upgrade at height {block_height} overlaps with the upgrade in progress since height {}",
+                current.block_height()
+            ),
+        };
+        // let operators know an upgrade is coming, so they can check they run the right binary
+        if let Some(next) = self.upgrade_scheduler.next_upgrade(chain_id, block_height) {
+            emit!(UpgradePending {
+                block_height,
+                upgrade_height: next.block_height(),
+                blocks_remaining: next.block_height() - block_height,
+            });
+        }
+
+        if let Some((upgrade, chunk)) = upgrade {
+            let upgrade_height = upgrade.block_height();
+            emit!(UpgradeExecuting {
+                block_height,
+                upgrade_height,
+            });
 
             // there is an upgrade scheduled for this height, lets run the migration
-            let res = upgrade.execute(&mut state).context("upgrade failed")?;
+            let res = match upgrade.execute_chunk(&mut state, chunk) {
+                Ok(res) => res,
+                Err(e) => {
+                    emit!(UpgradeFailed {
+                        block_height,
+                        upgrade_height,
+                        reason: format!("{e:#}").as_str(),
+                    });
+                    return Err(e.context("upgrade failed"));
+                }
+            };
+
+            let chunks_done = chunk + 1;
+            if chunks_done < upgrade.chunks() {
+                state.record_upgrade_progress(&upgrade.digest(), chunks_done);
+            } else {
+                state.record_executed_upgrade(&upgrade.digest());
+            }
+
+            if upgrade.chunks() > 1 {
+                emit!(UpgradeProgress {
+                    block_height,
+                    upgrade_height: upgrade.block_height(),
+                    chunks_done,
+                    chunks: upgrade.chunks(),
+                });
+            }
+
             if let Some(new_app_version) = res {
                 state.update_app_version(|app_version| {
                     *app_version = new_app_version;
@@ -71,6 +151,33 @@ where
 
                 tracing::info!(app_version = state.app_version(), "upgraded app version");
             }
+
+            if chunks_done == upgrade.chunks() {
+                emit!(UpgradeSucceeded {
+                    block_height,
+                    upgrade_height,
+                    app_version: state.app_version(),
+                });
+            }
+        }
+
+        // apply the gas parameters once governance activates them
+        if let Some(contract) = state.gas_governance() {
+            if let Some(params) = GasGovernance::new(&contract)?
+                .gas_params(&mut state)
+                .context("failed to check the gas parameters")?
+            {
+                if state.gas_params() != Some(&params) {
+                    tracing::info!(
+                        height,
+                        block_gas_limit = params.block_gas_limit,
+                        min_base_fee = params.min_base_fee.to_string(),
+                        base_fee_max_change_denominator = params.base_fee_max_change_denominator,
+                        "activating new gas parameters"
+                    );
+                    state.set_gas_params(params);
+                }
+            }
         }
 
         // Arbitrarily large gas limit for cron (matching how Forest does it, which matches Lotus).
@@ -131,6 +238,8 @@ where
             }
         }
 
+        execute_scheduled(&mut state).context("failed to execute scheduled messages")?;
+
         let ret = FvmApplyRet {
             apply_ret,
             from,
@@ -153,10 +262,23 @@ where
         let method_num = msg.method_num;
         let gas_limit = msg.gas_limit;
 
-        let (apply_ret, emitters) = if from == system::SYSTEM_ACTOR_ADDR {
+        let is_implicit = from == system::SYSTEM_ACTOR_ADDR;
+
+        let (apply_ret, emitters) = if is_implicit {
             state.execute_implicit(msg)?
         } else {
-            state.execute_explicit(msg)?
+            match state.block_gas_available() {
+                // The proposer shouldn't have included it, but the block is still valid;
+                // the message is rejected without charging the sender, like a failed check.
+                Some(available) if gas_limit > available => check_error(anyhow::anyhow!(
+                    "gas limit {gas_limit} over the {available} gas left in the block"
+                )),
+                _ => {
+                    let (apply_ret, emitters) = state.execute_explicit(msg)?;
+                    state.record_block_gas_used(apply_ret.msg_receipt.gas_used);
+                    (apply_ret, emitters)
+                }
+            }
         };
 
         tracing::info!(
@@ -169,6 +291,39 @@ where
             "tx delivered"
         );
 
+        emit!(MsgApplied {
+            block_height: state.block_height() as u64,
+            from: &from.to_string(),
+            to: &to.to_string(),
+            method_num,
+            exit_code: apply_ret.msg_receipt.exit_code.value(),
+            gas_used: apply_ret.msg_receipt.gas_used,
+        });
+
+        // Implicit messages run with the block gas limit, so only user messages are interesting here.
+        if !is_implicit {
+            let exit_code = apply_ret.msg_receipt.exit_code;
+            let gas_used = apply_ret.msg_receipt.gas_used;
+            let out_of_gas = exit_code == ExitCode::SYS_OUT_OF_GAS;
+            let trapped = exit_code == ExitCode::SYS_ILLEGAL_INSTRUCTION;
+            let near_gas_limit = !out_of_gas
+                && gas_used.saturating_mul(100) >= gas_limit.saturating_mul(NEAR_GAS_LIMIT_PERCENT);
+
+            if near_gas_limit || out_of_gas || trapped {
+                emit!(MsgExecLimits {
+                    block_height: state.block_height() as u64,
+                    to: &to.to_string(),
+                    method_num,
+                    exit_code: exit_code.value(),
+                    gas_used,
+                    gas_limit,
+                    near_gas_limit: near_gas_limit as usize,
+                    out_of_gas: out_of_gas as usize,
+                    trapped: trapped as usize,
+                });
+            }
+        }
+
         let ret = FvmApplyRet {
             apply_ret,
             from,
@@ -231,6 +386,124 @@ where
             PowerUpdates::default()
         };
 
+        // adjust the base fee of the next block to how full this one was
+        if let Some(params) = state.gas_params().cloned() {
+            let gas_used = state.block_gas_used();
+            state
+                .update_base_fee(|base_fee| *base_fee = next_base_fee(base_fee, gas_used, &params));
+        }
+
+        checkpoint::maybe_prune_checkpoints(
+            &self.gateway,
+            &mut state,
+            self.checkpoint_archive_dir.as_deref(),
+        )
+        .context("failed to prune checkpoints")?;
+
         Ok((state, updates))
     }
 }
+
+/// Execute the messages the scheduler actor has due at the current height, on behalf of
+/// the actors which scheduled them. The actor has already taken their gas fee out of the
+/// deposit made when they were scheduled, so they are executed implicitly.
+///
+/// Failing messages don't fail the block, but they aren't repeated anymore.
+fn execute_scheduled<DB>(state: &mut FvmExecState<DB>) -> anyhow::Result<()>
+where
+    DB: Blockstore + Clone + 'static + Send + Sync,
+{
+    // Chains created before the scheduler was added don't have the actor.
+    if state
+        .state_tree()
+        .get_actor(scheduler::SCHEDULER_ACTOR_ID)?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    let height = state.block_height();
+
+    let due = call_scheduler(
+        state,
+        fendermint_actor_scheduler::Method::TakeDue,
+        fvm_ipld_encoding::RawBytes::serialize(height)?,
+    )
+    .context("failed to take the due messages from the scheduler")?
+    .deserialize::<Vec<fendermint_actor_scheduler::ScheduledMessage>>()
+    .context("failed to decode the due messages")?;
+
+    for scheduled in due {
+        let msg = FvmMessage {
+            from: scheduled.from,
+            to: scheduled.to,
+            sequence: height as u64,
+            gas_limit: scheduled.gas_limit,
+            method_num: scheduled.method,
+            params: scheduled.params,
+            value: scheduled.value,
+            version: Default::default(),
+            gas_fee_cap: Default::default(),
+            gas_premium: Default::default(),
+        };
+
+        let (apply_ret, _) = state.execute_implicit(msg)?;
+
+        tracing::info!(
+            height,
+            id = scheduled.id,
+            from = scheduled.from.to_string(),
+            to = scheduled.to.to_string(),
+            method_num = scheduled.method,
+            exit_code = apply_ret.msg_receipt.exit_code.value(),
+            gas_used = apply_ret.msg_receipt.gas_used,
+            "scheduled message executed"
+        );
+
+        if scheduled.interval > 0 && !apply_ret.msg_receipt.exit_code.is_success() {
+            let params = fendermint_actor_scheduler::CancelParams {
+                height: height + scheduled.interval,
+                id: scheduled.id,
+            };
+            call_scheduler(
+                state,
+                fendermint_actor_scheduler::Method::CancelFailed,
+                fvm_ipld_encoding::RawBytes::serialize(params)?,
+            )
+            .context("failed to cancel a failed scheduled message")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Call the scheduler actor as the system, failing if the call fails.
+fn call_scheduler<DB>(
+    state: &mut FvmExecState<DB>,
+    method: fendermint_actor_scheduler::Method,
+    params: fvm_ipld_encoding::RawBytes,
+) -> anyhow::Result<fvm_ipld_encoding::RawBytes>
+where
+    DB: Blockstore + Clone + 'static + Send + Sync,
+{
+    let msg = FvmMessage {
+        from: system::SYSTEM_ACTOR_ADDR,
+        to: scheduler::SCHEDULER_ACTOR_ADDR,
+        sequence: state.block_height() as u64,
+        gas_limit: BLOCK_GAS_LIMIT * 10000,
+        method_num: method as u64,
+        params,
+        value: Default::default(),
+        version: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+
+    let (apply_ret, _) = state.execute_implicit(msg)?;
+
+    if let Some(err) = apply_ret.failure_info {
+        anyhow::bail!("{}", err);
+    }
+
+    Ok(apply_ret.msg_receipt.return_data)
+}
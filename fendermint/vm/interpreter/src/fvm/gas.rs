@@ -0,0 +1,134 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Block gas limit and base fee parameters set by on-chain governance.
+//!
+//! The parameters are read from a governance contract on the subnet at the beginning of every
+//! block, and take effect from the height the contract returns for them, so that the owner can
+//! change them with a transaction rather than every validator changing its configuration.
+//! The contract is part of the genesis execution limits, so every validator reads the same one.
+
+use anyhow::anyhow;
+use fendermint_vm_actor_interface::eam::EthAddress;
+use fendermint_vm_message::conv::{from_eth, from_fvm};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::{address::Address, bigint::BigInt, econ::TokenAmount};
+
+use crate::fvm::state::fevm::{ContractCaller, MockProvider, NoRevert};
+use crate::fvm::state::{FvmExecState, GasParams};
+
+ethers::contract::abigen!(
+    GasGovernanceContract,
+    r#"[
+        function gasParams() external view returns (uint64 activationHeight, uint64 blockGasLimit, uint256 minBaseFee, uint64 baseFeeMaxChangeDenominator)
+    ]"#
+);
+
+/// The contract deciding the gas parameters of the subnet.
+///
+/// It is expected to return the latest parameters set by the owner along with the height they
+/// activate at, or a zero activation height if the parameters were never set.
+#[derive(Clone)]
+pub struct GasGovernance<DB> {
+    caller: ContractCaller<DB, GasGovernanceContract<MockProvider>, NoRevert>,
+}
+
+impl<DB> GasGovernance<DB>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    pub fn new(contract: &Address) -> anyhow::Result<Self> {
+        Ok(Self {
+            caller: ContractCaller::new(contract_address(contract)?, GasGovernanceContract::new),
+        })
+    }
+
+    /// The gas parameters in effect at the current height of the state, if any were set.
+    pub fn gas_params(&self, state: &mut FvmExecState<DB>) -> anyhow::Result<Option<GasParams>> {
+        match self.caller.try_call(state, |c| c.gas_params())? {
+            Ok((0, _, _, _)) => Ok(None),
+            Ok((activation_height, block_gas_limit, min_base_fee, denominator)) => {
+                if activation_height > state.block_height() as u64 {
+                    return Ok(None);
+                }
+                Ok(Some(GasParams {
+                    block_gas_limit,
+                    min_base_fee: from_eth::to_fvm_tokens(&min_base_fee),
+                    base_fee_max_change_denominator: denominator,
+                }))
+            }
+            Err(e) => {
+                // A broken governance contract shouldn't halt the chain; the parameters
+                // already in effect stay until it's fixed.
+                tracing::warn!(
+                    error = ?e.error,
+                    exit_code = e.exit_code.value(),
+                    "failed to query the gas governance contract"
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// The Ethereum address of the governance contract, given as an `f410` or `f0` address.
+pub fn contract_address(addr: &Address) -> anyhow::Result<EthAddress> {
+    from_fvm::to_eth_address(addr)?
+        .map(EthAddress::from)
+        .ok_or_else(|| anyhow!("not a contract address: {addr}"))
+}
+
+/// The base fee of the next block given the gas used in the current one, following EIP-1559:
+/// it goes up when the block used more than half of the block gas limit and down when it used
+/// less, by at most `1 / base_fee_max_change_denominator` of itself, but never below the minimum.
+pub fn next_base_fee(base_fee: &TokenAmount, gas_used: u64, params: &GasParams) -> TokenAmount {
+    if params.block_gas_limit == 0 || params.base_fee_max_change_denominator == 0 {
+        return base_fee.clone();
+    }
+    let target = BigInt::from((params.block_gas_limit / 2).max(1));
+    let delta = BigInt::from(gas_used) - &target;
+    let change = base_fee.atto() * delta / target / params.base_fee_max_change_denominator;
+    let mut next = TokenAmount::from_atto(base_fee.atto() + change);
+    // The base fee can't go up from zero otherwise.
+    if gas_used > params.block_gas_limit / 2 && next == *base_fee {
+        next += TokenAmount::from_atto(1);
+    }
+    next.max(params.min_base_fee.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::econ::TokenAmount;
+
+    use super::next_base_fee;
+    use crate::fvm::state::GasParams;
+
+    #[test]
+    fn base_fee_adjustment() {
+        let params = GasParams {
+            block_gas_limit: 1000,
+            min_base_fee: TokenAmount::from_atto(100),
+            base_fee_max_change_denominator: 8,
+        };
+        let base_fee = TokenAmount::from_atto(800);
+        let next = |gas_used| next_base_fee(&base_fee, gas_used, &params).atto().clone();
+
+        assert_eq!(next(500), 800.into());
+        assert_eq!(next(1000), 900.into());
+        assert_eq!(next(0), 700.into());
+        assert_eq!(
+            next_base_fee(&TokenAmount::from_atto(100), 0, &params),
+            params.min_base_fee
+        );
+        assert_eq!(
+            next_base_fee(&TokenAmount::from_atto(0), 1000, &params),
+            params.min_base_fee
+        );
+
+        let unlimited = GasParams {
+            block_gas_limit: 0,
+            ..params
+        };
+        assert_eq!(next_base_fee(&base_fee, 1000, &unlimited), base_fee);
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::{BTreeSet, HashMap};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
 use ethers::abi::Tokenize;
 use ethers::core::types as et;
@@ -15,19 +15,28 @@ use fendermint_vm_actor_interface::diamond::{EthContract, EthContractMap};
 use fendermint_vm_actor_interface::eam::EthAddress;
 use fendermint_vm_actor_interface::ipc::IPC_CONTRACTS;
 use fendermint_vm_actor_interface::{
-    account, burntfunds, chainmetadata, cron, eam, init, ipc, reward, system, EMPTY_ARR,
+    account, burntfunds, chainmetadata, cron, eam, evm, init, ipc, reward, scheduler, system,
+    EMPTY_ARR,
 };
 use fendermint_vm_core::{chainid, Timestamp};
-use fendermint_vm_genesis::{ActorMeta, Genesis, Power, PowerScale, Validator};
+use fendermint_vm_genesis::{
+    ActorMeta, Deployments, ExecLimits, Genesis, Power, PowerScale, Validator,
+};
 use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{BytesSer, RawBytes};
+use fvm_shared::address::Address;
 use fvm_shared::chainid::ChainID;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
 use fvm_shared::version::NetworkVersion;
+use fvm_shared::BLOCK_GAS_LIMIT;
 use ipc_actors_abis::i_diamond::FacetCut;
+use libipld::Ipld;
 use num_traits::Zero;
 
 use crate::GenesisInterpreter;
 
+use super::gas;
 use super::state::FvmGenesisState;
 use super::FvmMessageInterpreter;
 
@@ -40,6 +49,7 @@ pub struct FvmGenesisOutput {
     pub power_scale: PowerScale,
     pub circ_supply: TokenAmount,
     pub validators: Vec<Validator<Power>>,
+    pub exec_limits: Option<ExecLimits>,
 }
 
 #[async_trait]
@@ -66,6 +76,7 @@ where
     /// * rewards (placeholder)
     /// * accounts
     /// * IPC
+    /// * actors and contracts from the genesis manifest
     ///
     /// TODO:
     /// * faucet?
@@ -107,8 +118,14 @@ where
             base_fee: genesis.base_fee,
             power_scale: genesis.power_scale,
             validators,
+            exec_limits: genesis.exec_limits.clone(),
         };
 
+        // Catch a bad governance contract here rather than failing every block.
+        if let Some(addr) = genesis.exec_limits.as_ref().and_then(|l| l.gas_governance) {
+            gas::contract_address(&addr).context("invalid gas governance contract")?;
+        }
+
         // STAGE 0: Declare the built-in EVM contracts we'll have to deploy.
 
         // Pre-defined IDs for top-level Ethereum contracts.
@@ -142,6 +159,9 @@ where
         // Only keep library dependencies, not contracts with constructors.
         eth_libs.retain(|(_, d)| !eth_contracts.contains_key(d.as_str()));
 
+        // Actors and contracts from the genesis manifest, deployed with dynamic IDs after the libraries.
+        let deployments = genesis.deployments.clone().unwrap_or_default();
+
         // STAGE 1: First we initialize native built-in actors.
 
         // System actor
@@ -164,6 +184,8 @@ where
             &genesis.accounts,
             &eth_builtin_ids,
             eth_libs.len() as u64,
+            deployments.actors.len() as u64,
+            deployments.contracts.len() as u64,
         )
         .context("failed to create init state")?;
 
@@ -247,6 +269,18 @@ where
             )
             .context("failed to create chainmetadata actor")?;
 
+        // Initialize the scheduler actor which keeps the messages to execute at future heights.
+        let scheduler_state = fendermint_actor_scheduler::State::new(&state.store())?;
+        state
+            .create_custom_actor(
+                fendermint_actor_scheduler::SCHEDULER_ACTOR_NAME,
+                scheduler::SCHEDULER_ACTOR_ID,
+                &scheduler_state,
+                TokenAmount::zero(),
+                None,
+            )
+            .context("failed to create scheduler actor")?;
+
         let eam_state = fendermint_actor_eam::State::new(
             state.store(),
             PermissionModeParams::from(genesis.eam_permission_mode),
@@ -372,10 +406,128 @@ where
             };
         }
 
+        // STAGE 4: Deploy the actors and contracts from the manifest.
+
+        deploy_manifest(&mut state, &mut next_id, deployments)
+            .context("failed to deploy the genesis manifest")?;
+
         Ok((state, out))
     }
 }
 
+/// Deploy the custom actors, then the EVM contracts, with the next free IDs, in the same
+/// order the `Init` actor reserved them, and finally make the seed calls.
+fn deploy_manifest<DB>(
+    state: &mut FvmGenesisState<DB>,
+    next_id: &mut u64,
+    deployments: Deployments,
+) -> anyhow::Result<()>
+where
+    DB: Blockstore + 'static + Send + Sync + Clone,
+{
+    deployments.check_contract_names()?;
+
+    for actor in deployments.actors {
+        let bytes = decode_hex(&actor.state)
+            .with_context(|| format!("invalid state of actor {}", actor.name))?;
+        let actor_state = fvm_ipld_encoding::from_slice::<Ipld>(&bytes)
+            .with_context(|| format!("state of actor {} is not DAG-CBOR", actor.name))?;
+
+        state
+            .create_custom_actor(&actor.name, *next_id, &actor_state, actor.balance, None)
+            .with_context(|| format!("failed to create actor {}", actor.name))?;
+
+        tracing::info!(actor_id = next_id, name = %actor.name, "deployed genesis actor");
+
+        *next_id += 1;
+    }
+
+    let mut contract_addrs = HashMap::new();
+    for contract in deployments.contracts {
+        let name = contract.name;
+        let mut initcode = decode_hex(&contract.bytecode)
+            .with_context(|| format!("invalid bytecode of {name}"))?;
+        initcode.extend(
+            decode_hex(&contract.constructor_args)
+                .with_context(|| format!("invalid constructor args of {name}"))?,
+        );
+
+        let eth_addr = state
+            .create_evm_actor(*next_id, initcode)
+            .with_context(|| format!("failed to create contract {name}"))?;
+
+        if !contract.balance.is_zero() {
+            let id = *next_id;
+            let exec_state = state
+                .exec_state()
+                .ok_or_else(|| anyhow!("execution engine not initialized"))?;
+            exec_state.state_tree_mut().mutate_actor(id, |actor| {
+                actor.balance = contract.balance.clone();
+                Ok(())
+            })?;
+        }
+
+        let id_addr = et::Address::from(EthAddress::from_id(*next_id).0);
+        let eth_addr = et::Address::from(eth_addr.0);
+
+        tracing::info!(
+            actor_id = next_id,
+            ?eth_addr,
+            ?id_addr,
+            %name,
+            "deployed genesis contract"
+        );
+
+        contract_addrs.insert(name, *next_id);
+        *next_id += 1;
+    }
+
+    for (i, call) in deployments.calls.into_iter().enumerate() {
+        let id = contract_addrs
+            .get(&call.contract)
+            .ok_or_else(|| anyhow!("call {i} is to unknown contract {}", call.contract))?;
+        let calldata =
+            decode_hex(&call.calldata).with_context(|| format!("invalid calldata of call {i}"))?;
+
+        let msg = Message {
+            version: Default::default(),
+            from: call.from.unwrap_or(system::SYSTEM_ACTOR_ADDR),
+            to: Address::new_id(*id),
+            sequence: 0, // Implicit execution doesn't check or modify this.
+            value: TokenAmount::zero(),
+            method_num: evm::Method::InvokeContract as u64,
+            params: RawBytes::serialize(BytesSer(&calldata))?,
+            gas_limit: BLOCK_GAS_LIMIT,
+            gas_fee_cap: TokenAmount::zero(),
+            gas_premium: TokenAmount::zero(),
+        };
+
+        let exec_state = state
+            .exec_state()
+            .ok_or_else(|| anyhow!("execution engine not initialized"))?;
+        let (ret, _) = exec_state
+            .execute_implicit(msg)
+            .with_context(|| format!("failed to execute call {i}"))?;
+
+        if !ret.msg_receipt.exit_code.is_success() {
+            bail!(
+                "call {i} to {} failed: code = {}; info = {:?}",
+                call.contract,
+                ret.msg_receipt.exit_code,
+                ret.failure_info
+            );
+        }
+
+        tracing::info!(contract = %call.contract, "executed genesis call");
+    }
+
+    Ok(())
+}
+
+fn decode_hex(s: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(hex::decode(s.trim().trim_start_matches("0x"))?)
+}
+
 fn contract_src(name: &str) -> PathBuf {
     PathBuf::from(format!("{name}.sol"))
 }
@@ -521,9 +673,17 @@ where
 
 /// Sum of balances in the genesis accounts.
 fn circ_supply(g: &Genesis) -> TokenAmount {
+    let deployed = g.deployments.iter().flat_map(|d| {
+        d.actors
+            .iter()
+            .map(|a| &a.balance)
+            .chain(d.contracts.iter().map(|c| &c.balance))
+    });
     g.accounts
         .iter()
-        .fold(TokenAmount::zero(), |s, a| s + a.balance.clone())
+        .map(|a| &a.balance)
+        .chain(deployed)
+        .fold(TokenAmount::zero(), |s, b| s + b.clone())
 }
 
 #[cfg(test)]
@@ -531,8 +691,13 @@ mod tests {
     use std::{str::FromStr, sync::Arc};
 
     use cid::Cid;
-    use fendermint_vm_genesis::{ipc::IpcParams, Genesis};
+    use fendermint_vm_actor_interface::init;
+    use fendermint_vm_genesis::{
+        ipc::IpcParams, Deployments, Genesis, GenesisActor, GenesisCall, GenesisContract,
+    };
     use fvm::engine::MultiEngine;
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::{address::Address, econ::TokenAmount};
     use quickcheck::Arbitrary;
     use tendermint_rpc::{MockClient, MockRequestMethodMatcher};
 
@@ -614,6 +779,83 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn load_genesis_deployments() {
+        let mut genesis = make_genesis();
+        genesis.deployments = Some(Deployments {
+            actors: vec![GenesisActor {
+                name: "chainmetadata".to_string(),
+                // An empty DAG-CBOR list; the state isn't checked until the actor is called.
+                state: "80".to_string(),
+                balance: TokenAmount::from_atto(5),
+            }],
+            contracts: vec![GenesisContract {
+                name: "Const".to_string(),
+                bytecode: CONST_CONTRACT_HEX.to_string(),
+                constructor_args: String::new(),
+                balance: TokenAmount::from_atto(7),
+            }],
+            calls: vec![GenesisCall {
+                contract: "Const".to_string(),
+                from: None,
+                calldata: "0x00000000".to_string(),
+            }],
+        });
+
+        let mut state = init_genesis(genesis)
+            .await
+            .expect("failed to create actors");
+        let exec_state = state.exec_state().expect("should be in exec stage");
+        let state_tree = exec_state.state_tree();
+
+        // The manifest got the last IDs the init actor reserved, in order.
+        let init_actor = state_tree
+            .get_actor(init::INIT_ACTOR_ID)
+            .unwrap()
+            .expect("init actor exists");
+        let init_state: init::State = state_tree
+            .store()
+            .get_cbor(&init_actor.state)
+            .unwrap()
+            .expect("init state exists");
+        let actor_id = init_state.next_id - 2;
+        let contract_id = init_state.next_id - 1;
+
+        let actor = state_tree.get_actor(actor_id).unwrap().expect("actor");
+        assert_eq!(actor.balance, TokenAmount::from_atto(5));
+
+        let contract = state_tree
+            .get_actor(contract_id)
+            .unwrap()
+            .expect("contract");
+        assert_eq!(contract.balance, TokenAmount::from_atto(7));
+        assert!(contract.delegated_address.is_some());
+
+        // The Ethereum address of the contract maps to its reserved ID.
+        let eth_addr = Address::from(init::builtin_actor_eth_addr(contract_id));
+        assert_eq!(state_tree.lookup_id(&eth_addr).unwrap(), Some(contract_id));
+    }
+
+    #[tokio::test]
+    async fn load_genesis_duplicate_contracts() {
+        let contract = GenesisContract {
+            name: "Const".to_string(),
+            bytecode: CONST_CONTRACT_HEX.to_string(),
+            constructor_args: String::new(),
+            balance: TokenAmount::default(),
+        };
+        let mut genesis = make_genesis();
+        genesis.deployments = Some(Deployments {
+            contracts: vec![contract.clone(), contract],
+            ..Default::default()
+        });
+
+        let err = init_genesis(genesis)
+            .await
+            .expect_err("duplicate names are rejected");
+        assert!(format!("{err:#}").contains("duplicate contract name: Const"));
+    }
+
     // This is a sort of canary test, if it fails means something changed in the way we do genesis,
     // which is probably fine, but it's better to know about it, and if anybody doesn't get the same
     // then we might have some non-determinism.
@@ -649,6 +891,25 @@ mod tests {
         assert_eq!(state_root_hash, expected_root_hash);
     }
 
+    /// Creation bytecode of a contract which returns the same 128 bytes from any call.
+    const CONST_CONTRACT_HEX: &str =
+        "601b600c600039601b6000f36003600052629896806020526064604052600860605260806000f3";
+
+    async fn init_genesis(genesis: Genesis) -> anyhow::Result<FvmGenesisState<MemoryBlockstore>> {
+        let bundle = read_bundle();
+        let custom_actors_bundle = read_custom_actors_bundle();
+        let interpreter = make_interpreter();
+        let multi_engine = Arc::new(MultiEngine::default());
+        let store = MemoryBlockstore::new();
+
+        let state = FvmGenesisState::new(store, multi_engine, &bundle, &custom_actors_bundle)
+            .await
+            .expect("failed to create state");
+
+        let (state, _) = interpreter.init(state, genesis).await?;
+        Ok(state)
+    }
+
     fn make_genesis() -> Genesis {
         let mut g = quickcheck::Gen::new(5);
         let mut genesis = Genesis::arbitrary(&mut g);
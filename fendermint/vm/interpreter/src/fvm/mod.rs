@@ -4,13 +4,17 @@ use std::path::PathBuf;
 
 mod broadcast;
 mod check;
-mod checkpoint;
+pub(crate) mod checkpoint;
 mod exec;
 mod externs;
+mod gas;
 mod genesis;
+mod policy;
 mod query;
+mod signer;
 pub mod state;
 pub mod store;
+mod trace;
 pub mod upgrades;
 
 #[cfg(any(test, feature = "bundle"))]
@@ -18,15 +22,18 @@ pub mod bundle;
 pub(crate) mod topdown;
 
 pub use check::FvmCheckRet;
-pub use checkpoint::PowerUpdates;
+pub use checkpoint::{simulate_checkpoint, CheckpointSimulation, PowerUpdates};
 pub use exec::FvmApplyRet;
 use fendermint_crypto::{PublicKey, SecretKey};
 use fendermint_eth_hardhat::Hardhat;
 pub use fendermint_vm_message::query::FvmQuery;
 use fvm_ipld_blockstore::Blockstore;
 pub use genesis::FvmGenesisOutput;
+pub use policy::CheckPolicy;
 pub use query::FvmQueryRet;
+pub use signer::{CheckpointSigner, RemoteSigner};
 use tendermint_rpc::Client;
+pub use trace::{actor_usage, call_trace};
 
 pub use self::broadcast::Broadcaster;
 use self::{state::ipc::GatewayCaller, upgrades::UpgradeScheduler};
@@ -35,9 +42,9 @@ pub type FvmMessage = fvm_shared::message::Message;
 
 #[derive(Clone)]
 pub struct ValidatorContext<C> {
-    /// The secret key the validator uses to produce blocks.
-    secret_key: SecretKey,
-    /// The public key identifying the validator (corresponds to the secret key.)
+    /// Signs the bottom-up checkpoints with the validator key.
+    signer: CheckpointSigner,
+    /// The public key identifying the validator (corresponds to the signer key.)
     public_key: PublicKey,
     /// Used to broadcast transactions. It might use a different secret key for
     /// signing transactions than the validator's block producing key.
@@ -49,11 +56,19 @@ impl<C> ValidatorContext<C> {
         // Derive the public keys so it's available to check whether this node is a validator at any point in time.
         let public_key = secret_key.public_key();
         Self {
-            secret_key,
+            signer: CheckpointSigner::Local(secret_key),
             public_key,
             broadcaster,
         }
     }
+
+    /// Sign checkpoints with the given signer, rather than the secret key used to broadcast
+    /// transactions, e.g. when the validator key is held by a remote service.
+    pub fn with_checkpoint_signer(mut self, signer: CheckpointSigner) -> Self {
+        self.public_key = signer.public_key();
+        self.signer = signer;
+        self
+    }
 }
 
 /// Interpreter working on already verified unsigned messages.
@@ -79,6 +94,10 @@ where
     gateway: GatewayCaller<DB>,
     /// Upgrade scheduler stores all the upgrades to be executed at given heights.
     upgrade_scheduler: UpgradeScheduler<DB>,
+    /// Directory to export checkpoints to before they are pruned from the ledger.
+    checkpoint_archive_dir: Option<PathBuf>,
+    /// Rules for admitting transactions to the mempool.
+    check_policy: CheckPolicy,
 }
 
 impl<DB, C> FvmMessageInterpreter<DB, C>
@@ -103,8 +122,22 @@ where
             exec_in_check,
             gateway: GatewayCaller::default(),
             upgrade_scheduler,
+            checkpoint_archive_dir: None,
+            check_policy: CheckPolicy::default(),
         }
     }
+
+    /// Export the checkpoints pruned from the ledger to a directory.
+    pub fn with_checkpoint_archive_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.checkpoint_archive_dir = dir;
+        self
+    }
+
+    /// Admit transactions to the mempool according to an operator configured policy.
+    pub fn with_check_policy(mut self, policy: CheckPolicy) -> Self {
+        self.check_policy = policy;
+        self
+    }
 }
 
 impl<DB, C> FvmMessageInterpreter<DB, C>
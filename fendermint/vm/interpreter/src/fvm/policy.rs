@@ -0,0 +1,177 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fendermint_vm_actor_interface::eam::EAM_ACTOR_ADDR;
+use fvm::state_tree::StateTree;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::{address::Address, econ::TokenAmount, error::ExitCode};
+
+use super::FvmMessage;
+
+/// Operator configured rules deciding which transactions the node admits to its mempool,
+/// on top of the sender, nonce and balance checks.
+///
+/// The rules only apply to the local mempool: they don't make a block invalid, so blocks
+/// proposed by other validators can still include transactions breaking them.
+#[derive(Clone, Debug, Default)]
+pub struct CheckPolicy {
+    /// Minimum gas premium of the transactions.
+    pub min_gas_premium: TokenAmount,
+    /// Maximum size of the message parameters in bytes; unlimited if `None`.
+    pub max_params_size: Option<usize>,
+    /// Senders whose transactions are rejected.
+    pub banned_senders: Vec<Address>,
+    /// Senders allowed to deploy contracts through the EAM; anyone can if empty.
+    pub contract_creators: Vec<Address>,
+}
+
+impl CheckPolicy {
+    /// Check a message against the policy, returning the exit code and the reason
+    /// to reject it with, if it breaks any of the rules.
+    pub fn check<DB: Blockstore>(
+        &self,
+        state_tree: &StateTree<DB>,
+        msg: &FvmMessage,
+    ) -> anyhow::Result<Option<(ExitCode, String)>> {
+        if msg.gas_premium < self.min_gas_premium {
+            return Ok(Some((
+                ExitCode::USR_ILLEGAL_ARGUMENT,
+                format!(
+                    "gas premium {} less than the minimum {}",
+                    msg.gas_premium.atto(),
+                    self.min_gas_premium.atto()
+                ),
+            )));
+        }
+
+        if let Some(max) = self.max_params_size {
+            if msg.params.len() > max {
+                return Ok(Some((
+                    ExitCode::USR_ILLEGAL_ARGUMENT,
+                    format!(
+                        "params of {} bytes over the maximum of {max}",
+                        msg.params.len()
+                    ),
+                )));
+            }
+        }
+
+        if self.is_listed(state_tree, &self.banned_senders, &msg.from)? {
+            return Ok(Some((
+                ExitCode::USR_FORBIDDEN,
+                format!("sender {} is banned", msg.from),
+            )));
+        }
+
+        if !self.contract_creators.is_empty()
+            && msg.to == EAM_ACTOR_ADDR
+            && !self.is_listed(state_tree, &self.contract_creators, &msg.from)?
+        {
+            return Ok(Some((
+                ExitCode::USR_FORBIDDEN,
+                format!("sender {} is not allowed to deploy contracts", msg.from),
+            )));
+        }
+
+        Ok(None)
+    }
+
+    /// Check whether the sender is on a list, under any of its addresses.
+    fn is_listed<DB: Blockstore>(
+        &self,
+        state_tree: &StateTree<DB>,
+        list: &[Address],
+        sender: &Address,
+    ) -> anyhow::Result<bool> {
+        if list.is_empty() {
+            return Ok(false);
+        }
+        if list.contains(sender) {
+            return Ok(true);
+        }
+        let Some(sender_id) = state_tree.lookup_id(sender)? else {
+            return Ok(false);
+        };
+        for addr in list {
+            if state_tree.lookup_id(addr)? == Some(sender_id) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_vm_actor_interface::eam::EAM_ACTOR_ADDR;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::{address::Address, econ::TokenAmount, error::ExitCode, message::Message};
+
+    use crate::fvm::state::empty_state_tree;
+    use crate::fvm::store::memory::MemoryBlockstore;
+
+    use super::CheckPolicy;
+
+    fn message(from: u64, to: Address) -> Message {
+        Message {
+            version: Default::default(),
+            from: Address::new_id(from),
+            to,
+            sequence: 0,
+            value: TokenAmount::from_atto(0),
+            method_num: 2,
+            params: RawBytes::new(vec![0; 10]),
+            gas_limit: 10_000_000,
+            gas_fee_cap: TokenAmount::from_atto(100),
+            gas_premium: TokenAmount::from_atto(10),
+        }
+    }
+
+    #[test]
+    fn check_policy() {
+        let state_tree = empty_state_tree(MemoryBlockstore::new()).unwrap();
+        let code = |policy: &CheckPolicy, msg: &Message| {
+            policy
+                .check(&state_tree, msg)
+                .unwrap()
+                .map(|(code, _)| code)
+        };
+
+        let other = Address::new_id(200);
+        assert_eq!(code(&CheckPolicy::default(), &message(100, other)), None);
+
+        let policy = CheckPolicy {
+            min_gas_premium: TokenAmount::from_atto(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            code(&policy, &message(100, other)),
+            Some(ExitCode::USR_ILLEGAL_ARGUMENT)
+        );
+
+        let policy = CheckPolicy {
+            max_params_size: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(
+            code(&policy, &message(100, other)),
+            Some(ExitCode::USR_ILLEGAL_ARGUMENT)
+        );
+
+        let policy = CheckPolicy {
+            banned_senders: vec![Address::new_id(100)],
+            contract_creators: vec![Address::new_id(101)],
+            ..Default::default()
+        };
+        assert_eq!(
+            code(&policy, &message(100, other)),
+            Some(ExitCode::USR_FORBIDDEN)
+        );
+        assert_eq!(code(&policy, &message(102, other)), None);
+        assert_eq!(
+            code(&policy, &message(102, EAM_ACTOR_ADDR)),
+            Some(ExitCode::USR_FORBIDDEN)
+        );
+        assert_eq!(code(&policy, &message(101, EAM_ACTOR_ADDR)), None);
+    }
+}
@@ -2,7 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use async_trait::async_trait;
 use cid::Cid;
-use fendermint_vm_message::query::{ActorState, FvmQuery, GasEstimate, StateParams};
+use fendermint_vm_message::query::{
+    ActorProof, ActorState, CallTrace, FvmQuery, GasEstimate, StateParams,
+};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared::{
@@ -12,7 +14,7 @@ use num_traits::Zero;
 
 use crate::QueryInterpreter;
 
-use super::{state::FvmQueryState, FvmApplyRet, FvmMessageInterpreter};
+use super::{call_trace, state::FvmQueryState, FvmApplyRet, FvmMessageInterpreter};
 
 /// Internal return type for queries. It will never be serialized
 /// and sent over the wire as it is, only its internal parts are
@@ -31,6 +33,10 @@ pub enum FvmQueryRet {
     StateParams(StateParams),
     /// Builtin actors known by the system.
     BuiltinActors(Vec<(String, Cid)>),
+    /// The calls made by a read-only message application, if it got as far as calling the recipient.
+    TraceCall(Option<CallTrace>),
+    /// The state of an actor and its storage, with the blocks proving them.
+    ActorProof(Box<ActorProof>),
 }
 
 #[async_trait]
@@ -121,7 +127,13 @@ where
                 );
                 // Populate gas message parameters.
 
-                match self.estimate_gassed_msg(state, &mut msg).await? {
+                // A limit set by the caller caps the gas the estimation can use.
+                let max_gas = match msg.gas_limit {
+                    0 => BLOCK_GAS_LIMIT,
+                    limit => limit.min(BLOCK_GAS_LIMIT),
+                };
+
+                match self.estimate_gassed_msg(state, &mut msg, max_gas).await? {
                     (state, Some(est)) => {
                         // return immediately if something is returned,
                         // it means that the message failed to execute so there's
@@ -130,7 +142,7 @@ where
                     }
                     (state, None) => {
                         // perform a gas search for an accurate value
-                        let (state, mut est) = self.gas_search(state, &msg).await?;
+                        let (state, mut est) = self.gas_search(state, &msg, max_gas).await?;
                         // we need an additional overestimation for the case where
                         // the exact value is returned as part of the gas search
                         // (for some reason with subsequent calls sometimes this is the case).
@@ -148,6 +160,11 @@ where
                     circ_supply: state_params.circ_supply.clone(),
                     chain_id: state_params.chain_id,
                     network_version: state_params.network_version,
+                    block_gas_limit: state_params
+                        .gas_params
+                        .as_ref()
+                        .map(|p| p.block_gas_limit)
+                        .unwrap_or_default(),
                 };
                 Ok((state, FvmQueryRet::StateParams(state_params)))
             }
@@ -155,6 +172,36 @@ where
                 let (state, ret) = state.builtin_actors().await?;
                 Ok((state, FvmQueryRet::BuiltinActors(ret)))
             }
+            FvmQuery::TraceCall(msg) => {
+                let to = msg.to;
+                let from = msg.from;
+                let method_num = msg.method_num;
+
+                let (state, (apply_ret, _)) = state.trace_call(*msg).await?;
+
+                tracing::info!(
+                    height = state.block_height(),
+                    to = to.to_string(),
+                    from = from.to_string(),
+                    method_num,
+                    exit_code = apply_ret.msg_receipt.exit_code.value(),
+                    "query trace call"
+                );
+
+                let out = FvmQueryRet::TraceCall(call_trace(&apply_ret.exec_trace));
+                Ok((state, out))
+            }
+            FvmQuery::ActorProof(address, keys) => {
+                let (state, proof) = state.actor_proof(&address, &keys).await?;
+                tracing::info!(
+                    height = state.block_height(),
+                    addr = address.to_string(),
+                    found = proof.actor.is_some(),
+                    keys = keys.len(),
+                    "query actor proof"
+                );
+                Ok((state, FvmQueryRet::ActorProof(Box::new(proof))))
+            }
         }
     }
 }
@@ -167,9 +214,10 @@ where
         &self,
         state: FvmQueryState<DB>,
         msg: &mut Message,
+        max_gas: u64,
     ) -> anyhow::Result<(FvmQueryState<DB>, Option<GasEstimate>)> {
-        // Setting BlockGasLimit as initial limit for gas estimation
-        msg.gas_limit = BLOCK_GAS_LIMIT;
+        // Setting the maximum, at most the BlockGasLimit, as initial limit for gas estimation
+        msg.gas_limit = max_gas;
 
         // With unlimited gas we are probably better off setting the prices to zero.
         let gas_premium = msg.gas_premium.clone();
@@ -235,6 +283,7 @@ where
         &self,
         mut state: FvmQueryState<DB>,
         msg: &Message,
+        max_gas: u64,
     ) -> anyhow::Result<(FvmQueryState<DB>, GasEstimate)> {
         let mut curr_limit = msg.gas_limit;
 
@@ -250,12 +299,12 @@ where
             }
 
             curr_limit = (curr_limit as f64 * self.gas_search_step) as u64;
-            if curr_limit > BLOCK_GAS_LIMIT {
+            if curr_limit > max_gas {
                 let est = GasEstimate {
                     exit_code: ExitCode::OK,
                     info: "".to_string(),
                     return_data: RawBytes::default(),
-                    gas_limit: BLOCK_GAS_LIMIT,
+                    gas_limit: max_gas,
                 };
                 return Ok((state, est));
             }
@@ -0,0 +1,156 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use fendermint_crypto::{recover, Message, PublicKey, RecoveryId, SecretKey, Signature};
+use fendermint_vm_message::signed::sign_secp256k1;
+use serde::{Deserialize, Serialize};
+
+/// The length of a Secp256k1 signature followed by the recovery ID.
+const SIGNATURE_LEN: usize = 65;
+
+/// Signs the bottom-up checkpoints on behalf of a validator.
+#[derive(Clone)]
+pub enum CheckpointSigner {
+    /// With the validator key held by the node.
+    Local(SecretKey),
+    /// With a remote service holding the validator key.
+    Remote(RemoteSigner),
+}
+
+impl CheckpointSigner {
+    /// The public key of the validator the checkpoints are signed for.
+    pub fn public_key(&self) -> PublicKey {
+        match self {
+            Self::Local(sk) => sk.public_key(),
+            Self::Remote(signer) => signer.public_key,
+        }
+    }
+
+    /// Sign the hash of a checkpoint, returning the signature followed by the recovery ID.
+    pub async fn sign(&self, hash: &[u8; 32]) -> anyhow::Result<[u8; SIGNATURE_LEN]> {
+        match self {
+            Self::Local(sk) => {
+                let signature = sign_secp256k1(sk, hash);
+                signature
+                    .bytes
+                    .try_into()
+                    .map_err(|_| anyhow!("unexpected signature length"))
+            }
+            Self::Remote(signer) => signer.sign(hash).await,
+        }
+    }
+}
+
+/// A signing service reached over HTTP, which is sent the hash to sign with a `POST` request
+/// like `{"public_key": "<hex>", "hash": "<hex>"}`, authenticated by a bearer token if there is
+/// one, and has to respond with `{"signature": "<hex>"}`: the 64 bytes of the signature followed
+/// by the recovery ID.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    auth_token: Option<String>,
+    public_key: PublicKey,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    public_key: String,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+impl RemoteSigner {
+    pub fn new(
+        url: reqwest::Url,
+        auth_token: Option<String>,
+        public_key: PublicKey,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("failed to build remote signer client")?;
+        Ok(Self {
+            client,
+            url,
+            auth_token,
+            public_key,
+        })
+    }
+
+    async fn sign(&self, hash: &[u8; 32]) -> anyhow::Result<[u8; SIGNATURE_LEN]> {
+        let request = SignRequest {
+            public_key: hex::encode(self.public_key.serialize_compressed()),
+            hash: hex::encode(hash),
+        };
+        let mut builder = self.client.post(self.url.clone()).json(&request);
+        if let Some(token) = &self.auth_token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder
+            .send()
+            .await
+            .context("failed to reach remote signer")?
+            .error_for_status()
+            .context("remote signer rejected the request")?
+            .json::<SignResponse>()
+            .await
+            .context("failed to parse remote signer response")?;
+
+        let signature = hex::decode(response.signature.trim_start_matches("0x"))
+            .context("remote signature is not hex encoded")?;
+        let signature: [u8; SIGNATURE_LEN] = signature
+            .try_into()
+            .map_err(|_| anyhow!("remote signature should be {SIGNATURE_LEN} bytes"))?;
+
+        // A signature by any other key would only be rejected by the gateway, after paying for it.
+        if recover_public_key(hash, &signature)? != self.public_key {
+            bail!("remote signer signed with a different key");
+        }
+
+        Ok(signature)
+    }
+}
+
+fn recover_public_key(
+    hash: &[u8; 32],
+    signature: &[u8; SIGNATURE_LEN],
+) -> anyhow::Result<PublicKey> {
+    let rec_id = RecoveryId::parse(signature[64]).context("invalid recovery ID")?;
+    let sig = Signature::parse_standard_slice(&signature[..64]).context("invalid signature")?;
+    recover(&Message::parse(hash), &sig, &rec_id).context("cannot recover public key")
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_crypto::SecretKey;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{recover_public_key, CheckpointSigner};
+
+    #[tokio::test]
+    async fn test_local_signature_recovers_public_key() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let sk = SecretKey::random(&mut rng);
+        let signer = CheckpointSigner::Local(sk.clone());
+
+        let hash = [7u8; 32];
+        let signature = signer.sign(&hash).await.unwrap();
+        assert_eq!(
+            recover_public_key(&hash, &signature).unwrap(),
+            sk.public_key()
+        );
+        assert_ne!(
+            recover_public_key(&[8u8; 32], &signature).unwrap(),
+            sk.public_key()
+        );
+    }
+}
@@ -1,11 +1,11 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::Ok;
 use cid::Cid;
-use fendermint_vm_genesis::PowerScale;
+use fendermint_vm_genesis::{ExecLimits, PowerScale};
 use fvm::{
     call_manager::DefaultCallManager,
     engine::MultiEngine,
@@ -20,12 +20,14 @@ use fvm_shared::{
     address::Address, chainid::ChainID, clock::ChainEpoch, econ::TokenAmount, error::ExitCode,
     message::Message, receipt::Receipt, version::NetworkVersion, ActorID,
 };
+use ipc_api::cross::IpcEnvelope;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::fvm::externs::FendermintExterns;
+use crate::fvm::{actor_usage, externs::FendermintExterns};
 use fendermint_vm_core::{chainid::HasChainID, Timestamp};
 use fendermint_vm_encoding::IsHumanReadable;
+use fendermint_vm_message::query::ActorStats;
 
 pub type BlockHash = [u8; 32];
 
@@ -62,6 +64,72 @@ pub struct FvmStateParams {
     /// The application protocol version.
     #[serde(default)]
     pub app_version: u64,
+    /// Resource limits applied during execution, if they differ from the FVM defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_limits: Option<ExecLimits>,
+    /// The upgrades executed on this chain, see [ExecutedUpgrades].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub executed_upgrades: ExecutedUpgrades,
+    /// Upgrades split across several blocks which haven't finished, see [UpgradesInProgress].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub upgrades_in_progress: UpgradesInProgress,
+    /// Top-down messages from committed parent finalities which didn't fit into the gas
+    /// budget of their block, waiting to be executed in the following blocks, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_topdown_msgs: Vec<IpcEnvelope>,
+    /// Bottom-up messages which didn't fit into the limits of their checkpoint,
+    /// waiting to be included in the following checkpoints, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_bottomup_msgs: Vec<IpcEnvelope>,
+    /// Gas parameters activated by governance, if any; until then blocks have no gas limit
+    /// and the base fee stays the same.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_params: Option<GasParams>,
+    /// Height of the last bottom-up checkpoint committed by the parent, as of the last
+    /// executed parent finality; checkpoints are only pruned below it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_checkpoint_height: Option<u64>,
+}
+
+/// Gas parameters adjustable by governance, see `GasGovernance`.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct GasParams {
+    /// Maximum gas the user messages of a block can use in total; unlimited if zero.
+    pub block_gas_limit: u64,
+    /// Minimum the base fee can be adjusted down to.
+    #[serde_as(as = "IsHumanReadable")]
+    pub min_base_fee: TokenAmount,
+    /// The base fee changes by at most this fraction of itself between blocks, as in EIP-1559,
+    /// depending on how far the gas used by a block is from half the block gas limit.
+    /// The base fee is not adjusted if zero, or if there is no block gas limit.
+    pub base_fee_max_change_denominator: u64,
+}
+
+/// The hex encoded digest of each upgrade which was executed successfully, with the height
+/// it was executed at, so that the same upgrade isn't executed twice on the same state.
+pub type ExecutedUpgrades = BTreeMap<String, u64>;
+
+/// The hex encoded digest of each upgrade whose migration is split across several blocks
+/// and hasn't finished yet, with the number of chunks already executed.
+pub type UpgradesInProgress = BTreeMap<String, u64>;
+
+/// Override the limits in the network configuration where they are set.
+///
+/// The engines are cached by their configuration, so different limits get different engines.
+fn apply_exec_limits(nc: &mut NetworkConfig, limits: &ExecLimits) {
+    if let Some(v) = limits.max_memory_bytes {
+        nc.max_memory_bytes = v;
+    }
+    if let Some(v) = limits.max_inst_memory_bytes {
+        nc.max_inst_memory_bytes = v;
+    }
+    if let Some(v) = limits.max_call_depth {
+        nc.max_call_depth = v;
+    }
+    if let Some(v) = limits.max_wasm_stack {
+        nc.max_wasm_stack = v;
+    }
 }
 
 /// Parts of the state which can be updated by message execution, apart from the actor state.
@@ -83,6 +151,18 @@ pub struct FvmUpdatableParams {
     /// Doesn't change at the moment but in theory it could,
     /// and it doesn't have a place within the FVM.
     pub power_scale: PowerScale,
+    /// Upgrades are recorded when they are executed.
+    pub executed_upgrades: ExecutedUpgrades,
+    /// Upgrades record their progress at each block until they are executed.
+    pub upgrades_in_progress: UpgradesInProgress,
+    /// Top-down messages are queued up when they exceed the gas budget of a block.
+    pub pending_topdown_msgs: Vec<IpcEnvelope>,
+    /// Bottom-up messages are queued up when they exceed the limits of a checkpoint.
+    pub pending_bottomup_msgs: Vec<IpcEnvelope>,
+    /// Gas parameters change when governance activates new ones.
+    pub gas_params: Option<GasParams>,
+    /// The checkpoint height committed by the parent changes with the parent finality.
+    pub parent_checkpoint_height: Option<u64>,
 }
 
 pub type MachineBlockstore<DB> = <DefaultMachine<DB, FendermintExterns<DB>> as Machine>::Blockstore;
@@ -108,6 +188,19 @@ where
 
     /// Indicate whether the parameters have been updated.
     params_dirty: bool,
+
+    /// Gas the top-down messages can use in a block, if limited.
+    topdown_gas_budget: Option<u64>,
+    /// Gas used by top-down messages so far in the current block.
+    topdown_gas_used: u64,
+    /// Maximum number and total size of the bottom-up messages in a checkpoint, if limited.
+    bottomup_limits: (Option<u32>, Option<u64>),
+    /// Number of blocks of checkpoint history to keep, if limited.
+    checkpoint_history: Option<u64>,
+    /// Contract setting the gas parameters, if they are governed.
+    gas_governance: Option<Address>,
+    /// Gas used by the user messages so far in the current block.
+    block_gas_used: u64,
 }
 
 impl<DB> FvmExecState<DB>
@@ -123,16 +216,33 @@ where
         multi_engine: &MultiEngine,
         block_height: ChainEpoch,
         params: FvmStateParams,
+    ) -> anyhow::Result<Self> {
+        Self::new_with_tracing(blockstore, multi_engine, block_height, params, false)
+    }
+
+    /// Create a new FVM execution environment, optionally recording the execution trace
+    /// of every message in its [ApplyRet], which makes the execution slower.
+    pub fn new_with_tracing(
+        blockstore: DB,
+        multi_engine: &MultiEngine,
+        block_height: ChainEpoch,
+        params: FvmStateParams,
+        tracing: bool,
     ) -> anyhow::Result<Self> {
         let mut nc = NetworkConfig::new(params.network_version);
         nc.chain_id = ChainID::from(params.chain_id);
 
+        if let Some(ref limits) = params.exec_limits {
+            apply_exec_limits(&mut nc, limits);
+        }
+
         // TODO: Configure:
         // * circ_supply; by default it's for Filecoin
         // * base_fee; by default it's zero
         let mut mc = nc.for_epoch(block_height, params.timestamp.0, params.state_root);
         mc.set_base_fee(params.base_fee.clone());
         mc.set_circulating_supply(params.circ_supply.clone());
+        mc.tracing = tracing;
 
         // Creating a new machine every time is prohibitively slow.
         // let ec = EngineConfig::from(&nc);
@@ -151,8 +261,30 @@ where
                 base_fee: params.base_fee,
                 circ_supply: params.circ_supply,
                 power_scale: params.power_scale,
+                executed_upgrades: params.executed_upgrades,
+                upgrades_in_progress: params.upgrades_in_progress,
+                pending_topdown_msgs: params.pending_topdown_msgs,
+                pending_bottomup_msgs: params.pending_bottomup_msgs,
+                gas_params: params.gas_params,
+                parent_checkpoint_height: params.parent_checkpoint_height,
             },
             params_dirty: false,
+            topdown_gas_budget: params
+                .exec_limits
+                .as_ref()
+                .and_then(|l| l.topdown_gas_budget),
+            topdown_gas_used: 0,
+            bottomup_limits: params
+                .exec_limits
+                .as_ref()
+                .map(|l| (l.bottomup_max_msgs, l.bottomup_max_bytes))
+                .unwrap_or_default(),
+            checkpoint_history: params
+                .exec_limits
+                .as_ref()
+                .and_then(|l| l.checkpoint_history),
+            gas_governance: params.exec_limits.and_then(|l| l.gas_governance),
+            block_gas_used: 0,
         })
     }
 
@@ -240,6 +372,28 @@ where
         self.executor.context().network.chain_id
     }
 
+    /// The resources used by each actor called while executing a message, keyed by ID address,
+    /// or the address it was called at if the actor doesn't exist.
+    ///
+    /// It's empty unless the state was created with tracing.
+    pub fn actor_usage(&self, ret: &ApplyRet) -> anyhow::Result<BTreeMap<Address, ActorStats>> {
+        let usage = actor_usage(&ret.exec_trace, self.executor.context().network.price_list);
+        let mut resolved = BTreeMap::<Address, ActorStats>::new();
+        for (addr, stats) in usage {
+            let actor = match self.state_tree().lookup_id(&addr)? {
+                Some(id) => Address::new_id(id),
+                None => addr,
+            };
+            match resolved.get_mut(&actor) {
+                Some(s) => s.add(&stats),
+                None => {
+                    resolved.insert(actor, ActorStats { actor, ..stats });
+                }
+            }
+        }
+        Ok(resolved)
+    }
+
     /// Collect all the event emitters' delegated addresses, for those who have any.
     fn emitter_delegated_addresses(&self, apply_ret: &ApplyRet) -> anyhow::Result<ActorAddressMap> {
         let emitter_ids = apply_ret
@@ -285,6 +439,122 @@ where
         self.update_params(|p| f(&mut p.circ_supply))
     }
 
+    /// The height at which an upgrade with the given digest was executed, if it was.
+    pub fn upgrade_executed_at(&self, digest: &[u8; 32]) -> Option<u64> {
+        self.params
+            .executed_upgrades
+            .get(&hex::encode(digest))
+            .copied()
+    }
+
+    /// Record that an upgrade was executed at the current height.
+    pub fn record_executed_upgrade(&mut self, digest: &[u8; 32]) {
+        let height = self.block_height() as u64;
+        let digest = hex::encode(digest);
+        self.update_params(|p| {
+            p.upgrades_in_progress.remove(&digest);
+            p.executed_upgrades.insert(digest, height);
+        })
+    }
+
+    /// The upgrades which have started but not finished executing.
+    pub fn upgrades_in_progress(&self) -> &UpgradesInProgress {
+        &self.params.upgrades_in_progress
+    }
+
+    /// Record how many chunks of an upgrade have been executed so far.
+    pub fn record_upgrade_progress(&mut self, digest: &[u8; 32], chunks_done: u64) {
+        self.update_params(|p| {
+            p.upgrades_in_progress
+                .insert(hex::encode(digest), chunks_done);
+        })
+    }
+
+    /// The top-down messages waiting to be executed.
+    pub fn pending_topdown_msgs(&self) -> &[IpcEnvelope] {
+        &self.params.pending_topdown_msgs
+    }
+
+    /// Replace the top-down messages waiting to be executed.
+    pub fn set_pending_topdown_msgs(&mut self, msgs: Vec<IpcEnvelope>) {
+        self.update_params(|p| p.pending_topdown_msgs = msgs)
+    }
+
+    /// Gas the top-down messages can still use in the current block, if limited.
+    pub fn topdown_gas_available(&self) -> Option<u64> {
+        self.topdown_gas_budget
+            .map(|budget| budget.saturating_sub(self.topdown_gas_used))
+    }
+
+    /// Account for the gas used by top-down messages in the current block.
+    pub fn record_topdown_gas_used(&mut self, gas_used: u64) {
+        self.topdown_gas_used = self.topdown_gas_used.saturating_add(gas_used);
+    }
+
+    /// The contract setting the gas parameters, if they are governed.
+    pub fn gas_governance(&self) -> Option<Address> {
+        self.gas_governance
+    }
+
+    /// The gas parameters activated by governance, if any.
+    pub fn gas_params(&self) -> Option<&GasParams> {
+        self.params.gas_params.as_ref()
+    }
+
+    /// Activate new gas parameters; the base fee applies from the next block.
+    pub fn set_gas_params(&mut self, gas_params: GasParams) {
+        self.update_params(|p| p.gas_params = Some(gas_params))
+    }
+
+    /// Gas the user messages of the current block can still use, if limited.
+    pub fn block_gas_available(&self) -> Option<u64> {
+        self.params
+            .gas_params
+            .as_ref()
+            .filter(|p| p.block_gas_limit > 0)
+            .map(|p| p.block_gas_limit.saturating_sub(self.block_gas_used))
+    }
+
+    /// Gas used by the user messages so far in the current block.
+    pub fn block_gas_used(&self) -> u64 {
+        self.block_gas_used
+    }
+
+    /// Account for the gas used by a user message in the current block.
+    pub fn record_block_gas_used(&mut self, gas_used: u64) {
+        self.block_gas_used = self.block_gas_used.saturating_add(gas_used);
+    }
+
+    /// The bottom-up messages waiting to be included in a checkpoint.
+    pub fn pending_bottomup_msgs(&self) -> &[IpcEnvelope] {
+        &self.params.pending_bottomup_msgs
+    }
+
+    /// Replace the bottom-up messages waiting to be included in a checkpoint.
+    pub fn set_pending_bottomup_msgs(&mut self, msgs: Vec<IpcEnvelope>) {
+        self.update_params(|p| p.pending_bottomup_msgs = msgs)
+    }
+
+    /// Maximum number and total size of the bottom-up messages in a checkpoint, if limited.
+    pub fn bottomup_limits(&self) -> (Option<u32>, Option<u64>) {
+        self.bottomup_limits
+    }
+
+    /// Height of the last bottom-up checkpoint committed by the parent, if known.
+    pub fn parent_checkpoint_height(&self) -> Option<u64> {
+        self.params.parent_checkpoint_height
+    }
+
+    /// Record the height of the last bottom-up checkpoint committed by the parent.
+    pub fn set_parent_checkpoint_height(&mut self, height: u64) {
+        self.update_params(|p| p.parent_checkpoint_height = Some(height))
+    }
+
+    /// Number of blocks of checkpoint history to keep, if limited.
+    pub fn checkpoint_history(&self) -> Option<u64> {
+        self.checkpoint_history
+    }
+
     /// Update the parameters and mark them as dirty.
     fn update_params<F>(&mut self, f: F)
     where
@@ -311,7 +581,7 @@ where
 /// because such messages can be included by malicious validators or user queries. We could
 /// use ABCI++ to filter out messages from blocks, but that doesn't affect queries, so we
 /// might as well encode it as an error. To keep the types simpler, let's fabricate an `ApplyRet`.
-fn check_error(e: anyhow::Error) -> (ApplyRet, ActorAddressMap) {
+pub(crate) fn check_error(e: anyhow::Error) -> (ApplyRet, ActorAddressMap) {
     let zero = TokenAmount::from_atto(0);
     let ret = ApplyRet {
         msg_receipt: Receipt {
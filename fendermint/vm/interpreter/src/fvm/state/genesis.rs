@@ -67,7 +67,10 @@ where
     stage: Stage<DB>,
 }
 
-async fn parse_bundle<DB: Blockstore>(store: &DB, bundle: &[u8]) -> anyhow::Result<(u32, Cid)> {
+pub(crate) async fn parse_bundle<DB: Blockstore>(
+    store: &DB,
+    bundle: &[u8],
+) -> anyhow::Result<(u32, Cid)> {
     let bundle_roots = load_car_unchecked(&store, bundle).await?;
     let bundle_root = match bundle_roots.as_slice() {
         [root] => root,
@@ -154,6 +157,13 @@ where
                     chain_id,
                     power_scale,
                     app_version: 0,
+                    exec_limits: None,
+                    executed_upgrades: Default::default(),
+                    upgrades_in_progress: Default::default(),
+                    pending_topdown_msgs: Default::default(),
+                    pending_bottomup_msgs: Default::default(),
+                    gas_params: None,
+                    parent_checkpoint_height: None,
                 };
 
                 let exec_state =
@@ -5,19 +5,19 @@ use anyhow::{anyhow, Context};
 use ethers::types as et;
 
 use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::crypto::signature::{Signature, SignatureType};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::ActorID;
 
-use fendermint_crypto::{PublicKey, SecretKey};
+use fendermint_crypto::PublicKey;
 use fendermint_vm_actor_interface::ipc;
 use fendermint_vm_actor_interface::{
     eam::EthAddress,
     init::builtin_actor_eth_addr,
-    ipc::{AbiHash, ValidatorMerkleTree, GATEWAY_ACTOR_ID},
+    ipc::{ValidatorMerkleTree, GATEWAY_ACTOR_ID},
 };
 use fendermint_vm_genesis::{Collateral, Power, PowerScale, Validator, ValidatorKey};
 use fendermint_vm_message::conv::{from_eth, from_fvm};
-use fendermint_vm_message::signed::sign_secp256k1;
 use fendermint_vm_topdown::IPCParentFinality;
 
 use ipc_actors_abis::checkpointing_facet::CheckpointingFacet;
@@ -138,6 +138,52 @@ impl<DB: Blockstore + Clone> GatewayCaller<DB> {
         })
     }
 
+    /// The height of the oldest checkpoint kept in the gateway; the ones before it were pruned.
+    pub fn checkpoint_retention_height(&self, state: &mut FvmExecState<DB>) -> anyhow::Result<u64> {
+        let height = self
+            .getter
+            .call(state, |c| c.get_checkpoint_retention_height())?;
+        Ok(height.as_u64())
+    }
+
+    /// Remove the checkpoints, their messages and signatures below the new retention height.
+    pub fn prune_bottom_up_checkpoints(
+        &self,
+        state: &mut FvmExecState<DB>,
+        retention_height: u64,
+    ) -> anyhow::Result<()> {
+        self.checkpointing.call(state, |c| {
+            c.prune_bottom_up_checkpoints(ethers::types::U256::from(retention_height))
+        })
+    }
+
+    /// The heights of the checkpoints which have not reached a quorum.
+    pub fn incomplete_checkpoint_heights(
+        &self,
+        state: &mut FvmExecState<DB>,
+    ) -> anyhow::Result<Vec<u64>> {
+        let heights = self
+            .getter
+            .call(state, |c| c.get_incomplete_checkpoint_heights())?;
+        Ok(heights.into_iter().map(|h| h.as_u64()).collect())
+    }
+
+    /// The checkpoint at a height along with its quorum and the signatures collected for it.
+    pub fn checkpoint_signature_bundle(
+        &self,
+        state: &mut FvmExecState<DB>,
+        height: u64,
+    ) -> anyhow::Result<(
+        getter::BottomUpCheckpoint,
+        getter::QuorumInfo,
+        Vec<et::Address>,
+        Vec<et::Bytes>,
+    )> {
+        self.getter.call(state, |c| {
+            c.get_checkpoint_signature_bundle(ethers::types::U256::from(height))
+        })
+    }
+
     /// Retrieve checkpoints which have not reached a quorum.
     pub fn incomplete_checkpoints(
         &self,
@@ -181,16 +227,15 @@ impl<DB: Blockstore + Clone> GatewayCaller<DB> {
         checkpoint: checkpointing_facet::BottomUpCheckpoint,
         power_table: &[Validator<Power>],
         validator: &Validator<Power>,
-        secret_key: &SecretKey,
+        signature: &[u8; 65],
     ) -> anyhow::Result<et::Bytes> {
-        debug_assert_eq!(validator.public_key.0, secret_key.public_key());
-
         let height = checkpoint.block_height;
         let weight = et::U256::from(validator.power.0);
 
-        let hash = checkpoint.abi_hash();
-
-        let signature = sign_secp256k1(secret_key, &hash);
+        let signature = Signature {
+            sig_type: SignatureType::Secp256k1,
+            bytes: signature.to_vec(),
+        };
         let signature =
             from_fvm::to_eth_signature(&signature, false).context("invalid signature")?;
         let signature = et::Bytes::from(signature.to_vec());
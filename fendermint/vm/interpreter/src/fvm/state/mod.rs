@@ -12,7 +12,12 @@ pub mod snapshot;
 use std::sync::Arc;
 
 pub use check::FvmCheckState;
-pub use exec::{BlockHash, FvmExecState, FvmStateParams, FvmUpdatableParams};
+pub(crate) use exec::check_error;
+pub use exec::{
+    BlockHash, ExecutedUpgrades, FvmExecState, FvmStateParams, FvmUpdatableParams, GasParams,
+    MachineBlockstore, UpgradesInProgress,
+};
+pub(crate) use genesis::parse_bundle;
 pub use genesis::{empty_state_tree, FvmGenesisState};
 pub use query::FvmQueryState;
 
@@ -7,20 +7,22 @@ use std::{cell::RefCell, sync::Arc};
 use anyhow::{anyhow, Context};
 
 use cid::Cid;
+use fendermint_vm_actor_interface::evm;
 use fendermint_vm_actor_interface::system::{
     is_system_addr, State as SystemState, SYSTEM_ACTOR_ADDR,
 };
 use fendermint_vm_core::chainid::HasChainID;
-use fendermint_vm_message::query::ActorState;
+use fendermint_vm_message::query::{ActorProof, ActorState, StorageProof, MAX_ACTOR_PROOF_KEYS};
 use fvm::engine::MultiEngine;
 use fvm::executor::ApplyRet;
 use fvm::state_tree::StateTree;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::CborStore;
+use fvm_ipld_encoding::{CborStore, RawBytes};
 use fvm_shared::{address::Address, chainid::ChainID, clock::ChainEpoch, ActorID};
 use num_traits::Zero;
 
-use crate::fvm::{store::ReadOnlyBlockstore, FvmMessage};
+use crate::fvm::store::{recording::RecordingBlockstore, ReadOnlyBlockstore};
+use crate::fvm::FvmMessage;
 
 use super::{CheckStateRef, FvmExecState, FvmStateParams};
 
@@ -172,35 +174,114 @@ where
     /// unless it's called with `revert`.
     pub async fn call(
         self,
-        mut msg: FvmMessage,
+        msg: FvmMessage,
     ) -> anyhow::Result<(Self, (ApplyRet, HashMap<u64, Address>))> {
-        self.with_exec_state(|s| {
-            // If the sequence is zero, treat it as a signal to use whatever is in the state.
-            if msg.sequence.is_zero() {
-                let state_tree = s.state_tree_mut();
-                if let Some(id) = state_tree.lookup_id(&msg.from)? {
-                    state_tree.get_actor(id)?.map(|st| {
-                        msg.sequence = st.sequence;
-                        st
-                    });
-                }
-            }
+        self.with_exec_state(|s| execute_call(s, msg)).await
+    }
 
-            // If the gas_limit is zero, set it to the block gas limit so that call will not hit
-            // gas limit not set error. It is possible, in the future, to estimate the gas limit
-            // based on the account balance and base fee + premium for higher accuracy.
-            if msg.gas_limit == 0 {
-                msg.gas_limit = fvm_shared::BLOCK_GAS_LIMIT;
-            }
+    /// Execute a message like [Self::call], on a separate execution state which records
+    /// the trace of the execution in the [ApplyRet].
+    ///
+    /// The pending state doesn't record traces, so this always uses the committed one.
+    pub async fn trace_call(
+        self,
+        msg: FvmMessage,
+    ) -> anyhow::Result<(Self, (ApplyRet, HashMap<u64, Address>))> {
+        let mut exec_state = FvmExecState::new_with_tracing(
+            self.store.clone(),
+            self.multi_engine.as_ref(),
+            self.block_height,
+            self.state_params.clone(),
+            true,
+        )
+        .context("error creating execution state")?;
+
+        // The state is dropped afterwards, so there's nothing to revert.
+        let ret = execute_call(&mut exec_state, msg)?;
 
-            if is_system_addr(&msg.from) {
-                // Explicit execution requires `from` to be an account kind.
-                s.execute_implicit(msg)
-            } else {
-                s.execute_explicit(msg)
+        Ok((self, ret))
+    }
+
+    /// Get the state of an actor and the values of EVM storage slots, along with the blocks
+    /// read while looking them up, which prove them against the state root.
+    ///
+    /// Like [Self::trace_call], this always uses the committed state.
+    pub async fn actor_proof(
+        self,
+        addr: &Address,
+        keys: &[evm::uints::U256],
+    ) -> anyhow::Result<(Self, ActorProof)> {
+        if keys.len() > MAX_ACTOR_PROOF_KEYS {
+            return Err(anyhow!(
+                "at most {MAX_ACTOR_PROOF_KEYS} storage keys can be proven at once"
+            ));
+        }
+
+        let recorder = RecordingBlockstore::new(self.store.clone());
+
+        let state_tree = StateTree::new_from_root(recorder.clone(), &self.state_params.state_root)
+            .context("failed to load state tree")?;
+        let actor = get_actor_state(&state_tree, addr)?;
+        let account_proof = recorder.take();
+
+        let mut storage_proof = Vec::new();
+
+        if let Some((id, _)) = actor.as_ref().filter(|_| !keys.is_empty()) {
+            let mut exec_state = FvmExecState::new(
+                recorder.clone(),
+                self.multi_engine.as_ref(),
+                self.block_height,
+                self.state_params.clone(),
+            )
+            .context("error creating execution state")?;
+
+            for key in keys {
+                let params = RawBytes::serialize(evm::GetStorageAtParams { storage_key: *key })?;
+                let msg = FvmMessage {
+                    version: 0,
+                    from: SYSTEM_ACTOR_ADDR,
+                    to: Address::new_id(*id),
+                    sequence: 0,
+                    value: Default::default(),
+                    method_num: evm::Method::GetStorageAt as u64,
+                    params,
+                    gas_limit: fvm_shared::BLOCK_GAS_LIMIT,
+                    gas_fee_cap: Default::default(),
+                    gas_premium: Default::default(),
+                };
+                // Only record what the actor reads for this key; the state tree caches the actor.
+                recorder.take();
+                let (ret, _) = exec_state.execute_implicit(msg)?;
+
+                // Anything but an EVM actor fails to handle the method.
+                let value = if ret.msg_receipt.exit_code.is_success() {
+                    fvm_ipld_encoding::from_slice::<evm::GetStorageAtReturn>(
+                        ret.msg_receipt.return_data.bytes(),
+                    )
+                    .context("failed to decode storage value")?
+                    .storage
+                } else {
+                    evm::uints::U256::zero()
+                };
+
+                storage_proof.push(StorageProof {
+                    key: *key,
+                    value,
+                    proof: to_raw_blocks(recorder.take()),
+                });
             }
-        })
-        .await
+        }
+
+        let state_params = RawBytes::serialize(&self.state_params)?;
+
+        let proof = ActorProof {
+            state_params,
+            actor,
+            account_proof: to_raw_blocks(account_proof),
+            storage_proof,
+        };
+
+        Ok((self, proof))
     }
 
     pub fn state_params(&self) -> &FvmStateParams {
@@ -266,3 +347,203 @@ where
         Ok(None)
     }
 }
+
+fn to_raw_blocks(blocks: Vec<Vec<u8>>) -> Vec<RawBytes> {
+    blocks.into_iter().map(RawBytes::new).collect()
+}
+
+/// Execute a read-only message, filling in the sequence and the gas limit if they are missing.
+fn execute_call<DB>(
+    s: &mut FvmExecState<DB>,
+    mut msg: FvmMessage,
+) -> anyhow::Result<(ApplyRet, HashMap<u64, Address>)>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    // If the sequence is zero, treat it as a signal to use whatever is in the state.
+    if msg.sequence.is_zero() {
+        let state_tree = s.state_tree_mut();
+        if let Some(id) = state_tree.lookup_id(&msg.from)? {
+            state_tree.get_actor(id)?.map(|st| {
+                msg.sequence = st.sequence;
+                st
+            });
+        }
+    }
+
+    // If the gas_limit is zero, set it to the block gas limit so that call will not hit
+    // gas limit not set error. It is possible, in the future, to estimate the gas limit
+    // based on the account balance and base fee + premium for higher accuracy.
+    if msg.gas_limit == 0 {
+        msg.gas_limit = fvm_shared::BLOCK_GAS_LIMIT;
+    }
+
+    if is_system_addr(&msg.from) {
+        // Explicit execution requires `from` to be an account kind.
+        s.execute_implicit(msg)
+    } else {
+        s.execute_explicit(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cid::Cid;
+    use fendermint_vm_actor_interface::{evm, ipc::GATEWAY_ACTOR_ID, system::SYSTEM_ACTOR_ADDR};
+    use fendermint_vm_genesis::{ipc::IpcParams, Genesis};
+    use fendermint_vm_message::query::MAX_ACTOR_PROOF_KEYS;
+    use fvm::engine::MultiEngine;
+    use fvm::state_tree::StateTree;
+    use fvm_ipld_blockstore::Blockstore;
+    use fvm_ipld_encoding::{CborStore, DAG_CBOR};
+    use fvm_shared::address::Address;
+    use multihash::{Code, MultihashDigest};
+    use quickcheck::Arbitrary;
+    use tendermint_rpc::{MockClient, MockRequestMethodMatcher};
+
+    use super::{get_actor_state, FvmQueryState};
+    use crate::fvm::bundle::{bundle_path, contracts_path, custom_actors_bundle_path};
+    use crate::fvm::state::{FvmGenesisState, FvmStateParams};
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use crate::fvm::upgrades::UpgradeScheduler;
+    use crate::fvm::FvmMessageInterpreter;
+    use crate::GenesisInterpreter;
+
+    /// Create the genesis state and query it at the first block.
+    async fn init_query_state() -> (FvmQueryState<MemoryBlockstore>, FvmStateParams) {
+        let mut g = quickcheck::Gen::new(5);
+        let mut genesis = Genesis::arbitrary(&mut g);
+        genesis.ipc = Some(IpcParams::arbitrary(&mut g));
+
+        let store = MemoryBlockstore::new();
+        let multi_engine = Arc::new(MultiEngine::default());
+        let bundle = std::fs::read(bundle_path()).expect("failed to read bundle");
+        let custom_actors_bundle =
+            std::fs::read(custom_actors_bundle_path()).expect("failed to read custom bundle");
+
+        let state = FvmGenesisState::new(
+            store.clone(),
+            multi_engine.clone(),
+            &bundle,
+            &custom_actors_bundle,
+        )
+        .await
+        .expect("failed to create state");
+
+        let (client, _) = MockClient::new(MockRequestMethodMatcher::default());
+        let interpreter = FvmMessageInterpreter::<MemoryBlockstore, _>::new(
+            client,
+            None,
+            contracts_path(),
+            1.05,
+            1.05,
+            false,
+            UpgradeScheduler::new(),
+        );
+
+        let (state, out) = interpreter
+            .init(state, genesis)
+            .await
+            .expect("failed to create actors");
+
+        let state_root = state.commit().expect("failed to commit genesis");
+
+        let params = FvmStateParams {
+            state_root,
+            timestamp: out.timestamp,
+            network_version: out.network_version,
+            base_fee: out.base_fee,
+            circ_supply: out.circ_supply,
+            chain_id: out.chain_id.into(),
+            power_scale: out.power_scale,
+            app_version: 0,
+            exec_limits: out.exec_limits,
+            executed_upgrades: Default::default(),
+            upgrades_in_progress: Default::default(),
+            pending_topdown_msgs: Default::default(),
+            pending_bottomup_msgs: Default::default(),
+            gas_params: None,
+            parent_checkpoint_height: None,
+        };
+
+        let state = FvmQueryState::new(
+            store,
+            multi_engine,
+            1,
+            params.clone(),
+            Default::default(),
+            false,
+        )
+        .expect("failed to create query state");
+
+        (state, params)
+    }
+
+    /// Put the blocks of a proof into an empty store, under the CIDs they hash to.
+    fn proof_store<'a>(blocks: impl IntoIterator<Item = &'a [u8]>) -> MemoryBlockstore {
+        let store = MemoryBlockstore::new();
+        for block in blocks {
+            let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(block));
+            store.put_keyed(&cid, block).expect("failed to put block");
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn actor_proof_verifies_against_app_hash() {
+        let (mut state, params) = init_query_state().await;
+        let app_hash = fendermint_vm_message::cid(&params).expect("state params have a CID");
+
+        let gateway = Address::new_id(GATEWAY_ACTOR_ID);
+        let missing = Address::new_id(u64::MAX - 1);
+        let keys = [evm::uints::U256::zero(), evm::uints::U256::from(1)];
+
+        for addr in [SYSTEM_ACTOR_ADDR, gateway, missing] {
+            let (s, proof) = state
+                .actor_proof(&addr, &keys)
+                .await
+                .expect("failed to get proof");
+            state = s;
+
+            let store = proof_store(
+                std::iter::once(proof.state_params.bytes())
+                    .chain(proof.account_proof.iter().map(|b| b.bytes())),
+            );
+
+            // The state params are the preimage of the app hash and lead to the state root.
+            let proven_params: FvmStateParams = store
+                .get_cbor(&app_hash)
+                .expect("failed to read state params")
+                .expect("the proof contains the state params");
+
+            // Looking up the actor needs nothing but the blocks of the proof.
+            let state_tree = StateTree::new_from_root(store.clone(), &proven_params.state_root)
+                .expect("failed to load state tree from the proof");
+            let actor = get_actor_state(&state_tree, &addr).expect("failed to look up actor");
+            assert_eq!(actor, proof.actor, "actor of {addr}");
+
+            match actor {
+                None => assert!(proof.storage_proof.is_empty()),
+                Some((_, actor)) => {
+                    assert_eq!(proof.storage_proof.len(), keys.len());
+                    if addr == gateway {
+                        // The EVM actor walks its storage from its own state.
+                        for p in proof.storage_proof {
+                            let store = proof_store(p.proof.iter().map(|b| b.bytes()));
+                            assert!(store.has(&actor.state).unwrap(), "state of {addr}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_proof_limits_keys() {
+        let (state, _) = init_query_state().await;
+        let keys = vec![evm::uints::U256::zero(); MAX_ACTOR_PROOF_KEYS + 1];
+        assert!(state.actor_proof(&SYSTEM_ACTOR_ADDR, &keys).await.is_err());
+    }
+}
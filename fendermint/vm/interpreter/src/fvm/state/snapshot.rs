@@ -375,6 +375,13 @@ mod tests {
             chain_id: 1024,
             power_scale: 0,
             app_version: 0,
+            exec_limits: None,
+            executed_upgrades: Default::default(),
+            upgrades_in_progress: Default::default(),
+            pending_topdown_msgs: Default::default(),
+            pending_bottomup_msgs: Default::default(),
+            gas_params: None,
+            parent_checkpoint_height: None,
         };
         let block_height = 2048;
 
@@ -0,0 +1,94 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::DAG_CBOR;
+
+/// A blockstore recording the CBOR blocks read through it, in the order they were first read,
+/// so they can be handed out as a proof of whatever was looked up.
+///
+/// Other kinds of blocks, e.g. the Wasm code of actors, aren't recorded.
+/// Clones share the recorded blocks.
+#[derive(Clone)]
+pub struct RecordingBlockstore<DB> {
+    inner: DB,
+    blocks: Arc<Mutex<Vec<(Cid, Vec<u8>)>>>,
+}
+
+impl<DB> RecordingBlockstore<DB> {
+    pub fn new(inner: DB) -> Self {
+        Self {
+            inner,
+            blocks: Default::default(),
+        }
+    }
+
+    /// Return the blocks recorded so far and start recording afresh.
+    pub fn take(&self) -> Vec<Vec<u8>> {
+        let mut guard = self.blocks.lock().unwrap();
+        std::mem::take(&mut *guard)
+            .into_iter()
+            .map(|(_, block)| block)
+            .collect()
+    }
+}
+
+impl<DB> Blockstore for RecordingBlockstore<DB>
+where
+    DB: Blockstore,
+{
+    fn has(&self, k: &Cid) -> Result<bool> {
+        self.inner.has(k)
+    }
+
+    fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
+        let block = self.inner.get(k)?;
+        if let Some(ref block) = block {
+            if k.codec() == DAG_CBOR {
+                let mut guard = self.blocks.lock().unwrap();
+                if !guard.iter().any(|(cid, _)| cid == k) {
+                    guard.push((*k, block.clone()));
+                }
+            }
+        }
+        Ok(block)
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.inner.put_keyed(k, block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::Blockstore;
+    use fvm_ipld_encoding::CborStore;
+    use multihash::Code;
+
+    use super::RecordingBlockstore;
+    use crate::fvm::store::memory::MemoryBlockstore;
+
+    #[test]
+    fn records_blocks_read() {
+        let store = RecordingBlockstore::new(MemoryBlockstore::new());
+        let a = store.put_cbor(&"a", Code::Blake2b256).unwrap();
+        let b = store.put_cbor(&"b", Code::Blake2b256).unwrap();
+        assert!(store.take().is_empty());
+
+        store.get(&b).unwrap();
+        store.get(&a).unwrap();
+        store.get(&b).unwrap();
+        assert_eq!(
+            store.take(),
+            vec![
+                fvm_ipld_encoding::to_vec(&"b").unwrap(),
+                fvm_ipld_encoding::to_vec(&"a").unwrap()
+            ]
+        );
+        assert!(store.take().is_empty());
+    }
+}
@@ -40,11 +40,118 @@ where
 
 /// Execute the top down messages implicitly. Before the execution, mint to the gateway of the funds
 /// transferred in the messages, and increase the circulating supply with the incoming value.
+///
+/// If the chain limits the gas top-down messages can use in a block, the messages are queued up
+/// behind the ones still pending from earlier blocks, and only as many are executed as fit into
+/// what is left of the budget; see [execute_pending_topdown_msgs].
 pub async fn execute_topdown_msgs<DB>(
     gateway_caller: &GatewayCaller<DB>,
     state: &mut FvmExecState<DB>,
     messages: Vec<IpcEnvelope>,
 ) -> anyhow::Result<FvmApplyRet>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+{
+    let Some(budget) = state.topdown_gas_available() else {
+        return apply_topdown_msgs(gateway_caller, state, messages);
+    };
+
+    let mut pending = state.pending_topdown_msgs().to_vec();
+    pending.extend(messages);
+
+    let ret = apply_topdown_msgs_with_budget(gateway_caller, state, pending, budget)?;
+
+    match ret {
+        Some(ret) => Ok(ret),
+        // Nothing fit into the budget; still call the gateway so there is a receipt.
+        None => apply_topdown_msgs(gateway_caller, state, Vec::new()),
+    }
+}
+
+/// Execute the top down messages which didn't fit into the gas budget of earlier blocks,
+/// as many as fit into the budget of the current one.
+///
+/// Returns `None` if there was nothing to execute.
+pub async fn execute_pending_topdown_msgs<DB>(
+    gateway_caller: &GatewayCaller<DB>,
+    state: &mut FvmExecState<DB>,
+) -> anyhow::Result<Option<FvmApplyRet>>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+{
+    let budget = match state.topdown_gas_available() {
+        Some(budget) if !state.pending_topdown_msgs().is_empty() => budget,
+        // The budget could have been removed, but that would take an upgrade,
+        // which should also take care of any pending messages.
+        _ => return Ok(None),
+    };
+
+    let pending = state.pending_topdown_msgs().to_vec();
+
+    apply_topdown_msgs_with_budget(gateway_caller, state, pending, budget)
+}
+
+/// Execute messages one by one until the gas they used reaches the budget, so the last one
+/// can go over it, then put the rest back into the pending queue.
+///
+/// Executing at least one message when there is any budget left guarantees progress even
+/// if a single message needs more gas than the budget.
+fn apply_topdown_msgs_with_budget<DB>(
+    gateway_caller: &GatewayCaller<DB>,
+    state: &mut FvmExecState<DB>,
+    mut messages: Vec<IpcEnvelope>,
+    budget: u64,
+) -> anyhow::Result<Option<FvmApplyRet>>
+where
+    DB: Blockstore + Sync + Send + Clone + 'static,
+{
+    let mut gas_used = 0;
+    let mut applied = 0;
+    let mut acc: Option<FvmApplyRet> = None;
+
+    while applied < messages.len() && gas_used < budget {
+        let ret = apply_topdown_msgs(gateway_caller, state, vec![messages[applied].clone()])?;
+        gas_used += ret.apply_ret.msg_receipt.gas_used;
+        applied += 1;
+
+        acc = Some(match acc {
+            None => ret,
+            Some(mut acc) => {
+                acc.apply_ret.msg_receipt.gas_used += ret.apply_ret.msg_receipt.gas_used;
+                acc.apply_ret.events.extend(ret.apply_ret.events);
+                acc.emitters.extend(ret.emitters);
+                acc
+            }
+        });
+    }
+
+    state.record_topdown_gas_used(gas_used);
+
+    let pending = messages.split_off(applied);
+
+    if !pending.is_empty() {
+        tracing::info!(
+            applied,
+            pending = pending.len(),
+            gas_used,
+            budget,
+            "top-down messages over the gas budget of the block"
+        );
+    }
+
+    if !pending.is_empty() || !state.pending_topdown_msgs().is_empty() {
+        state.set_pending_topdown_msgs(pending);
+    }
+
+    Ok(acc)
+}
+
+/// Mint the funds transferred in the messages and apply them in a single call to the gateway.
+fn apply_topdown_msgs<DB>(
+    gateway_caller: &GatewayCaller<DB>,
+    state: &mut FvmExecState<DB>,
+    messages: Vec<IpcEnvelope>,
+) -> anyhow::Result<FvmApplyRet>
 where
     DB: Blockstore + Sync + Send + Clone + 'static,
 {
@@ -63,3 +170,270 @@ where
 
     gateway_caller.apply_cross_messages(state, messages)
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use fendermint_vm_genesis::ipc::{GatewayParams, IpcParams};
+    use fendermint_vm_genesis::{ExecLimits, Genesis};
+    use fvm::engine::MultiEngine;
+    use fvm_shared::address::Address;
+    use fvm_shared::bigint::Zero;
+    use fvm_shared::clock::ChainEpoch;
+    use fvm_shared::econ::TokenAmount;
+    use ipc_api::cross::IpcEnvelope;
+    use ipc_api::subnet_id::SubnetID;
+    use quickcheck::Arbitrary;
+    use tendermint_rpc::{MockClient, MockRequestMethodMatcher};
+
+    use super::{apply_topdown_msgs, execute_pending_topdown_msgs, execute_topdown_msgs};
+    use crate::fvm::bundle::{bundle_path, contracts_path, custom_actors_bundle_path};
+    use crate::fvm::state::ipc::GatewayCaller;
+    use crate::fvm::state::{FvmExecState, FvmGenesisState, FvmStateParams};
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use crate::fvm::upgrades::UpgradeScheduler;
+    use crate::fvm::FvmMessageInterpreter;
+    use crate::GenesisInterpreter;
+
+    const SUBNET_ID: &str = "/r314159/f410fnfmitm2ww7oehhtbokf6wulhrr62sgq3sgqmenq";
+
+    /// A subnet executing one block at a time, committing the state params in between.
+    pub(crate) struct Chain {
+        pub store: MemoryBlockstore,
+        pub multi_engine: Arc<MultiEngine>,
+        pub params: FvmStateParams,
+        pub height: ChainEpoch,
+    }
+
+    impl Chain {
+        pub async fn new(genesis: Genesis) -> Self {
+            let store = MemoryBlockstore::new();
+            let multi_engine = Arc::new(MultiEngine::default());
+
+            let bundle = std::fs::read(bundle_path()).expect("failed to read bundle");
+            let custom_actors_bundle = std::fs::read(custom_actors_bundle_path())
+                .expect("failed to read custom actor bundle");
+
+            let state = FvmGenesisState::new(
+                store.clone(),
+                multi_engine.clone(),
+                &bundle,
+                &custom_actors_bundle,
+            )
+            .await
+            .expect("failed to create state");
+
+            let (client, _) = MockClient::new(MockRequestMethodMatcher::default());
+            let interpreter = FvmMessageInterpreter::<MemoryBlockstore, _>::new(
+                client,
+                None,
+                contracts_path(),
+                1.05,
+                1.05,
+                false,
+                UpgradeScheduler::new(),
+            );
+
+            let (state, out) = interpreter
+                .init(state, genesis)
+                .await
+                .expect("failed to create actors");
+
+            let state_root = state.commit().expect("failed to commit genesis");
+
+            let params = FvmStateParams {
+                state_root,
+                timestamp: out.timestamp,
+                network_version: out.network_version,
+                base_fee: out.base_fee,
+                circ_supply: out.circ_supply,
+                chain_id: out.chain_id.into(),
+                power_scale: out.power_scale,
+                app_version: 0,
+                exec_limits: out.exec_limits,
+                executed_upgrades: Default::default(),
+                upgrades_in_progress: Default::default(),
+                pending_topdown_msgs: Default::default(),
+                pending_bottomup_msgs: Default::default(),
+                gas_params: None,
+                parent_checkpoint_height: None,
+            };
+
+            Self {
+                store,
+                multi_engine,
+                params,
+                height: 0,
+            }
+        }
+
+        pub fn begin_block(&self) -> FvmExecState<MemoryBlockstore> {
+            FvmExecState::new(
+                self.store.clone(),
+                &self.multi_engine,
+                self.height + 1,
+                self.params.clone(),
+            )
+            .expect("failed to create exec state")
+        }
+
+        pub fn commit(&mut self, state: FvmExecState<MemoryBlockstore>) {
+            let (state_root, params, _) = state.commit().expect("failed to commit");
+            self.params.state_root = state_root;
+            self.params.circ_supply = params.circ_supply;
+            self.params.pending_topdown_msgs = params.pending_topdown_msgs;
+            self.height += 1;
+        }
+
+        fn balance(&self, addr: &Address) -> TokenAmount {
+            let state = self.begin_block();
+            let state_tree = state.state_tree();
+            match state_tree.lookup_id(addr).expect("failed to look up") {
+                Some(id) => {
+                    state_tree
+                        .get_actor(id)
+                        .expect("failed to get actor")
+                        .expect("actor exists")
+                        .balance
+                }
+                None => TokenAmount::zero(),
+            }
+        }
+    }
+
+    pub(crate) fn make_genesis(topdown_gas_budget: Option<u64>) -> Genesis {
+        let mut g = quickcheck::Gen::new(5);
+        let mut genesis = Genesis::arbitrary(&mut g);
+        genesis.ipc = Some(IpcParams {
+            gateway: GatewayParams {
+                subnet_id: SubnetID::from_str(SUBNET_ID).unwrap(),
+                bottom_up_check_period: 10,
+                majority_percentage: 60,
+                active_validators_limit: 100,
+            },
+        });
+        genesis.exec_limits = Some(ExecLimits {
+            topdown_gas_budget,
+            ..Default::default()
+        });
+        genesis.deployments = None;
+        genesis
+    }
+
+    fn receiver(i: usize) -> Address {
+        Address::new_delegated(10, &[i as u8 + 1; 20]).unwrap()
+    }
+
+    /// Fund messages with consecutive nonces, each to a different receiver, so the gateway
+    /// would reject any of them which wasn't applied in order.
+    fn fund_msgs(n: usize) -> Vec<IpcEnvelope> {
+        let subnet_id = SubnetID::from_str(SUBNET_ID).unwrap();
+        (0..n)
+            .map(|i| {
+                let mut msg = IpcEnvelope::new_fund_msg(
+                    &subnet_id,
+                    &Address::new_id(100),
+                    &receiver(i),
+                    TokenAmount::from_atto(1000),
+                )
+                .unwrap();
+                msg.nonce = i as u64;
+                msg
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn topdown_msgs_over_budget_still_make_progress() {
+        let mut chain = Chain::new(make_genesis(Some(1))).await;
+        let gateway = GatewayCaller::default();
+
+        let mut state = chain.begin_block();
+        let ret = execute_topdown_msgs(&gateway, &mut state, fund_msgs(1))
+            .await
+            .unwrap();
+
+        // The single message needs more gas than the budget, but it's executed anyway.
+        assert!(ret.apply_ret.msg_receipt.gas_used > 1);
+        assert!(state.pending_topdown_msgs().is_empty());
+        assert_eq!(state.topdown_gas_available(), Some(0));
+
+        chain.commit(state);
+        assert_eq!(chain.balance(&receiver(0)), TokenAmount::from_atto(1000));
+    }
+
+    #[tokio::test]
+    async fn topdown_msgs_are_split_across_blocks_in_order() {
+        let mut chain = Chain::new(make_genesis(Some(1))).await;
+        let gateway = GatewayCaller::default();
+
+        // Only the first message fits, the rest wait for the following blocks.
+        let mut state = chain.begin_block();
+        execute_topdown_msgs(&gateway, &mut state, fund_msgs(3))
+            .await
+            .unwrap();
+        assert_eq!(state.pending_topdown_msgs(), &fund_msgs(3)[1..]);
+        chain.commit(state);
+
+        for i in 1..3 {
+            assert_eq!(chain.balance(&receiver(i)), TokenAmount::zero());
+
+            let mut state = chain.begin_block();
+            let ret = execute_pending_topdown_msgs(&gateway, &mut state)
+                .await
+                .unwrap();
+            assert!(ret.is_some());
+            assert_eq!(state.pending_topdown_msgs(), &fund_msgs(3)[i + 1..]);
+            chain.commit(state);
+        }
+
+        // Every message was applied, which only happens if the nonces were in order.
+        for i in 0..3 {
+            assert_eq!(chain.balance(&receiver(i)), TokenAmount::from_atto(1000));
+        }
+
+        // Nothing left to execute.
+        let mut state = chain.begin_block();
+        let ret = execute_pending_topdown_msgs(&gateway, &mut state)
+            .await
+            .unwrap();
+        assert!(ret.is_none());
+    }
+
+    #[tokio::test]
+    async fn topdown_msgs_without_budget_are_applied_at_once() {
+        let genesis = make_genesis(None);
+        let mut chain0 = Chain::new(genesis.clone()).await;
+        let mut chain1 = Chain::new(genesis).await;
+        let gateway = GatewayCaller::default();
+
+        let mut state0 = chain0.begin_block();
+        let ret0 = execute_topdown_msgs(&gateway, &mut state0, fund_msgs(3))
+            .await
+            .unwrap();
+
+        // What the execution looked like before there was a budget.
+        let mut state1 = chain1.begin_block();
+        let ret1 = apply_topdown_msgs(&gateway, &mut state1, fund_msgs(3)).unwrap();
+
+        assert_eq!(
+            ret0.apply_ret.msg_receipt.gas_used,
+            ret1.apply_ret.msg_receipt.gas_used
+        );
+        assert!(state0.pending_topdown_msgs().is_empty());
+
+        chain0.commit(state0);
+        chain1.commit(state1);
+
+        // Same state params means the same app hash.
+        assert_eq!(chain0.params, chain1.params);
+
+        let mut state = chain0.begin_block();
+        let ret = execute_pending_topdown_msgs(&gateway, &mut state)
+            .await
+            .unwrap();
+        assert!(ret.is_none());
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::BTreeMap;
+
+use fendermint_vm_message::query::{ActorStats, CallTrace};
+use fvm::gas::PriceList;
+use fvm::trace::ExecutionEvent;
+use fvm_shared::address::Address;
+use fvm_shared::crypto::hash::SupportedHashes;
+
+/// Build the tree of calls made while executing a message from the flat list of events
+/// the FVM records when tracing is enabled.
+///
+/// Returns `None` if the message didn't get as far as calling its recipient,
+/// e.g. because it failed the pre-validation.
+pub fn call_trace(events: &[ExecutionEvent]) -> Option<CallTrace> {
+    // The calls in progress, the innermost one last.
+    let mut stack: Vec<CallTrace> = Vec::new();
+    let mut root = None;
+
+    for event in events {
+        match event {
+            ExecutionEvent::GasCharge(charge) => {
+                // Charges made before the first call, like the message inclusion, aren't attributed to any call.
+                if let Some(call) = stack.last_mut() {
+                    call.gas_used += charge.total().round_up();
+                }
+            }
+            ExecutionEvent::Call {
+                from,
+                to,
+                method,
+                params,
+                value,
+                gas_limit,
+                read_only,
+            } => {
+                stack.push(CallTrace {
+                    from: Address::new_id(*from),
+                    to: *to,
+                    method_num: *method,
+                    params: params
+                        .as_ref()
+                        .map(|p| hex::encode(&p.data))
+                        .unwrap_or_default(),
+                    value: value.clone(),
+                    gas_limit: *gas_limit,
+                    gas_used: 0,
+                    read_only: *read_only,
+                    exit_code: 0,
+                    return_data: String::new(),
+                    error: None,
+                    calls: Vec::new(),
+                });
+            }
+            ExecutionEvent::CallReturn(exit_code, data) => {
+                if let Some(mut call) = stack.pop() {
+                    call.exit_code = exit_code.value();
+                    call.return_data = data
+                        .as_ref()
+                        .map(|d| hex::encode(&d.data))
+                        .unwrap_or_default();
+                    finish_call(&mut stack, &mut root, call);
+                }
+            }
+            ExecutionEvent::CallError(e) => {
+                if let Some(mut call) = stack.pop() {
+                    call.error = Some(format!("{} ({})", e.0, e.1));
+                    finish_call(&mut stack, &mut root, call);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // If the execution was aborted, the calls in progress never returned.
+    while let Some(call) = stack.pop() {
+        finish_call(&mut stack, &mut root, call);
+    }
+
+    root
+}
+
+/// Add up the resources used by each actor called while executing a message, keyed by the
+/// address it was called at, from the flat list of events the FVM records when tracing is enabled.
+///
+/// Gas is attributed to the innermost call in progress, so the gas of an actor excludes that of
+/// the calls it made. The state bytes are estimated from the storage gas of the blocks the actor
+/// linked into its state, as charged by the price list.
+pub fn actor_usage(
+    events: &[ExecutionEvent],
+    price_list: &PriceList,
+) -> BTreeMap<Address, ActorStats> {
+    // The storage gas of linking a block is a flat amount plus an amount per byte.
+    let link_storage = |size| {
+        price_list
+            .on_block_link(SupportedHashes::Blake2b256, size)
+            .other_gas
+            .as_milligas()
+    };
+    let link_flat = link_storage(0);
+    let link_per_byte = link_storage(1).saturating_sub(link_flat);
+
+    let mut usage = BTreeMap::<Address, ActorStats>::new();
+    // The actors of the calls in progress, the innermost one last.
+    let mut stack: Vec<Address> = Vec::new();
+
+    for event in events {
+        match event {
+            ExecutionEvent::GasCharge(charge) => {
+                let Some(actor) = stack.last() else {
+                    continue;
+                };
+                let stats = usage.get_mut(actor).expect("called actors have stats");
+                stats.gas_used += charge.total().round_up();
+                if charge.name == "OnBlockLink" && link_per_byte > 0 {
+                    stats.state_bytes +=
+                        charge.other_gas.as_milligas().saturating_sub(link_flat) / link_per_byte;
+                }
+            }
+            ExecutionEvent::Call { to, .. } => {
+                usage
+                    .entry(*to)
+                    .or_insert_with(|| ActorStats {
+                        actor: *to,
+                        calls: 0,
+                        gas_used: 0,
+                        state_bytes: 0,
+                    })
+                    .calls += 1;
+                stack.push(*to);
+            }
+            ExecutionEvent::CallReturn(..) | ExecutionEvent::CallError(..) => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    usage
+}
+
+/// Add a call which returned to its caller, or make it the root if it was the top level call.
+fn finish_call(stack: &mut [CallTrace], root: &mut Option<CallTrace>, call: CallTrace) {
+    match stack.last_mut() {
+        Some(parent) => {
+            parent.gas_used += call.gas_used;
+            parent.calls.push(call);
+        }
+        None => *root = Some(call),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm::gas::{price_list_by_network_version, Gas, GasCharge};
+    use fvm::kernel::SyscallError;
+    use fvm::trace::ExecutionEvent;
+    use fvm_ipld_encoding::{ipld_block::IpldBlock, DAG_CBOR};
+    use fvm_shared::{
+        address::Address,
+        crypto::hash::SupportedHashes,
+        econ::TokenAmount,
+        error::{ErrorNumber, ExitCode},
+        version::NetworkVersion,
+    };
+
+    use super::{actor_usage, call_trace};
+
+    fn call(from: u64, to: u64) -> ExecutionEvent {
+        ExecutionEvent::Call {
+            from,
+            to: Address::new_id(to),
+            method: 2,
+            params: Some(IpldBlock {
+                codec: DAG_CBOR,
+                data: vec![1, 2],
+            }),
+            value: TokenAmount::from_atto(0),
+            gas_limit: 1000,
+            read_only: false,
+        }
+    }
+
+    fn charge(gas: u64) -> ExecutionEvent {
+        ExecutionEvent::GasCharge(GasCharge::new("test", Gas::new(gas), Gas::zero()))
+    }
+
+    #[test]
+    fn call_tree() {
+        assert!(call_trace(&[charge(5)]).is_none());
+
+        let events = vec![
+            charge(5),
+            call(100, 200),
+            charge(10),
+            call(200, 300),
+            charge(20),
+            ExecutionEvent::CallReturn(ExitCode::OK, None),
+            call(200, 400),
+            ExecutionEvent::CallError(SyscallError(
+                "actor does not exist".to_owned(),
+                ErrorNumber::NotFound,
+            )),
+            ExecutionEvent::CallReturn(ExitCode::USR_ILLEGAL_ARGUMENT, None),
+        ];
+
+        let root = call_trace(&events).expect("message was executed");
+        assert_eq!(root.from, Address::new_id(100));
+        assert_eq!(root.params, "0102");
+        assert_eq!(root.gas_used, 30);
+        assert_eq!(root.exit_code, ExitCode::USR_ILLEGAL_ARGUMENT.value());
+        assert_eq!(root.calls.len(), 2);
+        assert_eq!(root.calls[0].to, Address::new_id(300));
+        assert_eq!(root.calls[0].gas_used, 20);
+        assert!(root.calls[0].error.is_none());
+        assert!(root.calls[1].error.is_some());
+    }
+
+    #[test]
+    fn actor_usage_by_call() {
+        let price_list = price_list_by_network_version(NetworkVersion::V21);
+        let link = price_list.on_block_link(SupportedHashes::Blake2b256, 100);
+
+        let events = vec![
+            charge(5),
+            call(100, 200),
+            charge(10),
+            call(200, 300),
+            charge(20),
+            ExecutionEvent::GasCharge(link.clone()),
+            ExecutionEvent::CallReturn(ExitCode::OK, None),
+            call(200, 300),
+            ExecutionEvent::CallReturn(ExitCode::OK, None),
+            charge(1),
+            ExecutionEvent::CallReturn(ExitCode::OK, None),
+        ];
+
+        let usage = actor_usage(&events, price_list);
+        assert_eq!(usage.len(), 2);
+
+        let caller = &usage[&Address::new_id(200)];
+        assert_eq!(caller.calls, 1);
+        assert_eq!(caller.gas_used, 11);
+        assert_eq!(caller.state_bytes, 0);
+
+        let callee = &usage[&Address::new_id(300)];
+        assert_eq!(callee.calls, 2);
+        assert_eq!(callee.gas_used, 20 + link.total().round_up());
+        assert_eq!(callee.state_bytes, 100);
+    }
+}
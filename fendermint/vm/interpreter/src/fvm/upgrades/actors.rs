@@ -0,0 +1,313 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Replacing the code of actors at an upgrade height, to patch bugs in the built-in
+//! or custom actors without starting a new network.
+//!
+//! A typical migration loads a bundle with the fixed actors, points the system actor at
+//! the new code, then switches the existing actors over, transforming their state if
+//! the layout changed:
+//!
+//! ```text
+//! let replaced = install_bundle(state, &bundle, &["eam"])?;
+//! for (old_code, new_code) in replaced.into_values() {
+//!     replace_code(state, old_code, new_code, &mut keep_state)?;
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Context};
+use cid::{multihash::Code, Cid};
+use fendermint_vm_actor_interface::system;
+use futures_util::FutureExt;
+use fvm::state_tree::ActorState;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use fvm_shared::ActorID;
+
+use crate::fvm::state::{parse_bundle, FvmExecState, MachineBlockstore};
+
+/// Transform the state of an actor to the layout the new code expects, returning the
+/// CID of the new state.
+pub type StateTransform<'a, DB> =
+    &'a mut dyn FnMut(ActorID, &ActorState, &MachineBlockstore<DB>) -> anyhow::Result<Cid>;
+
+/// A [StateTransform] for code which reads the state as it is.
+pub fn keep_state<DB>(
+    _: ActorID,
+    actor: &ActorState,
+    _: &MachineBlockstore<DB>,
+) -> anyhow::Result<Cid> {
+    Ok(actor.state)
+}
+
+/// Load an actor bundle (CAR file) into the blockstore, returning the code CIDs in its
+/// manifest by actor name.
+pub fn load_bundle<DB>(
+    state: &FvmExecState<DB>,
+    bundle: &[u8],
+) -> anyhow::Result<BTreeMap<String, Cid>>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    let store = state.state_tree().store();
+
+    // Reading from a byte slice never has to wait.
+    let (version, manifest_cid) = parse_bundle(store, bundle)
+        .now_or_never()
+        .ok_or_else(|| anyhow!("loading the bundle did not complete"))??;
+
+    if version != 1 {
+        bail!("unsupported bundle manifest version: {version}");
+    }
+
+    let codes: Vec<(String, Cid)> = store
+        .get_cbor(&manifest_cid)?
+        .ok_or_else(|| anyhow!("bundle manifest {manifest_cid} not found"))?;
+
+    Ok(codes.into_iter().collect())
+}
+
+/// Load a bundle and point the system actor at its code for the named actors, so that actors
+/// created from the next block on run the new code.
+///
+/// Returns the old and the new code of each replaced actor type, for [replace_code] to switch
+/// the existing actors over. Actors not named keep their code, e.g. the custom actors which
+/// replaced built-in ones at genesis.
+pub fn install_bundle<DB>(
+    state: &mut FvmExecState<DB>,
+    bundle: &[u8],
+    names: &[&str],
+) -> anyhow::Result<BTreeMap<String, (Cid, Cid)>>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    let new_codes = load_bundle(state, bundle)?;
+
+    let system_actor = state
+        .state_tree()
+        .get_actor(system::SYSTEM_ACTOR_ID)?
+        .ok_or_else(|| anyhow!("system actor not found"))?;
+
+    let store = state.state_tree().store();
+    let mut system_state: system::State = store
+        .get_cbor(&system_actor.state)?
+        .ok_or_else(|| anyhow!("system actor state not found"))?;
+
+    let mut builtin_actors: Vec<(String, Cid)> = store
+        .get_cbor(&system_state.builtin_actors)?
+        .ok_or_else(|| anyhow!("builtin actor manifest not found"))?;
+
+    let mut replaced = BTreeMap::new();
+    for name in names {
+        let new_code = *new_codes
+            .get(*name)
+            .ok_or_else(|| anyhow!("actor {name} not found in the bundle"))?;
+
+        let (_, code) = builtin_actors
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| anyhow!("actor {name} not found in the system actor manifest"))?;
+
+        replaced.insert(name.to_string(), (*code, new_code));
+        *code = new_code;
+    }
+
+    system_state.builtin_actors = store
+        .put_cbor(&builtin_actors, Code::Blake2b256)
+        .context("failed to store builtin actor manifest")?;
+
+    let new_state = store
+        .put_cbor(&system_state, Code::Blake2b256)
+        .context("failed to store system actor state")?;
+
+    state
+        .state_tree_mut()
+        .mutate_actor(system::SYSTEM_ACTOR_ID, |actor| {
+            actor.state = new_state;
+            Ok(())
+        })?;
+
+    Ok(replaced)
+}
+
+/// Replace the code of a single actor, transforming its state with the given function.
+///
+/// The new code has to be in the blockstore already, e.g. loaded with [load_bundle].
+pub fn replace_actor_code<DB>(
+    state: &mut FvmExecState<DB>,
+    id: ActorID,
+    new_code: Cid,
+    transform: StateTransform<DB>,
+) -> anyhow::Result<()>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    let tree = state.state_tree();
+    if !tree.store().has(&new_code)? {
+        bail!("actor code {new_code} not found; load its bundle first");
+    }
+
+    let actor = tree
+        .get_actor(id)?
+        .ok_or_else(|| anyhow!("actor {id} not found"))?;
+
+    let new_state = transform(id, &actor, tree.store())
+        .with_context(|| format!("failed to transform the state of actor {id}"))?;
+
+    state.state_tree_mut().set_actor(
+        id,
+        ActorState {
+            code: new_code,
+            state: new_state,
+            ..actor
+        },
+    );
+    Ok(())
+}
+
+/// Replace the code of every actor running the old code, e.g. all the accounts, returning
+/// the number of actors changed.
+pub fn replace_code<DB>(
+    state: &mut FvmExecState<DB>,
+    old_code: Cid,
+    new_code: Cid,
+    transform: StateTransform<DB>,
+) -> anyhow::Result<usize>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    let mut ids = Vec::new();
+    state.state_tree().for_each(|addr, actor| {
+        if actor.code == old_code {
+            ids.push(addr.id()?);
+        }
+        Ok(())
+    })?;
+
+    for id in ids.iter() {
+        replace_actor_code(state, *id, new_code, transform)?;
+    }
+
+    tracing::info!(
+        old_code = old_code.to_string(),
+        new_code = new_code.to_string(),
+        count = ids.len(),
+        "replaced actor code"
+    );
+
+    Ok(ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use cid::Cid;
+    use fendermint_vm_actor_interface::{eam, system};
+    use fvm::state_tree::ActorState;
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::ActorID;
+
+    use super::{install_bundle, load_bundle, replace_actor_code, replace_code};
+    use crate::fvm::bundle::bundle_path;
+    use crate::fvm::state::{FvmExecState, MachineBlockstore};
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use crate::fvm::topdown::tests::{make_genesis, Chain};
+
+    /// The code of each actor type according to the system actor.
+    fn system_manifest(state: &FvmExecState<MemoryBlockstore>) -> BTreeMap<String, Cid> {
+        let tree = state.state_tree();
+        let actor = tree
+            .get_actor(system::SYSTEM_ACTOR_ID)
+            .unwrap()
+            .expect("system actor exists");
+        let system_state: system::State = tree.store().get_cbor(&actor.state).unwrap().unwrap();
+        let builtin_actors: Vec<(String, Cid)> = tree
+            .store()
+            .get_cbor(&system_state.builtin_actors)
+            .unwrap()
+            .unwrap();
+        builtin_actors.into_iter().collect()
+    }
+
+    fn get_actor(state: &FvmExecState<MemoryBlockstore>, id: ActorID) -> ActorState {
+        state
+            .state_tree()
+            .get_actor(id)
+            .unwrap()
+            .expect("actor exists")
+    }
+
+    #[tokio::test]
+    async fn test_install_bundle_and_replace_code() {
+        let mut chain = Chain::new(make_genesis(None)).await;
+        let mut state = chain.begin_block();
+
+        let bundle = std::fs::read(bundle_path()).expect("failed to read bundle");
+        let builtin_codes = load_bundle(&state, &bundle).unwrap();
+
+        // Give the actor something to lose.
+        state
+            .state_tree_mut()
+            .mutate_actor(eam::EAM_ACTOR_ID, |actor| {
+                actor.balance = TokenAmount::from_atto(1000);
+                Ok(())
+            })
+            .unwrap();
+        let before = get_actor(&state, eam::EAM_ACTOR_ID);
+
+        // The built-in EAM was replaced with the custom one at genesis, so this switches it back.
+        let replaced = install_bundle(&mut state, &bundle, &["eam"]).unwrap();
+        assert_eq!(replaced.len(), 1);
+        let (old_code, new_code) = replaced["eam"];
+        assert_eq!(old_code, before.code);
+        assert_eq!(new_code, builtin_codes["eam"]);
+        assert_ne!(old_code, new_code);
+        assert_eq!(system_manifest(&state)["eam"], new_code);
+
+        // Installing only points new actors at the code.
+        assert_eq!(get_actor(&state, eam::EAM_ACTOR_ID).code, old_code);
+
+        let mut transformed = Vec::new();
+        let mut transform = |id: ActorID,
+                             actor: &ActorState,
+                             _: &MachineBlockstore<MemoryBlockstore>|
+         -> anyhow::Result<Cid> {
+            transformed.push(id);
+            Ok(actor.state)
+        };
+        let count = replace_code(&mut state, old_code, new_code, &mut transform).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(transformed, vec![eam::EAM_ACTOR_ID]);
+
+        let after = get_actor(&state, eam::EAM_ACTOR_ID);
+        assert_eq!(after.code, new_code);
+        assert_eq!(after.state, before.state);
+        assert_eq!(after.balance, before.balance);
+        assert_eq!(after.sequence, before.sequence);
+
+        chain.commit(state);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_actors_are_rejected() {
+        let chain = Chain::new(make_genesis(None)).await;
+        let mut state = chain.begin_block();
+        let manifest = system_manifest(&state);
+
+        let bundle = std::fs::read(bundle_path()).expect("failed to read bundle");
+        assert!(install_bundle(&mut state, &bundle, &["no-such-actor"]).is_err());
+        assert_eq!(system_manifest(&state), manifest);
+
+        // Code which isn't in the blockstore can't be installed either.
+        let missing_code = Cid::default();
+        let mut transform = super::keep_state::<MemoryBlockstore>;
+        assert!(
+            replace_actor_code(&mut state, eam::EAM_ACTOR_ID, missing_code, &mut transform)
+                .is_err()
+        );
+    }
+}
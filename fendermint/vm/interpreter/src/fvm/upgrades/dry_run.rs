@@ -0,0 +1,177 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Running the migration of an upgrade against a copy of the state, to see what it would
+//! change before the chain reaches the upgrade height.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use cid::Cid;
+use fvm::engine::MultiEngine;
+use fvm::state_tree::{ActorState, StateTree};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::ActorID;
+
+use super::Upgrade;
+use crate::fvm::state::{FvmExecState, FvmStateParams};
+
+/// How an actor differs between the state before and after a migration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActorChange {
+    Added(ActorState),
+    Removed(ActorState),
+    Changed {
+        before: ActorState,
+        after: ActorState,
+    },
+}
+
+/// The outcome of running a migration without committing it to the application state.
+#[derive(Debug, Clone)]
+pub struct UpgradeDryRun {
+    pub state_root_before: Cid,
+    pub state_root_after: Cid,
+    pub app_version_before: u64,
+    /// The app version the upgrade sets, if any.
+    pub new_app_version: Option<u64>,
+    pub changes: BTreeMap<ActorID, ActorChange>,
+}
+
+impl<DB> Upgrade<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    /// Execute the migration on top of the state in the parameters, as if it was the beginning
+    /// of the upgrade block, and compare the state before and after.
+    ///
+    /// The resulting state is flushed to the blockstore, so it needs to be one the application
+    /// doesn't use, e.g. one the snapshot of the state was imported into.
+    pub fn dry_run(
+        &self,
+        store: DB,
+        multi_engine: &MultiEngine,
+        params: FvmStateParams,
+    ) -> anyhow::Result<UpgradeDryRun> {
+        let state_root_before = params.state_root;
+        let app_version_before = params.app_version;
+
+        let mut state = FvmExecState::new(
+            store.clone(),
+            multi_engine,
+            self.block_height as ChainEpoch,
+            params,
+        )
+        .context("failed to create the execution state")?;
+
+        let new_app_version = self.execute(&mut state).context("upgrade failed")?;
+        let (state_root_after, _, _) = state.commit()?;
+
+        let changes = diff_state_trees(&store, &state_root_before, &state_root_after)?;
+
+        Ok(UpgradeDryRun {
+            state_root_before,
+            state_root_after,
+            app_version_before,
+            new_app_version,
+            changes,
+        })
+    }
+}
+
+/// Compare the actors in two state trees.
+pub fn diff_state_trees<DB: Blockstore>(
+    store: &DB,
+    before: &Cid,
+    after: &Cid,
+) -> anyhow::Result<BTreeMap<ActorID, ActorChange>> {
+    let mut changes = BTreeMap::new();
+    if before == after {
+        return Ok(changes);
+    }
+
+    let mut before = actors(store, before)?;
+    for (id, after) in actors(store, after)? {
+        match before.remove(&id) {
+            None => {
+                changes.insert(id, ActorChange::Added(after));
+            }
+            Some(before) if before != after => {
+                changes.insert(id, ActorChange::Changed { before, after });
+            }
+            Some(_) => {}
+        }
+    }
+    for (id, before) in before {
+        changes.insert(id, ActorChange::Removed(before));
+    }
+    Ok(changes)
+}
+
+fn actors<DB: Blockstore>(store: &DB, root: &Cid) -> anyhow::Result<BTreeMap<ActorID, ActorState>> {
+    let tree = StateTree::new_from_root(store, root)
+        .with_context(|| format!("failed to load state tree {root}"))?;
+
+    let mut actors = BTreeMap::new();
+    tree.for_each(|addr, state| {
+        let id = addr
+            .id()
+            .with_context(|| format!("state tree is not keyed by ID: {addr}"))?;
+        actors.insert(id, state.clone());
+        Ok(())
+    })?;
+    Ok(actors)
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use fvm::state_tree::{ActorState, StateTree};
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::state::StateTreeVersion;
+
+    use super::{diff_state_trees, ActorChange};
+    use crate::fvm::store::memory::MemoryBlockstore;
+
+    fn actor(balance: u64) -> ActorState {
+        ActorState::new(
+            Cid::default(),
+            Cid::default(),
+            TokenAmount::from_atto(balance),
+            0,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_diff_state_trees() {
+        let store = MemoryBlockstore::new();
+        let mut tree = StateTree::new(store.clone(), StateTreeVersion::V5).unwrap();
+        tree.set_actor(100, actor(1));
+        tree.set_actor(101, actor(2));
+        tree.set_actor(102, actor(3));
+        let before = tree.flush().unwrap();
+
+        assert!(diff_state_trees(&store, &before, &before)
+            .unwrap()
+            .is_empty());
+
+        tree.set_actor(101, actor(20));
+        tree.delete_actor(102);
+        tree.set_actor(103, actor(4));
+        let after = tree.flush().unwrap();
+
+        let changes = diff_state_trees(&store, &before, &after).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes.get(&101),
+            Some(&ActorChange::Changed {
+                before: actor(2),
+                after: actor(20)
+            })
+        );
+        assert_eq!(changes.get(&102), Some(&ActorChange::Removed(actor(3))));
+        assert_eq!(changes.get(&103), Some(&ActorChange::Added(actor(4))));
+    }
+}
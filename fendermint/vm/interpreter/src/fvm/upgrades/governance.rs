@@ -0,0 +1,85 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Upgrades activated by on-chain governance rather than at a height fixed in advance.
+//!
+//! The upgrade is still built into the binary and declared in the manifest, but instead of
+//! running at its block height, it runs at the height a governance contract on the subnet
+//! returns for it, once the proposal to activate it reached quorum. The block height in the
+//! manifest is the earliest height it can activate at.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use ethers::core::types as et;
+use fendermint_vm_actor_interface::eam::EthAddress;
+use fvm_ipld_blockstore::Blockstore;
+
+use super::Upgrade;
+use crate::fvm::state::fevm::{ContractCaller, MockProvider, NoRevert};
+use crate::fvm::state::snapshot::BlockHeight;
+use crate::fvm::state::FvmExecState;
+
+ethers::contract::abigen!(
+    UpgradeGovernanceContract,
+    r#"[
+        function activationHeight(bytes32 upgradeId) external view returns (uint64)
+    ]"#
+);
+
+/// The contract deciding when governed upgrades activate.
+///
+/// It is expected to return the height at which the upgrade with the given ID, which is its
+/// digest, activates, or zero if no such proposal has reached quorum.
+#[derive(Clone)]
+pub struct UpgradeGovernance<DB> {
+    caller: ContractCaller<DB, UpgradeGovernanceContract<MockProvider>, NoRevert>,
+}
+
+impl<DB> UpgradeGovernance<DB>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    pub fn new(contract: EthAddress) -> Self {
+        Self {
+            caller: ContractCaller::new(contract, UpgradeGovernanceContract::new),
+        }
+    }
+
+    /// The height at which the upgrade activates, if it was approved.
+    pub fn activation_height(
+        &self,
+        state: &mut FvmExecState<DB>,
+        upgrade: &Upgrade<DB>,
+    ) -> anyhow::Result<Option<BlockHeight>> {
+        match self
+            .caller
+            .try_call(state, |c| c.activation_height(upgrade.digest()))?
+        {
+            Ok(0) => Ok(None),
+            Ok(height) => Ok(Some(height)),
+            Err(e) => {
+                // A broken governance contract shouldn't halt the chain; it just can't
+                // activate anything until it's fixed.
+                tracing::warn!(
+                    error = ?e.error,
+                    exit_code = e.exit_code.value(),
+                    "failed to query the upgrade governance contract"
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<DB> FromStr for UpgradeGovernance<DB>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let addr = et::Address::from_str(s).context("invalid governance contract address")?;
+        Ok(Self::new(EthAddress::from(addr)))
+    }
+}
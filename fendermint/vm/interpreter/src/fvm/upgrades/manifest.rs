@@ -0,0 +1,442 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Upgrades declared in a manifest file rather than compiled into the binary.
+//!
+//! The migrations are still code, registered by name in a [MigrationRegistry]; the manifest
+//! picks them by name and says on which chain, at which height and with which parameters
+//! they run, along with the signatures approving each upgrade. Scheduling an upgrade on a
+//! new network, or moving it to another height, only needs a new manifest.
+//!
+//! The same upgrade can be declared for several networks in a single definition, e.g. to
+//! run it on a testnet first and on mainnet later, at a different height and with some of
+//! the parameters changed. Each node only runs the upgrades of the chain it is on.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use fendermint_crypto::{from_b64, Signature};
+use fendermint_vm_core::chainid;
+use fvm_ipld_blockstore::Blockstore;
+use serde::{Deserialize, Serialize};
+
+use super::{upgrade_digest, Migration, MigrationParams, Upgrade, UpgradeScheduler};
+use crate::fvm::state::snapshot::BlockHeight;
+
+/// Creates a migration from the parameters declared for it.
+pub type MigrationFactory<DB> = fn(&MigrationParams) -> anyhow::Result<Migration<DB>>;
+
+/// The migrations an [UpgradeManifest] can refer to, by name.
+pub struct MigrationRegistry<DB> {
+    factories: BTreeMap<String, MigrationFactory<DB>>,
+}
+
+impl<DB> Default for MigrationRegistry<DB> {
+    fn default() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+}
+
+impl<DB> MigrationRegistry<DB> {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: MigrationFactory<DB>,
+    ) -> anyhow::Result<()> {
+        let name = name.into();
+        if self.factories.contains_key(&name) {
+            bail!("migration {name} is already registered");
+        }
+        self.factories.insert(name, factory);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MigrationFactory<DB>> {
+        self.factories.get(name)
+    }
+}
+
+/// A list of upgrades to schedule, typically loaded from a JSON file at startup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeManifest {
+    #[serde(default)]
+    pub upgrades: Vec<UpgradeDefinition>,
+}
+
+/// A single upgrade in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeDefinition {
+    /// The name of the chain, as in the genesis.
+    pub chain_name: String,
+    pub block_height: BlockHeight,
+    #[serde(default)]
+    pub new_app_version: Option<u64>,
+    /// The name of the migration in the [MigrationRegistry].
+    pub migration: String,
+    #[serde(default)]
+    pub params: MigrationParams,
+    /// Activate the upgrade at the height the governance contract decides, but not before
+    /// the block height, instead of at the block height.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub governed: bool,
+    /// What the upgrade does, for operators; not covered by the approvals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Base64 encoded signatures of the approvers over the digest of the upgrade.
+    #[serde(default)]
+    pub approvals: Vec<String>,
+    /// The same upgrade on other networks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<NetworkUpgrade>,
+}
+
+/// Where and when an [UpgradeDefinition] runs on another network, and what is different
+/// about it there; it's a separate upgrade with its own digest and approvals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkUpgrade {
+    pub chain_name: String,
+    pub block_height: BlockHeight,
+    /// Replaces the app version of the upgrade, if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_app_version: Option<u64>,
+    /// Merged into the parameters of the upgrade, replacing those with the same name.
+    #[serde(default, skip_serializing_if = "MigrationParams::is_empty")]
+    pub params: MigrationParams,
+    #[serde(default)]
+    pub approvals: Vec<String>,
+}
+
+impl UpgradeManifest {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("failed to parse upgrade manifest")
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read upgrade manifest {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// All the upgrades, with those on other networks as separate definitions.
+    pub fn definitions(&self) -> Vec<UpgradeDefinition> {
+        self.upgrades
+            .iter()
+            .flat_map(|definition| {
+                let networks = definition.networks.iter().map(|n| definition.on_network(n));
+                std::iter::once(definition.on_network_only()).chain(networks)
+            })
+            .collect()
+    }
+
+    /// Add all the upgrades to the scheduler, failing on the first one which refers to
+    /// an unknown migration or isn't approved according to the policy of the scheduler.
+    ///
+    /// Upgrades on other networks which have no approvals yet are left out, so that the
+    /// manifest can already list them while the upgrade is tried on the first network.
+    pub fn schedule<DB>(
+        &self,
+        registry: &MigrationRegistry<DB>,
+        scheduler: &mut UpgradeScheduler<DB>,
+    ) -> anyhow::Result<()>
+    where
+        DB: Blockstore + 'static + Clone,
+    {
+        for definition in self.upgrades.iter() {
+            definition.schedule(registry, scheduler)?;
+
+            for network in definition.networks.iter() {
+                if network.approvals.is_empty() && scheduler.requires_approval() {
+                    tracing::info!(
+                        migration = %definition.migration,
+                        chain_name = %network.chain_name,
+                        block_height = network.block_height,
+                        "not scheduling upgrade until it is approved"
+                    );
+                    continue;
+                }
+                definition
+                    .on_network(network)
+                    .schedule(registry, scheduler)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl UpgradeDefinition {
+    /// The upgrade as it runs on another network.
+    pub fn on_network(&self, network: &NetworkUpgrade) -> Self {
+        let mut params = self.params.clone();
+        params.extend(network.params.clone());
+        Self {
+            chain_name: network.chain_name.clone(),
+            block_height: network.block_height,
+            new_app_version: network.new_app_version.or(self.new_app_version),
+            params,
+            approvals: network.approvals.clone(),
+            networks: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// The upgrade on the network it was first declared for.
+    fn on_network_only(&self) -> Self {
+        Self {
+            networks: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    fn schedule<DB>(
+        &self,
+        registry: &MigrationRegistry<DB>,
+        scheduler: &mut UpgradeScheduler<DB>,
+    ) -> anyhow::Result<()>
+    where
+        DB: Blockstore + 'static + Clone,
+    {
+        let upgrade = self.to_upgrade(registry)?;
+        let approvals = self.approvals()?;
+        let res = if self.governed {
+            scheduler.add_governed(upgrade, &approvals)
+        } else {
+            scheduler.add_approved(upgrade, &approvals)
+        };
+        res.with_context(|| {
+            format!(
+                "failed to schedule upgrade {} of {} at height {}",
+                self.migration, self.chain_name, self.block_height
+            )
+        })
+    }
+
+    pub fn to_upgrade<DB>(&self, registry: &MigrationRegistry<DB>) -> anyhow::Result<Upgrade<DB>>
+    where
+        DB: Blockstore + 'static + Clone,
+    {
+        let factory = registry
+            .get(&self.migration)
+            .ok_or_else(|| anyhow!("unknown migration: {}", self.migration))?;
+
+        let migration = factory(&self.params)
+            .with_context(|| format!("invalid parameters for migration {}", self.migration))?;
+
+        let upgrade = Upgrade::new_with_params(
+            chainid::from_str_hashed(&self.chain_name)?,
+            self.block_height,
+            self.new_app_version,
+            self.params.clone(),
+            migration,
+        );
+
+        Ok(match self.description {
+            Some(ref description) => upgrade.with_description(description),
+            None => upgrade.with_description(&self.migration),
+        })
+    }
+
+    /// The digest approvers sign, the same as that of the upgrade it schedules.
+    pub fn digest(&self) -> anyhow::Result<[u8; 32]> {
+        Ok(upgrade_digest(
+            chainid::from_str_hashed(&self.chain_name)?,
+            self.block_height,
+            self.new_app_version,
+            &self.params,
+        ))
+    }
+
+    fn approvals(&self) -> anyhow::Result<Vec<Signature>> {
+        self.approvals
+            .iter()
+            .map(|b64| {
+                let bz = from_b64(b64)?;
+                Signature::parse_standard_slice(&bz)
+                    .map_err(|e| anyhow!("invalid upgrade approval: {e}"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Context;
+    use fendermint_crypto::{to_b64, SecretKey};
+    use fendermint_vm_core::chainid;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{MigrationRegistry, UpgradeManifest};
+    use crate::fvm::state::FvmExecState;
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use crate::fvm::upgrades::{Migration, MigrationParams, UpgradePolicy, UpgradeScheduler};
+
+    fn noop(_: &MigrationParams) -> anyhow::Result<Migration<MemoryBlockstore>> {
+        Ok(Arc::new(|_: &mut FvmExecState<MemoryBlockstore>| Ok(())))
+    }
+
+    fn set_limit(params: &MigrationParams) -> anyhow::Result<Migration<MemoryBlockstore>> {
+        let limit: u64 = params
+            .get("limit")
+            .context("missing limit")?
+            .parse()
+            .context("invalid limit")?;
+        Ok(Arc::new(move |_: &mut FvmExecState<MemoryBlockstore>| {
+            anyhow::ensure!(limit > 0, "limit must be positive");
+            Ok(())
+        }))
+    }
+
+    fn registry() -> MigrationRegistry<MemoryBlockstore> {
+        let mut registry = MigrationRegistry::default();
+        registry.register("noop", noop).unwrap();
+        registry.register("set_limit", set_limit).unwrap();
+        assert!(registry.register("noop", noop).is_err());
+        registry
+    }
+
+    #[test]
+    fn test_schedule_manifest() {
+        let manifest = UpgradeManifest::from_json(
+            r#"{
+              "upgrades": [
+                { "chain_name": "mychain", "block_height": 10, "migration": "noop" },
+                {
+                  "chain_name": "mychain",
+                  "block_height": 20,
+                  "new_app_version": 2,
+                  "migration": "set_limit",
+                  "params": { "limit": "100" }
+                }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut scheduler = UpgradeScheduler::new();
+        manifest.schedule(&registry(), &mut scheduler).unwrap();
+
+        let mychain_id = chainid::from_str_hashed("mychain").unwrap();
+        assert!(scheduler.get(mychain_id, 10).is_some());
+        assert!(scheduler.get(mychain_id, 20).is_some());
+
+        // Unknown migrations and invalid parameters are rejected.
+        let mut bad = manifest.clone();
+        bad.upgrades[0].migration = "unknown".into();
+        assert!(bad
+            .schedule(&registry(), &mut UpgradeScheduler::new())
+            .is_err());
+
+        let mut bad = manifest;
+        bad.upgrades[1].params.insert("limit".into(), "lots".into());
+        assert!(bad
+            .schedule(&registry(), &mut UpgradeScheduler::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_schedule_on_networks() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let sk = SecretKey::random(&mut rng);
+        let policy = UpgradePolicy::new(vec![sk.public_key()], 1).unwrap();
+
+        let mut manifest = UpgradeManifest::from_json(
+            r#"{
+              "upgrades": [{
+                "chain_name": "testnet",
+                "block_height": 10,
+                "migration": "set_limit",
+                "params": { "limit": "100" },
+                "networks": [
+                  { "chain_name": "mainnet", "block_height": 50, "params": { "limit": "200" } }
+                ]
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let definitions = manifest.definitions();
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(definitions[1].chain_name, "mainnet");
+        assert_eq!(definitions[1].migration, "set_limit");
+        assert_eq!(definitions[1].params.get("limit").unwrap(), "200");
+        assert_ne!(
+            definitions[0].digest().unwrap(),
+            definitions[1].digest().unwrap()
+        );
+
+        let testnet_id = chainid::from_str_hashed("testnet").unwrap();
+        let mainnet_id = chainid::from_str_hashed("mainnet").unwrap();
+
+        // Without a policy both networks are scheduled.
+        let mut scheduler = UpgradeScheduler::new();
+        manifest.schedule(&registry(), &mut scheduler).unwrap();
+        assert!(scheduler.get(testnet_id, 10).is_some());
+        assert!(scheduler.get(mainnet_id, 50).is_some());
+        assert!(scheduler.get(mainnet_id, 10).is_none());
+
+        // With a policy, mainnet waits for its own approval.
+        let sign =
+            |d: &super::UpgradeDefinition| to_b64(&sk.sign(&d.digest().unwrap()).0.serialize());
+        manifest.upgrades[0].approvals = vec![sign(&definitions[0])];
+
+        let mut scheduler = UpgradeScheduler::new_with_policy(policy.clone());
+        manifest.schedule(&registry(), &mut scheduler).unwrap();
+        assert!(scheduler.get(testnet_id, 10).is_some());
+        assert!(scheduler.get(mainnet_id, 50).is_none());
+
+        // The testnet approval doesn't carry over.
+        manifest.upgrades[0].networks[0].approvals = vec![sign(&definitions[0])];
+        let mut scheduler = UpgradeScheduler::new_with_policy(policy.clone());
+        assert!(manifest.schedule(&registry(), &mut scheduler).is_err());
+
+        manifest.upgrades[0].networks[0].approvals = vec![sign(&definitions[1])];
+        let mut scheduler = UpgradeScheduler::new_with_policy(policy);
+        manifest.schedule(&registry(), &mut scheduler).unwrap();
+        assert!(scheduler.get(mainnet_id, 50).is_some());
+    }
+
+    #[test]
+    fn test_manifest_approvals_cover_params() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let sk = SecretKey::random(&mut rng);
+        let policy = UpgradePolicy::new(vec![sk.public_key()], 1).unwrap();
+
+        let mut manifest = UpgradeManifest::from_json(
+            r#"{
+              "upgrades": [{
+                "chain_name": "mychain",
+                "block_height": 20,
+                "migration": "set_limit",
+                "params": { "limit": "100" }
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let digest = manifest.upgrades[0].digest().unwrap();
+        assert_eq!(
+            manifest.upgrades[0]
+                .to_upgrade(&registry())
+                .unwrap()
+                .digest(),
+            digest
+        );
+        let approval = to_b64(&sk.sign(&digest).0.serialize());
+        manifest.upgrades[0].approvals = vec![approval];
+
+        let mut scheduler = UpgradeScheduler::new_with_policy(policy.clone());
+        manifest.schedule(&registry(), &mut scheduler).unwrap();
+
+        // The approval doesn't carry over to different parameters.
+        manifest.upgrades[0]
+            .params
+            .insert("limit".into(), "200".into());
+        let mut scheduler = UpgradeScheduler::new_with_policy(policy);
+        assert!(manifest.schedule(&registry(), &mut scheduler).is_err());
+    }
+}
@@ -0,0 +1,796 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use fendermint_crypto::{PublicKey, Signature};
+use fendermint_vm_core::chainid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::chainid::ChainID;
+use std::collections::btree_map::Entry::{Occupied, Vacant};
+
+use super::state::{snapshot::BlockHeight, FvmExecState};
+
+pub mod actors;
+mod dry_run;
+mod governance;
+mod manifest;
+mod power_table;
+mod replay;
+mod validator_changes;
+
+pub use dry_run::{diff_state_trees, ActorChange, UpgradeDryRun};
+pub use governance::UpgradeGovernance;
+pub use manifest::{
+    MigrationFactory, MigrationRegistry, NetworkUpgrade, UpgradeDefinition, UpgradeManifest,
+};
+pub use power_table::{PowerTableSnapshot, ValidatorPower};
+pub use replay::{EventReplay, EventSource};
+pub use validator_changes::{BackfilledChange, ValidatorChangesBackfill};
+
+#[derive(PartialEq, Eq, Clone)]
+struct UpgradeKey(ChainID, BlockHeight);
+
+impl PartialOrd for UpgradeKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UpgradeKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self.0 == other.0 {
+            self.1.cmp(&other.1)
+        } else {
+            let chain_id: u64 = self.0.into();
+            chain_id.cmp(&other.0.into())
+        }
+    }
+}
+
+/// a function type for migration
+// TODO: Add missing parameters
+pub type MigrationFunc<DB> = fn(state: &mut FvmExecState<DB>) -> anyhow::Result<()>;
+
+/// A migration which can capture the parameters it was created with.
+pub type Migration<DB> = Arc<dyn Fn(&mut FvmExecState<DB>) -> anyhow::Result<()> + Send + Sync>;
+
+/// Parameters of a migration, declared along with the upgrade, e.g. in an [UpgradeManifest].
+pub type MigrationParams = BTreeMap<String, String>;
+
+/// The part of a migration split across several blocks to execute, see [Upgrade::new_chunked].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationChunk {
+    /// Zero based; chunk `index` runs at the height of the upgrade plus `index`.
+    pub index: u64,
+    pub count: u64,
+}
+
+/// A migration function which does one chunk of the work at a time.
+pub type ChunkedMigrationFunc<DB> =
+    fn(state: &mut FvmExecState<DB>, chunk: MigrationChunk) -> anyhow::Result<()>;
+
+type ChunkedMigration<DB> =
+    Arc<dyn Fn(&mut FvmExecState<DB>, MigrationChunk) -> anyhow::Result<()> + Send + Sync>;
+
+/// An invariant checked on the state after the migration, see [Upgrade::with_check].
+pub type UpgradeCheck<DB> = Arc<dyn Fn(&mut FvmExecState<DB>) -> anyhow::Result<()> + Send + Sync>;
+
+/// Upgrade represents a single upgrade to be executed at a given height
+#[derive(Clone)]
+pub struct Upgrade<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    /// the chain_id should match the chain_id from the network configuration
+    chain_id: ChainID,
+    /// the block height at which the upgrade should be executed
+    block_height: BlockHeight,
+    /// the application version after the upgrade (or None if not affected)
+    new_app_version: Option<u64>,
+    /// the migration function to be executed, one chunk at each block
+    migration: ChunkedMigration<DB>,
+    /// the number of consecutive blocks the migration is split across
+    chunks: u64,
+    /// the parameters the migration was created with, which are approved along with it
+    params: MigrationParams,
+    /// what the upgrade does, for operators
+    description: Option<String>,
+    /// invariants verified after the migration, by name
+    checks: Vec<(String, UpgradeCheck<DB>)>,
+}
+
+impl<DB> Upgrade<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    pub fn new(
+        chain_name: impl ToString,
+        block_height: BlockHeight,
+        new_app_version: Option<u64>,
+        migration: MigrationFunc<DB>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::new_by_id(
+            chainid::from_str_hashed(&chain_name.to_string())?,
+            block_height,
+            new_app_version,
+            migration,
+        ))
+    }
+
+    pub fn new_by_id(
+        chain_id: ChainID,
+        block_height: BlockHeight,
+        new_app_version: Option<u64>,
+        migration: MigrationFunc<DB>,
+    ) -> Self {
+        Self::new_with_params(
+            chain_id,
+            block_height,
+            new_app_version,
+            MigrationParams::new(),
+            Arc::new(migration),
+        )
+    }
+
+    /// Create an upgrade whose migration was built from parameters.
+    pub fn new_with_params(
+        chain_id: ChainID,
+        block_height: BlockHeight,
+        new_app_version: Option<u64>,
+        params: MigrationParams,
+        migration: Migration<DB>,
+    ) -> Self {
+        Self {
+            chain_id,
+            block_height,
+            new_app_version,
+            migration: Arc::new(move |state: &mut FvmExecState<DB>, _: MigrationChunk| {
+                migration(state)
+            }),
+            chunks: 1,
+            params,
+            description: None,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Create an upgrade whose migration is too long to run in a single block, so it is
+    /// split into a number of chunks executed at consecutive heights, starting with the
+    /// block height of the upgrade. The app version changes after the last chunk.
+    pub fn new_chunked(
+        chain_name: impl ToString,
+        block_height: BlockHeight,
+        new_app_version: Option<u64>,
+        chunks: u64,
+        migration: ChunkedMigrationFunc<DB>,
+    ) -> anyhow::Result<Self> {
+        if chunks == 0 {
+            bail!("an upgrade needs at least one chunk");
+        }
+        Ok(Self {
+            chain_id: chainid::from_str_hashed(&chain_name.to_string())?,
+            block_height,
+            new_app_version,
+            migration: Arc::new(migration),
+            chunks,
+            params: MigrationParams::new(),
+            description: None,
+            checks: Vec::new(),
+        })
+    }
+
+    /// Describe what the upgrade does; not part of what approvers sign.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Verify an invariant right after the migration, e.g. that the circulating supply
+    /// didn't change. If it doesn't hold, the upgrade fails, halting the node, instead of
+    /// carrying on with a broken state. Checks should only read the state.
+    pub fn with_check<F>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&mut FvmExecState<DB>) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.checks.push((name.into(), Arc::new(check)));
+        self
+    }
+
+    pub fn block_height(&self) -> BlockHeight {
+        self.block_height
+    }
+
+    pub fn new_app_version(&self) -> Option<u64> {
+        self.new_app_version
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn chunks(&self) -> u64 {
+        self.chunks
+    }
+
+    /// Execute all chunks of the migration at once.
+    pub fn execute(&self, state: &mut FvmExecState<DB>) -> anyhow::Result<Option<u64>> {
+        let mut res = None;
+        for index in 0..self.chunks {
+            res = self.execute_chunk(state, index)?;
+        }
+        Ok(res)
+    }
+
+    /// Execute a chunk of the migration; the new app version is only returned after the last.
+    pub fn execute_chunk(
+        &self,
+        state: &mut FvmExecState<DB>,
+        index: u64,
+    ) -> anyhow::Result<Option<u64>> {
+        let count = self.chunks;
+        if index >= count {
+            bail!("upgrade has no chunk {index}, only {count}");
+        }
+        (self.migration)(state, MigrationChunk { index, count })?;
+
+        if index + 1 < count {
+            return Ok(None);
+        }
+        self.verify(state)?;
+
+        Ok(self.new_app_version)
+    }
+
+    /// Run the checks on the state after the migration.
+    fn verify(&self, state: &mut FvmExecState<DB>) -> anyhow::Result<()> {
+        for (name, check) in self.checks.iter() {
+            if let Err(e) = check(state) {
+                tracing::error!(
+                    chain_id = ?self.chain_id,
+                    height = self.block_height,
+                    check = %name,
+                    error = %format!("{e:#}"),
+                    "UPGRADE INVARIANT VIOLATED"
+                );
+                return Err(e.context(format!("upgrade invariant violated: {name}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// The digest approvers sign to authorize this upgrade.
+    ///
+    /// The migration itself is compiled into the binary, so what is being approved
+    /// is running it on a given chain, at a given height, with the given parameters,
+    /// and the resulting app version.
+    pub fn digest(&self) -> [u8; 32] {
+        if self.chunks > 1 {
+            // Spreading the migration over more blocks is a different upgrade.
+            let mut params = self.params.clone();
+            params.insert(CHUNKS_PARAM.into(), self.chunks.to_string());
+            return upgrade_digest(
+                self.chain_id,
+                self.block_height,
+                self.new_app_version,
+                &params,
+            );
+        }
+        upgrade_digest(
+            self.chain_id,
+            self.block_height,
+            self.new_app_version,
+            &self.params,
+        )
+    }
+}
+
+/// The reserved parameter under which the number of chunks is covered by the digest.
+const CHUNKS_PARAM: &str = "@chunks";
+
+/// The digest of an upgrade, see [Upgrade::digest].
+pub fn upgrade_digest(
+    chain_id: ChainID,
+    block_height: BlockHeight,
+    new_app_version: Option<u64>,
+    params: &MigrationParams,
+) -> [u8; 32] {
+    let chain_id: u64 = chain_id.into();
+    // Upgrades without parameters keep the digest they had before parameters existed.
+    let bz = if params.is_empty() {
+        fvm_ipld_encoding::to_vec(&(chain_id, block_height, new_app_version))
+    } else {
+        fvm_ipld_encoding::to_vec(&(chain_id, block_height, new_app_version, params))
+    }
+    .expect("tuple of integers and strings can be serialized");
+    ethers::utils::keccak256(bz)
+}
+
+/// The set of keys which have to sign off on an upgrade before it can be scheduled,
+/// for example 2-of-3 core maintainers.
+#[derive(Clone, Debug)]
+pub struct UpgradePolicy {
+    approvers: Vec<PublicKey>,
+    threshold: usize,
+}
+
+impl UpgradePolicy {
+    pub fn new(approvers: Vec<PublicKey>, threshold: usize) -> anyhow::Result<Self> {
+        if threshold == 0 {
+            bail!("upgrade approval threshold must be positive");
+        }
+        if threshold > approvers.len() {
+            bail!(
+                "upgrade approval threshold {threshold} exceeds the number of approvers {}",
+                approvers.len()
+            );
+        }
+        Ok(Self {
+            approvers,
+            threshold,
+        })
+    }
+
+    /// Check that enough distinct approvers signed the digest.
+    pub fn verify(&self, digest: &[u8; 32], approvals: &[Signature]) -> anyhow::Result<()> {
+        let message = fendermint_crypto::Message::parse(digest);
+        let mut approved_by = HashSet::new();
+
+        for sig in approvals {
+            if let Some(idx) = self
+                .approvers
+                .iter()
+                .position(|pk| fendermint_crypto::verify(&message, sig, pk))
+            {
+                approved_by.insert(idx);
+            }
+        }
+
+        if approved_by.len() < self.threshold {
+            return Err(anyhow!(
+                "upgrade approved by {} of the required {} approvers",
+                approved_by.len(),
+                self.threshold
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// UpgradeScheduler represents a list of upgrades to be executed at given heights
+/// During each block height we check if there is an upgrade scheduled at that
+/// height, and if so the migration for that upgrade is performed.
+#[derive(Clone)]
+pub struct UpgradeScheduler<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    upgrades: BTreeMap<UpgradeKey, Upgrade<DB>>,
+    /// When set, only upgrades signed by the approvers can be added.
+    policy: Option<UpgradePolicy>,
+    /// Upgrades which run at the height the governance contract decides, at the
+    /// earliest at their own block height.
+    governed: Vec<Upgrade<DB>>,
+    governance: Option<UpgradeGovernance<DB>>,
+    /// Run upgrades again even if the state says they have already been executed.
+    allow_rerun: bool,
+}
+
+impl<DB> Default for UpgradeScheduler<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<DB> UpgradeScheduler<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            upgrades: BTreeMap::new(),
+            policy: None,
+            governed: Vec::new(),
+            governance: None,
+            allow_rerun: false,
+        }
+    }
+
+    /// Create a scheduler which only accepts upgrades approved according to the policy.
+    pub fn new_with_policy(policy: UpgradePolicy) -> Self {
+        Self {
+            upgrades: BTreeMap::new(),
+            policy: Some(policy),
+            governed: Vec::new(),
+            governance: None,
+            allow_rerun: false,
+        }
+    }
+
+    /// Let the governance contract activate the upgrades added with [Self::add_governed].
+    pub fn with_governance(mut self, governance: UpgradeGovernance<DB>) -> Self {
+        self.governance = Some(governance);
+        self
+    }
+
+    /// Let upgrades which already succeeded on a state run again, e.g. on development
+    /// networks where the same upgrade is tried repeatedly.
+    pub fn with_rerun(mut self, allow_rerun: bool) -> Self {
+        self.allow_rerun = allow_rerun;
+        self
+    }
+}
+
+impl<DB> UpgradeScheduler<DB>
+where
+    DB: Blockstore + 'static + Clone,
+{
+    // add a new upgrade to the schedule
+    pub fn add(&mut self, upgrade: Upgrade<DB>) -> anyhow::Result<()> {
+        self.add_approved(upgrade, &[])
+    }
+
+    // add a new upgrade to the schedule, along with the signatures of the approvers
+    pub fn add_approved(
+        &mut self,
+        upgrade: Upgrade<DB>,
+        approvals: &[Signature],
+    ) -> anyhow::Result<()> {
+        self.authorize(&upgrade, approvals)?;
+
+        // an upgrade at the same height is reported as a duplicate below
+        let start = upgrade.block_height;
+        if let Some(other) = self
+            .overlapping(&upgrade)
+            .find(|other| other.block_height != start)
+        {
+            bail!(
+                "Upgrade at height {start} overlaps with the upgrade at height {}",
+                other.block_height
+            );
+        }
+
+        match self
+            .upgrades
+            .entry(UpgradeKey(upgrade.chain_id, upgrade.block_height))
+        {
+            Vacant(entry) => {
+                entry.insert(upgrade);
+                Ok(())
+            }
+            Occupied(_) => {
+                bail!("Upgrade already exists");
+            }
+        }
+    }
+
+    // add a new upgrade to be activated by the governance contract
+    pub fn add_governed(
+        &mut self,
+        upgrade: Upgrade<DB>,
+        approvals: &[Signature],
+    ) -> anyhow::Result<()> {
+        if self.governance.is_none() {
+            bail!("no upgrade governance contract is configured");
+        }
+        self.authorize(&upgrade, approvals)?;
+
+        if self.governed.iter().any(|u| u.digest() == upgrade.digest()) {
+            bail!("Upgrade already exists");
+        }
+        self.governed.push(upgrade);
+        Ok(())
+    }
+
+    fn authorize(&self, upgrade: &Upgrade<DB>, approvals: &[Signature]) -> anyhow::Result<()> {
+        if let Some(ref policy) = self.policy {
+            policy.verify(&upgrade.digest(), approvals).map_err(|e| {
+                anyhow!(
+                    "Upgrade at height {} is not authorized: {e}",
+                    upgrade.block_height
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    // check if the governance contract activated an upgrade at the current height of the state
+    pub fn get_governed(
+        &self,
+        state: &mut FvmExecState<DB>,
+    ) -> anyhow::Result<Option<Upgrade<DB>>> {
+        let governance = match self.governance {
+            Some(ref governance) => governance,
+            None => return Ok(None),
+        };
+        let chain_id = state.chain_id();
+        let height = state.block_height() as BlockHeight;
+
+        for upgrade in self.governed.iter() {
+            if upgrade.chain_id != chain_id || upgrade.block_height > height {
+                continue;
+            }
+            if governance.activation_height(state, upgrade)? == Some(height) {
+                // Run it as if it had been scheduled at this height; the digest, which is
+                // what the governance contract knows it by, is not needed any more.
+                let mut upgrade = upgrade.clone();
+                upgrade.block_height = height;
+
+                // Governance can't know which heights the chunks of other upgrades take, so
+                // an activation clashing with them is rejected rather than halting the chain.
+                // Every node rejects it the same way; it takes a new proposal to activate it.
+                if let Some(other) = self.overlapping(&upgrade).next() {
+                    tracing::warn!(
+                        ?chain_id,
+                        height,
+                        other_height = other.block_height,
+                        "Rejecting a governed upgrade which overlaps with a scheduled upgrade"
+                    );
+                    continue;
+                }
+                if !state.upgrades_in_progress().is_empty() {
+                    tracing::warn!(
+                        ?chain_id,
+                        height,
+                        "Rejecting a governed upgrade activated while another upgrade is in progress"
+                    );
+                    continue;
+                }
+                return Ok(Some(upgrade));
+            }
+        }
+        Ok(None)
+    }
+
+    // the height at which the upgrade has already been executed on the state, in which case
+    // it must not run again, unless reruns are allowed
+    pub fn executed_at(&self, state: &FvmExecState<DB>, upgrade: &Upgrade<DB>) -> Option<u64> {
+        if self.allow_rerun {
+            return None;
+        }
+        state.upgrade_executed_at(&upgrade.digest())
+    }
+
+    // whether upgrades have to be approved before they can be added
+    pub fn requires_approval(&self) -> bool {
+        self.policy.is_some()
+    }
+
+    // check if an upgrade split across several blocks is in progress in the state, and if
+    // so, return it along with the index of the chunk to execute at the current height
+    pub fn get_in_progress(
+        &self,
+        state: &FvmExecState<DB>,
+    ) -> anyhow::Result<Option<(Upgrade<DB>, u64)>> {
+        let chain_id = state.chain_id();
+        let height = state.block_height() as BlockHeight;
+
+        for (digest, chunks_done) in state.upgrades_in_progress() {
+            // Chunks run at consecutive heights, so the upgrade started this many blocks ago;
+            // governed upgrades are recognised by their digest at the height they started.
+            let started = height.saturating_sub(*chunks_done);
+            let upgrade = self
+                .get(chain_id, started)
+                .into_iter()
+                .chain(self.governed_upgrades(chain_id))
+                .map(|upgrade| {
+                    let mut upgrade = upgrade.clone();
+                    upgrade.block_height = started;
+                    upgrade
+                })
+                .find(|upgrade| hex::encode(upgrade.digest()) == *digest);
+
+            match upgrade {
+                Some(upgrade) => return Ok(Some((upgrade, *chunks_done))),
+                None => bail!(
+                    "upgrade {digest} is in progress with {chunks_done} chunks done, but it's not scheduled at height {started}"
+                ),
+            }
+        }
+        Ok(None)
+    }
+
+    // check if there is an upgrade scheduled for the given chain_id at a given height
+    pub fn get(&self, chain_id: ChainID, height: BlockHeight) -> Option<&Upgrade<DB>> {
+        self.upgrades.get(&UpgradeKey(chain_id, height))
+    }
+
+    // the scheduled upgrades which run at any of the heights the chunks of the upgrade take
+    fn overlapping<'a>(&'a self, upgrade: &Upgrade<DB>) -> impl Iterator<Item = &'a Upgrade<DB>> {
+        let (start, end) = (upgrade.block_height, upgrade.block_height + upgrade.chunks);
+        self.upgrades(upgrade.chain_id).filter(move |other| {
+            other.block_height < end && start < other.block_height + other.chunks
+        })
+    }
+
+    // the upgrades scheduled for the given chain_id, in order of height
+    pub fn upgrades(&self, chain_id: ChainID) -> impl Iterator<Item = &Upgrade<DB>> {
+        self.upgrades
+            .iter()
+            .filter(move |(UpgradeKey(id, _), _)| *id == chain_id)
+            .map(|(_, upgrade)| upgrade)
+    }
+
+    // the first upgrade scheduled for the given chain_id at or after the given height
+    pub fn next_upgrade(&self, chain_id: ChainID, height: BlockHeight) -> Option<&Upgrade<DB>> {
+        self.upgrades(chain_id).find(|u| u.block_height >= height)
+    }
+
+    // the upgrades for the given chain_id which the governance contract can activate
+    pub fn governed_upgrades(&self, chain_id: ChainID) -> impl Iterator<Item = &Upgrade<DB>> {
+        self.governed.iter().filter(move |u| u.chain_id == chain_id)
+    }
+
+    // the chain_id and height of all the scheduled upgrades
+    pub fn upgrade_heights(&self) -> Vec<(ChainID, BlockHeight)> {
+        self.upgrades
+            .keys()
+            .map(|UpgradeKey(chain_id, height)| (*chain_id, *height))
+            .collect()
+    }
+}
+
+#[test]
+fn test_validate_upgrade_schedule() {
+    use crate::fvm::store::memory::MemoryBlockstore;
+
+    let mut upgrade_scheduler: UpgradeScheduler<MemoryBlockstore> = UpgradeScheduler::new();
+
+    let upgrade = Upgrade::new("mychain", 10, None, |_state| Ok(())).unwrap();
+    upgrade_scheduler.add(upgrade).unwrap();
+
+    let upgrade = Upgrade::new("mychain", 20, None, |_state| Ok(())).unwrap();
+    upgrade_scheduler.add(upgrade).unwrap();
+
+    // adding an upgrade with the same chain_id and height should fail
+    let upgrade = Upgrade::new("mychain", 20, None, |_state| Ok(())).unwrap();
+    let res = upgrade_scheduler.add(upgrade);
+    assert!(res.is_err());
+
+    let mychain_id = chainid::from_str_hashed("mychain").unwrap();
+    let otherhain_id = chainid::from_str_hashed("otherchain").unwrap();
+
+    assert!(upgrade_scheduler.get(mychain_id, 9).is_none());
+    assert!(upgrade_scheduler.get(mychain_id, 10).is_some());
+    assert!(upgrade_scheduler.get(otherhain_id, 10).is_none());
+
+    let next_height = |height| {
+        upgrade_scheduler
+            .next_upgrade(mychain_id, height)
+            .map(|u| u.block_height())
+    };
+    assert_eq!(next_height(5), Some(10));
+    assert_eq!(next_height(10), Some(10));
+    assert_eq!(next_height(11), Some(20));
+    assert_eq!(next_height(21), None);
+    assert!(upgrade_scheduler.next_upgrade(otherhain_id, 5).is_none());
+}
+
+#[test]
+fn test_upgrade_approval_policy() {
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use fendermint_crypto::SecretKey;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let keys = (0..3)
+        .map(|_| SecretKey::random(&mut rng))
+        .collect::<Vec<_>>();
+    let outsider = SecretKey::random(&mut rng);
+
+    let policy = UpgradePolicy::new(keys.iter().map(|sk| sk.public_key()).collect(), 2).unwrap();
+    assert!(UpgradePolicy::new(Vec::new(), 1).is_err());
+
+    let mut upgrade_scheduler: UpgradeScheduler<MemoryBlockstore> =
+        UpgradeScheduler::new_with_policy(policy);
+
+    let upgrade = Upgrade::new("mychain", 10, Some(1), |_state| Ok(())).unwrap();
+    let digest = upgrade.digest();
+    let sign = |sk: &SecretKey| sk.sign(&digest).0;
+
+    // unsigned upgrades are rejected
+    assert!(upgrade_scheduler.add(upgrade.clone()).is_err());
+    // the same approver signing twice doesn't count twice
+    assert!(upgrade_scheduler
+        .add_approved(upgrade.clone(), &[sign(&keys[0]), sign(&keys[0])])
+        .is_err());
+    // signatures from outside the approver set are ignored
+    assert!(upgrade_scheduler
+        .add_approved(upgrade.clone(), &[sign(&keys[0]), sign(&outsider)])
+        .is_err());
+    // signatures over a different upgrade are ignored
+    let other = Upgrade::new("mychain", 11, Some(1), |_state| Ok(())).unwrap();
+    assert!(upgrade_scheduler
+        .add_approved(other, &[sign(&keys[0]), sign(&keys[1])])
+        .is_err());
+
+    upgrade_scheduler
+        .add_approved(upgrade, &[sign(&keys[2]), sign(&keys[0])])
+        .unwrap();
+
+    let mychain_id = chainid::from_str_hashed("mychain").unwrap();
+    assert!(upgrade_scheduler.get(mychain_id, 10).is_some());
+}
+
+#[test]
+fn test_governed_upgrades() {
+    use crate::fvm::store::memory::MemoryBlockstore;
+    use fendermint_vm_actor_interface::eam::EthAddress;
+
+    let upgrade: Upgrade<MemoryBlockstore> =
+        Upgrade::new("mychain", 10, Some(1), |_state| Ok(())).unwrap();
+
+    // governed upgrades need a governance contract
+    let mut upgrade_scheduler = UpgradeScheduler::new();
+    assert!(upgrade_scheduler
+        .add_governed(upgrade.clone(), &[])
+        .is_err());
+
+    let mut upgrade_scheduler =
+        UpgradeScheduler::new().with_governance(UpgradeGovernance::new(EthAddress::from_id(100)));
+    upgrade_scheduler
+        .add_governed(upgrade.clone(), &[])
+        .unwrap();
+    assert!(upgrade_scheduler.add_governed(upgrade, &[]).is_err());
+
+    let mychain_id = chainid::from_str_hashed("mychain").unwrap();
+    let otherchain_id = chainid::from_str_hashed("otherchain").unwrap();
+
+    // they are not scheduled at their height
+    assert!(upgrade_scheduler.get(mychain_id, 10).is_none());
+    assert_eq!(upgrade_scheduler.governed_upgrades(mychain_id).count(), 1);
+    assert_eq!(
+        upgrade_scheduler.governed_upgrades(otherchain_id).count(),
+        0
+    );
+}
+
+#[test]
+fn test_chunked_upgrades() {
+    use crate::fvm::store::memory::MemoryBlockstore;
+
+    assert!(
+        Upgrade::<MemoryBlockstore>::new_chunked("mychain", 10, None, 0, |_state, _chunk| Ok(()))
+            .is_err()
+    );
+
+    let upgrade: Upgrade<MemoryBlockstore> =
+        Upgrade::new_chunked("mychain", 10, Some(1), 5, |_state, _chunk| Ok(())).unwrap();
+    assert_eq!(upgrade.chunks(), 5);
+
+    // the number of chunks is part of what is approved
+    let single: Upgrade<MemoryBlockstore> =
+        Upgrade::new("mychain", 10, Some(1), |_state| Ok(())).unwrap();
+    assert_ne!(upgrade.digest(), single.digest());
+
+    let mut upgrade_scheduler = UpgradeScheduler::new();
+    upgrade_scheduler.add(upgrade).unwrap();
+
+    // heights 10 to 14 are taken by the chunks
+    for height in [8, 12, 14] {
+        let upgrade = Upgrade::new_chunked("mychain", height, None, 3, |_state, _chunk| Ok(()));
+        assert!(upgrade_scheduler.add(upgrade.unwrap()).is_err());
+    }
+    let upgrade = Upgrade::new("mychain", 15, None, |_state| Ok(())).unwrap();
+    upgrade_scheduler.add(upgrade).unwrap();
+    let upgrade = Upgrade::new_chunked("mychain", 7, None, 3, |_state, _chunk| Ok(())).unwrap();
+    upgrade_scheduler.add(upgrade).unwrap();
+
+    // governed upgrades activated at a height taken by the chunks are rejected
+    let overlapping = |height| {
+        let upgrade = Upgrade::new("mychain", height, None, |_state| Ok(())).unwrap();
+        upgrade_scheduler
+            .overlapping(&upgrade)
+            .map(|u| u.block_height())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(overlapping(9), vec![7]);
+    assert_eq!(overlapping(10), vec![10]);
+    assert_eq!(overlapping(14), vec![10]);
+    assert!(overlapping(16).is_empty());
+}
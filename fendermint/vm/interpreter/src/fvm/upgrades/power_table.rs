@@ -0,0 +1,226 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Importing a validator power table from a file as part of an upgrade, to repair the
+//! power table of a subnet, e.g. after it missed changes from its parent.
+//!
+//! The power of each validator is set as federated power, with the same change requests
+//! the gateway receives from the parent with the top-down finality. Like those, they take
+//! effect at the next bottom-up checkpoint, which is also when CometBFT learns about them.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use ethers::abi::{encode, Token};
+use ethers::core::types as et;
+use fendermint_crypto::{from_b64, to_b64, PublicKey, SecretKey, Signature};
+use fendermint_vm_actor_interface::eam::EthAddress;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::address::Address;
+use ipc_api::staking::{StakingChange, StakingChangeRequest, StakingOperation};
+use serde::{Deserialize, Serialize};
+
+use crate::fvm::state::ipc::GatewayCaller;
+use crate::fvm::state::FvmExecState;
+
+/// The power table to import, signed by whoever put it together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerTableSnapshot {
+    /// The configuration number the gateway assigns to the next validator change;
+    /// the changes are stored with consecutive numbers from here.
+    pub next_configuration_number: u64,
+    pub validators: Vec<ValidatorPower>,
+    /// Base64 encoded signature over [PowerTableSnapshot::digest].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorPower {
+    /// Hex encoded uncompressed secp256k1 public key, which also gives the address.
+    pub public_key: String,
+    /// Weight as the gateway counts it, e.g. as set by `ipc-cli subnet set-federated-power`.
+    pub power: u128,
+}
+
+impl PowerTableSnapshot {
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read power table {}", path.display()))?;
+        serde_json::from_str(&json).context("failed to parse power table")
+    }
+
+    /// The digest the signature covers: everything but the signature.
+    pub fn digest(&self) -> anyhow::Result<[u8; 32]> {
+        let validators = self
+            .validators
+            .iter()
+            .map(|v| Ok((v.public_key()?.serialize().to_vec(), v.power.to_string())))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let bz = fvm_ipld_encoding::to_vec(&(self.next_configuration_number, validators))?;
+        Ok(ethers::utils::keccak256(bz))
+    }
+
+    pub fn sign(&mut self, sk: &SecretKey) -> anyhow::Result<()> {
+        let (sig, _) = sk.sign(&self.digest()?);
+        self.signature = Some(to_b64(&sig.serialize()));
+        Ok(())
+    }
+
+    /// Check that the table was signed by the given key and lists each validator once.
+    pub fn verify(&self, signer: &PublicKey) -> anyhow::Result<()> {
+        let sig = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| anyhow!("power table is not signed"))?;
+        let sig = Signature::parse_standard_slice(&from_b64(sig)?)
+            .map_err(|e| anyhow!("invalid power table signature: {e}"))?;
+
+        let message = fendermint_crypto::Message::parse(&self.digest()?);
+        if !fendermint_crypto::verify(&message, &sig, signer) {
+            bail!("power table is not signed by the expected key");
+        }
+
+        let mut seen = BTreeSet::new();
+        for v in self.validators.iter() {
+            if !seen.insert(v.public_key()?.serialize()) {
+                bail!("validator {} is listed more than once", v.public_key);
+            }
+        }
+        Ok(())
+    }
+
+    /// The changes setting the power of every validator in the table, and zeroing the power
+    /// of the current validators missing from it.
+    pub fn change_requests(
+        &self,
+        current: &[PublicKey],
+    ) -> anyhow::Result<Vec<StakingChangeRequest>> {
+        let mut powers = Vec::new();
+        for v in self.validators.iter() {
+            powers.push((v.public_key()?, v.power));
+        }
+        for pk in current {
+            if !powers.iter().any(|(k, _)| k == pk) {
+                powers.push((*pk, 0));
+            }
+        }
+
+        let changes = powers
+            .into_iter()
+            .zip(self.next_configuration_number..)
+            .map(|((pk, power), configuration_number)| StakingChangeRequest {
+                configuration_number,
+                change: StakingChange {
+                    op: StakingOperation::SetFederatedPower,
+                    // The metadata of a validator is its public key.
+                    payload: encode(&[
+                        Token::Bytes(pk.serialize().to_vec()),
+                        Token::Uint(et::U256::from(power)),
+                    ]),
+                    validator: Address::from(EthAddress::from(pk)),
+                },
+            })
+            .collect();
+
+        Ok(changes)
+    }
+
+    /// Store the changes in the gateway, to be applied at the next checkpoint.
+    pub fn apply<DB>(&self, state: &mut FvmExecState<DB>) -> anyhow::Result<usize>
+    where
+        DB: Blockstore + Clone + 'static,
+    {
+        let gateway = GatewayCaller::default();
+        let (_, power_table) = gateway
+            .current_power_table(state)
+            .context("failed to get the current power table")?;
+        let current = power_table
+            .into_iter()
+            .map(|v| v.public_key.0)
+            .collect::<Vec<_>>();
+
+        let changes = self.change_requests(&current)?;
+        let count = changes.len();
+        gateway
+            .store_validator_changes(state, changes)
+            .context("failed to store validator changes")?;
+
+        tracing::info!(
+            count,
+            next_configuration_number = self.next_configuration_number,
+            "stored power table changes"
+        );
+        Ok(count)
+    }
+}
+
+impl ValidatorPower {
+    pub fn public_key(&self) -> anyhow::Result<PublicKey> {
+        let bz = hex::decode(self.public_key.trim_start_matches("0x"))
+            .with_context(|| format!("invalid public key: {}", self.public_key))?;
+        PublicKey::parse_slice(&bz, None)
+            .map_err(|e| anyhow!("invalid public key {}: {e}", self.public_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_crypto::SecretKey;
+    use ipc_api::staking::StakingOperation;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{PowerTableSnapshot, ValidatorPower};
+
+    #[test]
+    fn test_power_table_snapshot() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let signer = SecretKey::random(&mut rng);
+        let v1 = SecretKey::random(&mut rng).public_key();
+        let v2 = SecretKey::random(&mut rng).public_key();
+        let v3 = SecretKey::random(&mut rng).public_key();
+
+        let mut table = PowerTableSnapshot {
+            next_configuration_number: 10,
+            validators: [(v1, 100), (v2, 200)]
+                .into_iter()
+                .map(|(pk, power)| ValidatorPower {
+                    public_key: hex::encode(pk.serialize()),
+                    power,
+                })
+                .collect(),
+            signature: None,
+        };
+
+        assert!(table.verify(&signer.public_key()).is_err());
+        table.sign(&signer).unwrap();
+        table.verify(&signer.public_key()).unwrap();
+        assert!(table.verify(&v1).is_err());
+
+        // The current validator missing from the table loses its power.
+        let changes = table.change_requests(&[v2, v3]).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| c.configuration_number)
+                .collect::<Vec<_>>(),
+            vec![10, 11, 12]
+        );
+        assert!(changes
+            .iter()
+            .all(|c| matches!(c.change.op, StakingOperation::SetFederatedPower)));
+
+        // Tampering invalidates the signature.
+        table.validators[0].power = 1000;
+        assert!(table.verify(&signer.public_key()).is_err());
+
+        // Validators can only be listed once.
+        table.validators[1] = table.validators[0].clone();
+        table.sign(&signer).unwrap();
+        assert!(table.verify(&signer.public_key()).is_err());
+    }
+}
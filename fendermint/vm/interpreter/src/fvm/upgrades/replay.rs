@@ -0,0 +1,268 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Replaying events a subnet missed from its parent as part of an upgrade.
+//!
+//! Rather than hardcoding the events in the migration, they are described by the contract
+//! that emitted them, their topic and the range of parent blocks, and fetched from an RPC
+//! endpoint of the parent, or read from a file bundled with the upgrade.
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use ethers::contract::{parse_log, EthLogDecode};
+use ethers::core::types as et;
+use ethers::prelude::ContractRevert;
+use ethers::providers::{Http, Middleware, Provider};
+use fvm_ipld_blockstore::Blockstore;
+
+use super::MigrationParams;
+use crate::fvm::state::fevm::{ContractCaller, MockContractCall};
+use crate::fvm::state::FvmExecState;
+
+/// Maximum number of blocks to ask for logs in a single request; providers typically
+/// limit the range of `eth_getLogs`.
+const MAX_BLOCK_RANGE: u64 = 2000;
+
+/// Where to get the logs from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventSource {
+    /// The JSON-RPC endpoint of the parent.
+    Rpc(String),
+    /// A JSON file with the logs, in the format `eth_getLogs` returns them.
+    File(PathBuf),
+}
+
+/// The events to replay: the logs emitted by a contract with a given topic in a block range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventReplay {
+    pub address: et::Address,
+    pub topic: et::H256,
+    pub from_block: u64,
+    /// Inclusive.
+    pub to_block: u64,
+    pub source: EventSource,
+}
+
+impl EventReplay {
+    /// Read the replay from the parameters of a migration: `address`, `topic`, `from_block`,
+    /// `to_block`, and either `rpc` or `file`.
+    pub fn from_params(params: &MigrationParams) -> anyhow::Result<Self> {
+        let get = |name: &str| {
+            params
+                .get(name)
+                .ok_or_else(|| anyhow!("missing migration parameter: {name}"))
+        };
+        let parse_block = |name: &str| -> anyhow::Result<u64> {
+            get(name)?
+                .parse()
+                .with_context(|| format!("invalid migration parameter: {name}"))
+        };
+
+        let source = match (params.get("rpc"), params.get("file")) {
+            (Some(url), None) => EventSource::Rpc(url.clone()),
+            (None, Some(path)) => EventSource::File(PathBuf::from(path)),
+            _ => bail!("exactly one of the rpc and file migration parameters is required"),
+        };
+
+        let replay = Self {
+            address: et::Address::from_str(get("address")?).context("invalid address")?,
+            topic: et::H256::from_str(get("topic")?).context("invalid topic")?,
+            from_block: parse_block("from_block")?,
+            to_block: parse_block("to_block")?,
+            source,
+        };
+        if replay.from_block > replay.to_block {
+            bail!(
+                "empty block range: {}..={}",
+                replay.from_block,
+                replay.to_block
+            );
+        }
+        Ok(replay)
+    }
+
+    /// Load the logs from the source, in the order they were emitted.
+    ///
+    /// Migrations run during block execution, which isn't async, so fetching from the RPC
+    /// endpoint is done on a separate thread with its own runtime.
+    pub fn load_logs(&self) -> anyhow::Result<Vec<et::Log>> {
+        let logs = match &self.source {
+            EventSource::File(path) => {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read logs from {}", path.display()))?;
+                serde_json::from_str(&json).context("failed to parse logs")?
+            }
+            EventSource::Rpc(url) => std::thread::scope(|s| {
+                s.spawn(|| {
+                    tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()?
+                        .block_on(self.fetch_logs(url))
+                })
+                .join()
+                .map_err(|_| anyhow!("fetching logs panicked"))?
+            })?,
+        };
+        Ok(self.select(logs))
+    }
+
+    async fn fetch_logs(&self, url: &str) -> anyhow::Result<Vec<et::Log>> {
+        let provider = Provider::<Http>::try_from(url).context("invalid parent RPC URL")?;
+        let mut logs = Vec::new();
+        let mut from_block = self.from_block;
+        while from_block <= self.to_block {
+            let to_block = self.to_block.min(from_block + MAX_BLOCK_RANGE - 1);
+            let filter = et::Filter::new()
+                .address(self.address)
+                .topic0(self.topic)
+                .from_block(from_block)
+                .to_block(to_block);
+            let chunk = provider.get_logs(&filter).await.with_context(|| {
+                format!("failed to fetch logs in blocks {from_block}..={to_block}")
+            })?;
+            logs.extend(chunk);
+            from_block = to_block + 1;
+        }
+        Ok(logs)
+    }
+
+    /// Keep the logs which match the replay, sorted by block and log index; the bundled file
+    /// can contain more than needed, and providers don't have to return them in order.
+    fn select(&self, mut logs: Vec<et::Log>) -> Vec<et::Log> {
+        logs.retain(|log| {
+            let height = log.block_number.map(|n| n.as_u64()).unwrap_or_default();
+            log.address == self.address
+                && log.topics.first() == Some(&self.topic)
+                && log.removed != Some(true)
+                && (self.from_block..=self.to_block).contains(&height)
+        });
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+        logs
+    }
+
+    /// Load the logs and decode them as events.
+    pub fn load_events<T: EthLogDecode>(&self) -> anyhow::Result<Vec<T>> {
+        self.load_logs()?
+            .into_iter()
+            .map(|log| parse_log(log).context("failed to decode log"))
+            .collect()
+    }
+
+    /// Load the events and apply each of them by calling the contract, e.g. to the
+    /// method which should have been called when the event was emitted.
+    pub fn apply<DB, C, E, T, F>(
+        &self,
+        state: &mut FvmExecState<DB>,
+        caller: &ContractCaller<DB, C, E>,
+        f: F,
+    ) -> anyhow::Result<usize>
+    where
+        DB: Blockstore + Clone + 'static,
+        E: ContractRevert + Debug,
+        T: EthLogDecode,
+        F: Fn(&C, T) -> MockContractCall<()>,
+    {
+        let events = self.load_events::<T>()?;
+        let count = events.len();
+        for (i, event) in events.into_iter().enumerate() {
+            caller
+                .call(state, |c| f(c, event))
+                .with_context(|| format!("failed to apply event {i} of {count}"))?;
+        }
+        tracing::info!(
+            address = ?self.address,
+            topic = ?self.topic,
+            count,
+            "replayed events"
+        );
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::core::types as et;
+
+    use super::{EventReplay, EventSource};
+    use crate::fvm::upgrades::MigrationParams;
+
+    fn params(pairs: &[(&str, &str)]) -> MigrationParams {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn log(address: et::Address, topic: et::H256, block: u64, index: u64) -> et::Log {
+        et::Log {
+            address,
+            topics: vec![topic],
+            block_number: Some(block.into()),
+            log_index: Some(index.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replay_from_params() {
+        let address = "0x0000000000000000000000000000000000000064";
+        let topic = "0x1111111111111111111111111111111111111111111111111111111111111111";
+        let mut ps = params(&[
+            ("address", address),
+            ("topic", topic),
+            ("from_block", "10"),
+            ("to_block", "20"),
+            ("file", "events.json"),
+        ]);
+
+        let replay = EventReplay::from_params(&ps).unwrap();
+        assert_eq!(replay.source, EventSource::File("events.json".into()));
+        assert_eq!(replay.to_block, 20);
+
+        // Ambiguous source.
+        ps.insert("rpc".into(), "http://localhost:8545".into());
+        assert!(EventReplay::from_params(&ps).is_err());
+
+        // Empty range.
+        ps.remove("file");
+        ps.insert("from_block".into(), "30".into());
+        assert!(EventReplay::from_params(&ps).is_err());
+    }
+
+    #[test]
+    fn test_select_logs() {
+        let address = et::Address::repeat_byte(1);
+        let topic = et::H256::repeat_byte(2);
+        let replay = EventReplay {
+            address,
+            topic,
+            from_block: 10,
+            to_block: 20,
+            source: EventSource::File("events.json".into()),
+        };
+
+        let logs = vec![
+            log(address, topic, 15, 1),
+            log(address, topic, 12, 0),
+            log(address, topic, 21, 0),
+            log(et::Address::repeat_byte(3), topic, 12, 1),
+            log(address, et::H256::repeat_byte(4), 12, 2),
+            log(address, topic, 15, 0),
+        ];
+
+        let selected = replay.select(logs);
+        let keys = selected
+            .iter()
+            .map(|l| {
+                (
+                    l.block_number.unwrap().as_u64(),
+                    l.log_index.unwrap().as_u64(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![(12, 0), (15, 0), (15, 1)]);
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Storing validator changes which the subnet missed from its parent as part of an upgrade.
+//!
+//! The gateway only accepts validator changes with consecutive configuration numbers, so if
+//! the top-down finality ever skipped one, all the later changes are rejected too. The missing
+//! changes can be collected from the parent with `fendermint topdown backfill-changes`, and
+//! stored by the upgrade as if they came with the parent finality, taking effect at the next
+//! bottom-up checkpoint like those.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use fvm_ipld_blockstore::Blockstore;
+use ipc_api::ethers_address_to_fil_address;
+use ipc_api::evm::payload_to_evm_address;
+use ipc_api::staking::{StakingChange, StakingChangeRequest, StakingOperation};
+use serde::{Deserialize, Serialize};
+
+use crate::fvm::state::ipc::GatewayCaller;
+use crate::fvm::state::FvmExecState;
+
+/// The validator changes to store in the gateway, in configuration number order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorChangesBackfill {
+    pub changes: Vec<BackfilledChange>,
+}
+
+/// A validator change as the parent gateway emitted it in a `NewStakingChangeRequest` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfilledChange {
+    pub configuration_number: u64,
+    /// The staking operation as numbered in the contracts, e.g. 0 for a deposit.
+    pub op: u8,
+    /// Hex encoded ABI payload of the operation.
+    pub payload: String,
+    /// Hex encoded Ethereum address of the validator.
+    pub validator: String,
+}
+
+impl ValidatorChangesBackfill {
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read validator changes {}", path.display()))?;
+        serde_json::from_str(&json).context("failed to parse validator changes")
+    }
+
+    pub fn from_change_requests(changes: &[StakingChangeRequest]) -> anyhow::Result<Self> {
+        let changes = changes
+            .iter()
+            .map(BackfilledChange::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { changes })
+    }
+
+    /// The digest an upgrade refers to the changes by, so the file can't be swapped
+    /// after the upgrade has been approved.
+    pub fn digest(&self) -> anyhow::Result<[u8; 32]> {
+        let bz = fvm_ipld_encoding::to_vec(&self.changes)?;
+        Ok(ethers::utils::keccak256(bz))
+    }
+
+    /// Parse the changes, checking that their configuration numbers are consecutive.
+    pub fn change_requests(&self) -> anyhow::Result<Vec<StakingChangeRequest>> {
+        let mut changes = Vec::new();
+        for c in self.changes.iter() {
+            let change = StakingChangeRequest::try_from(c)?;
+            if let Some(prev) = changes
+                .last()
+                .map(|c: &StakingChangeRequest| c.configuration_number)
+            {
+                if change.configuration_number != prev + 1 {
+                    bail!(
+                        "validator change {} doesn't follow {prev}",
+                        change.configuration_number
+                    );
+                }
+            }
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+
+    /// Store the changes in the gateway, to be applied at the next checkpoint.
+    pub fn apply<DB>(&self, state: &mut FvmExecState<DB>) -> anyhow::Result<usize>
+    where
+        DB: Blockstore + Clone + 'static,
+    {
+        let changes = self.change_requests()?;
+        let count = changes.len();
+        let first = changes.first().map(|c| c.configuration_number);
+
+        GatewayCaller::default()
+            .store_validator_changes(state, changes)
+            .context("failed to store validator changes")?;
+
+        tracing::info!(
+            count,
+            first_configuration_number = first,
+            "stored backfilled validator changes"
+        );
+        Ok(count)
+    }
+}
+
+impl TryFrom<&StakingChangeRequest> for BackfilledChange {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &StakingChangeRequest) -> Result<Self, Self::Error> {
+        let validator = payload_to_evm_address(value.change.validator.payload())?;
+        Ok(Self {
+            configuration_number: value.configuration_number,
+            op: value.change.op.clone().into(),
+            payload: hex::encode(&value.change.payload),
+            validator: hex::encode(validator.as_bytes()),
+        })
+    }
+}
+
+impl TryFrom<&BackfilledChange> for StakingChangeRequest {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &BackfilledChange) -> Result<Self, Self::Error> {
+        let op = StakingOperation::try_from(value.op)
+            .map_err(|_| anyhow!("unknown staking operation: {}", value.op))?;
+        let payload = hex::decode(value.payload.trim_start_matches("0x"))
+            .with_context(|| format!("invalid payload: {}", value.payload))?;
+        let validator = hex::decode(value.validator.trim_start_matches("0x"))
+            .with_context(|| format!("invalid validator address: {}", value.validator))?;
+        if validator.len() != 20 {
+            bail!("invalid validator address: {}", value.validator);
+        }
+        let validator =
+            ethers_address_to_fil_address(&ethers::types::Address::from_slice(&validator))?;
+
+        Ok(Self {
+            configuration_number: value.configuration_number,
+            change: StakingChange {
+                op,
+                payload,
+                validator,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Address;
+    use ipc_api::ethers_address_to_fil_address;
+    use ipc_api::staking::{StakingChange, StakingChangeRequest, StakingOperation};
+
+    use super::ValidatorChangesBackfill;
+
+    #[test]
+    fn test_validator_changes_backfill() {
+        let validator = ethers_address_to_fil_address(&Address::repeat_byte(1)).unwrap();
+        let change = |configuration_number| StakingChangeRequest {
+            configuration_number,
+            change: StakingChange {
+                op: StakingOperation::Deposit,
+                payload: vec![configuration_number as u8; 32],
+                validator,
+            },
+        };
+
+        let backfill =
+            ValidatorChangesBackfill::from_change_requests(&[change(5), change(6)]).unwrap();
+        let json = serde_json::to_string(&backfill).unwrap();
+        let parsed: ValidatorChangesBackfill = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.digest().unwrap(), backfill.digest().unwrap());
+
+        let changes = parsed.change_requests().unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1].configuration_number, 6);
+        assert_eq!(changes[1].change.validator, validator);
+        assert_eq!(changes[1].change.payload, vec![6; 32]);
+
+        // Gaps would be rejected by the gateway.
+        let backfill =
+            ValidatorChangesBackfill::from_change_requests(&[change(5), change(7)]).unwrap();
+        assert!(backfill.change_requests().is_err());
+    }
+}
@@ -203,10 +203,14 @@ where
                 Ok((state, Err(InvalidSignature(s))))
             }
             Ok(()) => {
-                let (state, ret) = self
+                let domain_hash = msg
+                    .domain_hash(&state.chain_id())
+                    .context("failed to compute domain hash")?;
+                let (state, mut ret) = self
                     .inner
                     .check(state, msg.into_message(), is_recheck)
                     .await?;
+                ret.domain_hash = domain_hash;
                 Ok((state, Ok(ret)))
             }
         }
@@ -0,0 +1,96 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Bloom filters of the Ethereum logs emitted in a block, like the `logsBloom` of Ethereum blocks,
+//! to tell which blocks can be skipped when looking for logs.
+
+use ethers_core::types::{self as et, Bloom, BloomInput};
+use fendermint_vm_actor_interface::eam::EthAddress;
+use fvm_shared::{address::Address, event::StampedEvent, ActorID};
+
+use crate::conv::from_fvm::to_eth_address;
+
+/// The Ethereum address logs emitted by an actor appear under: its delegated address,
+/// if it has one, or the one masking its ID.
+pub fn log_address(emitter: ActorID, delegated: Option<&Address>) -> et::H160 {
+    delegated
+        .and_then(|a| to_eth_address(a).ok())
+        .flatten()
+        .unwrap_or_else(|| et::H160::from_slice(&EthAddress::from_id(emitter).0))
+}
+
+/// The Ethereum topics of an event: the 32 byte values under the `t1` to `t4` keys.
+pub fn log_topics(event: &StampedEvent) -> Vec<et::H256> {
+    let mut topics = Vec::new();
+    for entry in event.event.entries.iter() {
+        let i = match entry.key.as_str() {
+            "t1" => 0,
+            "t2" => 1,
+            "t3" => 2,
+            "t4" => 3,
+            _ => continue,
+        };
+        if entry.value.len() != 32 {
+            continue;
+        }
+        while topics.len() <= i {
+            topics.push(et::H256::default())
+        }
+        topics[i] = et::H256::from_slice(&entry.value);
+    }
+    topics
+}
+
+/// Add the address and the topics of a log to a bloom filter.
+pub fn accrue_log(bloom: &mut Bloom, address: &et::H160, topics: &[et::H256]) {
+    bloom.accrue(BloomInput::Raw(address.as_bytes()));
+    for topic in topics {
+        bloom.accrue(BloomInput::Raw(topic.as_bytes()));
+    }
+}
+
+/// Whether a block with a bloom filter may contain logs emitted by any of the addresses,
+/// and with any of the topics at each position; an empty list matches anything.
+pub fn may_match(bloom: &Bloom, addresses: &[et::H160], topics: &[Vec<et::H256>]) -> bool {
+    let contains_any = |inputs: &mut dyn Iterator<Item = &[u8]>| {
+        let mut empty = true;
+        for input in inputs {
+            if bloom.contains_input(BloomInput::Raw(input)) {
+                return true;
+            }
+            empty = false;
+        }
+        empty
+    };
+    contains_any(&mut addresses.iter().map(|a| a.as_bytes()))
+        && topics
+            .iter()
+            .all(|ts| contains_any(&mut ts.iter().map(|t| t.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::{self as et, Bloom};
+
+    use super::{accrue_log, may_match};
+
+    #[test]
+    fn bloom_matches_logs() {
+        let address = et::H160::repeat_byte(1);
+        let other = et::H160::repeat_byte(2);
+        let topic = et::H256::repeat_byte(3);
+        let other_topic = et::H256::repeat_byte(4);
+
+        let mut bloom = Bloom::zero();
+        assert!(!may_match(&bloom, &[address], &[]));
+        assert!(may_match(&bloom, &[], &[]));
+
+        accrue_log(&mut bloom, &address, &[topic]);
+
+        assert!(may_match(&bloom, &[], &[]));
+        assert!(may_match(&bloom, &[address], &[]));
+        assert!(may_match(&bloom, &[other, address], &[vec![], vec![topic]]));
+        assert!(!may_match(&bloom, &[other], &[]));
+        assert!(!may_match(&bloom, &[address], &[vec![other_topic]]));
+    }
+}
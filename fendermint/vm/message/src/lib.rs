@@ -4,6 +4,7 @@ use cid::{multihash, multihash::MultihashDigest, Cid};
 use fvm_ipld_encoding::{to_vec, Error as IpldError, DAG_CBOR};
 use serde::Serialize;
 
+pub mod bloom;
 pub mod chain;
 pub mod conv;
 pub mod ipc;
@@ -1,14 +1,16 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use cid::Cid;
-use fvm_ipld_encoding::RawBytes;
+use fvm_ipld_encoding::{strict_bytes, RawBytes};
 use fvm_shared::{
     address::Address, econ::TokenAmount, error::ExitCode, message::Message as FvmMessage,
-    version::NetworkVersion,
+    version::NetworkVersion, ActorID, MethodNum,
 };
+use ipc_api::cross::IpcEnvelope;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+use fendermint_vm_actor_interface::evm;
 use fendermint_vm_encoding::IsHumanReadable;
 
 /// Height at which to run a query.
@@ -23,7 +25,7 @@ pub enum FvmQueryHeight {
     /// This option is less performant because a shared state needs to be locked.
     Pending,
     /// Run it on some historical block height, if it's still available.
-    /// Otherwise use the latest commit.
+    /// Otherwise the query fails, rather than running on a different state.
     Height(u64),
 }
 
@@ -76,8 +78,21 @@ pub enum FvmQuery {
     StateParams,
     /// Query the built-in actors known by the System actor.
     BuiltinActors,
+    /// Immediately execute an FVM message like [`Call`], recording the calls it makes.
+    ///
+    /// The response is the IPLD encoded [CallTrace] of the message, if it got as far as
+    /// calling its recipient. It always runs on the committed state at the queried height.
+    TraceCall(Box<FvmMessage>),
+    /// Query the state of an actor like [`ActorState`], along with the IPLD blocks proving it,
+    /// and the values of storage slots of an EVM actor, with the blocks proving those.
+    ///
+    /// The response is IPLD encoded [ActorProof].
+    ActorProof(Address, Vec<evm::uints::U256>),
 }
 
+/// The most storage keys an [FvmQuery::ActorProof] can ask for, as each takes a walk of the storage.
+pub const MAX_ACTOR_PROOF_KEYS: usize = 256;
+
 /// State of all actor implementations.
 ///
 /// This is a copy of `fvm::state_tree::ActorState` so that this crate
@@ -107,6 +122,32 @@ pub struct ActorState {
     pub delegated_address: Option<Address>,
 }
 
+/// The state of an actor at some height, with the IPLD blocks to verify it against the app hash.
+///
+/// The app hash of a block is the CID of the state parameters the previous block ended with,
+/// which contain the root of the state tree. The blocks of the account proof are the nodes
+/// visited to find the actor in the state tree, while those of a storage proof are the ones
+/// the EVM actor visited to load the slot, starting from its own state.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct ActorProof {
+    /// The IPLD encoded state parameters, whose CID is the app hash of the next block.
+    pub state_params: RawBytes,
+    /// The ID and state of the actor, if it exists.
+    pub actor: Option<(ActorID, ActorState)>,
+    pub account_proof: Vec<RawBytes>,
+    /// Empty unless the actor exists.
+    pub storage_proof: Vec<StorageProof>,
+}
+
+/// The value of an EVM storage slot and the IPLD blocks proving it.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct StorageProof {
+    pub key: evm::uints::U256,
+    /// Zero if the slot is empty, or the actor isn't an EVM contract.
+    pub value: evm::uints::U256,
+    pub proof: Vec<RawBytes>,
+}
+
 /// Result of gas estimation.
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct GasEstimate {
@@ -142,6 +183,10 @@ pub struct StateParams {
     pub chain_id: u64,
     /// Current network version.
     pub network_version: NetworkVersion,
+    /// Maximum gas the user messages of a block can use in total, as set by governance;
+    /// unlimited if zero.
+    #[serde(default)]
+    pub block_gas_limit: u64,
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
@@ -150,6 +195,307 @@ pub struct BuiltinActors {
     pub registry: Vec<(String, Cid)>,
 }
 
+/// ABCI query path of the parent finality votes collected by the node, see [VoteTally].
+///
+/// The votes aren't part of the ledger, so this isn't an [FvmQuery] running on the state.
+pub const VOTE_TALLY_QUERY_PATH: &str = "/ipc/vote_tally";
+
+/// The parent finality votes collected by a node, to diagnose why no quorum is forming.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct VoteTally {
+    /// The last parent height finalized in the ledger.
+    pub last_finalized_height: u64,
+    /// The latest parent height synced by the node, which it votes on.
+    pub latest_height: u64,
+    /// Total power of the validators.
+    pub total_power: u64,
+    /// The power needed for a quorum.
+    pub quorum_threshold: u64,
+    /// The votes at each height above the last finalized one.
+    pub heights: Vec<HeightVotes>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct HeightVotes {
+    pub height: u64,
+    /// Hex encoded hash of the parent block at this height as observed by the node;
+    /// missing if it's a null round or it hasn't been synced yet.
+    pub block_hash: Option<String>,
+    /// The votes for each block hash at this height.
+    pub votes: Vec<BlockVotes>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct BlockVotes {
+    /// Hex encoded hash of the block voted for.
+    pub block_hash: String,
+    /// Hex encoded public keys of the validators who voted for the block.
+    pub validators: Vec<String>,
+    /// The aggregate power of the validators in the current power table.
+    pub power: u64,
+}
+
+/// ABCI query path of the top-down finality administration of the node, see [TopDownAdminRequest].
+///
+/// Requests are only served if the node has an admin token configured, and they carry it.
+pub const TOPDOWN_ADMIN_QUERY_PATH: &str = "/ipc/topdown_admin";
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct TopDownAdminRequest {
+    /// The admin token configured on the node.
+    pub token: String,
+    pub command: TopDownAdminCommand,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum TopDownAdminCommand {
+    /// Report the state of the top-down finality.
+    Status,
+    /// Stop proposing new parent finality.
+    Pause,
+    /// Resume proposing new parent finality.
+    Resume,
+}
+
+/// The state of the top-down finality on a node, after executing a [TopDownAdminCommand].
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct TopDownStatus {
+    /// Whether the node syncs with the parent at all.
+    pub enabled: bool,
+    /// Whether the node has stopped proposing new parent finality.
+    pub paused: bool,
+    /// The last parent height finalized in the ledger.
+    pub last_committed_height: Option<u64>,
+    /// The latest parent height synced by the node.
+    pub latest_height: Option<u64>,
+}
+
+/// ABCI query path of the top-down finality settings the node is running with, see [TopDownParams].
+pub const TOPDOWN_PARAMS_QUERY_PATH: &str = "/ipc/topdown_params";
+
+/// The settings of a node which decide how fast it follows the parent and votes on it.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct TopDownParams {
+    /// Interval at which votes can be gossiped, in seconds.
+    pub vote_interval_secs: u64,
+    /// Time after which the last vote is re-published, in seconds.
+    pub vote_timeout_secs: u64,
+    /// The parent syncing settings; missing if the node doesn't sync with the parent.
+    pub parent: Option<ParentSyncParams>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct ParentSyncParams {
+    /// Number of blocks behind the parent chain head, or its finalized block, considered final.
+    pub chain_head_delay: u64,
+    /// Number of further blocks to wait before proposing a final block.
+    pub proposal_delay: u64,
+    /// Maximum number of parent blocks finalized at once.
+    pub max_proposal_range: u64,
+    /// Interval at which the parent is polled, in seconds.
+    pub polling_interval_secs: u64,
+    /// The rule deciding which parent blocks are final, e.g. `confirmations`.
+    pub finality_rule: String,
+}
+
+/// ABCI query path of the parent finalities committed by the subnet, as logged by the node.
+///
+/// The request is the parent height, and the response the [ParentFinalityLogEntry] for it, if any.
+pub const PARENT_FINALITY_LOG_QUERY_PATH: &str = "/ipc/parent_finality_log";
+
+/// A parent finality committed by the subnet, with the parent data it made the subnet execute.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct ParentFinalityLogEntry {
+    /// The subnet block height at which the finality was committed.
+    pub block_height: u64,
+    /// The parent height finalized.
+    pub parent_height: u64,
+    /// Hex encoded hash of the parent block finalized.
+    pub parent_block_hash: String,
+    /// The parent heights whose validator changes and messages were executed, inclusive.
+    pub executed_from: u64,
+    pub executed_to: u64,
+    pub validator_changes: Vec<LoggedValidatorChange>,
+    pub top_down_msgs: Vec<IpcEnvelope>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct LoggedValidatorChange {
+    pub configuration_number: u64,
+    /// The staking operation as numbered in the contracts, e.g. 0 for a deposit.
+    pub op: u8,
+    /// Hex encoded ABI payload of the operation.
+    pub payload: String,
+    pub validator: String,
+}
+
+/// ABCI query path of the transactions indexed by the node, if it maintains a transaction index.
+///
+/// The request is a [TxIndexQuery], and the response the matching [IndexedTx] list, newest first.
+pub const TX_INDEX_QUERY_PATH: &str = "/ipc/tx_index";
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum TxIndexQuery {
+    /// The transaction with a hash, either the CometBFT one or the domain specific one,
+    /// e.g. the Ethereum transaction hash.
+    Hash(#[serde(with = "strict_bytes")] Vec<u8>),
+    /// The transactions matching a filter, skipping the `skip` newest ones.
+    List {
+        filter: TxIndexFilter,
+        skip: u64,
+        limit: u64,
+    },
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum TxIndexFilter {
+    Sender(Address),
+    Recipient(Address),
+    Method(MethodNum),
+}
+
+/// A transaction executed in a block, as indexed by the node.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedTx {
+    /// Hex encoded CometBFT hash of the transaction.
+    pub hash: String,
+    /// Hex encoded domain specific hash of the transaction, e.g. the Ethereum one, if it has any.
+    pub domain_hash: Option<String>,
+    /// The height of the block the transaction was executed in.
+    pub height: u64,
+    /// The position of the transaction in the block.
+    pub index: u64,
+    pub from: Address,
+    pub to: Address,
+    pub method_num: MethodNum,
+    pub exit_code: u32,
+    pub gas_used: u64,
+}
+
+/// ABCI query path of the execution traces recorded by the node, if it runs with execution tracing.
+///
+/// The request is the CometBFT hash of a transaction, or its domain specific hash if the node
+/// also keeps a transaction index, and the response the [CallTrace] of its message, if any.
+pub const EXEC_TRACE_QUERY_PATH: &str = "/ipc/exec_trace";
+
+/// A call made while executing a message, with the calls it made in turn.
+#[serde_as]
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct CallTrace {
+    pub from: Address,
+    pub to: Address,
+    pub method_num: MethodNum,
+    /// Hex encoded parameters of the call.
+    pub params: String,
+    #[serde_as(as = "IsHumanReadable")]
+    pub value: TokenAmount,
+    pub gas_limit: u64,
+    /// Gas charged during the call, including the calls it made.
+    pub gas_used: u64,
+    pub read_only: bool,
+    pub exit_code: u32,
+    /// Hex encoded return value of the call.
+    pub return_data: String,
+    /// The reason the FVM aborted the call with, e.g. the recipient not existing,
+    /// as opposed to the actor returning an error exit code.
+    pub error: Option<String>,
+    pub calls: Vec<CallTrace>,
+}
+
+/// ABCI query path of the results of executing the transactions waiting in the mempool of the node,
+/// if it runs with pending receipts enabled.
+///
+/// The request is the CometBFT hash of a transaction, or its domain specific hash,
+/// and the response the [PendingReceipt] of its message, if any.
+pub const PENDING_RECEIPT_QUERY_PATH: &str = "/ipc/pending_receipt";
+
+/// The expected outcome of a transaction which hasn't been included in a block yet,
+/// from executing it on top of the latest committed state and the transactions checked before it.
+///
+/// It's only valid until the next block is committed, after which the transaction is executed again,
+/// if it's still in the mempool. The actual outcome can differ, e.g. if the block orders the
+/// transactions differently.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct PendingReceipt {
+    /// Hex encoded CometBFT hash of the transaction.
+    pub hash: String,
+    /// Hex encoded domain specific hash of the transaction, e.g. the Ethereum one, if it has any.
+    pub domain_hash: Option<String>,
+    /// The height of the last committed block the transaction was executed on top of.
+    pub height: u64,
+    pub from: Address,
+    pub exit_code: u32,
+    pub gas_used: u64,
+    /// Hex encoded return value of the message.
+    pub return_data: String,
+    pub info: Option<String>,
+}
+
+/// ABCI query path of the log index kept by the node, if enabled, to find the blocks which
+/// may contain Ethereum logs matching a filter without scanning every block in a range.
+///
+/// The request is a [LogIndexQuery], and the response the list of heights in the range,
+/// including the ones which were not indexed, which have to be checked. The range is
+/// limited to 10,000 blocks.
+pub const LOG_INDEX_QUERY_PATH: &str = "/ipc/log_index";
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct LogIndexQuery {
+    /// The first and last block of the range, inclusive.
+    pub from_height: u64,
+    pub to_height: u64,
+    /// The addresses any of which the logs have to be emitted by; any address if empty.
+    pub addresses: Vec<ethers_core::types::H160>,
+    /// The topics any of which the logs have to have at each position; any topic if empty.
+    pub topics: Vec<Vec<ethers_core::types::H256>>,
+}
+
+/// ABCI query path of the resources used by the actors in the recent blocks, if the node
+/// runs with actor statistics enabled.
+///
+/// The request is an [ActorStatsQuery], and the response an [ActorStatsReport].
+pub const ACTOR_STATS_QUERY_PATH: &str = "/ipc/actor_stats";
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct ActorStatsQuery {
+    /// Number of the most recent blocks to add up the statistics of.
+    pub blocks: u64,
+    /// Maximum number of actors to return, the ones using the most gas first; all of them if zero.
+    pub limit: u64,
+}
+
+/// The resources used by the transactions executed in a range of blocks, by actor.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct ActorStatsReport {
+    /// The first and last block of the range, inclusive.
+    pub from_height: u64,
+    pub to_height: u64,
+    /// The actors called in the range, the ones using the most gas first.
+    pub actors: Vec<ActorStats>,
+}
+
+/// The resources used by the calls made to an actor.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct ActorStats {
+    /// The ID address of the actor, or the address it was called at if it doesn't exist.
+    pub actor: Address,
+    /// Number of times the actor was called.
+    pub calls: u64,
+    /// Gas charged while the actor was executing, excluding the calls it made to other actors.
+    pub gas_used: u64,
+    /// Estimate of the bytes of state the actor wrote, from the storage gas it was charged.
+    pub state_bytes: u64,
+}
+
+impl ActorStats {
+    /// Add up the usage of the same actor.
+    pub fn add(&mut self, other: &ActorStats) {
+        self.calls += other.calls;
+        self.gas_used += other.gas_used;
+        self.state_bytes += other.state_bytes;
+    }
+}
+
 #[cfg(feature = "arb")]
 mod arb {
     use fendermint_testing::arb::{ArbAddress, ArbCid, ArbTokenAmount};
@@ -160,11 +506,12 @@ mod arb {
 
     impl quickcheck::Arbitrary for FvmQuery {
         fn arbitrary(g: &mut quickcheck::Gen) -> Self {
-            match u8::arbitrary(g) % 5 {
+            match u8::arbitrary(g) % 6 {
                 0 => FvmQuery::Ipld(ArbCid::arbitrary(g).0),
                 1 => FvmQuery::ActorState(ArbAddress::arbitrary(g).0),
                 2 => FvmQuery::Call(Box::new(SignedMessage::arbitrary(g).into_message())),
                 3 => FvmQuery::EstimateGas(Box::new(SignedMessage::arbitrary(g).into_message())),
+                4 => FvmQuery::TraceCall(Box::new(SignedMessage::arbitrary(g).into_message())),
                 _ => FvmQuery::StateParams,
             }
         }
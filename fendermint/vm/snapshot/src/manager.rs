@@ -4,7 +4,9 @@
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use crate::manifest::{file_checksum, list_manifests, write_manifest, SnapshotManifest};
+use crate::manifest::{
+    chunk_checksums, file_checksum, list_manifests, write_manifest, SnapshotManifest,
+};
 use crate::state::SnapshotState;
 use crate::{car, SnapshotClient, SnapshotItem, PARTS_DIR_NAME, SNAPSHOT_FILE_NAME};
 use anyhow::Context;
@@ -188,79 +190,105 @@ where
         }
     }
 
-    /// Export a snapshot to a temporary file, then copy it to the snapshot directory.
+    /// Export a snapshot into the snapshot directory.
     async fn create_snapshot(
         &self,
         block_height: BlockHeight,
         state_params: FvmStateParams,
     ) -> anyhow::Result<SnapshotItem> {
-        let snapshot = Snapshot::new(self.store.clone(), state_params.clone(), block_height)
-            .context("failed to create snapshot")?;
-
-        let snapshot_version = snapshot.version();
-        let snapshot_name = format!("snapshot-{block_height}");
-        let temp_dir = tempfile::Builder::new()
-            .prefix(&snapshot_name)
-            .tempdir()
-            .context("failed to create temp dir for snapshot")?;
-
-        let snapshot_path = temp_dir.path().join(SNAPSHOT_FILE_NAME);
-        let checksum_path = temp_dir.path().join(format!("{PARTS_DIR_NAME}.sha256"));
-        let parts_path = temp_dir.path().join(PARTS_DIR_NAME);
-
-        // TODO: See if we can reuse the contents of an existing CAR file.
-
-        tracing::debug!(
+        let snapshot_dir = self.snapshots_dir.join(format!("snapshot-{block_height}"));
+        export_snapshot(
+            self.store.clone(),
             block_height,
-            path = snapshot_path.to_string_lossy().to_string(),
-            "exporting snapshot..."
-        );
-
-        // Export the state to a CAR file.
-        snapshot
-            .write_car(&snapshot_path)
-            .await
-            .context("failed to write CAR file")?;
-
-        let snapshot_size = std::fs::metadata(&snapshot_path)
-            .context("failed to get snapshot metadata")?
-            .len() as usize;
-
-        // Create a checksum over the CAR file.
-        let checksum_bytes = file_checksum(&snapshot_path).context("failed to compute checksum")?;
-
-        std::fs::write(&checksum_path, checksum_bytes.to_string())
-            .context("failed to write checksum file")?;
-
-        // Create a directory for the parts.
-        std::fs::create_dir(&parts_path).context("failed to create parts dir")?;
-
-        // Split the CAR file into chunks.
-        // They can be listed in the right order with e.g. `ls | sort -n`
-        // Alternatively we could pad them with zeroes based on the original file size and the chunk size,
-        // but this way it will be easier to return them based on a numeric index.
-        let chunks_count = car::split(&snapshot_path, &parts_path, self.chunk_size, |idx| {
-            format!("{idx}.part")
-        })
+            state_params,
+            self.chunk_size,
+            &snapshot_dir,
+        )
         .await
-        .context("failed to split CAR into chunks")?;
+    }
+}
 
-        // Create and export a manifest that we can easily look up.
-        let manifest = SnapshotManifest {
-            block_height,
-            size: snapshot_size as u64,
-            chunks: chunks_count as u32,
-            checksum: checksum_bytes,
-            state_params,
-            version: snapshot_version,
-        };
-        let _ = write_manifest(temp_dir.path(), &manifest).context("failed to export manifest")?;
+/// Export the state at a block height to a temporary file, split it into parts of
+/// `chunk_size` bytes, then move the parts and the manifest to `snapshot_dir`.
+pub async fn export_snapshot<BS>(
+    store: BS,
+    block_height: BlockHeight,
+    state_params: FvmStateParams,
+    chunk_size: usize,
+    snapshot_dir: &Path,
+) -> anyhow::Result<SnapshotItem>
+where
+    BS: Blockstore + Clone + Send + Sync + 'static,
+{
+    let snapshot = Snapshot::new(store, state_params.clone(), block_height)
+        .context("failed to create snapshot")?;
+
+    let snapshot_version = snapshot.version();
+    let snapshot_name = format!("snapshot-{block_height}");
+    let temp_dir = tempfile::Builder::new()
+        .prefix(&snapshot_name)
+        .tempdir()
+        .context("failed to create temp dir for snapshot")?;
+
+    let snapshot_path = temp_dir.path().join(SNAPSHOT_FILE_NAME);
+    let checksum_path = temp_dir.path().join(format!("{PARTS_DIR_NAME}.sha256"));
+    let parts_path = temp_dir.path().join(PARTS_DIR_NAME);
+
+    // TODO: See if we can reuse the contents of an existing CAR file.
+
+    tracing::debug!(
+        block_height,
+        path = snapshot_path.to_string_lossy().to_string(),
+        "exporting snapshot..."
+    );
+
+    // Export the state to a CAR file.
+    snapshot
+        .write_car(&snapshot_path)
+        .await
+        .context("failed to write CAR file")?;
+
+    let snapshot_size = std::fs::metadata(&snapshot_path)
+        .context("failed to get snapshot metadata")?
+        .len() as usize;
+
+    // Create a checksum over the CAR file.
+    let checksum_bytes = file_checksum(&snapshot_path).context("failed to compute checksum")?;
+
+    std::fs::write(&checksum_path, checksum_bytes.to_string())
+        .context("failed to write checksum file")?;
+
+    // Create a directory for the parts.
+    std::fs::create_dir(&parts_path).context("failed to create parts dir")?;
+
+    // Split the CAR file into chunks.
+    // They can be listed in the right order with e.g. `ls | sort -n`
+    // Alternatively we could pad them with zeroes based on the original file size and the chunk size,
+    // but this way it will be easier to return them based on a numeric index.
+    let chunks_count = car::split(&snapshot_path, &parts_path, chunk_size, |idx| {
+        format!("{idx}.part")
+    })
+    .await
+    .context("failed to split CAR into chunks")?;
+
+    let chunk_checksums =
+        chunk_checksums(&parts_path).context("failed to compute chunk checksums")?;
+
+    // Create and export a manifest that we can easily look up.
+    let manifest = SnapshotManifest {
+        block_height,
+        size: snapshot_size as u64,
+        chunks: chunks_count as u32,
+        checksum: checksum_bytes,
+        chunk_checksums,
+        state_params,
+        version: snapshot_version,
+    };
+    let _ = write_manifest(temp_dir.path(), &manifest).context("failed to export manifest")?;
 
-        let snapshots_dir = self.snapshots_dir.join(&snapshot_name);
-        move_or_copy(temp_dir.path(), &snapshots_dir).context("failed to move snapshot")?;
+    move_or_copy(temp_dir.path(), snapshot_dir).context("failed to move snapshot")?;
 
-        Ok(SnapshotItem::new(snapshots_dir, manifest))
-    }
+    Ok(SnapshotItem::new(snapshot_dir.into(), manifest))
 }
 
 /// Periodically ask CometBFT if it has caught up with the chain.
@@ -326,9 +354,9 @@ mod tests {
     use fvm::engine::MultiEngine;
     use quickcheck::Arbitrary;
 
-    use crate::{manager::SnapshotParams, manifest, PARTS_DIR_NAME};
+    use crate::{manager::SnapshotParams, manifest, SnapshotItem, PARTS_DIR_NAME};
 
-    use super::SnapshotManager;
+    use super::{export_snapshot, SnapshotManager};
 
     // Initialise genesis and export it directly to see if it works.
     #[tokio::test]
@@ -342,6 +370,40 @@ mod tests {
             .expect("failed to write snapshot");
     }
 
+    // Export a snapshot into a directory, verify and import it, then see that corrupted parts are detected.
+    #[tokio::test]
+    async fn export_and_verify_snapshot() {
+        let (state_params, store) = init_genesis().await;
+        let tmp_dir = tempfile::tempdir().expect("failed to create tmp dir");
+        let snapshot_dir = tmp_dir.path().join("backup");
+
+        let snapshot = export_snapshot(store, 0, state_params, 10000, &snapshot_dir)
+            .await
+            .expect("failed to export snapshot");
+
+        assert_eq!(
+            snapshot.manifest.chunk_checksums.len(),
+            snapshot.manifest.chunks as usize
+        );
+
+        let loaded = SnapshotItem::load(snapshot_dir.clone()).expect("failed to load snapshot");
+        assert_eq!(loaded.manifest, snapshot.manifest);
+        loaded.verify().expect("snapshot should verify");
+
+        loaded
+            .import(MemoryBlockstore::new(), true)
+            .await
+            .expect("failed to import snapshot");
+
+        let part = snapshot_dir.join(PARTS_DIR_NAME).join("1.part");
+        let mut bytes = std::fs::read(&part).unwrap();
+        bytes[0] ^= 1;
+        std::fs::write(&part, bytes).unwrap();
+
+        let err = loaded.verify().expect_err("corruption should be detected");
+        assert!(err.to_string().contains("chunk 1"), "{err}");
+    }
+
     // Initialise genesis, create a snapshot manager, export a snapshot, create another manager, list snapshots.
     // Don't forget to run this with `--release` beause of Wasm.
     #[tokio::test]
@@ -483,6 +545,13 @@ mod tests {
             chain_id: out.chain_id.into(),
             power_scale: out.power_scale,
             app_version: 0,
+            exec_limits: out.exec_limits,
+            executed_upgrades: Default::default(),
+            upgrades_in_progress: Default::default(),
+            pending_topdown_msgs: Default::default(),
+            pending_bottomup_msgs: Default::default(),
+            gas_params: None,
+            parent_checkpoint_height: None,
         };
 
         (state_params, store)
@@ -25,6 +25,11 @@ pub struct SnapshotManifest {
     ///
     /// Using a [tendermint::Hash] type because it has nice formatting in JSON.
     pub checksum: tendermint::Hash,
+    /// SHA2 hash of each chunk, in order, to tell which one is corrupted on import.
+    ///
+    /// Empty in manifests received through state sync, which only carry the overall checksum.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunk_checksums: Vec<tendermint::Hash>,
     /// The FVM parameters at the time of the snapshot,
     /// which are also in the CAR file, but it might be
     /// useful to see. It is annotated for human readability.
@@ -48,6 +53,18 @@ pub fn write_manifest(
     Ok(manifest_path)
 }
 
+/// Read the manifest from a snapshot specific directory.
+pub fn read_manifest(snapshot_dir: impl AsRef<Path>) -> anyhow::Result<SnapshotManifest> {
+    let manifest_path = snapshot_dir.as_ref().join(MANIFEST_FILE_NAME);
+    let json = std::fs::read_to_string(&manifest_path).with_context(|| {
+        format!(
+            "failed to read manifest file: {}",
+            manifest_path.to_string_lossy()
+        )
+    })?;
+    serde_json::from_str(&json).context("failed to parse manifest")
+}
+
 /// Collect all the manifests from a directory containing snapshot-directories, e.g.
 /// `snapshots/snapshot-1/manifest.json` etc.
 pub fn list_manifests(snapshot_dir: impl AsRef<Path>) -> anyhow::Result<Vec<SnapshotItem>> {
@@ -123,10 +140,20 @@ pub fn parts_checksum(path: impl AsRef<Path>) -> anyhow::Result<tendermint::Hash
     Ok(tendermint::Hash::Sha256(hash))
 }
 
+/// Calculate the Sha256 checksum of each `{idx}.part` file in a directory, in order.
+pub fn chunk_checksums(path: impl AsRef<Path>) -> anyhow::Result<Vec<tendermint::Hash>> {
+    list_parts(path)?.into_iter().map(file_checksum).collect()
+}
+
 /// List all the `{idx}.part` files in a directory.
 pub fn list_parts(path: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
     let mut chunks = std::fs::read_dir(path.as_ref())
-        .unwrap()
+        .with_context(|| {
+            format!(
+                "failed to read parts directory: {}",
+                path.as_ref().to_string_lossy()
+            )
+        })?
         .collect::<Result<Vec<_>, _>>()
         .with_context(|| {
             format!(
@@ -179,6 +206,7 @@ mod arb {
                     &checksum,
                 )
                 .unwrap(),
+                chunk_checksums: Vec::new(),
                 state_params: FvmStateParams {
                     state_root: ArbCid::arbitrary(g).0,
                     timestamp: Timestamp(Arbitrary::arbitrary(g)),
@@ -190,6 +218,13 @@ mod arb {
                         .into(),
                     power_scale: *g.choose(&[-1, 0, 3]).unwrap(),
                     app_version: 0,
+                    exec_limits: None,
+                    executed_upgrades: Default::default(),
+                    upgrades_in_progress: Default::default(),
+                    pending_topdown_msgs: Default::default(),
+                    pending_bottomup_msgs: Default::default(),
+                    gas_params: None,
+                    parent_checkpoint_height: None,
                 },
                 version: Arbitrary::arbitrary(g),
             }
@@ -58,6 +58,12 @@ impl SnapshotItem {
         }
     }
 
+    /// Load a snapshot from a directory containing its manifest and parts.
+    pub fn load(snapshot_dir: PathBuf) -> anyhow::Result<Self> {
+        let manifest = manifest::read_manifest(&snapshot_dir)?;
+        Ok(Self::new(snapshot_dir, manifest))
+    }
+
     fn parts_dir(&self) -> PathBuf {
         self.snapshot_dir.join(PARTS_DIR_NAME)
     }
@@ -80,6 +86,63 @@ impl SnapshotItem {
         Ok(content)
     }
 
+    /// Check the parts on disk against the manifest: their number and size, the checksum of each
+    /// part if the manifest lists them, and the checksum of all of them together.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let parts =
+            manifest::list_parts(self.parts_dir()).context("failed to list snapshot parts")?;
+
+        if parts.len() != self.manifest.chunks as usize {
+            bail!(
+                "expected {} chunks in the snapshot, found {}",
+                self.manifest.chunks,
+                parts.len()
+            );
+        }
+        let checksums = &self.manifest.chunk_checksums;
+        if !checksums.is_empty() && checksums.len() != parts.len() {
+            bail!(
+                "the manifest has {} chunk checksums for {} chunks",
+                checksums.len(),
+                parts.len()
+            );
+        }
+
+        let mut size = 0;
+        for (i, part) in parts.iter().enumerate() {
+            if *part != self.parts_dir().join(format!("{i}.part")) {
+                bail!("chunk {i} is missing from the snapshot");
+            }
+            size += std::fs::metadata(part)
+                .with_context(|| format!("failed to read chunk {i}"))?
+                .len();
+
+            if let Some(expected) = checksums.get(i) {
+                let checksum = manifest::file_checksum(part)
+                    .with_context(|| format!("failed to compute the checksum of chunk {i}"))?;
+                if checksum != *expected {
+                    bail!("chunk {i} is corrupted; expected checksum {expected}, got {checksum}");
+                }
+            }
+        }
+
+        if size != self.manifest.size {
+            bail!(
+                "expected {} bytes in the snapshot, found {size}",
+                self.manifest.size
+            );
+        }
+        let checksum =
+            manifest::parts_checksum(self.parts_dir()).context("failed to compute checksum")?;
+        if checksum != self.manifest.checksum {
+            bail!(
+                "wrong snapshot checksum; expected {}, got {checksum}",
+                self.manifest.checksum
+            );
+        }
+        Ok(())
+    }
+
     /// Import a snapshot into the blockstore.
     pub async fn import<BS>(&self, store: BS, validate: bool) -> anyhow::Result<Snapshot<BS>>
     where
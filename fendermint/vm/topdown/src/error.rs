@@ -15,4 +15,8 @@ pub enum Error {
     ParentChainReorgDetected,
     #[error("Cannot query parent at height {1}: {0}")]
     CannotQueryParent(String, BlockHeight),
+    #[error("Cannot verify parent block at height {1}: {0}")]
+    CannotVerifyParent(String, BlockHeight),
+    #[error("Inconsistent parent data at height {1}: {0}")]
+    InconsistentParentData(String, BlockHeight),
 }
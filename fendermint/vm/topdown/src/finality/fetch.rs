@@ -8,7 +8,7 @@ use crate::{
     handle_null_round, BlockHash, BlockHeight, Config, Error, IPCParentFinality,
     ParentFinalityProvider, ParentViewProvider,
 };
-use async_stm::{Stm, StmResult};
+use async_stm::{Stm, StmResult, TVar};
 use ipc_api::cross::IpcEnvelope;
 use ipc_api::staking::StakingChangeRequest;
 use std::sync::Arc;
@@ -20,6 +20,8 @@ pub struct CachedFinalityProvider<T> {
     config: Config,
     /// The ipc client proxy that works as a back up if cache miss
     parent_client: Arc<T>,
+    /// Whether proposing new parent finality has been paused by the operator.
+    paused: TVar<bool>,
 }
 
 /// Exponential backoff for futures
@@ -106,12 +108,30 @@ impl<T: ParentQueryProxy + Send + Sync + 'static> ParentViewProvider for CachedF
         }
         Ok(v)
     }
+
+    /// Not cached, as it's only needed once per committed finality, but retried like the rest.
+    async fn last_bottom_up_checkpoint_height(
+        &self,
+        height: BlockHeight,
+    ) -> anyhow::Result<BlockHeight> {
+        retry!(
+            self.config.exponential_back_off,
+            self.config.exponential_retry_limit,
+            self.parent_client
+                .get_last_bottom_up_checkpoint_height(height)
+                .await
+        )
+    }
 }
 
 impl<T: ParentQueryProxy + Send + Sync + 'static> ParentFinalityProvider
     for CachedFinalityProvider<T>
 {
     fn next_proposal(&self) -> Stm<Option<IPCParentFinality>> {
+        if self.paused.read_clone()? {
+            tracing::debug!("parent finality proposals are paused");
+            return Ok(None);
+        }
         self.inner.next_proposal()
     }
 
@@ -196,6 +216,7 @@ impl<T> CachedFinalityProvider<T> {
             inner,
             config,
             parent_client,
+            paused: TVar::new(false),
         }
     }
 
@@ -221,6 +242,11 @@ impl<T> CachedFinalityProvider<T> {
         self.inner.reset(finality)
     }
 
+    /// Remove the cached heights above the given one, e.g. after the parent reorged.
+    pub fn rollback(&self, height: BlockHeight) -> Stm<()> {
+        self.inner.rollback(height)
+    }
+
     pub fn new_parent_view(
         &self,
         height: BlockHeight,
@@ -234,6 +260,21 @@ impl<T> CachedFinalityProvider<T> {
         self.inner.cached_blocks()
     }
 
+    /// Stop proposing new parent finality, e.g. during an incident on the parent chain,
+    /// while still syncing with the parent and checking the proposals of others.
+    pub fn pause(&self) -> Stm<()> {
+        self.paused.write(true)
+    }
+
+    /// Resume proposing new parent finality.
+    pub fn resume(&self) -> Stm<()> {
+        self.paused.write(false)
+    }
+
+    pub fn is_paused(&self) -> Stm<bool> {
+        self.paused.read_clone()
+    }
+
     pub fn first_non_null_block(&self, height: BlockHeight) -> Stm<Option<BlockHeight>> {
         self.inner.first_non_null_block(height)
     }
@@ -334,6 +375,13 @@ mod tests {
                 block_hash: r.0,
             })
         }
+
+        async fn get_last_bottom_up_checkpoint_height(
+            &self,
+            _height: BlockHeight,
+        ) -> anyhow::Result<BlockHeight> {
+            Ok(0)
+        }
     }
 
     fn new_provider(
@@ -346,6 +394,9 @@ mod tests {
             exponential_retry_limit: 0,
             max_proposal_range: Some(1),
             max_cache_blocks: None,
+            parallel_fetches: None,
+            max_reorg_lookback: None,
+            finality_rule: None,
             proposal_delay: None,
         };
         let genesis_epoch = blocks.lower_bound().unwrap();
@@ -12,7 +12,8 @@ use ipc_api::staking::StakingChangeRequest;
 
 pub use fetch::CachedFinalityProvider;
 
-pub(crate) type ParentViewPayload = (BlockHash, Vec<StakingChangeRequest>, Vec<IpcEnvelope>);
+/// The block hash, validator changes and top-down messages observed at a parent height.
+pub type ParentViewPayload = (BlockHash, Vec<StakingChangeRequest>, Vec<IpcEnvelope>);
 
 fn ensure_sequential<T, F: Fn(&T) -> u64>(msgs: &[T], f: F) -> StmResult<(), Error> {
     if msgs.is_empty() {
@@ -88,6 +89,13 @@ mod tests {
                 block_hash: vec![],
             })
         }
+
+        async fn get_last_bottom_up_checkpoint_height(
+            &self,
+            _height: BlockHeight,
+        ) -> anyhow::Result<BlockHeight> {
+            Ok(0)
+        }
     }
 
     fn mocked_agent_proxy() -> Arc<MockedParentQuery> {
@@ -109,6 +117,9 @@ mod tests {
             exponential_retry_limit: 10,
             max_proposal_range: None,
             max_cache_blocks: None,
+            parallel_fetches: None,
+            max_reorg_lookback: None,
+            finality_rule: None,
             proposal_delay: None,
         };
 
@@ -174,4 +185,34 @@ mod tests {
         .await
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_pause_proposals() {
+        let provider = new_provider();
+
+        atomically_or_err(|| {
+            for i in 10..=100 {
+                provider.new_parent_view(i, Some((vec![1u8; 32], vec![], vec![])))?;
+            }
+            assert!(provider.next_proposal()?.is_some());
+
+            provider.pause()?;
+            assert!(provider.is_paused()?);
+            assert!(provider.next_proposal()?.is_none());
+
+            // the proposals of others are still checked
+            let finality = IPCParentFinality {
+                height: 50,
+                block_hash: vec![1u8; 32],
+            };
+            assert!(provider.check_proposal(&finality)?);
+
+            provider.resume()?;
+            assert!(provider.next_proposal()?.is_some());
+
+            Ok(())
+        })
+        .await
+        .unwrap();
+    }
 }
@@ -4,14 +4,16 @@
 use crate::finality::{
     ensure_sequential, topdown_cross_msgs, validator_changes, ParentViewPayload,
 };
-use crate::{BlockHash, BlockHeight, Config, Error, IPCParentFinality, SequentialKeyCache};
+use crate::{
+    now_millis, BlockHash, BlockHeight, Config, Error, IPCParentFinality, SequentialKeyCache,
+};
 use async_stm::{abort, atomically, Stm, StmResult, TVar};
 use ipc_api::cross::IpcEnvelope;
 use ipc_api::staking::StakingChangeRequest;
 use std::cmp::min;
 
 use fendermint_tracing::emit;
-use fendermint_vm_event::ParentFinalityCommitted;
+use fendermint_vm_event::{ParentFinalityCommitted, ParentFinalityLatency};
 
 /// Finality provider that can handle null blocks
 #[derive(Clone)]
@@ -23,6 +25,9 @@ pub struct FinalityWithNull {
     /// This is a in memory view of the committed parent finality. We need this as a starting point
     /// for populating the cache
     last_committed_finality: TVar<Option<IPCParentFinality>>,
+    /// When the cached heights were synced, in milliseconds since the epoch, to measure
+    /// how long it takes for them to be committed.
+    synced_at: TVar<im::OrdMap<BlockHeight, u64>>,
 }
 
 impl FinalityWithNull {
@@ -36,6 +41,7 @@ impl FinalityWithNull {
             genesis_epoch,
             cached_data: TVar::new(SequentialKeyCache::sequential()),
             last_committed_finality: TVar::new(committed_finality),
+            synced_at: TVar::default(),
         }
     }
 
@@ -66,16 +72,31 @@ impl FinalityWithNull {
     /// Clear the cache and set the committed finality to the provided value
     pub fn reset(&self, finality: IPCParentFinality) -> Stm<()> {
         self.cached_data.write(SequentialKeyCache::sequential())?;
+        self.synced_at.write(im::OrdMap::new())?;
         self.last_committed_finality.write(Some(finality))
     }
 
+    /// Remove the cached heights above the given one, e.g. after the parent reorged.
+    pub fn rollback(&self, height: BlockHeight) -> Stm<()> {
+        self.cached_data.update(|mut cache| {
+            cache.remove_key_above(height);
+            cache
+        })?;
+        self.synced_at
+            .update(|synced_at| synced_at.split(&height).0)
+    }
+
     pub fn new_parent_view(
         &self,
         height: BlockHeight,
         maybe_payload: Option<ParentViewPayload>,
     ) -> StmResult<(), Error> {
         if let Some((block_hash, validator_changes, top_down_msgs)) = maybe_payload {
-            self.parent_block_filled(height, block_hash, validator_changes, top_down_msgs)
+            self.parent_block_filled(height, block_hash, validator_changes, top_down_msgs)?;
+            self.synced_at.update_mut(|synced_at| {
+                synced_at.insert(height, now_millis());
+            })?;
+            Ok(())
         } else {
             self.parent_null_round(height)
         }
@@ -119,6 +140,11 @@ impl FinalityWithNull {
             cache
         })?;
 
+        let synced_at = self.synced_at.modify(|synced_at| {
+            let (_, synced, above) = synced_at.split_lookup(&height);
+            (above, synced)
+        })?;
+
         let hash = hex::encode(&finality.block_hash);
 
         self.last_committed_finality.write(Some(finality))?;
@@ -129,6 +155,14 @@ impl FinalityWithNull {
             block_hash: &hash
         });
 
+        // Blocks this node has not synced itself, e.g. while catching up, have nothing to measure.
+        if let Some(synced_at) = synced_at {
+            emit!(ParentFinalityLatency {
+                block_height: height,
+                latency_ms: now_millis().saturating_sub(synced_at),
+            });
+        }
+
         Ok(())
     }
 }
@@ -376,6 +410,9 @@ mod tests {
             exponential_retry_limit: 0,
             max_proposal_range: Some(6),
             max_cache_blocks: None,
+            parallel_fetches: None,
+            max_reorg_lookback: None,
+            finality_rule: None,
             proposal_delay: Some(2),
         };
         let committed_finality = IPCParentFinality {
@@ -9,6 +9,7 @@ pub mod sync;
 pub mod convert;
 pub mod proxy;
 mod toggle;
+pub mod verify;
 pub mod voting;
 
 use async_stm::Stm;
@@ -23,7 +24,7 @@ use std::time::Duration;
 
 pub use crate::cache::{SequentialAppendError, SequentialKeyCache, ValueIter};
 pub use crate::error::Error;
-pub use crate::finality::CachedFinalityProvider;
+pub use crate::finality::{CachedFinalityProvider, ParentViewPayload};
 pub use crate::toggle::Toggle;
 
 pub type BlockHeight = u64;
@@ -36,6 +37,8 @@ pub(crate) const NULL_ROUND_ERR_MSG: &str = "requested epoch was a null round";
 pub(crate) const DEFAULT_MAX_PROPOSAL_RANGE: BlockHeight = 100;
 pub(crate) const DEFAULT_MAX_CACHE_BLOCK: BlockHeight = 500;
 pub(crate) const DEFAULT_PROPOSAL_DELAY: BlockHeight = 2;
+pub(crate) const DEFAULT_PARALLEL_FETCHES: usize = 10;
+pub(crate) const DEFAULT_MAX_REORG_LOOKBACK: BlockHeight = 100;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -55,6 +58,36 @@ pub struct Config {
     /// Max number of blocks that should be stored in cache
     pub max_cache_blocks: Option<BlockHeight>,
     pub proposal_delay: Option<BlockHeight>,
+    /// The max number of parent heights to fetch concurrently while catching up
+    pub parallel_fetches: Option<usize>,
+    /// The max number of blocks to walk back looking for where we diverged from the parent
+    /// after a reorg, before falling back to syncing again from the last committed finality
+    pub max_reorg_lookback: Option<BlockHeight>,
+    /// How to decide which parent blocks are final
+    pub finality_rule: Option<ParentFinalityRule>,
+}
+
+/// The rule deciding which blocks of the parent chain are final, and thus safe to propose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParentFinalityRule {
+    /// Blocks are final once they are `chain_head_delay` blocks behind the chain head,
+    /// e.g. with the deterministic finality of Filecoin, or a number of confirmations
+    /// on proof-of-work style chains.
+    #[default]
+    Confirmations,
+    /// Blocks are final up to the block with the `finalized` tag of the Ethereum JSON-RPC API,
+    /// e.g. the one finalized by the beacon chain, less `chain_head_delay` blocks.
+    FinalizedTag,
+}
+
+impl Display for ParentFinalityRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Confirmations => write!(f, "confirmations"),
+            Self::FinalizedTag => write!(f, "finalized_tag"),
+        }
+    }
 }
 
 impl Config {
@@ -72,6 +105,9 @@ impl Config {
             max_proposal_range: None,
             max_cache_blocks: None,
             proposal_delay: None,
+            parallel_fetches: None,
+            max_reorg_lookback: None,
+            finality_rule: None,
         }
     }
 
@@ -85,6 +121,21 @@ impl Config {
         self
     }
 
+    pub fn with_parallel_fetches(mut self, parallel_fetches: usize) -> Self {
+        self.parallel_fetches = Some(parallel_fetches);
+        self
+    }
+
+    pub fn with_max_reorg_lookback(mut self, max_reorg_lookback: BlockHeight) -> Self {
+        self.max_reorg_lookback = Some(max_reorg_lookback);
+        self
+    }
+
+    pub fn with_finality_rule(mut self, finality_rule: ParentFinalityRule) -> Self {
+        self.finality_rule = Some(finality_rule);
+        self
+    }
+
     pub fn max_proposal_range(&self) -> BlockHeight {
         self.max_proposal_range
             .unwrap_or(DEFAULT_MAX_PROPOSAL_RANGE)
@@ -97,6 +148,21 @@ impl Config {
     pub fn max_cache_blocks(&self) -> BlockHeight {
         self.max_cache_blocks.unwrap_or(DEFAULT_MAX_CACHE_BLOCK)
     }
+
+    pub fn max_reorg_lookback(&self) -> BlockHeight {
+        self.max_reorg_lookback
+            .unwrap_or(DEFAULT_MAX_REORG_LOOKBACK)
+    }
+
+    pub fn finality_rule(&self) -> ParentFinalityRule {
+        self.finality_rule.unwrap_or_default()
+    }
+
+    pub fn parallel_fetches(&self) -> usize {
+        self.parallel_fetches
+            .unwrap_or(DEFAULT_PARALLEL_FETCHES)
+            .max(1)
+    }
 }
 
 /// The finality view for IPC parent at certain height.
@@ -145,6 +211,11 @@ pub trait ParentViewProvider {
         from: BlockHeight,
         to: BlockHeight,
     ) -> anyhow::Result<Vec<IpcEnvelope>>;
+    /// Get the height of the last bottom-up checkpoint committed in the parent at a height.
+    async fn last_bottom_up_checkpoint_height(
+        &self,
+        height: BlockHeight,
+    ) -> anyhow::Result<BlockHeight>;
 }
 
 pub trait ParentFinalityProvider: ParentViewProvider {
@@ -160,6 +231,32 @@ pub trait ParentFinalityProvider: ParentViewProvider {
     ) -> Stm<()>;
 }
 
+/// A parent finality committed by the subnet, along with the parent data it made the subnet execute.
+#[derive(Debug, Clone)]
+pub struct CommittedFinality {
+    /// The subnet block height at which the finality was committed.
+    pub block_height: BlockHeight,
+    pub finality: IPCParentFinality,
+    /// The first parent height whose side effects were executed with the finality.
+    pub executed_from: BlockHeight,
+    /// The last parent height whose side effects were executed with the finality, inclusive.
+    pub executed_to: BlockHeight,
+    pub validator_changes: Vec<StakingChangeRequest>,
+    pub top_down_msgs: Vec<IpcEnvelope>,
+}
+
+/// Append-only log of the committed parent finalities, keyed by the parent height, so that it's
+/// possible to tell which parent data the subnet consumed without replaying the whole chain.
+pub trait ParentFinalityLog: Send + Sync {
+    /// The finality committed for a parent height, if any.
+    fn get(&self, height: BlockHeight) -> anyhow::Result<Option<CommittedFinality>>;
+    /// Add a committed finality to the log.
+    ///
+    /// Appending the same entry again, e.g. when a block is re-executed after a crash, is allowed,
+    /// but a different entry at the same height is an error.
+    fn append(&self, entry: &CommittedFinality) -> anyhow::Result<()>;
+}
+
 /// If res is null round error, returns the default value from f()
 pub(crate) fn handle_null_round<T, F: FnOnce() -> T>(
     res: anyhow::Result<T>,
@@ -177,10 +274,18 @@ pub(crate) fn handle_null_round<T, F: FnOnce() -> T>(
     }
 }
 
-pub(crate) fn is_null_round_error(err: &anyhow::Error) -> bool {
+pub fn is_null_round_error(err: &anyhow::Error) -> bool {
     is_null_round_str(&err.to_string())
 }
 
 pub(crate) fn is_null_round_str(s: &str) -> bool {
     s.contains(NULL_ROUND_ERR_MSG)
 }
+
+/// Milliseconds since the Unix epoch, for measuring latencies in the metrics.
+pub(crate) fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
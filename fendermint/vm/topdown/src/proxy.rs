@@ -1,7 +1,7 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use crate::BlockHeight;
+use crate::{BlockHeight, ParentFinalityRule};
 use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::clock::ChainEpoch;
@@ -10,6 +10,8 @@ use ipc_api::staking::StakingChangeRequest;
 use ipc_api::subnet_id::SubnetID;
 use ipc_provider::manager::{GetBlockHashResult, TopDownQueryPayload};
 use ipc_provider::IpcProvider;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tracing::instrument;
 
 /// The interface to querying state of the parent
@@ -18,6 +20,12 @@ pub trait ParentQueryProxy {
     /// Get the parent chain head block number or block height
     async fn get_chain_head_height(&self) -> anyhow::Result<BlockHeight>;
 
+    /// Get the height of the latest block finalized by the consensus of the parent, for chains
+    /// which report one, such as Ethereum. Defaults to the chain head.
+    async fn get_finalized_chain_head_height(&self) -> anyhow::Result<BlockHeight> {
+        self.get_chain_head_height().await
+    }
+
     /// Get the genesis epoch of the child subnet, i.e. the epoch that the subnet was created in
     /// the parent subnet.
     async fn get_genesis_epoch(&self) -> anyhow::Result<BlockHeight>;
@@ -36,6 +44,22 @@ pub trait ParentQueryProxy {
         &self,
         height: BlockHeight,
     ) -> anyhow::Result<TopDownQueryPayload<Vec<StakingChangeRequest>>>;
+
+    /// Get the height of the last bottom-up checkpoint of the child subnet committed in the
+    /// parent, as of the block at the specified height.
+    async fn get_last_bottom_up_checkpoint_height(
+        &self,
+        height: BlockHeight,
+    ) -> anyhow::Result<BlockHeight>;
+}
+
+/// Persistent storage for the block hashes of finalized parent heights.
+///
+/// These never change, so once fetched they can be served locally instead of
+/// asking the parent RPC node again, e.g. after a restart of the syncer.
+pub trait BlockHashStore: Send + Sync {
+    fn get(&self, height: BlockHeight) -> anyhow::Result<Option<GetBlockHashResult>>;
+    fn put(&self, height: BlockHeight, block_hash: &GetBlockHashResult) -> anyhow::Result<()>;
 }
 
 /// The proxy to the subnet's parent
@@ -46,6 +70,16 @@ pub struct IPCProviderProxy {
     parent_subnet: SubnetID,
     /// The child subnet that this node belongs to.
     child_subnet: SubnetID,
+    /// Optional cache of the block hashes at finalized parent heights.
+    block_hash_store: Option<Arc<dyn BlockHashStore>>,
+    /// Number of blocks behind the parent chain head after which a block is considered final.
+    finality_delay: BlockHeight,
+    /// The highest parent chain head seen so far.
+    chain_head: AtomicU64,
+    /// The rule deciding which blocks are final, hence safe to cache.
+    finality_rule: ParentFinalityRule,
+    /// The highest finalized parent chain head seen so far.
+    finalized_chain_head: AtomicU64,
 }
 
 impl IPCProviderProxy {
@@ -57,15 +91,57 @@ impl IPCProviderProxy {
             ipc_provider,
             parent_subnet: parent,
             child_subnet: target_subnet,
+            block_hash_store: None,
+            finality_delay: 0,
+            chain_head: AtomicU64::new(0),
+            finality_rule: ParentFinalityRule::default(),
+            finalized_chain_head: AtomicU64::new(0),
         })
     }
+
+    /// Cache the block hashes of heights which are at least `finality_delay` blocks
+    /// behind the parent chain head, and look them up before querying the parent.
+    pub fn with_block_hash_store(
+        mut self,
+        store: Arc<dyn BlockHashStore>,
+        finality_delay: BlockHeight,
+    ) -> Self {
+        self.block_hash_store = Some(store);
+        self.finality_delay = finality_delay;
+        self
+    }
+
+    pub fn with_finality_rule(mut self, finality_rule: ParentFinalityRule) -> Self {
+        self.finality_rule = finality_rule;
+        self
+    }
+
+    /// Check if a height is final, based on the latest chain head we have seen.
+    fn is_final(&self, height: BlockHeight) -> bool {
+        let chain_head = match self.finality_rule {
+            ParentFinalityRule::Confirmations => self.chain_head.load(Ordering::Relaxed),
+            ParentFinalityRule::FinalizedTag => self.finalized_chain_head.load(Ordering::Relaxed),
+        };
+        chain_head > 0 && height.saturating_add(self.finality_delay) <= chain_head
+    }
 }
 
 #[async_trait]
 impl ParentQueryProxy for IPCProviderProxy {
     async fn get_chain_head_height(&self) -> anyhow::Result<BlockHeight> {
-        let height = self.ipc_provider.chain_head(&self.parent_subnet).await?;
-        Ok(height as BlockHeight)
+        let height = self.ipc_provider.chain_head(&self.parent_subnet).await? as BlockHeight;
+        self.chain_head.fetch_max(height, Ordering::Relaxed);
+        Ok(height)
+    }
+
+    async fn get_finalized_chain_head_height(&self) -> anyhow::Result<BlockHeight> {
+        let height = self
+            .ipc_provider
+            .finalized_chain_head(&self.parent_subnet)
+            .await? as BlockHeight;
+        self.finalized_chain_head
+            .fetch_max(height, Ordering::Relaxed);
+        Ok(height)
     }
 
     /// Get the genesis epoch of the child subnet, i.e. the epoch that the subnet was created in
@@ -78,9 +154,28 @@ impl ParentQueryProxy for IPCProviderProxy {
     /// Getting the block hash at the target height.
     #[instrument(skip(self))]
     async fn get_block_hash(&self, height: BlockHeight) -> anyhow::Result<GetBlockHashResult> {
-        self.ipc_provider
+        if let Some(ref store) = self.block_hash_store {
+            match store.get(height) {
+                Ok(Some(r)) => return Ok(r),
+                Ok(None) => {}
+                Err(e) => tracing::warn!(error = e.to_string(), "cannot read cached block hash"),
+            }
+        }
+
+        let r = self
+            .ipc_provider
             .get_block_hash(&self.parent_subnet, height as ChainEpoch)
-            .await
+            .await?;
+
+        if let Some(ref store) = self.block_hash_store {
+            if self.is_final(height) {
+                if let Err(e) = store.put(height, &r) {
+                    tracing::warn!(error = e.to_string(), "cannot cache block hash");
+                }
+            }
+        }
+
+        Ok(r)
     }
 
     /// Get the top down messages from the starting to the ending height.
@@ -115,4 +210,17 @@ impl ParentQueryProxy for IPCProviderProxy {
                 v
             })
     }
+
+    /// Get the last bottom-up checkpoint height committed in the parent at the specified height.
+    #[instrument(skip(self))]
+    async fn get_last_bottom_up_checkpoint_height(
+        &self,
+        height: BlockHeight,
+    ) -> anyhow::Result<BlockHeight> {
+        let h = self
+            .ipc_provider
+            .last_bottom_up_checkpoint_height_at(&self.child_subnet, height as ChainEpoch)
+            .await?;
+        Ok(h as BlockHeight)
+    }
 }
@@ -8,8 +8,12 @@ mod tendermint;
 use crate::proxy::ParentQueryProxy;
 use crate::sync::syncer::LotusParentSyncer;
 use crate::sync::tendermint::TendermintAwareSyncer;
+use crate::verify::ParentBlockVerifier;
 use crate::voting::VoteTally;
-use crate::{CachedFinalityProvider, Config, IPCParentFinality, ParentFinalityProvider, Toggle};
+use crate::{
+    BlockHeight, CachedFinalityProvider, Config, IPCParentFinality, ParentFinalityProvider,
+    ParentViewPayload, Toggle,
+};
 use anyhow::anyhow;
 use async_stm::atomically;
 use ethers::utils::hex;
@@ -29,6 +33,19 @@ pub trait ParentFinalityStateQuery {
     fn get_power_table(&self) -> anyhow::Result<Option<Vec<Validator<Power>>>>;
 }
 
+/// Persistent storage for the parent view observed by the syncer, so that a restarted node
+/// can resume syncing from the last height it observed, instead of fetching everything since
+/// the last committed finality again.
+///
+/// The store doesn't have to keep everything; whatever it forgot is fetched again.
+pub trait ParentViewStore: Send + Sync {
+    /// The view at a height, `None` if it isn't stored, `Some(None)` if it was a null round.
+    fn get(&self, height: BlockHeight) -> anyhow::Result<Option<Option<ParentViewPayload>>>;
+    fn put(&self, height: BlockHeight, view: Option<&ParentViewPayload>) -> anyhow::Result<()>;
+    /// Forget the views above the height, e.g. because they were on a fork the parent abandoned.
+    fn remove_above(&self, height: BlockHeight) -> anyhow::Result<()>;
+}
+
 /// Queries the starting finality for polling. First checks the committed finality, if none, that
 /// means the chain has just started, then query from the parent to get the genesis epoch.
 async fn query_starting_finality<T, P>(
@@ -110,6 +127,8 @@ pub async fn launch_polling_syncer<T, C, P>(
     view_provider: Arc<Toggle<CachedFinalityProvider<P>>>,
     vote_tally: VoteTally,
     parent_client: Arc<P>,
+    verifier: Option<Arc<dyn ParentBlockVerifier>>,
+    view_store: Option<Arc<dyn ParentViewStore>>,
     tendermint_client: C,
 ) -> anyhow::Result<()>
 where
@@ -152,6 +171,8 @@ where
         view_provider,
         vote_tally,
         parent_client,
+        verifier,
+        view_store,
         query,
         tendermint_client,
     );
@@ -165,6 +186,8 @@ fn start_syncing<T, C, P>(
     view_provider: Arc<Toggle<CachedFinalityProvider<P>>>,
     vote_tally: VoteTally,
     parent_proxy: Arc<P>,
+    verifier: Option<Arc<dyn ParentBlockVerifier>>,
+    view_store: Option<Arc<dyn ParentViewStore>>,
     query: Arc<T>,
     tendermint_client: C,
 ) where
@@ -178,7 +201,13 @@ fn start_syncing<T, C, P>(
     tokio::spawn(async move {
         let lotus_syncer =
             LotusParentSyncer::new(config, parent_proxy, view_provider, vote_tally, query)
-                .expect("");
+                .expect("")
+                .with_verifier(verifier)
+                .with_view_store(view_store);
+
+        if let Err(e) = lotus_syncer.restore().await {
+            tracing::error!(error = e.to_string(), "failed to restore the parent view");
+        }
 
         let mut tendermint_syncer = TendermintAwareSyncer::new(lotus_syncer, tendermint_client);
 
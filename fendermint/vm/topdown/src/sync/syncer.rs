@@ -4,19 +4,24 @@
 
 use crate::finality::ParentViewPayload;
 use crate::proxy::ParentQueryProxy;
-use crate::sync::{query_starting_finality, ParentFinalityStateQuery};
+use crate::sync::{query_starting_finality, ParentFinalityStateQuery, ParentViewStore};
+use crate::verify::ParentBlockVerifier;
 use crate::voting::{self, VoteTally};
 use crate::{
-    is_null_round_str, BlockHash, BlockHeight, CachedFinalityProvider, Config, Error, Toggle,
+    is_null_round_str, BlockHash, BlockHeight, CachedFinalityProvider, Config, Error,
+    ParentFinalityRule, Toggle,
 };
 use anyhow::anyhow;
 use async_stm::{atomically, atomically_or_err, StmError};
 use ethers::utils::hex;
+use ipc_api::staking::StakingChangeRequest;
+use ipc_provider::manager::GetBlockHashResult;
+use std::collections::VecDeque;
 use std::sync::Arc;
 use tracing::instrument;
 
 use fendermint_tracing::emit;
-use fendermint_vm_event::{BlockHashHex, NewParentView};
+use fendermint_vm_event::{BlockHashHex, NewParentView, ParentFinalityLag};
 
 /// Parent syncer that constantly poll parent. This struct handles lotus null blocks and deferred
 /// execution. For ETH based parent, it should work out of the box as well.
@@ -26,6 +31,13 @@ pub(crate) struct LotusParentSyncer<T, P> {
     provider: Arc<Toggle<CachedFinalityProvider<P>>>,
     vote_tally: VoteTally,
     query: Arc<T>,
+    /// Checks the fetched blocks independently of the parent endpoint, if configured.
+    verifier: Option<Arc<dyn ParentBlockVerifier>>,
+    /// Persists the observed parent view, if configured.
+    view_store: Option<Arc<dyn ParentViewStore>>,
+    /// The configuration number of the last validator change added to the cache, to check
+    /// that the next one follows it; `None` if it isn't known, e.g. after a reorg.
+    last_configuration_number: Option<u64>,
 
     /// For testing purposes, we can sync one block at a time.
     /// Not part of `Config` as it's a very niche setting;
@@ -54,10 +66,113 @@ where
             provider,
             vote_tally,
             query,
+            verifier: None,
+            view_store: None,
+            last_configuration_number: None,
             sync_many: true,
         })
     }
 
+    /// Only accept parent blocks the verifier confirms.
+    pub fn with_verifier(mut self, verifier: Option<Arc<dyn ParentBlockVerifier>>) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    /// Persist the parent view as it's observed, to be restored after a restart.
+    pub fn with_view_store(mut self, view_store: Option<Arc<dyn ParentViewStore>>) -> Self {
+        self.view_store = view_store;
+        self
+    }
+
+    /// Put the heights persisted by a previous run back into the cache, continuing from
+    /// the latest height in it, until the first one which wasn't stored.
+    ///
+    /// If there is a verifier, the restored blocks have to pass it, with the parent hash
+    /// being the hash of the previous block restored, so they have to form a chain. Without
+    /// one they aren't checked against the parent again; if it reorged while the node was
+    /// down, the next fetched block won't follow them, which rolls the cache back.
+    pub async fn restore(&self) -> anyhow::Result<()> {
+        let Some(ref store) = self.view_store else {
+            return Ok(());
+        };
+
+        let (latest_height, mut parent_block_hash) = self.latest_cached_data().await;
+        let mut height = latest_height;
+
+        while !self.exceed_cache_size_limit().await {
+            let Some(view) = store.get(height + 1)? else {
+                break;
+            };
+
+            if let Some(ref verifier) = self.verifier {
+                let block = view.as_ref().map(|v| GetBlockHashResult {
+                    parent_block_hash: parent_block_hash.clone(),
+                    block_hash: v.0.clone(),
+                });
+                if let Err(e) = verifier.verify(height + 1, block.as_ref()).await {
+                    tracing::warn!(
+                        height = height + 1,
+                        error = e.to_string(),
+                        "restored parent view failed verification, fetching it again"
+                    );
+                    break;
+                }
+            }
+
+            height += 1;
+            if let Some(ref v) = view {
+                parent_block_hash = v.0.clone();
+            }
+
+            atomically_or_err::<_, Error, _>(|| {
+                self.provider.new_parent_view(height, view.clone())?;
+                self.vote_tally
+                    .add_block(height, view.as_ref().map(|v| v.0.clone()))
+                    .map_err(map_voting_err)?;
+                Ok(())
+            })
+            .await?;
+        }
+
+        if height > latest_height {
+            tracing::info!(
+                from = latest_height + 1,
+                to = height,
+                "restored parent view from the store"
+            );
+        }
+        Ok(())
+    }
+
+    /// Forget the persisted views above the height, which the parent no longer has, so they
+    /// aren't restored after a restart.
+    fn forget_above(&self, height: BlockHeight) {
+        if let Some(ref store) = self.view_store {
+            if let Err(e) = store.remove_above(height) {
+                tracing::warn!(
+                    height,
+                    error = e.to_string(),
+                    "failed to remove parent views"
+                );
+            }
+        }
+    }
+
+    /// Persist the view we just added to the cache; failing to do so only means it has to be
+    /// fetched again after a restart.
+    fn persist(&self, height: BlockHeight, view: Option<&ParentViewPayload>) {
+        if let Some(ref store) = self.view_store {
+            if let Err(e) = store.put(height, view) {
+                tracing::warn!(
+                    height,
+                    error = e.to_string(),
+                    "failed to persist parent view"
+                );
+            }
+        }
+    }
+
     /// Insert the height into cache when we see a new non null block
     pub async fn sync(&mut self) -> anyhow::Result<()> {
         let chain_head = if let Some(h) = self.finalized_chain_head().await? {
@@ -66,7 +181,15 @@ where
             return Ok(());
         };
 
-        let (mut latest_height_fetched, mut first_non_null_parent_hash) =
+        if let Some(finality) = atomically(|| self.provider.last_committed_finality()).await {
+            emit!(ParentFinalityLag {
+                parent_height: chain_head,
+                finalized_height: finality.height,
+                lag: chain_head.saturating_sub(finality.height),
+            });
+        }
+
+        let (latest_height_fetched, mut first_non_null_parent_hash) =
             self.latest_cached_data().await;
         tracing::debug!(chain_head, latest_height_fetched, "syncing heights");
 
@@ -76,6 +199,7 @@ where
                 latest_height_fetched,
                 "chain head went backwards, potential reorg detected from height"
             );
+            self.last_configuration_number = None;
             return self.reset().await;
         }
 
@@ -88,36 +212,67 @@ where
             return Ok(());
         }
 
-        loop {
+        // Fetch the next heights concurrently, up to the configured number at a time,
+        // but apply them to the cache strictly in order, so the parent hashes can be
+        // checked against each other the same way as when fetching one by one.
+        let parallelism = if self.sync_many {
+            self.config.parallel_fetches()
+        } else {
+            1
+        };
+        let mut next_height = latest_height_fetched + 1;
+        let mut pending = VecDeque::new();
+
+        let result = loop {
+            while pending.len() < parallelism && next_height <= chain_head {
+                let proxy = self.parent_proxy.clone();
+                let verifier = self.verifier.clone();
+                let fetch = tokio::spawn(fetch_block(proxy, verifier, next_height));
+                pending.push_back((next_height, fetch));
+                next_height += 1;
+            }
+
+            let Some((height, fetch)) = pending.pop_front() else {
+                break Ok(());
+            };
+
             if self.exceed_cache_size_limit().await {
                 tracing::debug!("exceeded cache size limit");
-                break;
+                break Ok(());
             }
 
+            let block = match fetch.await {
+                Ok(block) => block,
+                Err(e) => Err(Error::CannotQueryParent(format!("fetch task: {e}"), height)),
+            };
+
             first_non_null_parent_hash = match self
-                .poll_next(latest_height_fetched + 1, first_non_null_parent_hash)
+                .poll_next(height, first_non_null_parent_hash, block)
                 .await
             {
                 Ok(h) => h,
                 Err(Error::ParentChainReorgDetected) => {
-                    tracing::warn!("potential reorg detected, clear cache and retry");
-                    self.reset().await?;
-                    break;
+                    tracing::warn!("potential reorg detected, rolling back to the common ancestor");
+                    self.last_configuration_number = None;
+                    break self.recover_from_reorg().await;
                 }
-                Err(e) => return Err(anyhow!(e)),
+                Err(e) => break Err(anyhow!(e)),
             };
 
-            latest_height_fetched += 1;
-
-            if latest_height_fetched == chain_head {
+            if height == chain_head {
                 tracing::debug!("reached the tip of the chain");
-                break;
+                break Ok(());
             } else if !self.sync_many {
-                break;
+                break Ok(());
             }
+        };
+
+        // Whatever is still in flight is fetched again on the next sync.
+        for (_, fetch) in pending {
+            fetch.abort();
         }
 
-        Ok(())
+        result
     }
 }
 
@@ -173,11 +328,13 @@ where
         .await
     }
 
-    /// Poll the next block height. Returns finalized and executed block data.
+    /// Apply the data fetched for the next block height to the cache. Returns the hash of
+    /// the last non-null block, to check the parent hash of the next height against.
     async fn poll_next(
         &mut self,
         height: BlockHeight,
         parent_block_hash: BlockHash,
+        block: Result<Option<ParentBlock>, Error>,
     ) -> Result<BlockHash, Error> {
         tracing::debug!(
             height,
@@ -185,55 +342,59 @@ where
             "polling height with parent hash"
         );
 
-        let block_hash_res = match self.parent_proxy.get_block_hash(height).await {
-            Ok(res) => res,
-            Err(e) => {
-                let err = e.to_string();
-                if is_null_round_str(&err) {
-                    tracing::debug!(
-                        height,
-                        "detected null round at height, inserted None to cache"
-                    );
+        let Some(block) = block? else {
+            tracing::debug!(
+                height,
+                "detected null round at height, inserted None to cache"
+            );
 
-                    atomically_or_err::<_, Error, _>(|| {
-                        self.provider.new_parent_view(height, None)?;
-                        self.vote_tally
-                            .add_block(height, None)
-                            .map_err(map_voting_err)?;
-                        Ok(())
-                    })
-                    .await?;
-
-                    emit!(NewParentView {
-                        is_null: true,
-                        block_height: height,
-                        block_hash: None::<BlockHashHex>,
-                        num_msgs: 0,
-                        num_validator_changes: 0
-                    });
-
-                    // Null block received, no block hash for the current height being polled.
-                    // Return the previous parent hash as the non-null block hash.
-                    return Ok(parent_block_hash);
-                }
-                return Err(Error::CannotQueryParent(
-                    format!("get_block_hash: {e}"),
-                    height,
-                ));
-            }
+            atomically_or_err::<_, Error, _>(|| {
+                self.provider.new_parent_view(height, None)?;
+                self.vote_tally
+                    .add_block(height, None)
+                    .map_err(map_voting_err)?;
+                Ok(())
+            })
+            .await?;
+
+            self.persist(height, None);
+
+            emit!(NewParentView {
+                is_null: true,
+                block_height: height,
+                block_hash: None::<BlockHashHex>,
+                num_msgs: 0,
+                num_validator_changes: 0
+            });
+
+            // Null block received, no block hash for the current height being polled.
+            // Return the previous parent hash as the non-null block hash.
+            return Ok(parent_block_hash);
         };
 
-        if block_hash_res.parent_block_hash != parent_block_hash {
+        if block.parent_block_hash != parent_block_hash {
             tracing::warn!(
                 height,
-                parent_hash = hex::encode(&block_hash_res.parent_block_hash),
+                parent_hash = hex::encode(&block.parent_block_hash),
                 previous_hash = hex::encode(&parent_block_hash),
                 "parent block hash diff than previous hash",
             );
             return Err(Error::ParentChainReorgDetected);
         }
 
-        let data = self.fetch_data(height, block_hash_res.block_hash).await?;
+        let data = block.payload;
+
+        if let (Some(last), Some(first)) = (self.last_configuration_number, data.1.first()) {
+            if first.configuration_number != last + 1 {
+                return Err(Error::InconsistentParentData(
+                    format!(
+                        "validator change {} doesn't follow the last one seen, {last}",
+                        first.configuration_number
+                    ),
+                    height,
+                ));
+            }
+        }
 
         tracing::debug!(
             height,
@@ -255,6 +416,12 @@ where
         })
         .await?;
 
+        self.persist(height, Some(&data));
+
+        if let Some(last) = data.1.last() {
+            self.last_configuration_number = Some(last.configuration_number);
+        }
+
         emit!(NewParentView {
             is_null: false,
             block_height: height,
@@ -266,49 +433,13 @@ where
         Ok(data.0)
     }
 
-    #[instrument(skip(self))]
-    async fn fetch_data(
-        &self,
-        height: BlockHeight,
-        block_hash: BlockHash,
-    ) -> Result<ParentViewPayload, Error> {
-        let changes_res = self
-            .parent_proxy
-            .get_validator_changes(height)
-            .await
-            .map_err(|e| Error::CannotQueryParent(format!("get_validator_changes: {e}"), height))?;
-
-        if changes_res.block_hash != block_hash {
-            tracing::warn!(
-                height,
-                change_set_hash = hex::encode(&changes_res.block_hash),
-                block_hash = hex::encode(&block_hash),
-                "change set block hash does not equal block hash",
-            );
-            return Err(Error::ParentChainReorgDetected);
-        }
-
-        let topdown_msgs_res = self
-            .parent_proxy
-            .get_top_down_msgs(height)
-            .await
-            .map_err(|e| Error::CannotQueryParent(format!("get_top_down_msgs: {e}"), height))?;
-
-        if topdown_msgs_res.block_hash != block_hash {
-            tracing::warn!(
-                height,
-                topdown_msgs_hash = hex::encode(&topdown_msgs_res.block_hash),
-                block_hash = hex::encode(&block_hash),
-                "topdown messages block hash does not equal block hash",
-            );
-            return Err(Error::ParentChainReorgDetected);
-        }
-
-        Ok((block_hash, changes_res.value, topdown_msgs_res.value))
-    }
-
     async fn finalized_chain_head(&self) -> anyhow::Result<Option<BlockHeight>> {
-        let parent_chain_head_height = self.parent_proxy.get_chain_head_height().await?;
+        let parent_chain_head_height = match self.config.finality_rule() {
+            ParentFinalityRule::Confirmations => self.parent_proxy.get_chain_head_height().await?,
+            ParentFinalityRule::FinalizedTag => {
+                self.parent_proxy.get_finalized_chain_head_height().await?
+            }
+        };
         // sanity check
         if parent_chain_head_height < self.config.chain_head_delay {
             tracing::debug!("latest height not more than the chain head delay");
@@ -321,14 +452,191 @@ where
         ))
     }
 
+    /// Walk back the cache to the last block which the parent still has, and roll back to
+    /// it, so syncing continues on the new fork of the parent from there. Falls back to
+    /// resetting the cache if there is no such block within the configured lookback.
+    async fn recover_from_reorg(&self) -> anyhow::Result<()> {
+        let (latest_height, _) = self.latest_cached_data().await;
+        let finality_height = atomically(|| self.provider.last_committed_finality())
+            .await
+            .map(|f| f.height)
+            .unwrap_or_default();
+        let lowest_height = latest_height
+            .saturating_sub(self.config.max_reorg_lookback())
+            .max(finality_height);
+
+        for height in (lowest_height + 1..=latest_height).rev() {
+            // Null rounds can't be compared, the blocks around them tell if they changed.
+            let Some(cached_hash) = atomically(|| self.provider.block_hash(height)).await else {
+                continue;
+            };
+
+            let parent_hash = match self.parent_proxy.get_block_hash(height).await {
+                Ok(res) => res.block_hash,
+                Err(e) if is_null_round_str(&e.to_string()) => continue,
+                Err(e) => {
+                    return Err(e.context(format!("cannot query parent at height {height}")));
+                }
+            };
+
+            if parent_hash == cached_hash {
+                tracing::info!(
+                    height,
+                    rolled_back = latest_height - height,
+                    "rolled back the parent view to the common ancestor"
+                );
+                atomically(|| {
+                    self.provider.rollback(height)?;
+                    self.vote_tally.rollback(height)
+                })
+                .await;
+                self.forget_above(height);
+                return Ok(());
+            }
+        }
+
+        tracing::warn!(
+            lowest_height,
+            latest_height,
+            "no common ancestor with the parent within the reorg lookback, resetting"
+        );
+        self.reset().await
+    }
+
     /// Reset the cache in the face of a reorg
     async fn reset(&self) -> anyhow::Result<()> {
         let finality = query_starting_finality(&self.query, &self.parent_proxy).await?;
-        atomically(|| self.provider.reset(finality.clone())).await;
+        atomically(|| {
+            self.provider.reset(finality.clone())?;
+            // The tally has to follow the cache, otherwise it wouldn't take the blocks again.
+            self.vote_tally
+                .set_finalized(finality.height, finality.block_hash.clone())?;
+            self.vote_tally.rollback(finality.height)
+        })
+        .await;
+        self.forget_above(finality.height);
         Ok(())
     }
 }
 
+/// Everything fetched from the parent about a non-null block.
+struct ParentBlock {
+    parent_block_hash: BlockHash,
+    payload: ParentViewPayload,
+}
+
+/// Fetch the block at a parent height, or `None` if it's a null round. Doesn't touch the
+/// cache, so it can run concurrently with the fetches of other heights.
+async fn fetch_block<P>(
+    proxy: Arc<P>,
+    verifier: Option<Arc<dyn ParentBlockVerifier>>,
+    height: BlockHeight,
+) -> Result<Option<ParentBlock>, Error>
+where
+    P: ParentQueryProxy + Send + Sync + 'static,
+{
+    let block_hash_res = match proxy.get_block_hash(height).await {
+        Ok(res) => Some(res),
+        Err(e) => {
+            let err = e.to_string();
+            if !is_null_round_str(&err) {
+                return Err(Error::CannotQueryParent(
+                    format!("get_block_hash: {e}"),
+                    height,
+                ));
+            }
+            None
+        }
+    };
+
+    if let Some(verifier) = verifier {
+        if let Err(e) = verifier.verify(height, block_hash_res.as_ref()).await {
+            tracing::error!(
+                height,
+                error = e.to_string(),
+                "parent block failed verification"
+            );
+            return Err(Error::CannotVerifyParent(e.to_string(), height));
+        }
+    }
+
+    let Some(block_hash_res) = block_hash_res else {
+        return Ok(None);
+    };
+
+    let payload = fetch_data(proxy.as_ref(), height, block_hash_res.block_hash).await?;
+
+    Ok(Some(ParentBlock {
+        parent_block_hash: block_hash_res.parent_block_hash,
+        payload,
+    }))
+}
+
+#[instrument(skip(proxy))]
+async fn fetch_data<P>(
+    proxy: &P,
+    height: BlockHeight,
+    block_hash: BlockHash,
+) -> Result<ParentViewPayload, Error>
+where
+    P: ParentQueryProxy + Send + Sync + 'static,
+{
+    let (changes_res, topdown_msgs_res) = tokio::join!(
+        proxy.get_validator_changes(height),
+        proxy.get_top_down_msgs(height)
+    );
+
+    let changes_res = changes_res
+        .map_err(|e| Error::CannotQueryParent(format!("get_validator_changes: {e}"), height))?;
+
+    if changes_res.block_hash != block_hash {
+        tracing::warn!(
+            height,
+            change_set_hash = hex::encode(&changes_res.block_hash),
+            block_hash = hex::encode(&block_hash),
+            "change set block hash does not equal block hash",
+        );
+        return Err(Error::ParentChainReorgDetected);
+    }
+
+    let topdown_msgs_res = topdown_msgs_res
+        .map_err(|e| Error::CannotQueryParent(format!("get_top_down_msgs: {e}"), height))?;
+
+    if topdown_msgs_res.block_hash != block_hash {
+        tracing::warn!(
+            height,
+            topdown_msgs_hash = hex::encode(&topdown_msgs_res.block_hash),
+            block_hash = hex::encode(&block_hash),
+            "topdown messages block hash does not equal block hash",
+        );
+        return Err(Error::ParentChainReorgDetected);
+    }
+
+    ensure_contiguous_changes(height, &changes_res.value)?;
+
+    Ok((block_hash, changes_res.value, topdown_msgs_res.value))
+}
+
+/// Check that the validator changes at a height have consecutive configuration numbers,
+/// as the parent assigns them, otherwise the gateway would reject the ones after a gap.
+fn ensure_contiguous_changes(
+    height: BlockHeight,
+    changes: &[StakingChangeRequest],
+) -> Result<(), Error> {
+    for (prev, next) in changes.iter().zip(changes.iter().skip(1)) {
+        if next.configuration_number != prev.configuration_number + 1 {
+            return Err(Error::InconsistentParentData(
+                format!(
+                    "validator change {} follows {}",
+                    next.configuration_number, prev.configuration_number
+                ),
+                height,
+            ));
+        }
+    }
+    Ok(())
+}
+
 fn map_voting_err(e: StmError<voting::Error>) -> StmError<Error> {
     match e {
         StmError::Abort(e) => {
@@ -346,11 +654,12 @@ fn map_voting_err(e: StmError<voting::Error>) -> StmError<Error> {
 mod tests {
     use crate::proxy::ParentQueryProxy;
     use crate::sync::syncer::LotusParentSyncer;
-    use crate::sync::ParentFinalityStateQuery;
+    use crate::sync::{ParentFinalityStateQuery, ParentViewStore};
+    use crate::verify::WitnessVerifier;
     use crate::voting::VoteTally;
     use crate::{
         BlockHash, BlockHeight, CachedFinalityProvider, Config, IPCParentFinality,
-        SequentialKeyCache, Toggle, NULL_ROUND_ERR_MSG,
+        ParentFinalityRule, ParentViewPayload, SequentialKeyCache, Toggle, NULL_ROUND_ERR_MSG,
     };
     use anyhow::anyhow;
     use async_stm::atomically;
@@ -359,7 +668,8 @@ mod tests {
     use ipc_api::cross::IpcEnvelope;
     use ipc_api::staking::StakingChangeRequest;
     use ipc_provider::manager::{GetBlockHashResult, TopDownQueryPayload};
-    use std::sync::Arc;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
 
     /// How far behind the tip of the chain do we consider blocks final in the tests.
     const FINALITY_DELAY: u64 = 2;
@@ -379,6 +689,8 @@ mod tests {
 
     struct TestParentProxy {
         blocks: SequentialKeyCache<BlockHeight, Option<BlockHash>>,
+        /// The height of the block with the `finalized` tag, if the parent reports one.
+        finalized: Option<BlockHeight>,
     }
 
     #[async_trait]
@@ -387,6 +699,10 @@ mod tests {
             Ok(self.blocks.upper_bound().unwrap())
         }
 
+        async fn get_finalized_chain_head_height(&self) -> anyhow::Result<BlockHeight> {
+            self.finalized.ok_or_else(|| anyhow!("no finalized block"))
+        }
+
         async fn get_genesis_epoch(&self) -> anyhow::Result<BlockHeight> {
             Ok(self.blocks.lower_bound().unwrap() - 1)
         }
@@ -429,6 +745,34 @@ mod tests {
                 block_hash: self.blocks.get_value(height).cloned().unwrap().unwrap(),
             })
         }
+
+        async fn get_last_bottom_up_checkpoint_height(
+            &self,
+            _height: BlockHeight,
+        ) -> anyhow::Result<BlockHeight> {
+            Ok(0)
+        }
+    }
+
+    #[derive(Default)]
+    struct TestParentViewStore {
+        views: Mutex<BTreeMap<BlockHeight, Option<ParentViewPayload>>>,
+    }
+
+    impl ParentViewStore for TestParentViewStore {
+        fn get(&self, height: BlockHeight) -> anyhow::Result<Option<Option<ParentViewPayload>>> {
+            Ok(self.views.lock().unwrap().get(&height).cloned())
+        }
+
+        fn put(&self, height: BlockHeight, view: Option<&ParentViewPayload>) -> anyhow::Result<()> {
+            self.views.lock().unwrap().insert(height, view.cloned());
+            Ok(())
+        }
+
+        fn remove_above(&self, height: BlockHeight) -> anyhow::Result<()> {
+            self.views.lock().unwrap().split_off(&(height + 1));
+            Ok(())
+        }
     }
 
     async fn new_syncer(
@@ -442,10 +786,16 @@ mod tests {
             exponential_retry_limit: 0,
             max_proposal_range: Some(1),
             max_cache_blocks: None,
+            parallel_fetches: None,
+            max_reorg_lookback: None,
+            finality_rule: None,
             proposal_delay: None,
         };
         let genesis_epoch = blocks.lower_bound().unwrap();
-        let proxy = Arc::new(TestParentProxy { blocks });
+        let proxy = Arc::new(TestParentProxy {
+            blocks,
+            finalized: None,
+        });
         let committed_finality = IPCParentFinality {
             height: genesis_epoch,
             block_hash: vec![0; 32],
@@ -543,4 +893,259 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn sync_many_in_parallel() {
+        let parent_blocks = new_parent_blocks!(
+            100 => Some(vec![0; 32]),   // genesis block
+            101 => Some(vec![1; 32]),
+            102 => None,
+            103 => Some(vec![3; 32]),
+            104 => None,
+            105 => None,
+            106 => Some(vec![6; 32]),
+            107 => Some(vec![7; 32]),
+            108 => None,
+            109 => Some(vec![9; 32]),
+            110 => Some(vec![10; 32]),
+            111 => Some(vec![11; 32]),  // after chain head delay, we fetch only to here
+            112 => Some(vec![12; 32]),
+            113 => Some(vec![13; 32])   // chain head
+        );
+
+        let mut syncer = new_syncer(parent_blocks, true).await;
+        syncer.config.parallel_fetches = Some(3);
+
+        // everything up to the finalized chain head is fetched in one go, in order
+        syncer.sync().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(111)
+        );
+        assert_eq!(
+            atomically(|| syncer.provider.block_hash(109)).await,
+            Some(vec![9; 32])
+        );
+    }
+
+    #[tokio::test]
+    async fn sync_up_to_finalized_tag() {
+        let parent_blocks = new_parent_blocks!(
+            100 => Some(vec![0; 32]),   // genesis block
+            101 => Some(vec![1; 32]),
+            102 => Some(vec![2; 32]),   // after chain head delay, we fetch only to here
+            103 => Some(vec![3; 32]),
+            104 => Some(vec![4; 32]),   // finalized tag
+            105 => Some(vec![5; 32]),
+            106 => Some(vec![6; 32]),
+            107 => Some(vec![7; 32]),
+            108 => Some(vec![8; 32])    // chain head
+        );
+
+        let mut syncer = new_syncer(parent_blocks, true).await;
+        syncer.config.finality_rule = Some(ParentFinalityRule::FinalizedTag);
+
+        // the chain head is not considered at all, only what the parent finalized
+        assert!(syncer.sync().await.is_err());
+
+        let blocks = syncer.parent_proxy.blocks.clone();
+        syncer.parent_proxy = Arc::new(TestParentProxy {
+            blocks,
+            finalized: Some(104),
+        });
+
+        syncer.sync().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(102)
+        );
+    }
+
+    #[tokio::test]
+    async fn restore_from_store() {
+        let parent_blocks = || {
+            new_parent_blocks!(
+                100 => Some(vec![0; 32]),   // genesis block
+                101 => Some(vec![1; 32]),
+                102 => None,
+                103 => Some(vec![3; 32]),
+                104 => Some(vec![4; 32]),   // after chain head delay, we fetch only to here
+                105 => Some(vec![5; 32]),
+                106 => Some(vec![6; 32])    // chain head
+            )
+        };
+
+        let store = Arc::new(TestParentViewStore::default());
+
+        let mut syncer = new_syncer(parent_blocks(), true).await;
+        syncer.view_store = Some(store.clone());
+        syncer.sync().await.unwrap();
+        assert_eq!(store.views.lock().unwrap().len(), 4);
+
+        // a restarted node picks up where the previous one left off
+        let mut syncer = new_syncer(parent_blocks(), true).await;
+        syncer.view_store = Some(store.clone());
+        syncer.restore().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(104)
+        );
+        assert_eq!(
+            atomically(|| syncer.provider.block_hash(103)).await,
+            Some(vec![3; 32])
+        );
+
+        // and carries on syncing from there
+        syncer.sync().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(104)
+        );
+    }
+
+    #[tokio::test]
+    async fn restore_stops_at_unverified_view() {
+        let store = Arc::new(TestParentViewStore::default());
+
+        let mut syncer = new_syncer(
+            new_parent_blocks!(
+                100 => Some(vec![0; 32]),   // genesis block
+                101 => Some(vec![1; 32]),
+                102 => None,
+                103 => Some(vec![3; 32]),
+                104 => Some(vec![4; 32]),   // after chain head delay, we fetch only to here
+                105 => Some(vec![5; 32]),
+                106 => Some(vec![6; 32])    // chain head
+            ),
+            true,
+        )
+        .await;
+        syncer.view_store = Some(store.clone());
+        syncer.sync().await.unwrap();
+
+        // the parent reorged after height 102 while the node was down
+        let witness = Arc::new(TestParentProxy {
+            blocks: new_parent_blocks!(
+                100 => Some(vec![0; 32]),
+                101 => Some(vec![1; 32]),
+                102 => None,
+                103 => Some(vec![13; 32]),
+                104 => Some(vec![14; 32]),
+                105 => Some(vec![15; 32]),
+                106 => Some(vec![16; 32])
+            ),
+            finalized: None,
+        });
+        let verifier = WitnessVerifier::new(vec![witness], 1).unwrap();
+
+        let mut syncer = new_syncer(new_parent_blocks!(100 => Some(vec![0; 32])), true).await;
+        syncer.view_store = Some(store.clone());
+        syncer.verifier = Some(Arc::new(verifier));
+        syncer.restore().await.unwrap();
+
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(102)
+        );
+        assert_eq!(atomically(|| syncer.provider.block_hash(103)).await, None);
+    }
+
+    #[tokio::test]
+    async fn recover_from_reorg() {
+        let store = Arc::new(TestParentViewStore::default());
+
+        let mut syncer = new_syncer(
+            new_parent_blocks!(
+                100 => Some(vec![0; 32]),   // genesis block
+                101 => Some(vec![1; 32]),
+                102 => Some(vec![2; 32]),
+                103 => None,
+                104 => Some(vec![4; 32]),
+                105 => Some(vec![5; 32]),
+                106 => Some(vec![6; 32]),   // after chain head delay, we fetch only to here
+                107 => Some(vec![7; 32]),
+                108 => Some(vec![8; 32])    // chain head
+            ),
+            true,
+        )
+        .await;
+        syncer.view_store = Some(store.clone());
+
+        syncer.sync().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(106)
+        );
+
+        // the parent reorgs after height 104 and moves on
+        syncer.parent_proxy = Arc::new(TestParentProxy {
+            blocks: new_parent_blocks!(
+                100 => Some(vec![0; 32]),
+                101 => Some(vec![1; 32]),
+                102 => Some(vec![2; 32]),
+                103 => None,
+                104 => Some(vec![4; 32]),
+                105 => None,
+                106 => Some(vec![16; 32]),
+                107 => Some(vec![17; 32]),
+                108 => Some(vec![18; 32]),
+                109 => Some(vec![19; 32]),
+                110 => Some(vec![20; 32])
+            ),
+            finalized: None,
+        });
+
+        // the first block of the new fork doesn't follow, so we roll back to the fork point
+        syncer.sync().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(104)
+        );
+        // and forget the abandoned fork, so it isn't restored after a restart
+        assert_eq!(store.views.lock().unwrap().keys().max(), Some(&104));
+
+        // then carry on along the new fork
+        syncer.sync().await.unwrap();
+        assert_eq!(
+            atomically(|| syncer.provider.latest_height()).await,
+            Some(108)
+        );
+        assert_eq!(atomically(|| syncer.provider.block_hash(105)).await, None);
+        assert_eq!(
+            atomically(|| syncer.provider.block_hash(106)).await,
+            Some(vec![16; 32])
+        );
+        assert_eq!(
+            store
+                .views
+                .lock()
+                .unwrap()
+                .get(&106)
+                .cloned()
+                .flatten()
+                .map(|v| v.0),
+            Some(vec![16; 32])
+        );
+    }
+
+    #[test]
+    fn contiguous_changes() {
+        use crate::sync::syncer::ensure_contiguous_changes;
+        use fvm_shared::address::Address;
+        use ipc_api::staking::{StakingChange, StakingOperation};
+
+        let change = |configuration_number| StakingChangeRequest {
+            configuration_number,
+            change: StakingChange {
+                op: StakingOperation::Deposit,
+                payload: vec![],
+                validator: Address::new_id(100),
+            },
+        };
+
+        assert!(ensure_contiguous_changes(10, &[]).is_ok());
+        assert!(ensure_contiguous_changes(10, &[change(5), change(6), change(7)]).is_ok());
+        assert!(ensure_contiguous_changes(10, &[change(5), change(7)]).is_err());
+        assert!(ensure_contiguous_changes(10, &[change(6), change(5)]).is_err());
+    }
 }
@@ -71,6 +71,16 @@ impl<P: ParentViewProvider + Send + Sync + 'static> ParentViewProvider for Toggl
             None => Err(anyhow!("provider is toggled off")),
         }
     }
+
+    async fn last_bottom_up_checkpoint_height(
+        &self,
+        height: BlockHeight,
+    ) -> anyhow::Result<BlockHeight> {
+        match self.inner.as_ref() {
+            Some(p) => p.last_bottom_up_checkpoint_height(height).await,
+            None => Err(anyhow!("provider is toggled off")),
+        }
+    }
 }
 
 impl<P: ParentFinalityProvider + Send + Sync + 'static> ParentFinalityProvider for Toggle<P> {
@@ -120,6 +130,10 @@ impl<P> Toggle<CachedFinalityProvider<P>> {
         self.perform_or_else(|p| p.reset(finality), ())
     }
 
+    pub fn rollback(&self, height: BlockHeight) -> Stm<()> {
+        self.perform_or_else(|p| p.rollback(height), ())
+    }
+
     pub fn cached_blocks(&self) -> Stm<BlockHeight> {
         self.perform_or_else(|p| p.cached_blocks(), BlockHeight::MAX)
     }
@@ -127,4 +141,16 @@ impl<P> Toggle<CachedFinalityProvider<P>> {
     pub fn first_non_null_block(&self, height: BlockHeight) -> Stm<Option<BlockHeight>> {
         self.perform_or_else(|p| p.first_non_null_block(height), None)
     }
+
+    pub fn pause(&self) -> Stm<()> {
+        self.perform_or_else(|p| p.pause(), ())
+    }
+
+    pub fn resume(&self) -> Stm<()> {
+        self.perform_or_else(|p| p.resume(), ())
+    }
+
+    pub fn is_paused(&self) -> Stm<bool> {
+        self.perform_or_else(|p| p.is_paused(), false)
+    }
 }
@@ -0,0 +1,204 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Verifying the parent blocks fetched from the parent RPC endpoint before they go into the
+//! cache, and thus into a `ParentFinality` proposal, so a compromised endpoint can't feed us
+//! a fake parent hash.
+
+use crate::proxy::ParentQueryProxy;
+use crate::{is_null_round_str, BlockHeight};
+use anyhow::{bail, ensure};
+use async_trait::async_trait;
+use ethers::utils::hex;
+use ipc_provider::manager::GetBlockHashResult;
+use std::sync::Arc;
+
+/// Checks the parent blocks independently of the endpoint they were fetched from, e.g. against
+/// a header chain or finality certificates of the parent.
+#[async_trait]
+pub trait ParentBlockVerifier: Send + Sync {
+    /// Check that the parent block at the height has the given hashes, or is a null round
+    /// if there is no block.
+    async fn verify(
+        &self,
+        height: BlockHeight,
+        block: Option<&GetBlockHashResult>,
+    ) -> anyhow::Result<()>;
+}
+
+/// Verifies the parent blocks against independent witnesses, other endpoints of the parent
+/// operated by different parties, similar to how the light client of CometBFT cross-checks
+/// its primary.
+///
+/// A block is only accepted if at least `threshold` witnesses confirm it. A witness which
+/// contradicts the block is evidence that either side was compromised, so it fails the
+/// verification regardless of the others; unreachable witnesses merely don't count.
+pub struct WitnessVerifier<P> {
+    witnesses: Vec<Arc<P>>,
+    threshold: usize,
+}
+
+impl<P> WitnessVerifier<P> {
+    pub fn new(witnesses: Vec<Arc<P>>, threshold: usize) -> anyhow::Result<Self> {
+        ensure!(
+            threshold > 0 && threshold <= witnesses.len(),
+            "the witness threshold has to be between 1 and the number of witnesses ({})",
+            witnesses.len()
+        );
+        Ok(Self {
+            witnesses,
+            threshold,
+        })
+    }
+}
+
+#[async_trait]
+impl<P> ParentBlockVerifier for WitnessVerifier<P>
+where
+    P: ParentQueryProxy + Send + Sync + 'static,
+{
+    async fn verify(
+        &self,
+        height: BlockHeight,
+        block: Option<&GetBlockHashResult>,
+    ) -> anyhow::Result<()> {
+        let mut confirmations = 0;
+
+        for (i, witness) in self.witnesses.iter().enumerate() {
+            let witnessed = match witness.get_block_hash(height).await {
+                Ok(res) => Some(res),
+                Err(e) if is_null_round_str(&e.to_string()) => None,
+                Err(e) => {
+                    tracing::warn!(
+                        height,
+                        witness = i,
+                        error = e.to_string(),
+                        "cannot query parent witness"
+                    );
+                    continue;
+                }
+            };
+
+            match (block, witnessed) {
+                (None, None) => {}
+                (Some(b), Some(w))
+                    if b.block_hash == w.block_hash
+                        && b.parent_block_hash == w.parent_block_hash => {}
+                (b, w) => {
+                    let describe = |r: Option<&GetBlockHashResult>| {
+                        r.map(|r| hex::encode(&r.block_hash))
+                            .unwrap_or_else(|| "a null round".to_string())
+                    };
+                    bail!(
+                        "parent witness {i} reports {} instead of {}",
+                        describe(w.as_ref()),
+                        describe(b)
+                    );
+                }
+            }
+
+            confirmations += 1;
+        }
+
+        ensure!(
+            confirmations >= self.threshold,
+            "only {confirmations} parent witnesses confirmed the block, {} required",
+            self.threshold
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParentBlockVerifier, WitnessVerifier};
+    use crate::proxy::ParentQueryProxy;
+    use crate::{BlockHeight, NULL_ROUND_ERR_MSG};
+    use anyhow::anyhow;
+    use async_trait::async_trait;
+    use ipc_api::cross::IpcEnvelope;
+    use ipc_api::staking::StakingChangeRequest;
+    use ipc_provider::manager::{GetBlockHashResult, TopDownQueryPayload};
+    use std::sync::Arc;
+
+    /// Witnesses of a parent with a null round at height 100 and the same block everywhere else.
+    enum TestWitness {
+        Honest,
+        Lying,
+        Down,
+    }
+
+    #[async_trait]
+    impl ParentQueryProxy for TestWitness {
+        async fn get_chain_head_height(&self) -> anyhow::Result<BlockHeight> {
+            unimplemented!()
+        }
+
+        async fn get_genesis_epoch(&self) -> anyhow::Result<BlockHeight> {
+            unimplemented!()
+        }
+
+        async fn get_block_hash(&self, height: BlockHeight) -> anyhow::Result<GetBlockHashResult> {
+            match self {
+                TestWitness::Down => Err(anyhow!("connection refused")),
+                _ if height == 100 => Err(anyhow!(NULL_ROUND_ERR_MSG)),
+                TestWitness::Honest => Ok(block(1)),
+                TestWitness::Lying => Ok(block(2)),
+            }
+        }
+
+        async fn get_top_down_msgs(
+            &self,
+            _height: BlockHeight,
+        ) -> anyhow::Result<TopDownQueryPayload<Vec<IpcEnvelope>>> {
+            unimplemented!()
+        }
+
+        async fn get_validator_changes(
+            &self,
+            _height: BlockHeight,
+        ) -> anyhow::Result<TopDownQueryPayload<Vec<StakingChangeRequest>>> {
+            unimplemented!()
+        }
+
+        async fn get_last_bottom_up_checkpoint_height(
+            &self,
+            _height: BlockHeight,
+        ) -> anyhow::Result<BlockHeight> {
+            unimplemented!()
+        }
+    }
+
+    fn block(b: u8) -> GetBlockHashResult {
+        GetBlockHashResult {
+            parent_block_hash: vec![0; 32],
+            block_hash: vec![b; 32],
+        }
+    }
+
+    fn verifier(witnesses: Vec<TestWitness>, threshold: usize) -> WitnessVerifier<TestWitness> {
+        WitnessVerifier::new(witnesses.into_iter().map(Arc::new).collect(), threshold).unwrap()
+    }
+
+    #[tokio::test]
+    async fn witnesses_confirm_blocks() {
+        let v = verifier(vec![TestWitness::Down, TestWitness::Honest], 1);
+        v.verify(101, Some(&block(1))).await.unwrap();
+        v.verify(100, None).await.unwrap();
+
+        // fake block hash or hidden block
+        assert!(v.verify(101, Some(&block(2))).await.is_err());
+        assert!(v.verify(101, None).await.is_err());
+        assert!(v.verify(100, Some(&block(1))).await.is_err());
+
+        // not enough witnesses reachable
+        let v = verifier(vec![TestWitness::Down, TestWitness::Honest], 2);
+        assert!(v.verify(101, Some(&block(1))).await.is_err());
+
+        // a contradicting witness fails the verification, even if the others confirm it
+        let v = verifier(vec![TestWitness::Honest, TestWitness::Lying], 1);
+        assert!(v.verify(101, Some(&block(1))).await.is_err());
+
+        assert!(WitnessVerifier::<TestWitness>::new(vec![], 1).is_err());
+    }
+}
@@ -3,10 +3,13 @@
 
 use async_stm::{abort, atomically_or_err, retry, Stm, StmResult, TVar};
 use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeSet;
 use std::hash::Hash;
 use std::{fmt::Debug, time::Duration};
 
-use crate::{BlockHash, BlockHeight};
+use crate::{now_millis, BlockHash, BlockHeight};
+use fendermint_tracing::emit;
+use fendermint_vm_event::ParentFinalityQuorum;
 
 // Usign this type because it's `Hash`, unlike the normal `libsecp256k1::PublicKey`.
 pub use ipc_ipld_resolver::ValidatorKey;
@@ -33,6 +36,17 @@ pub enum Error<K = ValidatorKey, V: AsRef<[u8]> = BlockHash> {
     Equivocation(K, BlockHeight, V, V),
 }
 
+/// The votes collected for the blocks at a parent height, for diagnostics.
+#[derive(Debug, Clone)]
+pub struct HeightVotes<K = ValidatorKey, V = BlockHash> {
+    pub height: BlockHeight,
+    /// The block at this height on the parent chain as observed by this node;
+    /// `None` if it's a null round or we haven't synced it yet.
+    pub block_hash: Option<V>,
+    /// The validators who voted for each block hash, with their current weights.
+    pub votes: Vec<(V, Vec<(K, Weight)>)>,
+}
+
 /// Keep track of votes being gossiped about parent chain finality
 /// and tally up the weights of the validators on the child subnet,
 /// so that we can ask for proposals that are not going to be voted
@@ -63,6 +77,13 @@ pub struct VoteTally<K = ValidatorKey, V = BlockHash> {
     /// Adding votes can be paused if we observe that looking for a quorum takes too long
     /// and is often retried due to votes being added.
     pause_votes: TVar<bool>,
+
+    /// When the first vote was received at each height, in milliseconds since the epoch,
+    /// to measure how long it takes for the votes to reach a quorum.
+    first_vote_at: TVar<im::OrdMap<BlockHeight, u64>>,
+
+    /// The highest height a quorum has been found for, so the time it took is only reported once.
+    quorum_height: TVar<Option<BlockHeight>>,
 }
 
 impl<K, V> VoteTally<K, V>
@@ -81,6 +102,8 @@ where
             chain: TVar::default(),
             votes: TVar::default(),
             pause_votes: TVar::new(false),
+            first_vote_at: TVar::default(),
+            quorum_height: TVar::default(),
         }
     }
 
@@ -93,6 +116,8 @@ where
             chain: TVar::new(im::OrdMap::from_iter([(height, Some(hash))])),
             votes: TVar::default(),
             pause_votes: TVar::new(false),
+            first_vote_at: TVar::default(),
+            quorum_height: TVar::default(),
         }
     }
 
@@ -111,11 +136,16 @@ where
     /// This is inclusive, that is, if the sum of weight is greater or equal to this, it should pass.
     /// The equivalent formula can be found in CometBFT [here](https://github.com/cometbft/cometbft/blob/a8991d63e5aad8be82b90329b55413e3a4933dc0/types/vote_set.go#L307).
     pub fn quorum_threshold(&self) -> Stm<Weight> {
-        let total_weight: Weight = self.power_table.read().map(|pt| pt.values().sum())?;
+        let total_weight = self.total_power()?;
 
         Ok(total_weight * 2 / 3 + 1)
     }
 
+    /// Sum of the weights in the current power table.
+    pub fn total_power(&self) -> Stm<Weight> {
+        self.power_table.read().map(|pt| pt.values().sum())
+    }
+
     /// Return the height of the first entry in the chain.
     ///
     /// This is the block that was finalized *in the ledger*.
@@ -218,9 +248,59 @@ where
 
         self.votes.write(votes)?;
 
+        if !self.first_vote_at.read()?.contains_key(&block_height) {
+            self.first_vote_at.update_mut(|first_vote_at| {
+                first_vote_at.insert(block_height, now_millis());
+            })?;
+        }
+
         Ok(true)
     }
 
+    /// List the votes for the heights above the last finalized one, along with the blocks
+    /// we observed at those heights, to see why a quorum is not forming.
+    pub fn votes(&self) -> Stm<Vec<HeightVotes<K, V>>> {
+        let finalized_height = self.last_finalized_height()?;
+        let chain = self.chain.read()?;
+        let votes = self.votes.read()?;
+        let power_table = self.power_table.read()?;
+
+        let heights = chain
+            .keys()
+            .chain(votes.keys())
+            .filter(|h| **h > finalized_height)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        let tally = heights
+            .into_iter()
+            .map(|height| HeightVotes {
+                height,
+                block_hash: chain.get(&height).cloned().flatten(),
+                votes: votes
+                    .get(&height)
+                    .map(|votes_at_height| {
+                        votes_at_height
+                            .iter()
+                            .map(|(hash, voters)| {
+                                let voters = voters
+                                    .iter()
+                                    .map(|vk| {
+                                        let w = power_table.get(vk).cloned().unwrap_or_default();
+                                        (vk.clone(), w)
+                                    })
+                                    .collect();
+                                (hash.clone(), voters)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(tally)
+    }
+
     /// Pause adding more votes until we are finished calling `find_quorum` which
     /// automatically re-enables them.
     pub fn pause_votes_until_find_quorum(&self) -> Stm<()> {
@@ -278,6 +358,7 @@ where
             tracing::debug!(weight, quorum_threshold, "showdown");
 
             if weight >= quorum_threshold {
+                self.report_quorum(*block_height)?;
                 return Ok(Some((*block_height, block_hash.clone())));
             }
         }
@@ -296,10 +377,46 @@ where
         })?;
 
         self.votes.update(|votes| votes.split(&block_height).1)?;
+        self.first_vote_at
+            .update(|first_vote_at| first_vote_at.split(&block_height).1)?;
+
+        Ok(())
+    }
 
+    /// Emit the time it took to reach a quorum at a height, the first time it's found.
+    fn report_quorum(&self, block_height: BlockHeight) -> Stm<()> {
+        if self
+            .quorum_height
+            .read()?
+            .is_some_and(|h| h >= block_height)
+        {
+            return Ok(());
+        }
+        self.quorum_height.write(Some(block_height))?;
+
+        if let Some(first_vote_at) = self.first_vote_at.read()?.get(&block_height) {
+            emit!(ParentFinalityQuorum {
+                block_height,
+                latency_ms: now_millis().saturating_sub(*first_vote_at),
+            });
+        }
         Ok(())
     }
 
+    /// Remove the blocks above the given height from the chain, e.g. after the parent reorged,
+    /// so that the blocks of the new fork can be added in their place.
+    ///
+    /// The votes are kept, as they may well be for the new fork.
+    pub fn rollback(&self, block_height: BlockHeight) -> Stm<()> {
+        self.chain.update(|chain| {
+            let (mut chain, at, _) = chain.split_lookup(&block_height);
+            if let Some(hash) = at {
+                chain.insert(block_height, hash);
+            }
+            chain
+        })
+    }
+
     /// Overwrite the power table after it has changed to a new snapshot.
     ///
     /// This method expects absolute values, it completely replaces the existing powers.
@@ -434,3 +551,72 @@ pub async fn publish_vote_loop<V, F>(
         prev = Some((next_height, next_hash, has_power));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_stm::{atomically, atomically_or_err};
+
+    use super::{HeightVotes, VoteTally};
+
+    type Tally = VoteTally<u64, Vec<u8>>;
+
+    /// The votes at each height, with the voters sorted, as they come out of hash sets.
+    async fn tally_votes(tally: &Tally) -> Vec<HeightVotes<u64, Vec<u8>>> {
+        let mut votes = atomically(|| tally.votes()).await;
+        for height_votes in votes.iter_mut() {
+            height_votes.votes.sort();
+            for (_, voters) in height_votes.votes.iter_mut() {
+                voters.sort();
+            }
+        }
+        votes
+    }
+
+    #[tokio::test]
+    async fn votes_above_last_finalized() {
+        let tally = Tally::new(vec![(1, 10), (2, 20)], (10, vec![10]));
+
+        atomically_or_err(|| {
+            tally.add_block(11, Some(vec![11]))?;
+            tally.add_block(12, None)
+        })
+        .await
+        .unwrap();
+
+        atomically_or_err(|| {
+            tally.add_vote(1, 10, vec![10])?;
+            tally.add_vote(1, 11, vec![11])?;
+            tally.add_vote(2, 11, vec![11])?;
+            // ahead of the blocks we have seen
+            tally.add_vote(2, 13, vec![13])
+        })
+        .await
+        .unwrap();
+
+        let votes = tally_votes(&tally).await;
+        let summary = votes
+            .iter()
+            .map(|v| (v.height, v.block_hash.clone(), v.votes.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                (11, Some(vec![11]), vec![(vec![11], vec![(1, 10), (2, 20)])]),
+                (12, None, vec![]),
+                (13, None, vec![(vec![13], vec![(2, 20)])]),
+            ]
+        );
+
+        // voters no longer in the power table are listed without weight
+        atomically(|| tally.set_power_table(vec![(2, 20)])).await;
+        let votes = tally_votes(&tally).await;
+        assert_eq!(votes[0].votes, vec![(vec![11], vec![(1, 0), (2, 20)])]);
+
+        // finalized heights are no longer listed
+        atomically(|| tally.set_finalized(11, vec![11])).await;
+        let votes = tally_votes(&tally).await;
+        let heights = votes.iter().map(|v| v.height).collect::<Vec<_>>();
+        assert_eq!(heights, vec![12, 13]);
+    }
+}
@@ -58,6 +58,54 @@ pub struct BottomUpCheckpointBundle {
     pub signatories: Vec<Address>,
 }
 
+/// The quorum the validators of the child subnet collected for a bottom-up checkpoint.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointQuorum {
+    pub bundle: BottomUpCheckpointBundle,
+    /// The hash the validators signed, as recorded by the gateway.
+    pub hash: Vec<u8>,
+    /// The total weight of the validators who signed it.
+    pub current_weight: TokenAmount,
+    /// The weight needed to reach the quorum.
+    pub threshold: TokenAmount,
+    pub reached: bool,
+}
+
+/// The parent transaction that committed a bottom-up checkpoint.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointSubmission {
+    pub tx_hash: Vec<u8>,
+    /// The parent block the transaction was included in.
+    pub block_height: ChainEpoch,
+    /// The relayer who sent the transaction.
+    pub submitter: Address,
+    pub gas_used: u64,
+    /// What the relayer paid for the gas used, at the effective gas price.
+    pub gas_fee: TokenAmount,
+}
+
+/// What it takes to verify a bottom-up checkpoint end to end, from the signatures
+/// collected in the child subnet to the transaction that committed it in the parent.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointVerification {
+    pub quorum: CheckpointQuorum,
+    /// Whether the quorum hash is the hash of the checkpoint itself.
+    pub hash_matches: bool,
+    /// Whether the parent has committed this checkpoint, or one after it.
+    pub committed: bool,
+    /// The transaction that committed the checkpoint, if it was found in the parent blocks searched.
+    pub submission: Option<CheckpointSubmission>,
+}
+
+/// A bottom-up message of a child subnet which its parent has not committed yet.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct PendingBottomUpMsg {
+    /// The height of the checkpoint the message is batched for. Messages carried over from the
+    /// last submitted checkpoint are listed under the next one.
+    pub checkpoint_height: ChainEpoch,
+    pub msg: IpcEnvelope,
+}
+
 /// The collection of items for the bottom up checkpoint submission
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct BottomUpMsgBatch {
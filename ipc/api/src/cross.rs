@@ -5,6 +5,8 @@
 use crate::address::IPCAddress;
 use crate::subnet_id::SubnetID;
 use anyhow::anyhow;
+use ethers::abi::{decode, encode, ParamType, Token};
+use ethers::types::U256;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use serde::{Deserialize, Serialize};
@@ -82,6 +84,42 @@ impl IpcEnvelope {
         })
     }
 
+    /// Create a general-purpose message calling a contract in another subnet.
+    ///
+    /// Only contracts can send these through the gateway, so this is mostly useful
+    /// for contracts relaying calls, and for tests.
+    pub fn new_call_msg(
+        from: IPCAddress,
+        to: IPCAddress,
+        value: TokenAmount,
+        call: &CallMsg,
+    ) -> Self {
+        Self {
+            kind: IpcMsgKind::Call,
+            from,
+            to,
+            value,
+            nonce: 0,
+            message: call.encode(),
+        }
+    }
+
+    /// Decode the message of a `Call` envelope.
+    pub fn call_msg(&self) -> anyhow::Result<CallMsg> {
+        if self.kind != IpcMsgKind::Call {
+            return Err(anyhow!("not a call message: {:?}", self.kind));
+        }
+        CallMsg::decode(&self.message)
+    }
+
+    /// Decode the message of a `Receipt` envelope.
+    pub fn result_msg(&self) -> anyhow::Result<ResultMsg> {
+        if self.kind != IpcMsgKind::Receipt {
+            return Err(anyhow!("not a receipt message: {:?}", self.kind));
+        }
+        ResultMsg::decode(&self.message)
+    }
+
     pub fn ipc_type(&self) -> anyhow::Result<IPCMsgType> {
         let sto = self.to.subnet()?;
         let sfrom = self.from.subnet()?;
@@ -112,8 +150,7 @@ pub enum IpcMsgKind {
     Transfer,
     /// general-purpose cross-net transaction that call smart contracts.
     Call,
-    /// receipt from the execution of cross-net messages
-    /// (currently limited to `Transfer` messages)
+    /// receipt from the execution of `Call` cross-net messages
     Receipt,
 }
 
@@ -130,6 +167,125 @@ impl TryFrom<u8> for IpcMsgKind {
     }
 }
 
+/// The message of a general-purpose cross-net call to a contract.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct CallMsg {
+    /// Target method; a 4 byte function selector for EVM targets.
+    pub method: Vec<u8>,
+    /// Arguments of the method being called.
+    pub params: Vec<u8>,
+}
+
+impl CallMsg {
+    /// Call a Solidity function with abi encoded arguments.
+    pub fn new_evm(selector: [u8; 4], params: Vec<u8>) -> Self {
+        Self {
+            method: selector.to_vec(),
+            params,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        encode(&[Token::Tuple(vec![
+            Token::Bytes(self.method.clone()),
+            Token::Bytes(self.params.clone()),
+        ])])
+    }
+
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        let ty = ParamType::Tuple(vec![ParamType::Bytes, ParamType::Bytes]);
+        match decode(&[ty], bytes)?.pop() {
+            Some(Token::Tuple(mut fields)) => {
+                let params = fields.pop().and_then(Token::into_bytes);
+                let method = fields.pop().and_then(Token::into_bytes);
+                match (method, params) {
+                    (Some(method), Some(params)) => Ok(Self { method, params }),
+                    _ => Err(anyhow!("invalid call message fields")),
+                }
+            }
+            _ => Err(anyhow!("invalid call message")),
+        }
+    }
+}
+
+/// Whether the execution of a cross-net message succeeded, as reported in its receipt.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[repr(u8)]
+pub enum OutcomeType {
+    /// The execution was successful.
+    Ok,
+    /// The message failed with an IPC system error, e.g. invalid destination.
+    SystemErr,
+    /// The invoked contract failed, e.g. reverted or ran out of gas.
+    ActorErr,
+}
+
+impl TryFrom<u8> for OutcomeType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => OutcomeType::Ok,
+            1 => OutcomeType::SystemErr,
+            2 => OutcomeType::ActorErr,
+            _ => return Err(anyhow!("invalid outcome type")),
+        })
+    }
+}
+
+/// The message of a receipt sent back to the sender of a cross-net message.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ResultMsg {
+    /// Id of the envelope the result belongs to.
+    pub id: [u8; 32],
+    pub outcome: OutcomeType,
+    /// The abi encoded return value, or the reason of the failure.
+    pub ret: Vec<u8>,
+}
+
+impl ResultMsg {
+    pub fn encode(&self) -> Vec<u8> {
+        encode(&[Token::Tuple(vec![
+            Token::FixedBytes(self.id.to_vec()),
+            Token::Uint((self.outcome as u8).into()),
+            Token::Bytes(self.ret.clone()),
+        ])])
+    }
+
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        let ty = ParamType::Tuple(vec![
+            ParamType::FixedBytes(32),
+            ParamType::Uint(8),
+            ParamType::Bytes,
+        ]);
+        let fields = match decode(&[ty], bytes)?.pop() {
+            Some(Token::Tuple(fields)) if fields.len() == 3 => fields,
+            _ => return Err(anyhow!("invalid result message")),
+        };
+        let mut fields = fields.into_iter();
+        let (id, outcome, ret) = (
+            fields.next().and_then(Token::into_fixed_bytes),
+            fields.next().and_then(Token::into_uint),
+            fields.next().and_then(Token::into_bytes),
+        );
+        match (id, outcome, ret) {
+            // The decoder doesn't check the size of the word, so a large value would
+            // otherwise be truncated into a valid outcome.
+            (_, Some(outcome), _) if outcome > U256::from(u8::MAX) => {
+                Err(anyhow!("invalid outcome type"))
+            }
+            (Some(id), Some(outcome), Some(ret)) => Ok(Self {
+                id: id
+                    .try_into()
+                    .map_err(|_| anyhow!("invalid result message id"))?,
+                outcome: OutcomeType::try_from(outcome.low_u32() as u8)?,
+                ret,
+            }),
+            _ => Err(anyhow!("invalid result message fields")),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum IPCMsgType {
     BottomUp,
@@ -205,6 +361,33 @@ mod tests {
         bottom_up("/r123/f01/f02", "/r123/f01/f02/f03", false);
     }
 
+    #[test]
+    fn test_call_and_result_msg_roundtrip() {
+        let subnet = SubnetID::from_str("/r123/f01").unwrap();
+        let from = IPCAddress::new(&subnet.parent().unwrap(), &Address::new_id(100)).unwrap();
+        let to = IPCAddress::new(&subnet, &Address::new_id(101)).unwrap();
+
+        let call = CallMsg::new_evm([1, 2, 3, 4], vec![5; 40]);
+        let envelope = IpcEnvelope::new_call_msg(from, to, TokenAmount::from_atto(1), &call);
+        assert_eq!(envelope.call_msg().unwrap(), call);
+        assert!(envelope.result_msg().is_err());
+
+        let result = ResultMsg {
+            id: [7; 32],
+            outcome: OutcomeType::ActorErr,
+            ret: vec![8; 3],
+        };
+        assert_eq!(ResultMsg::decode(&result.encode()).unwrap(), result);
+
+        // An outcome which only looks valid in its lowest byte.
+        let invalid = encode(&[Token::Tuple(vec![
+            Token::FixedBytes(vec![7; 32]),
+            Token::Uint(U256::from(0x100 + OutcomeType::Ok as u32)),
+            Token::Bytes(vec![]),
+        ])]);
+        assert!(ResultMsg::decode(&invalid).is_err());
+    }
+
     fn bottom_up(a: &str, b: &str, res: bool) {
         assert_eq!(
             is_bottomup(
@@ -13,6 +13,7 @@ use crate::subnet::SupplySource;
 use crate::subnet_id::SubnetID;
 use crate::{eth_to_fil_amount, ethers_address_to_fil_address};
 use anyhow::anyhow;
+use ethers::abi::Tokenizable;
 use ethers::types::U256;
 use fvm_shared::address::{Address, Payload};
 use fvm_shared::clock::ChainEpoch;
@@ -295,6 +296,15 @@ pub fn vec_to_bytes32(v: Vec<u8>) -> anyhow::Result<[u8; 32]> {
     Ok(r)
 }
 
+/// The key a cross-net message is stored under in the postbox of the gateway, i.e. the
+/// `keccak256` hash of its ABI encoding, the same as `CrossMsgHelper.toHash`.
+pub fn postbox_msg_key(msg: &IpcEnvelope) -> anyhow::Result<[u8; 32]> {
+    let msg = gateway_getter_facet::IpcEnvelope::try_from(msg.clone())?;
+    Ok(ethers::utils::keccak256(ethers::abi::encode(&[
+        msg.into_token()
+    ])))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::evm::subnet_id_to_evm_addresses;
@@ -4,6 +4,7 @@
 //! Staking module related types and functions
 
 use crate::{eth_to_fil_amount, ethers_address_to_fil_address};
+use ethers::abi::{decode, ParamType};
 use ethers::utils::hex;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
@@ -12,7 +13,7 @@ use std::fmt::{Display, Formatter};
 
 pub type ConfigurationNumber = u64;
 
-#[derive(Clone, Debug, num_enum::TryFromPrimitive)]
+#[derive(Clone, Debug, num_enum::TryFromPrimitive, num_enum::IntoPrimitive)]
 #[non_exhaustive]
 #[repr(u8)]
 pub enum StakingOperation {
@@ -36,6 +37,53 @@ pub struct StakingChange {
     pub validator: Address,
 }
 
+impl StakingChange {
+    /// Decode the operation specific payload into a human readable form.
+    fn decode_payload(&self) -> anyhow::Result<String> {
+        match self.op {
+            StakingOperation::Deposit | StakingOperation::Withdraw => {
+                let tokens = decode(&[ParamType::Uint(256)], &self.payload)?;
+                let amount = tokens[0]
+                    .clone()
+                    .into_uint()
+                    .ok_or_else(|| anyhow::anyhow!("invalid amount"))?;
+                Ok(format!("amount: {}", eth_to_fil_amount(&amount)?))
+            }
+            StakingOperation::SetMetadata => {
+                Ok(format!("metadata: 0x{}", hex::encode(&self.payload)))
+            }
+            StakingOperation::SetFederatedPower => {
+                let tokens = decode(&[ParamType::Bytes, ParamType::Uint(256)], &self.payload)?;
+                let metadata = tokens[0]
+                    .clone()
+                    .into_bytes()
+                    .ok_or_else(|| anyhow::anyhow!("invalid metadata"))?;
+                let power = tokens[1]
+                    .clone()
+                    .into_uint()
+                    .ok_or_else(|| anyhow::anyhow!("invalid power"))?;
+                Ok(format!(
+                    "power: {power}, metadata: 0x{}",
+                    hex::encode(metadata)
+                ))
+            }
+        }
+    }
+}
+
+impl Display for StakingChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let payload = self
+            .decode_payload()
+            .unwrap_or_else(|_| format!("payload: 0x{}", hex::encode(&self.payload)));
+        write!(
+            f,
+            "StakingChange(op: {:?}, validator: {}, {payload})",
+            self.op, self.validator
+        )
+    }
+}
+
 impl TryFrom<lib_staking_change_log::NewStakingChangeRequestFilter> for StakingChangeRequest {
     type Error = anyhow::Error;
 
@@ -104,3 +152,38 @@ impl Display for ValidatorInfo {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::{encode, Token};
+    use ethers::types::U256;
+    use fvm_shared::address::Address;
+
+    use super::{StakingChange, StakingOperation};
+
+    #[test]
+    fn test_staking_change_display() {
+        let change = |op, payload| StakingChange {
+            op,
+            payload,
+            validator: Address::new_id(1),
+        };
+
+        let deposit = change(
+            StakingOperation::Deposit,
+            encode(&[Token::Uint(U256::from(10u64.pow(18)))]),
+        );
+        assert!(deposit.to_string().contains("op: Deposit"));
+        assert!(deposit.to_string().contains("amount: 1"));
+
+        let power = change(
+            StakingOperation::SetFederatedPower,
+            encode(&[Token::Bytes(vec![0xab]), Token::Uint(U256::from(5))]),
+        );
+        assert!(power.to_string().contains("power: 5, metadata: 0xab"));
+
+        // undecodable payloads are shown as raw bytes
+        let invalid = change(StakingOperation::Withdraw, vec![0xff]);
+        assert!(invalid.to_string().contains("payload: 0xff"));
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Append-only audit log of the state-changing operations performed through the cli.
+//!
+//! Each operation is written as a single JSON line, so the file can be tailed,
+//! grepped, or loaded with `jq` during post-incident review.
+
+use std::fmt::Debug;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::types::TxHash;
+use fvm_shared::address::Address;
+use ipc_provider::IpcProvider;
+use serde::Serialize;
+
+use crate::GlobalArguments;
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+    method: &'a str,
+    caller: Option<String>,
+    params: serde_json::Value,
+    tx_hashes: Vec<TxHash>,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+enum Outcome {
+    Success { result: String },
+    Failure { error: String },
+}
+
+/// Record the outcome of an operation in the audit log, if one is configured.
+///
+/// The caller is the explicit sender, or the default one the provider resolved.
+/// The transaction hashes are the ones sent by the provider since the last record.
+///
+/// Failing to write the log doesn't fail the command, since the operation has
+/// already taken place; the error is logged instead.
+pub(crate) fn record<P, T>(
+    global: &GlobalArguments,
+    provider: &IpcProvider,
+    method: &str,
+    from: Option<Address>,
+    params: &P,
+    outcome: &anyhow::Result<T>,
+) where
+    P: Serialize,
+    T: Debug,
+{
+    let tx_hashes = provider.take_sent_transactions();
+
+    let Some(path) = global.audit_log() else {
+        return;
+    };
+
+    let record = AuditRecord {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        method,
+        caller: from.or_else(|| provider.sender()).map(|a| a.to_string()),
+        params: serde_json::to_value(params).unwrap_or_default(),
+        tx_hashes,
+        outcome: match outcome {
+            Ok(result) => Outcome::Success {
+                result: format!("{result:?}"),
+            },
+            Err(e) => Outcome::Failure {
+                error: format!("{e:#}"),
+            },
+        },
+    };
+
+    if let Err(e) = append(path, &record) {
+        log::error!("failed to write audit log {path}: {e:#}");
+    }
+}
+
+fn append(path: impl AsRef<Path>, record: &AuditRecord) -> anyhow::Result<()> {
+    let line = serde_json::to_string(record)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append, AuditRecord, Outcome};
+
+    #[test]
+    fn appends_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        for method in ["fund", "release"] {
+            let record = AuditRecord {
+                timestamp: 0,
+                method,
+                caller: None,
+                params: serde_json::json!({ "amount": 1.0 }),
+                tx_hashes: vec![Default::default()],
+                outcome: Outcome::Failure {
+                    error: "boom".into(),
+                },
+            };
+            append(&path, &record).unwrap();
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+
+        let value: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(value["method"], "release");
+        assert_eq!(value["outcome"], "failure");
+        assert_eq!(value["error"], "boom");
+        assert_eq!(value["tx_hashes"].as_array().unwrap().len(), 1);
+    }
+}
@@ -14,6 +14,11 @@ use crate::commands::checkpoint::quorum_reached::{
     GetQuorumReacehdEvents, GetQuorumReachedEventsArgs,
 };
 use crate::commands::checkpoint::relayer::{BottomUpRelayer, BottomUpRelayerArgs};
+use crate::commands::checkpoint::submission_costs::{SubmissionCosts, SubmissionCostsArgs};
+use crate::commands::checkpoint::verify_checkpoint::{
+    VerifyBottomUpCheckpoint, VerifyBottomUpCheckpointArgs,
+};
+use crate::commands::checkpoint::watch::{WatchBottomUpCheckpoints, WatchBottomUpCheckpointsArgs};
 use crate::{CommandLineHandler, GlobalArguments};
 use clap::{Args, Subcommand};
 
@@ -23,6 +28,10 @@ mod list_checkpoints;
 mod list_validator_changes;
 mod quorum_reached;
 mod relayer;
+mod relayer_admin;
+mod submission_costs;
+mod verify_checkpoint;
+mod watch;
 
 #[derive(Debug, Args)]
 #[command(name = "checkpoint", about = "checkpoint related commands")]
@@ -47,6 +56,9 @@ impl CheckpointCommandsArgs {
             Commands::LastBottomupCheckpointHeight(args) => {
                 LastBottomUpCheckpointHeight::handle(global, args).await
             }
+            Commands::VerifyBottomup(args) => VerifyBottomUpCheckpoint::handle(global, args).await,
+            Commands::SubmissionCosts(args) => SubmissionCosts::handle(global, args).await,
+            Commands::Watch(args) => WatchBottomUpCheckpoints::handle(global, args).await,
         }
     }
 }
@@ -59,4 +71,7 @@ pub(crate) enum Commands {
     ListBottomupBundle(GetBottomUpBundlesArgs),
     QuorumReachedEvents(GetQuorumReachedEventsArgs),
     LastBottomupCheckpointHeight(LastBottomUpCheckpointHeightArgs),
+    VerifyBottomup(VerifyBottomUpCheckpointArgs),
+    SubmissionCosts(SubmissionCostsArgs),
+    Watch(WatchBottomUpCheckpointsArgs),
 }
@@ -1,6 +1,7 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: MIT
 
+use crate::commands::checkpoint::relayer_admin::RelayerAdmin;
 use crate::commands::get_subnet_config;
 use crate::{require_fil_addr_from_str, CommandLineHandler, GlobalArguments};
 use anyhow::anyhow;
@@ -9,13 +10,19 @@ use clap::Args;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use ipc_api::subnet_id::SubnetID;
-use ipc_provider::checkpoint::BottomUpCheckpointManager;
+use ipc_provider::checkpoint::{BottomUpCheckpointManager, RelayerMetrics};
+use ipc_provider::checkpoint_costs::CostLedger;
 use ipc_provider::config::Config;
+use ipc_provider::manager::EthSubnetManager;
 use ipc_provider::new_evm_keystore_from_config;
 use ipc_wallet::EvmKeyStore;
+use prometheus::Registry;
+use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 const DEFAULT_POLLING_INTERVAL: u64 = 15;
 
@@ -29,53 +36,251 @@ impl CommandLineHandler for BottomUpRelayer {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("start bottom up relayer with args: {:?}", arguments);
 
-        let config_path = global.config_path();
-        let config = Arc::new(Config::from_file(&config_path)?);
-        let mut keystore = new_evm_keystore_from_config(config)?;
-        let submitter = match (arguments.submitter.as_ref(), keystore.get_default()?) {
-            (Some(submitter), _) => require_fil_addr_from_str(submitter)?,
-            (None, Some(addr)) => {
-                log::info!("using default address: {addr:?}");
-                Address::try_from(addr)?
-            }
-            _ => {
-                return Err(anyhow!("no submitter address provided"));
-            }
-        };
-
         let subnet = SubnetID::from_str(&arguments.subnet)?;
-        let parent = subnet
-            .parent()
-            .ok_or_else(|| anyhow!("root does not have parent"))?;
-
-        let child = get_subnet_config(&config_path, &subnet)?;
-        let parent = get_subnet_config(&config_path, &parent)?;
-
-        let mut manager = BottomUpCheckpointManager::new_evm_manager(
-            parent.clone(),
-            child.clone(),
-            Arc::new(RwLock::new(keystore)),
-            arguments.max_parallelism,
-        )
-        .await?;
-
-        if let Some(v) = arguments.finalization_blocks {
-            manager = manager.with_finalization_blocks(v as ChainEpoch);
-        }
-
         let interval = Duration::from_secs(
             arguments
                 .checkpoint_interval_sec
                 .unwrap_or(DEFAULT_POLLING_INTERVAL),
         );
-        manager.run(submitter, interval).await;
+        let drain_timeout = Duration::from_secs(arguments.shutdown_timeout_sec);
+
+        let metrics = Arc::new(RelayerMetrics::new()?);
+        let cost_ledger = arguments
+            .cost_ledger
+            .as_ref()
+            .map(|path| Arc::new(CostLedger::new(path)));
+        let running = Arc::new(AtomicBool::new(false));
+
+        let (signals_tx, mut signals) = mpsc::unbounded_channel();
+        listen_signals(signals_tx.clone());
+
+        if let Some(listen_addr) = arguments.admin_listen_addr {
+            let registry = Registry::new();
+            metrics.register(&registry)?;
+            let admin = RelayerAdmin {
+                metrics: metrics.clone(),
+                registry,
+                cost_ledger: cost_ledger.clone(),
+                running: running.clone(),
+                signals: signals_tx,
+                token: arguments.admin_token.clone(),
+            };
+            tokio::spawn(async move {
+                if let Err(e) = admin.serve(listen_addr).await {
+                    log::error!("relayer admin endpoints stopped: {e}");
+                }
+            });
+        }
+
+        let mut relayer =
+            Some(new_relayer(global, arguments, &subnet, &metrics, &cost_ledger).await?);
+
+        loop {
+            let Some((submitters, manager)) = relayer.take() else {
+                // Stopped on request, wait to be started again, with the config as it is by then.
+                match signals.recv().await {
+                    Some(RelayerSignal::Start) => {
+                        match new_relayer(global, arguments, &subnet, &metrics, &cost_ledger).await
+                        {
+                            Ok(r) => relayer = Some(r),
+                            Err(e) => log::error!("cannot start the relayer: {e}"),
+                        }
+                    }
+                    Some(RelayerSignal::Reload | RelayerSignal::Stop) => {}
+                    Some(RelayerSignal::Shutdown) | None => return Ok(()),
+                }
+                continue;
+            };
+
+            let mut stopped = false;
+
+            // Keep running the current manager until we are asked to shut down or stop, or until
+            // the config is reloaded successfully; an invalid config keeps the current one.
+            let stop = async {
+                while let Some(signal) = signals.recv().await {
+                    match signal {
+                        RelayerSignal::Reload => {
+                            log::info!("reloading config from {}", global.config_path());
+                            match new_relayer(global, arguments, &subnet, &metrics, &cost_ledger)
+                                .await
+                            {
+                                Ok(r) => {
+                                    relayer = Some(r);
+                                    return;
+                                }
+                                Err(e) => {
+                                    log::error!(
+                                        "cannot reload config, keeping the current one: {e}"
+                                    )
+                                }
+                            }
+                        }
+                        RelayerSignal::Stop => {
+                            stopped = true;
+                            return;
+                        }
+                        RelayerSignal::Start => log::debug!("relayer already running"),
+                        RelayerSignal::Shutdown => return,
+                    }
+                }
+            };
+
+            running.store(true, Ordering::SeqCst);
+            manager
+                .run_until(submitters, interval, stop, drain_timeout)
+                .await;
+            running.store(false, Ordering::SeqCst);
+
+            if relayer.is_none() && !stopped {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Create a checkpoint manager from the current contents of the config file, along with the
+/// addresses of the submitters, re-reading the keystore to pick up any rotated keys.
+async fn new_relayer(
+    global: &GlobalArguments,
+    arguments: &BottomUpRelayerArgs,
+    subnet: &SubnetID,
+    metrics: &Arc<RelayerMetrics>,
+    cost_ledger: &Option<Arc<CostLedger>>,
+) -> anyhow::Result<(Vec<Address>, BottomUpCheckpointManager<EthSubnetManager>)> {
+    let config_path = global.config_path();
+    let config = Arc::new(Config::from_file(&config_path)?);
+    let mut keystore = new_evm_keystore_from_config(config)?;
+    let submitters = match (arguments.submitter.is_empty(), keystore.get_default()?) {
+        (false, _) => arguments
+            .submitter
+            .iter()
+            .map(|s| require_fil_addr_from_str(s))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        (true, Some(addr)) => {
+            log::info!("using default address: {addr:?}");
+            vec![Address::try_from(addr)?]
+        }
+        _ => {
+            return Err(anyhow!("no submitter address provided"));
+        }
+    };
+
+    let parent = subnet
+        .parent()
+        .ok_or_else(|| anyhow!("root does not have parent"))?;
+
+    let child = get_subnet_config(&config_path, subnet)?;
+    let parent = get_subnet_config(&config_path, &parent)?;
+
+    let mut manager = BottomUpCheckpointManager::new_evm_manager(
+        parent,
+        child,
+        Arc::new(RwLock::new(keystore)),
+        arguments.max_parallelism,
+    )
+    .await?
+    .with_metrics(metrics.clone());
+
+    if let Some(v) = arguments.finalization_blocks {
+        manager = manager.with_finalization_blocks(v as ChainEpoch);
+    }
+    if let Some(ledger) = cost_ledger {
+        manager = manager.with_cost_ledger(ledger.clone());
+    }
+
+    Ok((submitters, manager))
+}
+
+/// Signals the relayer reacts to, from the process or the admin endpoints.
+pub(crate) enum RelayerSignal {
+    /// Re-read the config and restart with it (SIGHUP).
+    Reload,
+    /// Drain in-flight submissions and exit (SIGINT or SIGTERM).
+    Shutdown,
+    /// Drain in-flight submissions and wait to be started again (`POST /stop`).
+    Stop,
+    /// Start submitting again after a stop (`POST /start`).
+    Start,
+}
 
-        Ok(())
+/// Forward the process signals to a channel, so they can be awaited repeatedly.
+fn listen_signals(tx: mpsc::UnboundedSender<RelayerSignal>) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(s) => Some(s),
+            Err(e) => {
+                log::error!("cannot listen for SIGHUP, config reload disabled: {e}");
+                None
+            }
+        };
+
+        loop {
+            #[cfg(unix)]
+            let reload = async {
+                match hangup.as_mut() {
+                    Some(s) => {
+                        s.recv().await;
+                    }
+                    None => std::future::pending::<()>().await,
+                }
+            };
+
+            #[cfg(not(unix))]
+            let reload = std::future::pending::<()>();
+
+            let signal = tokio::select! {
+                _ = shutdown_signal() => RelayerSignal::Shutdown,
+                _ = reload => RelayerSignal::Reload,
+            };
+
+            let is_shutdown = matches!(signal, RelayerSignal::Shutdown);
+
+            if tx.send(signal).is_err() || is_shutdown {
+                return;
+            }
+        }
+    });
+}
+
+/// Resolves when the process receives either SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("cannot listen for ctrl-c: {e}");
+            std::future::pending::<()>().await
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut s) => {
+                s.recv().await;
+            }
+            Err(e) => {
+                log::error!("cannot listen for SIGTERM: {e}");
+                std::future::pending::<()>().await
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
     }
 }
 
 #[derive(Debug, Args)]
-#[command(about = "Start the bottom up relayer daemon")]
+#[command(
+    about = "Start the bottom up relayer daemon",
+    long_about = "Start the bottom up relayer daemon. Send SIGHUP to reload the config and keystore without a restart. \
+        With --admin-listen-addr, its health and metrics are served over HTTP, where it can be stopped and started too."
+)]
 pub(crate) struct BottomUpRelayerArgs {
     #[arg(long, help = "The subnet id of the checkpointing subnet")]
     pub subnet: String,
@@ -87,12 +292,38 @@ pub(crate) struct BottomUpRelayerArgs {
         help = "The number of blocks away from chain head that is considered final"
     )]
     pub finalization_blocks: Option<u64>,
-    #[arg(long, help = "The hex encoded address of the submitter")]
-    pub submitter: Option<String>,
+    #[arg(
+        long,
+        num_args = 1..,
+        help = "The hex encoded addresses of the submitters, separated by space, used in turns"
+    )]
+    pub submitter: Vec<String>,
     #[arg(
         long,
         default_value = "4",
         help = "The max parallelism for submitting checkpoints"
     )]
     pub max_parallelism: usize,
+    #[arg(
+        long,
+        default_value = "30",
+        help = "The number of seconds to wait for in-flight checkpoint submissions on shutdown"
+    )]
+    pub shutdown_timeout_sec: u64,
+    #[arg(
+        long,
+        help = "The address to serve the relayer health, metrics and start/stop endpoints on, e.g. 127.0.0.1:9185"
+    )]
+    pub admin_listen_addr: Option<SocketAddr>,
+    #[arg(
+        long,
+        env = "IPC_RELAYER_ADMIN_TOKEN",
+        help = "The bearer token required to stop and start the relayer over HTTP; without it, only local requests can"
+    )]
+    pub admin_token: Option<String>,
+    #[arg(
+        long,
+        help = "The file to record the parent gas spent on each checkpoint submission in, reported by `checkpoint submission-costs`"
+    )]
+    pub cost_ledger: Option<String>,
 }
@@ -0,0 +1,173 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! HTTP endpoints to supervise a running bottom up relayer.
+
+use crate::commands::checkpoint::relayer::RelayerSignal;
+use axum::extract::{ConnectInfo, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ipc_provider::checkpoint::RelayerMetrics;
+use ipc_provider::checkpoint_costs::{cost_report, CostLedger};
+use prometheus::{Encoder, Registry, TextEncoder};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+
+/// The number of failed submission rounds in a row after which the relayer is reported unhealthy.
+const UNHEALTHY_AFTER_FAILED_ROUNDS: u64 = 3;
+
+pub(crate) struct RelayerAdmin {
+    pub metrics: Arc<RelayerMetrics>,
+    pub registry: Registry,
+    /// Where the costs of the submissions are recorded, if anywhere.
+    pub cost_ledger: Option<Arc<CostLedger>>,
+    /// Whether a checkpoint manager is currently running.
+    pub running: Arc<AtomicBool>,
+    pub signals: mpsc::UnboundedSender<RelayerSignal>,
+    /// The bearer token the start and stop requests have to carry; without one,
+    /// they are only accepted from the local host.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelayerHealth {
+    healthy: bool,
+    running: bool,
+    consecutive_failed_rounds: u64,
+    last_round_timestamp: Option<u64>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CostsQuery {
+    /// Only include the submissions from this Unix timestamp.
+    since: Option<u64>,
+    /// The number of seconds to add up the costs over in each period.
+    period: Option<u64>,
+}
+
+impl RelayerAdmin {
+    /// Serve the health, metrics, costs and start/stop endpoints until the process exits.
+    pub async fn serve(self, listen_addr: SocketAddr) -> anyhow::Result<()> {
+        let router = Router::new()
+            .route("/health", get(health))
+            .route("/metrics", get(metrics))
+            .route("/costs", get(costs))
+            .route("/start", post(start))
+            .route("/stop", post(stop))
+            .with_state(Arc::new(self));
+
+        log::info!("serving relayer admin endpoints on {listen_addr}");
+        axum::Server::try_bind(&listen_addr)?
+            .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+            .await?;
+        Ok(())
+    }
+
+    fn health(&self) -> RelayerHealth {
+        let running = self.running.load(Ordering::SeqCst);
+        let consecutive_failed_rounds = self.metrics.consecutive_failed_rounds();
+        RelayerHealth {
+            healthy: running && consecutive_failed_rounds < UNHEALTHY_AFTER_FAILED_ROUNDS,
+            running,
+            consecutive_failed_rounds,
+            last_round_timestamp: self.metrics.last_round_timestamp(),
+            last_error: self.metrics.last_error(),
+        }
+    }
+
+    /// Check that a start or stop request is allowed to control the relayer.
+    fn authorize(&self, peer: &SocketAddr, headers: &HeaderMap) -> Result<(), StatusCode> {
+        let Some(token) = &self.token else {
+            return if peer.ip().is_loopback() {
+                Ok(())
+            } else {
+                Err(StatusCode::FORBIDDEN)
+            };
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if bool::from(given.as_bytes().ct_eq(token.as_bytes())) {
+            Ok(())
+        } else {
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+
+    fn send(&self, signal: RelayerSignal) -> StatusCode {
+        match self.signals.send(signal) {
+            Ok(()) => StatusCode::ACCEPTED,
+            Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+async fn health(State(admin): State<Arc<RelayerAdmin>>) -> impl IntoResponse {
+    let health = admin.health();
+    let status = if health.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health))
+}
+
+async fn metrics(State(admin): State<Arc<RelayerAdmin>>) -> impl IntoResponse {
+    let mut buffer = Vec::new();
+    match TextEncoder::new().encode(&admin.registry.gather(), &mut buffer) {
+        Ok(()) => (StatusCode::OK, String::from_utf8_lossy(&buffer).to_string()),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+async fn costs(
+    State(admin): State<Arc<RelayerAdmin>>,
+    Query(query): Query<CostsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let Some(ledger) = &admin.cost_ledger else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "the relayer is not recording submission costs".to_string(),
+        ));
+    };
+    let period = query.period.unwrap_or(24 * 3600);
+    ledger
+        .read()
+        .and_then(|costs| cost_report(&costs, None, query.since, period))
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))
+}
+
+async fn start(
+    State(admin): State<Arc<RelayerAdmin>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = admin.authorize(&peer, &headers) {
+        log::warn!("unauthorized relayer start request from {peer}");
+        return status;
+    }
+    log::info!("relayer start requested");
+    admin.send(RelayerSignal::Start)
+}
+
+async fn stop(
+    State(admin): State<Arc<RelayerAdmin>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = admin.authorize(&peer, &headers) {
+        log::warn!("unauthorized relayer stop request from {peer}");
+        return status;
+    }
+    log::info!("relayer stop requested");
+    admin.send(RelayerSignal::Stop)
+}
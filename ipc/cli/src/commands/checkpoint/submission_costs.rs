@@ -0,0 +1,73 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Report the costs of the checkpoint submissions of a relayer
+
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use clap::Args;
+use ipc_api::subnet_id::SubnetID;
+use ipc_provider::checkpoint_costs::{cost_report, CostLedger};
+
+use crate::{CommandLineHandler, GlobalArguments};
+
+/// The command to add up the costs a relayer recorded for its checkpoint submissions.
+pub(crate) struct SubmissionCosts;
+
+#[async_trait]
+impl CommandLineHandler for SubmissionCosts {
+    type Arguments = SubmissionCostsArgs;
+
+    async fn handle(_global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("submission costs with args: {:?}", arguments);
+
+        let subnet = match &arguments.subnet {
+            Some(subnet) => Some(SubnetID::from_str(subnet)?),
+            None => None,
+        };
+        let since = match arguments.days {
+            Some(days) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                // Going back further than the epoch includes everything.
+                Some(now.saturating_sub(days.saturating_mul(24 * 3600)))
+            }
+            None => None,
+        };
+
+        let costs = CostLedger::new(&arguments.ledger).read()?;
+        let period_secs = arguments.period_hours.checked_mul(3600).ok_or_else(|| {
+            anyhow!(
+                "the reporting period of {} hours is too long",
+                arguments.period_hours
+            )
+        })?;
+        let report = cost_report(&costs, subnet.as_ref(), since, period_secs)?;
+
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Add up the parent gas spent on checkpoint submissions, per submitter and per period",
+    long_about = "Add up the parent gas spent on checkpoint submissions, per submitter and per period, \
+        from the ledger a relayer started with --cost-ledger records them in."
+)]
+pub(crate) struct SubmissionCostsArgs {
+    #[arg(long, help = "The cost ledger the relayer records its submissions in")]
+    pub ledger: String,
+    #[arg(long, help = "Only include the submissions for this subnet")]
+    pub subnet: Option<String>,
+    #[arg(long, help = "Only include the submissions of the last number of days")]
+    pub days: Option<u64>,
+    #[arg(
+        long,
+        default_value = "24",
+        help = "The number of hours to add up the costs over in each period"
+    )]
+    pub period_hours: u64,
+}
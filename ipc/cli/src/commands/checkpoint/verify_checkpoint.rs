@@ -0,0 +1,92 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Verify a bottom up checkpoint end to end
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use clap::Args;
+use fvm_shared::clock::ChainEpoch;
+use ipc_api::subnet_id::SubnetID;
+
+use crate::commands::get_ipc_provider;
+use crate::{CommandLineHandler, GlobalArguments};
+
+/// The command to show the quorum of a checkpoint and where the parent committed it.
+pub(crate) struct VerifyBottomUpCheckpoint;
+
+#[async_trait]
+impl CommandLineHandler for VerifyBottomUpCheckpoint {
+    type Arguments = VerifyBottomUpCheckpointArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("verify bottom up checkpoint with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+
+        let parent_blocks = match (arguments.parent_from_epoch, arguments.parent_to_epoch) {
+            (Some(from), Some(to)) => Some((from, to)),
+            (None, None) => None,
+            _ => anyhow::bail!("both ends of the parent block range are needed"),
+        };
+
+        let v = provider
+            .verify_bottom_up_checkpoint(&subnet, arguments.epoch, parent_blocks)
+            .await?;
+
+        println!("checkpoint: {:?}", v.quorum.bundle.checkpoint);
+        println!("hash: {}", hex::encode(&v.quorum.hash));
+        println!("hash matches checkpoint: {}", v.hash_matches);
+        for (signatory, signature) in v
+            .quorum
+            .bundle
+            .signatories
+            .iter()
+            .zip(v.quorum.bundle.signatures.iter())
+        {
+            println!("signature: {signatory} {}", hex::encode(signature));
+        }
+        println!(
+            "weight: {} of {} needed, quorum reached: {}",
+            v.quorum.current_weight, v.quorum.threshold, v.quorum.reached
+        );
+        println!("committed in parent: {}", v.committed);
+        match v.submission {
+            Some(s) => println!(
+                "parent transaction: 0x{} at height {} by {}",
+                hex::encode(s.tx_hash),
+                s.block_height,
+                s.submitter
+            ),
+            None if parent_blocks.is_some() => {
+                println!("parent transaction: not found in the searched blocks")
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Show the quorum of a bottom up checkpoint and the parent transaction that committed it"
+)]
+pub(crate) struct VerifyBottomUpCheckpointArgs {
+    #[arg(long, help = "The subnet which created the checkpoint")]
+    pub subnet: String,
+    #[arg(long, help = "The epoch of the checkpoint in the subnet")]
+    pub epoch: ChainEpoch,
+    #[arg(
+        long,
+        help = "Search the parent for the committing transaction from this epoch"
+    )]
+    pub parent_from_epoch: Option<ChainEpoch>,
+    #[arg(
+        long,
+        help = "Search the parent for the committing transaction up to this epoch"
+    )]
+    pub parent_to_epoch: Option<ChainEpoch>,
+}
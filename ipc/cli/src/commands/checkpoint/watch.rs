@@ -0,0 +1,119 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Watch the parent for missing bottom up checkpoints
+
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use clap::Args;
+use ipc_api::subnet_id::SubnetID;
+use ipc_provider::checkpoint_watcher::{CheckpointWatcher, WatcherMetrics};
+use prometheus::{Encoder, Registry, TextEncoder};
+
+use crate::commands::get_subnet_config;
+use crate::{CommandLineHandler, GlobalArguments};
+
+/// The command to alert when the parent stops committing the checkpoints of a subnet.
+pub(crate) struct WatchBottomUpCheckpoints;
+
+#[async_trait]
+impl CommandLineHandler for WatchBottomUpCheckpoints {
+    type Arguments = WatchBottomUpCheckpointsArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("watch bottom up checkpoints with args: {:?}", arguments);
+
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+        let parent = subnet
+            .parent()
+            .ok_or_else(|| anyhow!("root does not have parent"))?;
+
+        let config_path = global.config_path();
+        let child = get_subnet_config(&config_path, &subnet)?;
+        let parent = get_subnet_config(&config_path, &parent)?;
+
+        let metrics = Arc::new(WatcherMetrics::new()?);
+        let mut watcher =
+            CheckpointWatcher::new_evm_watcher(&parent, &child, arguments.max_missed_checkpoints)
+                .await?
+                .with_metrics(metrics.clone());
+        if let Some(url) = &arguments.webhook_url {
+            watcher = watcher.with_webhook(url.parse()?);
+        }
+
+        if let Some(listen_addr) = arguments.metrics_listen_addr {
+            let registry = Registry::new();
+            metrics.register(&registry)?;
+            tokio::spawn(async move {
+                if let Err(e) = serve_metrics(registry, listen_addr).await {
+                    log::error!("checkpoint watcher metrics endpoint stopped: {e}");
+                }
+            });
+        }
+
+        watcher
+            .run(Duration::from_secs(arguments.interval_sec))
+            .await;
+        Ok(())
+    }
+}
+
+async fn serve_metrics(registry: Registry, listen_addr: SocketAddr) -> anyhow::Result<()> {
+    let router = Router::new().route(
+        "/metrics",
+        get(move || {
+            let metrics = registry.gather();
+            async move {
+                let mut buffer = Vec::new();
+                match TextEncoder::new().encode(&metrics, &mut buffer) {
+                    Ok(()) => (StatusCode::OK, String::from_utf8_lossy(&buffer).to_string()),
+                    Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+                }
+            }
+        }),
+    );
+
+    log::info!("serving checkpoint watcher metrics on {listen_addr}");
+    axum::Server::try_bind(&listen_addr)?
+        .serve(router.into_make_service())
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Alert when the parent falls behind the bottom up checkpoints of a subnet",
+    long_about = "Alert when the parent falls behind the bottom up checkpoints of a subnet. \
+        The alert is logged, counted in the metrics, and posted to the webhook, if any, as JSON when it is raised and when it is resolved."
+)]
+pub(crate) struct WatchBottomUpCheckpointsArgs {
+    #[arg(long, help = "The subnet id of the checkpointing subnet")]
+    pub subnet: String,
+    #[arg(
+        long,
+        default_value = "2",
+        help = "The number of checkpoints due but not committed in the parent to alert after"
+    )]
+    pub max_missed_checkpoints: i64,
+    #[arg(
+        long,
+        default_value = "60",
+        help = "The number of seconds between checks"
+    )]
+    pub interval_sec: u64,
+    #[arg(long, help = "The URL to post the alerts to")]
+    pub webhook_url: Option<String>,
+    #[arg(
+        long,
+        help = "The address to serve the watcher metrics on, e.g. 127.0.0.1:9186"
+    )]
+    pub metrics_listen_addr: Option<SocketAddr>,
+}
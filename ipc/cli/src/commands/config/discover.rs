@@ -0,0 +1,105 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Discover the IPC contracts of a subnet and update the config with them.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use clap::Args;
+use ipc_api::subnet_id::SubnetID;
+
+use crate::{get_ipc_provider, require_fil_addr_from_str, CommandLineHandler, GlobalArguments};
+
+/// The command to discover the contract addresses of a subnet from its registry.
+pub(crate) struct DiscoverContracts;
+
+#[async_trait]
+impl CommandLineHandler for DiscoverContracts {
+    type Arguments = DiscoverContractsArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("discover contracts with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+        let registry = match &arguments.registry {
+            Some(address) => Some(require_fil_addr_from_str(address)?),
+            None => None,
+        };
+
+        let discovered = provider.discover_contracts(&subnet, registry).await?;
+
+        println!("registry: {}", discovered.registry_addr);
+        println!("gateway: {}", discovered.gateway_addr);
+        if let Some(addr) = discovered.subnet_actor_addr {
+            println!("subnet actor: {addr}");
+        }
+        if let Some(addr) = discovered.parent_gateway_addr {
+            println!("parent gateway: {addr}");
+        }
+
+        if arguments.dry_run {
+            return Ok(());
+        }
+
+        let config_path = global.config_path();
+        let mut config = global.config()?;
+        let mut updated = false;
+
+        if let Some(s) = config.subnets.get_mut(&subnet) {
+            if s.registry_addr() != discovered.registry_addr
+                || s.gateway_addr() != discovered.gateway_addr
+            {
+                log::info!(
+                    "updating {subnet} gateway from {} to {}",
+                    s.gateway_addr(),
+                    discovered.gateway_addr
+                );
+                s.set_registry_addr(discovered.registry_addr);
+                s.set_gateway_addr(discovered.gateway_addr);
+                updated = true;
+            }
+        }
+
+        if let (Some(parent), Some(parent_gateway)) =
+            (subnet.parent(), discovered.parent_gateway_addr)
+        {
+            if let Some(p) = config.subnets.get_mut(&parent) {
+                if p.gateway_addr() != parent_gateway {
+                    log::info!(
+                        "updating {parent} gateway from {} to {parent_gateway}",
+                        p.gateway_addr()
+                    );
+                    p.set_gateway_addr(parent_gateway);
+                    updated = true;
+                }
+            }
+        }
+
+        if updated {
+            config.write_to_file_async(&config_path).await?;
+            println!("config updated in {config_path}");
+        } else {
+            println!("config already up to date");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Discover the contracts of a subnet from its registry and update the config")]
+pub(crate) struct DiscoverContractsArgs {
+    #[arg(long, help = "The subnet to discover the contracts of")]
+    pub subnet: String,
+    #[arg(
+        long,
+        help = "The address of the subnet registry, defaults to the one in the config"
+    )]
+    pub registry: Option<String>,
+    #[arg(
+        long,
+        help = "Only print the discovered addresses without updating the config"
+    )]
+    pub dry_run: bool,
+}
@@ -2,11 +2,13 @@
 // SPDX-License-Identifier: MIT
 //! This mod triggers a config reload in the IPC-Agent Json RPC server.
 
+mod discover;
 mod init;
 
 use clap::{Args, Subcommand};
 use std::fmt::Debug;
 
+use crate::commands::config::discover::{DiscoverContracts, DiscoverContractsArgs};
 use crate::commands::config::init::{InitConfig, InitConfigArgs};
 use crate::{CommandLineHandler, GlobalArguments};
 
@@ -22,6 +24,7 @@ impl ConfigCommandsArgs {
     pub async fn handle(&self, global: &GlobalArguments) -> anyhow::Result<()> {
         match &self.command {
             Commands::Init(args) => InitConfig::handle(global, args).await,
+            Commands::Discover(args) => DiscoverContracts::handle(global, args).await,
         }
     }
 }
@@ -29,4 +32,5 @@ impl ConfigCommandsArgs {
 #[derive(Debug, Subcommand)]
 pub(crate) enum Commands {
     Init(InitConfigArgs),
+    Discover(DiscoverContractsArgs),
 }
@@ -0,0 +1,53 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! List pending bottom up cross messages
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use clap::Args;
+use ipc_api::evm::postbox_msg_key;
+use ipc_api::subnet_id::SubnetID;
+
+use crate::commands::crossmsg::topdown_cross::describe_message;
+use crate::commands::get_ipc_provider;
+use crate::{CommandLineHandler, GlobalArguments};
+
+/// The command to list the bottom up cross messages of a subnet not committed in its parent yet
+pub(crate) struct ListPendingBottomupMsgs;
+
+#[async_trait]
+impl CommandLineHandler for ListPendingBottomupMsgs {
+    type Arguments = ListPendingBottomupMsgsArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("list pending bottomup messages with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+
+        let pending = provider.pending_bottom_up_msgs(&subnet).await?;
+        println!("number of pending messages: {}", pending.len());
+        for p in pending {
+            println!(
+                "checkpoint height: {}, from: {}, to: {}, {}, nonce: {}, postbox key: 0x{}",
+                p.checkpoint_height,
+                p.msg.from.to_string()?,
+                p.msg.to.to_string()?,
+                describe_message(&p.msg),
+                p.msg.nonce,
+                hex::encode(postbox_msg_key(&p.msg)?)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "List the bottom up cross messages of a subnet its parent has not committed yet")]
+pub(crate) struct ListPendingBottomupMsgsArgs {
+    #[arg(long, help = "The subnet id of the bottom up subnet")]
+    pub subnet: String,
+}
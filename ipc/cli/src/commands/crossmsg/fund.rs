@@ -8,10 +8,11 @@ use fvm_shared::bigint::BigInt;
 use fvm_shared::econ::TokenAmount;
 use ipc_api::subnet_id::SubnetID;
 use num_traits::Num;
+use serde::Serialize;
 use std::{fmt::Debug, str::FromStr};
 
 use crate::{
-    f64_to_token_amount, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler,
+    audit, f64_to_token_amount, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler,
     GlobalArguments,
 };
 
@@ -40,24 +41,17 @@ impl CommandLineHandler for Fund {
             None => None,
         };
 
-        println!(
-            "fund performed in epoch: {:?}",
-            provider
-                .fund(
-                    subnet,
-                    gateway_addr,
-                    from,
-                    to,
-                    f64_to_token_amount(arguments.amount)?,
-                )
-                .await?,
-        );
+        let amount = f64_to_token_amount(arguments.amount)?;
+        let res = provider.fund(subnet, gateway_addr, from, to, amount).await;
+        audit::record(global, &provider, "fund", from, arguments, &res);
+
+        println!("fund performed in epoch: {:?}", res?);
 
         Ok(())
     }
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Serialize)]
 #[command(about = "Send funds from a parent to a child subnet")]
 pub(crate) struct FundArgs {
     #[arg(long, help = "The gateway address of the subnet")]
@@ -1,21 +1,25 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: MIT
+use self::bottomup_cross::{ListPendingBottomupMsgs, ListPendingBottomupMsgsArgs};
 use self::fund::{FundWithToken, FundWithTokenArgs, PreFund, PreFundArgs};
+use self::nonces::{CrossMsgNonces, CrossMsgNoncesArgs};
 use self::release::{PreRelease, PreReleaseArgs};
 use self::topdown_cross::{
     LatestParentFinality, LatestParentFinalityArgs, ListTopdownMsgs, ListTopdownMsgsArgs,
 };
 use crate::commands::crossmsg::fund::Fund;
-use crate::commands::crossmsg::propagate::Propagate;
+use crate::commands::crossmsg::propagate::{Propagate, ShowPostboxMsg};
 use crate::commands::crossmsg::release::Release;
 use crate::{CommandLineHandler, GlobalArguments};
 use fund::FundArgs;
-use propagate::PropagateArgs;
+use propagate::{PropagateArgs, ShowPostboxMsgArgs};
 use release::ReleaseArgs;
 
 use clap::{Args, Subcommand};
 
+mod bottomup_cross;
 pub mod fund;
+mod nonces;
 pub mod propagate;
 pub mod release;
 mod topdown_cross;
@@ -37,8 +41,13 @@ impl CrossMsgsCommandsArgs {
             Commands::Release(args) => Release::handle(global, args).await,
             Commands::PreRelease(args) => PreRelease::handle(global, args).await,
             Commands::Propagate(args) => Propagate::handle(global, args).await,
+            Commands::ShowPostboxMsg(args) => ShowPostboxMsg::handle(global, args).await,
             Commands::ListTopdownMsgs(args) => ListTopdownMsgs::handle(global, args).await,
+            Commands::ListPendingBottomupMsgs(args) => {
+                ListPendingBottomupMsgs::handle(global, args).await
+            }
             Commands::ParentFinality(args) => LatestParentFinality::handle(global, args).await,
+            Commands::Nonces(args) => CrossMsgNonces::handle(global, args).await,
         }
     }
 }
@@ -51,6 +60,9 @@ pub(crate) enum Commands {
     Release(ReleaseArgs),
     PreRelease(PreReleaseArgs),
     Propagate(PropagateArgs),
+    ShowPostboxMsg(ShowPostboxMsgArgs),
     ListTopdownMsgs(ListTopdownMsgsArgs),
+    ListPendingBottomupMsgs(ListPendingBottomupMsgsArgs),
     ParentFinality(LatestParentFinalityArgs),
+    Nonces(CrossMsgNoncesArgs),
 }
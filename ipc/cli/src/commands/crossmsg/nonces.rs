@@ -0,0 +1,59 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Cross-net message nonces of a subnet
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use clap::Args;
+use ipc_api::subnet_id::SubnetID;
+use ipc_provider::NoncePair;
+
+use crate::commands::get_ipc_provider;
+use crate::{CommandLineHandler, GlobalArguments};
+
+/// The command to compare the cross-net message nonces between a subnet and its parent
+pub(crate) struct CrossMsgNonces;
+
+#[async_trait]
+impl CommandLineHandler for CrossMsgNonces {
+    type Arguments = CrossMsgNoncesArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("cross msg nonces with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+
+        let nonces = provider.cross_msg_nonces(&subnet).await?;
+        print_pair("top-down", "parent", "child", &nonces.top_down);
+        print_pair("bottom-up", "child", "parent", &nonces.bottom_up);
+
+        Ok(())
+    }
+}
+
+fn print_pair(direction: &str, source: &str, destination: &str, pair: &NoncePair) {
+    let status = if pair.in_sync() {
+        "in sync".to_string()
+    } else {
+        format!("MISMATCH, {} pending", pair.pending())
+    };
+    println!(
+        "{direction}: next nonce in {source}: {}, next applied nonce in {destination}: {} ({status})",
+        pair.sent, pair.applied
+    );
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Compare the cross-net message nonces of a subnet with its parent",
+    long_about = "Compare the cross-net message nonces of a subnet with its parent, in both directions. \
+    A mismatch that doesn't go away means cross-net messages are not being applied; \
+    bottom-up messages are only applied with checkpoints, so some lag is expected there."
+)]
+pub(crate) struct CrossMsgNoncesArgs {
+    #[arg(long, help = "The subnet id to check the nonces of")]
+    pub subnet: String,
+}
@@ -2,11 +2,17 @@
 // SPDX-License-Identifier: MIT
 //! Propagate cli command handler.
 
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use clap::Args;
-use std::fmt::Debug;
+use ipc_api::subnet_id::SubnetID;
+use serde::Serialize;
+use std::{fmt::Debug, str::FromStr};
 
-use crate::{CommandLineHandler, GlobalArguments};
+use crate::commands::crossmsg::topdown_cross::describe_message;
+use crate::{
+    audit, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler, GlobalArguments,
+};
 
 /// The command to propagate a message in the postbox.
 pub(crate) struct Propagate;
@@ -15,20 +21,105 @@ pub(crate) struct Propagate;
 impl CommandLineHandler for Propagate {
     type Arguments = PropagateArgs;
 
-    async fn handle(_global: &GlobalArguments, _arguments: &Self::Arguments) -> anyhow::Result<()> {
-        todo!()
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("propagate operation with args: {:?}", arguments);
+
+        let mut provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+        let from = match &arguments.from {
+            Some(address) => Some(require_fil_addr_from_str(address)?),
+            None => None,
+        };
+        let gateway_addr = match &arguments.gateway_address {
+            Some(address) => Some(require_fil_addr_from_str(address)?),
+            None => None,
+        };
+        let key = parse_postbox_msg_key(&arguments.postbox_msg_key)?;
+
+        // Propagating a missing message would still cost gas, so check it is there first.
+        let msg = provider
+            .postbox_msg(&subnet, gateway_addr, key.clone())
+            .await?
+            .ok_or_else(|| anyhow!("no message in the postbox of {subnet} under this key"))?;
+        println!(
+            "propagating message from: {}, to: {}, {}, nonce: {}",
+            msg.from.to_string()?,
+            msg.to.to_string()?,
+            describe_message(&msg),
+            msg.nonce
+        );
+
+        let res = provider.propagate(subnet, gateway_addr, from, key).await;
+        audit::record(global, &provider, "propagate", from, arguments, &res);
+        res?;
+
+        println!("message propagated");
+
+        Ok(())
     }
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Serialize)]
 #[command(about = "Propagate operation in the gateway actor")]
 pub(crate) struct PropagateArgs {
-    #[arg(long, help = "The JSON RPC server url for ipc agent")]
-    pub ipc_agent_url: Option<String>,
+    #[arg(long, help = "The gateway address of the subnet")]
+    pub gateway_address: Option<String>,
     #[arg(long, help = "The address that pays for the propagation gas")]
     pub from: Option<String>,
-    #[arg(long, help = "The subnet of the message to propagate")]
+    #[arg(long, help = "The subnet whose postbox holds the message")]
     pub subnet: String,
-    #[arg(help = "The message cid to propagate")]
+    #[arg(help = "The postbox key of the message to propagate, as hex")]
     pub postbox_msg_key: String,
 }
+
+/// The command to show a message waiting in the postbox.
+pub(crate) struct ShowPostboxMsg;
+
+#[async_trait]
+impl CommandLineHandler for ShowPostboxMsg {
+    type Arguments = ShowPostboxMsgArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("show postbox message with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+        let gateway_addr = match &arguments.gateway_address {
+            Some(address) => Some(require_fil_addr_from_str(address)?),
+            None => None,
+        };
+        let key = parse_postbox_msg_key(&arguments.postbox_msg_key)?;
+
+        match provider.postbox_msg(&subnet, gateway_addr, key).await? {
+            None => println!("no message in the postbox under this key"),
+            Some(msg) => println!(
+                "from: {}, to: {}, {}, nonce: {}",
+                msg.from.to_string()?,
+                msg.to.to_string()?,
+                describe_message(&msg),
+                msg.nonce
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show the message waiting to be propagated in the postbox under a key")]
+pub(crate) struct ShowPostboxMsgArgs {
+    #[arg(long, help = "The gateway address of the subnet")]
+    pub gateway_address: Option<String>,
+    #[arg(long, help = "The subnet whose postbox holds the message")]
+    pub subnet: String,
+    #[arg(help = "The postbox key of the message, as hex")]
+    pub postbox_msg_key: String,
+}
+
+fn parse_postbox_msg_key(key: &str) -> anyhow::Result<Vec<u8>> {
+    let key = hex::decode(key.trim_start_matches("0x")).context("postbox key is not hex")?;
+    if key.len() != 32 {
+        return Err(anyhow!("postbox key should be 32 bytes"));
+    }
+    Ok(key)
+}
@@ -5,10 +5,11 @@
 use async_trait::async_trait;
 use clap::Args;
 use ipc_api::subnet_id::SubnetID;
+use serde::Serialize;
 use std::{fmt::Debug, str::FromStr};
 
 use crate::{
-    f64_to_token_amount, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler,
+    audit, f64_to_token_amount, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler,
     GlobalArguments,
 };
 
@@ -37,24 +38,19 @@ impl CommandLineHandler for Release {
             None => None,
         };
 
-        println!(
-            "release performed in epoch: {:?}",
-            provider
-                .release(
-                    subnet,
-                    gateway_addr,
-                    from,
-                    to,
-                    f64_to_token_amount(arguments.amount)?,
-                )
-                .await?,
-        );
+        let amount = f64_to_token_amount(arguments.amount)?;
+        let res = provider
+            .release(subnet, gateway_addr, from, to, amount)
+            .await;
+        audit::record(global, &provider, "release", from, arguments, &res);
+
+        println!("release performed in epoch: {:?}", res?);
 
         Ok(())
     }
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Serialize)]
 #[command(about = "Release operation in the gateway actor")]
 pub(crate) struct ReleaseArgs {
     #[arg(long, help = "The gateway address of the subnet")]
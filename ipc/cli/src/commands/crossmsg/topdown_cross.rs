@@ -8,6 +8,7 @@ use std::str::FromStr;
 use async_trait::async_trait;
 use clap::Args;
 use fvm_shared::clock::ChainEpoch;
+use ipc_api::cross::{IpcEnvelope, IpcMsgKind};
 use ipc_api::subnet_id::SubnetID;
 
 use crate::commands::get_ipc_provider;
@@ -36,10 +37,10 @@ impl CommandLineHandler for ListTopdownMsgs {
             );
             for msg in result.value {
                 println!(
-                    "from: {}, to: {}, message: {}, nonce: {} ",
+                    "from: {}, to: {}, {}, nonce: {} ",
                     msg.from.to_string()?,
                     msg.to.to_string()?,
-                    hex::encode(msg.message),
+                    describe_message(&msg),
                     msg.nonce
                 );
             }
@@ -49,6 +50,31 @@ impl CommandLineHandler for ListTopdownMsgs {
     }
 }
 
+/// Show the kind of the message, and decode the payload of calls and receipts.
+pub(crate) fn describe_message(msg: &IpcEnvelope) -> String {
+    match msg.kind {
+        IpcMsgKind::Transfer => format!("kind: transfer, value: {}", msg.value),
+        IpcMsgKind::Call => match msg.call_msg() {
+            Ok(call) => format!(
+                "kind: call, value: {}, method: 0x{}, params: 0x{}",
+                msg.value,
+                hex::encode(call.method),
+                hex::encode(call.params)
+            ),
+            Err(_) => format!("kind: call, message: 0x{}", hex::encode(&msg.message)),
+        },
+        IpcMsgKind::Receipt => match msg.result_msg() {
+            Ok(result) => format!(
+                "kind: receipt, id: 0x{}, outcome: {:?}, return: 0x{}",
+                hex::encode(result.id),
+                result.outcome,
+                hex::encode(result.ret)
+            ),
+            Err(_) => format!("kind: receipt, message: 0x{}", hex::encode(&msg.message)),
+        },
+    }
+}
+
 #[derive(Debug, Args)]
 #[command(about = "List topdown cross messages for a specific epoch")]
 pub(crate) struct ListTopdownMsgsArgs {
@@ -0,0 +1,88 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Local event index cli command
+
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use clap::Args;
+use fvm_shared::clock::ChainEpoch;
+use ipc_api::subnet_id::SubnetID;
+
+use crate::{get_ipc_provider, CommandLineHandler, GlobalArguments};
+
+/// The command to keep the local event index of a subnet up to date.
+pub(crate) struct Index;
+
+#[async_trait]
+impl CommandLineHandler for Index {
+    type Arguments = IndexArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("index with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+        provider.check_subnet(&subnet).await?;
+
+        // Index as soon as a new block is produced if the subnet has a WebSocket endpoint.
+        let mut new_heads = if arguments.once {
+            None
+        } else {
+            provider.subscribe_new_heads(&subnet)?
+        };
+
+        loop {
+            provider.set_deadline(global.deadline());
+            match provider
+                .index_events(&subnet, arguments.from, arguments.confirmations)
+                .await
+            {
+                Ok(height) => log::info!("events of {subnet} indexed up to height {height}"),
+                Err(e) if arguments.once => return Err(e),
+                Err(e) => log::error!("failed to index events of {subnet}: {e:#}"),
+            }
+
+            if arguments.once {
+                return Ok(());
+            }
+            let interval = Duration::from_secs(arguments.interval);
+            match new_heads.as_mut() {
+                // Wait for the next block, but no longer than the interval, so we keep
+                // indexing if the subscription can't reconnect.
+                Some(heads) => {
+                    let _ = tokio::time::timeout(interval, heads.recv()).await;
+                }
+                None => tokio::time::sleep(interval).await,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    name = "index",
+    about = "Index the events of a subnet and of its child subnets into the local event index"
+)]
+pub(crate) struct IndexArgs {
+    #[arg(long, help = "The subnet whose gateway events are indexed")]
+    pub subnet: String,
+    #[arg(
+        long,
+        default_value = "0",
+        help = "The height to start indexing the gateway events from"
+    )]
+    pub from: ChainEpoch,
+    #[arg(
+        long,
+        default_value = "20",
+        help = "Only index blocks with this many blocks on top of them"
+    )]
+    pub confirmations: u64,
+    #[arg(long, default_value = "30", help = "Seconds between indexing rounds")]
+    pub interval: u64,
+    #[arg(long, help = "Index the events produced so far and exit")]
+    pub once: bool,
+}
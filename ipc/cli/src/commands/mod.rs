@@ -5,15 +5,19 @@
 mod checkpoint;
 mod config;
 mod crossmsg;
+mod index;
 // mod daemon;
 mod subnet;
+mod top;
 mod util;
 mod wallet;
 
 use crate::commands::checkpoint::CheckpointCommandsArgs;
 use crate::commands::crossmsg::CrossMsgsCommandsArgs;
+use crate::commands::index::{Index, IndexArgs};
+use crate::commands::top::{Top, TopArgs};
 use crate::commands::util::UtilCommandsArgs;
-use crate::GlobalArguments;
+use crate::{CommandLineHandler, GlobalArguments};
 use anyhow::{anyhow, Context, Result};
 
 use clap::{Command, CommandFactory, Parser, Subcommand};
@@ -47,6 +51,8 @@ enum Commands {
     CrossMsg(CrossMsgsCommandsArgs),
     Checkpoint(CheckpointCommandsArgs),
     Util(UtilCommandsArgs),
+    Top(TopArgs),
+    Index(IndexArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -133,6 +139,8 @@ pub async fn cli() -> anyhow::Result<()> {
                 Commands::Wallet(args) => args.handle(global).await,
                 Commands::Checkpoint(args) => args.handle(global).await,
                 Commands::Util(args) => args.handle(global).await,
+                Commands::Top(args) => Top::handle(global, args).await,
+                Commands::Index(args) => Index::handle(global, args).await,
             };
 
             r.with_context(|| format!("error processing command {:?}", args.command))
@@ -147,7 +155,10 @@ fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
 }
 
 pub(crate) fn get_ipc_provider(global: &GlobalArguments) -> Result<ipc_provider::IpcProvider> {
-    ipc_provider::IpcProvider::new_from_config(global.config_path())
+    let provider = ipc_provider::IpcProvider::new_from_config(global.config_path())?
+        .with_fee_override(global.fee_override());
+    provider.set_deadline(global.deadline());
+    Ok(provider)
 }
 
 pub(crate) fn f64_to_token_amount(f: f64) -> anyhow::Result<TokenAmount> {
@@ -0,0 +1,166 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Compare the genesis of two subnet nodes cli command
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::Value;
+use std::fmt::Debug;
+use url::Url;
+
+use crate::{CommandLineHandler, GlobalArguments};
+
+/// The command to compare the genesis served by two nodes.
+pub(crate) struct GenesisDiff;
+
+#[async_trait]
+impl CommandLineHandler for GenesisDiff {
+    type Arguments = GenesisDiffArgs;
+
+    async fn handle(_global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("genesis diff with args: {:?}", arguments);
+
+        let left = fetch_genesis(&arguments.left).await?;
+        let right = fetch_genesis(&arguments.right).await?;
+
+        let diffs = diff(&left, &right);
+        if diffs.is_empty() {
+            println!("genesis is identical");
+            return Ok(());
+        }
+
+        for d in diffs.iter() {
+            println!("{}: {} != {}", d.path, show(&d.left), show(&d.right));
+        }
+        Err(anyhow::anyhow!("found {} differences", diffs.len()))
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    name = "genesis-diff",
+    about = "Compare the genesis of two subnet nodes, field by field"
+)]
+pub(crate) struct GenesisDiffArgs {
+    #[arg(
+        long,
+        help = "The CometBFT RPC endpoint of the first node, e.g. http://node-1:26657"
+    )]
+    pub left: Url,
+    #[arg(long, help = "The CometBFT RPC endpoint of the second node")]
+    pub right: Url,
+}
+
+/// Fetch the genesis document, including the application state, from the CometBFT RPC endpoint.
+async fn fetch_genesis(url: &Url) -> anyhow::Result<Value> {
+    let url = format!("{}/genesis", url.as_str().trim_end_matches('/'));
+    let mut res: Value = reqwest::get(&url).await?.error_for_status()?.json().await?;
+
+    if let Some(err) = res.get("error") {
+        return Err(anyhow::anyhow!("failed to get genesis from {url}: {err}"));
+    }
+
+    match res.pointer_mut("/result/genesis") {
+        Some(genesis) => Ok(genesis.take()),
+        None => Err(anyhow::anyhow!("unexpected genesis response from {url}")),
+    }
+}
+
+/// A field that has a different value, or only exists, on one side.
+#[derive(Debug, PartialEq)]
+struct Difference {
+    path: String,
+    left: Option<Value>,
+    right: Option<Value>,
+}
+
+/// Collect the differences between two JSON values, down to the leaf fields.
+fn diff(left: &Value, right: &Value) -> Vec<Difference> {
+    let mut diffs = Vec::new();
+    diff_at(String::new(), Some(left), Some(right), &mut diffs);
+    diffs
+}
+
+fn diff_at(path: String, left: Option<&Value>, right: Option<&Value>, diffs: &mut Vec<Difference>) {
+    match (left, right) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            let mut keys = l.keys().chain(r.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for k in keys {
+                let path = if path.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{path}.{k}")
+                };
+                diff_at(path, l.get(k), r.get(k), diffs);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                diff_at(format!("{path}[{i}]"), l.get(i), r.get(i), diffs);
+            }
+        }
+        (l, r) if l != r => diffs.push(Difference {
+            path,
+            left: l.cloned(),
+            right: r.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn show(value: &Option<Value>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "<missing>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{diff, Difference};
+
+    #[test]
+    fn test_genesis_diff() {
+        let left = json!({
+            "chain_id": "123",
+            "app_state": {
+                "validators": [{"power": "100"}, {"power": "200"}],
+                "accounts": [],
+            }
+        });
+        let right = json!({
+            "chain_id": "123",
+            "app_state": {
+                "validators": [{"power": "100"}, {"power": "300"}],
+                "accounts": [{"balance": "1"}],
+                "ipc": null,
+            }
+        });
+
+        assert!(diff(&left, &left).is_empty());
+        assert_eq!(
+            diff(&left, &right),
+            vec![
+                Difference {
+                    path: "app_state.accounts[0]".into(),
+                    left: None,
+                    right: Some(json!({"balance": "1"})),
+                },
+                Difference {
+                    path: "app_state.ipc".into(),
+                    left: None,
+                    right: Some(json!(null)),
+                },
+                Difference {
+                    path: "app_state.validators[1].power".into(),
+                    left: Some(json!("200")),
+                    right: Some(json!("300")),
+                },
+            ]
+        );
+    }
+}
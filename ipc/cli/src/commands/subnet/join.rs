@@ -6,10 +6,11 @@ use async_trait::async_trait;
 use clap::Args;
 use ipc_api::subnet_id::SubnetID;
 use num_traits::Zero;
+use serde::Serialize;
 use std::{fmt::Debug, str::FromStr};
 
 use crate::{
-    f64_to_token_amount, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler,
+    audit, f64_to_token_amount, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler,
     GlobalArguments,
 };
 
@@ -30,27 +31,33 @@ impl CommandLineHandler for JoinSubnet {
             None => None,
         };
         let public_key = hex::decode(&arguments.public_key)?;
-        if let Some(initial_balance) = arguments.initial_balance.filter(|x| !x.is_zero()) {
-            log::info!("pre-funding address with {initial_balance}");
+        let collateral = f64_to_token_amount(arguments.collateral)?;
+        let initial_balance = match arguments.initial_balance.filter(|x| !x.is_zero()) {
+            Some(initial_balance) => Some(f64_to_token_amount(initial_balance)?),
+            None => None,
+        };
+
+        let res = async {
+            if let Some(initial_balance) = initial_balance {
+                log::info!("pre-funding address with {initial_balance}");
+                provider
+                    .pre_fund(subnet.clone(), from, initial_balance)
+                    .await?;
+            }
             provider
-                .pre_fund(subnet.clone(), from, f64_to_token_amount(initial_balance)?)
-                .await?;
+                .join_subnet(subnet, from, collateral, public_key)
+                .await
         }
-        let epoch = provider
-            .join_subnet(
-                subnet,
-                from,
-                f64_to_token_amount(arguments.collateral)?,
-                public_key,
-            )
-            .await?;
-        println!("joined at epoch: {epoch}");
+        .await;
+        audit::record(global, &provider, "join", from, arguments, &res);
+
+        println!("joined at epoch: {}", res?);
 
         Ok(())
     }
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Serialize)]
 #[command(name = "join", about = "Join a subnet")]
 pub struct JoinSubnetArgs {
     #[arg(long, help = "The address that joins the subnet")]
@@ -5,9 +5,13 @@
 use async_trait::async_trait;
 use clap::Args;
 use ipc_api::subnet_id::SubnetID;
+use serde::Serialize;
+use std::time::Duration;
 use std::{fmt::Debug, str::FromStr};
 
-use crate::{get_ipc_provider, require_fil_addr_from_str, CommandLineHandler, GlobalArguments};
+use crate::{
+    audit, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler, GlobalArguments,
+};
 
 /// The command to kill an existing subnet.
 pub struct KillSubnet;
@@ -26,15 +30,54 @@ impl CommandLineHandler for KillSubnet {
             None => None,
         };
 
-        provider.kill_subnet(subnet, from).await
+        let action = format!(
+            "kill subnet {subnet}, after which it can no longer be joined, staked in, or funded, and its validators can only claim their collateral back"
+        );
+
+        let Some(token) = &arguments.confirm else {
+            let token = provider.prepare_destructive(
+                &subnet,
+                from,
+                action.clone(),
+                Duration::from_secs(arguments.confirm_ttl),
+            )?;
+            println!("This will {action}.");
+            println!(
+                "To proceed, run the same command again within {} seconds with:\n--confirm {token}",
+                arguments.confirm_ttl
+            );
+            return Ok(());
+        };
+
+        provider.confirm_destructive(&subnet, from, &action, token)?;
+
+        let res = provider.kill_subnet(subnet, from).await;
+        audit::record(global, &provider, "kill", from, arguments, &res);
+        res
     }
 }
 
-#[derive(Debug, Args)]
-#[command(name = "kill", about = "Kill an existing subnet")]
+#[derive(Debug, Args, Serialize)]
+#[command(
+    name = "kill",
+    about = "Kill an existing subnet; run it once to get a confirmation token, then again with --confirm"
+)]
 pub struct KillSubnetArgs {
     #[arg(long, help = "The address that kills the subnet")]
     pub from: Option<String>,
     #[arg(long, help = "The subnet to kill")]
     pub subnet: String,
+    #[serde(skip)]
+    #[arg(
+        long,
+        help = "The confirmation token returned by running the command without it"
+    )]
+    pub confirm: Option<String>,
+    #[serde(skip)]
+    #[arg(
+        long,
+        default_value = "300",
+        help = "Seconds the confirmation token is valid for"
+    )]
+    pub confirm_ttl: u64,
 }
@@ -5,9 +5,12 @@
 use async_trait::async_trait;
 use clap::Args;
 use ipc_api::subnet_id::SubnetID;
+use serde::Serialize;
 use std::{fmt::Debug, str::FromStr};
 
-use crate::{get_ipc_provider, require_fil_addr_from_str, CommandLineHandler, GlobalArguments};
+use crate::{
+    audit, get_ipc_provider, require_fil_addr_from_str, CommandLineHandler, GlobalArguments,
+};
 
 /// The command to leave a new subnet.
 pub struct LeaveSubnet;
@@ -25,11 +28,13 @@ impl CommandLineHandler for LeaveSubnet {
             Some(address) => Some(require_fil_addr_from_str(address)?),
             None => None,
         };
-        provider.leave_subnet(subnet, from).await
+        let res = provider.leave_subnet(subnet, from).await;
+        audit::record(global, &provider, "leave", from, arguments, &res);
+        res
     }
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Serialize)]
 #[command(name = "leave", about = "Leaving a subnet")]
 pub struct LeaveSubnetArgs {
     #[arg(long, help = "The address that leaves the subnet")]
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 
 pub use crate::commands::subnet::create::{CreateSubnet, CreateSubnetArgs};
+use crate::commands::subnet::genesis_diff::{GenesisDiff, GenesisDiffArgs};
 use crate::commands::subnet::genesis_epoch::{GenesisEpoch, GenesisEpochArgs};
 pub use crate::commands::subnet::join::{JoinSubnet, JoinSubnetArgs};
 pub use crate::commands::subnet::kill::{KillSubnet, KillSubnetArgs};
@@ -13,6 +14,8 @@ use crate::commands::subnet::set_federated_power::{SetFederatedPower, SetFederat
 use crate::commands::subnet::show_gateway_contract_commit_sha::{
     ShowGatewayContractCommitSha, ShowGatewayContractCommitShaArgs,
 };
+use crate::commands::subnet::staking_queue::{ListStakingChangeQueue, ListStakingChangeQueueArgs};
+use crate::commands::subnet::status::{ShowSubnetStatus, ShowSubnetStatusArgs};
 use crate::commands::subnet::validator::{ValidatorInfo, ValidatorInfoArgs};
 use crate::{CommandLineHandler, GlobalArguments};
 use clap::{Args, Subcommand};
@@ -24,6 +27,7 @@ use self::rpc::{ChainIdSubnet, ChainIdSubnetArgs};
 
 pub mod bootstrap;
 pub mod create;
+mod genesis_diff;
 mod genesis_epoch;
 pub mod join;
 pub mod kill;
@@ -33,6 +37,8 @@ pub mod rpc;
 pub mod send_value;
 mod set_federated_power;
 pub mod show_gateway_contract_commit_sha;
+mod staking_queue;
+mod status;
 mod validator;
 
 #[derive(Debug, Args)]
@@ -63,7 +69,10 @@ impl SubnetCommandsArgs {
             Commands::AddBootstrap(args) => AddBootstrap::handle(global, args).await,
             Commands::ListBootstraps(args) => ListBootstraps::handle(global, args).await,
             Commands::GenesisEpoch(args) => GenesisEpoch::handle(global, args).await,
+            Commands::GenesisDiff(args) => GenesisDiff::handle(global, args).await,
             Commands::GetValidator(args) => ValidatorInfo::handle(global, args).await,
+            Commands::Status(args) => ShowSubnetStatus::handle(global, args).await,
+            Commands::StakingQueue(args) => ListStakingChangeQueue::handle(global, args).await,
             Commands::ShowGatewayContractCommitSha(args) => {
                 ShowGatewayContractCommitSha::handle(global, args).await
             }
@@ -88,7 +97,10 @@ pub(crate) enum Commands {
     AddBootstrap(AddBootstrapArgs),
     ListBootstraps(ListBootstrapsArgs),
     GenesisEpoch(GenesisEpochArgs),
+    GenesisDiff(GenesisDiffArgs),
     GetValidator(ValidatorInfoArgs),
+    Status(ShowSubnetStatusArgs),
+    StakingQueue(ListStakingChangeQueueArgs),
     ShowGatewayContractCommitSha(ShowGatewayContractCommitShaArgs),
     SetFederatedPower(SetFederatedPowerArgs),
 }
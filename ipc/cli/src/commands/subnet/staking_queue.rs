@@ -0,0 +1,63 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! List the pending staking changes of a subnet cli command
+
+use async_trait::async_trait;
+use clap::Args;
+use ipc_api::subnet_id::SubnetID;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use crate::{get_ipc_provider, CommandLineHandler, GlobalArguments};
+
+/// The command to list the staking changes which are waiting to be applied.
+pub(crate) struct ListStakingChangeQueue;
+
+#[async_trait]
+impl CommandLineHandler for ListStakingChangeQueue {
+    type Arguments = ListStakingChangeQueueArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("list staking change queue with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+
+        let changes = provider.pending_staking_changes(&subnet).await?;
+        if changes.is_empty() {
+            println!("no pending staking changes");
+            return Ok(());
+        }
+
+        // The subnet itself might not be configured or reachable, in which case
+        // we can still list the changes, just not tell which ones it has applied.
+        let applied = match provider.current_configuration_number(&subnet).await {
+            Ok(n) => Some(n),
+            Err(e) => {
+                log::warn!("cannot get the applied configuration number from the subnet: {e}");
+                None
+            }
+        };
+
+        for c in changes {
+            let status = match applied {
+                Some(n) if c.configuration_number <= n => "applied in subnet",
+                Some(_) => "queued",
+                None => "unknown",
+            };
+            println!("{}: {} ({status})", c.configuration_number, c.change);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    name = "staking-queue",
+    about = "List the staking changes of a subnet which are not yet confirmed by a checkpoint"
+)]
+pub(crate) struct ListStakingChangeQueueArgs {
+    #[arg(long, help = "The subnet id to list the pending staking changes of")]
+    pub subnet: String,
+}
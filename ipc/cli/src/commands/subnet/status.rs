@@ -0,0 +1,54 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Subnet status cli command
+
+use async_trait::async_trait;
+use clap::Args;
+use ipc_api::subnet_id::SubnetID;
+use ipc_provider::manager::SubnetStatus;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use crate::{get_ipc_provider, CommandLineHandler, GlobalArguments};
+
+/// The command to show the lifecycle status of a subnet.
+pub(crate) struct ShowSubnetStatus;
+
+#[async_trait]
+impl CommandLineHandler for ShowSubnetStatus {
+    type Arguments = ShowSubnetStatusArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("show subnet status with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let subnet = SubnetID::from_str(&arguments.subnet)?;
+
+        let status = provider.subnet_status(&subnet).await?;
+        println!("status: {status}");
+
+        if status != SubnetStatus::Created {
+            let height = provider.last_bottom_up_checkpoint_height(&subnet).await?;
+            println!("last bottom-up checkpoint height: {height}");
+        }
+
+        if status == SubnetStatus::Killed {
+            println!(
+                "the subnet is no longer registered in the gateway; validators and checkpoints \
+                 can still be queried, and remaining collateral claimed with `subnet claim`"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    name = "status",
+    about = "Show whether a subnet is waiting to bootstrap, active or killed"
+)]
+pub(crate) struct ShowSubnetStatusArgs {
+    #[arg(long, help = "The subnet id to query the status of")]
+    pub subnet: String,
+}
@@ -0,0 +1,274 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Live overview of the configured subnets cli command
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clap::Args;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_api::ethers_address_to_fil_address;
+use ipc_api::subnet_id::SubnetID;
+use ipc_provider::{CrossMsgNonces, IpcProvider};
+use ipc_wallet::{EthKeyAddress, EvmKeyStore};
+
+use crate::{get_ipc_provider, CommandLineHandler, GlobalArguments};
+
+/// The number of recent errors kept on screen.
+const MAX_ERRORS: usize = 10;
+
+/// The command to show a live overview of the configured subnets.
+pub(crate) struct Top;
+
+#[async_trait]
+impl CommandLineHandler for Top {
+    type Arguments = TopArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("top with args: {:?}", arguments);
+
+        let provider = get_ipc_provider(global)?;
+        let mut errors = VecDeque::new();
+
+        loop {
+            provider.set_deadline(global.deadline());
+            let overview = fetch_overview(&provider, &mut errors).await;
+            let screen = render(&overview, &errors);
+
+            if arguments.once {
+                print!("{screen}");
+                return Ok(());
+            }
+
+            // Clear the screen and move the cursor to the top, like `watch` does.
+            print!("\x1b[2J\x1b[H{screen}");
+
+            tokio::time::sleep(Duration::from_secs(arguments.interval)).await;
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(
+    name = "top",
+    about = "Show a live overview of the configured subnets: sync lag, checkpoints, pending messages and balances"
+)]
+pub(crate) struct TopArgs {
+    #[arg(long, default_value = "5", help = "Seconds between refreshes")]
+    pub interval: u64,
+    #[arg(long, help = "Print the overview once instead of refreshing it")]
+    pub once: bool,
+}
+
+/// What we know about a subnet at the time of the last refresh.
+#[derive(Debug, Default)]
+struct SubnetOverview {
+    id: String,
+    chain_head: Option<ChainEpoch>,
+    /// Only for child subnets with their parent in the config.
+    parent: Option<ParentOverview>,
+    balances: Vec<(String, Option<TokenAmount>)>,
+}
+
+#[derive(Debug, Default)]
+struct ParentOverview {
+    chain_head: Option<ChainEpoch>,
+    /// The latest parent height committed in the child.
+    finality: Option<ChainEpoch>,
+    /// The child height of the latest checkpoint committed in the parent.
+    checkpoint: Option<ChainEpoch>,
+    nonces: Option<CrossMsgNonces>,
+}
+
+impl ParentOverview {
+    /// How many blocks the child is behind the parent.
+    fn sync_lag(&self) -> Option<ChainEpoch> {
+        Some(self.chain_head? - self.finality?)
+    }
+}
+
+/// Query everything shown on the screen, recording the errors rather than giving up.
+async fn fetch_overview(
+    provider: &IpcProvider,
+    errors: &mut VecDeque<String>,
+) -> Vec<SubnetOverview> {
+    let mut subnets = provider
+        .list_connections()
+        .into_keys()
+        .collect::<Vec<SubnetID>>();
+    subnets.sort_by_key(|s| s.to_string());
+
+    let addresses = match evm_addresses(provider) {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            record(errors, "wallet", e);
+            Vec::new()
+        }
+    };
+
+    let mut overviews = Vec::new();
+    for subnet in subnets.iter() {
+        let id = subnet.to_string();
+        let mut overview = SubnetOverview {
+            id: id.clone(),
+            chain_head: ok_or_record(errors, &id, provider.chain_head(subnet).await),
+            ..Default::default()
+        };
+
+        if let Some(parent) = subnet.parent().filter(|p| subnets.contains(p)) {
+            overview.parent = Some(ParentOverview {
+                chain_head: ok_or_record(errors, &id, provider.chain_head(&parent).await),
+                finality: ok_or_record(errors, &id, provider.latest_parent_finality(subnet).await),
+                checkpoint: ok_or_record(
+                    errors,
+                    &id,
+                    provider.last_bottom_up_checkpoint_height(subnet).await,
+                ),
+                nonces: ok_or_record(errors, &id, provider.cross_msg_nonces(subnet).await),
+            });
+        }
+
+        for address in addresses.iter() {
+            let balance = match ethers_address_to_fil_address(&(address.clone()).into()) {
+                Ok(addr) => provider.wallet_balance(subnet, &addr).await,
+                Err(e) => Err(e),
+            };
+            let balance = ok_or_record(errors, &id, balance);
+            overview.balances.push((address.to_string(), balance));
+        }
+
+        overviews.push(overview);
+    }
+    overviews
+}
+
+fn evm_addresses(provider: &IpcProvider) -> anyhow::Result<Vec<EthKeyAddress>> {
+    let wallet = provider.evm_wallet()?;
+    let mut addresses = wallet.read().unwrap().list()?;
+    addresses.retain(|a| *a != EthKeyAddress::default());
+    addresses.sort_by_key(|a| a.to_string());
+    Ok(addresses)
+}
+
+fn ok_or_record<T>(
+    errors: &mut VecDeque<String>,
+    source: &str,
+    res: anyhow::Result<T>,
+) -> Option<T> {
+    match res {
+        Ok(v) => Some(v),
+        Err(e) => {
+            record(errors, source, e);
+            None
+        }
+    }
+}
+
+fn record(errors: &mut VecDeque<String>, source: &str, e: anyhow::Error) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    errors.push_front(format!("[{now}] {source}: {e}"));
+    errors.truncate(MAX_ERRORS);
+}
+
+fn render(overview: &[SubnetOverview], errors: &VecDeque<String>) -> String {
+    let mut s = String::new();
+    let show = |v: Option<ChainEpoch>| v.map(|v| v.to_string()).unwrap_or_else(|| "?".into());
+
+    let _ = writeln!(
+        s,
+        "{:<60} {:>10} {:>10} {:>12} {:>10} {:>10}",
+        "SUBNET", "HEIGHT", "SYNC LAG", "CHECKPOINT", "TOP-DOWN", "BOTTOM-UP"
+    );
+    for o in overview {
+        let pending = |f: fn(&CrossMsgNonces) -> u64| match o.parent.as_ref().and_then(|p| p.nonces)
+        {
+            Some(n) => f(&n).to_string(),
+            None => "?".into(),
+        };
+        let (lag, checkpoint, top_down, bottom_up) = match &o.parent {
+            Some(p) => (
+                show(p.sync_lag()),
+                // The age of the last checkpoint in child blocks.
+                show(o.chain_head.zip(p.checkpoint).map(|(h, c)| h - c)),
+                pending(|n| n.top_down.pending()),
+                pending(|n| n.bottom_up.pending()),
+            ),
+            None => Default::default(),
+        };
+        let _ = writeln!(
+            s,
+            "{:<60} {:>10} {:>10} {:>12} {:>10} {:>10}",
+            o.id,
+            show(o.chain_head),
+            lag,
+            checkpoint,
+            top_down,
+            bottom_up
+        );
+    }
+
+    let _ = writeln!(s, "\nBALANCES");
+    for o in overview {
+        for (address, balance) in o.balances.iter() {
+            let balance = balance
+                .as_ref()
+                .map(|b| b.to_string())
+                .unwrap_or_else(|| "?".into());
+            let _ = writeln!(s, "{:<60} {address} {balance}", o.id);
+        }
+    }
+
+    let _ = writeln!(s, "\nRECENT ERRORS");
+    for e in errors {
+        let _ = writeln!(s, "{e}");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{render, ParentOverview, SubnetOverview};
+
+    #[test]
+    fn test_render_overview() {
+        let overview = vec![
+            SubnetOverview {
+                id: "/r314159".into(),
+                chain_head: Some(1000),
+                ..Default::default()
+            },
+            SubnetOverview {
+                id: "/r314159/t410f".into(),
+                chain_head: Some(500),
+                parent: Some(ParentOverview {
+                    chain_head: Some(1000),
+                    finality: Some(990),
+                    checkpoint: Some(400),
+                    nonces: None,
+                }),
+                ..Default::default()
+            },
+        ];
+        let errors = VecDeque::from(vec!["[0] /r314159: timeout".to_string()]);
+
+        let screen = render(&overview, &errors);
+        let child = screen
+            .lines()
+            .find(|l| l.starts_with("/r314159/t410f"))
+            .unwrap();
+        let columns = child.split_whitespace().collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec!["/r314159/t410f", "500", "10", "100", "?", "?"]
+        );
+        assert!(screen.ends_with("[0] /r314159: timeout\n"));
+    }
+}
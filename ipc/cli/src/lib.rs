@@ -3,13 +3,17 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Args;
+use ethers::types::U256;
 use fvm_shared::address::Network;
 use num_traits::cast::FromPrimitive;
+use std::time::{Duration, Instant};
 
+mod audit;
 mod commands;
 
 pub use commands::*;
 use ipc_provider::config::Config;
+use ipc_provider::manager::evm::FeeOverride;
 
 /// The trait that represents the abstraction of a command line handler. To implement a new command
 /// line operation, implement this trait and register it.
@@ -43,6 +47,23 @@ pub struct GlobalArguments {
     #[arg(long = "network", default_value = "testnet", env = "IPC_NETWORK", value_parser = parse_network)]
     _network: Network,
 
+    /// Append a JSON line describing each fund, release, join and leave operation to this file.
+    #[arg(long, env = "IPC_CLI_AUDIT_LOG")]
+    audit_log: Option<String>,
+
+    /// Max fee per gas of the transactions, in attoFIL, instead of the one estimated from recent blocks.
+    #[arg(long, env = "IPC_CLI_MAX_FEE_PER_GAS")]
+    max_fee_per_gas: Option<u128>,
+
+    /// Priority fee per gas of the transactions, in attoFIL, instead of the one estimated from recent blocks.
+    #[arg(long, env = "IPC_CLI_MAX_PRIORITY_FEE_PER_GAS")]
+    max_priority_fee_per_gas: Option<u128>,
+
+    /// Cancel the requests to the subnets still in flight after this many seconds; for commands
+    /// which keep running, the timeout applies to each round.
+    #[arg(long, env = "IPC_CLI_TIMEOUT")]
+    timeout: Option<u64>,
+
     /// Legacy env var for network
     #[arg(long = "__network", hide = true, env = "NETWORK", value_parser = parse_network)]
     __network: Option<Network>,
@@ -60,6 +81,23 @@ impl GlobalArguments {
         Config::from_file(config_path)
     }
 
+    pub fn audit_log(&self) -> Option<&str> {
+        self.audit_log.as_deref()
+    }
+
+    pub fn fee_override(&self) -> FeeOverride {
+        FeeOverride {
+            max_fee_per_gas: self.max_fee_per_gas.map(U256::from),
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.map(U256::from),
+        }
+    }
+
+    /// The deadline of the requests sent from now on, if a timeout is set.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.timeout
+            .map(|secs| Instant::now() + Duration::from_secs(secs))
+    }
+
     pub fn network(&self) -> Network {
         self.__network.unwrap_or(self._network)
     }
@@ -2,18 +2,25 @@
 // SPDX-License-Identifier: MIT
 //! Bottom up checkpoint manager
 
+use crate::checkpoint_costs::CostLedger;
 use crate::config::Subnet;
-use crate::manager::{BottomUpCheckpointRelayer, EthSubnetManager};
+use crate::manager::{BottomUpCheckpointRelayer, EthSubnetManager, SubmitterUnavailable};
 use anyhow::{anyhow, Result};
 use futures_util::future::try_join_all;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
-use ipc_api::checkpoint::{BottomUpCheckpointBundle, QuorumReachedEvent};
+use ipc_api::checkpoint::{BottomUpCheckpointBundle, CheckpointSubmission, QuorumReachedEvent};
+use ipc_api::subnet_id::SubnetID;
 use ipc_wallet::{EthKeyAddress, PersistentKeyStore};
+use num_traits::ToPrimitive;
+use prometheus::{CounterVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry};
 use std::cmp::max;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::Semaphore;
 
 /// Tracks the config required for bottom up checkpoint submissions
@@ -24,6 +31,187 @@ pub struct CheckpointConfig {
     period: ChainEpoch,
 }
 
+/// What became of a checkpoint the relayer set out to submit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SubmissionStatus {
+    /// Committed in the parent by this relayer.
+    Submitted(CheckpointSubmission),
+    /// Committed in the parent by another relayer, before or while this one was submitting it.
+    RelayedByOther,
+}
+
+/// How long a submitter account is left out of the rotation after a nonce conflict or running
+/// out of funds, unless no other account is available.
+const SUBMITTER_BACKOFF: Duration = Duration::from_secs(300);
+
+/// The submitter accounts of a relayer, used in turns so that one drained or stuck account
+/// doesn't halt the checkpoint submissions.
+struct SubmitterPool {
+    submitters: Vec<Address>,
+    next: AtomicUsize,
+    /// The accounts left out of the rotation, until the given time.
+    backed_off: Mutex<HashMap<Address, Instant>>,
+}
+
+impl SubmitterPool {
+    fn new(submitters: Vec<Address>) -> Self {
+        Self {
+            submitters,
+            next: AtomicUsize::new(0),
+            backed_off: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The next submitter in turn which isn't backed off, or the one whose back off ends first.
+    fn next(&self) -> Address {
+        let now = Instant::now();
+        let backed_off = self.backed_off.lock().unwrap();
+        let start = self.next.fetch_add(1, Ordering::SeqCst);
+        (0..self.submitters.len())
+            .map(|i| self.submitters[(start + i) % self.submitters.len()])
+            .min_by_key(|a| backed_off.get(a).filter(|until| **until > now).copied())
+            .expect("at least one submitter")
+    }
+
+    fn back_off(&self, submitter: Address) {
+        let until = Instant::now() + SUBMITTER_BACKOFF;
+        self.backed_off.lock().unwrap().insert(submitter, until);
+    }
+
+    fn len(&self) -> usize {
+        self.submitters.len()
+    }
+}
+
+/// Whether the submission failed because of the account it was sent from, rather than the
+/// checkpoint itself, so it could go through from another account.
+fn is_submitter_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|e| e.is::<SubmitterUnavailable>())
+}
+
+/// Metrics on the checkpoints submitted by a relayer, which can be shared by the managers
+/// created over its lifetime, e.g. across config reloads.
+pub struct RelayerMetrics {
+    submitted: IntCounter,
+    relayed_by_other: IntCounter,
+    failed: IntCounter,
+    gas_used: IntCounterVec,
+    gas_fee: CounterVec,
+    last_submitted_height: IntGauge,
+    last_round_timestamp: IntGauge,
+    consecutive_failed_rounds: IntGauge,
+    last_error: Mutex<Option<String>>,
+}
+
+impl RelayerMetrics {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            submitted: IntCounter::new(
+                "ipc_relayer_checkpoints_submitted_total",
+                "Number of bottom-up checkpoints committed in the parent by this relayer",
+            )?,
+            relayed_by_other: IntCounter::new(
+                "ipc_relayer_checkpoints_relayed_by_other_total",
+                "Number of bottom-up checkpoints skipped because another relayer committed them",
+            )?,
+            failed: IntCounter::new(
+                "ipc_relayer_checkpoint_failures_total",
+                "Number of bottom-up checkpoint submissions which failed",
+            )?,
+            gas_used: IntCounterVec::new(
+                Opts::new(
+                    "ipc_relayer_gas_used_total",
+                    "Gas used by the checkpoint submissions of this relayer, per submitter",
+                ),
+                &["submitter"],
+            )?,
+            gas_fee: CounterVec::new(
+                Opts::new(
+                    "ipc_relayer_gas_fee_total",
+                    "Fees paid for the checkpoint submissions of this relayer, per submitter, in whole tokens",
+                ),
+                &["submitter"],
+            )?,
+            last_submitted_height: IntGauge::new(
+                "ipc_relayer_last_submitted_height",
+                "Height of the last bottom-up checkpoint committed by this relayer",
+            )?,
+            last_round_timestamp: IntGauge::new(
+                "ipc_relayer_last_round_timestamp",
+                "Unix timestamp of the end of the last submission round",
+            )?,
+            consecutive_failed_rounds: IntGauge::new(
+                "ipc_relayer_consecutive_failed_rounds",
+                "Number of submission rounds which failed since the last successful one",
+            )?,
+            last_error: Mutex::new(None),
+        })
+    }
+
+    pub fn register(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.submitted.clone()))?;
+        registry.register(Box::new(self.relayed_by_other.clone()))?;
+        registry.register(Box::new(self.failed.clone()))?;
+        registry.register(Box::new(self.gas_used.clone()))?;
+        registry.register(Box::new(self.gas_fee.clone()))?;
+        registry.register(Box::new(self.last_submitted_height.clone()))?;
+        registry.register(Box::new(self.last_round_timestamp.clone()))?;
+        registry.register(Box::new(self.consecutive_failed_rounds.clone()))?;
+        Ok(())
+    }
+
+    /// The number of submission rounds which failed since the last successful one.
+    pub fn consecutive_failed_rounds(&self) -> u64 {
+        self.consecutive_failed_rounds.get() as u64
+    }
+
+    /// The Unix timestamp of the end of the last submission round, if there has been one.
+    pub fn last_round_timestamp(&self) -> Option<u64> {
+        match self.last_round_timestamp.get() {
+            0 => None,
+            t => Some(t as u64),
+        }
+    }
+
+    /// The error the last failed submission round ended with, cleared by a successful round.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    fn record_submission(&self, submission: &CheckpointSubmission, height: ChainEpoch) {
+        self.submitted.inc();
+        let submitter = submission.submitter.to_string();
+        self.gas_used
+            .with_label_values(&[&submitter])
+            .inc_by(submission.gas_used);
+        // Only used for reporting, so the precision of a float is enough.
+        let fee = submission.gas_fee.atto().to_f64().unwrap_or_default() / 1e18;
+        self.gas_fee.with_label_values(&[&submitter]).inc_by(fee);
+        if height > self.last_submitted_height.get() {
+            self.last_submitted_height.set(height);
+        }
+    }
+
+    fn record_round(&self, result: &Result<()>) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.last_round_timestamp.set(now.as_secs() as i64);
+
+        let mut last_error = self.last_error.lock().unwrap();
+        match result {
+            Ok(()) => {
+                self.consecutive_failed_rounds.set(0);
+                *last_error = None;
+            }
+            Err(e) => {
+                self.consecutive_failed_rounds.inc();
+                *last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
 /// Manages the submission of bottom up checkpoint. It checks if the submitter has already
 /// submitted in the `last_checkpoint_height`, if not, it will submit the checkpoint at that height.
 /// Then it will submit at the next submission height for the new checkpoint.
@@ -34,6 +222,10 @@ pub struct BottomUpCheckpointManager<T> {
     /// The number of blocks away from the chain head that is considered final
     finalization_blocks: ChainEpoch,
     submission_semaphore: Arc<Semaphore>,
+    /// Set once a shutdown has been requested, stops new submissions from being started.
+    shutting_down: AtomicBool,
+    metrics: Option<Arc<RelayerMetrics>>,
+    cost_ledger: Option<Arc<CostLedger>>,
 }
 
 impl<T: BottomUpCheckpointRelayer> BottomUpCheckpointManager<T> {
@@ -58,6 +250,9 @@ impl<T: BottomUpCheckpointRelayer> BottomUpCheckpointManager<T> {
             child_handler,
             finalization_blocks: 0,
             submission_semaphore: Arc::new(Semaphore::new(max_parallelism)),
+            shutting_down: AtomicBool::new(false),
+            metrics: None,
+            cost_ledger: None,
         })
     }
 
@@ -65,6 +260,18 @@ impl<T: BottomUpCheckpointRelayer> BottomUpCheckpointManager<T> {
         self.finalization_blocks = finalization_blocks;
         self
     }
+
+    /// Record the outcome of the checkpoint submissions in the given metrics.
+    pub fn with_metrics(mut self, metrics: Arc<RelayerMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record the cost of every checkpoint submission in the given ledger.
+    pub fn with_cost_ledger(mut self, ledger: Arc<CostLedger>) -> Self {
+        self.cost_ledger = Some(ledger);
+        self
+    }
 }
 
 impl BottomUpCheckpointManager<EthSubnetManager> {
@@ -74,8 +281,12 @@ impl BottomUpCheckpointManager<EthSubnetManager> {
         keystore: Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>,
         max_parallelism: usize,
     ) -> Result<Self> {
+        // Checkpoint submissions stuck with too low fees are always resubmitted,
+        // with the policy of the parent subnet if it has one.
+        let resubmit = parent.resubmit_policy().cloned().unwrap_or_default();
         let parent_handler =
-            EthSubnetManager::from_subnet_with_wallet_store(&parent, Some(keystore.clone()))?;
+            EthSubnetManager::from_subnet_with_wallet_store(&parent, Some(keystore.clone()))?
+                .with_resubmit_policy(Some(resubmit));
         let child_handler =
             EthSubnetManager::from_subnet_with_wallet_store(&child, Some(keystore))?;
         Self::new(
@@ -116,19 +327,92 @@ impl<T: BottomUpCheckpointRelayer + Send + Sync + 'static> BottomUpCheckpointMan
     }
 
     /// Run the bottom up checkpoint submission daemon in the foreground
-    pub async fn run(self, submitter: Address, submission_interval: Duration) {
-        log::info!("launching {self} for {submitter}");
+    pub async fn run(self, submitters: Vec<Address>, submission_interval: Duration) {
+        self.run_until(
+            submitters,
+            submission_interval,
+            std::future::pending(),
+            Duration::ZERO,
+        )
+        .await
+    }
+
+    /// Run the bottom up checkpoint submission daemon in the foreground until `shutdown` resolves.
+    ///
+    /// Once shutdown is requested no new checkpoint submissions are started, and the ones already
+    /// in flight are given up to `drain_timeout` to be included in the parent before returning.
+    ///
+    /// Each checkpoint is submitted from the next of the `submitters` in turn. One whose
+    /// submission fails with a nonce conflict or for lack of funds is left out for a while,
+    /// and the checkpoint is submitted again from another.
+    pub async fn run_until<F>(
+        self,
+        submitters: Vec<Address>,
+        submission_interval: Duration,
+        shutdown: F,
+        drain_timeout: Duration,
+    ) where
+        F: Future<Output = ()>,
+    {
+        if submitters.is_empty() {
+            log::error!("cannot launch {self} without submitters");
+            return;
+        }
+        log::info!("launching {self} for {submitters:?}");
+
+        let submitters = Arc::new(SubmitterPool::new(submitters));
+
+        tokio::pin!(shutdown);
 
         loop {
-            if let Err(e) = self.submit_next_epoch(submitter).await {
-                log::error!("cannot submit checkpoint for submitter: {submitter} due to {e}");
+            let round = self.submit_next_epoch(&submitters);
+            tokio::pin!(round);
+
+            tokio::select! {
+                r = &mut round => {
+                    if let Err(e) = &r {
+                        log::error!("cannot submit checkpoint due to {e}");
+                    }
+                    self.record_round(&r);
+                }
+                _ = &mut shutdown => {
+                    log::info!("shutdown requested, draining in-flight checkpoint submissions");
+                    self.shutting_down.store(true, Ordering::SeqCst);
+
+                    match tokio::time::timeout(drain_timeout, round).await {
+                        Ok(r) => {
+                            match &r {
+                                Ok(()) => log::info!("in-flight checkpoint submissions drained"),
+                                Err(e) => log::error!("cannot submit checkpoint due to {e}"),
+                            }
+                            self.record_round(&r);
+                        }
+                        Err(_) => log::warn!("in-flight checkpoint submissions did not finish within {drain_timeout:?}"),
+                    }
+                    break;
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(submission_interval) => {}
+                _ = &mut shutdown => {
+                    log::info!("shutdown requested, no checkpoint submissions in flight");
+                    break;
+                }
             }
-            tokio::time::sleep(submission_interval).await;
+        }
+
+        log::info!("stopped {self}");
+    }
+
+    fn record_round(&self, result: &Result<()>) {
+        if let Some(m) = &self.metrics {
+            m.record_round(result);
         }
     }
 
     /// Checks if the relayer has already submitted at the next submission epoch, if not it submits it.
-    async fn submit_next_epoch(&self, submitter: Address) -> Result<()> {
+    async fn submit_next_epoch(&self, submitters: &Arc<SubmitterPool>) -> Result<()> {
         let last_checkpoint_epoch = self
             .parent_handler
             .last_bottom_up_checkpoint_height(&self.metadata.child.id)
@@ -154,6 +438,11 @@ impl<T: BottomUpCheckpointRelayer + Send + Sync + 'static> BottomUpCheckpointMan
         let mut all_submit_tasks = vec![];
 
         for h in start..=finalized_height {
+            if self.shutting_down.load(Ordering::SeqCst) {
+                log::info!("shutting down, not submitting checkpoints from height {h}");
+                break;
+            }
+
             let events = self.child_handler.quorum_reached_events(h).await?;
             if events.is_empty() {
                 log::debug!("no reached events at height : {h}");
@@ -183,6 +472,8 @@ impl<T: BottomUpCheckpointRelayer + Send + Sync + 'static> BottomUpCheckpointMan
                 // We need to acquire a permit (from a limited permit pool) before submitting a checkpoint.
                 // We may wait here until a permit is available.
                 let parent_handler_clone = Arc::clone(&self.parent_handler);
+                let subnet = self.metadata.child.id.clone();
+                let submitters = submitters.clone();
                 let submission_permit = self
                     .submission_semaphore
                     .clone()
@@ -191,14 +482,19 @@ impl<T: BottomUpCheckpointRelayer + Send + Sync + 'static> BottomUpCheckpointMan
                     .unwrap();
                 all_submit_tasks.push(tokio::task::spawn(async move {
                     let height = event.height;
-                    let result =
-                        Self::submit_checkpoint(parent_handler_clone, submitter, bundle, event)
-                            .await
-                            .inspect_err(|err| {
-                                log::error!("Fail to submit checkpoint at height {height}: {err}");
-                            });
+                    let result = Self::submit_checkpoint_rotating(
+                        parent_handler_clone,
+                        &subnet,
+                        &submitters,
+                        bundle,
+                        event,
+                    )
+                    .await
+                    .inspect_err(|err| {
+                        log::error!("Fail to submit checkpoint at height {height}: {err}");
+                    });
                     drop(submission_permit);
-                    result
+                    (height, result)
                 }));
 
                 count += 1;
@@ -208,37 +504,210 @@ impl<T: BottomUpCheckpointRelayer + Send + Sync + 'static> BottomUpCheckpointMan
 
         log::debug!("Waiting for all submissions to finish");
         // Return error if any of the submit task failed.
-        try_join_all(all_submit_tasks).await?;
+        let statuses = try_join_all(all_submit_tasks).await?;
 
-        Ok(())
+        let mut relayed_by_other = 0;
+        let mut last_included = None;
+        let mut failures = Vec::new();
+        for (height, status) in statuses {
+            match status {
+                Ok(SubmissionStatus::Submitted(submission)) => {
+                    last_included = max(last_included, Some(submission.block_height));
+                    if let Some(m) = &self.metrics {
+                        m.record_submission(&submission, height);
+                    }
+                    if let Some(ledger) = &self.cost_ledger {
+                        // The checkpoint is committed either way, so only the report is affected.
+                        if let Err(e) = ledger.record(&self.metadata.child.id, height, &submission)
+                        {
+                            log::error!(
+                                "failed to record the cost of checkpoint {height} in {}: {e:#}",
+                                ledger.path().display()
+                            );
+                        }
+                    }
+                }
+                Ok(SubmissionStatus::RelayedByOther) => {
+                    relayed_by_other += 1;
+                    if let Some(m) = &self.metrics {
+                        m.relayed_by_other.inc();
+                    }
+                }
+                Err(e) => {
+                    if let Some(m) = &self.metrics {
+                        m.failed.inc();
+                    }
+                    failures.push(e);
+                }
+            }
+        }
+        log::debug!(
+            "round finished, {relayed_by_other} checkpoint(s) relayed by another relayer, last included at parent height {last_included:?}"
+        );
+
+        match failures.pop() {
+            None => Ok(()),
+            Some(e) => Err(anyhow!(
+                "{} checkpoint submission(s) failed, the last one due to: {e:#}",
+                failures.len() + 1
+            )),
+        }
     }
 
+    /// Submits the checkpoint from the next submitter in turn, moving on to the others when
+    /// the failure is down to the submitter account.
+    async fn submit_checkpoint_rotating(
+        parent_handler: Arc<T>,
+        subnet: &SubnetID,
+        submitters: &SubmitterPool,
+        bundle: BottomUpCheckpointBundle,
+        event: QuorumReachedEvent,
+    ) -> Result<SubmissionStatus> {
+        let mut attempts = submitters.len();
+        loop {
+            let submitter = submitters.next();
+            let result = Self::submit_checkpoint(
+                parent_handler.clone(),
+                subnet,
+                submitter,
+                bundle.clone(),
+                event.clone(),
+            )
+            .await;
+
+            match result {
+                Err(e) if is_submitter_error(&e) => {
+                    submitters.back_off(submitter);
+                    attempts -= 1;
+                    if attempts == 0 {
+                        return Err(e);
+                    }
+                    log::warn!(
+                        "submitter {submitter} cannot submit bottom up checkpoint({}), trying another one: {e:#}",
+                        event.height
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Submits the checkpoint unless the parent already has it, which is the case when several
+    /// relayers run for the same subnet. The parent rejects a checkpoint committed before, so
+    /// checking first saves the gas of a reverted transaction.
     async fn submit_checkpoint(
         parent_handler: Arc<T>,
+        subnet: &SubnetID,
         submitter: Address,
         bundle: BottomUpCheckpointBundle,
         event: QuorumReachedEvent,
-    ) -> Result<(), anyhow::Error> {
-        let epoch = parent_handler
+    ) -> Result<SubmissionStatus, anyhow::Error> {
+        if Self::is_committed(&parent_handler, subnet, event.height).await? {
+            log::info!(
+                "bottom up checkpoint({}) already relayed by another relayer, skipping",
+                event.height
+            );
+            return Ok(SubmissionStatus::RelayedByOther);
+        }
+
+        let result = parent_handler
             .submit_checkpoint(
                 &submitter,
                 bundle.checkpoint,
                 bundle.signatures,
                 bundle.signatories,
             )
-            .await
-            .map_err(|e| {
-                anyhow!(
-                    "cannot submit bottom up checkpoint at height {} due to: {e}",
+            .await;
+
+        let submission = match result {
+            Ok(submission) => submission,
+            Err(e) => {
+                // Another relayer got in first while ours was pending, and it either reverted
+                // or was given up on instead of raising its fees further; nothing is lost.
+                if Self::is_committed(&parent_handler, subnet, event.height)
+                    .await
+                    .unwrap_or_default()
+                {
+                    log::info!(
+                        "bottom up checkpoint({}) relayed by another relayer during submission: {e}",
+                        event.height
+                    );
+                    return Ok(SubmissionStatus::RelayedByOther);
+                }
+                return Err(e.context(format!(
+                    "cannot submit bottom up checkpoint at height {}",
                     event.height
-                )
-            })?;
+                )));
+            }
+        };
 
         log::info!(
             "submitted bottom up checkpoint({}) in parent at height {}",
             event.height,
-            epoch
+            submission.block_height
         );
-        Ok(())
+        Ok(SubmissionStatus::Submitted(submission))
+    }
+
+    /// Whether the parent has committed the checkpoint at the given height, or a later one.
+    async fn is_committed(
+        parent_handler: &T,
+        subnet: &SubnetID,
+        height: ChainEpoch,
+    ) -> Result<bool> {
+        let last_checkpoint_epoch = parent_handler
+            .last_bottom_up_checkpoint_height(subnet)
+            .await
+            .map_err(|e| {
+                anyhow!("cannot obtain the last bottom up checkpoint height due to: {e:}")
+            })?;
+        Ok(last_checkpoint_epoch >= height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_submitter_error, SubmitterPool};
+    use crate::manager::SubmitterUnavailable;
+    use anyhow::anyhow;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+
+    #[test]
+    fn test_submitter_rotation() {
+        let (a, b, c) = (Address::new_id(1), Address::new_id(2), Address::new_id(3));
+        let pool = SubmitterPool::new(vec![a, b, c]);
+        assert_eq!(pool.next(), a);
+        assert_eq!(pool.next(), b);
+        assert_eq!(pool.next(), c);
+        assert_eq!(pool.next(), a);
+
+        // Backed off submitters are skipped while there are others.
+        pool.back_off(b);
+        assert_eq!(pool.next(), c);
+        assert_eq!(pool.next(), c);
+        assert_eq!(pool.next(), a);
+
+        // Once all of them are, the one backed off first is used.
+        pool.back_off(a);
+        pool.back_off(c);
+        assert_eq!(pool.next(), b);
+    }
+
+    #[test]
+    fn test_submitter_errors() {
+        let e = anyhow::Error::from(SubmitterUnavailable::InsufficientFunds {
+            submitter: Address::new_id(1),
+            balance: TokenAmount::from_atto(1),
+            required: TokenAmount::from_atto(2),
+        });
+        assert!(is_submitter_error(&e.context("cannot submit")));
+        // the wording of the error doesn't matter, only its type
+        assert!(!is_submitter_error(&anyhow!(
+            "insufficient funds for gas * price + value"
+        )));
+        assert!(!is_submitter_error(&anyhow!(
+            "execution reverted: InvalidCheckpointEpoch"
+        )));
     }
 }
@@ -0,0 +1,248 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Ledger of what the checkpoint submissions of a relayer cost in the parent.
+//!
+//! Each submission is appended as a single JSON line, so the costs survive restarts
+//! and can be aggregated over any period afterwards.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use fvm_shared::bigint::BigInt;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_api::checkpoint::CheckpointSubmission;
+use ipc_api::subnet_id::SubnetID;
+use serde::{Deserialize, Serialize};
+
+/// The cost of committing one bottom-up checkpoint in the parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionCost {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub subnet: String,
+    pub checkpoint_height: ChainEpoch,
+    /// The parent block the submission was included in.
+    pub parent_height: ChainEpoch,
+    pub submitter: String,
+    pub tx_hash: String,
+    pub gas_used: u64,
+    /// The fee paid for the gas used, in atto.
+    pub gas_fee: String,
+}
+
+impl SubmissionCost {
+    pub fn gas_fee(&self) -> Result<TokenAmount> {
+        let atto = BigInt::from_str(&self.gas_fee)
+            .map_err(|e| anyhow!("invalid gas fee {}: {e}", self.gas_fee))?;
+        Ok(TokenAmount::from_atto(atto))
+    }
+}
+
+/// The append-only file the costs of the checkpoint submissions are recorded in.
+pub struct CostLedger {
+    path: PathBuf,
+    /// Keeps the lines of submissions finishing at the same time from interleaving.
+    lock: Mutex<()>,
+}
+
+impl CostLedger {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record the cost of a checkpoint of the subnet committed in the parent.
+    pub fn record(
+        &self,
+        subnet: &SubnetID,
+        checkpoint_height: ChainEpoch,
+        submission: &CheckpointSubmission,
+    ) -> Result<()> {
+        let cost = SubmissionCost {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            subnet: subnet.to_string(),
+            checkpoint_height,
+            parent_height: submission.block_height,
+            submitter: submission.submitter.to_string(),
+            tx_hash: format!("0x{}", hex::encode(&submission.tx_hash)),
+            gas_used: submission.gas_used,
+            gas_fee: submission.gas_fee.atto().to_string(),
+        };
+        self.append(&cost)
+    }
+
+    fn append(&self, cost: &SubmissionCost) -> Result<()> {
+        let line = serde_json::to_string(cost)?;
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+
+    /// Read all the costs recorded so far; a missing ledger has none.
+    pub fn read(&self) -> Result<Vec<SubmissionCost>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !matches!(line, Ok(l) if l.trim().is_empty()))
+            .map(|(i, line)| {
+                let line = line?;
+                serde_json::from_str(&line)
+                    .with_context(|| format!("invalid cost ledger entry on line {}", i + 1))
+            })
+            .collect()
+    }
+}
+
+/// The costs of a number of checkpoint submissions added up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CostSummary {
+    pub submissions: u64,
+    pub gas_used: u64,
+    /// The fees paid, in whole tokens.
+    #[serde(serialize_with = "serialize_whole_tokens")]
+    pub gas_fee: TokenAmount,
+}
+
+impl CostSummary {
+    fn add(&mut self, cost: &SubmissionCost) -> Result<()> {
+        self.submissions += 1;
+        self.gas_used += cost.gas_used;
+        self.gas_fee += cost.gas_fee()?;
+        Ok(())
+    }
+}
+
+fn serialize_whole_tokens<S: serde::Serializer>(
+    amount: &TokenAmount,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&amount.to_string())
+}
+
+/// The costs of the checkpoint submissions over a span of time, in total, per submitter,
+/// and per period, keyed by the Unix timestamp the period starts at.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CostReport {
+    pub total: CostSummary,
+    pub by_submitter: BTreeMap<String, CostSummary>,
+    pub by_period: BTreeMap<u64, CostSummary>,
+}
+
+/// Add up the costs recorded for a subnet from a point in time, if given, in periods of
+/// `period_secs` seconds.
+pub fn cost_report(
+    costs: &[SubmissionCost],
+    subnet: Option<&SubnetID>,
+    since: Option<u64>,
+    period_secs: u64,
+) -> Result<CostReport> {
+    if period_secs == 0 {
+        return Err(anyhow!("the reporting period cannot be empty"));
+    }
+    let subnet = subnet.map(|s| s.to_string());
+
+    let mut report = CostReport::default();
+    for cost in costs {
+        if subnet.as_ref().is_some_and(|s| *s != cost.subnet)
+            || since.is_some_and(|t| cost.timestamp < t)
+        {
+            continue;
+        }
+        report.total.add(cost)?;
+        report
+            .by_submitter
+            .entry(cost.submitter.clone())
+            .or_default()
+            .add(cost)?;
+        report
+            .by_period
+            .entry(cost.timestamp - cost.timestamp % period_secs)
+            .or_default()
+            .add(cost)?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use ipc_api::checkpoint::CheckpointSubmission;
+    use ipc_api::subnet_id::SubnetID;
+
+    use super::{cost_report, CostLedger};
+
+    #[test]
+    fn test_cost_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = CostLedger::new(dir.path().join("costs.jsonl"));
+        assert!(ledger.read().unwrap().is_empty());
+
+        let subnet = SubnetID::from_str("/r314159/f0102").unwrap();
+        let submitters = [Address::new_id(100), Address::new_id(101)];
+        for (i, submitter) in [0, 1, 0].into_iter().enumerate() {
+            let submission = CheckpointSubmission {
+                tx_hash: vec![i as u8; 32],
+                block_height: 10 + i as i64,
+                submitter: submitters[submitter],
+                gas_used: 1000,
+                gas_fee: TokenAmount::from_atto(500),
+            };
+            ledger.record(&subnet, 60 * i as i64, &submission).unwrap();
+        }
+
+        let mut costs = ledger.read().unwrap();
+        assert_eq!(costs.len(), 3);
+        costs[0].timestamp = 0;
+        costs[1].timestamp = 100;
+        costs[2].timestamp = 7200;
+
+        let report = cost_report(&costs, Some(&subnet), None, 3600).unwrap();
+        assert_eq!(report.total.submissions, 3);
+        assert_eq!(report.total.gas_used, 3000);
+        assert_eq!(report.total.gas_fee, TokenAmount::from_atto(1500));
+        assert_eq!(
+            report.by_submitter[&submitters[0].to_string()].submissions,
+            2
+        );
+        assert_eq!(
+            report.by_period.keys().copied().collect::<Vec<_>>(),
+            [0, 7200]
+        );
+        assert_eq!(report.by_period[&0].submissions, 2);
+
+        let report = cost_report(&costs, Some(&subnet), Some(100), 3600).unwrap();
+        assert_eq!(report.total.submissions, 2);
+
+        let other = SubnetID::from_str("/r314159").unwrap();
+        let report = cost_report(&costs, Some(&other), None, 3600).unwrap();
+        assert_eq!(report.total, Default::default());
+    }
+}
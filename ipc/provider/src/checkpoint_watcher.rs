@@ -0,0 +1,268 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Watches the parent for the bottom-up checkpoints of a subnet, and alerts when the parent
+//! falls behind the checkpoints the subnet should have produced.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use fvm_shared::clock::ChainEpoch;
+use ipc_api::subnet_id::SubnetID;
+use prometheus::{IntCounter, IntGauge, Registry};
+use serde::Serialize;
+
+use crate::config::Subnet;
+use crate::manager::{BottomUpCheckpointRelayer, EthSubnetManager};
+
+/// How long to wait for the webhook to accept a connection.
+const WEBHOOK_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait for the webhook to respond, so a hanging endpoint doesn't stall the watcher.
+const WEBHOOK_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Metrics on the checkpoints of a subnet committed in its parent.
+pub struct WatcherMetrics {
+    last_committed_height: IntGauge,
+    child_height: IntGauge,
+    missed_checkpoints: IntGauge,
+    alerts: IntCounter,
+}
+
+impl WatcherMetrics {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            last_committed_height: IntGauge::new(
+                "ipc_watcher_last_committed_height",
+                "Height of the last bottom-up checkpoint committed in the parent",
+            )?,
+            child_height: IntGauge::new(
+                "ipc_watcher_child_height",
+                "Height of the chain head of the subnet",
+            )?,
+            missed_checkpoints: IntGauge::new(
+                "ipc_watcher_missed_checkpoints",
+                "Number of checkpoints the subnet should have produced which the parent has not committed",
+            )?,
+            alerts: IntCounter::new(
+                "ipc_watcher_alerts_total",
+                "Number of alerts raised for checkpoints missing in the parent",
+            )?,
+        })
+    }
+
+    pub fn register(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.last_committed_height.clone()))?;
+        registry.register(Box::new(self.child_height.clone()))?;
+        registry.register(Box::new(self.missed_checkpoints.clone()))?;
+        registry.register(Box::new(self.alerts.clone()))?;
+        Ok(())
+    }
+}
+
+/// What the watcher found in one round, posted to the webhook when an alert is raised or resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckpointLag {
+    pub subnet: String,
+    pub checkpoint_period: ChainEpoch,
+    pub last_committed_height: ChainEpoch,
+    pub child_height: ChainEpoch,
+    pub missed_checkpoints: i64,
+    /// Whether the validators of the subnet reached a quorum on the first missing checkpoint,
+    /// i.e. whether it is waiting for a relayer rather than for signatures; unknown if the
+    /// subnet could not be asked.
+    pub first_missing_quorum_reached: Option<bool>,
+    /// Whether the lag is over the alert threshold.
+    pub alerting: bool,
+}
+
+/// The number of checkpoints due in the subnet up to `child_height` after the last one
+/// committed in the parent.
+pub fn missed_checkpoints(
+    last_committed_height: ChainEpoch,
+    child_height: ChainEpoch,
+    period: ChainEpoch,
+) -> i64 {
+    let last_due = child_height - child_height % period;
+    ((last_due - last_committed_height) / period).max(0)
+}
+
+/// Polls the last checkpoint height committed in the parent and the chain head of the subnet,
+/// and raises an alert, in the log, the metrics and the optional webhook, when more than
+/// `max_missed` checkpoints are due but not committed.
+pub struct CheckpointWatcher<T> {
+    subnet: SubnetID,
+    period: ChainEpoch,
+    parent_handler: T,
+    child_handler: T,
+    max_missed: i64,
+    metrics: Option<Arc<WatcherMetrics>>,
+    webhook: Option<reqwest::Url>,
+    client: reqwest::Client,
+}
+
+impl<T: BottomUpCheckpointRelayer> CheckpointWatcher<T> {
+    pub async fn new(
+        subnet: SubnetID,
+        parent_handler: T,
+        child_handler: T,
+        max_missed: i64,
+    ) -> Result<Self> {
+        let period = parent_handler
+            .checkpoint_period(&subnet)
+            .await
+            .map_err(|e| anyhow!("cannot get bottom up checkpoint period: {e}"))?;
+        if period <= 0 {
+            return Err(anyhow!("invalid checkpoint period: {period}"));
+        }
+        let client = reqwest::Client::builder()
+            .connect_timeout(WEBHOOK_CONNECT_TIMEOUT)
+            .timeout(WEBHOOK_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| anyhow!("cannot build webhook client: {e}"))?;
+        Ok(Self {
+            subnet,
+            period,
+            parent_handler,
+            child_handler,
+            max_missed,
+            metrics: None,
+            webhook: None,
+            client,
+        })
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<WatcherMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Post the lag as JSON to the given URL whenever an alert is raised or resolved.
+    pub fn with_webhook(mut self, url: reqwest::Url) -> Self {
+        self.webhook = Some(url);
+        self
+    }
+
+    /// Watch the parent in the foreground, checking every `interval`.
+    pub async fn run(self, interval: Duration) {
+        log::info!(
+            "watching the parent for the checkpoints of {}, every {} blocks",
+            self.subnet,
+            self.period
+        );
+
+        let mut alerting = false;
+        loop {
+            match self.check().await {
+                Ok(lag) => {
+                    if lag.alerting {
+                        log::warn!(
+                            "parent has not committed {} checkpoint(s) of {}: last committed at {}, subnet at {}, quorum reached on the first missing one: {:?}",
+                            lag.missed_checkpoints,
+                            lag.subnet,
+                            lag.last_committed_height,
+                            lag.child_height,
+                            lag.first_missing_quorum_reached
+                        );
+                    }
+                    if lag.alerting != alerting {
+                        if lag.alerting {
+                            if let Some(m) = &self.metrics {
+                                m.alerts.inc();
+                            }
+                        } else {
+                            log::info!(
+                                "parent caught up with the checkpoints of {}, last committed at {}",
+                                lag.subnet,
+                                lag.last_committed_height
+                            );
+                        }
+                        self.notify(&lag).await;
+                        alerting = lag.alerting;
+                    }
+                }
+                Err(e) => log::error!("cannot check the checkpoints of {}: {e}", self.subnet),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Compare the checkpoints committed in the parent with the ones due in the subnet.
+    pub async fn check(&self) -> Result<CheckpointLag> {
+        let last_committed_height = self
+            .parent_handler
+            .last_bottom_up_checkpoint_height(&self.subnet)
+            .await?;
+        let child_height = self.child_handler.current_epoch().await?;
+        let missed = missed_checkpoints(last_committed_height, child_height, self.period);
+
+        let first_missing_quorum_reached = if missed > 0 {
+            match self
+                .child_handler
+                .checkpoint_quorum_at(last_committed_height + self.period)
+                .await
+            {
+                Ok(quorum) => Some(quorum.reached),
+                Err(e) => {
+                    log::debug!("cannot get the quorum of the first missing checkpoint: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(m) = &self.metrics {
+            m.last_committed_height.set(last_committed_height);
+            m.child_height.set(child_height);
+            m.missed_checkpoints.set(missed);
+        }
+
+        Ok(CheckpointLag {
+            subnet: self.subnet.to_string(),
+            checkpoint_period: self.period,
+            last_committed_height,
+            child_height,
+            missed_checkpoints: missed,
+            first_missing_quorum_reached,
+            alerting: missed > self.max_missed,
+        })
+    }
+
+    async fn notify(&self, lag: &CheckpointLag) {
+        let Some(url) = &self.webhook else {
+            return;
+        };
+        let res = self
+            .client
+            .post(url.clone())
+            .json(lag)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        if let Err(e) = res {
+            log::error!("cannot notify the checkpoint watcher webhook: {e}");
+        }
+    }
+}
+
+impl CheckpointWatcher<EthSubnetManager> {
+    pub async fn new_evm_watcher(parent: &Subnet, child: &Subnet, max_missed: i64) -> Result<Self> {
+        let parent_handler = EthSubnetManager::from_subnet_with_wallet_store(parent, None)?;
+        let child_handler = EthSubnetManager::from_subnet_with_wallet_store(child, None)?;
+        Self::new(child.id.clone(), parent_handler, child_handler, max_missed).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::missed_checkpoints;
+
+    #[test]
+    fn test_missed_checkpoints() {
+        assert_eq!(missed_checkpoints(100, 100, 10), 0);
+        assert_eq!(missed_checkpoints(100, 119, 10), 1);
+        assert_eq!(missed_checkpoints(100, 120, 10), 2);
+        // The parent can be ahead of a lagging subnet endpoint.
+        assert_eq!(missed_checkpoints(120, 110, 10), 0);
+    }
+}
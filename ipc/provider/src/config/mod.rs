@@ -6,6 +6,7 @@
 //! [`Config`] struct.
 
 pub mod deserialize;
+pub mod secret;
 pub mod subnet;
 
 pub mod serialize;
@@ -28,6 +29,11 @@ pub const JSON_RPC_VERSION: &str = "2.0";
 /// DefaulDEFAULT_CHAIN_IDSUBNET_e
 pub const DEFAULT_CONFIG_TEMPLATE: &str = r#"
 keystore_path = "~/.ipc"
+# Optionally load EVM private keys from a secret manager instead of the keystore,
+# e.g. from a file written by the Vault Agent or an environment variable.
+# wallet_keys = ["file:/vault/secrets/validator-key", "env:IPC_RELAYER_KEY"]
+# Optionally serve event queries from a local index kept up to date by `ipc-cli index`.
+# index_path = "~/.ipc/index"
 
 # Filecoin Calibration
 [[subnets]]
@@ -42,12 +48,44 @@ registry_addr = "0x0b4e239FF21b40120cDa817fba77bD1B366c1bcD"
 # Subnet template - uncomment and adjust before using
 # [[subnets]]
 # id = "/r314159/<SUBNET_ID>"
+# Optionally use a custom subnet manager registered with `IpcProvider::register_manager`.
+# manager = "<MANAGER_NAME>"
+# Optionally connect without a signer, so the subnet can only be queried.
+# read_only = true
 
 # [subnets.config]
 # network_type = "fevm"
 # provider_http = "https://<RPC_ADDR>/"
+# provider_http_fallbacks = ["https://<BACKUP_RPC_ADDR>/"]
+# Optionally subscribe to new blocks and events over a WebSocket endpoint.
+# provider_ws = "wss://<RPC_ADDR>/"
+# Optionally send the queries reaching beyond what provider_http keeps to an archival node.
+# provider_archive = "https://<ARCHIVE_RPC_ADDR>/"
+# The auth token can also be read from a secret with "env:<NAME>" or "file:<PATH>".
+# auth_token = "file:/vault/secrets/rpc-token"
 # gateway_addr = "0x77aa40b105843728088c0132e43fc44348881da8"
 # registry_addr = "0x74539671a1d2f1c8f200826baba665179f53a1b7"
+
+# Optionally retry requests which fail to reach the provider, and stop sending
+# requests for a while if it keeps failing.
+# [subnets.config.provider_retry]
+# max_retries = 3
+# initial_backoff = 250
+# max_backoff = 10000
+# circuit_breaker_threshold = 5
+# circuit_breaker_cooldown = 30
+
+# Optionally limit the fees estimated from recent blocks, in atto per unit of gas.
+# [subnets.config.fee_caps]
+# max_fee_per_gas = 100000000000
+# max_priority_fee_per_gas = 2000000000
+
+# Optionally resubmit transactions with higher fees if they aren't included in time.
+# [subnets.config.resubmit]
+# resubmit_after = 60
+# fee_bump_percent = 20
+# max_fee_per_gas = 200000000000
+# max_resubmissions = 5
 "#;
 
 /// The top-level struct representing the config. Calls to [`Config::from_file`] deserialize into
@@ -56,6 +94,13 @@ registry_addr = "0x0b4e239FF21b40120cDa817fba77bD1B366c1bcD"
 pub struct Config {
     /// Directory of the keystore that wants to be made available by the provider.
     pub keystore_path: Option<String>,
+    /// Hex encoded EVM private keys, usually references to secrets, which are added to
+    /// the keystore in memory only, without being written to disk.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wallet_keys: Vec<String>,
+    /// Directory of the local index of contract events, see `ipc-cli index`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_path: Option<String>,
     #[serde(deserialize_with = "deserialize_subnets_from_vec", default)]
     #[serde(serialize_with = "serialize_subnets_to_str")]
     pub subnets: HashMap<SubnetID, Subnet>,
@@ -66,6 +111,8 @@ impl Config {
     pub fn new() -> Self {
         Config {
             keystore_path: None,
+            wallet_keys: Default::default(),
+            index_path: None,
             subnets: Default::default(),
         }
     }
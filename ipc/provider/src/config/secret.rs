@@ -0,0 +1,55 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Secrets referenced from the config instead of being written in it.
+//!
+//! Secret managers such as HashiCorp Vault (through the Vault Agent) or AWS Secrets Manager
+//! (through the Secrets Store CSI driver or an ECS task definition) hand secrets to a process
+//! by writing them to a file or an environment variable, so a value in the config can be:
+//! * `env:<NAME>` to read it from the `NAME` environment variable;
+//! * `file:<PATH>` to read it from a file, ignoring trailing whitespace;
+//! * anything else is taken as the value itself.
+//!
+//! References are resolved every time the value is used, so a rotated secret is picked up
+//! by the next connection without restarting.
+
+use std::fs;
+
+use anyhow::{anyhow, Context};
+
+const ENV_PREFIX: &str = "env:";
+const FILE_PREFIX: &str = "file:";
+
+/// Resolve a value from the config which can be a reference to a secret.
+pub fn resolve_secret(value: &str) -> anyhow::Result<String> {
+    if let Some(name) = value.strip_prefix(ENV_PREFIX) {
+        std::env::var(name).map_err(|e| anyhow!("cannot read secret from env var {name}: {e}"))
+    } else if let Some(path) = value.strip_prefix(FILE_PREFIX) {
+        let path = crate::expand_tilde(path);
+        let secret = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read secret from {}", path.to_string_lossy()))?;
+        Ok(secret.trim_end().to_string())
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::resolve_secret;
+
+    #[test]
+    fn test_resolve_secret() {
+        assert_eq!(resolve_secret("plain-token").unwrap(), "plain-token");
+
+        std::env::set_var("IPC_TEST_SECRET", "from-env");
+        assert_eq!(resolve_secret("env:IPC_TEST_SECRET").unwrap(), "from-env");
+        assert!(resolve_secret("env:IPC_TEST_SECRET_MISSING").is_err());
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "from-file").unwrap();
+        let reference = format!("file:{}", file.path().to_string_lossy());
+        assert_eq!(resolve_secret(&reference).unwrap(), "from-file");
+    }
+}
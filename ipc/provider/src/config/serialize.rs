@@ -121,6 +121,8 @@ mod tests {
     fn test_serialization() {
         let mut config = Config {
             keystore_path: Some(String::from("~/.ipc")),
+            wallet_keys: Default::default(),
+            index_path: None,
             subnets: Default::default(),
         };
 
@@ -130,10 +132,20 @@ mod tests {
             config: SubnetConfig::Fevm(EVMSubnet {
                 gateway_addr: Address::from(eth_addr1),
                 provider_http: "http://127.0.0.1:3030/rpc/v1".parse().unwrap(),
+                provider_http_fallbacks: Vec::new(),
+                provider_ws: None,
+                provider_archive: None,
                 provider_timeout: None,
+                provider_max_in_flight: None,
+                provider_queue_timeout: None,
+                provider_retry: None,
+                fee_caps: None,
+                resubmit: None,
                 auth_token: None,
                 registry_addr: Address::from(eth_addr1),
             }),
+            manager: None,
+            read_only: false,
         };
         config.add_subnet(subnet2);
         assert!(toml::to_string(&config).is_ok());
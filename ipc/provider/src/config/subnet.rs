@@ -1,3 +1,4 @@
+use std::num::NonZeroUsize;
 use std::time::Duration;
 
 // Copyright 2022-2024 Protocol Labs
@@ -5,12 +6,13 @@ use std::time::Duration;
 use fvm_shared::address::Address;
 use ipc_api::subnet_id::SubnetID;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DurationSeconds};
+use serde_with::{serde_as, DurationMilliseconds, DurationSeconds};
 use url::Url;
 
 use crate::config::deserialize::{
     deserialize_address_from_str, deserialize_eth_address_from_str, deserialize_subnet_id,
 };
+use crate::config::secret::resolve_secret;
 use crate::config::serialize::{
     serialize_address_to_str, serialize_eth_address_to_str, serialize_subnet_id_to_str,
 };
@@ -22,6 +24,14 @@ pub struct Subnet {
     #[serde(serialize_with = "serialize_subnet_id_to_str")]
     pub id: SubnetID,
     pub config: SubnetConfig,
+    /// Name of a custom subnet manager registered with the provider, to use instead
+    /// of the built-in one for the network type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manager: Option<String>,
+    /// Connect to the subnet without a signer, so it can be queried but no transactions
+    /// are sent to it; for deployments which shouldn't have any private keys.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
@@ -50,23 +60,99 @@ impl Subnet {
         }
     }
 
+    /// The auth token, read from the secret it references if it isn't given directly.
+    pub fn resolved_auth_token(&self) -> anyhow::Result<Option<String>> {
+        self.auth_token()
+            .map(|token| resolve_secret(&token))
+            .transpose()
+    }
+
     pub fn rpc_http(&self) -> &Url {
         match &self.config {
             SubnetConfig::Fevm(s) => &s.provider_http,
         }
     }
 
+    /// Endpoints to fail over to when [`Self::rpc_http`] is unreachable, in order of preference.
+    pub fn rpc_http_fallbacks(&self) -> &[Url] {
+        match &self.config {
+            SubnetConfig::Fevm(s) => &s.provider_http_fallbacks,
+        }
+    }
+
+    /// The WebSocket endpoint to subscribe to new blocks and events, if any.
+    pub fn rpc_ws(&self) -> Option<&Url> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.provider_ws.as_ref(),
+        }
+    }
+
+    /// The archival endpoint to send the queries to which the other endpoints pruned, if any.
+    pub fn rpc_archive(&self) -> Option<&Url> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.provider_archive.as_ref(),
+        }
+    }
+
     pub fn rpc_timeout(&self) -> Option<Duration> {
         match &self.config {
             SubnetConfig::Fevm(s) => s.provider_timeout,
         }
     }
 
+    pub fn rpc_max_in_flight(&self) -> Option<NonZeroUsize> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.provider_max_in_flight,
+        }
+    }
+
+    pub fn rpc_queue_timeout(&self) -> Option<Duration> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.provider_queue_timeout,
+        }
+    }
+
+    pub fn rpc_retry(&self) -> Option<&ProviderRetry> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.provider_retry.as_ref(),
+        }
+    }
+
+    pub fn fee_caps(&self) -> Option<&FeeCaps> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.fee_caps.as_ref(),
+        }
+    }
+
+    pub fn resubmit_policy(&self) -> Option<&ResubmitPolicy> {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.resubmit.as_ref(),
+        }
+    }
+
     pub fn gateway_addr(&self) -> Address {
         match &self.config {
             SubnetConfig::Fevm(s) => s.gateway_addr,
         }
     }
+
+    pub fn registry_addr(&self) -> Address {
+        match &self.config {
+            SubnetConfig::Fevm(s) => s.registry_addr,
+        }
+    }
+
+    pub fn set_gateway_addr(&mut self, gateway_addr: Address) {
+        match &mut self.config {
+            SubnetConfig::Fevm(s) => s.gateway_addr = gateway_addr,
+        }
+    }
+
+    pub fn set_registry_addr(&mut self, registry_addr: Address) {
+        match &mut self.config {
+            SubnetConfig::Fevm(s) => s.registry_addr = registry_addr,
+        }
+    }
 }
 
 /// The FVM subnet config parameters
@@ -84,8 +170,37 @@ pub struct FVMSubnet {
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct EVMSubnet {
     pub provider_http: Url,
+    /// Further endpoints of the same network, used when `provider_http` cannot be reached.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provider_http_fallbacks: Vec<Url>,
+    /// WebSocket endpoint of the same network, used to subscribe to new blocks and events
+    /// instead of polling `provider_http`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_ws: Option<Url>,
+    /// Archival endpoint of the same network, for the queries reaching further back than
+    /// the state and logs `provider_http` keeps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_archive: Option<Url>,
     #[serde_as(as = "Option<DurationSeconds<u64>>")]
     pub provider_timeout: Option<Duration>,
+    /// Maximum number of concurrent requests sent to the provider; unlimited if not set.
+    /// Zero is rejected, as it would block every request.
+    pub provider_max_in_flight: Option<NonZeroUsize>,
+    /// How long a request can wait for a free slot when `provider_max_in_flight` is reached.
+    #[serde_as(as = "Option<DurationSeconds<u64>>")]
+    pub provider_queue_timeout: Option<Duration>,
+    /// Retry and circuit breaker settings for requests which fail to reach the provider;
+    /// requests fail on the first error if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_retry: Option<ProviderRetry>,
+    /// Upper limits on the estimated fees of the transactions sent to the subnet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_caps: Option<FeeCaps>,
+    /// Resubmit transactions with higher fees if they aren't included in time;
+    /// they are sent only once if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resubmit: Option<ResubmitPolicy>,
+    /// Bearer token for the provider; can reference a secret, see [crate::config::secret].
     pub auth_token: Option<String>,
 
     #[serde(deserialize_with = "deserialize_eth_address_from_str")]
@@ -96,3 +211,106 @@ pub struct EVMSubnet {
     #[serde(serialize_with = "serialize_eth_address_to_str")]
     pub gateway_addr: Address,
 }
+
+/// How to deal with requests failing to reach the provider.
+#[serde_as]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProviderRetry {
+    /// Number of times a request is retried after the first attempt.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled for each further one, with jitter.
+    #[serde_as(as = "DurationMilliseconds<u64>")]
+    #[serde(default = "default_initial_backoff")]
+    pub initial_backoff: Duration,
+    /// Upper limit on the delay between retries in milliseconds.
+    #[serde_as(as = "DurationMilliseconds<u64>")]
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: Duration,
+    /// Number of consecutive failed requests after which further requests fail immediately,
+    /// without trying to reach the provider, until the cooldown is over; disabled if not set.
+    pub circuit_breaker_threshold: Option<u32>,
+    /// Seconds to wait before letting requests through again after the circuit breaker opened.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[serde(default = "default_circuit_breaker_cooldown")]
+    pub circuit_breaker_cooldown: Duration,
+}
+
+/// Upper limits on the EIP-1559 fees estimated from recent blocks, in atto per unit of gas,
+/// so that a fee spike doesn't make transactions arbitrarily expensive.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeeCaps {
+    /// Maximum total fee per gas, base fee included.
+    pub max_fee_per_gas: Option<u64>,
+    /// Maximum priority fee (tip) per gas.
+    pub max_priority_fee_per_gas: Option<u64>,
+}
+
+impl Default for ProviderRetry {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            initial_backoff: default_initial_backoff(),
+            max_backoff: default_max_backoff(),
+            circuit_breaker_threshold: None,
+            circuit_breaker_cooldown: default_circuit_breaker_cooldown(),
+        }
+    }
+}
+
+/// When and how to resubmit transactions which are stuck because their fees are too low.
+#[serde_as]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ResubmitPolicy {
+    /// Seconds to wait for a transaction to be included before resubmitting it.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[serde(default = "default_resubmit_after")]
+    pub resubmit_after: Duration,
+    /// Percentage by which the fees are raised on each resubmission; at least 10.
+    #[serde(default = "default_fee_bump_percent")]
+    pub fee_bump_percent: u64,
+    /// The fees are never raised above this max fee per gas, in atto.
+    pub max_fee_per_gas: Option<u64>,
+    /// Maximum number of times a transaction is resubmitted.
+    #[serde(default = "default_max_resubmissions")]
+    pub max_resubmissions: u32,
+}
+
+impl Default for ResubmitPolicy {
+    fn default() -> Self {
+        Self {
+            resubmit_after: default_resubmit_after(),
+            fee_bump_percent: default_fee_bump_percent(),
+            max_fee_per_gas: None,
+            max_resubmissions: default_max_resubmissions(),
+        }
+    }
+}
+
+fn default_resubmit_after() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_fee_bump_percent() -> u64 {
+    20
+}
+
+fn default_max_resubmissions() -> u32 {
+    5
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_backoff() -> Duration {
+    Duration::from_millis(250)
+}
+
+fn default_max_backoff() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_circuit_breaker_cooldown() -> Duration {
+    Duration::from_secs(30)
+}
@@ -1,5 +1,6 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: MIT
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 
 use fvm_shared::address::Address;
@@ -8,6 +9,7 @@ use ipc_api::subnet_id::SubnetID;
 use ipc_types::EthAddress;
 use url::Url;
 
+use crate::config::subnet::ProviderRetry;
 use crate::config::Config;
 
 // Arguments for the config's fields
@@ -40,6 +42,92 @@ fn check_subnets_config() {
     );
     assert_eq!(*child.rpc_http(), Url::from_str(PROVIDER_HTTP).unwrap(),);
     assert_eq!(child.auth_token().as_ref().unwrap(), CHILD_AUTH_TOKEN);
+    assert!(child.rpc_http_fallbacks().is_empty());
+    assert_eq!(child.rpc_max_in_flight(), None);
+    assert_eq!(child.rpc_queue_timeout(), None);
+}
+
+#[test]
+fn check_update_subnet_addresses() {
+    let mut config = read_config();
+
+    let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+    let child = config.subnets.get_mut(&child_id).unwrap();
+    let new_addr =
+        Address::from(EthAddress::from_str("0x77aa40b105843728088c0132e43fc44348881da8").unwrap());
+
+    assert_ne!(child.gateway_addr(), new_addr);
+    child.set_gateway_addr(new_addr);
+    child.set_registry_addr(new_addr);
+    assert_eq!(child.gateway_addr(), new_addr);
+    assert_eq!(child.registry_addr(), new_addr);
+}
+
+#[test]
+fn check_subnet_fallbacks_config() {
+    let config_str = config_str().replace(
+        "provider_http =",
+        "provider_http_fallbacks = [\"http://127.0.0.1:3031/rpc/v1\"]\nprovider_http =",
+    );
+    let config = Config::from_toml_str(&config_str).unwrap();
+
+    let child = &config.subnets[&SubnetID::from_str(CHILD_ID).unwrap()];
+    assert_eq!(
+        child.rpc_http_fallbacks(),
+        &[Url::from_str("http://127.0.0.1:3031/rpc/v1").unwrap()]
+    );
+}
+
+#[test]
+fn check_subnet_max_in_flight_config() {
+    let config_str = config_str().replace(
+        "provider_http =",
+        "provider_max_in_flight = 8\nprovider_http =",
+    );
+    let config = Config::from_toml_str(&config_str).unwrap();
+
+    let child = &config.subnets[&SubnetID::from_str(CHILD_ID).unwrap()];
+    assert_eq!(child.rpc_max_in_flight(), NonZeroUsize::new(8));
+
+    // No request could ever be sent.
+    let config_str = config_str.replace("provider_max_in_flight = 8", "provider_max_in_flight = 0");
+    assert!(Config::from_toml_str(&config_str).is_err());
+}
+
+#[test]
+fn check_subnet_retry_config() {
+    let config_str = formatdoc!(
+        r#"
+        {}
+        [subnets.config.provider_retry]
+        max_retries = 5
+        circuit_breaker_threshold = 10
+        "#,
+        config_str()
+    );
+    let config = Config::from_toml_str(&config_str).unwrap();
+
+    let child = &config.subnets[&SubnetID::from_str(CHILD_ID).unwrap()];
+    let retry = child.rpc_retry().unwrap();
+    assert_eq!(retry.max_retries, 5);
+    assert_eq!(
+        retry.initial_backoff,
+        ProviderRetry::default().initial_backoff
+    );
+    assert_eq!(retry.circuit_breaker_threshold, Some(10));
+}
+
+#[test]
+fn check_subnet_read_only_config() {
+    let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+    assert!(!read_config().subnets[&child_id].read_only);
+
+    let config_str = config_str().replace(
+        &format!("id = \"{CHILD_ID}\""),
+        &format!("id = \"{CHILD_ID}\"\nread_only = true"),
+    );
+    let config = Config::from_toml_str(&config_str).unwrap();
+    assert!(config.subnets[&child_id].read_only);
 }
 
 fn config_str() -> String {
@@ -0,0 +1,155 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Two-step confirmation of destructive actions.
+//!
+//! Preparing an action returns a challenge describing exactly what will happen, signed with
+//! the key of the sender and valid for a limited time. The action is only executed when the
+//! challenge is handed back, for the same action and sender, before it expires; every challenge
+//! can be confirmed once.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, Signature};
+use ethers::utils::{hash_message, keccak256};
+use serde::{Deserialize, Serialize};
+
+/// A prepared destructive action waiting to be confirmed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Challenge {
+    /// What will happen when the action is confirmed.
+    pub action: String,
+    /// The address of the key which signed the challenge, and has to confirm it.
+    pub signer: Address,
+    /// Seconds since the Unix epoch after which the challenge can't be confirmed.
+    pub expires_at: u64,
+    /// Random value making every challenge unique.
+    pub nonce: u64,
+}
+
+impl Challenge {
+    pub fn new(action: String, signer: Address, ttl: Duration) -> Self {
+        Self {
+            action,
+            signer,
+            expires_at: now() + ttl.as_secs(),
+            nonce: rand::random(),
+        }
+    }
+
+    /// Sign the challenge, returning the token to confirm it with.
+    pub fn sign(&self, wallet: &LocalWallet) -> anyhow::Result<String> {
+        let payload = serde_json::to_string(self)?;
+        let signature = wallet.sign_hash(hash_message(&payload))?;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(payload);
+        Ok(format!("{payload}.{signature}"))
+    }
+
+    /// Check that a token was signed by `signer` for `action`, and hasn't expired.
+    pub fn verify(token: &str, action: &str, signer: Address) -> anyhow::Result<Self> {
+        let (payload, signature) = token
+            .split_once('.')
+            .ok_or_else(|| anyhow!("malformed confirmation token"))?;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .context("malformed confirmation token")?;
+        let payload = String::from_utf8(payload).context("malformed confirmation token")?;
+        let signature = signature
+            .parse::<Signature>()
+            .context("malformed confirmation signature")?;
+
+        signature
+            .verify(payload.as_str(), signer)
+            .map_err(|_| anyhow!("the confirmation was not signed by {signer:?}"))?;
+
+        let challenge: Challenge = serde_json::from_str(&payload)?;
+        if challenge.signer != signer {
+            bail!("the confirmation was prepared for {:?}", challenge.signer);
+        }
+        if challenge.action != action {
+            bail!(
+                "the confirmation was prepared for a different action: {}",
+                challenge.action
+            );
+        }
+        if challenge.expires_at < now() {
+            bail!("the confirmation expired, prepare the action again");
+        }
+        Ok(challenge)
+    }
+
+    /// Record the challenge as confirmed in the file at `path`, failing if it already was.
+    ///
+    /// Expired challenges are dropped from the file, since they can't be confirmed anyway.
+    pub fn consume(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut confirmed: BTreeMap<String, u64> = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)
+                .with_context(|| format!("cannot parse {}", path.to_string_lossy()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        let now = now();
+        confirmed.retain(|_, expires_at| *expires_at >= now);
+
+        let id = hex::encode(keccak256(serde_json::to_vec(self)?));
+        if confirmed.insert(id, self.expires_at).is_some() {
+            bail!("the confirmation has already been used");
+        }
+
+        std::fs::write(path, serde_json::to_string_pretty(&confirmed)?)
+            .with_context(|| format!("cannot write {}", path.to_string_lossy()))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::Challenge;
+
+    #[test]
+    fn test_confirm_challenge() {
+        let wallet = LocalWallet::new(&mut rand::thread_rng());
+        let other = LocalWallet::new(&mut rand::thread_rng());
+        let action = "kill subnet /r314159/t410f";
+
+        let challenge = Challenge::new(action.into(), wallet.address(), Duration::from_secs(60));
+        let token = challenge.sign(&wallet).unwrap();
+
+        assert_eq!(
+            Challenge::verify(&token, action, wallet.address()).unwrap(),
+            challenge
+        );
+        assert!(Challenge::verify(&token, "kill subnet /r314159/t410g", wallet.address()).is_err());
+        assert!(Challenge::verify(&token, action, other.address()).is_err());
+
+        let forged = other.sign_hash(ethers::utils::hash_message("x")).unwrap();
+        let forged = format!("{}.{forged}", token.split_once('.').unwrap().0);
+        assert!(Challenge::verify(&forged, action, wallet.address()).is_err());
+
+        let mut expired = Challenge::new(action.into(), wallet.address(), Duration::ZERO);
+        expired.expires_at -= 1;
+        let expired = expired.sign(&wallet).unwrap();
+        assert!(Challenge::verify(&expired, action, wallet.address()).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("confirmations.json");
+        challenge.consume(&path).unwrap();
+        assert!(challenge.consume(&path).is_err());
+    }
+}
@@ -6,11 +6,18 @@ use crate::manager::{GetBlockHashResult, TopDownQueryPayload};
 use anyhow::anyhow;
 use base64::Engine;
 use config::Config;
+use ethers::abi::Tokenizable;
+use ethers::signers::LocalWallet;
+use ethers::types::TxHash;
 use fvm_shared::{
     address::Address, clock::ChainEpoch, crypto::signature::SignatureType, econ::TokenAmount,
 };
-use ipc_api::checkpoint::{BottomUpCheckpointBundle, QuorumReachedEvent};
-use ipc_api::staking::{StakingChangeRequest, ValidatorInfo};
+use ipc_actors_abis::subnet_actor_checkpointing_facet;
+use ipc_api::checkpoint::{
+    BottomUpCheckpointBundle, CheckpointVerification, PendingBottomUpMsg, QuorumReachedEvent,
+};
+use ipc_api::evm::payload_to_evm_address;
+use ipc_api::staking::{ConfigurationNumber, StakingChangeRequest, ValidatorInfo};
 use ipc_api::subnet::{PermissionMode, SupplySource};
 use ipc_api::{
     cross::IpcEnvelope,
@@ -21,25 +28,37 @@ use ipc_wallet::{
     EthKeyAddress, EvmKeyStore, KeyStore, KeyStoreConfig, PersistentKeyStore, Wallet,
 };
 use lotus::message::wallet::WalletKeyType;
-use manager::{EthSubnetManager, SubnetGenesisInfo, SubnetInfo, SubnetManager};
+use manager::evm::{Deadline, EventIndex, FeeOverride, SharedClientState, TxJournal};
+use manager::{
+    EthSubnetManager, ManagerRegistry, SubnetGenesisInfo, SubnetInfo, SubnetManager,
+    SubnetManagerFactory, SubnetStatus,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Borrow,
     collections::HashMap,
     path::{Path, PathBuf},
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
 };
 use zeroize::Zeroize;
 
 pub mod checkpoint;
+pub mod checkpoint_costs;
+pub mod checkpoint_watcher;
 pub mod config;
+pub mod confirm;
 pub mod jsonrpc;
 pub mod lotus;
 pub mod manager;
 
 const DEFAULT_REPO_PATH: &str = ".ipc";
 const DEFAULT_CONFIG_NAME: &str = "config.toml";
+/// The confirmed destructive actions, kept in the keystore repo to prevent replays.
+const CONFIRMATIONS_FILE: &str = "confirmations.json";
+/// The most checkpoint batches read when listing the pending bottom-up messages, one request each.
+const MAX_PENDING_CHECKPOINT_BATCHES: i64 = 1000;
 
 /// The subnet manager connection that holds the subnet config and the manager instance.
 pub struct Connection {
@@ -65,6 +84,15 @@ pub struct IpcProvider {
     config: Arc<Config>,
     fvm_wallet: Option<Arc<RwLock<Wallet>>>,
     evm_keystore: Option<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>>,
+    /// Client state shared by all the connections to the same subnet.
+    client_states: Arc<Mutex<HashMap<SubnetID, SharedClientState>>>,
+    /// Hashes of the transactions sent through any of the connections.
+    tx_journal: TxJournal,
+    deadline: Deadline,
+    /// Custom subnet managers which subnets can select by name.
+    managers: ManagerRegistry,
+    /// Fees to use for the transactions instead of the estimated ones.
+    fee_override: FeeOverride,
 }
 
 impl IpcProvider {
@@ -78,6 +106,11 @@ impl IpcProvider {
             config,
             fvm_wallet: Some(fvm_wallet),
             evm_keystore: Some(evm_keystore),
+            client_states: Default::default(),
+            tx_journal: Default::default(),
+            deadline: Default::default(),
+            managers: Default::default(),
+            fee_override: Default::default(),
         }
     }
 
@@ -114,6 +147,11 @@ impl IpcProvider {
                 config,
                 fvm_wallet: None,
                 evm_keystore: None,
+                client_states: Default::default(),
+                tx_journal: Default::default(),
+                deadline: Default::default(),
+                managers: Default::default(),
+                fee_override: Default::default(),
             })
         }
     }
@@ -123,27 +161,70 @@ impl IpcProvider {
         Self::new_from_config(default_config_path())
     }
 
+    /// Get the client state for the subnet, such as its request limiter and circuit breaker.
+    ///
+    /// The same state is handed out to every connection to the subnet, so the limits
+    /// apply to the provider as a whole rather than to each connection separately.
+    fn client_state(&self, subnet: &config::Subnet) -> SharedClientState {
+        let mut states = self.client_states.lock().unwrap();
+        states
+            .entry(subnet.id.clone())
+            .or_insert_with(|| {
+                let state = SharedClientState::from_subnet(subnet)
+                    .with_journal(self.tx_journal.clone())
+                    .with_deadline(self.deadline.clone());
+                match &self.config.index_path {
+                    Some(path) => {
+                        state.with_index(EventIndex::for_subnet(expand_tilde(path), &subnet.id))
+                    }
+                    None => state,
+                }
+            })
+            .clone()
+    }
+
+    /// Send transactions with these fees instead of the ones estimated from recent blocks.
+    pub fn with_fee_override(mut self, fee_override: FeeOverride) -> Self {
+        self.fee_override = fee_override;
+        self
+    }
+
+    /// Register a custom subnet manager, which subnets can use by setting `manager`
+    /// to its name in their config, instead of the built-in one for their network type.
+    pub fn register_manager(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl SubnetManagerFactory + 'static,
+    ) -> anyhow::Result<()> {
+        self.managers.register(name, factory)
+    }
+
     /// Get the connection instance for the subnet.
     pub fn connection(&self, subnet: &SubnetID) -> Option<Connection> {
         let subnets = &self.config.subnets;
         match subnets.get(subnet) {
+            Some(subnet) if subnet.manager.is_some() => self.custom_connection(subnet),
             Some(subnet) => match &subnet.config {
                 config::subnet::SubnetConfig::Fevm(_) => {
+                    // Read-only subnets don't need a wallet, so don't warn about it.
                     let wallet = match self.evm_wallet() {
+                        _ if subnet.read_only => None,
                         Ok(w) => Some(w),
                         Err(e) => {
                             log::warn!("error initializing evm wallet: {e}");
                             None
                         }
                     };
-                    let manager =
-                        match EthSubnetManager::from_subnet_with_wallet_store(subnet, wallet) {
-                            Ok(w) => Some(w),
-                            Err(e) => {
-                                log::warn!("error initializing evm wallet: {e}");
-                                return None;
-                            }
-                        };
+                    let shared = self.client_state(subnet);
+                    let manager = match EthSubnetManager::from_subnet_with_shared_state(
+                        subnet, wallet, shared,
+                    ) {
+                        Ok(w) => Some(w.with_fee_override(self.fee_override.clone())),
+                        Err(e) => {
+                            log::warn!("error initializing evm wallet: {e}");
+                            return None;
+                        }
+                    };
                     Some(Connection {
                         manager: Box::new(manager.unwrap()),
                         subnet: subnet.clone(),
@@ -154,11 +235,57 @@ impl IpcProvider {
         }
     }
 
+    /// Create a connection using the custom manager the subnet is configured with.
+    fn custom_connection(&self, subnet: &config::Subnet) -> Option<Connection> {
+        let name = subnet.manager.as_deref()?;
+        let factory = match self.managers.get(name) {
+            Some(f) => f,
+            None => {
+                log::warn!(
+                    "subnet manager {name} of subnet {} is not registered",
+                    subnet.id
+                );
+                return None;
+            }
+        };
+        let keystore = if subnet.read_only {
+            None
+        } else {
+            self.evm_keystore.clone()
+        };
+        match factory.create(subnet, keystore) {
+            Ok(manager) => Some(Connection {
+                manager,
+                subnet: subnet.clone(),
+            }),
+            Err(e) => {
+                log::warn!("error initializing subnet manager {name}: {e}");
+                None
+            }
+        }
+    }
+
     /// Set the default account for the provider
     pub fn with_sender(&mut self, from: Address) {
         self.sender = Some(from);
     }
 
+    /// The default account of the provider, if it has been set or resolved from the wallet.
+    pub fn sender(&self) -> Option<Address> {
+        self.sender
+    }
+
+    /// Cancel the requests to the subnets which haven't completed by `deadline`, or stop
+    /// doing so with `None`. Applies to the connections created before the call as well.
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        self.deadline.set(deadline);
+    }
+
+    /// Remove and return the hashes of the transactions sent since the last call.
+    pub fn take_sent_transactions(&self) -> Vec<TxHash> {
+        self.tx_journal.take()
+    }
+
     /// Returns the evm wallet if it is configured, and throws an error if no wallet configured.
     ///
     /// This method should be used when we want the wallet retrieval to throw an error
@@ -186,6 +313,13 @@ impl IpcProvider {
         subnet: &config::Subnet,
         from: Option<Address>,
     ) -> anyhow::Result<Address> {
+        if subnet.read_only {
+            return Err(anyhow!(
+                "read-only connection: transactions cannot be sent to subnet {}",
+                subnet.id
+            ));
+        }
+
         // if there is from use that.
         if let Some(from) = from {
             return Ok(from);
@@ -413,6 +547,112 @@ impl IpcProvider {
         conn.manager().kill_subnet(subnet, sender).await
     }
 
+    /// Prepare the confirmation of a destructive action on a child subnet, returning a token
+    /// signed by the sender which [IpcProvider::confirm_destructive] accepts until it expires.
+    pub fn prepare_destructive(
+        &mut self,
+        subnet: &SubnetID,
+        from: Option<Address>,
+        action: String,
+        ttl: Duration,
+    ) -> anyhow::Result<String> {
+        let (signer, wallet) = self.confirmation_signer(subnet, from)?;
+        confirm::Challenge::new(action, signer, ttl).sign(&wallet)
+    }
+
+    /// Check the token of a destructive action prepared with [IpcProvider::prepare_destructive]
+    /// for the same subnet, sender and action, and mark it as used.
+    pub fn confirm_destructive(
+        &mut self,
+        subnet: &SubnetID,
+        from: Option<Address>,
+        action: &str,
+        token: &str,
+    ) -> anyhow::Result<()> {
+        let (signer, _) = self.confirmation_signer(subnet, from)?;
+        let challenge = confirm::Challenge::verify(token, action, signer)?;
+
+        let repo = self
+            .config
+            .keystore_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("No keystore repo found in config"))?;
+        challenge.consume(expand_tilde(repo).join(CONFIRMATIONS_FILE))
+    }
+
+    /// The sender of transactions to the parent of `subnet`, with its key.
+    fn confirmation_signer(
+        &mut self,
+        subnet: &SubnetID,
+        from: Option<Address>,
+    ) -> anyhow::Result<(ethers::types::Address, LocalWallet)> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let subnet_config = match self.connection(&parent) {
+            None => return Err(anyhow!("target parent subnet not found")),
+            Some(conn) => conn.subnet().clone(),
+        };
+        let sender = self.check_sender(&subnet_config, from)?;
+
+        let addr = payload_to_evm_address(sender.payload())?;
+        let wallet = self.evm_wallet()?;
+        let key = wallet
+            .read()
+            .unwrap()
+            .get(&addr.into())?
+            .ok_or_else(|| anyhow!("address {addr:} does not have private key in key store"))?;
+        Ok((addr, LocalWallet::from_bytes(key.private_key())?))
+    }
+
+    /// Index the events of a subnet and of its child subnets in the config into the local
+    /// event index, returning the height they are indexed up to.
+    pub async fn index_events(
+        &self,
+        subnet: &SubnetID,
+        from: ChainEpoch,
+        confirmations: u64,
+    ) -> anyhow::Result<ChainEpoch> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+        let children = self
+            .config
+            .subnets
+            .keys()
+            .filter(|id| id.parent().as_ref() == Some(subnet))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        conn.manager()
+            .index_events(&children, from, confirmations)
+            .await
+    }
+
+    /// Check that the provider of a subnet serves the network the subnet is configured with.
+    ///
+    /// Transactions are only sent once the check passed, this is for long running processes
+    /// to fail at startup rather than on their first transaction.
+    pub async fn check_subnet(&self, subnet: &SubnetID) -> anyhow::Result<()> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+        conn.manager().check_subnet().await
+    }
+
+    /// Subscribe to the heights of new blocks of a subnet, if it has a WebSocket endpoint
+    /// configured; otherwise new blocks have to be polled for.
+    pub fn subscribe_new_heads(
+        &self,
+        subnet: &SubnetID,
+    ) -> anyhow::Result<Option<tokio::sync::mpsc::Receiver<ChainEpoch>>> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+        Ok(conn.manager().subscribe_new_heads())
+    }
+
     pub async fn list_child_subnets(
         &self,
         gateway_addr: Option<Address>,
@@ -517,14 +757,52 @@ impl IpcProvider {
     /// Propagate a cross-net message forward. For `postbox_msg_key`, we are using bytes because different
     /// runtime have different representations. For FVM, it should be `CID` as bytes. For EVM, it is
     /// `bytes32`.
+    /// If `gateway_addr` is `None`, the gateway of the subnet in `ipc.toml` is used.
     pub async fn propagate(
-        &self,
-        _subnet: SubnetID,
-        _gateway_addr: Address,
-        _from: Address,
-        _postbox_msg_key: Vec<u8>,
+        &mut self,
+        subnet: SubnetID,
+        gateway_addr: Option<Address>,
+        from: Option<Address>,
+        postbox_msg_key: Vec<u8>,
     ) -> anyhow::Result<()> {
-        todo!()
+        let conn = match self.connection(&subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let subnet_config = conn.subnet();
+        let sender = self.check_sender(subnet_config, from)?;
+
+        let gateway_addr = match gateway_addr {
+            None => subnet_config.gateway_addr(),
+            Some(addr) => addr,
+        };
+
+        conn.manager()
+            .propagate(subnet, gateway_addr, sender, postbox_msg_key)
+            .await
+    }
+
+    /// Get the cross-net message waiting to be propagated in the postbox of a subnet.
+    pub async fn postbox_msg(
+        &self,
+        subnet: &SubnetID,
+        gateway_addr: Option<Address>,
+        postbox_msg_key: Vec<u8>,
+    ) -> anyhow::Result<Option<IpcEnvelope>> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let gateway_addr = match gateway_addr {
+            None => conn.subnet().gateway_addr(),
+            Some(addr) => addr,
+        };
+
+        conn.manager()
+            .postbox_msg(gateway_addr, postbox_msg_key)
+            .await
     }
 
     /// Send value between two addresses in a subnet
@@ -582,6 +860,17 @@ impl IpcProvider {
         conn.manager().chain_head_height().await
     }
 
+    /// The latest block finalized by the consensus of the chain, see
+    /// [TopDownFinalityQuery::finalized_chain_head_height].
+    pub async fn finalized_chain_head(&self, subnet: &SubnetID) -> anyhow::Result<ChainEpoch> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        conn.manager().finalized_chain_head_height().await
+    }
+
     /// Obtain the genesis epoch of the input subnet.
     pub async fn genesis_epoch(&self, subnet: &SubnetID) -> anyhow::Result<ChainEpoch> {
         let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
@@ -589,7 +878,10 @@ impl IpcProvider {
             None => return Err(anyhow!("parent subnet config not found")),
             Some(conn) => conn,
         };
-        conn.manager().genesis_epoch(subnet).await
+        match conn.manager().genesis_epoch(subnet).await {
+            Ok(epoch) => Ok(epoch),
+            Err(e) => Err(self.explain_unregistered(subnet, e).await),
+        }
     }
 
     /// Get the validator information.
@@ -622,6 +914,53 @@ impl IpcProvider {
         conn.manager().get_validator_changeset(subnet, epoch).await
     }
 
+    /// Get the lifecycle status of a child subnet from its subnet actor in the parent.
+    pub async fn subnet_status(&self, subnet: &SubnetID) -> anyhow::Result<SubnetStatus> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let conn = match self.connection(&parent) {
+            None => return Err(anyhow!("parent subnet config not found")),
+            Some(conn) => conn,
+        };
+        conn.manager().subnet_status(subnet).await
+    }
+
+    /// Get the staking changes of a child subnet which are recorded in the parent but
+    /// not yet confirmed by a bottom-up checkpoint.
+    pub async fn pending_staking_changes(
+        &self,
+        subnet: &SubnetID,
+    ) -> anyhow::Result<Vec<StakingChangeRequest>> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let conn = match self.connection(&parent) {
+            None => return Err(anyhow!("parent subnet config not found")),
+            Some(conn) => conn,
+        };
+        conn.manager().pending_staking_changes(subnet).await
+    }
+
+    /// Get the configuration number of the membership the subnet has applied.
+    pub async fn current_configuration_number(
+        &self,
+        subnet: &SubnetID,
+    ) -> anyhow::Result<ConfigurationNumber> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+        conn.manager().current_configuration_number().await
+    }
+
+    /// Add context to an error from a gateway lookup if it failed because the subnet has been
+    /// killed, in which case the gateway no longer has it, but its subnet actor can still be queried.
+    async fn explain_unregistered(&self, subnet: &SubnetID, err: anyhow::Error) -> anyhow::Error {
+        match self.subnet_status(subnet).await {
+            Ok(SubnetStatus::Killed) => err.context(format!(
+                "subnet {subnet} has been killed and is no longer registered in the gateway"
+            )),
+            _ => err,
+        }
+    }
+
     /// Get genesis info for a child subnet. This can be used to deterministically
     /// generate the genesis of the subnet
     pub async fn get_genesis_info(&self, subnet: &SubnetID) -> anyhow::Result<SubnetGenesisInfo> {
@@ -678,6 +1017,73 @@ impl IpcProvider {
         conn.manager().get_commit_sha().await
     }
 
+    /// Discovers the IPC contracts of a subnet on-chain, starting from its registry. If
+    /// `registry_addr` is `None` the registry in the subnet config is used.
+    ///
+    /// For child subnets, the subnet actor is also queried in the parent, if the parent is
+    /// configured, for the gateway it is registered with.
+    pub async fn discover_contracts(
+        &self,
+        subnet: &SubnetID,
+        registry_addr: Option<Address>,
+    ) -> anyhow::Result<DiscoveredContracts> {
+        let conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let registry_addr = registry_addr.unwrap_or_else(|| conn.subnet().registry_addr());
+        let gateway_addr = conn.manager().get_registry_gateway(&registry_addr).await?;
+
+        let parent_gateway_addr = match subnet.parent().and_then(|p| self.connection(&p)) {
+            Some(parent_conn) => Some(parent_conn.manager().get_subnet_gateway(subnet).await?),
+            None => None,
+        };
+
+        Ok(DiscoveredContracts {
+            registry_addr,
+            gateway_addr,
+            subnet_actor_addr: (!subnet.is_root()).then(|| subnet.subnet_actor()),
+            parent_gateway_addr,
+        })
+    }
+
+    /// Queries the cross-net message nonces of a child subnet on both sides, i.e. the ones
+    /// tracked by the parent gateway and by the subnet's own gateway.
+    pub async fn cross_msg_nonces(&self, subnet: &SubnetID) -> anyhow::Result<CrossMsgNonces> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let parent_conn = match self.connection(&parent) {
+            None => return Err(anyhow!("target parent subnet not found")),
+            Some(conn) => conn,
+        };
+        let child_conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let (top_down_sent, bottom_up_applied) =
+            match parent_conn.manager().child_cross_msg_nonces(subnet).await {
+                Ok(nonces) => nonces,
+                Err(e) => return Err(self.explain_unregistered(subnet, e).await),
+            };
+        let (top_down_applied, bottom_up_sent) =
+            child_conn.manager().own_cross_msg_nonces().await?;
+
+        let top_down = NoncePair {
+            sent: top_down_sent,
+            applied: top_down_applied,
+        };
+        let bottom_up = NoncePair {
+            sent: bottom_up_sent,
+            applied: bottom_up_applied,
+        };
+
+        Ok(CrossMsgNonces {
+            top_down,
+            bottom_up,
+        })
+    }
+
     pub async fn get_chain_head_height(&self, subnet: &SubnetID) -> anyhow::Result<ChainEpoch> {
         let conn = match self.connection(subnet) {
             None => return Err(anyhow!("target subnet not found")),
@@ -715,6 +1121,163 @@ impl IpcProvider {
             .await
     }
 
+    /// Get the height of the last bottom-up checkpoint of a subnet committed in its parent,
+    /// as of the parent block at the epoch.
+    pub async fn last_bottom_up_checkpoint_height_at(
+        &self,
+        subnet: &SubnetID,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<ChainEpoch> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let conn = match self.connection(&parent) {
+            None => return Err(anyhow!("parent subnet not found")),
+            Some(conn) => conn,
+        };
+
+        conn.manager()
+            .last_bottom_up_checkpoint_height_at(subnet, epoch)
+            .await
+    }
+
+    /// List the bottom-up messages of a subnet its parent has not committed yet, i.e. the ones
+    /// batched for the checkpoints after the last one submitted, up to the one being built, and
+    /// the ones carried over from the last submitted checkpoint because they didn't fit in it.
+    ///
+    /// Messages are included in checkpoints in nonce order, so the carried over ones are those
+    /// of the earlier batches with a nonce above the last one the submitted checkpoint included.
+    pub async fn pending_bottom_up_msgs(
+        &self,
+        subnet: &SubnetID,
+    ) -> anyhow::Result<Vec<PendingBottomUpMsg>> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let child_conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+        let parent_conn = match self.connection(&parent) {
+            None => return Err(anyhow!("parent subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let period = parent_conn.manager().checkpoint_period(subnet).await?;
+        if period <= 0 {
+            return Err(anyhow!("invalid checkpoint period: {period}"));
+        }
+        let last_submitted = parent_conn
+            .manager()
+            .last_bottom_up_checkpoint_height(subnet)
+            .await?;
+        let current = child_conn.manager().current_epoch().await?;
+
+        let batches = (current - last_submitted + period - 1) / period;
+        if batches > MAX_PENDING_CHECKPOINT_BATCHES {
+            return Err(anyhow!(
+                "the parent is {batches} checkpoints behind, more than the {MAX_PENDING_CHECKPOINT_BATCHES} that can be listed"
+            ));
+        }
+
+        let mut pending = Vec::new();
+
+        if last_submitted > 0 {
+            let submitted = child_conn
+                .manager()
+                .checkpoint_bundle_at(last_submitted)
+                .await?;
+            // An empty checkpoint means nothing was pending, as one message always fits.
+            if let Some(last_nonce) = submitted.checkpoint.msgs.iter().map(|m| m.nonce).max() {
+                let mut carried = Vec::new();
+                let mut height = last_submitted;
+                let mut batches = 0;
+                while height > 0 {
+                    if batches == MAX_PENDING_CHECKPOINT_BATCHES {
+                        return Err(anyhow!(
+                            "could not find the batch of the last submitted checkpoint in the last {MAX_PENDING_CHECKPOINT_BATCHES} batches"
+                        ));
+                    }
+                    let msgs = child_conn.manager().bottom_up_msgs_at(height).await?;
+                    let reached = msgs.iter().any(|m| m.nonce <= last_nonce);
+                    carried.extend(msgs.into_iter().filter(|m| m.nonce > last_nonce));
+                    if reached {
+                        break;
+                    }
+                    height -= period;
+                    batches += 1;
+                }
+                carried.sort_by_key(|m| m.nonce);
+                pending.extend(carried.into_iter().map(|msg| PendingBottomUpMsg {
+                    checkpoint_height: last_submitted + period,
+                    msg,
+                }));
+            }
+        }
+
+        let mut height = last_submitted + period;
+        // Messages sent now go into the batch of the first checkpoint at or after the current epoch.
+        while height - period < current {
+            for msg in child_conn.manager().bottom_up_msgs_at(height).await? {
+                pending.push(PendingBottomUpMsg {
+                    checkpoint_height: height,
+                    msg,
+                });
+            }
+            height += period;
+        }
+        Ok(pending)
+    }
+
+    /// Collect what it takes to verify the checkpoint of a subnet at a height end to end: the
+    /// quorum its validators reached, and whether and by which transaction the parent committed it.
+    ///
+    /// The transaction is only looked for in the parent blocks of `parent_blocks`, if given,
+    /// because without an event to filter on it takes a request for every block.
+    pub async fn verify_bottom_up_checkpoint(
+        &self,
+        subnet: &SubnetID,
+        height: ChainEpoch,
+        parent_blocks: Option<(ChainEpoch, ChainEpoch)>,
+    ) -> anyhow::Result<CheckpointVerification> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
+        let child_conn = match self.connection(subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+        let parent_conn = match self.connection(&parent) {
+            None => return Err(anyhow!("parent subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let quorum = child_conn.manager().checkpoint_quorum_at(height).await?;
+
+        let checkpoint = subnet_actor_checkpointing_facet::BottomUpCheckpoint::try_from(
+            quorum.bundle.checkpoint.clone(),
+        )?;
+        let hash = ethers::utils::keccak256(ethers::abi::encode(&[checkpoint.into_token()]));
+        let hash_matches = quorum.hash == hash;
+
+        let committed = parent_conn
+            .manager()
+            .last_bottom_up_checkpoint_height(subnet)
+            .await?
+            >= height;
+
+        let submission = match parent_blocks {
+            Some((from, to)) if committed => {
+                parent_conn
+                    .manager()
+                    .checkpoint_submission(subnet, height, from, to)
+                    .await?
+            }
+            _ => None,
+        };
+
+        Ok(CheckpointVerification {
+            quorum,
+            hash_matches,
+            committed,
+            submission,
+        })
+    }
+
     pub async fn quorum_reached_events(
         &self,
         subnet: &SubnetID,
@@ -789,6 +1352,49 @@ impl IpcProvider {
     }
 }
 
+/// The IPC contract addresses of a subnet, as discovered on-chain.
+#[derive(Debug)]
+pub struct DiscoveredContracts {
+    /// The registry the discovery started from.
+    pub registry_addr: Address,
+    /// The gateway deployed in the subnet, as reported by the registry.
+    pub gateway_addr: Address,
+    /// The subnet actor deployed in the parent, for child subnets.
+    pub subnet_actor_addr: Option<Address>,
+    /// The gateway in the parent the subnet actor is registered with, if the parent is configured.
+    pub parent_gateway_addr: Option<Address>,
+}
+
+/// The nonces of the cross-net messages flowing in one direction between a parent and a child.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoncePair {
+    /// The nonce the source gateway will assign to the next message.
+    pub sent: u64,
+    /// The nonce of the next message the destination gateway expects to apply.
+    pub applied: u64,
+}
+
+impl NoncePair {
+    /// The number of messages sent but not yet applied in the destination.
+    pub fn pending(&self) -> u64 {
+        self.sent.saturating_sub(self.applied)
+    }
+
+    /// Whether the destination has applied every message sent from the source.
+    pub fn in_sync(&self) -> bool {
+        self.sent == self.applied
+    }
+}
+
+/// The cross-net message nonces of a child subnet in both directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrossMsgNonces {
+    /// Nonces of the messages from the parent to the child.
+    pub top_down: NoncePair,
+    /// Nonces of the messages from the child to the parent.
+    pub bottom_up: NoncePair,
+}
+
 /// Lotus JSON keytype format
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -889,11 +1495,21 @@ pub fn new_evm_keystore_from_config(
     config: Arc<Config>,
 ) -> anyhow::Result<PersistentKeyStore<EthKeyAddress>> {
     let repo_str = &config.keystore_path;
-    if let Some(repo_str) = repo_str {
-        new_evm_keystore_from_path(repo_str)
+    let mut keystore = if let Some(repo_str) = repo_str {
+        new_evm_keystore_from_path(repo_str)?
     } else {
-        Err(anyhow!("No keystore repo found in config"))
+        return Err(anyhow!("No keystore repo found in config"));
+    };
+
+    for key in config.wallet_keys.iter() {
+        let mut private_key = config::secret::resolve_secret(key)?;
+        let bytes = hex::decode(private_key.trim_start_matches("0x"));
+        private_key.zeroize();
+        let addr = keystore.put_ephemeral(ipc_wallet::EvmKeyInfo::new(bytes?))?;
+        log::info!("loaded evm key of {} from secret", addr.to_string());
     }
+
+    Ok(keystore)
 }
 
 pub fn new_evm_keystore_from_path(
@@ -0,0 +1,119 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! Verification that the provider of a subnet serves the network the subnet is configured
+//! with, before any transaction is sent through it.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::bail;
+use ethers::types::{Address, Bytes};
+use ipc_api::subnet_id::SubnetID;
+use tokio::sync::OnceCell;
+
+/// Whether the provider of a subnet passed the check, shared by all the managers
+/// connected to it so it only runs once.
+#[derive(Debug, Clone, Default)]
+pub struct SubnetCheck {
+    passed: Arc<OnceCell<()>>,
+}
+
+impl SubnetCheck {
+    /// Run the check unless it already passed. Failures are not cached, so the check is
+    /// repeated on the next call, e.g. once the config has been fixed.
+    pub async fn ensure<F, Fut>(&self, check: F) -> anyhow::Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        self.passed.get_or_try_init(check).await?;
+        Ok(())
+    }
+}
+
+/// What the provider of a subnet reports about the network it serves.
+#[derive(Debug, Clone)]
+pub struct RemoteSubnet {
+    pub chain_id: u64,
+    /// The code deployed at the configured gateway address.
+    pub gateway_code: Bytes,
+    /// The subnet the gateway reports to belong to, if it has code.
+    pub network_name: Option<SubnetID>,
+}
+
+/// Compare what the provider reports against the configuration of the subnet.
+pub fn verify(subnet: &SubnetID, gateway: Address, remote: &RemoteSubnet) -> anyhow::Result<()> {
+    let chain_id = subnet.chain_id();
+    if remote.chain_id != chain_id {
+        bail!(
+            "the provider of subnet {subnet} serves chain ID {}, but the subnet has chain ID {chain_id}; check its provider_http",
+            remote.chain_id
+        );
+    }
+    if remote.gateway_code.is_empty() {
+        bail!("no contract is deployed at the gateway address {gateway:?} of subnet {subnet}; check its gateway_addr");
+    }
+    match &remote.network_name {
+        Some(name) if name != subnet => bail!(
+            "the gateway at {gateway:?} belongs to subnet {name}, not to {subnet}; check its gateway_addr"
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::types::{Address, Bytes};
+    use ipc_api::subnet_id::SubnetID;
+
+    use super::{verify, RemoteSubnet, SubnetCheck};
+
+    #[test]
+    fn test_verify_remote_subnet() {
+        let subnet = SubnetID::new_root(314159);
+        let gateway = Address::repeat_byte(1);
+        let remote = RemoteSubnet {
+            chain_id: 314159,
+            gateway_code: Bytes::from(vec![0x60, 0x80]),
+            network_name: Some(subnet.clone()),
+        };
+        assert!(verify(&subnet, gateway, &remote).is_ok());
+
+        let wrong_chain = RemoteSubnet {
+            chain_id: 314,
+            ..remote.clone()
+        };
+        assert!(verify(&subnet, gateway, &wrong_chain).is_err());
+
+        let no_gateway = RemoteSubnet {
+            gateway_code: Bytes::default(),
+            network_name: None,
+            ..remote.clone()
+        };
+        assert!(verify(&subnet, gateway, &no_gateway).is_err());
+
+        let other_gateway = RemoteSubnet {
+            network_name: Some(SubnetID::from_str("/r314159/f0100").unwrap()),
+            ..remote
+        };
+        assert!(verify(&subnet, gateway, &other_gateway).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_runs_until_it_passes() {
+        let check = SubnetCheck::default();
+        assert!(check
+            .ensure(|| async { anyhow::bail!("down") })
+            .await
+            .is_err());
+        assert!(check.ensure(|| async { Ok(()) }).await.is_ok());
+        // Once passed, the check isn't run again.
+        assert!(check
+            .ensure(|| async { anyhow::bail!("down") })
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,654 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! JSON-RPC transport used by the EVM subnet manager.
+
+use std::fmt::Debug;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ethers::providers::{
+    Http, HttpClientError, JsonRpcClient, JsonRpcError, ProviderError, RpcError,
+};
+use ethers::types::{TxHash, U64};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::check::SubnetCheck;
+use super::index::EventIndex;
+use super::multicall::MulticallSupport;
+use super::nonce::NonceTracker;
+use crate::config::subnet::ProviderRetry;
+use crate::config::Subnet;
+
+/// Bounds the number of requests in flight against a single subnet endpoint.
+///
+/// Callers beyond the limit are queued until a slot frees up; if a queue timeout
+/// is configured, they give up with [`SubnetClientError::QueueTimeout`] instead
+/// of waiting indefinitely. Clones share the same slots.
+#[derive(Debug, Clone)]
+pub struct RequestLimiter {
+    semaphore: Arc<Semaphore>,
+    queue_timeout: Option<Duration>,
+}
+
+impl RequestLimiter {
+    pub fn new(max_in_flight: NonZeroUsize, queue_timeout: Option<Duration>) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_in_flight.get())),
+            queue_timeout,
+        }
+    }
+
+    /// Wait for a free slot, respecting the queue timeout.
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit, SubnetClientError> {
+        let acquire = self.semaphore.clone().acquire_owned();
+        let permit = match self.queue_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire)
+                .await
+                .map_err(|_| SubnetClientError::QueueTimeout(timeout))?,
+            None => acquire.await,
+        };
+        // The semaphore is never closed.
+        Ok(permit.expect("request limiter semaphore closed"))
+    }
+}
+
+/// Records the hashes of the transactions submitted through the clients it is attached to.
+///
+/// Clones share the same records.
+#[derive(Debug, Clone, Default)]
+pub struct TxJournal(Arc<Mutex<Vec<TxHash>>>);
+
+impl TxJournal {
+    /// Remove and return the hashes recorded so far.
+    pub fn take(&self) -> Vec<TxHash> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    fn record(&self, hash: TxHash) {
+        self.0.lock().unwrap().push(hash);
+    }
+}
+
+/// The time by which the requests sent through the clients it is attached to have to
+/// complete; the ones still in flight then are cancelled, instead of being left to wait
+/// for a slow node. Clones share the same deadline.
+#[derive(Debug, Clone, Default)]
+pub struct Deadline(Arc<Mutex<Option<Instant>>>);
+
+impl Deadline {
+    /// Set the deadline of the requests sent from now on, or remove it with `None`.
+    pub fn set(&self, deadline: Option<Instant>) {
+        *self.0.lock().unwrap() = deadline;
+    }
+
+    fn get(&self) -> Option<Instant> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Stops sending requests to a subnet for a while after several consecutive ones failed.
+///
+/// Once the cooldown is over requests are let through again; the next failure reopens
+/// the breaker, while a success closes it. Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Default::default(),
+        }
+    }
+
+    /// Fail if the breaker is open.
+    fn check(&self) -> Result<(), SubnetClientError> {
+        match self.state.lock().unwrap().open_until {
+            Some(until) if until > Instant::now() => Err(SubnetClientError::CircuitOpen(
+                until.saturating_duration_since(Instant::now()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures >= self.threshold {
+            if state.open_until.is_none() {
+                log::warn!(
+                    "opening circuit breaker after {} consecutive failures",
+                    state.consecutive_failures
+                );
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+/// The parts of a [SubnetClient] shared by all the connections to the same subnet.
+#[derive(Debug, Clone, Default)]
+pub struct SharedClientState {
+    pub limiter: Option<RequestLimiter>,
+    pub breaker: Option<CircuitBreaker>,
+    pub journal: Option<TxJournal>,
+    pub nonces: NonceTracker,
+    pub multicall: MulticallSupport,
+    pub index: Option<EventIndex>,
+    pub check: SubnetCheck,
+    pub deadline: Option<Deadline>,
+}
+
+impl SharedClientState {
+    /// Create a fresh state based on the subnet configuration.
+    pub fn from_subnet(subnet: &Subnet) -> Self {
+        let limiter = subnet
+            .rpc_max_in_flight()
+            .map(|max| RequestLimiter::new(max, subnet.rpc_queue_timeout()));
+
+        let breaker = subnet.rpc_retry().and_then(|retry| {
+            retry
+                .circuit_breaker_threshold
+                .map(|threshold| CircuitBreaker::new(threshold, retry.circuit_breaker_cooldown))
+        });
+
+        Self {
+            limiter,
+            breaker,
+            journal: None,
+            nonces: NonceTracker::default(),
+            multicall: MulticallSupport::default(),
+            index: None,
+            check: SubnetCheck::default(),
+            deadline: None,
+        }
+    }
+
+    /// Record the hashes of the transactions sent through the clients.
+    pub fn with_journal(mut self, journal: TxJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Cancel the requests still in flight when the deadline expires.
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Serve event queries from a local index where it covers them.
+    pub fn with_index(mut self, index: EventIndex) -> Self {
+        self.index = Some(index);
+        self
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubnetClientError {
+    #[error(transparent)]
+    Http(#[from] HttpClientError),
+    #[error("timed out after {0:?} waiting for a free request slot")]
+    QueueTimeout(Duration),
+    #[error("provider is failing; not sending requests for another {0:?}")]
+    CircuitOpen(Duration),
+    #[error("deadline expired before {0} completed")]
+    DeadlineExceeded(String),
+}
+
+impl RpcError for SubnetClientError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            SubnetClientError::Http(e) => e.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            SubnetClientError::Http(e) => e.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<SubnetClientError> for ProviderError {
+    fn from(e: SubnetClientError) -> Self {
+        match e {
+            SubnetClientError::Http(e) => e.into(),
+            e => ProviderError::JsonRpcClientError(Box::new(e)),
+        }
+    }
+}
+
+/// HTTP transport with an optional limit on concurrent requests.
+///
+/// The client can have fallback endpoints for the same network. It keeps using
+/// the same endpoint until a request to it fails at the transport level, then
+/// moves on to the next one which passes a health check and sticks with that.
+/// JSON-RPC errors returned by a node don't trigger a failover.
+///
+/// If none of the endpoints can be reached, the request can be retried with
+/// exponential backoff, and a circuit breaker can stop sending requests for a
+/// while after repeated failures.
+///
+/// Queries for state or logs the endpoints have pruned can be sent to an
+/// archival endpoint instead.
+#[derive(Debug, Clone)]
+pub struct SubnetClient {
+    endpoints: Arc<Vec<Http>>,
+    /// Index of the endpoint currently in use.
+    current: Arc<AtomicUsize>,
+    shared: SharedClientState,
+    retry: Option<ProviderRetry>,
+    archive: Option<Http>,
+}
+
+impl SubnetClient {
+    pub fn new(http: Http, shared: SharedClientState) -> Self {
+        Self {
+            endpoints: Arc::new(vec![http]),
+            current: Arc::new(AtomicUsize::new(0)),
+            shared,
+            retry: None,
+            archive: None,
+        }
+    }
+
+    /// Add endpoints to fail over to, in order of preference.
+    pub fn with_fallbacks(mut self, fallbacks: Vec<Http>) -> Self {
+        let mut endpoints = self.endpoints.as_ref().clone();
+        endpoints.extend(fallbacks);
+        self.endpoints = Arc::new(endpoints);
+        self
+    }
+
+    /// Retry requests which fail to reach any of the endpoints.
+    pub fn with_retry(mut self, retry: ProviderRetry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Send the queries the endpoints can't answer because they pruned the data to an archival endpoint.
+    pub fn with_archive(mut self, archive: Http) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Send the request to a specific endpoint.
+    async fn request_to<T, R>(
+        &self,
+        http: &Http,
+        method: &str,
+        params: T,
+    ) -> Result<R, SubnetClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match &self.shared.journal {
+            Some(journal) if is_send_transaction(method) => {
+                let hash: TxHash = http.request(method, params).await?;
+                journal.record(hash);
+                // Round-trip through JSON so we can return the hash as whatever type the caller asked for.
+                serde_json::to_value(hash)
+                    .and_then(serde_json::from_value)
+                    .map_err(|err| {
+                        SubnetClientError::Http(HttpClientError::SerdeJson {
+                            err,
+                            text: format!("{hash:?}"),
+                        })
+                    })
+            }
+            _ => Ok(http.request(method, params).await?),
+        }
+    }
+
+    /// Send the request to the endpoints, and to the archival endpoint if they pruned the
+    /// state or logs it asks for.
+    async fn request_with_archive<T, R>(
+        &self,
+        method: &str,
+        params: &T,
+    ) -> Result<R, SubnetClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match (
+            self.request_with_failover(method, params).await,
+            &self.archive,
+        ) {
+            (Err(e), Some(archive)) if may_be_pruned(method, &e) => {
+                log::info!("request {method} reaches beyond the pruned history, sending it to the archival endpoint: {e}");
+                self.request_to(archive, method, params).await
+            }
+            (res, _) => res,
+        }
+    }
+
+    /// Send the request to the current endpoint, failing over to the others if it can't be reached.
+    ///
+    /// Transactions are only sent to the current endpoint: a request which failed may still have
+    /// reached the node, and another node would see the same transaction twice.
+    async fn request_with_failover<T, R>(
+        &self,
+        method: &str,
+        params: &T,
+    ) -> Result<R, SubnetClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let start = self.current.load(Ordering::Relaxed);
+        let num_endpoints = self.endpoints.len();
+        let num_attempts = if is_send_transaction(method) {
+            1
+        } else {
+            num_endpoints
+        };
+        let mut last_err = None;
+
+        for i in 0..num_attempts {
+            let idx = (start + i) % num_endpoints;
+            let http = &self.endpoints[idx];
+
+            if i > 0 && !is_healthy(http).await {
+                log::warn!("skipping unhealthy fallback endpoint {idx}");
+                continue;
+            }
+
+            match self.request_to(http, method, params).await {
+                Err(e) if is_unreachable(&e) && num_endpoints > 1 => {
+                    log::warn!("request {method} to endpoint {idx} failed: {e}");
+                    last_err = Some(e);
+                }
+                res => {
+                    if i > 0 {
+                        // Only switch if nobody else has done so in the meantime.
+                        if self
+                            .current
+                            .compare_exchange(start, idx, Ordering::Relaxed, Ordering::Relaxed)
+                            .is_ok()
+                        {
+                            log::info!("failed over from endpoint {start} to {idx}");
+                        }
+                    }
+                    return res;
+                }
+            }
+        }
+
+        Err(last_err.expect("there is at least one endpoint"))
+    }
+}
+
+/// Check that the endpoint responds to a cheap query.
+async fn is_healthy(http: &Http) -> bool {
+    http.request::<_, U64>("eth_blockNumber", ()).await.is_ok()
+}
+
+/// Whether the endpoint could not be reached, as opposed to returning an error.
+fn is_unreachable(err: &SubnetClientError) -> bool {
+    matches!(
+        err,
+        SubnetClientError::Http(HttpClientError::ReqwestError(_))
+    )
+}
+
+/// Methods reading state, blocks or logs at a given height, which the node may have pruned.
+const HISTORY_METHODS: &[&str] = &[
+    "eth_call",
+    "eth_estimateGas",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+    "eth_getProof",
+    "eth_getLogs",
+    "eth_getBlockByNumber",
+    "eth_getBlockReceipts",
+];
+
+/// Parts of the messages nodes return when they pruned the state or blocks asked for:
+/// geth and its forks, and Lotus refusing to look back beyond its splitstore.
+const PRUNED_ERRORS: &[&str] = &[
+    "state not found",
+    "missing trie node",
+    "lookbacks of more than",
+];
+
+/// Whether the node returned an error because it no longer has the data asked for.
+///
+/// Nodes don't agree on an error code for pruned history, so the message is matched instead;
+/// anything else, e.g. a reverted call, would fail on an archival node just the same.
+fn may_be_pruned(method: &str, err: &SubnetClientError) -> bool {
+    HISTORY_METHODS.contains(&method)
+        && err.as_error_response().map_or(false, |e| {
+            let message = e.message.to_lowercase();
+            PRUNED_ERRORS.iter().any(|m| message.contains(m))
+        })
+}
+
+#[async_trait]
+impl JsonRpcClient for SubnetClient {
+    type Error = SubnetClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self.shared.deadline.as_ref().and_then(Deadline::get) {
+            // Dropping the request on timeout cancels it, including any retries.
+            Some(deadline) => {
+                tokio::time::timeout_at(deadline.into(), self.request_with_retry(method, params))
+                    .await
+                    .map_err(|_| SubnetClientError::DeadlineExceeded(method.to_string()))?
+            }
+            None => self.request_with_retry(method, params).await,
+        }
+    }
+}
+
+impl SubnetClient {
+    /// Send the request through the limiter and the circuit breaker, retrying it if
+    /// none of the endpoints can be reached, unless it sends a transaction.
+    async fn request_with_retry<T, R>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Result<R, SubnetClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if let Some(ref breaker) = self.shared.breaker {
+            breaker.check()?;
+        }
+
+        let mut attempt = 0;
+        loop {
+            // The permit is only held during the attempt, not while backing off.
+            let permit = match &self.shared.limiter {
+                Some(limiter) => Some(limiter.acquire().await?),
+                None => None,
+            };
+            let res = self.request_with_archive(method, &params).await;
+            drop(permit);
+
+            match res {
+                Err(e) if is_unreachable(&e) => match self.retry {
+                    Some(ref retry)
+                        if attempt < retry.max_retries && !is_send_transaction(method) =>
+                    {
+                        let delay = backoff(retry, attempt);
+                        log::warn!("request {method} failed, retrying in {delay:?}: {e}");
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    _ => {
+                        if let Some(ref breaker) = self.shared.breaker {
+                            breaker.record_failure();
+                        }
+                        return Err(e);
+                    }
+                },
+                res => {
+                    if let Some(ref breaker) = self.shared.breaker {
+                        breaker.record_success();
+                    }
+                    return res;
+                }
+            }
+        }
+    }
+}
+
+/// Exponential backoff with full jitter: a random delay up to the doubled delay of the previous attempt.
+fn backoff(retry: &ProviderRetry, attempt: u32) -> Duration {
+    let max = retry
+        .initial_backoff
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(retry.max_backoff);
+    max.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+}
+
+fn is_send_transaction(method: &str) -> bool {
+    matches!(method, "eth_sendRawTransaction" | "eth_sendTransaction")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use crate::config::subnet::ProviderRetry;
+
+    use ethers::providers::{Http, HttpClientError, JsonRpcClient, JsonRpcError};
+    use ethers::types::U64;
+
+    use super::{
+        backoff, may_be_pruned, CircuitBreaker, Deadline, RequestLimiter, SharedClientState,
+        SubnetClient, SubnetClientError,
+    };
+
+    #[tokio::test]
+    async fn limiter_times_out_when_full() {
+        let limiter = RequestLimiter::new(NonZeroUsize::MIN, Some(Duration::from_millis(50)));
+
+        let permit = limiter.acquire().await.unwrap();
+        let err = limiter.acquire().await.unwrap_err();
+        assert!(matches!(err, SubnetClientError::QueueTimeout(_)));
+
+        drop(permit);
+        assert!(limiter.acquire().await.is_ok());
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let retry = ProviderRetry {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            ..Default::default()
+        };
+        assert!(backoff(&retry, 0) <= Duration::from_millis(100));
+        assert!(backoff(&retry, 3) <= Duration::from_millis(800));
+        assert!(backoff(&retry, 30) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn circuit_breaker_opens_and_closes() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        breaker.record_failure();
+        assert!(breaker.check().is_ok());
+        breaker.record_failure();
+        assert!(matches!(
+            breaker.check(),
+            Err(SubnetClientError::CircuitOpen(_))
+        ));
+
+        breaker.record_success();
+        assert!(breaker.check().is_ok());
+    }
+
+    #[tokio::test]
+    async fn requests_are_cancelled_at_the_deadline() {
+        // Accepts connections but never responds.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let deadline = Deadline::default();
+        let shared = SharedClientState::default().with_deadline(deadline.clone());
+        let client = SubnetClient::new(Http::new(url.parse::<url::Url>().unwrap()), shared);
+
+        deadline.set(Some(std::time::Instant::now() + Duration::from_millis(100)));
+        let err = client
+            .request::<_, U64>("eth_blockNumber", ())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SubnetClientError::DeadlineExceeded(_)));
+    }
+
+    #[test]
+    fn pruned_history_is_detected() {
+        let rpc_error = |code: i64, message: &str| {
+            SubnetClientError::Http(HttpClientError::JsonRpcError(JsonRpcError {
+                code,
+                message: message.into(),
+                data: None,
+            }))
+        };
+        let lookback = "bad tipset height: lookbacks of more than 16h40m0s are disallowed";
+        assert!(may_be_pruned("eth_getLogs", &rpc_error(1, lookback)));
+        assert!(may_be_pruned(
+            "eth_call",
+            &rpc_error(-32000, "missing trie node 4f8c... (path )")
+        ));
+        assert!(may_be_pruned(
+            "eth_getBalance",
+            &rpc_error(-32000, "State not found for block 0x1234")
+        ));
+        // reverted calls and other failures would fail on the archive too
+        assert!(!may_be_pruned(
+            "eth_call",
+            &rpc_error(3, "execution reverted")
+        ));
+        assert!(!may_be_pruned(
+            "eth_getLogs",
+            &rpc_error(-32005, "query returned more than 10000 results")
+        ));
+        // only queries about the history can hit pruned data
+        assert!(!may_be_pruned(
+            "eth_sendRawTransaction",
+            &rpc_error(1, lookback)
+        ));
+        assert!(!may_be_pruned(
+            "eth_getLogs",
+            &SubnetClientError::QueueTimeout(Duration::ZERO)
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! EIP-1559 fee estimation for the transactions sent to a subnet.
+
+use anyhow::anyhow;
+use ethers::providers::Middleware;
+use ethers::types::{BlockNumber, I256, U256};
+
+use crate::config::subnet::FeeCaps;
+
+/// Fees chosen by the user, used instead of the estimated ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeeOverride {
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+}
+
+/// The fees of an EIP-1559 transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fees {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Estimates the fees of a transaction from the base fees and priority fees paid in recent blocks.
+///
+/// The estimates are limited by the caps configured for the subnet, so a fee spike on the
+/// parent doesn't make transactions arbitrarily expensive, while fees given explicitly by
+/// the user are taken as they are.
+#[derive(Debug, Clone, Default)]
+pub struct FeeOracle {
+    caps: FeeCaps,
+    overrides: FeeOverride,
+}
+
+impl FeeOracle {
+    pub fn new(caps: FeeCaps) -> Self {
+        Self {
+            caps,
+            overrides: Default::default(),
+        }
+    }
+
+    pub fn with_override(mut self, overrides: FeeOverride) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Estimate the fees of the next transaction; only queries the node if
+    /// the user didn't set both fees.
+    pub async fn estimate<M>(&self, client: &M) -> anyhow::Result<Fees>
+    where
+        M: Middleware,
+        M::Error: 'static,
+    {
+        if let FeeOverride {
+            max_fee_per_gas: Some(max_fee_per_gas),
+            max_priority_fee_per_gas: Some(max_priority_fee_per_gas),
+        } = self.overrides
+        {
+            return Ok(Fees {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            });
+        }
+
+        let fee_history = client
+            .fee_history(
+                ethers::utils::EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
+                BlockNumber::Latest,
+                &[ethers::utils::EIP1559_FEE_ESTIMATION_REWARD_PERCENTILE],
+            )
+            .await?;
+
+        // The history includes the base fee of the block after the latest one.
+        let base_fee_per_gas = *fee_history
+            .base_fee_per_gas
+            .last()
+            .ok_or_else(|| anyhow!("EIP-1559 not activated"))?;
+
+        let estimated = estimate_fees(base_fee_per_gas, fee_history.reward);
+
+        Ok(self.apply(estimated))
+    }
+
+    /// Apply the user overrides and the caps to the estimated fees.
+    fn apply(&self, estimated: Fees) -> Fees {
+        let mut max_fee_per_gas = estimated.max_fee_per_gas;
+        let mut max_priority_fee_per_gas = estimated.max_priority_fee_per_gas;
+
+        if let Some(cap) = self.caps.max_fee_per_gas.map(U256::from) {
+            if max_fee_per_gas > cap {
+                log::warn!("estimated max fee per gas {max_fee_per_gas} capped at {cap}");
+                max_fee_per_gas = cap;
+            }
+        }
+        if let Some(cap) = self.caps.max_priority_fee_per_gas.map(U256::from) {
+            max_priority_fee_per_gas = max_priority_fee_per_gas.min(cap);
+        }
+
+        let max_fee_per_gas = self.overrides.max_fee_per_gas.unwrap_or(max_fee_per_gas);
+        let max_priority_fee_per_gas = self
+            .overrides
+            .max_priority_fee_per_gas
+            .unwrap_or(max_priority_fee_per_gas);
+
+        Fees {
+            max_fee_per_gas,
+            // The tip is paid out of the max fee, it can't be higher.
+            max_priority_fee_per_gas: max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+}
+
+/// Returns an estimation of an optimal `gas_premium` and `gas_fee_cap`
+/// for a transaction considering the average premium, base_fee and reward percentile from
+/// past blocks
+/// This is adaptation of ethers' `eip1559_default_estimator`:
+/// https://github.com/gakonst/ethers-rs/blob/5dcd3b7e754174448f9a8cbfc0523896609629f9/ethers-core/src/utils/mod.rs#L476
+fn estimate_fees(base_fee_per_gas: U256, rewards: Vec<Vec<U256>>) -> Fees {
+    let max_priority_fee_per_gas = estimate_priority_fee(rewards); //overestimate?
+    let potential_max_fee = base_fee_surged(base_fee_per_gas);
+    let max_fee_per_gas = if max_priority_fee_per_gas > potential_max_fee {
+        max_priority_fee_per_gas + potential_max_fee
+    } else {
+        potential_max_fee
+    };
+
+    Fees {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    }
+}
+
+/// Implementation borrowed from
+/// https://github.com/gakonst/ethers-rs/blob/ethers-v2.0.8/ethers-core/src/utils/mod.rs#L582
+/// Refer to the implementation for unit tests
+fn base_fee_surged(base_fee_per_gas: U256) -> U256 {
+    if base_fee_per_gas <= U256::from(40_000_000_000u64) {
+        base_fee_per_gas * 2
+    } else if base_fee_per_gas <= U256::from(100_000_000_000u64) {
+        base_fee_per_gas * 16 / 10
+    } else if base_fee_per_gas <= U256::from(200_000_000_000u64) {
+        base_fee_per_gas * 14 / 10
+    } else {
+        base_fee_per_gas * 12 / 10
+    }
+}
+
+/// Implementation borrowed from
+/// https://github.com/gakonst/ethers-rs/blob/ethers-v2.0.8/ethers-core/src/utils/mod.rs#L536
+/// Refer to the implementation for unit tests
+fn estimate_priority_fee(rewards: Vec<Vec<U256>>) -> U256 {
+    let mut rewards: Vec<U256> = rewards
+        .iter()
+        .map(|r| r[0])
+        .filter(|r| *r > U256::zero())
+        .collect();
+    if rewards.is_empty() {
+        return U256::zero();
+    }
+    if rewards.len() == 1 {
+        return rewards[0];
+    }
+    // Sort the rewards as we will eventually take the median.
+    rewards.sort();
+
+    // A copy of the same vector is created for convenience to calculate percentage change
+    // between subsequent fee values.
+    let mut rewards_copy = rewards.clone();
+    rewards_copy.rotate_left(1);
+
+    let mut percentage_change: Vec<I256> = rewards
+        .iter()
+        .zip(rewards_copy.iter())
+        .map(|(a, b)| {
+            let a = I256::try_from(*a).expect("priority fee overflow");
+            let b = I256::try_from(*b).expect("priority fee overflow");
+            ((b - a) * 100) / a
+        })
+        .collect();
+    percentage_change.pop();
+
+    // Fetch the max of the percentage change, and that element's index.
+    let max_change = percentage_change.iter().max().unwrap();
+    let max_change_index = percentage_change
+        .iter()
+        .position(|&c| c == *max_change)
+        .unwrap();
+
+    // If we encountered a big change in fees at a certain position, then consider only
+    // the values >= it.
+    let values = if *max_change >= ethers::utils::EIP1559_FEE_ESTIMATION_THRESHOLD_MAX_CHANGE.into()
+        && (max_change_index >= (rewards.len() / 2))
+    {
+        rewards[max_change_index..].to_vec()
+    } else {
+        rewards
+    };
+
+    // Return the median.
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::{FeeOracle, FeeOverride, Fees};
+    use crate::config::subnet::FeeCaps;
+
+    #[test]
+    fn test_fee_caps_and_overrides() {
+        let estimated = Fees {
+            max_fee_per_gas: U256::from(1000),
+            max_priority_fee_per_gas: U256::from(100),
+        };
+
+        let oracle = FeeOracle::default();
+        assert_eq!(oracle.apply(estimated), estimated);
+
+        let oracle = FeeOracle::new(FeeCaps {
+            max_fee_per_gas: Some(50),
+            max_priority_fee_per_gas: Some(200),
+        });
+        assert_eq!(
+            oracle.apply(estimated),
+            Fees {
+                max_fee_per_gas: U256::from(50),
+                max_priority_fee_per_gas: U256::from(50),
+            }
+        );
+
+        // Overrides are not capped.
+        let oracle = oracle.with_override(FeeOverride {
+            max_fee_per_gas: Some(U256::from(2000)),
+            max_priority_fee_per_gas: None,
+        });
+        assert_eq!(
+            oracle.apply(estimated),
+            Fees {
+                max_fee_per_gas: U256::from(2000),
+                max_priority_fee_per_gas: U256::from(100),
+            }
+        );
+    }
+}
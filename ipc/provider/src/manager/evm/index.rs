@@ -0,0 +1,366 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! Local index of the events emitted by the IPC contracts.
+//!
+//! Queries like the pending staking changes of a subnet look for events over ranges
+//! reaching back to the genesis of the subnet, which most nodes either reject or answer
+//! slowly. The [EventIndexer] tails the logs of the events we query into an embedded
+//! database, remembering the height each of them is indexed up to, so those queries only
+//! have to ask the node for the blocks produced since.
+//!
+//! The database is only kept open while it is being read or written, so the process
+//! indexing the events and the ones querying them can share it; if it can't be opened,
+//! the queries go to the node instead.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use ethers::providers::Middleware;
+use ethers::types::{Address, BlockNumber, Filter, Log, ValueOrArray, H256};
+use ipc_api::subnet_id::SubnetID;
+use sled::transaction::ConflictableTransactionError;
+use sled::Transactional;
+
+/// Logs of the indexed events, keyed by source, block number and log index.
+const LOGS_TREE: &str = "logs";
+/// The range of blocks indexed for each source.
+const HEIGHTS_TREE: &str = "heights";
+
+/// The logs of one event emitted by one contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventSource {
+    pub address: Address,
+    /// The signature of the event.
+    pub topic0: H256,
+}
+
+impl EventSource {
+    fn key(&self) -> Vec<u8> {
+        [self.address.as_bytes(), self.topic0.as_bytes()].concat()
+    }
+
+    /// The source of the logs a filter matches, if it is for one event of one contract.
+    fn of_filter(filter: &Filter) -> Option<Self> {
+        let address = match &filter.address {
+            Some(ValueOrArray::Value(address)) => *address,
+            _ => return None,
+        };
+        let topic0 = match &filter.topics[0] {
+            Some(ValueOrArray::Value(Some(topic0))) => *topic0,
+            _ => return None,
+        };
+        Some(Self { address, topic0 })
+    }
+}
+
+/// An embedded database of event logs.
+#[derive(Debug, Clone)]
+pub struct EventIndex {
+    path: PathBuf,
+}
+
+impl EventIndex {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The index of the events of a subnet, in its own directory under `root`.
+    pub fn for_subnet(root: impl AsRef<Path>, subnet: &SubnetID) -> Self {
+        let name = subnet.to_string().trim_matches('/').replace('/', "_");
+        Self::new(root.as_ref().join(name))
+    }
+
+    fn open(&self) -> anyhow::Result<sled::Db> {
+        sled::open(&self.path)
+            .with_context(|| format!("cannot open event index {}", self.path.to_string_lossy()))
+    }
+
+    /// The first and last block indexed for a source, if any.
+    pub fn indexed_range(&self, source: &EventSource) -> anyhow::Result<Option<(u64, u64)>> {
+        let db = self.open()?;
+        let heights = db.open_tree(HEIGHTS_TREE)?;
+        heights
+            .get(source.key())?
+            .map(|v| decode_range(&v))
+            .transpose()
+    }
+
+    /// Add the logs of a source emitted up to block `to`, which continue the indexed range,
+    /// or start it at block `from` if the source hasn't been indexed yet.
+    pub fn append(
+        &self,
+        source: &EventSource,
+        from: u64,
+        to: u64,
+        logs: &[Log],
+    ) -> anyhow::Result<()> {
+        let db = self.open()?;
+        let logs_tree = db.open_tree(LOGS_TREE)?;
+        let heights_tree = db.open_tree(HEIGHTS_TREE)?;
+
+        let key = source.key();
+        let mut entries = Vec::with_capacity(logs.len());
+        for log in logs.iter().filter(|l| !l.removed.unwrap_or_default()) {
+            let (Some(block), Some(index)) = (log.block_number, log.log_index) else {
+                return Err(anyhow!("log without position: {log:?}"));
+            };
+            let log_key = [
+                key.as_slice(),
+                &block.as_u64().to_be_bytes(),
+                &index.as_u64().to_be_bytes(),
+            ]
+            .concat();
+            entries.push((log_key, serde_json::to_vec(log)?));
+        }
+
+        (&logs_tree, &heights_tree)
+            .transaction(|(logs_tree, heights_tree)| {
+                let start = match heights_tree.get(&key)? {
+                    Some(v) => {
+                        let (start, end) = decode_range(&v).map_err(abort)?;
+                        if from != end + 1 {
+                            return Err(abort(anyhow!(
+                                "blocks {from}..={to} don't continue the indexed range {start}..={end}"
+                            )));
+                        }
+                        start
+                    }
+                    None => from,
+                };
+                for (k, v) in entries.iter() {
+                    logs_tree.insert(k.as_slice(), v.as_slice())?;
+                }
+                heights_tree.insert(key.as_slice(), encode_range(start, to).as_slice())?;
+                Ok(())
+            })
+            .map_err(|e| match e {
+                sled::transaction::TransactionError::Abort(e) => e,
+                sled::transaction::TransactionError::Storage(e) => e.into(),
+            })?;
+
+        db.flush()?;
+        Ok(())
+    }
+
+    /// Split a log query into the logs available in the index, and a query for the rest
+    /// of the range, if any.
+    ///
+    /// Returns `None` if the index doesn't cover the start of the range.
+    pub fn lookup(&self, filter: &Filter) -> anyhow::Result<Option<(Vec<Log>, Option<Filter>)>> {
+        let Some(source) = EventSource::of_filter(filter) else {
+            return Ok(None);
+        };
+        let Some(from) = filter.get_from_block().map(|b| b.as_u64()) else {
+            return Ok(None);
+        };
+        // Anything but a block number, e.g. `latest`, is beyond the index.
+        let to = filter.get_to_block().map(|b| b.as_u64());
+
+        let db = self.open()?;
+        let heights = db.open_tree(HEIGHTS_TREE)?;
+        let Some((start, end)) = heights
+            .get(source.key())?
+            .map(|v| decode_range(&v))
+            .transpose()?
+        else {
+            return Ok(None);
+        };
+        if from < start || from > end {
+            return Ok(None);
+        }
+
+        let last = to.map_or(end, |to| to.min(end));
+        let key = source.key();
+        let lower = [key.as_slice(), &from.to_be_bytes()].concat();
+        let upper = [key.as_slice(), &(last + 1).to_be_bytes()].concat();
+
+        let mut logs = Vec::new();
+        for entry in db.open_tree(LOGS_TREE)?.range(lower..upper) {
+            let (_, v) = entry?;
+            let log: Log = serde_json::from_slice(&v)?;
+            if matches_topics(filter, &log) {
+                logs.push(log);
+            }
+        }
+
+        let rest = match to {
+            Some(to) if to <= end => None,
+            _ => Some(
+                filter
+                    .clone()
+                    .from_block(BlockNumber::Number((end + 1).into())),
+            ),
+        };
+
+        Ok(Some((logs, rest)))
+    }
+}
+
+fn encode_range(start: u64, end: u64) -> Vec<u8> {
+    [start.to_be_bytes(), end.to_be_bytes()].concat()
+}
+
+fn decode_range(bytes: &[u8]) -> anyhow::Result<(u64, u64)> {
+    let (start, end) = (bytes.get(..8), bytes.get(8..16));
+    match (start, end) {
+        (Some(start), Some(end)) => Ok((
+            u64::from_be_bytes(start.try_into()?),
+            u64::from_be_bytes(end.try_into()?),
+        )),
+        _ => Err(anyhow!("invalid indexed range")),
+    }
+}
+
+fn abort(e: anyhow::Error) -> ConflictableTransactionError<anyhow::Error> {
+    ConflictableTransactionError::Abort(e)
+}
+
+/// Check the indexed topics of a log against the ones in the filter; the event signature
+/// was already matched by the source.
+fn matches_topics(filter: &Filter, log: &Log) -> bool {
+    filter.topics.iter().enumerate().skip(1).all(|(i, topic)| {
+        let topics = match topic {
+            None => return true,
+            Some(ValueOrArray::Value(topic)) => vec![*topic],
+            Some(ValueOrArray::Array(topics)) => topics.clone(),
+        };
+        topics
+            .iter()
+            .any(|t| t.is_none() || log.topics.get(i) == t.as_ref())
+    })
+}
+
+/// Tails the logs of a set of sources into an [EventIndex].
+pub struct EventIndexer<M> {
+    client: Arc<M>,
+    index: EventIndex,
+    /// The sources with the block to start indexing them from.
+    sources: Vec<(EventSource, u64)>,
+    /// The number of blocks asked from the node in one query.
+    batch_size: u64,
+    /// Blocks are only indexed once this many blocks were built on top of them,
+    /// so the index doesn't have to deal with reorgs.
+    confirmations: u64,
+}
+
+impl<M: Middleware> EventIndexer<M> {
+    pub fn new(client: Arc<M>, index: EventIndex, batch_size: u64, confirmations: u64) -> Self {
+        Self {
+            client,
+            index,
+            sources: Vec::new(),
+            batch_size: batch_size.max(1),
+            confirmations,
+        }
+    }
+
+    /// Index the logs of a source, starting from block `start` unless it was already indexed.
+    pub fn with_source(mut self, source: EventSource, start: u64) -> Self {
+        self.sources.push((source, start));
+        self
+    }
+
+    /// Index the logs of every source up to the latest confirmed block, returning its height.
+    pub async fn sync(&self) -> anyhow::Result<u64> {
+        let head = self
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| anyhow!("cannot get block number: {e}"))?
+            .as_u64();
+        let target = head.saturating_sub(self.confirmations);
+
+        for (source, start) in self.sources.iter() {
+            let mut from = match self.index.indexed_range(source)? {
+                Some((_, end)) => end + 1,
+                None => *start,
+            };
+            while from <= target {
+                let to = target.min(from + self.batch_size - 1);
+                let filter = Filter::new()
+                    .address(source.address)
+                    .topic0(source.topic0)
+                    .from_block(from)
+                    .to_block(to);
+                let logs = self
+                    .client
+                    .get_logs(&filter)
+                    .await
+                    .map_err(|e| anyhow!("cannot get logs in blocks {from}..={to}: {e}"))?;
+                self.index.append(source, from, to, &logs)?;
+                log::debug!(
+                    "indexed {} logs of {:?} in blocks {from}..={to}",
+                    logs.len(),
+                    source.address
+                );
+                from = to + 1;
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, Filter, Log, H256, U256, U64};
+
+    use super::{EventIndex, EventSource};
+
+    fn log(source: &EventSource, block: u64, topic1: H256) -> Log {
+        Log {
+            address: source.address,
+            topics: vec![source.topic0, topic1],
+            block_number: Some(U64::from(block)),
+            log_index: Some(U256::zero()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lookup_indexed_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = EventIndex::new(dir.path().join("index"));
+        let source = EventSource {
+            address: Address::repeat_byte(1),
+            topic0: H256::repeat_byte(2),
+        };
+        let (a, b) = (H256::repeat_byte(3), H256::repeat_byte(4));
+
+        index
+            .append(&source, 10, 19, &[log(&source, 12, a), log(&source, 15, b)])
+            .unwrap();
+        index
+            .append(&source, 20, 29, &[log(&source, 25, a)])
+            .unwrap();
+        // Gaps are rejected.
+        assert!(index.append(&source, 31, 40, &[]).is_err());
+        assert_eq!(index.indexed_range(&source).unwrap(), Some((10, 29)));
+
+        let filter = Filter::new().address(source.address).topic0(source.topic0);
+
+        // Not covered.
+        assert!(index
+            .lookup(&filter.clone().from_block(5))
+            .unwrap()
+            .is_none());
+
+        // Fully covered.
+        let (logs, rest) = index
+            .lookup(&filter.clone().from_block(12).to_block(20))
+            .unwrap()
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(rest.is_none());
+
+        // Covered up to the last indexed block, filtering on topic1.
+        let (logs, rest) = index
+            .lookup(&filter.clone().topic1(a).from_block(10))
+            .unwrap()
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(rest.unwrap().get_from_block(), Some(U64::from(30)));
+    }
+}
@@ -6,12 +6,12 @@ use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
-use ethers_contract::{ContractError, EthLogDecode, LogMeta};
+use ethers_contract::{ContractError, EthEvent, EthLogDecode, LogMeta};
 use ipc_actors_abis::{
     checkpointing_facet, gateway_getter_facet, gateway_manager_facet, gateway_messenger_facet,
     lib_gateway, lib_quorum, lib_staking_change_log, register_subnet_facet,
     subnet_actor_checkpointing_facet, subnet_actor_getter_facet, subnet_actor_manager_facet,
-    subnet_actor_reward_facet,
+    subnet_actor_reward_facet, subnet_getter_facet,
 };
 use ipc_api::evm::{fil_to_eth_amount, payload_to_evm_address, subnet_id_to_evm_addresses};
 use ipc_api::validator::from_contract_validators;
@@ -22,12 +22,20 @@ use std::net::{IpAddr, SocketAddr};
 use ipc_api::subnet::{PermissionMode, SupplyKind, SupplySource};
 use ipc_api::{eth_to_fil_amount, ethers_address_to_fil_address};
 
-use crate::config::subnet::SubnetConfig;
+use super::check::{verify, RemoteSubnet, SubnetCheck};
+use super::client::{SharedClientState, SubnetClient};
+use super::fees::{FeeOracle, FeeOverride};
+use super::index::{EventIndex, EventIndexer, EventSource};
+use super::multicall::{MulticallSupport, ReadBatch};
+use super::nonce::{NonceMiddleware, NonceTracker};
+use super::resubmit::{send_with_resubmission, send_with_resubmission_while};
+use super::subscribe::WsEndpoint;
+use crate::config::subnet::{FeeCaps, ResubmitPolicy, SubnetConfig};
 use crate::config::Subnet;
 use crate::lotus::message::ipc::SubnetInfo;
 use crate::manager::subnet::{
-    BottomUpCheckpointRelayer, GetBlockHashResult, SubnetGenesisInfo, TopDownFinalityQuery,
-    TopDownQueryPayload,
+    BottomUpCheckpointRelayer, GetBlockHashResult, SubmitterUnavailable, SubnetGenesisInfo,
+    SubnetStatus, TopDownFinalityQuery, TopDownQueryPayload,
 };
 use crate::manager::{EthManager, SubnetManager};
 use anyhow::{anyhow, Context, Result};
@@ -37,21 +45,26 @@ use ethers::prelude::k256::ecdsa::SigningKey;
 use ethers::prelude::{Signer, SignerMiddleware};
 use ethers::providers::{Authorization, Http, Middleware, Provider};
 use ethers::signers::{LocalWallet, Wallet};
-use ethers::types::{BlockId, Eip1559TransactionRequest, ValueOrArray, I256, U256};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockId, BlockNumber, Eip1559TransactionRequest, ValueOrArray, U256};
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::{address::Address, econ::TokenAmount};
 use ipc_api::checkpoint::{
-    BottomUpCheckpoint, BottomUpCheckpointBundle, QuorumReachedEvent, Signature,
+    BottomUpCheckpoint, BottomUpCheckpointBundle, CheckpointQuorum, CheckpointSubmission,
+    QuorumReachedEvent, Signature,
 };
 use ipc_api::cross::IpcEnvelope;
-use ipc_api::staking::{StakingChangeRequest, ValidatorInfo, ValidatorStakingInfo};
+use ipc_api::staking::{
+    ConfigurationNumber, StakingChangeRequest, ValidatorInfo, ValidatorStakingInfo,
+};
 use ipc_api::subnet::ConstructParams;
 use ipc_api::subnet_id::SubnetID;
 use ipc_wallet::{EthKeyAddress, EvmKeyStore, PersistentKeyStore};
 use num_traits::ToPrimitive;
 use std::result;
 
-pub type DefaultSignerMiddleware = SignerMiddleware<Provider<Http>, Wallet<SigningKey>>;
+pub type DefaultSignerMiddleware =
+    NonceMiddleware<SignerMiddleware<Provider<SubnetClient>, Wallet<SigningKey>>>;
 
 /// Default polling time used by the Ethers provider to check for pending
 /// transactions and events. Default is 7, and for our child subnets we
@@ -66,12 +79,32 @@ const ETH_PROVIDER_POLLING_TIME: Duration = Duration::from_secs(1);
 /// roots (like Calibration and mainnet).
 const TRANSACTION_RECEIPT_RETRIES: usize = 200;
 
+/// The number of blocks whose logs are fetched in one query when indexing events.
+const EVENT_INDEX_BATCH_SIZE: u64 = 2000;
+
 /// The majority vote percentage for checkpoint submission when creating a subnet.
 const SUBNET_MAJORITY_PERCENTAGE: u8 = 67;
 
 pub struct EthSubnetManager {
     keystore: Option<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>>,
     ipc_contract_info: IPCContractInfo,
+    /// The next nonce of the signers, shared with other managers of the same subnet.
+    nonces: NonceTracker,
+    /// Estimates the fees of the transactions sent by the manager.
+    fees: FeeOracle,
+    /// Resubmit transactions with higher fees if they aren't included in time.
+    resubmit: Option<ResubmitPolicy>,
+    /// Whether reads can be batched with Multicall3, shared with other managers of the same subnet.
+    multicall: MulticallSupport,
+    /// Local index of the contract events, if one is configured.
+    index: Option<EventIndex>,
+    /// Endpoint for subscriptions, blocks and events are polled if not set.
+    ws: Option<WsEndpoint>,
+    /// Whether the manager was built without a signer on purpose, see [Subnet::read_only].
+    read_only: bool,
+    /// The subnet to verify the provider against before sending transactions, with the
+    /// result shared with other managers of the same subnet; not verified if not set.
+    check: Option<(SubnetID, SubnetCheck)>,
 }
 
 /// Keep track of the on chain information for the subnet manager
@@ -79,7 +112,7 @@ struct IPCContractInfo {
     gateway_addr: ethers::types::Address,
     registry_addr: ethers::types::Address,
     chain_id: u64,
-    provider: Provider<Http>,
+    provider: Provider<SubnetClient>,
 }
 
 #[async_trait]
@@ -111,6 +144,20 @@ impl TopDownFinalityQuery for EthSubnetManager {
         Ok(block.as_u64() as ChainEpoch)
     }
 
+    async fn finalized_chain_head_height(&self) -> Result<ChainEpoch> {
+        let block = self
+            .ipc_contract_info
+            .provider
+            .get_block(BlockNumber::Finalized)
+            .await
+            .context("cannot get evm finalized block")?
+            .ok_or_else(|| anyhow!("no finalized block"))?;
+        let number = block
+            .number
+            .ok_or_else(|| anyhow!("finalized block has no number"))?;
+        Ok(number.as_u64() as ChainEpoch)
+    }
+
     async fn get_top_down_msgs(
         &self,
         subnet_id: &SubnetID,
@@ -137,7 +184,9 @@ impl TopDownFinalityQuery for EthSubnetManager {
 
         let mut messages = vec![];
         let mut hash = None;
-        for (event, meta) in query_with_meta(ev, gateway_contract.client()).await? {
+        for (event, meta) in
+            query_with_meta(ev, gateway_contract.client(), self.index.as_ref()).await?
+        {
             if let Some(h) = hash {
                 if h != meta.block_hash {
                     return Err(anyhow!("block hash not equal"));
@@ -199,7 +248,7 @@ impl TopDownFinalityQuery for EthSubnetManager {
 
         let mut changes = vec![];
         let mut hash = None;
-        for (event, meta) in query_with_meta(ev, contract.client()).await? {
+        for (event, meta) in query_with_meta(ev, contract.client(), self.index.as_ref()).await? {
             if let Some(h) = hash {
                 if h != meta.block_hash {
                     return Err(anyhow!("block hash not equal"));
@@ -231,6 +280,24 @@ impl TopDownFinalityQuery for EthSubnetManager {
         let finality = contract.get_latest_parent_finality().call().await?;
         Ok(finality.height.as_u64() as ChainEpoch)
     }
+
+    async fn last_bottom_up_checkpoint_height_at(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+    ) -> Result<ChainEpoch> {
+        let address = contract_address_from_subnet(subnet_id)?;
+        let contract = subnet_actor_getter_facet::SubnetActorGetterFacet::new(
+            address,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        let height = contract
+            .last_bottom_up_checkpoint_height()
+            .block(epoch as u64)
+            .call()
+            .await?;
+        Ok(height.as_u64() as ChainEpoch)
+    }
 }
 
 #[async_trait]
@@ -268,21 +335,23 @@ impl SubnetManager for EthSubnetManager {
 
         log::info!("creating subnet on evm with params: {params:?}");
 
-        let signer = self.get_signer(&from)?;
+        let signer = self.checked_signer(&from).await?;
         let signer = Arc::new(signer);
         let registry_contract = register_subnet_facet::RegisterSubnetFacet::new(
             self.ipc_contract_info.registry_addr,
             signer.clone(),
         );
 
-        let call = call_with_premium_estimation(signer, registry_contract.new_subnet_actor(params))
-            .await?;
-        // TODO: Edit call to get estimate premium
-        let pending_tx = call.send().await?;
+        let call = call_with_premium_estimation(
+            &self.fees,
+            signer.clone(),
+            registry_contract.new_subnet_actor(params),
+        )
+        .await?;
         // We need the retry to parse the deployment event. At the time of this writing, it's a bug
         // in current FEVM that without the retries, events are not picked up.
         // See https://github.com/filecoin-project/community/discussions/638 for more info and updates.
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
+        let receipt = self.send_and_wait(signer, call).await?;
         match receipt {
             Some(r) => {
                 for log in r.logs {
@@ -327,19 +396,18 @@ impl SubnetManager for EthSubnetManager {
             "interacting with evm subnet contract: {address:} with collateral: {collateral:}"
         );
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
         let mut txn = contract.join(ethers::types::Bytes::from(pub_key));
         txn.tx.set_value(collateral);
-        let txn = call_with_premium_estimation(signer, txn).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer.clone(), txn).await?;
 
         // Use the pending state to get the nonce because there could have been a pre-fund. Best would be to use this for everything.
         let txn = txn.block(BlockId::Number(ethers::types::BlockNumber::Pending));
 
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
+        let receipt = self.send_and_wait(signer, txn).await?;
         block_number_from_receipt(receipt)
     }
 
@@ -352,13 +420,13 @@ impl SubnetManager for EthSubnetManager {
         let address = contract_address_from_subnet(&subnet)?;
         log::info!("interacting with evm subnet contract: {address:} with balance: {balance:}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
         let mut txn = contract.pre_fund();
         txn.tx.set_value(balance);
-        let txn = call_with_premium_estimation(signer, txn).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer.clone(), txn).await?;
 
         txn.send().await?;
         Ok(())
@@ -378,11 +446,11 @@ impl SubnetManager for EthSubnetManager {
             .to_u128()
             .ok_or_else(|| anyhow!("invalid pre-release amount"))?;
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
-        call_with_premium_estimation(signer, contract.pre_release(amount.into()))
+        call_with_premium_estimation(&self.fees, signer, contract.pre_release(amount.into()))
             .await?
             .send()
             .await?
@@ -402,13 +470,13 @@ impl SubnetManager for EthSubnetManager {
             "interacting with evm subnet contract: {address:} with collateral: {collateral:}"
         );
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
         let mut txn = contract.stake();
         txn.tx.set_value(collateral);
-        let txn = call_with_premium_estimation(signer, txn).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer, txn).await?;
 
         txn.send().await?.await?;
 
@@ -431,11 +499,13 @@ impl SubnetManager for EthSubnetManager {
             "interacting with evm subnet contract: {address:} with collateral: {collateral:}"
         );
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
-        let txn = call_with_premium_estimation(signer, contract.unstake(collateral.into())).await?;
+        let txn =
+            call_with_premium_estimation(&self.fees, signer, contract.unstake(collateral.into()))
+                .await?;
         txn.send().await?.await?;
 
         Ok(())
@@ -445,11 +515,11 @@ impl SubnetManager for EthSubnetManager {
         let address = contract_address_from_subnet(&subnet)?;
         log::info!("leaving evm subnet: {subnet:} at contract: {address:}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
-        call_with_premium_estimation(signer, contract.leave())
+        call_with_premium_estimation(&self.fees, signer, contract.leave())
             .await?
             .send()
             .await?
@@ -462,11 +532,11 @@ impl SubnetManager for EthSubnetManager {
         let address = contract_address_from_subnet(&subnet)?;
         log::info!("kill evm subnet: {subnet:} at contract: {address:}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
-        call_with_premium_estimation(signer, contract.kill())
+        call_with_premium_estimation(&self.fees, signer, contract.kill())
             .await?
             .send()
             .await?
@@ -503,11 +573,11 @@ impl SubnetManager for EthSubnetManager {
         let address = contract_address_from_subnet(&subnet)?;
         log::info!("claim collateral evm subnet: {subnet:} at contract: {address:}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let contract =
             subnet_actor_reward_facet::SubnetActorRewardFacet::new(address, signer.clone());
 
-        call_with_premium_estimation(signer, contract.claim())
+        call_with_premium_estimation(&self.fees, signer, contract.claim())
             .await?
             .send()
             .await?
@@ -536,7 +606,7 @@ impl SubnetManager for EthSubnetManager {
         let evm_subnet_id = gateway_manager_facet::SubnetID::try_from(&subnet)?;
         log::debug!("evm subnet id to fund: {evm_subnet_id:?}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let gateway_contract = gateway_manager_facet::GatewayManagerFacet::new(
             self.ipc_contract_info.gateway_addr,
             signer.clone(),
@@ -547,10 +617,9 @@ impl SubnetManager for EthSubnetManager {
             gateway_manager_facet::FvmAddress::try_from(to)?,
         );
         txn.tx.set_value(value);
-        let txn = call_with_premium_estimation(signer, txn).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer, txn).await?;
 
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
+        let receipt = self.send_and_wait(signer, txn).await?;
         block_number_from_receipt(receipt)
     }
 
@@ -566,7 +635,7 @@ impl SubnetManager for EthSubnetManager {
         let value = fil_amount_to_eth_amount(&amount)?;
         let evm_subnet_id = gateway_manager_facet::SubnetID::try_from(&subnet)?;
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let gateway_contract = gateway_manager_facet::GatewayManagerFacet::new(
             self.ipc_contract_info.gateway_addr,
             signer.clone(),
@@ -577,10 +646,9 @@ impl SubnetManager for EthSubnetManager {
             gateway_manager_facet::FvmAddress::try_from(to)?,
             value,
         );
-        let txn = call_with_premium_estimation(signer, txn).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer.clone(), txn).await?;
 
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
+        let receipt = self.send_and_wait(signer, txn).await?;
         block_number_from_receipt(receipt)
     }
 
@@ -600,17 +668,16 @@ impl SubnetManager for EthSubnetManager {
 
         log::info!("release with evm gateway contract: {gateway_addr:} with value: {value:}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let gateway_contract = gateway_manager_facet::GatewayManagerFacet::new(
             self.ipc_contract_info.gateway_addr,
             signer.clone(),
         );
         let mut txn = gateway_contract.release(gateway_manager_facet::FvmAddress::try_from(to)?);
         txn.tx.set_value(value);
-        let txn = call_with_premium_estimation(signer, txn).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer.clone(), txn).await?;
 
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
+        let receipt = self.send_and_wait(signer, txn).await?;
         block_number_from_receipt(receipt)
     }
 
@@ -633,7 +700,7 @@ impl SubnetManager for EthSubnetManager {
 
         log::info!("propagate postbox evm gateway contract: {gateway_addr:} with message key: {postbox_msg_key:?}");
 
-        let signer = Arc::new(self.get_signer(&from)?);
+        let signer = Arc::new(self.checked_signer(&from).await?);
         let gateway_contract = gateway_messenger_facet::GatewayMessengerFacet::new(
             self.ipc_contract_info.gateway_addr,
             signer.clone(),
@@ -642,23 +709,56 @@ impl SubnetManager for EthSubnetManager {
         let mut key = [0u8; 32];
         key.copy_from_slice(&postbox_msg_key);
 
-        call_with_premium_estimation(signer, gateway_contract.propagate(key))
+        let txn = call_with_premium_estimation(
+            &self.fees,
+            signer.clone(),
+            gateway_contract.propagate(key),
+        )
+        .await?;
+        self.send_and_wait(signer, txn)
             .await?
-            .send()
-            .await?;
+            .ok_or_else(|| anyhow!("txn sent to network, but receipt cannot be obtained"))?;
 
         Ok(())
     }
 
+    async fn postbox_msg(
+        &self,
+        gateway_addr: Address,
+        postbox_msg_key: Vec<u8>,
+    ) -> Result<Option<IpcEnvelope>> {
+        let key: [u8; 32] = postbox_msg_key.try_into().map_err(|k: Vec<u8>| {
+            anyhow!(
+                "invalid message cid length, expect 32 but found {}",
+                k.len()
+            )
+        })?;
+
+        self.ensure_same_gateway(&gateway_addr)?;
+
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        let msg = gateway_contract.postbox(key).call().await?;
+
+        // Missing entries read as a zeroed envelope, which no message can be, as their
+        // destination always has a root network.
+        if msg.to.subnet_id.root == 0 {
+            return Ok(None);
+        }
+        Ok(Some(IpcEnvelope::try_from(msg)?))
+    }
+
     /// Send value between two addresses in a subnet
     async fn send_value(&self, from: Address, to: Address, amount: TokenAmount) -> Result<()> {
-        let signer = Arc::new(self.get_signer(&from)?);
-        let (fee, fee_cap) = premium_estimation(signer.clone()).await?;
+        let signer = Arc::new(self.checked_signer(&from).await?);
+        let fees = self.fees.estimate(signer.as_ref()).await?;
         let tx = Eip1559TransactionRequest::new()
             .to(payload_to_evm_address(to.payload())?)
             .value(fil_to_eth_amount(&amount)?)
-            .max_priority_fee_per_gas(fee)
-            .max_fee_per_gas(fee_cap);
+            .max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+            .max_fee_per_gas(fees.max_fee_per_gas);
 
         let tx_pending = signer.send_transaction(tx, None).await?;
 
@@ -712,6 +812,278 @@ impl SubnetManager for EthSubnetManager {
         Ok(commit_sha)
     }
 
+    async fn get_registry_gateway(&self, registry_addr: &Address) -> Result<Address> {
+        let registry_addr = payload_to_evm_address(registry_addr.payload())?;
+        let registry_contract = subnet_getter_facet::SubnetGetterFacet::new(
+            registry_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let gateway_addr = registry_contract.get_gateway().call().await.map_err(|e| {
+            anyhow!("cannot get gateway from registry {registry_addr:?} due to: {e:}")
+        })?;
+        log::debug!("registry {registry_addr:?} points to gateway {gateway_addr:?}");
+
+        ethers_address_to_fil_address(&gateway_addr)
+    }
+
+    async fn get_subnet_gateway(&self, subnet: &SubnetID) -> Result<Address> {
+        let address = contract_address_from_subnet(subnet)?;
+        let contract = subnet_actor_getter_facet::SubnetActorGetterFacet::new(
+            address,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let gateway_addr =
+            contract.ipc_gateway_addr().call().await.map_err(|e| {
+                anyhow!("cannot get gateway of subnet actor {address:?} due to: {e:}")
+            })?;
+
+        ethers_address_to_fil_address(&gateway_addr)
+    }
+
+    async fn get_top_down_nonce(&self, subnet: &SubnetID) -> Result<u64> {
+        EthManager::get_applied_top_down_nonce(self, subnet).await
+    }
+
+    async fn get_applied_bottom_up_nonce(&self, subnet: &SubnetID) -> Result<u64> {
+        let route = subnet_id_to_evm_addresses(subnet)?;
+        let evm_subnet_id = gateway_getter_facet::SubnetID {
+            root: subnet.root_id(),
+            route,
+        };
+
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let (exists, nonce) = gateway_contract
+            .get_applied_bottom_up_nonce(evm_subnet_id)
+            .call()
+            .await
+            .map_err(|e| anyhow!("cannot get applied bottom up nonce due to: {e:}"))?;
+
+        if !exists {
+            Err(anyhow!("subnet {:?} does not exists", subnet))
+        } else {
+            Ok(nonce)
+        }
+    }
+
+    async fn applied_top_down_nonce(&self) -> Result<u64> {
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        Ok(gateway_contract.applied_top_down_nonce().call().await?)
+    }
+
+    async fn bottom_up_nonce(&self) -> Result<u64> {
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        Ok(gateway_contract.bottom_up_nonce().call().await?)
+    }
+
+    async fn child_cross_msg_nonces(&self, subnet: &SubnetID) -> Result<(u64, u64)> {
+        let evm_subnet_id = gateway_getter_facet::SubnetID {
+            root: subnet.root_id(),
+            route: subnet_id_to_evm_addresses(subnet)?,
+        };
+
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let ((exists, top_down), (_, bottom_up)): ((bool, u64), (bool, u64)) = self
+            .read_batch()?
+            .add(gateway_contract.get_top_down_nonce(evm_subnet_id.clone()))
+            .add(gateway_contract.get_applied_bottom_up_nonce(evm_subnet_id))
+            .call()
+            .await
+            .map_err(|e| anyhow!("cannot get cross-net message nonces due to: {e:}"))?;
+
+        if !exists {
+            Err(anyhow!("subnet {:?} does not exists", subnet))
+        } else {
+            Ok((top_down, bottom_up))
+        }
+    }
+
+    async fn check_subnet(&self) -> Result<()> {
+        let Some((subnet, check)) = &self.check else {
+            return Ok(());
+        };
+        let gateway = self.ipc_contract_info.gateway_addr;
+        check
+            .ensure(|| async {
+                let provider = &self.ipc_contract_info.provider;
+                let chain_id = provider.get_chainid().await?.as_u64();
+                let gateway_code = provider.get_code(gateway, None).await?;
+                let network_name = if gateway_code.is_empty() {
+                    None
+                } else {
+                    let contract = gateway_getter_facet::GatewayGetterFacet::new(
+                        gateway,
+                        Arc::new(provider.clone()),
+                    );
+                    Some(SubnetID::try_from(
+                        contract.get_network_name().call().await?,
+                    )?)
+                };
+                let remote = RemoteSubnet {
+                    chain_id,
+                    gateway_code,
+                    network_name,
+                };
+                verify(subnet, gateway, &remote)
+            })
+            .await
+    }
+
+    fn subscribe_new_heads(&self) -> Option<tokio::sync::mpsc::Receiver<ChainEpoch>> {
+        self.ws.as_ref().map(|ws| ws.subscribe_new_heads())
+    }
+
+    async fn index_events(
+        &self,
+        children: &[SubnetID],
+        from: ChainEpoch,
+        confirmations: u64,
+    ) -> Result<ChainEpoch> {
+        let index = self
+            .index
+            .clone()
+            .ok_or_else(|| anyhow!("no event index configured, set `index_path` in the config"))?;
+        let gateway = self.ipc_contract_info.gateway_addr;
+
+        let mut indexer = EventIndexer::new(
+            Arc::new(self.ipc_contract_info.provider.clone()),
+            index,
+            EVENT_INDEX_BATCH_SIZE,
+            confirmations,
+        )
+        .with_source(
+            EventSource {
+                address: gateway,
+                topic0: lib_gateway::NewTopDownMessageFilter::signature(),
+            },
+            from as u64,
+        )
+        .with_source(
+            EventSource {
+                address: gateway,
+                topic0: lib_quorum::QuorumReachedFilter::signature(),
+            },
+            from as u64,
+        );
+
+        for child in children {
+            let genesis = match self.genesis_epoch(child).await {
+                Ok(genesis) => genesis,
+                Err(e) => {
+                    log::warn!("not indexing the staking changes of {child}: {e}");
+                    continue;
+                }
+            };
+            indexer = indexer.with_source(
+                EventSource {
+                    address: contract_address_from_subnet(child)?,
+                    topic0: lib_staking_change_log::NewStakingChangeRequestFilter::signature(),
+                },
+                genesis as u64,
+            );
+        }
+
+        Ok(indexer.sync().await? as ChainEpoch)
+    }
+
+    async fn own_cross_msg_nonces(&self) -> Result<(u64, u64)> {
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        self.read_batch()?
+            .add(gateway_contract.applied_top_down_nonce())
+            .add(gateway_contract.bottom_up_nonce())
+            .call()
+            .await
+    }
+
+    async fn subnet_status(&self, subnet: &SubnetID) -> Result<SubnetStatus> {
+        let address = contract_address_from_subnet(subnet)?;
+        let contract = subnet_actor_getter_facet::SubnetActorGetterFacet::new(
+            address,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let (killed, bootstrapped): (bool, bool) = self
+            .read_batch()?
+            .add(contract.killed())
+            .add(contract.bootstrapped())
+            .call()
+            .await?;
+
+        let status = if killed {
+            SubnetStatus::Killed
+        } else if bootstrapped {
+            SubnetStatus::Active
+        } else {
+            SubnetStatus::Created
+        };
+        Ok(status)
+    }
+
+    async fn pending_staking_changes(
+        &self,
+        subnet: &SubnetID,
+    ) -> Result<Vec<StakingChangeRequest>> {
+        let address = contract_address_from_subnet(subnet)?;
+        let getter = subnet_actor_getter_facet::SubnetActorGetterFacet::new(
+            address,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        // changes in [start, next) are recorded but not yet confirmed by a checkpoint
+        let (next, start) = getter.get_configuration_numbers().call().await?;
+        if start >= next {
+            return Ok(vec![]);
+        }
+
+        let contract = subnet_actor_manager_facet::SubnetActorManagerFacet::new(
+            address,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        let ev = contract
+            .event::<lib_staking_change_log::NewStakingChangeRequestFilter>()
+            .from_block(self.genesis_epoch(subnet).await? as u64)
+            .address(ValueOrArray::Value(contract.address()));
+
+        let mut changes = vec![];
+        for (event, _meta) in query_with_meta(ev, contract.client(), self.index.as_ref()).await? {
+            if (start..next).contains(&event.configuration_number) {
+                changes.push(StakingChangeRequest::try_from(event)?);
+            }
+        }
+        changes.sort_by_key(|c| c.configuration_number);
+
+        Ok(changes)
+    }
+
+    async fn current_configuration_number(&self) -> Result<ConfigurationNumber> {
+        let gateway_contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        Ok(gateway_contract
+            .get_current_configuration_number()
+            .call()
+            .await?)
+    }
+
     async fn get_genesis_info(&self, subnet: &SubnetID) -> Result<SubnetGenesisInfo> {
         let address = contract_address_from_subnet(subnet)?;
         let contract = subnet_actor_getter_facet::SubnetActorGetterFacet::new(
@@ -719,22 +1091,37 @@ impl SubnetManager for EthSubnetManager {
             Arc::new(self.ipc_contract_info.provider.clone()),
         );
 
-        let genesis_balances = contract.genesis_balances().await?;
-        let bottom_up_checkpoint_period = contract.bottom_up_check_period().call().await?.as_u64();
+        let (
+            genesis_balances,
+            bottom_up_checkpoint_period,
+            active_validators_limit,
+            majority_percentage,
+            min_collateral,
+            validators,
+        ) = self
+            .read_batch()?
+            .add(contract.genesis_balances())
+            .add(contract.bottom_up_check_period())
+            .add(contract.active_validators_limit())
+            .add(contract.majority_percentage())
+            .add(contract.min_activation_collateral())
+            .add(contract.genesis_validators())
+            .call::<GenesisReads>()
+            .await?;
 
         Ok(SubnetGenesisInfo {
             // Active validators limit set for the child subnet.
-            active_validators_limit: contract.active_validators_limit().call().await?,
+            active_validators_limit,
             // Bottom-up checkpoint period set in the subnet actor.
-            bottom_up_checkpoint_period,
+            bottom_up_checkpoint_period: bottom_up_checkpoint_period.as_u64(),
             // Genesis epoch when the subnet was bootstrapped in the parent.
             genesis_epoch: self.genesis_epoch(subnet).await?,
             // Majority percentage of
-            majority_percentage: contract.majority_percentage().call().await?,
+            majority_percentage,
             // Minimum collateral required for subnets to register into the subnet
-            min_collateral: eth_to_fil_amount(&contract.min_activation_collateral().call().await?)?,
+            min_collateral: eth_to_fil_amount(&min_collateral)?,
             // Custom message fee that the child subnet wants to set for cross-net messages
-            validators: from_contract_validators(contract.genesis_validators().call().await?)?,
+            validators: from_contract_validators(validators)?,
             genesis_balances: into_genesis_balance_map(genesis_balances.0, genesis_balances.1)?,
             // TODO: fixme https://github.com/consensus-shipyard/ipc-monorepo/issues/496
             permission_mode: PermissionMode::Collateral,
@@ -757,11 +1144,11 @@ impl SubnetManager for EthSubnetManager {
             return Err(anyhow!("wrong format for bootstrap endpoint"));
         }
 
-        let signer = Arc::new(self.get_signer(from)?);
+        let signer = Arc::new(self.checked_signer(from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
-        call_with_premium_estimation(signer, contract.add_bootstrap_node(endpoint))
+        call_with_premium_estimation(&self.fees, signer, contract.add_bootstrap_node(endpoint))
             .await?
             .send()
             .await?
@@ -791,9 +1178,13 @@ impl SubnetManager for EthSubnetManager {
         );
         let validator = payload_to_evm_address(validator.payload())?;
 
-        let validator_info = contract.get_validator(validator).call().await?;
-        let is_active = contract.is_active_validator(validator).call().await?;
-        let is_waiting = contract.is_waiting_validator(validator).call().await?;
+        let (validator_info, is_active, is_waiting) = self
+            .read_batch()?
+            .add(contract.get_validator(validator))
+            .add(contract.is_active_validator(validator))
+            .add(contract.is_waiting_validator(validator))
+            .call()
+            .await?;
 
         Ok(ValidatorInfo {
             staking: ValidatorStakingInfo::try_from(validator_info)?,
@@ -813,7 +1204,7 @@ impl SubnetManager for EthSubnetManager {
         let address = contract_address_from_subnet(subnet)?;
         log::info!("interacting with evm subnet contract: {address:}");
 
-        let signer = Arc::new(self.get_signer(from)?);
+        let signer = Arc::new(self.checked_signer(from).await?);
         let contract =
             subnet_actor_manager_facet::SubnetActorManagerFacet::new(address, signer.clone());
 
@@ -838,9 +1229,8 @@ impl SubnetManager for EthSubnetManager {
         log::debug!("from address: {:?}", from);
 
         let call = contract.set_federated_power(addresses, pubkeys, power_u256);
-        let txn = call_with_premium_estimation(signer, call).await?;
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
+        let txn = call_with_premium_estimation(&self.fees, signer.clone(), call).await?;
+        let receipt = self.send_and_wait(signer, txn).await?;
         block_number_from_receipt(receipt)
     }
 }
@@ -951,7 +1341,7 @@ impl EthSubnetManager {
         gateway_addr: ethers::types::Address,
         registry_addr: ethers::types::Address,
         chain_id: u64,
-        provider: Provider<Http>,
+        provider: Provider<SubnetClient>,
         keystore: Option<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>>,
     ) -> Self {
         Self {
@@ -962,9 +1352,139 @@ impl EthSubnetManager {
                 chain_id,
                 provider,
             },
+            nonces: NonceTracker::default(),
+            fees: FeeOracle::default(),
+            resubmit: None,
+            multicall: MulticallSupport::default(),
+            index: None,
+            ws: None,
+            read_only: false,
+            check: None,
         }
     }
 
+    /// Share the nonces of the signers with other managers.
+    pub fn with_nonces(mut self, nonces: NonceTracker) -> Self {
+        self.nonces = nonces;
+        self
+    }
+
+    /// Share the Multicall3 availability of the subnet with other managers.
+    pub fn with_multicall(mut self, multicall: MulticallSupport) -> Self {
+        self.multicall = multicall;
+        self
+    }
+
+    /// Serve event queries from a local index where it covers them.
+    pub fn with_index(mut self, index: Option<EventIndex>) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Subscribe to new blocks and events through a WebSocket endpoint.
+    pub fn with_ws(mut self, ws: Option<WsEndpoint>) -> Self {
+        self.ws = ws;
+        self
+    }
+
+    /// Subscribe to the logs matching a filter, if the subnet has a WebSocket endpoint.
+    pub fn subscribe_logs(
+        &self,
+        filter: ethers::types::Filter,
+    ) -> Option<tokio::sync::mpsc::Receiver<ethers::types::Log>> {
+        self.ws.as_ref().map(|ws| ws.subscribe_logs(filter))
+    }
+
+    /// Verify the provider against the subnet before sending the first transaction.
+    pub fn with_check(mut self, subnet: SubnetID, check: SubnetCheck) -> Self {
+        self.check = Some((subnet, check));
+        self
+    }
+
+    /// Start a batch of read-only calls, sent in a single round trip where possible.
+    fn read_batch(&self) -> Result<ReadBatch<Provider<SubnetClient>>> {
+        ReadBatch::new(
+            Arc::new(self.ipc_contract_info.provider.clone()),
+            self.multicall.clone(),
+        )
+    }
+
+    /// Limit the estimated fees of the transactions.
+    pub fn with_fee_caps(mut self, caps: FeeCaps) -> Self {
+        self.fees = FeeOracle::new(caps);
+        self
+    }
+
+    /// Use the fees chosen by the user instead of the estimated ones.
+    pub fn with_fee_override(mut self, overrides: FeeOverride) -> Self {
+        self.fees = self.fees.with_override(overrides);
+        self
+    }
+
+    /// Resubmit the transactions with higher fees if they aren't included in time.
+    pub fn with_resubmit_policy(mut self, policy: Option<ResubmitPolicy>) -> Self {
+        self.resubmit = policy;
+        self
+    }
+
+    /// Send the transaction of a contract call and wait for its receipt,
+    /// resubmitting it with higher fees according to the policy of the subnet.
+    async fn send_and_wait<B, D, M>(
+        &self,
+        signer: Arc<DefaultSignerMiddleware>,
+        call: ethers_contract::FunctionCall<B, D, M>,
+    ) -> Result<Option<ethers::types::TransactionReceipt>>
+    where
+        B: Borrow<D>,
+        M: ethers::abi::Detokenize,
+    {
+        match &self.resubmit {
+            Some(policy) => {
+                send_with_resubmission(signer, call.tx, call.block, policy.clone()).await
+            }
+            None => {
+                let pending_tx = call.send().await?;
+                Ok(pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?)
+            }
+        }
+    }
+
+    /// Check that the sender can pay for the transaction at the fees it was given, failing
+    /// with [SubmitterUnavailable] if it can't.
+    async fn ensure_submitter_can_pay(
+        &self,
+        sender: &Address,
+        tx: &TypedTransaction,
+    ) -> Result<()> {
+        let provider = &self.ipc_contract_info.provider;
+        let from = payload_to_evm_address(sender.payload())?;
+
+        // Estimated without the fees, as the node would fail the estimate on the balance.
+        let mut request = Eip1559TransactionRequest::new().from(from);
+        if let Some(to) = tx.to() {
+            request = request.to(to.clone());
+        }
+        if let Some(data) = tx.data() {
+            request = request.data(data.clone());
+        }
+        let value = tx.value().copied().unwrap_or_default();
+        let gas = provider
+            .estimate_gas(&request.value(value).into(), None)
+            .await?;
+
+        let required = gas * tx.gas_price().unwrap_or_default() + value;
+        let balance = provider.get_balance(from, None).await?;
+        if balance < required {
+            return Err(SubmitterUnavailable::InsufficientFunds {
+                submitter: *sender,
+                balance: eth_to_fil_amount(&balance)?,
+                required: eth_to_fil_amount(&required)?,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
     pub fn ensure_same_gateway(&self, gateway: &Address) -> Result<()> {
         let evm_gateway_addr = payload_to_evm_address(gateway.payload())?;
         if evm_gateway_addr != self.ipc_contract_info.gateway_addr {
@@ -975,11 +1495,30 @@ impl EthSubnetManager {
     }
 
     pub fn keystore(&self) -> Result<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>> {
+        if self.read_only {
+            return Err(anyhow!(
+                "read-only connection: transactions cannot be sent to this subnet"
+            ));
+        }
         self.keystore
             .clone()
             .ok_or(anyhow!("no evm keystore available"))
     }
 
+    /// Build the manager without a signer, so that every transaction fails with a
+    /// read-only connection error.
+    pub fn read_only(mut self) -> Self {
+        self.keystore = None;
+        self.read_only = true;
+        self
+    }
+
+    /// Get the signer instance, once the provider passed [Self::check_subnet].
+    async fn checked_signer(&self, addr: &Address) -> Result<DefaultSignerMiddleware> {
+        self.check_subnet().await?;
+        self.get_signer(addr)
+    }
+
     /// Get the ethers singer instance.
     /// We use filecoin addresses throughout our whole code-base
     /// and translate them to evm addresses when relevant.
@@ -994,24 +1533,37 @@ impl EthSubnetManager {
         let wallet = LocalWallet::from_bytes(private_key.private_key())?
             .with_chain_id(self.ipc_contract_info.chain_id);
 
-        Ok(SignerMiddleware::new(
-            self.ipc_contract_info.provider.clone(),
-            wallet,
-        ))
+        let signer = SignerMiddleware::new(self.ipc_contract_info.provider.clone(), wallet);
+
+        Ok(NonceMiddleware::new(signer, addr, self.nonces.clone()))
     }
 
     pub fn from_subnet_with_wallet_store(
         subnet: &Subnet,
         keystore: Option<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>>,
+    ) -> Result<Self> {
+        Self::from_subnet_with_shared_state(
+            subnet,
+            keystore,
+            SharedClientState::from_subnet(subnet),
+        )
+    }
+
+    /// Same as [`Self::from_subnet_with_wallet_store`], but sharing the request limiter,
+    /// circuit breaker and transaction journal with other managers for the same subnet.
+    pub fn from_subnet_with_shared_state(
+        subnet: &Subnet,
+        keystore: Option<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>>,
+        shared: SharedClientState,
     ) -> Result<Self> {
         let url = subnet.rpc_http().clone();
-        let auth_token = subnet.auth_token();
+        let auth_token = subnet.resolved_auth_token()?;
 
         let SubnetConfig::Fevm(config) = &subnet.config;
 
         let mut client = Client::builder();
 
-        if let Some(auth_token) = auth_token {
+        if let Some(auth_token) = auth_token.clone() {
             let auth = Authorization::Bearer(auth_token);
             let mut auth_value = HeaderValue::from_str(&auth.to_string())?;
             auth_value.set_sensitive(true);
@@ -1028,7 +1580,30 @@ impl EthSubnetManager {
 
         let client = client.build()?;
 
-        let provider = Http::new_with_client(url, client);
+        let fallbacks = subnet
+            .rpc_http_fallbacks()
+            .iter()
+            .map(|url| Http::new_with_client(url.clone(), client.clone()))
+            .collect();
+        let archive = subnet
+            .rpc_archive()
+            .map(|url| Http::new_with_client(url.clone(), client.clone()));
+
+        let nonces = shared.nonces.clone();
+        let multicall = shared.multicall.clone();
+        let index = shared.index.clone();
+        let check = shared.check.clone();
+        let ws = subnet
+            .rpc_ws()
+            .map(|url| WsEndpoint::new(url.clone(), auth_token));
+        let mut provider =
+            SubnetClient::new(Http::new_with_client(url, client), shared).with_fallbacks(fallbacks);
+        if let Some(retry) = subnet.rpc_retry() {
+            provider = provider.with_retry(retry.clone());
+        }
+        if let Some(archive) = archive {
+            provider = provider.with_archive(archive);
+        }
 
         let mut provider = Provider::new(provider);
         // set polling interval for provider to fit fast child subnets block times.
@@ -1038,13 +1613,26 @@ impl EthSubnetManager {
         let gateway_address = payload_to_evm_address(config.gateway_addr.payload())?;
         let registry_address = payload_to_evm_address(config.registry_addr.payload())?;
 
-        Ok(Self::new(
+        let manager = Self::new(
             gateway_address,
             registry_address,
             subnet.id.chain_id(),
             provider,
             keystore,
-        ))
+        )
+        .with_nonces(nonces)
+        .with_multicall(multicall)
+        .with_index(index)
+        .with_ws(ws)
+        .with_check(subnet.id.clone(), check)
+        .with_fee_caps(subnet.fee_caps().cloned().unwrap_or_default())
+        .with_resubmit_policy(subnet.resubmit_policy().cloned());
+
+        Ok(if subnet.read_only {
+            manager.read_only()
+        } else {
+            manager
+        })
     }
 }
 
@@ -1056,7 +1644,7 @@ impl BottomUpCheckpointRelayer for EthSubnetManager {
         checkpoint: BottomUpCheckpoint,
         signatures: Vec<Signature>,
         signatories: Vec<Address>,
-    ) -> anyhow::Result<ChainEpoch> {
+    ) -> anyhow::Result<CheckpointSubmission> {
         let address = contract_address_from_subnet(&checkpoint.subnet_id)?;
         log::debug!(
             "submit bottom up checkpoint: {checkpoint:?} in evm subnet contract: {address:}"
@@ -1071,20 +1659,51 @@ impl BottomUpCheckpointRelayer for EthSubnetManager {
             .map(|addr| payload_to_evm_address(addr.payload()))
             .collect::<result::Result<Vec<_>, _>>()?;
 
+        let height = checkpoint.block_height;
         let checkpoint =
             subnet_actor_checkpointing_facet::BottomUpCheckpoint::try_from(checkpoint)?;
 
-        let signer = Arc::new(self.get_signer(submitter)?);
+        let signer = Arc::new(self.checked_signer(submitter).await?);
         let contract = subnet_actor_checkpointing_facet::SubnetActorCheckpointingFacet::new(
             address,
             signer.clone(),
         );
         let call = contract.submit_checkpoint(checkpoint, signatories, signatures);
-        let call = call_with_premium_estimation(signer, call).await?;
-
-        let pending_tx = call.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
-        block_number_from_receipt(receipt)
+        let call = call_with_premium_estimation(&self.fees, signer.clone(), call).await?;
+        self.ensure_submitter_can_pay(submitter, &call.tx).await?;
+
+        let receipt = match &self.resubmit {
+            Some(policy) => {
+                // Stop raising the fees of a stuck submission once another relayer
+                // got the checkpoint committed, as ours would only revert.
+                let getter = subnet_actor_getter_facet::SubnetActorGetterFacet::new(
+                    address,
+                    Arc::new(self.ipc_contract_info.provider.clone()),
+                );
+                let still_needed = move || {
+                    let getter = getter.clone();
+                    async move {
+                        match getter.last_bottom_up_checkpoint_height().call().await {
+                            Ok(last) => (last.as_u64() as ChainEpoch) < height,
+                            Err(e) => {
+                                log::warn!("cannot get the last bottom up checkpoint height: {e}");
+                                true
+                            }
+                        }
+                    }
+                };
+                send_with_resubmission_while(
+                    signer,
+                    call.tx,
+                    call.block,
+                    policy.clone(),
+                    still_needed,
+                )
+                .await?
+            }
+            None => self.send_and_wait(signer, call).await?,
+        };
+        submission_from_receipt(receipt)
     }
 
     async fn last_bottom_up_checkpoint_height(
@@ -1140,6 +1759,23 @@ impl BottomUpCheckpointRelayer for EthSubnetManager {
         })
     }
 
+    async fn bottom_up_msgs_at(&self, height: ChainEpoch) -> Result<Vec<IpcEnvelope>> {
+        let contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let batch = contract
+            .bottom_up_msg_batch(U256::from(height))
+            .call()
+            .await?;
+        batch
+            .msgs
+            .into_iter()
+            .map(IpcEnvelope::try_from)
+            .collect::<Result<Vec<_>, _>>()
+    }
+
     async fn quorum_reached_events(&self, height: ChainEpoch) -> Result<Vec<QuorumReachedEvent>> {
         let contract = checkpointing_facet::CheckpointingFacet::new(
             self.ipc_contract_info.gateway_addr,
@@ -1153,7 +1789,7 @@ impl BottomUpCheckpointRelayer for EthSubnetManager {
             .address(ValueOrArray::Value(contract.address()));
 
         let mut events = vec![];
-        for (event, _meta) in query_with_meta(ev, contract.client()).await? {
+        for (event, _meta) in query_with_meta(ev, contract.client(), self.index.as_ref()).await? {
             events.push(QuorumReachedEvent {
                 obj_kind: event.obj_kind,
                 height: event.height.as_u64() as ChainEpoch,
@@ -1164,6 +1800,89 @@ impl BottomUpCheckpointRelayer for EthSubnetManager {
 
         Ok(events)
     }
+
+    async fn checkpoint_quorum_at(&self, height: ChainEpoch) -> Result<CheckpointQuorum> {
+        let contract = gateway_getter_facet::GatewayGetterFacet::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+
+        let (checkpoint, info, signatories, signatures) = contract
+            .get_checkpoint_signature_bundle(U256::from(height))
+            .call()
+            .await?;
+
+        if checkpoint.block_height.is_zero() {
+            return Err(anyhow!("no checkpoint at height {height}"));
+        }
+
+        let checkpoint = BottomUpCheckpoint::try_from(checkpoint)?;
+        let signatories = signatories
+            .into_iter()
+            .map(|s| ethers_address_to_fil_address(&s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let signatures = signatures
+            .into_iter()
+            .map(|s| s.to_vec())
+            .collect::<Vec<_>>();
+
+        Ok(CheckpointQuorum {
+            bundle: BottomUpCheckpointBundle {
+                checkpoint,
+                signatures,
+                signatories,
+            },
+            hash: info.hash.to_vec(),
+            current_weight: eth_to_fil_amount(&info.current_weight)?,
+            threshold: eth_to_fil_amount(&info.threshold)?,
+            reached: info.reached,
+        })
+    }
+
+    async fn checkpoint_submission(
+        &self,
+        subnet_id: &SubnetID,
+        height: ChainEpoch,
+        from_block: ChainEpoch,
+        to_block: ChainEpoch,
+    ) -> Result<Option<CheckpointSubmission>> {
+        use ethers::abi::AbiDecode;
+        use subnet_actor_checkpointing_facet::SubmitCheckpointCall;
+
+        let address = contract_address_from_subnet(subnet_id)?;
+        let provider = &self.ipc_contract_info.provider;
+
+        // The subnet actor doesn't emit an event when a checkpoint is committed,
+        // so look for a successful call to `submitCheckpoint` with its height.
+        for block_number in from_block..=to_block {
+            let block = match provider.get_block_with_txs(block_number as u64).await {
+                Ok(Some(block)) => block,
+                Ok(None) => continue,
+                Err(e) if e.to_string().contains("null round") => continue,
+                Err(e) => return Err(anyhow!("cannot get block {block_number}: {e}")),
+            };
+
+            for tx in block.transactions {
+                if tx.to != Some(address) {
+                    continue;
+                }
+                let Ok(call) = SubmitCheckpointCall::decode(&tx.input) else {
+                    continue;
+                };
+                if call.checkpoint.block_height != U256::from(height) {
+                    continue;
+                }
+                let receipt = provider.get_transaction_receipt(tx.hash).await?;
+                if receipt.as_ref().and_then(|r| r.status) != Some(1.into()) {
+                    continue;
+                }
+                return submission_from_receipt(receipt).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
     async fn current_epoch(&self) -> Result<ChainEpoch> {
         let epoch = self
             .ipc_contract_info
@@ -1176,121 +1895,46 @@ impl BottomUpCheckpointRelayer for EthSubnetManager {
 }
 
 /// Receives an input `FunctionCall` and returns a new instance
-/// after estimating an optimal `gas_premium` for the transaction
+/// with the EIP-1559 fees estimated by the oracle.
 pub(crate) async fn call_with_premium_estimation<B, D, M>(
+    fees: &FeeOracle,
     signer: Arc<DefaultSignerMiddleware>,
-    call: ethers_contract::FunctionCall<B, D, M>,
+    mut call: ethers_contract::FunctionCall<B, D, M>,
 ) -> Result<ethers_contract::FunctionCall<B, D, M>>
 where
     B: std::borrow::Borrow<D>,
     M: ethers::abi::Detokenize,
 {
-    let (max_priority_fee_per_gas, _) = premium_estimation(signer).await?;
-    Ok(call.gas_price(max_priority_fee_per_gas))
-}
-
-/// Returns an estimation of an optimal `gas_premium` and `gas_fee_cap`
-/// for a transaction considering the average premium, base_fee and reward percentile from
-/// past blocks
-/// This is adaptation of ethers' `eip1559_default_estimator`:
-/// https://github.com/gakonst/ethers-rs/blob/5dcd3b7e754174448f9a8cbfc0523896609629f9/ethers-core/src/utils/mod.rs#L476
-async fn premium_estimation(
-    signer: Arc<DefaultSignerMiddleware>,
-) -> Result<(ethers::types::U256, ethers::types::U256)> {
-    let base_fee_per_gas = signer
-        .get_block(ethers::types::BlockNumber::Latest)
-        .await?
-        .ok_or_else(|| anyhow!("Latest block not found"))?
-        .base_fee_per_gas
-        .ok_or_else(|| anyhow!("EIP-1559 not activated"))?;
-
-    let fee_history = signer
-        .fee_history(
-            ethers::utils::EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
-            ethers::types::BlockNumber::Latest,
-            &[ethers::utils::EIP1559_FEE_ESTIMATION_REWARD_PERCENTILE],
-        )
-        .await?;
-
-    let max_priority_fee_per_gas = estimate_priority_fee(fee_history.reward); //overestimate?
-    let potential_max_fee = base_fee_surged(base_fee_per_gas);
-    let max_fee_per_gas = if max_priority_fee_per_gas > potential_max_fee {
-        max_priority_fee_per_gas + potential_max_fee
-    } else {
-        potential_max_fee
-    };
-
-    Ok((max_priority_fee_per_gas, max_fee_per_gas))
-}
-
-/// Implementation borrowed from
-/// https://github.com/gakonst/ethers-rs/blob/ethers-v2.0.8/ethers-core/src/utils/mod.rs#L582
-/// Refer to the implementation for unit tests
-fn base_fee_surged(base_fee_per_gas: U256) -> U256 {
-    if base_fee_per_gas <= U256::from(40_000_000_000u64) {
-        base_fee_per_gas * 2
-    } else if base_fee_per_gas <= U256::from(100_000_000_000u64) {
-        base_fee_per_gas * 16 / 10
-    } else if base_fee_per_gas <= U256::from(200_000_000_000u64) {
-        base_fee_per_gas * 14 / 10
-    } else {
-        base_fee_per_gas * 12 / 10
+    let fees = fees.estimate(signer.as_ref()).await?;
+    match call.tx.as_eip1559_mut() {
+        Some(tx) => {
+            tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+            Ok(call)
+        }
+        None => Ok(call.gas_price(fees.max_fee_per_gas)),
     }
 }
 
-/// Implementation borrowed from
-/// https://github.com/gakonst/ethers-rs/blob/ethers-v2.0.8/ethers-core/src/utils/mod.rs#L536
-/// Refer to the implementation for unit tests
-fn estimate_priority_fee(rewards: Vec<Vec<U256>>) -> U256 {
-    let mut rewards: Vec<U256> = rewards
-        .iter()
-        .map(|r| r[0])
-        .filter(|r| *r > U256::zero())
-        .collect();
-    if rewards.is_empty() {
-        return U256::zero();
-    }
-    if rewards.len() == 1 {
-        return rewards[0];
-    }
-    // Sort the rewards as we will eventually take the median.
-    rewards.sort();
-
-    // A copy of the same vector is created for convenience to calculate percentage change
-    // between subsequent fee values.
-    let mut rewards_copy = rewards.clone();
-    rewards_copy.rotate_left(1);
-
-    let mut percentage_change: Vec<I256> = rewards
-        .iter()
-        .zip(rewards_copy.iter())
-        .map(|(a, b)| {
-            let a = I256::try_from(*a).expect("priority fee overflow");
-            let b = I256::try_from(*b).expect("priority fee overflow");
-            ((b - a) * 100) / a
-        })
-        .collect();
-    percentage_change.pop();
-
-    // Fetch the max of the percentage change, and that element's index.
-    let max_change = percentage_change.iter().max().unwrap();
-    let max_change_index = percentage_change
-        .iter()
-        .position(|&c| c == *max_change)
-        .unwrap();
-
-    // If we encountered a big change in fees at a certain position, then consider only
-    // the values >= it.
-    let values = if *max_change >= ethers::utils::EIP1559_FEE_ESTIMATION_THRESHOLD_MAX_CHANGE.into()
-        && (max_change_index >= (rewards.len() / 2))
-    {
-        rewards[max_change_index..].to_vec()
-    } else {
-        rewards
-    };
-
-    // Return the median.
-    values[values.len() / 2]
+/// The committed checkpoint submission a transaction receipt is for.
+fn submission_from_receipt(
+    receipt: Option<ethers::types::TransactionReceipt>,
+) -> Result<CheckpointSubmission> {
+    let receipt = receipt.ok_or_else(|| {
+        anyhow!("txn sent to network, but receipt cannot be obtained, please check scanner")
+    })?;
+    let block_height = receipt
+        .block_number
+        .ok_or_else(|| anyhow!("cannot get block number"))?;
+    let gas_used = receipt.gas_used.unwrap_or_default();
+    let gas_price = receipt.effective_gas_price.unwrap_or_default();
+    Ok(CheckpointSubmission {
+        tx_hash: receipt.transaction_hash.as_bytes().to_vec(),
+        block_height: block_height.as_u64() as ChainEpoch,
+        submitter: ethers_address_to_fil_address(&receipt.from)?,
+        gas_used: gas_used.as_u64(),
+        gas_fee: eth_to_fil_amount(&(gas_used * gas_price))?,
+    })
 }
 
 /// Get the block number from the transaction receipt
@@ -1331,17 +1975,32 @@ fn is_valid_bootstrap_addr(input: &str) -> Option<(String, IpAddr, u16)> {
 async fn query_with_meta<B, M, D>(
     event: ethers::contract::Event<B, M, D>,
     client: B,
+    index: Option<&EventIndex>,
 ) -> Result<Vec<(D, LogMeta)>, ContractError<M>>
 where
     B: Borrow<M>,
     M: Middleware,
     D: EthLogDecode,
 {
-    let logs = client
-        .borrow()
-        .get_logs(&event.filter)
-        .await
-        .map_err(ContractError::from_middleware_error)?;
+    // Take what we can from the index, and only ask the node for the rest.
+    let lookup = match index.map(|index| index.lookup(&event.filter)) {
+        Some(Ok(lookup)) => lookup,
+        Some(Err(e)) => {
+            log::debug!("cannot query the event index: {e:#}");
+            None
+        }
+        None => None,
+    };
+    let (mut logs, rest) = lookup.unwrap_or_else(|| (vec![], Some(event.filter.clone())));
+
+    if let Some(rest) = rest {
+        let fetched = client
+            .borrow()
+            .get_logs(&rest)
+            .await
+            .map_err(ContractError::from_middleware_error)?;
+        logs.extend(fetched);
+    }
 
     let events = logs
         .into_iter()
@@ -1356,6 +2015,16 @@ where
     Ok(events)
 }
 
+/// The results of the batched reads of [EthSubnetManager::get_genesis_info].
+type GenesisReads = (
+    (Vec<ethers::types::Address>, Vec<U256>),
+    U256,
+    u16,
+    u8,
+    U256,
+    Vec<subnet_actor_getter_facet::Validator>,
+);
+
 fn into_genesis_balance_map(
     addrs: Vec<ethers::types::Address>,
     balances: Vec<ethers::types::U256>,
@@ -1,14 +1,33 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: MIT
 
+mod check;
+mod client;
+mod fees;
+mod index;
 mod manager;
+mod multicall;
+mod nonce;
+mod resubmit;
+mod subscribe;
 
 use async_trait::async_trait;
 use fvm_shared::clock::ChainEpoch;
 use ipc_api::subnet_id::SubnetID;
 
 use super::subnet::SubnetManager;
+pub use check::SubnetCheck;
+pub use client::{
+    CircuitBreaker, Deadline, RequestLimiter, SharedClientState, SubnetClient, SubnetClientError,
+    TxJournal,
+};
+pub use fees::{FeeOracle, FeeOverride, Fees};
+pub use index::{EventIndex, EventIndexer, EventSource};
 pub use manager::EthSubnetManager;
+pub use multicall::{MulticallSupport, ReadBatch};
+pub use nonce::{NonceMiddleware, NonceTracker};
+pub use resubmit::{send_with_resubmission, send_with_resubmission_while};
+pub use subscribe::WsEndpoint;
 
 use ipc_actors_abis::subnet_actor_checkpointing_facet;
 
@@ -0,0 +1,157 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! Batching of read-only contract calls into a single Multicall3 call.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use ethers::abi::{Detokenize, Function, Token, Tokenizable};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::BlockId;
+use ethers_contract::{ContractCall, Multicall, MULTICALL_ADDRESS};
+use tokio::sync::OnceCell;
+
+/// Whether the Multicall3 contract is deployed on a subnet, shared by all the managers
+/// connected to it so it is only checked once.
+#[derive(Debug, Clone, Default)]
+pub struct MulticallSupport {
+    deployed: Arc<OnceCell<bool>>,
+}
+
+impl MulticallSupport {
+    /// Check whether there is code at the canonical Multicall3 address. Errors are not
+    /// cached, so the check is repeated on the next batch.
+    pub async fn is_deployed<M: Middleware>(&self, client: &M) -> bool {
+        let deployed = self
+            .deployed
+            .get_or_try_init(|| async {
+                let code = client.get_code(MULTICALL_ADDRESS, None).await?;
+                if code.is_empty() {
+                    log::info!("multicall not deployed at {MULTICALL_ADDRESS:?}, batched reads are sent one by one");
+                }
+                Ok::<_, M::Error>(!code.is_empty())
+            })
+            .await;
+
+        match deployed {
+            Ok(deployed) => *deployed,
+            Err(e) => {
+                log::warn!("cannot check whether multicall is deployed: {e}");
+                false
+            }
+        }
+    }
+}
+
+/// A batch of read-only calls, which are sent as a single Multicall3 call if the contract
+/// is deployed on the subnet, or one by one otherwise.
+pub struct ReadBatch<M> {
+    client: Arc<M>,
+    support: MulticallSupport,
+    multicall: Multicall<M>,
+    calls: Vec<(TypedTransaction, Function, Option<BlockId>)>,
+}
+
+impl<M: Middleware> ReadBatch<M> {
+    pub fn new(client: Arc<M>, support: MulticallSupport) -> anyhow::Result<Self> {
+        let multicall =
+            Multicall::new_with_chain_id(client.clone(), Some(MULTICALL_ADDRESS), None::<u64>)
+                .map_err(|e| anyhow!("cannot create multicall: {e}"))?;
+        Ok(Self {
+            client,
+            support,
+            multicall,
+            calls: Vec::new(),
+        })
+    }
+
+    /// Add a call to the batch; its result is returned at the same position by [ReadBatch::call].
+    pub fn add<D: Detokenize>(&mut self, call: ContractCall<M, D>) -> &mut Self {
+        self.calls
+            .push((call.tx.clone(), call.function.clone(), call.block));
+        self.multicall.add_call(call, false);
+        self
+    }
+
+    /// Execute the calls and return their results as a tuple, in the order they were added.
+    ///
+    /// If the multicall fails, e.g. because one of the calls reverted, the calls are
+    /// repeated one by one so the error points at the call which failed.
+    pub async fn call<T: Tokenizable>(&self) -> anyhow::Result<T> {
+        if self.support.is_deployed(self.client.as_ref()).await {
+            match self.multicall.call::<T>().await {
+                Ok(results) => return Ok(results),
+                Err(e) => log::debug!("multicall failed, sending the calls one by one: {e}"),
+            }
+        }
+
+        let mut tokens = Vec::with_capacity(self.calls.len());
+        for (tx, function, block) in self.calls.iter() {
+            let bytes = self
+                .client
+                .call(tx, *block)
+                .await
+                .map_err(|e| anyhow!("call to {} failed: {e}", function.name))?;
+            tokens.push(decode_output(function, &bytes)?);
+        }
+
+        Ok(T::from_token(Token::Tuple(tokens))?)
+    }
+}
+
+/// Decode the output of a call the same way as the multicall does: a single value
+/// as itself, several values as a tuple.
+fn decode_output(function: &Function, bytes: &[u8]) -> anyhow::Result<Token> {
+    let mut tokens = function.decode_output(bytes)?;
+    if tokens.len() == 1 {
+        Ok(tokens.pop().unwrap())
+    } else {
+        Ok(Token::Tuple(tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::{encode, Function, Param, ParamType, StateMutability, Token, Tokenizable};
+    use ethers::types::U256;
+
+    use super::decode_output;
+
+    #[allow(deprecated)]
+    fn function(outputs: Vec<ParamType>) -> Function {
+        Function {
+            name: "f".into(),
+            inputs: vec![],
+            outputs: outputs
+                .into_iter()
+                .map(|kind| Param {
+                    name: "".into(),
+                    kind,
+                    internal_type: None,
+                })
+                .collect(),
+            constant: None,
+            state_mutability: StateMutability::View,
+        }
+    }
+
+    #[test]
+    fn test_decode_output_like_multicall() {
+        let single = function(vec![ParamType::Uint(64)]);
+        let pair = function(vec![ParamType::Bool, ParamType::Uint(64)]);
+
+        let a = decode_output(&single, &encode(&[Token::Uint(U256::from(5))])).unwrap();
+        let b = decode_output(
+            &pair,
+            &encode(&[Token::Bool(true), Token::Uint(U256::from(7))]),
+        )
+        .unwrap();
+
+        let (a, (exists, b)) = <(u64, (bool, u64))>::from_token(Token::Tuple(vec![a, b])).unwrap();
+        assert_eq!(a, 5);
+        assert!(exists);
+        assert_eq!(b, 7);
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! Nonce assignment for transactions sent concurrently by the same signer.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use ethers::providers::{Middleware, MiddlewareError, PendingTransaction};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, BlockId, BlockNumber, TxHash, U256};
+
+/// The next nonce of each signer, shared by all the managers connected to the same subnet.
+///
+/// Without this, every transaction takes its nonce from the pending transaction count
+/// of the node, so concurrent transactions from the same signer end up with the same nonce
+/// and all but one of them are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct NonceTracker {
+    signers: Arc<Mutex<HashMap<Address, Arc<tokio::sync::Mutex<Option<U256>>>>>>,
+}
+
+impl NonceTracker {
+    /// The next nonce of a signer, or `None` if it has to be fetched from the node.
+    fn signer(&self, address: Address) -> Arc<tokio::sync::Mutex<Option<U256>>> {
+        self.signers
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_default()
+            .clone()
+    }
+}
+
+/// Middleware assigning nonces to the transactions of a signer from a [NonceTracker].
+///
+/// Transactions of the same signer are submitted one at a time, so the nonces they get
+/// are consecutive. If the node rejects a transaction, the nonce is synced from the node
+/// next time. The transaction is only sent again with the nonce the node expects if that
+/// is lower, i.e. an earlier transaction was dropped, and the node doesn't already know
+/// the transaction, which would mean it was sent twice.
+#[derive(Debug)]
+pub struct NonceMiddleware<M> {
+    inner: M,
+    address: Address,
+    tracker: NonceTracker,
+}
+
+impl<M: Middleware> NonceMiddleware<M> {
+    pub fn new(inner: M, address: Address, tracker: NonceTracker) -> Self {
+        Self {
+            inner,
+            address,
+            tracker,
+        }
+    }
+
+    /// The nonce of the next transaction according to the node, including the ones in its mempool.
+    async fn pending_nonce(&self) -> Result<U256, NonceMiddlewareError<M>> {
+        self.inner
+            .get_transaction_count(self.address, Some(BlockId::Number(BlockNumber::Pending)))
+            .await
+            .map_err(MiddlewareError::from_err)
+    }
+
+    /// The hash the transaction has once the signer of the inner middleware signed it.
+    async fn tx_hash(&self, tx: &TypedTransaction) -> Result<TxHash, NonceMiddlewareError<M>> {
+        let signature = self
+            .inner
+            .sign_transaction(tx, self.address)
+            .await
+            .map_err(MiddlewareError::from_err)?;
+        Ok(tx.hash(&signature))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NonceMiddlewareError<M: Middleware> {
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+}
+
+impl<M: Middleware> MiddlewareError for NonceMiddlewareError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        NonceMiddlewareError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            NonceMiddlewareError::MiddlewareError(e) => Some(e),
+        }
+    }
+}
+
+#[async_trait]
+impl<M> Middleware for NonceMiddleware<M>
+where
+    M: Middleware,
+{
+    type Error = NonceMiddlewareError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx = tx.into();
+
+        if tx.nonce().is_some() {
+            return self
+                .inner
+                .send_transaction(tx, block)
+                .await
+                .map_err(MiddlewareError::from_err);
+        }
+
+        // Hold the lock of the signer until the node accepted the transaction,
+        // so the next one can't be assigned the same nonce.
+        let signer = self.tracker.signer(self.address);
+        let mut next = signer.lock().await;
+
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => self.pending_nonce().await?,
+        };
+        tx.set_nonce(nonce);
+
+        // Fill the transaction before signing it, so the hash is the one the node sees.
+        self.inner
+            .fill_transaction(&mut tx, block)
+            .await
+            .map_err(MiddlewareError::from_err)?;
+        let hash = self.tx_hash(&tx).await?;
+
+        match self.inner.send_transaction(tx.clone(), block).await {
+            Ok(pending) => {
+                *next = Some(nonce + 1);
+                Ok(pending)
+            }
+            Err(e) => {
+                // We don't know if the nonce was consumed, so ask the node next time.
+                *next = None;
+
+                if e.as_error_response().is_none() {
+                    return Err(MiddlewareError::from_err(e));
+                }
+
+                // The node rejects a transaction it already has, e.g. when the request was
+                // sent again after a timeout, in which case it must not be sent with a new nonce.
+                let known = self
+                    .inner
+                    .get_transaction(hash)
+                    .await
+                    .map_err(MiddlewareError::from_err)?;
+                if known.is_some() {
+                    log::warn!(
+                        "transaction {hash:?} of {:?} already known: {e}",
+                        self.address
+                    );
+                    *next = Some(nonce + 1);
+                    return Ok(PendingTransaction::new(hash, self.provider()));
+                }
+
+                // Nodes word nonce errors differently, so rather than parsing the error,
+                // the nonce is compared with the one the node expects. Only a gap left by a
+                // dropped transaction is filled; if the nonce was used by somebody else,
+                // the transaction isn't sent again in case it was one of ours after all.
+                let expected = self.pending_nonce().await?;
+                if expected >= nonce {
+                    return Err(MiddlewareError::from_err(e));
+                }
+                log::warn!(
+                    "nonce {nonce} of {:?} rejected, resending with {expected}: {e}",
+                    self.address
+                );
+                tx.set_nonce(expected);
+                let pending = self
+                    .inner
+                    .send_transaction(tx, block)
+                    .await
+                    .map_err(MiddlewareError::from_err)?;
+                *next = Some(expected + 1);
+                Ok(pending)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::middleware::SignerMiddleware;
+    use ethers::providers::{JsonRpcError, Middleware, MockProvider, MockResponse, Provider};
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{Address, Transaction, TransactionRequest, TxHash, U256};
+    use serde_json::json;
+
+    use super::{NonceMiddleware, NonceTracker};
+
+    type TestMiddleware = NonceMiddleware<SignerMiddleware<Provider<MockProvider>, LocalWallet>>;
+
+    const CHAIN_ID: u64 = 31415926;
+
+    fn wallet() -> LocalWallet {
+        "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(CHAIN_ID)
+    }
+
+    fn setup() -> (TestMiddleware, MockProvider, NonceTracker) {
+        let (provider, mock) = Provider::mocked();
+        let wallet = wallet();
+        let address = wallet.address();
+        let tracker = NonceTracker::default();
+        let middleware = NonceMiddleware::new(
+            SignerMiddleware::new(provider, wallet),
+            address,
+            tracker.clone(),
+        );
+        (middleware, mock, tracker)
+    }
+
+    /// The mock answers with the response pushed last, so they are pushed in reverse.
+    fn respond(mock: &MockProvider, responses: Vec<MockResponse>) {
+        for response in responses.into_iter().rev() {
+            mock.push_response(response);
+        }
+    }
+
+    fn value<T: serde::Serialize>(value: T) -> MockResponse {
+        MockResponse::Value(json!(value))
+    }
+
+    fn rejected() -> MockResponse {
+        MockResponse::Error(JsonRpcError {
+            code: 1,
+            message: "message execution failed".into(),
+            data: None,
+        })
+    }
+
+    fn tx() -> TransactionRequest {
+        TransactionRequest::new()
+            .to(Address::zero())
+            .value(1)
+            .gas(21000)
+            .gas_price(1)
+    }
+
+    /// The hash of [tx] signed with the given nonce.
+    async fn signed_hash(nonce: u64) -> TxHash {
+        let wallet = wallet();
+        let tx: TypedTransaction = tx()
+            .from(wallet.address())
+            .chain_id(CHAIN_ID)
+            .nonce(nonce)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+        tx.hash(&signature)
+    }
+
+    async fn next_nonce(middleware: &TestMiddleware, tracker: &NonceTracker) -> Option<U256> {
+        *tracker.signer(middleware.address).lock().await
+    }
+
+    async fn set_next_nonce(middleware: &TestMiddleware, tracker: &NonceTracker, nonce: u64) {
+        *tracker.signer(middleware.address).lock().await = Some(U256::from(nonce));
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_nonces() {
+        let (middleware, mock, tracker) = setup();
+
+        respond(
+            &mock,
+            vec![
+                value(U256::from(5)),
+                value(TxHash::repeat_byte(1)),
+                value(TxHash::repeat_byte(2)),
+            ],
+        );
+
+        middleware.send_transaction(tx(), None).await.unwrap();
+        assert_eq!(next_nonce(&middleware, &tracker).await, Some(U256::from(6)));
+
+        // The second transaction doesn't ask the node.
+        middleware.send_transaction(tx(), None).await.unwrap();
+        assert_eq!(next_nonce(&middleware, &tracker).await, Some(U256::from(7)));
+    }
+
+    #[tokio::test]
+    async fn test_known_transaction_is_not_resent() {
+        let (middleware, mock, tracker) = setup();
+        set_next_nonce(&middleware, &tracker, 5).await;
+
+        let hash = signed_hash(5).await;
+
+        // The node already has the transaction, e.g. from a request which timed out.
+        respond(
+            &mock,
+            vec![
+                rejected(),
+                value(Transaction {
+                    hash,
+                    ..Default::default()
+                }),
+            ],
+        );
+
+        let pending = middleware.send_transaction(tx(), None).await.unwrap();
+        assert_eq!(pending.tx_hash(), hash);
+        assert_eq!(next_nonce(&middleware, &tracker).await, Some(U256::from(6)));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_gap_is_filled() {
+        let (middleware, mock, tracker) = setup();
+        set_next_nonce(&middleware, &tracker, 7).await;
+
+        // An earlier transaction was dropped, so the node expects a lower nonce.
+        respond(
+            &mock,
+            vec![
+                rejected(),
+                value(serde_json::Value::Null),
+                value(U256::from(5)),
+                value(TxHash::repeat_byte(1)),
+            ],
+        );
+
+        middleware.send_transaction(tx(), None).await.unwrap();
+        assert_eq!(next_nonce(&middleware, &tracker).await, Some(U256::from(6)));
+    }
+
+    #[tokio::test]
+    async fn test_used_nonce_is_not_resent() {
+        let (middleware, mock, tracker) = setup();
+        set_next_nonce(&middleware, &tracker, 5).await;
+
+        // The nonce was used by a transaction with another hash, which may have been ours.
+        respond(
+            &mock,
+            vec![
+                rejected(),
+                value(serde_json::Value::Null),
+                value(U256::from(6)),
+            ],
+        );
+
+        assert!(middleware.send_transaction(tx(), None).await.is_err());
+        assert_eq!(next_nonce(&middleware, &tracker).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_with_expected_nonce() {
+        let (middleware, mock, tracker) = setup();
+        set_next_nonce(&middleware, &tracker, 5).await;
+
+        // The node rejected the transaction for another reason.
+        respond(
+            &mock,
+            vec![
+                rejected(),
+                value(serde_json::Value::Null),
+                value(U256::from(5)),
+            ],
+        );
+
+        assert!(middleware.send_transaction(tx(), None).await.is_err());
+        assert_eq!(next_nonce(&middleware, &tracker).await, None);
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! Resubmission of transactions which are not included in a block in time.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockId, TransactionReceipt, TxHash, U256};
+use tokio::time::Instant;
+
+use crate::config::subnet::ResubmitPolicy;
+
+/// How often the receipts of the sent transactions are checked.
+const RECEIPT_POLLING_TIME: Duration = Duration::from_secs(1);
+
+/// Nodes only accept a replacement transaction if it raises the fees by at least this much.
+const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// A zero priority fee, which no percentage can raise, is raised to this much instead; 1 gwei.
+const MIN_BUMPED_PRIORITY_FEE: u64 = 1_000_000_000;
+
+/// Send a transaction and wait for it to be included in a block. If that doesn't happen within
+/// the window of the policy, it is sent again with the same nonce and higher fees.
+///
+/// The monitoring runs in a background task, so the transaction keeps being resubmitted even if
+/// the caller stops waiting, until one of the versions is included or the fees hit the ceiling.
+/// Returns the receipt of the version which was included, or `None` if that didn't happen within
+/// one more window after the last resubmission.
+pub async fn send_with_resubmission<M>(
+    client: Arc<M>,
+    tx: TypedTransaction,
+    block: Option<BlockId>,
+    policy: ResubmitPolicy,
+) -> anyhow::Result<Option<TransactionReceipt>>
+where
+    M: Middleware + 'static,
+{
+    send_with_resubmission_while(client, tx, block, policy, || async { true }).await
+}
+
+/// Like [send_with_resubmission], but before each resubmission `still_needed` is asked whether
+/// the transaction is still worth paying more for, e.g. because nobody else has done the same
+/// thing in the meantime. If not, the fees are no longer raised and `None` is returned.
+pub async fn send_with_resubmission_while<M, F, Fut>(
+    client: Arc<M>,
+    tx: TypedTransaction,
+    block: Option<BlockId>,
+    policy: ResubmitPolicy,
+    still_needed: F,
+) -> anyhow::Result<Option<TransactionReceipt>>
+where
+    M: Middleware + 'static,
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    tokio::spawn(monitor(client, tx, block, policy, still_needed)).await?
+}
+
+async fn monitor<M, F, Fut>(
+    client: Arc<M>,
+    mut tx: TypedTransaction,
+    block: Option<BlockId>,
+    policy: ResubmitPolicy,
+    still_needed: F,
+) -> anyhow::Result<Option<TransactionReceipt>>
+where
+    M: Middleware,
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let hash = send(&client, tx.clone(), block).await?;
+
+    // Replacements need the same nonce and gas limit as the original, which are
+    // only assigned when it is sent, so take them from the node.
+    let sent = client
+        .get_transaction(hash)
+        .await
+        .map_err(|e| anyhow!("cannot get sent transaction {hash:?}: {e}"))?
+        .ok_or_else(|| anyhow!("sent transaction {hash:?} not found"))?;
+    tx.set_nonce(sent.nonce);
+    tx.set_gas(sent.gas);
+    match tx.as_eip1559_mut() {
+        Some(inner) => {
+            inner.max_fee_per_gas = sent.max_fee_per_gas;
+            inner.max_priority_fee_per_gas = sent.max_priority_fee_per_gas;
+        }
+        None => {
+            if let Some(gas_price) = sent.gas_price {
+                tx.set_gas_price(gas_price);
+            }
+        }
+    }
+
+    let mut hashes = vec![hash];
+    let mut resubmissions = 0;
+    let mut can_bump = true;
+    let mut deadline = Instant::now() + policy.resubmit_after;
+
+    loop {
+        tokio::time::sleep(RECEIPT_POLLING_TIME).await;
+
+        for hash in hashes.iter() {
+            match client.get_transaction_receipt(*hash).await {
+                Ok(Some(receipt)) => return Ok(Some(receipt)),
+                Ok(None) => {}
+                Err(e) => log::warn!("cannot get receipt of {hash:?}: {e}"),
+            }
+        }
+
+        if Instant::now() < deadline {
+            continue;
+        }
+
+        if !can_bump || resubmissions >= policy.max_resubmissions {
+            log::warn!(
+                "transaction with nonce {:?} not included after {resubmissions} resubmissions",
+                tx.nonce()
+            );
+            return Ok(None);
+        }
+
+        if !still_needed().await {
+            log::info!(
+                "transaction with nonce {:?} is no longer needed, not resubmitting it",
+                tx.nonce()
+            );
+            return Ok(None);
+        }
+
+        if !bump_fees(&mut tx, &policy) {
+            log::warn!(
+                "cannot raise the fees of transaction {:?} any further",
+                hashes.last()
+            );
+            // Give the last version one more window.
+            can_bump = false;
+            deadline = Instant::now() + policy.resubmit_after;
+            continue;
+        }
+
+        match send(&client, tx.clone(), block).await {
+            Ok(hash) => {
+                log::info!(
+                    "resubmitted transaction {:?} as {hash:?} with higher fees",
+                    hashes.last()
+                );
+                hashes.push(hash);
+            }
+            // The nonce being used means one of the versions was included,
+            // which the receipts will show on the next round.
+            Err(e) => log::warn!("failed to resubmit transaction: {e}"),
+        }
+        resubmissions += 1;
+        deadline = Instant::now() + policy.resubmit_after;
+    }
+}
+
+async fn send<M: Middleware>(
+    client: &M,
+    tx: TypedTransaction,
+    block: Option<BlockId>,
+) -> anyhow::Result<TxHash> {
+    let pending = client
+        .send_transaction(tx, block)
+        .await
+        .map_err(|e| anyhow!("failed to send transaction: {e}"))?;
+    Ok(pending.tx_hash())
+}
+
+/// Raise the fees of the transaction by the percentage of the policy, up to its ceiling.
+/// A zero priority fee is raised to [MIN_BUMPED_PRIORITY_FEE], and fees too small for the
+/// percentage to make a difference are raised by one.
+///
+/// Returns `false` if they can't be raised enough for the node to accept the replacement.
+fn bump_fees(tx: &mut TypedTransaction, policy: &ResubmitPolicy) -> bool {
+    let percent = policy.fee_bump_percent.max(MIN_REPLACEMENT_BUMP_PERCENT);
+    let ceiling = policy.max_fee_per_gas.map(U256::from);
+
+    let bump_from = |fee: U256, floor: U256| -> Option<U256> {
+        let bumped = if fee.is_zero() {
+            floor
+        } else {
+            (fee * (100 + percent) / 100).max(fee + 1)
+        };
+        let bumped = ceiling.map_or(bumped, |c| bumped.min(c));
+        let min = fee * (100 + MIN_REPLACEMENT_BUMP_PERCENT) / 100;
+        if bumped < min || bumped <= fee {
+            None
+        } else {
+            Some(bumped)
+        }
+    };
+    let bump = |fee: U256| bump_from(fee, U256::zero());
+
+    match tx.as_eip1559_mut() {
+        Some(inner) => {
+            let (Some(max_fee), Some(priority_fee)) =
+                (inner.max_fee_per_gas, inner.max_priority_fee_per_gas)
+            else {
+                return false;
+            };
+            let priority_fee = bump_from(priority_fee, U256::from(MIN_BUMPED_PRIORITY_FEE));
+            let (Some(max_fee), Some(priority_fee)) = (bump(max_fee), priority_fee) else {
+                return false;
+            };
+            inner.max_fee_per_gas = Some(max_fee);
+            inner.max_priority_fee_per_gas = Some(priority_fee.min(max_fee));
+            true
+        }
+        None => match tx.gas_price().and_then(bump) {
+            Some(gas_price) => {
+                tx.set_gas_price(gas_price);
+                true
+            }
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{Eip1559TransactionRequest, U256};
+
+    use super::bump_fees;
+    use crate::config::subnet::ResubmitPolicy;
+
+    #[test]
+    fn test_bump_fees() {
+        let policy = ResubmitPolicy {
+            fee_bump_percent: 20,
+            max_fee_per_gas: Some(1400),
+            ..Default::default()
+        };
+
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(1000)
+            .max_priority_fee_per_gas(100)
+            .into();
+
+        assert!(bump_fees(&mut tx, &policy));
+        let inner = tx.as_eip1559_ref().unwrap();
+        assert_eq!(inner.max_fee_per_gas, Some(U256::from(1200)));
+        assert_eq!(inner.max_priority_fee_per_gas, Some(U256::from(120)));
+
+        // Capped at the ceiling, which is still enough for a replacement.
+        assert!(bump_fees(&mut tx, &policy));
+        let inner = tx.as_eip1559_ref().unwrap();
+        assert_eq!(inner.max_fee_per_gas, Some(U256::from(1400)));
+
+        // The ceiling doesn't leave room for another replacement.
+        assert!(!bump_fees(&mut tx, &policy));
+    }
+
+    #[test]
+    fn test_bump_zero_priority_fee() {
+        let policy = ResubmitPolicy {
+            fee_bump_percent: 20,
+            ..Default::default()
+        };
+
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(10_000_000_000u64)
+            .max_priority_fee_per_gas(0)
+            .into();
+
+        // The tip is raised to the floor instead of staying zero.
+        assert!(bump_fees(&mut tx, &policy));
+        let inner = tx.as_eip1559_ref().unwrap();
+        assert_eq!(inner.max_fee_per_gas, Some(U256::from(12_000_000_000u64)));
+        assert_eq!(
+            inner.max_priority_fee_per_gas,
+            Some(U256::from(1_000_000_000u64))
+        );
+
+        // Then it's raised by the percentage as usual.
+        assert!(bump_fees(&mut tx, &policy));
+        let inner = tx.as_eip1559_ref().unwrap();
+        assert_eq!(
+            inner.max_priority_fee_per_gas,
+            Some(U256::from(1_200_000_000u64))
+        );
+    }
+}
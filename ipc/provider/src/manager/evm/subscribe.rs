@@ -0,0 +1,224 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+
+//! Subscriptions to new blocks and events over a WebSocket endpoint.
+//!
+//! The subscriptions run in background tasks which reconnect whenever the connection drops,
+//! and deliver what was missed while they were disconnected before anything new, so the
+//! consumers see every block height and every log once, in order.
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use ethers::providers::{Authorization, ConnectionDetails, Middleware, Provider, StreamExt, Ws};
+use ethers::types::{BlockNumber, Filter, Log};
+use fvm_shared::clock::ChainEpoch;
+use tokio::sync::mpsc;
+use url::Url;
+
+/// Delay before the first reconnection attempt, doubled after every failed one.
+const MIN_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+/// The number of items buffered for a consumer that is not keeping up.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// A WebSocket endpoint of a subnet.
+#[derive(Debug, Clone)]
+pub struct WsEndpoint {
+    url: Url,
+    auth_token: Option<String>,
+}
+
+impl WsEndpoint {
+    pub fn new(url: Url, auth_token: Option<String>) -> Self {
+        Self { url, auth_token }
+    }
+
+    async fn connect(&self) -> anyhow::Result<Provider<Ws>> {
+        let auth = self.auth_token.clone().map(Authorization::Bearer);
+        let ws = Ws::connect(ConnectionDetails::new(self.url.as_str(), auth))
+            .await
+            .map_err(|e| anyhow!("cannot connect to {}: {e}", self.url))?;
+        Ok(Provider::new(ws))
+    }
+
+    /// Subscribe to the heights of new blocks.
+    ///
+    /// The subscription ends when the receiver is dropped.
+    pub fn subscribe_new_heads(&self) -> mpsc::Receiver<ChainEpoch> {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let endpoint = self.clone();
+
+        tokio::spawn(async move {
+            let mut last = None;
+            let mut backoff = MIN_RECONNECT_BACKOFF;
+            while !tx.is_closed() {
+                match endpoint.follow_heads(&tx, &mut last).await {
+                    Ok(()) => {
+                        log::warn!("head subscription to {} closed", endpoint.url);
+                        backoff = MIN_RECONNECT_BACKOFF;
+                    }
+                    Err(e) => log::warn!("head subscription to {} failed: {e}", endpoint.url),
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+            }
+        });
+
+        rx
+    }
+
+    async fn follow_heads(
+        &self,
+        tx: &mpsc::Sender<ChainEpoch>,
+        last: &mut Option<ChainEpoch>,
+    ) -> anyhow::Result<()> {
+        let provider = self.connect().await?;
+        let mut blocks = provider.subscribe_blocks().await?;
+
+        while let Some(block) = blocks.next().await {
+            let Some(number) = block.number else {
+                continue;
+            };
+            for height in missed_heights(*last, number.as_u64() as ChainEpoch) {
+                if tx.send(height).await.is_err() {
+                    return Ok(());
+                }
+                *last = Some(height);
+            }
+        }
+        Ok(())
+    }
+
+    /// Subscribe to the logs matching a filter; only the addresses and topics of the filter
+    /// are used, the subscription starts at the latest block.
+    ///
+    /// The subscription ends when the receiver is dropped.
+    pub fn subscribe_logs(&self, filter: Filter) -> mpsc::Receiver<Log> {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let endpoint = self.clone();
+
+        tokio::spawn(async move {
+            let mut last = None;
+            let mut backoff = MIN_RECONNECT_BACKOFF;
+            while !tx.is_closed() {
+                match endpoint.follow_logs(&filter, &tx, &mut last).await {
+                    Ok(()) => {
+                        log::warn!("log subscription to {} closed", endpoint.url);
+                        backoff = MIN_RECONNECT_BACKOFF;
+                    }
+                    Err(e) => log::warn!("log subscription to {} failed: {e}", endpoint.url),
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+            }
+        });
+
+        rx
+    }
+
+    async fn follow_logs(
+        &self,
+        filter: &Filter,
+        tx: &mpsc::Sender<Log>,
+        last: &mut Option<LogPosition>,
+    ) -> anyhow::Result<()> {
+        let provider = self.connect().await?;
+        // Subscribe before backfilling, so nothing falls between the two;
+        // the logs delivered by both are only sent once.
+        let mut logs = provider.subscribe_logs(filter).await?;
+
+        if let Some((block, _)) = *last {
+            let missed = filter
+                .clone()
+                .from_block(block)
+                .to_block(BlockNumber::Latest);
+            for log in provider.get_logs(&missed).await? {
+                if !send_if_new(tx, last, log).await {
+                    return Ok(());
+                }
+            }
+        }
+
+        while let Some(log) = logs.next().await {
+            if !send_if_new(tx, last, log).await {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The block number and index of a log.
+type LogPosition = (u64, u64);
+
+/// The heights to deliver when a block at `height` arrives after `last`.
+fn missed_heights(
+    last: Option<ChainEpoch>,
+    height: ChainEpoch,
+) -> impl Iterator<Item = ChainEpoch> {
+    let from = last.map_or(height, |last| last + 1);
+    from..=height
+}
+
+/// Send a log unless it was already sent, returning `false` if the receiver is gone.
+async fn send_if_new(tx: &mpsc::Sender<Log>, last: &mut Option<LogPosition>, log: Log) -> bool {
+    if log.removed.unwrap_or_default() {
+        return true;
+    }
+    let (Some(block), Some(index)) = (log.block_number, log.log_index) else {
+        return true;
+    };
+    let position = (block.as_u64(), index.as_u64());
+    if last.map_or(false, |last| position <= last) {
+        return true;
+    }
+    *last = Some(position);
+    tx.send(log).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Log, U256, U64};
+    use tokio::sync::mpsc;
+
+    use super::{missed_heights, send_if_new};
+
+    #[test]
+    fn test_missed_heights() {
+        assert_eq!(missed_heights(None, 10).collect::<Vec<_>>(), vec![10]);
+        assert_eq!(
+            missed_heights(Some(7), 10).collect::<Vec<_>>(),
+            vec![8, 9, 10]
+        );
+        // Reorgs to a lower height don't deliver anything.
+        assert_eq!(missed_heights(Some(10), 9).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_logs_are_sent_once() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let mut last = None;
+
+        let log = |block: u64, index: u64| Log {
+            block_number: Some(U64::from(block)),
+            log_index: Some(U256::from(index)),
+            ..Default::default()
+        };
+
+        // The backfill and the subscription overlap after a reconnect.
+        for (block, index) in [(5, 0), (5, 1), (5, 1), (4, 3), (6, 0)] {
+            assert!(send_if_new(&tx, &mut last, log(block, index)).await);
+        }
+        drop(tx);
+
+        let mut sent = vec![];
+        while let Some(log) = rx.recv().await {
+            sent.push((
+                log.block_number.unwrap().as_u64(),
+                log.log_index.unwrap().as_u64(),
+            ));
+        }
+        assert_eq!(sent, vec![(5, 0), (5, 1), (6, 0)]);
+    }
+}
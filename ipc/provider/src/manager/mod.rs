@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: MIT
 pub use crate::lotus::message::ipc::SubnetInfo;
 pub use evm::{EthManager, EthSubnetManager};
+pub use registry::{ManagerKeyStore, ManagerRegistry, SubnetManagerFactory};
 pub use subnet::{
-    BottomUpCheckpointRelayer, GetBlockHashResult, SubnetGenesisInfo, SubnetManager,
-    TopDownFinalityQuery, TopDownQueryPayload,
+    BottomUpCheckpointRelayer, GetBlockHashResult, SubmitterUnavailable, SubnetGenesisInfo,
+    SubnetManager, SubnetStatus, TopDownFinalityQuery, TopDownQueryPayload,
 };
 
 pub mod evm;
+mod registry;
 mod subnet;
@@ -0,0 +1,82 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Registration of custom subnet manager backends.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{bail, Result};
+use ipc_wallet::{EthKeyAddress, PersistentKeyStore};
+
+use crate::config::Subnet;
+use crate::manager::SubnetManager;
+
+/// The keystore handed to the factories, if the provider has one.
+pub type ManagerKeyStore = Option<Arc<RwLock<PersistentKeyStore<EthKeyAddress>>>>;
+
+/// Creates the [`SubnetManager`] of a subnet from its config.
+///
+/// Embedders can implement this to plug in their own backend, e.g. a mock for tests,
+/// or a chain which is not EVM based, and register it with the provider under a name.
+/// Subnets select it by setting `manager` to that name in their config.
+pub trait SubnetManagerFactory: Send + Sync {
+    fn create(&self, subnet: &Subnet, keystore: ManagerKeyStore) -> Result<Box<dyn SubnetManager>>;
+}
+
+impl<F> SubnetManagerFactory for F
+where
+    F: Fn(&Subnet, ManagerKeyStore) -> Result<Box<dyn SubnetManager>> + Send + Sync,
+{
+    fn create(&self, subnet: &Subnet, keystore: ManagerKeyStore) -> Result<Box<dyn SubnetManager>> {
+        self(subnet, keystore)
+    }
+}
+
+/// The custom subnet manager factories, by name.
+#[derive(Clone, Default)]
+pub struct ManagerRegistry {
+    factories: HashMap<String, Arc<dyn SubnetManagerFactory>>,
+}
+
+impl ManagerRegistry {
+    /// Register a factory under a name; names cannot be registered twice.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl SubnetManagerFactory + 'static,
+    ) -> Result<()> {
+        let name = name.into();
+        if self.factories.contains_key(&name) {
+            bail!("subnet manager {name} is already registered");
+        }
+        self.factories.insert(name, Arc::new(factory));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn SubnetManagerFactory>> {
+        self.factories.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::{ManagerKeyStore, ManagerRegistry};
+    use crate::config::Subnet;
+    use crate::manager::SubnetManager;
+
+    fn unavailable(_: &Subnet, _: ManagerKeyStore) -> anyhow::Result<Box<dyn SubnetManager>> {
+        Err(anyhow!("unavailable"))
+    }
+
+    #[test]
+    fn register_by_name() {
+        let mut registry = ManagerRegistry::default();
+        registry.register("mock", unavailable).unwrap();
+
+        assert!(registry.get("mock").is_some());
+        assert!(registry.get("other").is_none());
+        assert!(registry.register("mock", unavailable).is_err());
+    }
+}
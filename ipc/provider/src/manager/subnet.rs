@@ -8,13 +8,15 @@ use async_trait::async_trait;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::{address::Address, econ::TokenAmount};
 use ipc_api::checkpoint::{
-    BottomUpCheckpoint, BottomUpCheckpointBundle, QuorumReachedEvent, Signature,
+    BottomUpCheckpoint, BottomUpCheckpointBundle, CheckpointQuorum, CheckpointSubmission,
+    QuorumReachedEvent, Signature,
 };
 use ipc_api::cross::IpcEnvelope;
-use ipc_api::staking::{StakingChangeRequest, ValidatorInfo};
+use ipc_api::staking::{ConfigurationNumber, StakingChangeRequest, ValidatorInfo};
 use ipc_api::subnet::{ConstructParams, PermissionMode, SupplySource};
 use ipc_api::subnet_id::SubnetID;
 use ipc_api::validator::Validator;
+use strum::Display;
 
 use crate::lotus::message::ipc::SubnetInfo;
 
@@ -123,6 +125,14 @@ pub trait SubnetManager: Send + Sync + TopDownFinalityQuery + BottomUpCheckpoint
         postbox_msg_key: Vec<u8>,
     ) -> Result<()>;
 
+    /// Get the cross-net message waiting in the postbox of the gateway under `postbox_msg_key`,
+    /// if there is one. The key has the same representation as in [`SubnetManager::propagate`].
+    async fn postbox_msg(
+        &self,
+        gateway_addr: Address,
+        postbox_msg_key: Vec<u8>,
+    ) -> Result<Option<IpcEnvelope>>;
+
     /// Send value between two addresses in a subnet
     async fn send_value(&self, from: Address, to: Address, amount: TokenAmount) -> Result<()>;
 
@@ -137,6 +147,66 @@ pub trait SubnetManager: Send + Sync + TopDownFinalityQuery + BottomUpCheckpoint
     /// Get commit sha for deployed contracts
     async fn get_commit_sha(&self) -> Result<[u8; 32]>;
 
+    /// Queries the subnet registry for the address of the gateway it registers subnets with.
+    async fn get_registry_gateway(&self, registry_addr: &Address) -> Result<Address>;
+
+    /// Queries the subnet actor for the address of the gateway it is registered with in the parent.
+    async fn get_subnet_gateway(&self, subnet: &SubnetID) -> Result<Address>;
+
+    /// Returns the nonce that the gateway will assign to the next top-down message
+    /// sent to the child `subnet`.
+    async fn get_top_down_nonce(&self, subnet: &SubnetID) -> Result<u64>;
+
+    /// Returns the nonce of the next bottom-up message from the child `subnet` that the
+    /// gateway expects to apply.
+    async fn get_applied_bottom_up_nonce(&self, subnet: &SubnetID) -> Result<u64>;
+
+    /// Returns the nonce of the next top-down message that this subnet's gateway
+    /// expects to apply.
+    async fn applied_top_down_nonce(&self) -> Result<u64>;
+
+    /// Returns the nonce that this subnet's gateway will assign to the next bottom-up message.
+    async fn bottom_up_nonce(&self) -> Result<u64>;
+
+    /// Returns both nonces of the child `subnet` tracked by the gateway in a single round trip
+    /// where possible, i.e. `(get_top_down_nonce, get_applied_bottom_up_nonce)`.
+    async fn child_cross_msg_nonces(&self, subnet: &SubnetID) -> Result<(u64, u64)>;
+
+    /// Returns both nonces tracked by this subnet's gateway in a single round trip where
+    /// possible, i.e. `(applied_top_down_nonce, bottom_up_nonce)`.
+    async fn own_cross_msg_nonces(&self) -> Result<(u64, u64)>;
+
+    /// Checks that the provider serves the chain the subnet is configured with, and that the
+    /// gateway at the configured address is deployed and belongs to the subnet, so that
+    /// transactions aren't sent to the wrong network.
+    async fn check_subnet(&self) -> Result<()>;
+
+    /// Subscribes to the heights of new blocks, if the subnet has a WebSocket endpoint.
+    fn subscribe_new_heads(&self) -> Option<tokio::sync::mpsc::Receiver<ChainEpoch>>;
+
+    /// Indexes the top-down messages and checkpoint quorum events of the gateway from
+    /// height `from`, and the staking changes of the `children` from their genesis, into
+    /// the local event index. Only blocks with at least `confirmations` blocks on top of
+    /// them are indexed. Returns the height the events are indexed up to.
+    async fn index_events(
+        &self,
+        children: &[SubnetID],
+        from: ChainEpoch,
+        confirmations: u64,
+    ) -> Result<ChainEpoch>;
+
+    /// Gets the lifecycle status of a child subnet from its subnet actor, which remains
+    /// queryable after the subnet is killed and removed from the gateway.
+    async fn subnet_status(&self, subnet: &SubnetID) -> Result<SubnetStatus>;
+
+    /// Gets the staking changes recorded in the subnet actor of a child subnet which
+    /// have not been confirmed by a bottom-up checkpoint yet, in configuration number order.
+    async fn pending_staking_changes(&self, subnet: &SubnetID)
+        -> Result<Vec<StakingChangeRequest>>;
+
+    /// Gets the configuration number of the membership currently applied by the gateway.
+    async fn current_configuration_number(&self) -> Result<ConfigurationNumber>;
+
     /// Gets the genesis information required to bootstrap a child subnet
     async fn get_genesis_info(&self, subnet: &SubnetID) -> Result<SubnetGenesisInfo>;
 
@@ -168,6 +238,19 @@ pub trait SubnetManager: Send + Sync + TopDownFinalityQuery + BottomUpCheckpoint
     ) -> Result<ChainEpoch>;
 }
 
+/// The lifecycle stage of a child subnet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum SubnetStatus {
+    /// The subnet actor exists but hasn't got enough collateral and validators to start.
+    Created,
+    /// The subnet is bootstrapped and registered in the gateway.
+    Active,
+    /// The subnet has been killed: it is no longer registered in the gateway,
+    /// but its subnet actor can still be queried for its history.
+    Killed,
+}
+
 #[derive(Debug)]
 pub struct SubnetGenesisInfo {
     pub bottom_up_checkpoint_period: u64,
@@ -202,6 +285,10 @@ pub trait TopDownFinalityQuery: Send + Sync {
     async fn genesis_epoch(&self, subnet_id: &SubnetID) -> Result<ChainEpoch>;
     /// Returns the chain head height
     async fn chain_head_height(&self) -> Result<ChainEpoch>;
+    /// Returns the height of the latest block finalized by the consensus of the chain,
+    /// i.e. the block with the `finalized` tag, such as the one finalized by the beacon
+    /// chain on Ethereum.
+    async fn finalized_chain_head_height(&self) -> Result<ChainEpoch>;
     /// Returns the list of top down messages
     async fn get_top_down_msgs(
         &self,
@@ -218,6 +305,25 @@ pub trait TopDownFinalityQuery: Send + Sync {
     ) -> Result<TopDownQueryPayload<Vec<StakingChangeRequest>>>;
     /// Returns the latest parent finality committed in a child subnet
     async fn latest_parent_finality(&self) -> Result<ChainEpoch>;
+    /// Returns the height of the last bottom-up checkpoint of the subnet committed in this
+    /// network, as of the block at the epoch.
+    async fn last_bottom_up_checkpoint_height_at(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+    ) -> Result<ChainEpoch>;
+}
+
+/// The account a checkpoint is submitted from cannot send the transaction, although another
+/// account could.
+#[derive(Debug, thiserror::Error)]
+pub enum SubmitterUnavailable {
+    #[error("submitter {submitter} has a balance of {balance}, but the submission can cost up to {required}")]
+    InsufficientFunds {
+        submitter: Address,
+        balance: TokenAmount,
+        required: TokenAmount,
+    },
 }
 
 /// The bottom up checkpoint manager that handles the bottom up relaying from child subnet to the parent
@@ -226,22 +332,37 @@ pub trait TopDownFinalityQuery: Send + Sync {
 pub trait BottomUpCheckpointRelayer: Send + Sync {
     /// Submit a checkpoint for execution.
     /// It triggers the commitment of the checkpoint and the execution of related cross-net messages.
-    /// Returns the epoch that the execution is successful
+    /// Returns the transaction that committed it, with the epoch its execution succeeded at.
+    /// Fails with [SubmitterUnavailable] if the submitter can't send the transaction.
     async fn submit_checkpoint(
         &self,
         submitter: &Address,
         checkpoint: BottomUpCheckpoint,
         signatures: Vec<Signature>,
         signatories: Vec<Address>,
-    ) -> Result<ChainEpoch>;
+    ) -> Result<CheckpointSubmission>;
     /// The last confirmed/submitted checkpoint height.
     async fn last_bottom_up_checkpoint_height(&self, subnet_id: &SubnetID) -> Result<ChainEpoch>;
     /// Get the checkpoint period, i.e the number of blocks to submit bottom up checkpoints.
     async fn checkpoint_period(&self, subnet_id: &SubnetID) -> Result<ChainEpoch>;
     /// Get the checkpoint bundle at a specific height. If it does not exist, it will through error.
     async fn checkpoint_bundle_at(&self, height: ChainEpoch) -> Result<BottomUpCheckpointBundle>;
+    /// Get the bottom-up messages batched in the current subnet for the checkpoint at a specific
+    /// height. The batch of a checkpoint still being built keeps growing until its height.
+    async fn bottom_up_msgs_at(&self, height: ChainEpoch) -> Result<Vec<IpcEnvelope>>;
     /// Queries the signature quorum reached events at target height.
     async fn quorum_reached_events(&self, height: ChainEpoch) -> Result<Vec<QuorumReachedEvent>>;
+    /// Get the checkpoint bundle at a specific height along with the quorum collected for it.
+    async fn checkpoint_quorum_at(&self, height: ChainEpoch) -> Result<CheckpointQuorum>;
+    /// Search a range of blocks in the parent for the transaction which committed
+    /// the checkpoint of the subnet at a specific height.
+    async fn checkpoint_submission(
+        &self,
+        subnet_id: &SubnetID,
+        height: ChainEpoch,
+        from_block: ChainEpoch,
+        to_block: ChainEpoch,
+    ) -> Result<Option<CheckpointSubmission>>;
     /// Get the current epoch in the current subnet
     async fn current_epoch(&self) -> Result<ChainEpoch>;
 }
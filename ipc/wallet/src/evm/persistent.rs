@@ -8,7 +8,7 @@ use crate::evm::{KeyInfo, KeyStore};
 use anyhow::anyhow;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::hash::Hash;
@@ -20,6 +20,8 @@ use zeroize::Zeroize;
 pub struct PersistentKeyStore<T> {
     memory: MemoryKeyStore<T>,
     file_path: PathBuf,
+    /// Keys which are only kept in memory and never written to the file.
+    ephemeral: HashSet<T>,
 }
 
 /// The persistent key information written to disk
@@ -72,6 +74,7 @@ impl<T: Clone + Eq + Hash + TryFrom<KeyInfo> + Default + ToString> KeyStore
 
     fn remove(&mut self, addr: &Self::Key) -> Result<()> {
         self.memory.remove(addr)?;
+        self.ephemeral.remove(addr);
         self.flush_no_encryption()
     }
 
@@ -106,6 +109,7 @@ impl<T: Clone + Eq + Hash + TryFrom<KeyInfo> + Default + ToString> PersistentKey
                             default: None,
                         },
                         file_path: path,
+                        ephemeral: Default::default(),
                     })
                 } else {
                     Err(anyhow!("cannot create key store: {e:}"))
@@ -151,9 +155,30 @@ impl<T: Clone + Eq + Hash + TryFrom<KeyInfo> + Default + ToString> PersistentKey
                 default,
             },
             file_path: path,
+            ephemeral: Default::default(),
         })
     }
 
+    /// Add a key that is only kept in memory, never written to the key store file,
+    /// e.g. one loaded from a secret manager at startup.
+    pub fn put_ephemeral(&mut self, info: KeyInfo) -> Result<T> {
+        let addr = self.memory.put(info)?;
+        self.ephemeral.insert(addr.clone());
+        Ok(addr)
+    }
+
+    fn is_ephemeral(&self, key: &T) -> bool {
+        if *key == T::default() {
+            // The default key holds a copy of the key set as default.
+            self.memory
+                .default
+                .as_ref()
+                .is_some_and(|d| self.ephemeral.contains(d))
+        } else {
+            self.ephemeral.contains(key)
+        }
+    }
+
     /// Write all keys to file without any encryption.
     fn flush_no_encryption(&self) -> Result<()> {
         let dir = self
@@ -173,6 +198,7 @@ impl<T: Clone + Eq + Hash + TryFrom<KeyInfo> + Default + ToString> PersistentKey
             .memory
             .data
             .iter()
+            .filter(|(key, _)| !self.is_ephemeral(key))
             .map(|(key, val)| {
                 let private_key = hex::encode(&val.private_key);
                 let address = key.to_string();
@@ -286,4 +312,30 @@ mod tests {
         // the default is also recovered from persistent storage
         assert_eq!(ks.get_default().unwrap().unwrap(), new_addr);
     }
+
+    #[test]
+    fn test_ephemeral_keys_are_not_persisted() {
+        let keystore_folder = tempfile::tempdir().unwrap().into_path();
+        let keystore_location = keystore_folder.join("eth_keystore");
+
+        let mut ks = PersistentKeyStore::new(keystore_location.clone()).unwrap();
+
+        let ephemeral = KeyInfo {
+            private_key: vec![0, 1, 2],
+        };
+        let ephemeral_addr = ks.put_ephemeral(ephemeral.clone()).unwrap();
+        ks.set_default(&ephemeral_addr).unwrap();
+
+        let persisted = KeyInfo {
+            private_key: vec![0, 1, 3],
+        };
+        let persisted_addr = ks.put(persisted.clone()).unwrap();
+        assert_eq!(ks.get(&ephemeral_addr).unwrap(), Some(ephemeral));
+
+        // Create the key store again
+        let ks = PersistentKeyStore::new(keystore_location).unwrap();
+        assert!(ks.get(&ephemeral_addr).unwrap().is_none());
+        assert!(ks.get(&Key::default()).unwrap().is_none());
+        assert_eq!(ks.get(&persisted_addr).unwrap(), Some(persisted));
+    }
 }
@@ -73,6 +73,28 @@ pub struct Config {
     pub min_time_between_publish: Duration,
     /// Maximum age of provider records before the peer is removed without an update.
     pub max_provider_age: Duration,
+    /// Maximum age of vote records before they are dropped instead of being delivered,
+    /// to ignore replayed or long delayed votes. Zero means no limit.
+    pub max_vote_age: Duration,
+    /// Overrides for the peer scoring of the gossip.
+    pub peer_score: PeerScoreConfig,
+}
+
+/// Overrides for the `Gossipsub` peer scoring; anything not set uses the `libp2p` defaults.
+///
+/// Peers with a low score are first excluded from gossip, then from publishing,
+/// and finally all their messages are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct PeerScoreConfig {
+    /// Score below which gossip is not propagated to or accepted from the peer.
+    pub gossip_threshold: Option<f64>,
+    /// Score below which our own messages are not published to the peer.
+    pub publish_threshold: Option<f64>,
+    /// Score below which all messages from the peer are ignored.
+    pub graylist_threshold: Option<f64>,
+    /// Weight of the voting topics in the peer score, rewarding peers who deliver votes
+    /// and penalizing invalid ones. Votes don't affect the score if not set.
+    pub vote_topic_weight: Option<f64>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -119,6 +141,10 @@ pub struct Behaviour<V> {
     next_publish_timestamp: Timestamp,
     /// Maximum time a provider can be without an update before it's pruned from the cache.
     max_provider_age: Duration,
+    /// Maximum age of votes before they are dropped; zero means no limit.
+    max_vote_age: Duration,
+    /// Weight of the voting topics in the peer score, if they are scored.
+    vote_topic_weight: Option<f64>,
     _phantom_vote: PhantomData<V>,
 }
 
@@ -153,7 +179,7 @@ where
         gossipsub
             .with_peer_score(
                 scoring::build_peer_score_params(membership_topic.clone()),
-                scoring::build_peer_score_thresholds(),
+                scoring::build_peer_score_thresholds(&mc.peer_score),
             )
             .map_err(ConfigError::InvalidGossipsubConfig)?;
 
@@ -182,6 +208,8 @@ where
             last_publish_timestamp: Timestamp::default(),
             next_publish_timestamp: Timestamp::now() + mc.publish_interval,
             max_provider_age: mc.max_provider_age,
+            max_vote_age: mc.max_vote_age,
+            vote_topic_weight: mc.peer_score.vote_topic_weight,
             _phantom_vote: PhantomData,
         };
 
@@ -246,6 +274,14 @@ where
     fn voting_subscribe(&mut self, subnet_id: &SubnetID) -> Result<(), SubscriptionError> {
         let topic = self.voting_topic(subnet_id);
         self.subscribe(&topic)?;
+        if let Some(weight) = self.vote_topic_weight {
+            if let Err(e) = self
+                .inner
+                .set_topic_params(topic.clone(), scoring::build_vote_topic_params(weight))
+            {
+                warn!("failed to set the peer score parameters of {topic}: {e}");
+            }
+        }
         self.voting_topics.insert(topic.hash());
         Ok(())
     }
@@ -464,8 +500,16 @@ where
         }
     }
 
-    /// Raise an event to tell we received a new vote.
+    /// Raise an event to tell we received a new vote, unless it's expired.
     fn handle_vote_record(&mut self, record: VoteRecord<V>) {
+        if record.is_expired(self.max_vote_age, Timestamp::now()) {
+            stats::MEMBERSHIP_EXPIRED_VOTE.inc();
+            debug!(
+                "dropping expired vote from {:?} in {}",
+                record.public_key, record.subnet_id
+            );
+            return;
+        }
         self.outbox.push_back(Event::ReceivedVote(Box::new(record)))
     }
 
@@ -653,6 +697,8 @@ mod scoring {
 
     use libp2p::gossipsub::{IdentTopic, PeerScoreParams, PeerScoreThresholds, TopicScoreParams};
 
+    use super::PeerScoreConfig;
+
     pub fn build_peer_score_params(membership_topic: IdentTopic) -> PeerScoreParams {
         let mut params = PeerScoreParams::default();
         params
@@ -661,7 +707,24 @@ mod scoring {
         params
     }
 
-    pub fn build_peer_score_thresholds() -> PeerScoreThresholds {
-        PeerScoreThresholds::default()
+    pub fn build_peer_score_thresholds(config: &PeerScoreConfig) -> PeerScoreThresholds {
+        let mut thresholds = PeerScoreThresholds::default();
+        if let Some(t) = config.gossip_threshold {
+            thresholds.gossip_threshold = t;
+        }
+        if let Some(t) = config.publish_threshold {
+            thresholds.publish_threshold = t;
+        }
+        if let Some(t) = config.graylist_threshold {
+            thresholds.graylist_threshold = t;
+        }
+        thresholds
+    }
+
+    pub fn build_vote_topic_params(topic_weight: f64) -> TopicScoreParams {
+        TopicScoreParams {
+            topic_weight,
+            ..Default::default()
+        }
     }
 }
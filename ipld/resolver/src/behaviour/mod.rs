@@ -16,7 +16,7 @@ pub mod membership;
 
 pub use content::Config as ContentConfig;
 pub use discovery::Config as DiscoveryConfig;
-pub use membership::Config as MembershipConfig;
+pub use membership::{Config as MembershipConfig, PeerScoreConfig};
 use serde::{de::DeserializeOwned, Serialize};
 
 #[derive(Clone, Debug)]
@@ -19,7 +19,9 @@ mod arb;
 #[cfg(feature = "missing_blocks")]
 pub mod missing_blocks;
 
-pub use behaviour::{ContentConfig, DiscoveryConfig, MembershipConfig, NetworkConfig};
+pub use behaviour::{
+    ContentConfig, DiscoveryConfig, MembershipConfig, NetworkConfig, PeerScoreConfig,
+};
 pub use client::{Client, Resolver};
 pub use service::{Config, ConnectionConfig, Event, NoKnownPeers, Service};
 pub use timestamp::Timestamp;
@@ -64,6 +64,11 @@ metrics! {
         "Number of invalid messages received"
     );
 
+    MEMBERSHIP_EXPIRED_VOTE: IntCounter = IntCounter::new(
+        "membership_expired_vote",
+        "Number of votes dropped for being too old"
+    );
+
     MEMBERSHIP_PUBLISH_SUCCESS: IntCounter = IntCounter::new(
       "membership_publish_total", "Number of published messages"
     );
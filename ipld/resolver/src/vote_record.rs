@@ -4,6 +4,7 @@ use ipc_api::subnet_id::SubnetID;
 use libp2p::identity::{Keypair, PublicKey};
 use serde::de::{DeserializeOwned, Error};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::{
     signed_record::{Record, SignedRecord},
@@ -87,6 +88,13 @@ impl<C> Record for VoteRecord<C> {
 
 pub type SignedVoteRecord<C> = SignedRecord<VoteRecord<C>>;
 
+impl<C> VoteRecord<C> {
+    /// Check whether the vote was cast longer ago than the maximum age; zero means no limit.
+    pub fn is_expired(&self, max_age: Duration, now: Timestamp) -> bool {
+        !max_age.is_zero() && self.timestamp < now - max_age
+    }
+}
+
 impl<C> VoteRecord<C>
 where
     C: Serialize + DeserializeOwned,
@@ -138,12 +146,35 @@ mod arb {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use ipc_api::subnet_id::SubnetID;
+    use libp2p::identity::Keypair;
     use quickcheck_macros::quickcheck;
 
-    use super::SignedVoteRecord;
+    use crate::Timestamp;
+
+    use super::{SignedVoteRecord, ValidatorKey, VoteRecord};
 
     #[quickcheck]
     fn prop_roundtrip(signed_record: SignedVoteRecord<String>) -> bool {
         crate::signed_record::tests::prop_roundtrip(signed_record)
     }
+
+    #[test]
+    fn vote_expiry() {
+        let now = Timestamp::now();
+        let vote = VoteRecord {
+            public_key: ValidatorKey(Keypair::generate_secp256k1().public()),
+            subnet_id: SubnetID::new_root(123),
+            content: (),
+            timestamp: now - Duration::from_secs(60),
+        };
+
+        assert!(vote.is_expired(Duration::from_secs(30), now));
+        assert!(!vote.is_expired(Duration::from_secs(60), now));
+        assert!(!vote.is_expired(Duration::from_secs(120), now));
+        // no limit
+        assert!(!vote.is_expired(Duration::ZERO, now));
+    }
 }
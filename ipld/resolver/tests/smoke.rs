@@ -339,6 +339,8 @@ fn make_config(rng: &mut StdRng, cluster_size: u32, bootstrap_addr: Option<Multi
             publish_interval: Duration::from_secs(5),
             min_time_between_publish: Duration::from_secs(1),
             max_provider_age: Duration::from_secs(60),
+            max_vote_age: Duration::ZERO,
+            peer_score: Default::default(),
         },
         content: ContentConfig {
             rate_limit_bytes: 1 << 20,